@@ -7,5 +7,7 @@ fn main() {
     {
         println!("cargo:rustc-link-arg=-Tbearpi_hi2821.ld");
         println!("cargo:rustc-link-arg=-nostartfiles");
+        // defmt日志的格式字符串表
+        println!("cargo:rustc-link-arg=-Tdefmt.x");
     }
 } 
\ No newline at end of file
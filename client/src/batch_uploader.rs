@@ -0,0 +1,164 @@
+use common::hal::Hardware;
+use common::protocol::batch::{SampleBatch, MAX_BATCH_SAMPLES};
+use common::protocol::reliable::{ReliableError, ReliableSender, MAX_FRAME_PAYLOAD};
+use crate::offline_buffer::OfflineBuffer;
+use crate::sensor_driver::SensorData;
+use crate::service_client::ServiceEndpoint;
+
+/// 批量上传配置
+#[derive(Debug, Clone, Copy)]
+pub struct BatchConfig {
+    /// 采样间隔（毫秒）
+    pub sample_interval_ms: u64,
+    /// 最早样本等待上传的最长时间（毫秒）
+    pub upload_interval_ms: u64,
+    /// 达到该样本数时立即上传
+    pub max_samples: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            sample_interval_ms: 5000,
+            upload_interval_ms: 60000,
+            max_samples: MAX_BATCH_SAMPLES,
+        }
+    }
+}
+
+/// 在本地累积传感器样本，按时间间隔或数量阈值一次性上传
+pub struct BatchUploader {
+    config: BatchConfig,
+    batch: SampleBatch,
+    /// 无法上传期间积压的批量
+    offline: OfflineBuffer,
+    /// 上次采样时间
+    last_sample: Option<u64>,
+}
+
+impl BatchUploader {
+    pub fn new(config: BatchConfig) -> Self {
+        Self {
+            config: BatchConfig {
+                max_samples: config.max_samples.clamp(1, MAX_BATCH_SAMPLES),
+                ..config
+            },
+            batch: SampleBatch::new(),
+            offline: OfflineBuffer::new(),
+            last_sample: None,
+        }
+    }
+    
+    /// 修改采样间隔
+    pub fn set_sample_interval(&mut self, interval_ms: u64) {
+        self.config.sample_interval_ms = interval_ms;
+    }
+    
+    /// 是否到了采样时间
+    pub fn should_sample(&self, current_time: u64) -> bool {
+        match self.last_sample {
+            Some(last) => current_time.saturating_sub(last) >= self.config.sample_interval_ms,
+            None => true,
+        }
+    }
+    
+    /// 加入一个样本
+    ///
+    /// 当前批量已满（无法上传）时先转入离线缓冲，离线缓冲也满时丢弃最早的批量并返回false。
+    pub fn add_sample<H: Hardware>(&mut self, hardware: &mut H, current_time: u64, data: &SensorData) -> bool {
+        self.last_sample = Some(current_time);
+        
+        let mut kept = true;
+        if self.batch.len() >= self.config.max_samples {
+            kept = self.offline.push(hardware.get_nvs(), &self.batch);
+            self.batch.clear();
+        }
+        self.batch.push(current_time, data.temperature, data.humidity, data.pressure);
+        
+        kept
+    }
+    
+    /// 是否应该上传：有积压的批量、样本数达到阈值，或最早的样本已等待超过上传间隔
+    pub fn should_upload(&self, current_time: u64) -> bool {
+        if !self.offline.is_empty() || self.batch.len() >= self.config.max_samples {
+            return true;
+        }
+        
+        match self.batch.oldest_timestamp() {
+            Some(oldest) => current_time.saturating_sub(oldest) >= self.config.upload_interval_ms,
+            None => false,
+        }
+    }
+    
+    /// 下一次采样的时间
+    pub fn next_sample_at(&self) -> u64 {
+        self.last_sample.map_or(0, |last| last + self.config.sample_interval_ms)
+    }
+    
+    /// 按上传间隔应上传的时间，没有样本时返回None
+    pub fn next_upload_at(&self) -> Option<u64> {
+        self.batch.oldest_timestamp().map(|oldest| oldest + self.config.upload_interval_ms)
+    }
+    
+    /// 当前批量中的样本数
+    pub fn pending_samples(&self) -> usize {
+        self.batch.len()
+    }
+    
+    /// 离线期间积压的批量数
+    pub fn backlog(&self) -> usize {
+        self.offline.len()
+    }
+    
+    /// 上传样本，返回已发送的样本数
+    ///
+    /// 先按时间顺序补传离线积压的批量，再发送当前批量，每个批量一帧。
+    /// 发送窗口已满时停止，剩余的批量留到下次上传。
+    pub fn upload<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        uplink: &mut ReliableSender,
+        endpoint: &ServiceEndpoint,
+        current_time: u64
+    ) -> Result<usize, ReliableError> {
+        let mut sent = 0;
+        
+        while let Some(batch) = self.offline.oldest(hardware.get_nvs()) {
+            if !batch.is_empty() {
+                match send_batch(hardware, uplink, endpoint, &batch, current_time) {
+                    Ok(_) => sent += batch.len(),
+                    Err(e) if sent == 0 => return Err(e),
+                    Err(_) => return Ok(sent),
+                }
+            }
+            self.offline.discard_oldest(hardware.get_nvs());
+        }
+        
+        if !self.batch.is_empty() {
+            match send_batch(hardware, uplink, endpoint, &self.batch, current_time) {
+                Ok(_) => {
+                    sent += self.batch.len();
+                    self.batch.clear();
+                },
+                Err(e) if sent == 0 => return Err(e),
+                Err(_) => {},
+            }
+        }
+        
+        Ok(sent)
+    }
+}
+
+/// 将一个批量作为一帧可靠发送，样本时间按发送时刻换算
+fn send_batch<H: Hardware>(
+    hardware: &mut H,
+    uplink: &mut ReliableSender,
+    endpoint: &ServiceEndpoint,
+    batch: &SampleBatch,
+    current_time: u64
+) -> Result<u16, ReliableError> {
+    let mut payload = [0u8; MAX_FRAME_PAYLOAD];
+    let len = batch.serialize(endpoint.service_id, current_time, &mut payload);
+    
+    uplink.send(hardware, endpoint.service_id, endpoint.server_id, &payload[..len], current_time)
+}
\ No newline at end of file
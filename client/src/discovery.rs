@@ -1,62 +1,268 @@
-use common::hal::Hardware;
-use common::protocol::{Beacon, NodeId, PacketType};
-use core::time::Duration;
+use common::hal::{Hardware, RadioInterface};
+use common::protocol::{Beacon, NodeId, NodeRole, PacketType};
+use common::metrics::{self, Gauge};
+use common::security;
+use common::utils::jitter;
+use common::{info, warn};
 
-/// 尝试发现网络中的服务器节点
+/// 每轮扫描收集信标的时长（毫秒）
+const SCAN_WINDOW_MS: u64 = 3000;
+
+/// 扫描窗口内两次接收之间的间隔（毫秒）
+const SCAN_POLL_MS: u32 = 100;
+
+/// 最多扫描的轮数
+const MAX_SCAN_ROUNDS: u32 = 6;
+
+/// 首轮失败后的退避时间（毫秒）
+const INITIAL_BACKOFF_MS: u32 = 1000;
+
+/// 退避时间上限（毫秒）
+const MAX_BACKOFF_MS: u32 = 30000;
+
+/// 离线后首次重新发现网络前的等待时间（毫秒）
+const OFFLINE_RETRY_MS: u64 = 5000;
+
+/// 离线重新发现的等待时间上限（毫秒）
+const OFFLINE_RETRY_MAX_MS: u64 = 300000;
+
+/// 一轮扫描中最多记录的候选中继数
+pub const MAX_CANDIDATES: usize = 8;
+
+/// 扫描中听到的候选中继
+#[derive(Debug, Clone, Copy)]
+pub struct RelayCandidate {
+    /// 节点ID
+    pub node_id: NodeId,
+    /// 本地测得的接收信号强度（平滑后）
+    pub rssi: i8,
+    /// 对方电池电量
+    pub battery_level: u8,
+    /// 对方声明的角色
+    pub role: NodeRole,
+    /// 对方到服务器的跳数
+    pub hop_count: u8,
+    /// 本轮听到的信标数
+    pub heard: u8,
+}
+
+impl RelayCandidate {
+    /// 排序分数，越高越优先
+    ///
+    /// 以信号强度为主，电池电量和跳数为辅，扫描窗口内多次听到的节点链路更稳定。
+    pub fn score(&self) -> i32 {
+        let role_bonus = match self.role {
+            NodeRole::Forward => 40,
+            _ => 0,
+        };
+        
+        self.rssi as i32 * 2
+            + self.battery_level as i32 / 2
+            - self.hop_count as i32 * 20
+            + self.heard.min(5) as i32 * 4
+            + role_bonus
+    }
+    
+    /// 是否能作为中继：客户端和服务器不处理服务请求
+    pub fn can_relay(&self) -> bool {
+        matches!(self.role, NodeRole::Forward | NodeRole::Unknown)
+    }
+}
+
+/// 尝试发现网络中的转发节点，返回排名最高的候选中继
+///
+/// 每轮扫描收集窗口内听到的所有信标，没有可用中继时按带抖动的指数退避等待后重试。
 pub fn find_server<H: Hardware>(hardware: &mut H) -> Option<NodeId> {
-    println!("开始寻找服务器节点...");
+    let mut candidates = [None; MAX_CANDIDATES];
+    
+    match find_relays(hardware, &mut candidates) {
+        0 => None,
+        _ => candidates[0].map(|candidate| candidate.node_id),
+    }
+}
+
+/// 扫描一个窗口寻找转发节点，不重试
+pub fn probe_server<H: Hardware>(hardware: &mut H) -> Option<NodeId> {
+    let mut candidates = [None; MAX_CANDIDATES];
+    scan(hardware, SCAN_WINDOW_MS, &mut candidates);
+    candidates[0].map(|candidate| candidate.node_id)
+}
+
+/// 离线期间重新发现网络的退避计划，两次尝试之间继续采样和休眠
+pub struct DiscoveryBackoff {
+    /// 下一次尝试的时间
+    next_attempt: u64,
+    /// 当前退避时间（毫秒）
+    delay_ms: u64,
+}
+
+impl DiscoveryBackoff {
+    pub fn new() -> Self {
+        Self {
+            next_attempt: 0,
+            delay_ms: OFFLINE_RETRY_MS,
+        }
+    }
+    
+    /// 是否到了下一次尝试的时间
+    pub fn due(&self, current_time: u64) -> bool {
+        current_time >= self.next_attempt
+    }
+    
+    /// 下一次尝试的时间
+    pub fn next_attempt_at(&self) -> u64 {
+        self.next_attempt
+    }
+    
+    /// 本次尝试失败，按带抖动的指数退避安排下一次尝试，返回等待时间
+    pub fn on_failure<H: Hardware>(&mut self, hardware: &H, current_time: u64) -> u64 {
+        let half = self.delay_ms / 2;
+        let wait = half + backoff_jitter(hardware, half as u32) as u64;
+        
+        self.next_attempt = current_time + wait;
+        self.delay_ms = (self.delay_ms * 2).min(OFFLINE_RETRY_MAX_MS);
+        
+        wait
+    }
+    
+    /// 发现成功后恢复初始退避时间
+    pub fn reset(&mut self) {
+        self.next_attempt = 0;
+        self.delay_ms = OFFLINE_RETRY_MS;
+    }
+}
+
+/// 多轮扫描直到发现可用中继，候选中继按分数从高到低写入out，返回个数
+pub fn find_relays<H: Hardware>(hardware: &mut H, out: &mut [Option<RelayCandidate>; MAX_CANDIDATES]) -> usize {
+    info!("开始寻找转发节点...");
     
-    // 最多尝试30秒
-    let max_attempts = 30;
-    let mut attempt = 0;
+    let mut backoff = INITIAL_BACKOFF_MS;
     
-    while attempt < max_attempts {
-        // 发送广播信标
-        send_discovery_beacon(hardware);
+    for round in 1..=MAX_SCAN_ROUNDS {
+        let count = scan(hardware, SCAN_WINDOW_MS, out);
+        if count > 0 {
+            if let Some(best) = out[0] {
+                info!("发现 {} 个候选中继，选择 {}（RSSI: {}, 电量: {}%, 跳数: {}）",
+                         count, best.node_id, best.rssi, best.battery_level, best.hop_count);
+            }
+            return count;
+        }
         
-        // 尝试接收服务器响应
-        if let Some(server_id) = receive_server_response(hardware) {
-            return Some(server_id);
+        if round == MAX_SCAN_ROUNDS {
+            break;
         }
         
-        // 等待1秒再尝试
-        let _ = hardware.delay_ms(1000);
-        attempt += 1;
-        println!("搜索服务器中... {}/{}s", attempt, max_attempts);
+        // 抖动避免多个客户端同时重试
+        let wait = backoff / 2 + backoff_jitter(hardware, backoff / 2);
+        info!("第 {}/{} 轮未发现转发节点，{}ms 后重试", round, MAX_SCAN_ROUNDS, wait);
+        let _ = hardware.delay_ms(wait);
+        
+        backoff = backoff.saturating_mul(2).min(MAX_BACKOFF_MS);
     }
     
-    println!("未找到服务器节点");
-    None
+    warn!("未找到转发节点");
+    0
+}
+
+/// 扫描一个窗口，收集可用中继并按分数排序，返回个数
+pub fn scan<H: Hardware>(hardware: &mut H, window_ms: u64, out: &mut [Option<RelayCandidate>; MAX_CANDIDATES]) -> usize {
+    *out = [None; MAX_CANDIDATES];
+    
+    send_discovery_beacon(hardware);
+    
+    let start = hardware.get_timestamp_ms().unwrap_or(0);
+    loop {
+        while let Some((beacon, link_rssi)) = receive_beacon(hardware) {
+            record_candidate(out, &beacon, link_rssi);
+        }
+        
+        let now = hardware.get_timestamp_ms().unwrap_or(0);
+        if now.saturating_sub(start) >= window_ms {
+            break;
+        }
+        let _ = hardware.delay_ms(SCAN_POLL_MS);
+    }
+    
+    // 不能中继的节点不参与排名
+    for entry in out.iter_mut() {
+        if matches!(entry, Some(candidate) if !candidate.can_relay()) {
+            *entry = None;
+        }
+    }
+    
+    out.sort_unstable_by_key(|entry| match entry {
+        Some(candidate) => -candidate.score(),
+        None => i32::MAX,
+    });
+    
+    out.iter().flatten().count()
+}
+
+/// 记录一个信标，同一节点多次听到时平滑其信号强度
+fn record_candidate(candidates: &mut [Option<RelayCandidate>; MAX_CANDIDATES], beacon: &Beacon, link_rssi: i8) {
+    let node_id = NodeId(beacon.source);
+    
+    for entry in candidates.iter_mut() {
+        if let Some(candidate) = entry {
+            if candidate.node_id == node_id {
+                candidate.rssi = ((candidate.rssi as i16 + link_rssi as i16) / 2) as i8;
+                candidate.battery_level = beacon.battery_level;
+                candidate.hop_count = beacon.hop_count;
+                candidate.heard = candidate.heard.saturating_add(1);
+                return;
+            }
+        }
+    }
+    
+    let candidate = RelayCandidate {
+        node_id,
+        rssi: link_rssi,
+        battery_level: beacon.battery_level,
+        role: beacon.role(),
+        hop_count: beacon.hop_count,
+        heard: 1,
+    };
+    
+    if let Some(slot) = candidates.iter_mut().find(|entry| entry.is_none()) {
+        *slot = Some(candidate);
+    } else if let Some(worst) = candidates.iter_mut().min_by_key(|entry| entry.map_or(i32::MIN, |c| c.score())) {
+        // 候选已满时替换分数最低的
+        if worst.map_or(true, |c| c.score() < candidate.score()) {
+            *worst = Some(candidate);
+        }
+    }
 }
 
 /// 发送发现信标
 fn send_discovery_beacon<H: Hardware>(hardware: &mut H) {
     let node_id = hardware.get_node_id();
     let battery_level = hardware.get_battery_level().unwrap_or(100);
+    metrics::set(Gauge::BatteryLevel, battery_level as u32);
     let rssi = hardware.get_radio().get_rssi().unwrap_or(-80);
     
     // 创建信标
-    let beacon = Beacon::new(node_id, battery_level, rssi);
+    let mut beacon = Beacon::with_role(node_id, battery_level, rssi, NodeRole::Client);
+    security::sign_beacon(hardware, &mut beacon);
     
     // 发送信标
     let radio = hardware.get_radio();
     if let Err(e) = radio.send_beacon(&beacon) {
-        println!("发送发现信标失败: {:?}", e);
+        warn!("发送发现信标失败: {:?}", e);
     }
 }
 
-/// 接收服务器响应
-fn receive_server_response<H: Hardware>(hardware: &mut H) -> Option<NodeId> {
-    // 尝试接收信标
-    let radio = hardware.get_radio();
-    if let Ok(Some(beacon)) = radio.receive_beacon() {
-        // 验证是否是服务器节点
-        if beacon.is_valid() && beacon.packet_type == PacketType::Beacon as u8 {
-            // 实际项目中可能需要更复杂的验证逻辑
-            println!("发现潜在服务器节点，RSSI: {}", beacon.rssi);
-            return Some(NodeId(beacon.source));
+/// 接收一个有效且通过认证的信标及其接收信号强度
+fn receive_beacon<H: Hardware>(hardware: &mut H) -> Option<(Beacon, i8)> {
+    loop {
+        let beacon = hardware.get_radio().receive_beacon().ok()??;
+        if beacon.is_valid() && beacon.packet_type == PacketType::Beacon as u8 && security::verify_beacon(hardware, &beacon) {
+            let link_rssi = hardware.get_radio().get_rssi().unwrap_or(beacon.rssi);
+            return Some((beacon, link_rssi));
         }
     }
-    
-    None
-} 
\ No newline at end of file
+}
+
+/// 生成[0, max)范围内的抖动，以节点ID和当前时间为种子
+fn backoff_jitter<H: Hardware>(hardware: &H, max: u32) -> u32 {
+    jitter(hardware.get_node_id(), hardware.get_timestamp_ms().unwrap_or(0) as u32, max)
+}
\ No newline at end of file
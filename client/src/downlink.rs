@@ -0,0 +1,261 @@
+use core::sync::atomic::{AtomicU16, Ordering};
+
+use common::hal::Hardware;
+use common::hal::nvs::{RebootBreadcrumb, RebootReason};
+use common::protocol::{DataPacket, NodeId, PacketType, ServiceType};
+use common::protocol::command::{
+    CommandStatus, CommandType, ConfigParam, LoggedSample,
+    COMMAND_TAG_LEN, LOG_RESPONSE_HEADER_LEN, MAX_LOG_RESPONSE_SAMPLES,
+};
+use common::protocol::data::Fragmenter;
+use common::protocol::payload::{self, Command, PayloadType};
+use common::protocol::wire::{get_u32be, put_u32be};
+use common::security::{send_secure, verify_command};
+use common::{info, warn};
+use crate::sample_log::SampleLog;
+use crate::settings::{read_qos, ClientSettings, NETWORK_KEY_LEN};
+
+/// 配置命令带来的变更，由主循环据此调整运行状态
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SettingsChange {
+    /// 采样间隔已修改
+    pub sample_interval: bool,
+    /// 无线信道已修改
+    pub channel: bool,
+    /// 服务质量或目标服务器已修改，需要重建会话
+    pub sessions: bool,
+    /// 已执行重启
+    pub reboot: bool,
+}
+
+/// 下一个日志响应的包ID，同一响应的分片共用，接收端据此区分不同的响应
+static NEXT_LOG_RESPONSE_ID: AtomicU16 = AtomicU16::new(1);
+
+/// 数据包是否为下行命令
+pub fn is_command(packet: &DataPacket) -> bool {
+    packet.data.len() >= 2 && PayloadType::of(packet.data) == Some(PayloadType::Command)
+}
+
+/// 处理下行命令并回复执行结果，返回需要主循环处理的配置变更
+///
+/// 特权命令末尾带认证码，重启以及修改节点身份和网络密钥必须通过认证，
+/// 确认命令来自持有网络密钥的网关，其余配置项不要求认证。
+pub fn handle_command<H: Hardware>(
+    hardware: &mut H,
+    settings: &mut ClientSettings,
+    sample_log: &SampleLog,
+    packet: &DataPacket
+) -> SettingsChange {
+    let source = NodeId(packet.header.source);
+    let (command_type, parameters) = match payload::decode::<Command>(packet.data) {
+        Some(command) => (command.command, command.parameters),
+        None => {
+            info!("收到未知命令: {}", packet.data[1]);
+            return SettingsChange::default();
+        }
+    };
+    
+    info!("收到来自 {} 的命令: {:?}", source, command_type);
+    
+    let (parameters, authenticated) = if command_type.is_privileged() {
+        if parameters.len() < COMMAND_TAG_LEN {
+            send_response(hardware, source, command_type, CommandStatus::InvalidParameter);
+            return SettingsChange::default();
+        }
+        (&parameters[..parameters.len() - COMMAND_TAG_LEN], verify_command(hardware, packet.data))
+    } else {
+        (parameters, false)
+    };
+    
+    match command_type {
+        CommandType::Configure => {
+            let mut updated = *settings;
+            match apply_config(&mut updated, parameters, authenticated) {
+                Ok(change) => {
+                    *settings = updated;
+                    
+                    // 新配置已生效，保存失败时重启后会恢复旧配置
+                    let status = match settings.store(hardware.get_nvs()) {
+                        Ok(()) => CommandStatus::Ok,
+                        Err(_) => CommandStatus::PersistFailed,
+                    };
+                    send_response(hardware, source, command_type, status);
+                    change
+                },
+                Err(status) => {
+                    send_response(hardware, source, command_type, status);
+                    SettingsChange::default()
+                },
+            }
+        },
+        CommandType::Reboot if !authenticated => {
+            warn!("来自 {} 的重启命令未通过认证", source);
+            send_response(hardware, source, command_type, CommandStatus::Unauthorized);
+            SettingsChange::default()
+        },
+        CommandType::Reboot => {
+            // 先确认，再记录重启原因并复位
+            send_response(hardware, source, command_type, CommandStatus::Ok);
+            
+            let breadcrumb = RebootBreadcrumb {
+                reason: RebootReason::Command,
+                requested_by: source,
+                timestamp: hardware.get_timestamp_ms().unwrap_or(0),
+            };
+            if breadcrumb.store(hardware.get_nvs()).is_err() {
+                warn!("写入重启记录失败");
+            }
+            
+            let reboot = hardware.system_reset().is_ok();
+            SettingsChange { reboot, ..SettingsChange::default() }
+        },
+        CommandType::ReadLog => {
+            if parameters.len() < 5 {
+                send_response(hardware, source, command_type, CommandStatus::InvalidParameter);
+            } else {
+                let start_seq = get_u32be(&parameters[0..4]);
+                send_log(hardware, sample_log, source, start_seq, parameters[4] as usize);
+            }
+            SettingsChange::default()
+        },
+        _ => {
+            send_response(hardware, source, command_type, CommandStatus::Unsupported);
+            SettingsChange::default()
+        },
+    }
+}
+
+/// 将配置参数应用到配置副本，任一参数无效时整条命令被拒绝
+///
+/// 节点身份和网络密钥只接受通过认证的命令。
+fn apply_config(settings: &mut ClientSettings, mut params: &[u8], authenticated: bool) -> Result<SettingsChange, CommandStatus> {
+    let mut change = SettingsChange::default();
+    
+    if params.is_empty() {
+        return Err(CommandStatus::InvalidParameter);
+    }
+    
+    while !params.is_empty() {
+        let param = ConfigParam::from_u8(params[0]).ok_or(CommandStatus::Unsupported)?;
+        let len = param.value_len();
+        if params.len() < 1 + len {
+            return Err(CommandStatus::InvalidParameter);
+        }
+        let value = &params[1..1 + len];
+        if matches!(param, ConfigParam::NodeIdentity | ConfigParam::NetworkKey) && !authenticated {
+            return Err(CommandStatus::Unauthorized);
+        }
+        
+        match param {
+            ConfigParam::SampleInterval => {
+                let interval = get_u32be(&value[0..4]);
+                // 采样间隔限制在1秒到1天之间
+                if !(1000..=86_400_000).contains(&interval) {
+                    return Err(CommandStatus::InvalidParameter);
+                }
+                settings.sample_interval_ms = interval;
+                change.sample_interval = true;
+            },
+            ConfigParam::Qos => {
+                let qos = read_qos(&value[1..6]);
+                if qos.reliability > 100 {
+                    return Err(CommandStatus::InvalidParameter);
+                }
+                match value[0] {
+                    x if x == ServiceType::VideoRelay as u8 => settings.video_qos = qos,
+                    x if x == ServiceType::SensorCollection as u8 => settings.sensor_qos = qos,
+                    _ => return Err(CommandStatus::InvalidParameter),
+                }
+                change.sessions = true;
+            },
+            ConfigParam::Server => {
+                let mut id = [0u8; 6];
+                id.copy_from_slice(value);
+                let server = NodeId(id);
+                // 广播地址表示取消指定，恢复由服务目录分配
+                settings.server_affinity = if server.is_broadcast() { None } else { Some(server) };
+                change.sessions = true;
+            },
+            ConfigParam::Channel => {
+                settings.channel = value[0];
+                change.channel = true;
+            },
+            ConfigParam::NodeIdentity => {
+                let mut id = [0u8; 6];
+                id.copy_from_slice(value);
+                let node_id = NodeId(id);
+                if node_id.is_broadcast() {
+                    return Err(CommandStatus::InvalidParameter);
+                }
+                // 运行中更换身份会中断现有会话，保存后在下次启动时生效
+                settings.node_id = Some(node_id);
+            },
+            ConfigParam::NetworkKey => {
+                let mut key = [0u8; NETWORK_KEY_LEN];
+                key.copy_from_slice(value);
+                // 全零密钥会关闭链路保护，只能在本地清除，无线下发一律拒绝
+                if key.iter().all(|&b| b == 0) {
+                    return Err(CommandStatus::InvalidParameter);
+                }
+                settings.network_key = Some(key);
+            },
+        }
+        
+        params = &params[1 + len..];
+    }
+    
+    Ok(change)
+}
+
+/// 回复命令执行结果，格式与服务器响应相同：命令类型(1) 状态(1)
+fn send_response<H: Hardware>(
+    hardware: &mut H,
+    destination: NodeId,
+    command_type: CommandType,
+    status: CommandStatus
+) {
+    let response = [command_type as u8, status as u8];
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::new(node_id, destination, 0, &response);
+    
+    if let Err(e) = send_secure(hardware, &packet) {
+        warn!("发送命令响应失败: {:?}", e);
+    }
+}
+
+/// 回复本地日志中从指定序号开始的样本
+///
+/// 格式：命令类型(1) 状态(1) 首个样本序号(4) 样本数(1) 样本*，样本数为0表示已读完。
+/// 超过一帧的响应拆成分片发送，由接收端重组。
+fn send_log<H: Hardware>(
+    hardware: &mut H,
+    sample_log: &SampleLog,
+    destination: NodeId,
+    start_seq: u32,
+    max_samples: usize
+) {
+    let mut samples = [LoggedSample { timestamp: 0, temperature: 0.0, humidity: 0.0, pressure: 0.0 }; MAX_LOG_RESPONSE_SAMPLES];
+    let limit = max_samples.min(MAX_LOG_RESPONSE_SAMPLES);
+    let (first_seq, count) = sample_log.read(hardware.get_nvs(), start_seq, &mut samples[..limit]);
+    
+    let mut response = [0u8; LOG_RESPONSE_HEADER_LEN + MAX_LOG_RESPONSE_SAMPLES * LoggedSample::SIZE];
+    response[0] = CommandType::ReadLog as u8;
+    response[1] = CommandStatus::Ok as u8;
+    put_u32be(&mut response[2..6], first_seq);
+    response[6] = count as u8;
+    
+    let mut len = LOG_RESPONSE_HEADER_LEN;
+    for sample in &samples[..count] {
+        response[len..len + LoggedSample::SIZE].copy_from_slice(&sample.to_bytes());
+        len += LoggedSample::SIZE;
+    }
+    
+    let node_id = hardware.get_node_id();
+    let packet_id = NEXT_LOG_RESPONSE_ID.fetch_add(1, Ordering::Relaxed);
+    for fragment in Fragmenter::new(node_id, destination, PacketType::Data, packet_id, &response[..len]).into_iter().flatten() {
+        if let Err(e) = send_secure(hardware, &fragment) {
+            warn!("发送日志响应失败: {:?}", e);
+            return;
+        }
+    }
+}
\ No newline at end of file
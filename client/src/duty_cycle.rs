@@ -0,0 +1,117 @@
+use common::hal::Hardware;
+
+/// 在预期的时间信标之前提前打开下行窗口的余量，覆盖时钟漂移和多跳转发时延（毫秒）
+pub const BEACON_GUARD_MS: u64 = 200;
+
+/// 占空比参数
+#[derive(Debug, Clone, Copy)]
+pub struct DutyCycleConfig {
+    /// 每次收发后保持接收的下行窗口（毫秒）
+    pub downlink_window_ms: u64,
+    /// 与转发节点约定的下行监听周期，没有上行任务时也按此周期醒来（毫秒）；
+    /// 收到时间信标后取信标间隔的整数倍，窗口对准信标到达的时刻
+    pub downlink_period_ms: u64,
+    /// 短于该时长时不进入睡眠（毫秒）
+    pub min_sleep_ms: u64,
+    /// 单次睡眠的最长时间（毫秒）
+    pub max_sleep_ms: u64,
+}
+
+impl Default for DutyCycleConfig {
+    fn default() -> Self {
+        Self {
+            downlink_window_ms: 2000,
+            downlink_period_ms: 30000,
+            min_sleep_ms: 1000,
+            max_sleep_ms: 60000,
+        }
+    }
+}
+
+/// 客户端占空比控制：在采样/上传窗口之间让MCU和无线电进入低功耗模式
+///
+/// 睡眠期间RAM保持，会话、租约和批量数据不受影响。听到时间信标后，定期下行窗口与信标同步，
+/// 醒来时正好收到信标和随后转发节点发出的下行数据。
+pub struct DutyCycle {
+    config: DutyCycleConfig,
+    /// 当前下行窗口的结束时间
+    awake_until: u64,
+    /// 下一次定期下行窗口的开始时间
+    next_downlink: u64,
+    /// 最近一次收到时间信标的本地时间和信标间隔
+    beacon: Option<(u64, u64)>,
+    /// 累计睡眠时间（毫秒）
+    total_sleep_ms: u64,
+}
+
+impl DutyCycle {
+    pub fn new(config: DutyCycleConfig, current_time: u64) -> Self {
+        Self {
+            config,
+            awake_until: current_time + config.downlink_window_ms,
+            next_downlink: current_time + config.downlink_period_ms,
+            beacon: None,
+            total_sleep_ms: 0,
+        }
+    }
+    
+    /// 收到时间信标，之后的定期下行窗口对准后续信标的到达时间
+    pub fn observe_beacon(&mut self, current_time: u64, interval_ms: u64) {
+        if interval_ms == 0 {
+            return;
+        }
+        self.beacon = Some((current_time, interval_ms));
+        self.next_downlink = self.next_window_after(current_time);
+    }
+    
+    /// 晚于指定时间的下一个定期下行窗口
+    ///
+    /// 没有听到信标时按固定周期；听到后每隔整数个信标间隔醒来一次，在预期信标前提前打开。
+    pub fn next_window_after(&self, time: u64) -> u64 {
+        match self.beacon {
+            Some((heard_at, interval_ms)) => {
+                let period = interval_ms * (self.config.downlink_period_ms / interval_ms).max(1);
+                let since = (time + BEACON_GUARD_MS).saturating_sub(heard_at);
+                heard_at + (since / period + 1) * period - BEACON_GUARD_MS
+            },
+            None => time + self.config.downlink_period_ms,
+        }
+    }
+    
+    /// 有收发活动时保持唤醒一个下行窗口，以便接收确认和下行命令
+    pub fn keep_awake(&mut self, current_time: u64) {
+        self.awake_until = self.awake_until.max(current_time + self.config.downlink_window_ms);
+    }
+    
+    /// 睡眠到下一个计划任务或下行窗口，返回实际睡眠的毫秒数
+    pub fn sleep_until<H: Hardware>(&mut self, hardware: &mut H, current_time: u64, next_task: u64) -> u64 {
+        if current_time < self.awake_until {
+            return 0;
+        }
+        
+        // 到了定期下行窗口，保持唤醒接收
+        if current_time >= self.next_downlink {
+            self.next_downlink = self.next_window_after(current_time);
+            self.keep_awake(current_time);
+            return 0;
+        }
+        
+        let wake_at = next_task.min(self.next_downlink);
+        let duration = wake_at.saturating_sub(current_time).min(self.config.max_sleep_ms);
+        if duration < self.config.min_sleep_ms {
+            return 0;
+        }
+        
+        let _ = hardware.enter_low_power_mode();
+        let _ = hardware.delay_ms(duration as u32);
+        let _ = hardware.exit_low_power_mode();
+        
+        self.total_sleep_ms += duration;
+        duration
+    }
+    
+    /// 累计睡眠时间（毫秒）
+    pub fn total_sleep_ms(&self) -> u64 {
+        self.total_sleep_ms
+    }
+}
\ No newline at end of file
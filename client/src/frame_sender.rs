@@ -0,0 +1,146 @@
+use common::hal::Hardware;
+use common::protocol::PacketType;
+use common::protocol::data::{flow_id_of, DataHeader, Fragmenter, MAX_FRAGMENT_PAYLOAD};
+use common::protocol::frame::{MAX_VIDEO_FRAME_SIZE, MAX_VIDEO_PAYLOAD, VIDEO_FRAME_HEADER_LEN};
+use common::protocol::payload::{self, VideoFrame};
+use common::protocol::reliable::{ReliableError, ReliableSender};
+use common::{info, warn};
+use crate::service_client::ServiceEndpoint;
+
+/// 提交视频帧的错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+pub enum FrameError {
+    /// 上一帧尚未发送完
+    Busy,
+    /// 帧为空
+    Empty,
+    /// 帧超过最大长度
+    TooLarge,
+}
+
+/// 视频帧发送器，整帧经[`Fragmenter`]拆成数据包分片逐个可靠发送，并按协商带宽控制发送节奏
+pub struct FrameSender {
+    /// 当前帧的负载，头部在发出第一个分片时写入
+    frame: [u8; MAX_VIDEO_PAYLOAD],
+    /// 当前帧长度，0表示空闲
+    len: usize,
+    /// 当前帧序号
+    frame_number: u16,
+    /// 当前帧各分片共用的包ID
+    packet_id: u16,
+    /// 下一个待发送的分片
+    next_fragment: u8,
+    /// 当前帧的分片总数
+    fragment_count: u8,
+    /// 按带宽计算的下一次允许发送时间
+    next_send_at: u64,
+    /// 协商带宽（kbps）
+    bandwidth_kbps: u16,
+}
+
+impl FrameSender {
+    pub fn new(bandwidth_kbps: u16) -> Self {
+        Self {
+            frame: [0; MAX_VIDEO_PAYLOAD],
+            len: 0,
+            frame_number: 0,
+            packet_id: 0,
+            next_fragment: 0,
+            fragment_count: 0,
+            next_send_at: 0,
+            bandwidth_kbps: bandwidth_kbps.max(1),
+        }
+    }
+    
+    /// 修改协商带宽
+    pub fn set_bandwidth(&mut self, bandwidth_kbps: u16) {
+        self.bandwidth_kbps = bandwidth_kbps.max(1);
+    }
+    
+    /// 是否还有未发送完的帧
+    pub fn is_busy(&self) -> bool {
+        self.len > 0
+    }
+    
+    /// 提交一帧，返回分配的帧序号
+    pub fn submit(&mut self, frame: &[u8]) -> Result<u16, FrameError> {
+        if self.is_busy() {
+            return Err(FrameError::Busy);
+        }
+        if frame.is_empty() {
+            return Err(FrameError::Empty);
+        }
+        if frame.len() > MAX_VIDEO_FRAME_SIZE {
+            return Err(FrameError::TooLarge);
+        }
+        
+        self.len = VIDEO_FRAME_HEADER_LEN + frame.len();
+        self.frame[VIDEO_FRAME_HEADER_LEN..self.len].copy_from_slice(frame);
+        self.frame_number = self.frame_number.wrapping_add(1);
+        self.next_fragment = 0;
+        self.fragment_count = self.len.div_ceil(MAX_FRAGMENT_PAYLOAD) as u8;
+        
+        Ok(self.frame_number)
+    }
+    
+    /// 放弃当前帧
+    pub fn abort(&mut self) {
+        self.len = 0;
+    }
+    
+    /// 在带宽和发送窗口允许的范围内发送分片，返回本次发送的分片数
+    pub fn poll<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        uplink: &mut ReliableSender,
+        endpoint: &ServiceEndpoint,
+        current_time: u64
+    ) -> usize {
+        let mut sent = 0;
+        
+        // 会话在提交后才确定，发出第一个分片前写入负载头部并分配各分片共用的包ID
+        if self.is_busy() && self.next_fragment == 0 {
+            let header = VideoFrame { service_id: endpoint.service_id, frame_number: self.frame_number, data: &[] };
+            payload::encode(&header, &mut self.frame[..VIDEO_FRAME_HEADER_LEN]);
+            self.packet_id = uplink.allocate_packet_id();
+        }
+        
+        let node_id = hardware.get_node_id();
+        while self.is_busy() && current_time >= self.next_send_at {
+            let fragment = Fragmenter::new(node_id, endpoint.server_id, PacketType::Data, self.packet_id, &self.frame[..self.len])
+                .and_then(|fragments| fragments.with_flow(flow_id_of(endpoint.service_id)).nth(self.next_fragment as usize));
+            let fragment = match fragment {
+                Some(fragment) => fragment,
+                None => {
+                    self.len = 0;
+                    break;
+                },
+            };
+            
+            match uplink.send_fragment(hardware, endpoint.service_id, &fragment, current_time) {
+                Ok(_) => {},
+                // 发送窗口已满，等待确认后再继续
+                Err(ReliableError::WindowFull) => break,
+                Err(e) => {
+                    warn!("发送视频帧 #{} 分片 {} 失败: {:?}", self.frame_number, self.next_fragment, e);
+                    break;
+                },
+            }
+            
+            // 按协商带宽计算下一个分片的发送时间（kbps即每毫秒比特数）
+            let bits = ((fragment.data.len() + DataHeader::SIZE) * 8) as u64;
+            self.next_send_at = current_time + bits / self.bandwidth_kbps as u64;
+            
+            sent += 1;
+            self.next_fragment += 1;
+            if self.next_fragment >= self.fragment_count {
+                info!("视频帧 #{} 已发送，{} 字节，{} 个分片",
+                      self.frame_number, self.len - VIDEO_FRAME_HEADER_LEN, self.fragment_count);
+                self.len = 0;
+            }
+        }
+        
+        sent
+    }
+}
\ No newline at end of file
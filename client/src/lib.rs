@@ -0,0 +1,721 @@
+#![cfg_attr(not(feature = "simulator"), no_std)]
+
+mod sensor_driver;
+mod batch_uploader;
+mod discovery;
+mod service_client;
+mod session_manager;
+mod rate_control;
+mod duty_cycle;
+mod settings;
+mod downlink;
+mod frame_sender;
+mod roaming;
+mod offline_buffer;
+mod relay_cache;
+mod rtt;
+pub mod sample_log;
+mod video_quality;
+
+// 样本日志的追加接口使用传感器数据
+pub use sensor_driver::SensorData;
+
+use common::protocol::{NodeId, NodeRole, Beacon, DataPacket, ServiceType, PacketType, PathStatus};
+use common::hal::{Hardware, ResetCause, RADIO_DIAGNOSTICS_INTERVAL_MS};
+use common::hal::nvs::{RebootBreadcrumb, RebootReason};
+use common::channel_plan::ChannelFollower;
+use common::clock::{NetworkClock, TIME_BEACON_INTERVAL_MS};
+use common::config::NodeConfig;
+use common::link_budget::LinkBudget;
+use common::mgmt::MgmtAgent;
+use common::ota::{OtaEvent, OtaReceiver, FIRMWARE_VERSION};
+use common::protocol::echo::answer_echo;
+use common::protocol::route_discovery::answer_route_request;
+use common::protocol::error_report::{ErrorCode, ErrorReporter};
+use common::protocol::frame::{VideoTierNotice, MAX_VIDEO_FRAME_SIZE};
+use common::protocol::reliable::{DeliveryEvent, Reception, ReliableReceiver, ReliableSender, RetryConfig};
+use common::protocol::stats::answer_stats;
+use common::protocol::tdma::{send_slot_request, SlotRequest, SlotTable, SLOT_REQUEST_INTERVAL_MS};
+use common::protocol::time_sync::TimeBeacon;
+use common::protocol::wire::{put_f32be, put_u64be};
+use common::security::{self, receive_secure};
+use common::utils::AlignedBuffer;
+use common::{info, warn};
+use batch_uploader::{BatchConfig, BatchUploader};
+use duty_cycle::{DutyCycle, DutyCycleConfig};
+use downlink::{handle_command, is_command};
+use frame_sender::FrameSender;
+use relay_cache::{CachedRelay, RelayCache};
+use roaming::{RoamingConfig, RoamingMonitor, MIN_LINK_MARGIN_DB};
+use rtt::{measure_rtt, trace_route};
+use sample_log::SampleLog;
+use settings::ClientSettings;
+use sensor_driver::Sensor;
+use sensor_driver::sht3x::{Sht3x, SHT3X_DEFAULT_ADDRESS};
+use discovery::{find_server, probe_server, DiscoveryBackoff};
+use service_client::{request_service, renew_service, handle_renewal_response, update_service_endpoint, handover_service, close_service};
+use service_client::{KeepAlive, MAX_MISSED_KEEPALIVES};
+use session_manager::{SessionManager, MAX_SESSIONS};
+use video_quality::{QualityConfig, VideoQuality};
+
+/// 客户端主循环，收到重启命令后返回，由入口重新启动
+pub fn client_main<H: Hardware>(hardware: &mut H) {
+    // 加载保存的配置，可通过下行命令修改
+    let mut settings = ClientSettings::load(hardware.get_nvs());
+    
+    // 使用已配置的节点身份，无需重新烧录
+    if let Some(node_id) = settings.node_id {
+        info!("使用已配置的节点身份: {}", node_id);
+        hardware.set_node_id(node_id);
+    }
+    
+    // 恢复发送计数器，网络密钥以客户端配置为准
+    security::restore(hardware);
+    if settings.network_key.is_some() {
+        hardware.get_security().set_network_key(settings.network_key);
+    }
+    
+    // 运行配置，可通过远程管理调整；客户端不发送信标，信道以下行命令保存的设置为准
+    let mut config = NodeConfig::load(hardware.get_nvs(), NodeRole::Client);
+    config.channel = settings.channel;
+    hardware.set_default_ttl(config.default_ttl);
+    let mut mgmt = MgmtAgent::new(hardware.get_nvs());
+    
+    // 网络时钟，跟随主节点广播的时间信标
+    let mut clock = NetworkClock::new();
+    
+    // 跟随主节点组织的信道切换
+    let mut channel_follower = ChannelFollower::new();
+    
+    // 主节点随时间信标下发的时隙表，视频和批量数据只在本节点的时隙内发送
+    let mut slots = SlotTable::empty();
+    let mut slot_request_timer: u64 = 0;
+    let mut diagnostics_timer: u64 = 0;
+    
+    // 配置无线电
+    let radio = hardware.get_radio();
+    let _ = radio.configure(config.channel, config.tx_power);
+    let _ = radio.set_airtime_limit(config.airtime_config());
+    
+    // 注册并初始化传感器
+    let mut sht3x = Sht3x::new(SHT3X_DEFAULT_ADDRESS);
+    let mut sensors: [&mut dyn Sensor<H>; 1] = [&mut sht3x];
+    let ready = sensor_driver::init_all(hardware, &mut sensors);
+    info!("已初始化 {}/{} 个传感器", ready, sensors.len());
+    
+    // 创建缓冲区
+    let mut rx_buffer = AlignedBuffer::<1024>::new();
+    let mut tx_buffer = AlignedBuffer::<256>::new();
+    
+    // 初始化固件接收端，恢复中断的更新进度
+    let mut ota = OtaReceiver::new(FIRMWARE_VERSION, hardware.get_nvs());
+    
+    // 发现服务器节点（转发节点）
+    info!("正在搜索网络...");
+    
+    // 先探测最近成功使用过的中继，都不可达时再多轮扫描并按信号强度、电量和角色排序候选中继
+    let mut relay_cache = RelayCache::load(hardware.get_nvs());
+    let mut forward_id = relay_cache.probe(hardware, &mut rx_buffer)
+        .map(|relay| relay.node_id)
+        .or_else(|| find_server(hardware));
+    
+    // 监视当前中继的链路质量，余量不足时漫游到更好的中继；中继的发射功率按默认值估计
+    let roaming_config = RoamingConfig::with_min_margin(&LinkBudget::default(), MIN_LINK_MARGIN_DB);
+    let mut roaming = RoamingMonitor::new(roaming_config, forward_id.unwrap_or(NodeId::BROADCAST),
+                                          hardware.get_timestamp_ms().unwrap_or(0));
+    
+    // 同时保持视频中继和传感器数据收集两个会话
+    let mut sessions = SessionManager::new();
+    
+    // 需要重建的会话（服务类型）以及是否需要重新发现转发节点
+    let mut broken_sessions: [Option<ServiceType>; MAX_SESSIONS] = [None; MAX_SESSIONS];
+    let mut rediscover = false;
+    
+    // 离线时不退出，继续采样并按退避间隔重新发现网络
+    let mut discovery_backoff = DiscoveryBackoff::new();
+    
+    // 上行可靠发送，跟踪未确认的帧并按退避重传，服务请求也经它重传
+    let mut uplink = ReliableSender::new(RetryConfig::default());
+    
+    // 下行可靠接收，确认中继发来的路径确认并丢弃重传的副本
+    let mut downlink = ReliableReceiver::new();
+    
+    match forward_id {
+        Some(relay) => {
+            info!("找到转发节点: {}", relay);
+            
+            info!("正在请求视频中继服务...");
+            if !open_session(hardware, &mut sessions, &mut uplink, relay, ServiceType::VideoRelay,
+                             &settings, &mut tx_buffer, &mut rx_buffer) {
+                warn!("无法获取视频中继服务，稍后重试");
+                mark_broken(&mut broken_sessions, ServiceType::VideoRelay);
+            }
+            
+            info!("正在请求传感器数据收集服务...");
+            if !open_session(hardware, &mut sessions, &mut uplink, relay, ServiceType::SensorCollection,
+                             &settings, &mut tx_buffer, &mut rx_buffer) {
+                warn!("无法获取传感器数据收集服务，稍后重试");
+                mark_broken(&mut broken_sessions, ServiceType::SensorCollection);
+            }
+        },
+        None => {
+            warn!("无法找到转发节点，离线缓存样本并稍后重试");
+            let now = hardware.get_timestamp_ms().unwrap_or(0);
+            discovery_backoff.on_failure(hardware, now);
+            mark_broken(&mut broken_sessions, ServiceType::VideoRelay);
+            mark_broken(&mut broken_sessions, ServiceType::SensorCollection);
+            rediscover = true;
+        },
+    }
+    
+    // 等待路径建立完成
+    info!("等待中继路径建立...");
+    
+    // 错误上报，投递失败等错误发往主节点
+    let mut errors = ErrorReporter::new();
+    if hardware.reset_cause() == ResetCause::Watchdog {
+        errors.record(ErrorCode::WatchdogReset, 0);
+    }
+    
+    // 视频帧按协商带宽分片发送
+    let mut frame_sender = FrameSender::new(settings.video_qos.min_bandwidth);
+    let mut frame_buffer = [0u8; MAX_VIDEO_FRAME_SIZE];
+    
+    // 视频帧率和帧长按丢包、时延和协商带宽在质量档位间自适应
+    let mut video_quality = VideoQuality::new(
+        QualityConfig::default(),
+        &settings.video_qos,
+        hardware.get_timestamp_ms().unwrap_or(0)
+    );
+    
+    // 传感器样本在本地攒批，减少无线电唤醒次数
+    let mut batcher = BatchUploader::new(BatchConfig {
+        sample_interval_ms: settings.sample_interval_ms as u64,
+        ..BatchConfig::default()
+    });
+    
+    // 每个样本都写入本地闪存日志，长时间离线后可在现场用读取日志命令取回
+    let mut sample_log = SampleLog::load(hardware.get_nvs());
+    info!("本地日志中有 {} 个样本，下一个序号 {}", sample_log.sample_count(), sample_log.next_seq());
+    
+    // 在采样和上传之间进入低功耗模式
+    let start_time = hardware.get_timestamp_ms().unwrap_or(0);
+    let mut duty_cycle = DutyCycle::new(DutyCycleConfig::default(), start_time);
+    
+    // 主循环
+    loop {
+        // 获取当前时间
+        let now = hardware.get_timestamp_ms().unwrap_or(0);
+        
+        // 处理收到的数据包，按服务ID分发到对应会话
+        let buffer = rx_buffer.as_mut_slice();
+        
+        if let Some(packet) = receive_secure(hardware, buffer) {
+            let packet_type = PacketType::from_u8(packet.header.packet_type);
+            duty_cycle.keep_awake(now);
+            
+            if packet_type == Some(PacketType::Data) && is_command(&packet) {
+                // 下行命令
+                let change = handle_command(hardware, &mut settings, &sample_log, &packet);
+                
+                if change.reboot {
+                    return;
+                }
+                if change.sample_interval {
+                    batcher.set_sample_interval(settings.sample_interval_ms as u64);
+                }
+                if change.channel {
+                    config.channel = settings.channel;
+                    let _ = hardware.get_radio().configure(config.channel, config.tx_power);
+                }
+                if change.sessions {
+                    frame_sender.set_bandwidth(settings.video_qos.min_bandwidth);
+                    video_quality.set_qos(&settings.video_qos);
+                    // 服务质量或目标服务器变化，按新配置重建所有会话
+                    for session in sessions.iter_mut() {
+                        mark_broken(&mut broken_sessions, session.endpoint.service_type);
+                    }
+                }
+            } else if packet_type == Some(PacketType::EchoRequest) {
+                // 其他节点测量到本节点的往返时延
+                answer_echo(hardware, &packet);
+            } else if packet_type == Some(PacketType::StatsRequest) {
+                // 运维节点远程读取本节点的计数器
+                answer_stats(hardware, &packet);
+            } else if packet_type == Some(PacketType::Flood) {
+                // 转发节点查找本节点的路由请求，客户端只应答不转发
+                answer_route_request(hardware, &packet);
+            } else if packet_type == Some(PacketType::TimeSync) {
+                // 时间信标，客户端只校准不转发
+                if let Some(beacon) = TimeBeacon::deserialize(packet.data) {
+                    if let Some((_, step)) = clock.apply(&beacon, now) {
+                        if step != 0 {
+                            info!("网络时钟跳变 {}ms，主节点: {}", step, beacon.master);
+                        }
+                        slots = SlotTable::from_time_sync(packet.data);
+                        // 之后的定期下行窗口对准信标
+                        duty_cycle.observe_beacon(now, TIME_BEACON_INTERVAL_MS);
+                    }
+                }
+            } else if packet_type == Some(PacketType::ChannelPlan) {
+                // 信道切换通告，客户端不转发
+                channel_follower.handle(&packet, config.channel);
+            } else if packet_type == Some(PacketType::Mgmt) {
+                // 远程管理请求
+                mgmt.handle(hardware, &packet, &mut config);
+            } else if packet_type == Some(PacketType::Ota) {
+                // 固件更新，安装完成后重启切换镜像
+                match ota.handle(hardware, &packet, now) {
+                    OtaEvent::Installed { version } => {
+                        info!("固件版本 {} 已安装，重启切换", version);
+                        let breadcrumb = RebootBreadcrumb {
+                            reason: RebootReason::FirmwareUpdate,
+                            requested_by: NodeId(packet.header.source),
+                            timestamp: now,
+                        };
+                        let _ = breadcrumb.store(hardware.get_nvs());
+                        if hardware.system_reset().is_ok() {
+                            return;
+                        }
+                    },
+                    OtaEvent::Failed(status) => warn!("固件更新失败: {:?}", status),
+                    OtaEvent::Rejected => warn!("固件更新消息未通过认证，已丢弃"),
+                    _ => {},
+                }
+            } else if packet_type == Some(PacketType::Ack) {
+                // 上行数据确认
+                if let Some(DeliveryEvent::Delivered { session_id, packet_id }) = uplink.handle_ack(&packet) {
+                    info!("会话 {} 的数据已确认，包ID: {}", session_id, packet_id);
+                    if let Some(session) = sessions.get_mut(session_id) {
+                        session.rate.on_delivered();
+                        if session.endpoint.service_type == ServiceType::VideoRelay {
+                            video_quality.on_delivered();
+                        }
+                    }
+                }
+            } else if let Some(session) = sessions.dispatch(&packet) {
+                match packet_type {
+                    Some(PacketType::PathConfirm)
+                        if downlink.receive(hardware, &packet, session.endpoint.service_id, now) == Reception::Duplicate => {
+                        // 中继收到确认前会重传路径确认，重复的确认已重新确认，不再处理
+                    },
+                    Some(PacketType::PathConfirm) => {
+                        // 处理路径确认
+                        let status = packet.data[6];
+                        
+                        if status == PathStatus::Success as u8 {
+                            session.path_established = true;
+                            session.keepalive = KeepAlive::new(now);
+                            update_service_endpoint(&mut session.endpoint, packet.data[7]);
+                            
+                            // 记住成功建立路径的中继，下次唤醒或重启时优先尝试
+                            if let Some(relay) = forward_id {
+                                let rssi = hardware.get_radio().get_rssi().unwrap_or(0);
+                                if relay_cache.remember(CachedRelay { node_id: relay, rssi, hop_count: packet.data[7] }) {
+                                    let _ = relay_cache.store(hardware.get_nvs());
+                                }
+                            }
+                            info!("服务 {} 的中继路径建立成功，跳数: {}",
+                                     session.endpoint.service_id, packet.data[7]);
+                        } else {
+                            warn!("服务 {} 的中继路径建立失败，状态: {}",
+                                     session.endpoint.service_id, status);
+                        }
+                    },
+                    Some(PacketType::Congestion) => {
+                        // 中继转发拥塞，降低该会话的发送速率
+                        session.rate.on_congestion(now);
+                        if session.endpoint.service_type == ServiceType::VideoRelay {
+                            video_quality.on_lost();
+                        }
+                        info!("服务 {} 的中继拥塞，发送间隔调整为 {}ms",
+                                 session.endpoint.service_id, session.rate.interval_ms());
+                    },
+                    Some(PacketType::PathKeepAliveAck) => {
+                        session.keepalive.handle_ack(&packet);
+                    },
+                    Some(PacketType::ServiceResponse) => {
+                        // 租约续期响应
+                        if !handle_renewal_response(&mut session.endpoint, &packet, now) {
+                            mark_broken(&mut broken_sessions, session.endpoint.service_type);
+                            rediscover = true;
+                        }
+                    },
+                    _ => {
+                        info!("会话 {} 收到数据包，类型: {:?}",
+                                 session.endpoint.service_id, packet.header.packet_type);
+                    }
+                }
+            } else {
+                // 不属于任何会话的数据包
+                info!("收到数据包，类型: {:?}", packet.header.packet_type);
+            }
+        }
+        
+        // 路径建立后测量端到端往返时延，确认路径满足QoS的最大延迟要求
+        if let Some(relay) = forward_id {
+            for session in sessions.iter_mut() {
+                if !session.path_established || session.latency_checked {
+                    continue;
+                }
+                session.latency_checked = true;
+                
+                let endpoint = &session.endpoint;
+                let max_latency = settings.qos_for(endpoint.service_type).max_latency as u32;
+                let sample = measure_rtt(hardware, relay, endpoint.server_id, &mut rx_buffer);
+                if let (Some(sample), ServiceType::VideoRelay) = (&sample, endpoint.service_type) {
+                    video_quality.on_rtt(sample.rtt_ms);
+                }
+                match sample {
+                    Some(sample) if sample.rtt_ms > max_latency => {
+                        warn!("服务 {} 的往返时延 {}ms 超过要求的 {}ms，重新请求服务",
+                                 endpoint.service_id, sample.rtt_ms, max_latency);
+                        // 记录慢路径上的各跳，便于定位是哪段链路变差
+                        let trace = trace_route(hardware, relay, endpoint.server_id, &mut rx_buffer)
+                            .and_then(|sample| sample.trace);
+                        for hop in trace.iter().flat_map(|trace| trace.hops()) {
+                            warn!("  经过 {}，RSSI {} dBm", hop.node_id, hop.rssi);
+                        }
+                        mark_broken(&mut broken_sessions, endpoint.service_type);
+                    },
+                    Some(sample) => {
+                        info!("服务 {} 的往返时延 {}ms，跳数: {}",
+                                 endpoint.service_id, sample.rtt_ms, sample.hop_count);
+                    },
+                    None => {
+                        warn!("服务 {} 的回显请求超时，无法验证时延", endpoint.service_id);
+                    },
+                }
+            }
+        }
+        
+        // 监听中继信标，评估当前中继的链路质量
+        while let Ok(Some(beacon)) = hardware.get_radio().receive_beacon() {
+            if beacon.is_valid() && security::verify_beacon(hardware, &beacon) {
+                let link_rssi = hardware.get_radio().get_rssi().unwrap_or(beacon.rssi);
+                roaming.observe_beacon(&beacon, link_rssi, now);
+            }
+        }
+        
+        // 当前中继变差且听到更好的中继时，保留服务ID把所有会话迁移过去
+        if let (Some(relay), Some(new_relay)) = (forward_id, roaming.handover_target(now)) {
+            info!("中继 {} 链路变差，切换到 {}", relay, new_relay);
+            
+            for session in sessions.iter_mut() {
+                let qos = settings.qos_for(session.endpoint.service_type);
+                if handover_service(hardware, new_relay, &mut session.endpoint, qos, &mut tx_buffer, &mut rx_buffer) {
+                    // 等待经由新中继的路径确认
+                    session.path_established = false;
+                    session.latency_checked = false;
+                    session.opened_at = now;
+                } else {
+                    mark_broken(&mut broken_sessions, session.endpoint.service_type);
+                }
+            }
+            
+            forward_id = Some(new_relay);
+            roaming.set_serving(new_relay, now);
+        }
+        
+        // 重传超时的帧，持续失败说明该会话的路径已不可用
+        uplink.poll(hardware, now, |event| {
+            if let DeliveryEvent::Failed { session_id, packet_id, destination } = event {
+                warn!("会话 {} 的包 {} 多次重传仍未确认，目标 {} 不可达",
+                         session_id, packet_id, destination);
+                if let Some(session) = sessions.get_mut(session_id) {
+                    session.rate.on_congestion(now);
+                    if session.endpoint.service_type == ServiceType::VideoRelay {
+                        video_quality.on_lost();
+                    }
+                    mark_broken(&mut broken_sessions, session.endpoint.service_type);
+                }
+            }
+        });
+        
+        for session in sessions.iter_mut() {
+            // 中继不再确认保活说明路径已断，重新发现网络并建立路径
+            if session.path_established {
+                // 发送保活后保持唤醒，等待中继的确认
+                if now >= session.keepalive.due_at() {
+                    duty_cycle.keep_awake(now);
+                }
+                session.keepalive.poll(hardware, &session.endpoint, now, |endpoint| {
+                    warn!("服务 {} 的路径保活连续 {} 次未确认，路径已失效", endpoint.service_id, MAX_MISSED_KEEPALIVES);
+                    mark_broken(&mut broken_sessions, endpoint.service_type);
+                    rediscover = true;
+                });
+            }
+            
+            let endpoint = &mut session.endpoint;
+            
+            // 在租约到期前续期，到期仍未续上则视为服务丢失
+            if endpoint.is_expired(now) {
+                info!("服务 {} 的租约已到期", endpoint.service_id);
+                mark_broken(&mut broken_sessions, endpoint.service_type);
+                rediscover = true;
+            } else if endpoint.needs_renewal(now) {
+                renew_service(hardware, endpoint, &mut tx_buffer);
+            }
+            
+            // 等待路径建立超时（30秒）
+            if !session.path_established && now.saturating_sub(session.opened_at) > 30000 {
+                warn!("服务 {} 等待路径建立超时", endpoint.service_id);
+                mark_broken(&mut broken_sessions, endpoint.service_type);
+            }
+        }
+        
+        // 租约丢失说明转发节点可能已不可用，按退避间隔重新发现网络
+        if rediscover && discovery_backoff.due(now) {
+            info!("重新发现转发节点...");
+            let cached = relay_cache.probe(hardware, &mut rx_buffer).map(|relay| relay.node_id);
+            match cached.or_else(|| probe_server(hardware)) {
+                Some(node) => {
+                    info!("找到转发节点: {}，积压 {} 个样本批量待补传", node, batcher.backlog());
+                    rediscover = false;
+                    discovery_backoff.reset();
+                    forward_id = Some(node);
+                    roaming.set_serving(node, now);
+                },
+                None => {
+                    let wait = discovery_backoff.on_failure(hardware, now);
+                    warn!("未找到转发节点，{}ms 后重试", wait);
+                },
+            }
+        }
+        
+        // 重建失效的会话，离线期间等到重新发现网络后再请求
+        if let (Some(relay), false) = (forward_id, rediscover) {
+            for entry in broken_sessions.iter_mut() {
+                if let Some(service_type) = entry.take() {
+                    if service_type == ServiceType::VideoRelay {
+                        frame_sender.abort();
+                        video_quality.restart(now);
+                    }
+                    
+                    if let Some(old) = sessions.get_by_type(service_type) {
+                        let service_id = old.endpoint.service_id;
+                        // 尽力通知原中继拆除路径，中继不可达时由其空闲超时回收
+                        let endpoint = old.endpoint;
+                        close_service(hardware, &endpoint, &mut tx_buffer);
+                        uplink.cancel_session(service_id);
+                        sessions.remove(service_id);
+                    }
+                    
+                    info!("重新请求服务: {:?}", service_type);
+                    if !open_session(hardware, &mut sessions, &mut uplink, relay, service_type, &settings,
+                                     &mut tx_buffer, &mut rx_buffer) {
+                        // 保留待重建的记录，重新发现网络后再试
+                        *entry = Some(service_type);
+                        rediscover = true;
+                        discovery_backoff.on_failure(hardware, now);
+                        break;
+                    }
+                }
+            }
+        }
+        
+        if sessions.is_empty() && !rediscover {
+            info!("所有服务会话均已失效，离线缓存样本并重新发现网络");
+            rediscover = true;
+        }
+        
+        // 按采样间隔采集传感器数据，离线或路径未建立时也继续累积
+        if batcher.should_sample(now) {
+            let sensor_data = sensor_driver::read_all(hardware, &mut sensors);
+            if sample_log.append(hardware.get_nvs(), now, &sensor_data).is_err() {
+                warn!("写入本地样本日志失败");
+            }
+            if !batcher.add_sample(hardware, now, &sensor_data) {
+                warn!("离线缓冲已满，丢弃最早的样本批量");
+            }
+        }
+        
+        // 视频会话需要可预期的时延，定期经当前中继向主节点申请时隙
+        let streaming = matches!(sessions.get_by_type(ServiceType::VideoRelay), Some(s) if s.path_established);
+        if let (true, Some(relay), Some(master)) = (streaming, forward_id, clock.master()) {
+            if now - slot_request_timer > SLOT_REQUEST_INTERVAL_MS {
+                let request = SlotRequest { node: hardware.get_node_id(), master };
+                if let Err(e) = send_slot_request(hardware, relay, &request) {
+                    warn!("发送时隙申请失败: {:?}", e);
+                }
+                slot_request_timer = now;
+            }
+        }
+        
+        // 向已建立路径的会话发送数据，大流量数据等到本节点的时隙再发
+        let in_slot = slots.may_send_bulk(hardware.get_node_id(), clock.now(now));
+        for session in sessions.iter_mut() {
+            if !session.path_established {
+                continue;
+            }
+            
+            match session.endpoint.service_type {
+                ServiceType::VideoRelay => {
+                    if let Some(tier) = video_quality.poll(now) {
+                        let current = video_quality.current();
+                        info!("视频质量切换到档位 {}：每 {}ms 一帧，{} 字节",
+                                 tier, current.frame_interval_ms, current.frame_size);
+                    }
+                    
+                    // 通知服务器当前档位，发送窗口已满时下次再试
+                    if let Some(tier) = video_quality.announcement() {
+                        let notice = VideoTierNotice { service_id: session.endpoint.service_id, tier };
+                        let endpoint = &session.endpoint;
+                        if uplink.send(hardware, endpoint.service_id, endpoint.server_id, &notice.serialize(), now).is_ok() {
+                            video_quality.mark_announced();
+                        }
+                    }
+                    
+                    // 视频帧间隔随路径反馈自适应调整，且不短于当前档位的帧间隔，上一帧发完后才采集下一帧
+                    let tier = video_quality.current();
+                    let interval_elapsed = now.saturating_sub(session.last_send) >= tier.frame_interval_ms as u64;
+                    if !frame_sender.is_busy() && interval_elapsed && session.rate.ready(session.last_send, now) {
+                        let sensor_data = sensor_driver::read_all(hardware, &mut sensors);
+                        let len = capture_frame(&sensor_data, now, &mut frame_buffer[..tier.frame_size as usize]);
+                        
+                        if let Err(e) = frame_sender.submit(&frame_buffer[..len]) {
+                            warn!("提交视频帧失败: {:?}", e);
+                        }
+                        
+                        session.last_send = now;
+                    }
+                    
+                    if in_slot {
+                        frame_sender.poll(hardware, &mut uplink, &session.endpoint, now);
+                    }
+                },
+                _ => {
+                    // 传感器数据达到批量阈值或上传间隔时一次性上传，并补传离线积压的批量
+                    if in_slot && batcher.should_upload(now) {
+                        match batcher.upload(hardware, &mut uplink, &session.endpoint, now) {
+                            Ok(samples) => info!("已上传 {} 个传感器样本", samples),
+                            Err(e) => warn!("上传传感器批量失败: {:?}", e),
+                        }
+                        
+                        session.last_send = now;
+                        duty_cycle.keep_awake(now);
+                    }
+                },
+            }
+        }
+        
+        // 重新请求超时的固件分块
+        if ota.poll(hardware, now) == OtaEvent::Stalled {
+            warn!("固件更新无响应，已暂停并保留进度");
+        }
+        
+        // 到了切换时间换到新信道，保存到设置中，重启后仍在新信道上
+        if let Some(channel) = channel_follower.poll(clock.now(now)) {
+            info!("跟随主节点切换到信道 {}", channel);
+            settings.channel = channel;
+            config.channel = channel;
+            let _ = settings.store(hardware.get_nvs());
+            let _ = hardware.get_radio().configure(config.channel, config.tx_power);
+        }
+        
+        // 定期读取无线电收发统计，只能读取累计值的后端借此更新指标
+        if now - diagnostics_timer > RADIO_DIAGNOSTICS_INTERVAL_MS {
+            hardware.get_radio().diagnostics();
+            diagnostics_timer = now;
+        }
+        
+        // 限速上报本节点的错误
+        errors.poll(hardware, clock.master(), clock.now(now));
+        
+        // 只有在没有视频流、路径均已建立、没有待确认帧且不在接收固件时才进入睡眠
+        let can_sleep = uplink.pending() == 0 && !ota.is_active() && sessions.iter_mut().all(|session| {
+            session.path_established && session.endpoint.service_type != ServiceType::VideoRelay
+        });
+        
+        let slept = if can_sleep {
+            let mut next_task = batcher.next_sample_at();
+            if let Some(upload_at) = batcher.next_upload_at() {
+                // 离线时无法上传，不因上传时间提前唤醒
+                if sessions.get_by_type(ServiceType::SensorCollection).is_some() {
+                    next_task = next_task.min(upload_at);
+                }
+            }
+            if rediscover {
+                next_task = next_task.min(discovery_backoff.next_attempt_at());
+            }
+            for session in sessions.iter_mut() {
+                next_task = next_task.min(session.endpoint.renewal_due_at()).min(session.keepalive.due_at());
+            }
+            
+            duty_cycle.sleep_until(hardware, now, next_task)
+        } else {
+            0
+        };
+        
+        if slept == 0 {
+            // 延迟100ms
+            let _ = hardware.delay_ms(100);
+        }
+    }
+}
+
+/// 请求服务并加入会话管理器
+fn open_session<H: Hardware>(
+    hardware: &mut H,
+    sessions: &mut SessionManager,
+    uplink: &mut ReliableSender,
+    forward_id: NodeId,
+    service_type: ServiceType,
+    settings: &ClientSettings,
+    tx_buffer: &mut AlignedBuffer<256>,
+    rx_buffer: &mut AlignedBuffer<1024>
+) -> bool {
+    let endpoint = request_service(
+        hardware,
+        uplink,
+        forward_id,
+        service_type,
+        settings.qos_for(service_type),
+        60, // 60秒过期时间
+        tx_buffer,
+        rx_buffer
+    );
+    
+    match endpoint {
+        Some(mut endpoint) => {
+            // 配置了目标服务器时，数据直接发往该服务器
+            if let Some(server) = settings.server_affinity {
+                endpoint.server_id = server;
+            }
+            
+            info!("成功获取服务 {:?}：服务器={}, 服务ID={}",
+                     service_type, endpoint.server_id, endpoint.service_id);
+            let now = hardware.get_timestamp_ms().unwrap_or(0);
+            sessions.add(endpoint, now)
+        },
+        None => false,
+    }
+}
+
+/// 记录需要重建的会话，同一类型只记录一次
+fn mark_broken(broken: &mut [Option<ServiceType>; MAX_SESSIONS], service_type: ServiceType) {
+    if broken.iter().any(|entry| *entry == Some(service_type)) {
+        return;
+    }
+    if let Some(slot) = broken.iter_mut().find(|entry| entry.is_none()) {
+        *slot = Some(service_type);
+    }
+}
+
+/// 模拟摄像头按缓冲区长度采集一帧，返回帧长度
+///
+/// 帧头包含时间戳和传感器读数，其余部分填充随时间变化的图案。
+fn capture_frame(sensor_data: &SensorData, timestamp: u64, buffer: &mut [u8]) -> usize {
+    let len = buffer.len();
+    
+    put_u64be(&mut buffer[0..8], timestamp);
+    put_f32be(&mut buffer[8..12], sensor_data.temperature);
+    put_f32be(&mut buffer[12..16], sensor_data.humidity);
+    put_f32be(&mut buffer[16..20], sensor_data.pressure);
+    
+    for (i, byte) in buffer[20..len].iter_mut().enumerate() {
+        *byte = (i as u64).wrapping_add(timestamp / 100) as u8;
+    }
+    
+    len
+}
\ No newline at end of file
@@ -0,0 +1,169 @@
+use common::hal::nvs::{keys, NvStorage};
+use common::protocol::batch::{SampleBatch, MAX_BATCH_SAMPLES};
+
+/// 内存中最多缓存的批量数
+pub const RAM_BATCHES: usize = 4;
+
+/// 闪存中最多缓存的批量数
+pub const FLASH_BATCHES: u16 = 32;
+
+/// 溢出记录中单个样本的长度：时间戳(8) 温度(4) 湿度(4) 气压(4)
+const SPILL_SAMPLE_LEN: usize = 20;
+
+/// 溢出记录的最大长度：样本数(1) 样本*
+const SPILL_RECORD_LEN: usize = 1 + MAX_BATCH_SAMPLES * SPILL_SAMPLE_LEN;
+
+/// 离线期间的样本缓冲
+///
+/// 无法上传时已满的批量先保存在内存中，内存满后最早的批量溢出到闪存，
+/// 闪存也满时丢弃最早的批量。样本保留原始采样时间，恢复连接后按时间顺序补传。
+pub struct OfflineBuffer {
+    /// 内存中的批量（环形队列）
+    ram: [Option<SampleBatch>; RAM_BATCHES],
+    ram_head: usize,
+    ram_len: usize,
+    /// 闪存中最早批量所在的槽位
+    flash_head: u16,
+    flash_len: u16,
+    /// 因缓冲已满丢弃的批量数
+    dropped: u32,
+}
+
+impl OfflineBuffer {
+    pub fn new() -> Self {
+        Self {
+            ram: [None; RAM_BATCHES],
+            ram_head: 0,
+            ram_len: 0,
+            flash_head: 0,
+            flash_len: 0,
+            dropped: 0,
+        }
+    }
+    
+    /// 缓存的批量数
+    pub fn len(&self) -> usize {
+        self.ram_len + self.flash_len as usize
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// 因缓冲已满丢弃的批量数
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+    
+    /// 保存一个已满的批量，缓冲已满不得不丢弃最早的批量时返回false
+    pub fn push<N: NvStorage>(&mut self, nvs: &mut N, batch: &SampleBatch) -> bool {
+        let mut kept = true;
+        
+        if self.ram_len == RAM_BATCHES {
+            // 内存已满，最早的批量溢出到闪存
+            let oldest = self.ram[self.ram_head].take();
+            self.ram_head = (self.ram_head + 1) % RAM_BATCHES;
+            self.ram_len -= 1;
+            
+            if let Some(oldest) = oldest {
+                kept = self.spill(nvs, &oldest);
+            }
+        }
+        
+        let tail = (self.ram_head + self.ram_len) % RAM_BATCHES;
+        self.ram[tail] = Some(*batch);
+        self.ram_len += 1;
+        
+        kept
+    }
+    
+    /// 最早的缓存批量，闪存中的记录损坏时返回空批量
+    pub fn oldest<N: NvStorage>(&self, nvs: &mut N) -> Option<SampleBatch> {
+        if self.flash_len > 0 {
+            let mut record = [0u8; SPILL_RECORD_LEN];
+            let batch = match nvs.nvs_read(keys::OFFLINE_SPILL_BASE + self.flash_head, &mut record) {
+                Ok(Some(len)) => decode(&record[..len]),
+                _ => None,
+            };
+            return Some(batch.unwrap_or_else(SampleBatch::new));
+        }
+        
+        if self.ram_len > 0 {
+            return self.ram[self.ram_head];
+        }
+        
+        None
+    }
+    
+    /// 移除最早的缓存批量
+    pub fn discard_oldest<N: NvStorage>(&mut self, nvs: &mut N) {
+        if self.flash_len > 0 {
+            let _ = nvs.nvs_erase(keys::OFFLINE_SPILL_BASE + self.flash_head);
+            self.flash_head = (self.flash_head + 1) % FLASH_BATCHES;
+            self.flash_len -= 1;
+        } else if self.ram_len > 0 {
+            self.ram[self.ram_head] = None;
+            self.ram_head = (self.ram_head + 1) % RAM_BATCHES;
+            self.ram_len -= 1;
+        }
+    }
+    
+    /// 将批量写入闪存队列尾部，闪存已满时覆盖最早的批量
+    fn spill<N: NvStorage>(&mut self, nvs: &mut N, batch: &SampleBatch) -> bool {
+        let mut kept = true;
+        
+        if self.flash_len == FLASH_BATCHES {
+            self.flash_head = (self.flash_head + 1) % FLASH_BATCHES;
+            self.flash_len -= 1;
+            self.dropped += 1;
+            kept = false;
+        }
+        
+        let mut record = [0u8; SPILL_RECORD_LEN];
+        let len = encode(batch, &mut record);
+        let slot = (self.flash_head + self.flash_len) % FLASH_BATCHES;
+        
+        if nvs.nvs_write(keys::OFFLINE_SPILL_BASE + slot, &record[..len]).is_err() {
+            self.dropped += 1;
+            return false;
+        }
+        self.flash_len += 1;
+        
+        kept
+    }
+}
+
+/// 编码溢出记录，保留完整的采样时间戳，返回写入长度
+fn encode(batch: &SampleBatch, buffer: &mut [u8; SPILL_RECORD_LEN]) -> usize {
+    buffer[0] = batch.len() as u8;
+    
+    let mut offset = 1;
+    for (timestamp, sample) in batch.iter() {
+        buffer[offset..offset + 8].copy_from_slice(&timestamp.to_be_bytes());
+        buffer[offset + 8..offset + 12].copy_from_slice(&sample.temperature.to_be_bytes());
+        buffer[offset + 12..offset + 16].copy_from_slice(&sample.humidity.to_be_bytes());
+        buffer[offset + 16..offset + 20].copy_from_slice(&sample.pressure.to_be_bytes());
+        offset += SPILL_SAMPLE_LEN;
+    }
+    
+    offset
+}
+
+/// 解析溢出记录
+fn decode(buffer: &[u8]) -> Option<SampleBatch> {
+    let count = *buffer.first()? as usize;
+    if count > MAX_BATCH_SAMPLES || buffer.len() < 1 + count * SPILL_SAMPLE_LEN {
+        return None;
+    }
+    
+    let mut batch = SampleBatch::new();
+    for chunk in buffer[1..1 + count * SPILL_SAMPLE_LEN].chunks_exact(SPILL_SAMPLE_LEN) {
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&chunk[0..8]);
+        let read_f32 = |offset: usize| f32::from_be_bytes([chunk[offset], chunk[offset + 1], chunk[offset + 2], chunk[offset + 3]]);
+        
+        batch.push(u64::from_be_bytes(timestamp), read_f32(8), read_f32(12), read_f32(16));
+    }
+    
+    Some(batch)
+}
\ No newline at end of file
@@ -0,0 +1,77 @@
+/// 自适应发送速率参数
+#[derive(Debug, Clone, Copy)]
+pub struct RateConfig {
+    /// 初始发送间隔（毫秒）
+    pub initial_interval_ms: u32,
+    /// 最小发送间隔，即最高发送速率（毫秒）
+    pub min_interval_ms: u32,
+    /// 最大发送间隔，即最低发送速率（毫秒）
+    pub max_interval_ms: u32,
+    /// 每次确认后缩短的间隔（毫秒）
+    pub additive_step_ms: u32,
+    /// 两次降速之间的最短时间，避免一次拥塞引起连续降速（毫秒）
+    pub decrease_holdoff_ms: u64,
+}
+
+impl Default for RateConfig {
+    fn default() -> Self {
+        Self {
+            initial_interval_ms: 500,
+            min_interval_ms: 200,
+            max_interval_ms: 8000,
+            additive_step_ms: 20,
+            decrease_holdoff_ms: 1000,
+        }
+    }
+}
+
+/// AIMD速率控制：确认后线性提速，丢包或拥塞时发送间隔加倍
+#[derive(Debug, Clone, Copy)]
+pub struct RateController {
+    config: RateConfig,
+    /// 当前发送间隔（毫秒）
+    interval_ms: u32,
+    /// 上次降速的时间
+    last_decrease: Option<u64>,
+}
+
+impl RateController {
+    pub fn new(config: RateConfig) -> Self {
+        Self {
+            interval_ms: config.initial_interval_ms.clamp(config.min_interval_ms, config.max_interval_ms),
+            config,
+            last_decrease: None,
+        }
+    }
+    
+    /// 当前发送间隔（毫秒）
+    pub fn interval_ms(&self) -> u32 {
+        self.interval_ms
+    }
+    
+    /// 距上次发送是否已经过了当前间隔
+    pub fn ready(&self, last_send: u64, current_time: u64) -> bool {
+        current_time.saturating_sub(last_send) >= self.interval_ms as u64
+    }
+    
+    /// 数据已确认：加性提速
+    pub fn on_delivered(&mut self) {
+        self.interval_ms = self.interval_ms
+            .saturating_sub(self.config.additive_step_ms)
+            .max(self.config.min_interval_ms);
+    }
+    
+    /// 重传失败或收到拥塞通知：乘性降速
+    pub fn on_congestion(&mut self, current_time: u64) {
+        if let Some(last) = self.last_decrease {
+            if current_time.saturating_sub(last) < self.config.decrease_holdoff_ms {
+                return;
+            }
+        }
+        
+        self.interval_ms = self.interval_ms
+            .saturating_mul(2)
+            .min(self.config.max_interval_ms);
+        self.last_decrease = Some(current_time);
+    }
+}
\ No newline at end of file
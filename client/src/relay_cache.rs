@@ -0,0 +1,120 @@
+use common::hal::Hardware;
+use common::hal::nvs::{keys, NvStorage};
+use common::protocol::NodeId;
+use common::utils::AlignedBuffer;
+use common::info;
+use crate::rtt::measure_rtt;
+
+/// 记住的中继数
+pub const MAX_CACHED_RELAYS: usize = 4;
+
+/// 成功使用过的中继及当时观测到的链路质量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachedRelay {
+    /// 节点ID
+    pub node_id: NodeId,
+    /// 路径建立时测得的接收信号强度
+    pub rssi: i8,
+    /// 经该中继到服务器的跳数
+    pub hop_count: u8,
+}
+
+/// 最近成功使用的中继，按使用时间从新到旧排列
+///
+/// 唤醒或重启后先逐个探测缓存的中继，都不可达时才做完整的扫描发现。
+pub struct RelayCache {
+    relays: [Option<CachedRelay>; MAX_CACHED_RELAYS],
+}
+
+impl RelayCache {
+    /// 序列化后的长度：个数(1) [节点ID(6) 信号强度(1) 跳数(1)]*
+    pub const SIZE: usize = 1 + 8 * MAX_CACHED_RELAYS;
+    
+    /// 创建空缓存
+    pub fn new() -> Self {
+        Self {
+            relays: [None; MAX_CACHED_RELAYS],
+        }
+    }
+    
+    /// 记录一次成功使用的中继，移到最前；原来不在最前时返回true，需要保存
+    pub fn remember(&mut self, relay: CachedRelay) -> bool {
+        let moved = !matches!(self.relays[0], Some(first) if first.node_id == relay.node_id);
+        
+        // 从原位置（或最旧的位置）开始整体后移一位
+        let end = self.relays.iter()
+            .position(|entry| matches!(entry, Some(cached) if cached.node_id == relay.node_id))
+            .unwrap_or(MAX_CACHED_RELAYS - 1);
+        self.relays[..=end].rotate_right(1);
+        self.relays[0] = Some(relay);
+        
+        moved
+    }
+    
+    /// 遍历缓存的中继
+    pub fn iter(&self) -> impl Iterator<Item = &CachedRelay> {
+        self.relays.iter().flatten()
+    }
+    
+    /// 按使用顺序探测缓存的中继，返回第一个应答回显的
+    pub fn probe<H: Hardware>(&self, hardware: &mut H, rx_buffer: &mut AlignedBuffer<1024>) -> Option<CachedRelay> {
+        for relay in self.iter() {
+            if let Some(sample) = measure_rtt(hardware, relay.node_id, relay.node_id, rx_buffer) {
+                info!("缓存的中继 {} 可达，往返时延 {}ms", relay.node_id, sample.rtt_ms);
+                return Some(*relay);
+            }
+            info!("缓存的中继 {} 无应答", relay.node_id);
+        }
+        None
+    }
+    
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        let mut count = 0;
+        for relay in self.iter() {
+            let offset = 1 + count * 8;
+            bytes[offset..offset + 6].copy_from_slice(&relay.node_id.0);
+            bytes[offset + 6] = relay.rssi as u8;
+            bytes[offset + 7] = relay.hop_count;
+            count += 1;
+        }
+        bytes[0] = count as u8;
+        bytes
+    }
+    
+    /// 从字节解析
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let count = *bytes.first()? as usize;
+        if count > MAX_CACHED_RELAYS || bytes.len() < 1 + count * 8 {
+            return None;
+        }
+        
+        let mut cache = Self::new();
+        for (i, entry) in cache.relays.iter_mut().take(count).enumerate() {
+            let offset = 1 + i * 8;
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&bytes[offset..offset + 6]);
+            *entry = Some(CachedRelay {
+                node_id: NodeId(id),
+                rssi: bytes[offset + 6] as i8,
+                hop_count: bytes[offset + 7],
+            });
+        }
+        Some(cache)
+    }
+    
+    /// 从非易失存储加载，不存在或无法解析时返回空缓存
+    pub fn load<N: NvStorage>(nvs: &mut N) -> Self {
+        let mut buffer = [0u8; Self::SIZE];
+        match nvs.nvs_read(keys::RELAY_CACHE, &mut buffer) {
+            Ok(Some(len)) => Self::from_bytes(&buffer[..len]).unwrap_or_else(Self::new),
+            _ => Self::new(),
+        }
+    }
+    
+    /// 写入非易失存储
+    pub fn store<N: NvStorage>(&self, nvs: &mut N) -> Result<(), N::Error> {
+        nvs.nvs_write(keys::RELAY_CACHE, &self.to_bytes())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,171 @@
+use common::link_budget::LinkBudget;
+use common::protocol::{Beacon, NodeId, NodeRole};
+
+/// 当前中继的链路余量低于该值时视为变差（dB）
+pub const MIN_LINK_MARGIN_DB: i16 = 10;
+
+/// 漫游参数
+#[derive(Debug, Clone, Copy)]
+pub struct RoamingConfig {
+    /// 当前中继信号低于该值时视为变差（dBm）
+    pub rssi_threshold: i8,
+    /// 新中继信号至少要比当前中继强这么多才切换（dB）
+    pub hysteresis_db: i8,
+    /// 中继信标的广播间隔（毫秒）
+    pub beacon_interval_ms: u64,
+    /// 连续错过这么多个信标视为当前中继丢失
+    pub max_missed_beacons: u64,
+    /// 两次切换之间的最短间隔（毫秒）
+    pub min_handover_interval_ms: u64,
+}
+
+impl RoamingConfig {
+    /// 按链路预算设置变差门限，余量低于指定值时开始寻找更好的中继
+    pub fn with_min_margin(budget: &LinkBudget, min_margin_db: i16) -> Self {
+        Self {
+            rssi_threshold: budget.rssi_for_margin(min_margin_db),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for RoamingConfig {
+    fn default() -> Self {
+        Self {
+            rssi_threshold: -85,
+            hysteresis_db: 8,
+            beacon_interval_ms: 60000,
+            max_missed_beacons: 3,
+            min_handover_interval_ms: 120000,
+        }
+    }
+}
+
+/// 听到的中继信标状况
+#[derive(Debug, Clone, Copy)]
+struct RelayLink {
+    relay: NodeId,
+    /// 平滑后的接收信号强度
+    rssi: i8,
+    /// 最近一次听到信标的时间
+    last_heard: u64,
+}
+
+impl RelayLink {
+    fn observe(&mut self, link_rssi: i8, current_time: u64) {
+        self.rssi = ((self.rssi as i16 * 3 + link_rssi as i16) / 4) as i8;
+        self.last_heard = current_time;
+    }
+    
+    /// 备选中继在两个信标周期内听到过才有效
+    fn is_fresh(&self, beacon_interval_ms: u64, current_time: u64) -> bool {
+        current_time.saturating_sub(self.last_heard) <= beacon_interval_ms * 2
+    }
+}
+
+/// 监视当前中继的信号和信标丢失情况，在有更好的中继时给出切换目标
+pub struct RoamingMonitor {
+    config: RoamingConfig,
+    serving: RelayLink,
+    /// 当前听到的最佳备选中继
+    best_alternative: Option<RelayLink>,
+    /// 上次切换的时间
+    last_handover: u64,
+}
+
+impl RoamingMonitor {
+    pub fn new(config: RoamingConfig, relay: NodeId, current_time: u64) -> Self {
+        Self {
+            config,
+            serving: RelayLink {
+                relay,
+                rssi: 0,
+                last_heard: current_time,
+            },
+            best_alternative: None,
+            last_handover: current_time,
+        }
+    }
+    
+    /// 当前中继
+    pub fn serving(&self) -> NodeId {
+        self.serving.relay
+    }
+    
+    /// 切换完成后设置新的当前中继
+    pub fn set_serving(&mut self, relay: NodeId, current_time: u64) {
+        let rssi = match self.best_alternative {
+            Some(link) if link.relay == relay => link.rssi,
+            _ => 0,
+        };
+        
+        self.serving = RelayLink {
+            relay,
+            rssi,
+            last_heard: current_time,
+        };
+        self.best_alternative = None;
+        self.last_handover = current_time;
+    }
+    
+    /// 处理听到的信标
+    pub fn observe_beacon(&mut self, beacon: &Beacon, link_rssi: i8, current_time: u64) {
+        let source = NodeId(beacon.source);
+        
+        if source == self.serving.relay {
+            // 首次听到当前中继时直接采用测量值
+            if self.serving.rssi == 0 {
+                self.serving.rssi = link_rssi;
+            }
+            self.serving.observe(link_rssi, current_time);
+            return;
+        }
+        
+        if !matches!(beacon.role(), NodeRole::Forward | NodeRole::Unknown) {
+            return;
+        }
+        
+        let beacon_interval_ms = self.config.beacon_interval_ms;
+        match &mut self.best_alternative {
+            Some(link) if link.relay == source => link.observe(link_rssi, current_time),
+            Some(link) if !link.is_fresh(beacon_interval_ms, current_time) || link_rssi > link.rssi => {
+                *link = RelayLink { relay: source, rssi: link_rssi, last_heard: current_time };
+            },
+            Some(_) => {},
+            None => {
+                self.best_alternative = Some(RelayLink { relay: source, rssi: link_rssi, last_heard: current_time });
+            },
+        }
+    }
+    
+    /// 当前中继是否已经变差：信号低于阈值或连续错过信标
+    pub fn serving_degraded(&self, current_time: u64) -> bool {
+        self.serving_lost(current_time) || (self.serving.rssi != 0 && self.serving.rssi < self.config.rssi_threshold)
+    }
+    
+    /// 如果当前中继变差且听到明显更好的中继，返回切换目标
+    pub fn handover_target(&self, current_time: u64) -> Option<NodeId> {
+        if current_time.saturating_sub(self.last_handover) < self.config.min_handover_interval_ms {
+            return None;
+        }
+        if !self.serving_degraded(current_time) {
+            return None;
+        }
+        
+        let candidate = self.best_alternative
+            .filter(|link| link.is_fresh(self.config.beacon_interval_ms, current_time))?;
+        
+        // 当前中继已丢失时只要有备选就切换，否则要求信号明显更强，避免来回切换
+        if self.serving_lost(current_time)
+            || candidate.rssi as i16 >= self.serving.rssi as i16 + self.config.hysteresis_db as i16 {
+            Some(candidate.relay)
+        } else {
+            None
+        }
+    }
+    
+    fn serving_lost(&self, current_time: u64) -> bool {
+        current_time.saturating_sub(self.serving.last_heard)
+            > self.config.beacon_interval_ms * self.config.max_missed_beacons
+    }
+}
\ No newline at end of file
@@ -0,0 +1,96 @@
+use common::hal::Hardware;
+use common::protocol::{NodeId, PacketType};
+use common::protocol::echo::{answer_echo, send_echo, Echo, Trace};
+use common::security::receive_secure;
+use common::utils::AlignedBuffer;
+use common::warn;
+
+/// 等待回显应答的最长时间（毫秒）
+const ECHO_TIMEOUT_MS: u64 = 2000;
+
+/// 等待期间两次接收之间的间隔（毫秒）
+const ECHO_POLL_MS: u32 = 20;
+
+/// 一次往返时延测量的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RttSample {
+    /// 往返时延（毫秒）
+    pub rtt_ms: u32,
+    /// 请求到达目标经过的跳数
+    pub hop_count: u8,
+    /// 沿途各节点及其收到请求时的信号强度，只有路径追踪时才有
+    pub trace: Option<Trace>,
+}
+
+/// 经由中继向目标发送回显请求并等待应答，测量端到端往返时延和跳数
+///
+/// 目标就是中继本身时直接发送。超时未收到应答时返回None。
+pub fn measure_rtt<H: Hardware>(
+    hardware: &mut H,
+    relay: NodeId,
+    destination: NodeId,
+    rx_buffer: &mut AlignedBuffer<1024>
+) -> Option<RttSample> {
+    round_trip(hardware, relay, destination, None, rx_buffer)
+}
+
+/// 与`measure_rtt`相同，但沿途每个节点都记录自己的ID和信号强度，用于排查路径问题
+pub fn trace_route<H: Hardware>(
+    hardware: &mut H,
+    relay: NodeId,
+    destination: NodeId,
+    rx_buffer: &mut AlignedBuffer<1024>
+) -> Option<RttSample> {
+    round_trip(hardware, relay, destination, Some(Trace::new()), rx_buffer)
+}
+
+/// 发送回显请求并等待对应的应答
+fn round_trip<H: Hardware>(
+    hardware: &mut H,
+    relay: NodeId,
+    destination: NodeId,
+    trace: Option<Trace>,
+    rx_buffer: &mut AlignedBuffer<1024>
+) -> Option<RttSample> {
+    let sent_at = hardware.get_timestamp_ms().unwrap_or(0);
+    let request = Echo {
+        origin: hardware.get_node_id(),
+        target: destination,
+        sent_at,
+        hop_count: 0,
+        trace,
+    };
+    
+    if let Err(e) = send_echo(hardware, relay, PacketType::EchoRequest, 0, &request) {
+        warn!("发送回显请求失败: {:?}", e);
+        return None;
+    }
+    
+    loop {
+        let buffer = rx_buffer.as_mut_slice();
+        if let Some(packet) = receive_secure(hardware, buffer) {
+            if packet.header.packet_type == PacketType::EchoReply as u8 {
+                match Echo::deserialize(packet.data) {
+                    Some(reply) if reply.origin == request.origin && reply.target == destination && reply.sent_at == sent_at => {
+                        let now = hardware.get_timestamp_ms().unwrap_or(0);
+                        return Some(RttSample {
+                            rtt_ms: now.saturating_sub(sent_at) as u32,
+                            hop_count: reply.hop_count,
+                            trace: reply.trace,
+                        });
+                    },
+                    _ => {},
+                }
+            } else {
+                // 等待期间也应答别人的测量
+                answer_echo(hardware, &packet);
+            }
+        }
+        
+        let now = hardware.get_timestamp_ms().unwrap_or(0);
+        if now.saturating_sub(sent_at) >= ECHO_TIMEOUT_MS {
+            return None;
+        }
+        let _ = hardware.delay_ms(ECHO_POLL_MS);
+    }
+}
\ No newline at end of file
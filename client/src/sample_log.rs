@@ -0,0 +1,235 @@
+use common::hal::nvs::{keys, NvStorage};
+use common::protocol::command::LoggedSample;
+use crate::sensor_driver::SensorData;
+
+/// 日志占用的页数
+pub const LOG_PAGES: u16 = 128;
+
+/// 每页的样本数
+pub const PAGE_SAMPLES: usize = 12;
+
+/// 页记录长度：首个样本序号(4) 样本数(1) 样本*
+const PAGE_RECORD_LEN: usize = 5 + PAGE_SAMPLES * LoggedSample::SIZE;
+
+/// 页索引记录长度：最早页的槽位(2) 页数(2)
+const META_LEN: usize = 4;
+
+/// 日志中的一页样本
+#[derive(Clone, Copy)]
+struct LogPage {
+    first_seq: u32,
+    samples: [LoggedSample; PAGE_SAMPLES],
+    len: usize,
+}
+
+impl LogPage {
+    fn empty(first_seq: u32) -> Self {
+        Self {
+            first_seq,
+            samples: [LoggedSample { timestamp: 0, temperature: 0.0, humidity: 0.0, pressure: 0.0 }; PAGE_SAMPLES],
+            len: 0,
+        }
+    }
+    
+    /// 页中是否包含指定序号的样本
+    fn contains(&self, seq: u32) -> bool {
+        seq >= self.first_seq && seq - self.first_seq < self.len as u32
+    }
+    
+    /// 编码页记录，返回写入长度
+    fn encode(&self, buffer: &mut [u8; PAGE_RECORD_LEN]) -> usize {
+        buffer[0..4].copy_from_slice(&self.first_seq.to_be_bytes());
+        buffer[4] = self.len as u8;
+        
+        let mut offset = 5;
+        for sample in &self.samples[..self.len] {
+            buffer[offset..offset + LoggedSample::SIZE].copy_from_slice(&sample.to_bytes());
+            offset += LoggedSample::SIZE;
+        }
+        
+        offset
+    }
+    
+    /// 解析页记录
+    fn decode(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < 5 {
+            return None;
+        }
+        let len = buffer[4] as usize;
+        if len > PAGE_SAMPLES || buffer.len() < 5 + len * LoggedSample::SIZE {
+            return None;
+        }
+        
+        let mut page = Self::empty(u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]));
+        for (i, chunk) in buffer[5..5 + len * LoggedSample::SIZE].chunks_exact(LoggedSample::SIZE).enumerate() {
+            page.samples[i] = LoggedSample::from_bytes(chunk)?;
+        }
+        page.len = len;
+        
+        Some(page)
+    }
+}
+
+/// 闪存中的本地样本日志
+///
+/// 与网络状态无关，每个样本都追加到日志，日志满后覆盖最早的一页。
+/// 样本按追加顺序编号，现场可通过读取日志命令从任意序号开始取回。
+/// 当前页在每次追加时整页重写，页索引只在换页时更新。
+pub struct SampleLog {
+    /// 最早一页所在的槽位
+    head: u16,
+    /// 已使用的页数，包括当前页
+    pages: u16,
+    /// 当前页，即最后一页
+    current: LogPage,
+}
+
+impl SampleLog {
+    /// 从非易失存储加载，末尾无法读取的页视为丢失
+    pub fn load<N: NvStorage>(nvs: &mut N) -> Self {
+        let mut log = Self {
+            head: 0,
+            pages: 0,
+            current: LogPage::empty(0),
+        };
+        
+        let mut meta = [0u8; META_LEN];
+        if let Ok(Some(META_LEN)) = nvs.nvs_read(keys::SAMPLE_LOG_META, &mut meta) {
+            let head = u16::from_be_bytes([meta[0], meta[1]]);
+            let pages = u16::from_be_bytes([meta[2], meta[3]]);
+            if head < LOG_PAGES && pages <= LOG_PAGES {
+                log.head = head;
+                log.pages = pages;
+            }
+        }
+        
+        while log.pages > 0 {
+            match load_page(nvs, log.slot(log.pages - 1)) {
+                Some(page) => {
+                    log.current = page;
+                    break;
+                },
+                None => log.pages -= 1,
+            }
+        }
+        
+        log
+    }
+    
+    /// 最早一个样本的序号
+    pub fn oldest_seq(&self) -> u32 {
+        // 除当前页外的页都是满的
+        let full_pages = self.pages.saturating_sub(1) as u32;
+        self.current.first_seq.saturating_sub(full_pages * PAGE_SAMPLES as u32)
+    }
+    
+    /// 下一个样本的序号
+    pub fn next_seq(&self) -> u32 {
+        self.current.first_seq + self.current.len as u32
+    }
+    
+    /// 日志中的样本数
+    pub fn sample_count(&self) -> usize {
+        (self.next_seq() - self.oldest_seq()) as usize
+    }
+    
+    /// 追加一个样本
+    ///
+    /// 写入失败时样本仍保留在当前页中，下次追加时随整页重写。
+    pub fn append<N: NvStorage>(&mut self, nvs: &mut N, timestamp: u64, data: &SensorData) -> Result<(), N::Error> {
+        let mut meta_result = Ok(());
+        if self.pages == 0 || self.current.len == PAGE_SAMPLES {
+            self.start_page();
+            meta_result = self.store_meta(nvs);
+        }
+        
+        self.current.samples[self.current.len] = LoggedSample {
+            timestamp,
+            temperature: data.temperature,
+            humidity: data.humidity,
+            pressure: data.pressure,
+        };
+        self.current.len += 1;
+        
+        let mut record = [0u8; PAGE_RECORD_LEN];
+        let len = self.current.encode(&mut record);
+        let page_result = nvs.nvs_write(keys::SAMPLE_LOG_BASE + self.slot(self.pages - 1), &record[..len]);
+        
+        meta_result.and(page_result)
+    }
+    
+    /// 从指定序号开始读取样本，返回实际的首个序号和读取的样本数
+    ///
+    /// 早于日志中最早样本的序号从最早样本开始；无法读取的页被跳过。
+    pub fn read<N: NvStorage>(&self, nvs: &mut N, start_seq: u32, out: &mut [LoggedSample]) -> (u32, usize) {
+        let oldest = self.oldest_seq();
+        let end = self.next_seq();
+        let mut seq = start_seq.clamp(oldest, end);
+        let mut first = seq;
+        let mut count = 0;
+        
+        while seq < end && count < out.len() {
+            let index = ((seq - oldest) as usize / PAGE_SAMPLES) as u16;
+            let page = if index + 1 == self.pages {
+                Some(self.current)
+            } else {
+                load_page(nvs, self.slot(index))
+            };
+            
+            match page {
+                Some(page) if page.contains(seq) => {
+                    let offset = (seq - page.first_seq) as usize;
+                    let n = (page.len - offset).min(out.len() - count);
+                    out[count..count + n].copy_from_slice(&page.samples[offset..offset + n]);
+                    seq += n as u32;
+                    count += n;
+                },
+                _ => {
+                    // 已取到的样本必须连续，遇到损坏的页就先返回
+                    if count > 0 {
+                        break;
+                    }
+                    seq = (oldest + (index as u32 + 1) * PAGE_SAMPLES as u32).min(end);
+                    first = seq;
+                },
+            }
+        }
+        
+        (first, count)
+    }
+    
+    /// 第index页（从最早一页算起）所在的槽位
+    fn slot(&self, index: u16) -> u16 {
+        (self.head + index) % LOG_PAGES
+    }
+    
+    /// 开始新的一页，日志已满时覆盖最早的一页
+    fn start_page(&mut self) {
+        if self.pages > 0 {
+            let next_seq = self.next_seq();
+            if self.pages == LOG_PAGES {
+                self.head = (self.head + 1) % LOG_PAGES;
+                self.pages -= 1;
+            }
+            self.current = LogPage::empty(next_seq);
+        }
+        self.pages += 1;
+    }
+    
+    /// 保存页索引
+    fn store_meta<N: NvStorage>(&self, nvs: &mut N) -> Result<(), N::Error> {
+        let mut meta = [0u8; META_LEN];
+        meta[0..2].copy_from_slice(&self.head.to_be_bytes());
+        meta[2..4].copy_from_slice(&self.pages.to_be_bytes());
+        nvs.nvs_write(keys::SAMPLE_LOG_META, &meta)
+    }
+}
+
+/// 读取指定槽位的页
+fn load_page<N: NvStorage>(nvs: &mut N, slot: u16) -> Option<LogPage> {
+    let mut record = [0u8; PAGE_RECORD_LEN];
+    match nvs.nvs_read(keys::SAMPLE_LOG_BASE + slot, &mut record) {
+        Ok(Some(len)) => LogPage::decode(&record[..len]),
+        _ => None,
+    }
+}
\ No newline at end of file
@@ -1,65 +0,0 @@
-/// 传感器数据结构
-#[derive(Debug, Clone, Copy)]
-pub struct SensorData {
-    /// 温度 (°C)
-    pub temperature: f32,
-    /// 湿度 (%)
-    pub humidity: f32,
-    /// 气压 (Pa)
-    pub pressure: f32,
-}
-
-/// 读取所有传感器数据
-pub fn read_sensors() -> SensorData {
-    #[cfg(feature = "bearpi")]
-    {
-        // 实际硬件上读取传感器
-        // 这里是模拟实现
-        SensorData {
-            temperature: 25.5,
-            humidity: 65.0,
-            pressure: 101325.0,
-        }
-    }
-    
-    #[cfg(feature = "simulator")]
-    {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        
-        // 模拟动态变化的传感器数据
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        
-        // 温度在20-30°C之间波动
-        let temp_variation = ((now % 100) as f32) / 10.0;
-        let temperature = 20.0 + temp_variation;
-        
-        // 湿度在50-80%之间波动
-        let humidity_variation = ((now % 60) as f32) / 2.0;
-        let humidity = 50.0 + humidity_variation;
-        
-        // 气压在101000-102000Pa之间波动
-        let pressure_variation = ((now % 1000) as f32);
-        let pressure = 101000.0 + pressure_variation;
-        
-        SensorData {
-            temperature,
-            humidity,
-            pressure,
-        }
-    }
-}
-
-/// 初始化传感器
-pub fn init_sensors() -> Result<(), ()> {
-    // 在实际硬件上初始化传感器
-    Ok(())
-}
-
-/// 关闭传感器，进入低功耗模式
-pub fn shutdown_sensors() -> Result<(), ()> {
-    // 在实际硬件上关闭传感器
-    Ok(())
-} 
\ No newline at end of file
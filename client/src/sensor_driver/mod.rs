@@ -0,0 +1,126 @@
+use common::hal::Hardware;
+use common::warn;
+
+pub mod sht3x;
+
+/// 单个传感器最多提供的通道数
+pub const MAX_CHANNELS: usize = 4;
+
+/// 传感器数据结构
+#[derive(Debug, Clone, Copy)]
+pub struct SensorData {
+    /// 温度 (°C)
+    pub temperature: f32,
+    /// 湿度 (%)
+    pub humidity: f32,
+    /// 气压 (Pa)
+    pub pressure: f32,
+}
+
+/// 传感器测量通道
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// 温度 (°C)
+    Temperature,
+    /// 相对湿度 (%)
+    Humidity,
+    /// 气压 (Pa)
+    Pressure,
+}
+
+/// 单个通道的读数
+#[derive(Debug, Clone, Copy)]
+pub struct Reading {
+    pub channel: Channel,
+    pub value: f32,
+}
+
+/// 传感器错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+pub enum SensorError {
+    /// 总线通信失败或设备无应答
+    Bus,
+    /// 数据校验失败
+    Crc,
+    /// 传感器尚未初始化
+    NotInitialized,
+}
+
+/// 传感器驱动接口，通过硬件抽象层访问总线
+pub trait Sensor<H: Hardware> {
+    /// 传感器名称
+    fn name(&self) -> &'static str;
+    
+    /// 该传感器提供的测量通道
+    fn channels(&self) -> &'static [Channel];
+    
+    /// 初始化传感器
+    fn init(&mut self, hardware: &mut H) -> Result<(), SensorError>;
+    
+    /// 采样一次，读数写入out，返回写入的读数个数
+    fn sample(&mut self, hardware: &mut H, out: &mut [Reading]) -> Result<usize, SensorError>;
+    
+    /// 关闭传感器，进入低功耗模式
+    fn power_down(&mut self, hardware: &mut H) -> Result<(), SensorError>;
+}
+
+impl SensorData {
+    /// 所有通道都没有读数时的值
+    pub const fn empty() -> Self {
+        Self {
+            temperature: f32::NAN,
+            humidity: f32::NAN,
+            pressure: f32::NAN,
+        }
+    }
+    
+    /// 写入一个通道的读数
+    pub fn apply(&mut self, reading: &Reading) {
+        match reading.channel {
+            Channel::Temperature => self.temperature = reading.value,
+            Channel::Humidity => self.humidity = reading.value,
+            Channel::Pressure => self.pressure = reading.value,
+        }
+    }
+}
+
+/// 初始化所有已注册的传感器，返回初始化成功的个数
+pub fn init_all<H: Hardware>(hardware: &mut H, sensors: &mut [&mut dyn Sensor<H>]) -> usize {
+    let mut ready = 0;
+    
+    for sensor in sensors.iter_mut() {
+        match sensor.init(hardware) {
+            Ok(()) => ready += 1,
+            Err(e) => warn!("传感器 {} 初始化失败: {:?}", sensor.name(), e),
+        }
+    }
+    
+    ready
+}
+
+/// 依次采样所有已注册的传感器并合并读数，采样失败的通道保持为NaN
+pub fn read_all<H: Hardware>(hardware: &mut H, sensors: &mut [&mut dyn Sensor<H>]) -> SensorData {
+    let mut data = SensorData::empty();
+    let mut readings = [Reading { channel: Channel::Temperature, value: 0.0 }; MAX_CHANNELS];
+    
+    for sensor in sensors.iter_mut() {
+        match sensor.sample(hardware, &mut readings) {
+            Ok(count) => {
+                for reading in readings[..count].iter() {
+                    data.apply(reading);
+                }
+            },
+            Err(e) => warn!("传感器 {} 采样失败: {:?}", sensor.name(), e),
+        }
+    }
+    
+    data
+}
+
+/// 关闭所有已注册的传感器
+pub fn power_down_all<H: Hardware>(hardware: &mut H, sensors: &mut [&mut dyn Sensor<H>]) {
+    for sensor in sensors.iter_mut() {
+        let _ = sensor.power_down(hardware);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,96 @@
+use embedded_hal::blocking::i2c::{Read, Write};
+use common::hal::Hardware;
+use common::utils::checksum::calculate_crc8;
+use super::{Channel, Reading, Sensor, SensorError};
+
+/// ADDR引脚接地时的默认地址
+pub const SHT3X_DEFAULT_ADDRESS: u8 = 0x44;
+
+/// 单次测量，高重复性，不使用时钟拉伸
+const CMD_MEASURE_HIGH: [u8; 2] = [0x24, 0x00];
+/// 软复位
+const CMD_SOFT_RESET: [u8; 2] = [0x30, 0xA2];
+/// 高重复性测量所需时间（毫秒）
+const MEASURE_TIME_MS: u32 = 16;
+/// 软复位后的等待时间（毫秒）
+const RESET_TIME_MS: u32 = 2;
+
+const CHANNELS: [Channel; 2] = [Channel::Temperature, Channel::Humidity];
+
+/// Sensirion SHT3x温湿度传感器驱动
+pub struct Sht3x {
+    address: u8,
+    initialized: bool,
+}
+
+impl Sht3x {
+    pub fn new(address: u8) -> Self {
+        Self {
+            address,
+            initialized: false,
+        }
+    }
+    
+    /// 发送两字节命令
+    fn command<H: Hardware>(&self, hardware: &mut H, command: &[u8; 2]) -> Result<(), SensorError> {
+        hardware.get_i2c().write(self.address, command).map_err(|_| SensorError::Bus)
+    }
+}
+
+/// 校验一个16位字及其CRC
+fn checked_word(bytes: &[u8]) -> Result<u16, SensorError> {
+    if calculate_crc8(&bytes[0..2]) != bytes[2] {
+        return Err(SensorError::Crc);
+    }
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+impl<H: Hardware> Sensor<H> for Sht3x {
+    fn name(&self) -> &'static str {
+        "SHT3x"
+    }
+    
+    fn channels(&self) -> &'static [Channel] {
+        &CHANNELS
+    }
+    
+    fn init(&mut self, hardware: &mut H) -> Result<(), SensorError> {
+        self.command(hardware, &CMD_SOFT_RESET)?;
+        hardware.delay_ms(RESET_TIME_MS).map_err(|_| SensorError::Bus)?;
+        self.initialized = true;
+        Ok(())
+    }
+    
+    fn sample(&mut self, hardware: &mut H, out: &mut [Reading]) -> Result<usize, SensorError> {
+        if !self.initialized {
+            return Err(SensorError::NotInitialized);
+        }
+        
+        self.command(hardware, &CMD_MEASURE_HIGH)?;
+        hardware.delay_ms(MEASURE_TIME_MS).map_err(|_| SensorError::Bus)?;
+        
+        // 温度(2) CRC(1) 湿度(2) CRC(1)
+        let mut raw = [0u8; 6];
+        hardware.get_i2c().read(self.address, &mut raw).map_err(|_| SensorError::Bus)?;
+        
+        let raw_temp = checked_word(&raw[0..3])?;
+        let raw_hum = checked_word(&raw[3..6])?;
+        
+        let temperature = -45.0 + 175.0 * (raw_temp as f32) / 65535.0;
+        let humidity = 100.0 * (raw_hum as f32) / 65535.0;
+        
+        let readings = [
+            Reading { channel: Channel::Temperature, value: temperature },
+            Reading { channel: Channel::Humidity, value: humidity },
+        ];
+        let count = readings.len().min(out.len());
+        out[..count].copy_from_slice(&readings[..count]);
+        
+        Ok(count)
+    }
+    
+    fn power_down(&mut self, _hardware: &mut H) -> Result<(), SensorError> {
+        // 单次测量模式下传感器测量完成后自动进入空闲状态，无需额外命令
+        Ok(())
+    }
+}
\ No newline at end of file
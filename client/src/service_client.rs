@@ -1,7 +1,16 @@
+use core::sync::atomic::{AtomicU16, Ordering};
 use common::protocol::{NodeId, DataPacket, ServiceType, QosRequirements, PacketType};
 use common::protocol::{ServiceRequest, ServiceResponse, serialize_service_request, deserialize_service_response};
+use common::protocol::{ServiceRenewal, serialize_service_renewal};
+use common::protocol::{ServiceHandover, serialize_service_handover};
+use common::protocol::{ServiceClose, serialize_service_close};
+use common::protocol::data::flow_id_of;
+use common::protocol::keepalive::{send_keepalive, PathKeepAlive};
+use common::protocol::reliable::{DeliveryEvent, ReliableSender};
 use common::hal::Hardware;
+use common::security::{receive_secure, send_secure};
 use common::utils::AlignedBuffer;
+use common::{info, warn};
 
 /// 服务端点，表示可以连接的远程服务
 #[derive(Debug, Clone, Copy)]
@@ -16,11 +25,158 @@ pub struct ServiceEndpoint {
     pub service_type: ServiceType,
     /// 跳数
     pub hops: u8,
+    /// 租期（秒）
+    pub lease_secs: u32,
+    /// 租约到期时间
+    pub lease_expires_at: u64,
+    /// 上次发送续期请求的时间
+    pub last_renew_attempt: u64,
+}
+
+/// 在租约到期前多久开始续期（租期的比例，百分比）
+const RENEW_AT_PERCENT: u64 = 70;
+
+/// 两次续期请求之间的最小间隔（毫秒）
+const RENEW_RETRY_INTERVAL_MS: u64 = 3000;
+
+impl ServiceEndpoint {
+    /// 是否需要发送续期请求
+    pub fn needs_renewal(&self, current_time: u64) -> bool {
+        current_time >= self.renewal_due_at()
+            && current_time.saturating_sub(self.last_renew_attempt) >= RENEW_RETRY_INTERVAL_MS
+    }
+    
+    /// 应开始续期的时间
+    pub fn renewal_due_at(&self) -> u64 {
+        let lease_ms = self.lease_secs as u64 * 1000;
+        self.lease_expires_at.saturating_sub(lease_ms * (100 - RENEW_AT_PERCENT) / 100)
+    }
+    
+    /// 租约是否已到期
+    pub fn is_expired(&self, current_time: u64) -> bool {
+        current_time >= self.lease_expires_at
+    }
+    
+    /// 续期成功后延长租约
+    pub fn extend_lease(&mut self, current_time: u64) {
+        self.lease_expires_at = current_time + self.lease_secs as u64 * 1000;
+    }
+}
+
+/// 路径保活间隔（毫秒）
+pub const KEEPALIVE_INTERVAL_MS: u64 = 10000;
+
+/// 连续多少次保活未被确认视为路径失效
+pub const MAX_MISSED_KEEPALIVES: u8 = 3;
+
+/// 已建立路径的保活状态
+///
+/// 中继失效后客户端收不到任何错误，只能靠保活确认判断路径是否仍然可用。
+#[derive(Debug, Clone, Copy)]
+pub struct KeepAlive {
+    /// 最近一次保活的序号
+    sequence: u16,
+    /// 最近一次发送保活的时间
+    last_sent: u64,
+    /// 最近一次保活是否仍未确认
+    outstanding: bool,
+    /// 连续未确认的次数
+    missed: u8,
+}
+
+impl KeepAlive {
+    /// 创建保活状态，路径建立后一个间隔再发送第一次保活
+    pub fn new(current_time: u64) -> Self {
+        Self {
+            sequence: 0,
+            last_sent: current_time,
+            outstanding: false,
+            missed: 0,
+        }
+    }
+    
+    /// 下一次发送保活的时间
+    pub fn due_at(&self) -> u64 {
+        self.last_sent + KEEPALIVE_INTERVAL_MS
+    }
+    
+    /// 到期时向中继发送保活；连续多次未确认时通过回调报告路径失效
+    pub fn poll<H: Hardware, F: FnOnce(&ServiceEndpoint)>(
+        &mut self,
+        hardware: &mut H,
+        endpoint: &ServiceEndpoint,
+        current_time: u64,
+        on_failure: F
+    ) {
+        if current_time.saturating_sub(self.last_sent) < KEEPALIVE_INTERVAL_MS {
+            return;
+        }
+        
+        if self.outstanding {
+            self.missed += 1;
+            if self.missed >= MAX_MISSED_KEEPALIVES {
+                *self = Self::new(current_time);
+                on_failure(endpoint);
+                return;
+            }
+        }
+        
+        self.sequence = self.sequence.wrapping_add(1);
+        self.last_sent = current_time;
+        self.outstanding = true;
+        
+        let keepalive = PathKeepAlive {
+            service_id: endpoint.service_id,
+            sequence: self.sequence,
+        };
+        if let Err(e) = send_keepalive(hardware, endpoint.relay_id, PacketType::PathKeepAlive, &keepalive) {
+            warn!("发送路径保活失败: {:?}", e);
+        }
+    }
+    
+    /// 处理中继的保活确认，只接受最近一次保活的确认
+    pub fn handle_ack(&mut self, packet: &DataPacket) -> bool {
+        match PathKeepAlive::deserialize(packet.data) {
+            Some(ack) if ack.sequence == self.sequence => {
+                self.outstanding = false;
+                self.missed = 0;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+/// 下一个服务请求ID
+static NEXT_REQUEST_ID: AtomicU16 = AtomicU16::new(1);
+
+/// 建立会话之前的请求在可靠发送端中使用的会话ID，服务ID不会为0
+const CONTROL_SESSION: u32 = 0;
+
+/// 等待服务响应时检查接收和重传的间隔（毫秒）
+const REQUEST_POLL_INTERVAL_MS: u32 = 100;
+
+/// 分配服务请求ID（跳过0，0表示不关联请求）
+fn next_request_id() -> u16 {
+    loop {
+        let id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        if id != 0 {
+            return id;
+        }
+    }
+}
+
+/// 响应是否对应指定请求；v1中继的响应没有请求ID，解析为0时不检查
+fn answers(response: &ServiceResponse, request_id: u16) -> bool {
+    response.request_id == 0 || response.request_id == request_id
 }
 
 /// 请求服务，与转发节点通信，获取合适的服务端点
+///
+/// 请求经可靠发送端发出，转发节点的服务响应沿用请求的包ID，收到响应前按退避重传，重传次数用完时放弃。
 pub fn request_service<H: Hardware>(
     hardware: &mut H,
+    uplink: &mut ReliableSender,
     forward_id: NodeId,
     service_type: ServiceType,
     qos: &QosRequirements,
@@ -28,10 +184,12 @@ pub fn request_service<H: Hardware>(
     tx_buffer: &mut AlignedBuffer<256>,
     rx_buffer: &mut AlignedBuffer<1024>
 ) -> Option<ServiceEndpoint> {
-    println!("请求服务：类型={:?}, 转发节点={:?}", service_type, forward_id);
+    info!("请求服务：类型={:?}, 转发节点={:?}", service_type, forward_id);
     
     // 创建服务请求
+    let request_id = next_request_id();
     let service_request = ServiceRequest {
+        request_id,
         service_type,
         qos: *qos,
         expiry_time,
@@ -42,44 +200,37 @@ pub fn request_service<H: Hardware>(
     let request_len = serialize_service_request(&service_request, tx_data);
     
     if request_len == 0 {
-        println!("序列化服务请求失败");
-        return None;
-    }
-    
-    // 创建请求数据包
-    let node_id = hardware.get_node_id();
-    let request_packet = DataPacket::new(
-        node_id,
-        forward_id,
-        0, // 包ID
-        &tx_data[..request_len]
-    );
-    
-    // 发送请求
-    let radio = hardware.get_radio();
-    if let Err(e) = radio.send_data(&request_packet) {
-        println!("发送服务请求失败: {:?}", e);
+        warn!("序列化服务请求失败");
         return None;
     }
     
-    println!("已发送服务请求，等待响应...");
+    // 发送请求，还没有会话，按控制会话跟踪
+    let request_time = hardware.get_timestamp_ms().unwrap_or(0);
+    let packet_id = match uplink.send_typed(hardware, CONTROL_SESSION, forward_id, PacketType::ServiceRequest,
+                                            &tx_data[..request_len], request_time) {
+        Ok(packet_id) => packet_id,
+        Err(e) => {
+            warn!("发送服务请求失败: {:?}", e);
+            return None;
+        },
+    };
     
-    // 等待响应（最多等待10秒）
-    let mut retry_count = 0;
-    const MAX_RETRIES: u8 = 10;
+    info!("已发送服务请求，等待响应...");
     
-    while retry_count < MAX_RETRIES {
+    let mut failed = false;
+    while !failed {
         // 尝试接收数据
         let buffer = rx_buffer.as_mut_slice();
-        if let Ok(Some(packet)) = radio.receive_data(buffer) {
+        if let Some(packet) = receive_secure(hardware, buffer) {
             let source = NodeId(packet.header.source);
             
             // 检查是否是来自转发节点的响应
-            if source == forward_id && packet.header.packet_type == PacketType::ServiceResponse {
-                // 尝试解析服务响应
-                if let Some(response) = deserialize_service_response(packet.data) {
+            if source == forward_id && packet.header.packet_type == PacketType::ServiceResponse as u8 {
+                // 尝试解析服务响应，忽略之前请求的迟到响应
+                if let Some(response) = deserialize_service_response(packet.data).filter(|response| answers(response, request_id)) {
+                    uplink.complete(CONTROL_SESSION, packet_id);
                     if response.status == 0 { // 成功
-                        println!("收到成功的服务响应: 服务器={:?}, 服务ID={}", 
+                        info!("收到成功的服务响应: 服务器={}, 服务ID={}", 
                                  response.server_node_id, response.service_id);
                         
                         // 创建服务端点
@@ -89,24 +240,163 @@ pub fn request_service<H: Hardware>(
                             relay_id: forward_id,
                             service_type,
                             hops: 0, // 初始值，将在路径确认中更新
+                            lease_secs: expiry_time,
+                            lease_expires_at: request_time + expiry_time as u64 * 1000,
+                            last_renew_attempt: request_time,
                         });
                     } else {
-                        println!("服务响应表示失败，状态: {}", response.status);
+                        warn!("服务响应表示失败，状态: {}", response.status);
                         return None;
                     }
                 }
             }
         }
         
-        // 等待1秒后重试
-        let _ = hardware.delay_ms(1000);
-        retry_count += 1;
+        // 超时重传请求，重传次数用完时放弃；其他会话的帧留给主循环
+        let now = hardware.get_timestamp_ms().unwrap_or(0);
+        uplink.poll_session(hardware, CONTROL_SESSION, now, |event| {
+            failed |= matches!(event, DeliveryEvent::Failed { packet_id: id, .. } if id == packet_id);
+        });
+        let _ = hardware.delay_ms(REQUEST_POLL_INTERVAL_MS);
     }
     
-    println!("等待服务响应超时");
+    warn!("等待服务响应超时");
     None
 }
 
+/// 将服务切换到新的中继，保留原服务ID和服务器，成功后更新端点
+pub fn handover_service<H: Hardware>(
+    hardware: &mut H,
+    new_relay: NodeId,
+    endpoint: &mut ServiceEndpoint,
+    qos: &QosRequirements,
+    tx_buffer: &mut AlignedBuffer<256>,
+    rx_buffer: &mut AlignedBuffer<1024>
+) -> bool {
+    info!("将服务 {} 从 {} 切换到 {}", endpoint.service_id, endpoint.relay_id, new_relay);
+    
+    let request_id = next_request_id();
+    let handover = ServiceHandover {
+        service_id: endpoint.service_id,
+        server_node_id: endpoint.server_id,
+        request: ServiceRequest {
+            request_id,
+            service_type: endpoint.service_type,
+            qos: *qos,
+            expiry_time: endpoint.lease_secs,
+        },
+    };
+    
+    let tx_data = tx_buffer.as_mut_slice();
+    let handover_len = serialize_service_handover(&handover, tx_data);
+    if handover_len == 0 {
+        return false;
+    }
+    
+    let node_id = hardware.get_node_id();
+    let handover_packet = DataPacket::with_type(
+        node_id,
+        new_relay,
+        PacketType::ServiceHandover,
+        0, // 包ID
+        &tx_data[..handover_len]
+    ).with_flow(flow_id_of(endpoint.service_id));
+    
+    if let Err(e) = send_secure(hardware, &handover_packet) {
+        warn!("发送服务切换请求失败: {:?}", e);
+        return false;
+    }
+    
+    // 等待新中继的响应（最多等待3秒）
+    for _ in 0..3 {
+        let buffer = rx_buffer.as_mut_slice();
+        if let Some(packet) = receive_secure(hardware, buffer) {
+            if NodeId(packet.header.source) == new_relay
+                && packet.header.packet_type == PacketType::ServiceResponse as u8 {
+                match deserialize_service_response(packet.data).filter(|response| answers(response, request_id)) {
+                    Some(response) if response.service_id == endpoint.service_id && response.status == 0 => {
+                        let now = hardware.get_timestamp_ms().unwrap_or(0);
+                        endpoint.relay_id = new_relay;
+                        endpoint.extend_lease(now);
+                        endpoint.last_renew_attempt = now;
+                        info!("服务 {} 已切换到 {}", endpoint.service_id, new_relay);
+                        return true;
+                    },
+                    Some(_) => {
+                        warn!("新中继拒绝了服务 {} 的切换", endpoint.service_id);
+                        return false;
+                    },
+                    None => {},
+                }
+            }
+        }
+        
+        let _ = hardware.delay_ms(1000);
+    }
+    
+    warn!("等待服务切换响应超时");
+    false
+}
+
+/// 发送租约续期请求，响应由主循环处理
+pub fn renew_service<H: Hardware>(
+    hardware: &mut H,
+    endpoint: &mut ServiceEndpoint,
+    tx_buffer: &mut AlignedBuffer<256>
+) -> bool {
+    let renewal = ServiceRenewal {
+        service_id: endpoint.service_id,
+        expiry_time: endpoint.lease_secs,
+    };
+    
+    let tx_data = tx_buffer.as_mut_slice();
+    let renewal_len = serialize_service_renewal(&renewal, tx_data);
+    if renewal_len == 0 {
+        return false;
+    }
+    
+    let node_id = hardware.get_node_id();
+    let renewal_packet = DataPacket::with_type(
+        node_id,
+        endpoint.relay_id,
+        PacketType::ServiceRenew,
+        0, // 包ID
+        &tx_data[..renewal_len]
+    ).with_flow(flow_id_of(endpoint.service_id));
+    
+    endpoint.last_renew_attempt = hardware.get_timestamp_ms().unwrap_or(0);
+    
+    if let Err(e) = send_secure(hardware, &renewal_packet) {
+        warn!("发送续期请求失败: {:?}", e);
+        return false;
+    }
+    
+    info!("已发送服务 {} 的续期请求", endpoint.service_id);
+    true
+}
+
+/// 处理续期响应，返回租约是否仍然有效
+pub fn handle_renewal_response(
+    endpoint: &mut ServiceEndpoint,
+    packet: &DataPacket,
+    current_time: u64
+) -> bool {
+    match deserialize_service_response(packet.data) {
+        Some(response) if response.service_id == endpoint.service_id => {
+            if response.status == 0 {
+                endpoint.extend_lease(current_time);
+                info!("服务 {} 续期成功", endpoint.service_id);
+                true
+            } else {
+                warn!("服务 {} 续期被拒绝", endpoint.service_id);
+                false
+            }
+        },
+        // 与当前服务无关的响应，不影响租约
+        _ => true,
+    }
+}
+
 /// 更新服务端点（例如更新跳数信息）
 pub fn update_service_endpoint(endpoint: &mut ServiceEndpoint, hops: u8) {
     endpoint.hops = hops;
@@ -118,35 +408,34 @@ pub fn close_service<H: Hardware>(
     endpoint: &ServiceEndpoint,
     tx_buffer: &mut AlignedBuffer<256>
 ) -> bool {
-    println!("关闭服务连接: 服务ID={}, 服务器={:?}", 
+    info!("关闭服务连接: 服务ID={}, 服务器={}", 
              endpoint.service_id, endpoint.server_id);
     
     // 创建关闭服务请求
-    let mut close_data = [0u8; 6];
-    
-    // 0-3: 服务ID
-    let service_id_bytes = endpoint.service_id.to_be_bytes();
-    close_data[0..4].copy_from_slice(&service_id_bytes);
-    
-    // 4: 关闭原因（0=正常关闭）
-    close_data[4] = 0;
+    let close = ServiceClose {
+        service_id: endpoint.service_id,
+        reason: 0, // 正常关闭
+    };
     
-    // 5: 预留
-    close_data[5] = 0;
+    let tx_data = tx_buffer.as_mut_slice();
+    let close_len = serialize_service_close(&close, tx_data);
+    if close_len == 0 {
+        return false;
+    }
     
     // 创建关闭请求数据包
     let node_id = hardware.get_node_id();
-    let close_packet = DataPacket::new(
+    let close_packet = DataPacket::with_type(
         node_id,
         endpoint.relay_id, // 发送给中继节点
+        PacketType::ServiceClose,
         0, // 包ID
-        &close_data
-    );
+        &tx_data[..close_len]
+    ).with_flow(flow_id_of(endpoint.service_id));
     
     // 发送关闭请求
-    let radio = hardware.get_radio();
-    if let Err(e) = radio.send_data(&close_packet) {
-        println!("发送服务关闭请求失败: {:?}", e);
+    if let Err(e) = send_secure(hardware, &close_packet) {
+        warn!("发送服务关闭请求失败: {:?}", e);
         return false;
     }
     
@@ -0,0 +1,133 @@
+use common::protocol::{DataPacket, PacketType, ServiceType, deserialize_service_response};
+use common::protocol::wire::get_u32be;
+use crate::rate_control::{RateConfig, RateController};
+use crate::service_client::{KeepAlive, ServiceEndpoint};
+
+/// 客户端同时保持的最大会话数
+pub const MAX_SESSIONS: usize = 4;
+
+/// 单个服务会话
+#[derive(Debug, Clone, Copy)]
+pub struct Session {
+    /// 服务端点
+    pub endpoint: ServiceEndpoint,
+    /// 中继路径是否已建立
+    pub path_established: bool,
+    /// 路径建立后是否已测量往返时延
+    pub latency_checked: bool,
+    /// 会话建立（或重建）的时间，用于路径建立超时
+    pub opened_at: u64,
+    /// 上次发送数据的时间
+    pub last_send: u64,
+    /// 根据确认和拥塞反馈调整的发送速率
+    pub rate: RateController,
+    /// 路径建立后的保活状态
+    pub keepalive: KeepAlive,
+}
+
+/// 会话管理器，按服务ID区分多个并发会话
+pub struct SessionManager {
+    sessions: [Option<Session>; MAX_SESSIONS],
+}
+
+impl SessionManager {
+    /// 创建空的会话管理器
+    pub fn new() -> Self {
+        Self {
+            sessions: [None; MAX_SESSIONS],
+        }
+    }
+    
+    /// 添加会话，同类型的旧会话会被替换
+    pub fn add(&mut self, endpoint: ServiceEndpoint, current_time: u64) -> bool {
+        let session = Session {
+            endpoint,
+            path_established: false,
+            latency_checked: false,
+            opened_at: current_time,
+            last_send: 0,
+            rate: RateController::new(RateConfig::default()),
+            keepalive: KeepAlive::new(current_time),
+        };
+        
+        let index = self.sessions.iter()
+            .position(|entry| matches!(entry, Some(s) if s.endpoint.service_type == endpoint.service_type))
+            .or_else(|| self.sessions.iter().position(|entry| entry.is_none()));
+        
+        match index {
+            Some(index) => {
+                self.sessions[index] = Some(session);
+                true
+            },
+            None => false,
+        }
+    }
+    
+    /// 移除会话
+    pub fn remove(&mut self, service_id: u32) -> Option<Session> {
+        for entry in self.sessions.iter_mut() {
+            if matches!(entry, Some(s) if s.endpoint.service_id == service_id) {
+                return entry.take();
+            }
+        }
+        None
+    }
+    
+    /// 按服务ID查找会话
+    pub fn get_mut(&mut self, service_id: u32) -> Option<&mut Session> {
+        self.sessions.iter_mut()
+            .flatten()
+            .find(|s| s.endpoint.service_id == service_id)
+    }
+    
+    /// 按服务类型查找会话
+    pub fn get_by_type(&self, service_type: ServiceType) -> Option<&Session> {
+        self.sessions.iter()
+            .flatten()
+            .find(|s| s.endpoint.service_type == service_type)
+    }
+    
+    /// 遍历所有会话
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Session> {
+        self.sessions.iter_mut().flatten()
+    }
+    
+    /// 当前会话数
+    pub fn len(&self) -> usize {
+        self.sessions.iter().flatten().count()
+    }
+    
+    /// 是否没有会话
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// 将收到的数据包分发到对应的会话
+    pub fn dispatch(&mut self, packet: &DataPacket) -> Option<&mut Session> {
+        let service_id = session_id_of(packet)?;
+        self.get_mut(service_id)
+    }
+}
+
+/// 从数据包中提取所属的服务ID
+pub fn session_id_of(packet: &DataPacket) -> Option<u32> {
+    let data = packet.data;
+    let read_u32 = |offset: usize| -> Option<u32> {
+        if data.len() < offset + 4 {
+            return None;
+        }
+        Some(get_u32be(&data[offset..offset + 4]))
+    };
+    
+    match PacketType::from_u8(packet.header.packet_type)? {
+        // 服务响应带版本和请求ID，服务ID位置随版本变化
+        PacketType::ServiceResponse => deserialize_service_response(data).map(|response| response.service_id),
+        // 确认包、拥塞通知和保活确认：字节0-3为服务ID
+        PacketType::Ack | PacketType::Congestion | PacketType::PathKeepAliveAck => read_u32(0),
+        // 路径确认：客户端ID(6) 状态(1) 跳数(1) 服务ID(4)
+        PacketType::PathConfirm => read_u32(8),
+        // 应用数据：类型(1) 服务ID(4)
+        PacketType::Data => read_u32(1),
+        _ => None,
+    }
+}
\ No newline at end of file
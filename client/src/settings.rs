@@ -0,0 +1,165 @@
+use common::hal::nvs::{keys, NvStorage};
+use common::protocol::{NodeId, QosRequirements, ServiceType};
+
+/// 配置格式版本，格式变化时递增
+const SETTINGS_VERSION: u8 = 2;
+
+/// 第1版配置的长度，加载时兼容升级前保存的配置
+const SETTINGS_V1_SIZE: usize = 24;
+
+/// 网络密钥长度
+pub const NETWORK_KEY_LEN: usize = common::security::NETWORK_KEY_LEN;
+
+/// 可远程修改并持久保存的客户端配置
+#[derive(Debug, Clone, Copy)]
+pub struct ClientSettings {
+    /// 已配置的节点身份，None表示使用硬件默认ID
+    pub node_id: Option<NodeId>,
+    /// 网络密钥，None表示尚未配置
+    pub network_key: Option<[u8; NETWORK_KEY_LEN]>,
+    /// 传感器采样间隔（毫秒）
+    pub sample_interval_ms: u32,
+    /// 无线信道
+    pub channel: u8,
+    /// 指定的目标服务器，None表示使用服务目录分配的服务器
+    pub server_affinity: Option<NodeId>,
+    /// 视频中继的服务质量要求
+    pub video_qos: QosRequirements,
+    /// 传感器数据收集的服务质量要求
+    pub sensor_qos: QosRequirements,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        Self {
+            node_id: None,
+            network_key: None,
+            sample_interval_ms: 5000,
+            channel: 15,
+            server_affinity: None,
+            video_qos: QosRequirements {
+                min_bandwidth: 500, // 至少500kbps带宽
+                max_latency: 200,   // 最大200ms延迟
+                reliability: 80,    // 80%可靠性
+            },
+            // 传感器数据收集对带宽和延迟要求较低
+            sensor_qos: QosRequirements {
+                min_bandwidth: 10,  // 10kbps即可
+                max_latency: 2000,  // 2秒延迟
+                reliability: 90,    // 90%可靠性
+            },
+        }
+    }
+}
+
+/// 序列化服务质量要求：最小带宽(2) 最大延迟(2) 可靠性(1)
+fn write_qos(qos: &QosRequirements, buffer: &mut [u8]) {
+    buffer[0..2].copy_from_slice(&qos.min_bandwidth.to_be_bytes());
+    buffer[2..4].copy_from_slice(&qos.max_latency.to_be_bytes());
+    buffer[4] = qos.reliability;
+}
+
+/// 解析服务质量要求
+pub fn read_qos(buffer: &[u8]) -> QosRequirements {
+    QosRequirements {
+        min_bandwidth: u16::from_be_bytes([buffer[0], buffer[1]]),
+        max_latency: u16::from_be_bytes([buffer[2], buffer[3]]),
+        reliability: buffer[4],
+    }
+}
+
+impl ClientSettings {
+    /// 序列化后的长度
+    pub const SIZE: usize = 48;
+    
+    /// 指定服务类型的服务质量要求
+    pub fn qos_for(&self, service_type: ServiceType) -> &QosRequirements {
+        match service_type {
+            ServiceType::VideoRelay => &self.video_qos,
+            _ => &self.sensor_qos,
+        }
+    }
+    
+    /// 序列化为字节
+    ///
+    /// 格式：版本(1) 采样间隔(4) 信道(1) 是否指定服务器(1) 服务器ID(6) 视频QoS(5) 传感器QoS(5) 预留(1)
+    /// 是否配置身份(1) 节点ID(6) 是否配置密钥(1) 网络密钥(16)
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0] = SETTINGS_VERSION;
+        bytes[1..5].copy_from_slice(&self.sample_interval_ms.to_be_bytes());
+        bytes[5] = self.channel;
+        if let Some(server) = self.server_affinity {
+            bytes[6] = 1;
+            bytes[7..13].copy_from_slice(&server.0);
+        }
+        write_qos(&self.video_qos, &mut bytes[13..18]);
+        write_qos(&self.sensor_qos, &mut bytes[18..23]);
+        if let Some(node_id) = self.node_id {
+            bytes[24] = 1;
+            bytes[25..31].copy_from_slice(&node_id.0);
+        }
+        if let Some(key) = self.network_key {
+            bytes[31] = 1;
+            bytes[32..48].copy_from_slice(&key);
+        }
+        bytes
+    }
+    
+    /// 从字节解析，兼容第1版格式（身份和密钥视为未配置）
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let version = *bytes.first()?;
+        let required = match version {
+            1 => SETTINGS_V1_SIZE,
+            SETTINGS_VERSION => Self::SIZE,
+            _ => return None,
+        };
+        if bytes.len() < required {
+            return None;
+        }
+        
+        let read_id = |offset: usize| -> Option<NodeId> {
+            if bytes[offset] == 0 {
+                return None;
+            }
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&bytes[offset + 1..offset + 7]);
+            Some(NodeId(id))
+        };
+        
+        let mut settings = Self {
+            node_id: None,
+            network_key: None,
+            sample_interval_ms: u32::from_be_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]),
+            channel: bytes[5],
+            server_affinity: read_id(6),
+            video_qos: read_qos(&bytes[13..18]),
+            sensor_qos: read_qos(&bytes[18..23]),
+        };
+        
+        if version >= 2 {
+            settings.node_id = read_id(24);
+            if bytes[31] != 0 {
+                let mut key = [0u8; NETWORK_KEY_LEN];
+                key.copy_from_slice(&bytes[32..48]);
+                settings.network_key = Some(key);
+            }
+        }
+        
+        Some(settings)
+    }
+    
+    /// 从非易失存储加载，不存在或无法解析时使用默认配置
+    pub fn load<N: NvStorage>(nvs: &mut N) -> Self {
+        let mut buffer = [0u8; Self::SIZE];
+        match nvs.nvs_read(keys::CLIENT_SETTINGS, &mut buffer) {
+            Ok(Some(len)) => Self::from_bytes(&buffer[..len]).unwrap_or_default(),
+            _ => Self::default(),
+        }
+    }
+    
+    /// 写入非易失存储
+    pub fn store<N: NvStorage>(&self, nvs: &mut N) -> Result<(), N::Error> {
+        nvs.nvs_write(keys::CLIENT_SETTINGS, &self.to_bytes())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,165 @@
+use common::protocol::QosRequirements;
+use common::protocol::frame::{VideoTier, DEFAULT_VIDEO_TIER, VIDEO_TIERS};
+
+/// 视频质量自适应参数
+#[derive(Debug, Clone, Copy)]
+pub struct QualityConfig {
+    /// 统计丢包的窗口长度（毫秒）
+    pub window_ms: u64,
+    /// 窗口内丢包率超过此百分比时降一档
+    pub downgrade_loss_percent: u8,
+    /// 窗口内丢包率不超过此百分比才算良好
+    pub upgrade_loss_percent: u8,
+    /// 连续多少个良好窗口后升一档
+    pub upgrade_windows: u8,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: 5000,
+            downgrade_loss_percent: 10,
+            upgrade_loss_percent: 2,
+            upgrade_windows: 3,
+        }
+    }
+}
+
+/// 视频质量档位控制：丢包多或时延超过QoS要求时降档，持续良好时升档，不超过协商带宽允许的档位
+pub struct VideoQuality {
+    config: QualityConfig,
+    /// 当前档位，`VIDEO_TIERS`的下标
+    tier: u8,
+    /// 协商带宽允许的最高档位
+    max_tier: u8,
+    /// QoS要求的最大时延（毫秒）
+    max_latency_ms: u32,
+    /// 当前窗口内已确认的帧数
+    delivered: u32,
+    /// 当前窗口内丢失或遇到拥塞的帧数
+    lost: u32,
+    /// 最近一次测得的往返时延
+    rtt_ms: Option<u32>,
+    /// 当前窗口开始的时间
+    window_start: u64,
+    /// 连续良好的窗口数
+    good_windows: u8,
+    /// 服务器是否已知道当前档位
+    announced: bool,
+}
+
+impl VideoQuality {
+    pub fn new(config: QualityConfig, qos: &QosRequirements, current_time: u64) -> Self {
+        let mut quality = Self {
+            config,
+            tier: DEFAULT_VIDEO_TIER,
+            max_tier: 0,
+            max_latency_ms: 0,
+            delivered: 0,
+            lost: 0,
+            rtt_ms: None,
+            window_start: current_time,
+            good_windows: 0,
+            announced: false,
+        };
+        quality.set_qos(qos);
+        quality
+    }
+    
+    /// 当前档位号
+    pub fn tier(&self) -> u8 {
+        self.tier
+    }
+    
+    /// 当前档位参数
+    pub fn current(&self) -> VideoTier {
+        VIDEO_TIERS[self.tier as usize]
+    }
+    
+    /// 服务质量要求变化，当前档位超过新带宽时立即降到允许的最高档位
+    pub fn set_qos(&mut self, qos: &QosRequirements) {
+        self.max_latency_ms = qos.max_latency as u32;
+        self.max_tier = VIDEO_TIERS.iter()
+            .rposition(|tier| tier.bitrate_kbps() <= qos.min_bandwidth as u32)
+            .unwrap_or(0) as u8;
+        if self.tier > self.max_tier {
+            self.change_tier(self.max_tier);
+        }
+    }
+    
+    /// 会话重建后重新统计，并把当前档位通知给新的服务器
+    pub fn restart(&mut self, current_time: u64) {
+        self.delivered = 0;
+        self.lost = 0;
+        self.rtt_ms = None;
+        self.window_start = current_time;
+        self.good_windows = 0;
+        self.announced = false;
+    }
+    
+    /// 一帧已确认
+    pub fn on_delivered(&mut self) {
+        self.delivered += 1;
+    }
+    
+    /// 一帧重传失败或收到拥塞通知
+    pub fn on_lost(&mut self) {
+        self.lost += 1;
+    }
+    
+    /// 测得一次往返时延
+    pub fn on_rtt(&mut self, rtt_ms: u32) {
+        self.rtt_ms = Some(rtt_ms);
+    }
+    
+    /// 窗口结束时评估是否换档，档位变化时返回新档位
+    pub fn poll(&mut self, current_time: u64) -> Option<u8> {
+        if current_time.saturating_sub(self.window_start) < self.config.window_ms {
+            return None;
+        }
+        
+        let total = self.delivered + self.lost;
+        let loss_percent = if total > 0 { self.lost * 100 / total } else { 0 };
+        let slow = matches!(self.rtt_ms, Some(rtt) if rtt > self.max_latency_ms);
+        let fast = !matches!(self.rtt_ms, Some(rtt) if rtt > self.max_latency_ms / 2);
+        
+        self.delivered = 0;
+        self.lost = 0;
+        self.window_start = current_time;
+        
+        let old_tier = self.tier;
+        if slow || loss_percent > self.config.downgrade_loss_percent as u32 {
+            self.good_windows = 0;
+            if self.tier > 0 {
+                self.change_tier(self.tier - 1);
+            }
+        } else if total > 0 && fast && loss_percent <= self.config.upgrade_loss_percent as u32 {
+            // 没有发送的窗口不能说明路径良好，不计入
+            self.good_windows += 1;
+            if self.good_windows >= self.config.upgrade_windows && self.tier < self.max_tier {
+                self.good_windows = 0;
+                self.change_tier(self.tier + 1);
+            }
+        } else {
+            self.good_windows = 0;
+        }
+        
+        (self.tier != old_tier).then_some(self.tier)
+    }
+    
+    /// 服务器尚未知道的当前档位
+    pub fn announcement(&self) -> Option<u8> {
+        (!self.announced).then_some(self.tier)
+    }
+    
+    /// 档位通知已发出
+    pub fn mark_announced(&mut self) {
+        self.announced = true;
+    }
+    
+    fn change_tier(&mut self, tier: u8) {
+        self.tier = tier;
+        self.good_windows = 0;
+        self.announced = false;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,122 @@
+use crate::config::{ConfigChanges, ConfigObserver, NodeConfig, MIN_BEACON_INTERVAL_MS};
+use crate::protocol::{Beacon, NodeId};
+
+/// 跟踪的最大邻居数
+pub const MAX_DENSITY_NEIGHBORS: usize = 32;
+
+/// 邻居数达到此值时使用配置的信标间隔，更多时拉长，更少时缩短
+pub const TARGET_NEIGHBORS: u32 = 4;
+
+/// 信标间隔可缩短或拉长的倍数
+pub const INTERVAL_SCALE: u32 = 4;
+
+/// 多少个最长信标间隔内没有听到就不再计为邻居，邻居自己也可能拉长了间隔
+const NEIGHBOR_TTL_INTERVALS: u64 = 3;
+
+/// 信标间隔的上下限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeaconBounds {
+    /// 邻居数为目标值时的间隔（毫秒）
+    pub base_ms: u32,
+    /// 孤立节点的最短间隔（毫秒）
+    pub min_ms: u32,
+    /// 密集网络中的最长间隔（毫秒）
+    pub max_ms: u32,
+}
+
+impl BeaconBounds {
+    /// 以配置的信标间隔为基准，上下各留`INTERVAL_SCALE`倍
+    pub fn around(base_ms: u32) -> Self {
+        Self {
+            base_ms,
+            min_ms: (base_ms / INTERVAL_SCALE).max(MIN_BEACON_INTERVAL_MS).min(base_ms),
+            max_ms: base_ms.saturating_mul(INTERVAL_SCALE),
+        }
+    }
+}
+
+/// 按邻居密度调整的信标间隔
+///
+/// 听到的邻居越多，发现本节点的途径越多，信标可以少发，减少空闲时的占空和碰撞；
+/// 听不到邻居的节点多发信标，让新节点尽快发现它。
+pub struct AdaptiveBeacon {
+    bounds: BeaconBounds,
+    /// 直接听到的邻居及最近听到的时间
+    heard: [Option<(NodeId, u64)>; MAX_DENSITY_NEIGHBORS],
+}
+
+impl AdaptiveBeacon {
+    /// 以配置的信标间隔创建
+    pub fn new(base_ms: u32) -> Self {
+        Self {
+            bounds: BeaconBounds::around(base_ms),
+            heard: [None; MAX_DENSITY_NEIGHBORS],
+        }
+    }
+    
+    /// 当前的上下限
+    pub fn bounds(&self) -> BeaconBounds {
+        self.bounds
+    }
+    
+    /// 记录听到的信标，只统计直接发出信标的邻居，转发的信标不算
+    pub fn observe(&mut self, beacon: &Beacon, now: u64) {
+        if beacon.hop_count != 0 {
+            return;
+        }
+        self.observe_node(NodeId(beacon.source), now);
+    }
+    
+    /// 记录直接听到的邻居
+    pub fn observe_node(&mut self, node: NodeId, now: u64) {
+        self.expire(now);
+        
+        if let Some(entry) = self.heard.iter_mut().flatten().find(|(id, _)| *id == node) {
+            entry.1 = now;
+            return;
+        }
+        // 表满时已经远超目标密度，不必再记
+        if let Some(slot) = self.heard.iter_mut().find(|entry| entry.is_none()) {
+            *slot = Some((node, now));
+        }
+    }
+    
+    /// 当前听到的邻居数
+    pub fn neighbor_count(&self, now: u64) -> usize {
+        let ttl = self.neighbor_ttl_ms();
+        self.heard.iter()
+            .flatten()
+            .filter(|(_, heard_at)| now.saturating_sub(*heard_at) <= ttl)
+            .count()
+    }
+    
+    /// 按当前邻居数计算的信标间隔（毫秒）
+    pub fn interval_ms(&self, now: u64) -> u64 {
+        let neighbors = self.neighbor_count(now) as u64;
+        let BeaconBounds { base_ms, min_ms, max_ms } = self.bounds;
+        let scaled = base_ms as u64 * (neighbors + 1) / (TARGET_NEIGHBORS as u64 + 1);
+        scaled.clamp(min_ms as u64, max_ms as u64)
+    }
+    
+    /// 移除太久没听到的邻居
+    fn expire(&mut self, now: u64) {
+        let ttl = self.neighbor_ttl_ms();
+        for entry in self.heard.iter_mut() {
+            if matches!(entry, Some((_, heard_at)) if now.saturating_sub(*heard_at) > ttl) {
+                *entry = None;
+            }
+        }
+    }
+    
+    fn neighbor_ttl_ms(&self) -> u64 {
+        self.bounds.max_ms as u64 * NEIGHBOR_TTL_INTERVALS
+    }
+}
+
+impl ConfigObserver for AdaptiveBeacon {
+    fn config_changed(&mut self, config: &NodeConfig, changes: ConfigChanges) {
+        if changes.beacon {
+            self.bounds = BeaconBounds::around(config.beacon_interval_ms);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,238 @@
+use crate::hal::RadioDiagnostics;
+use crate::protocol::DataPacket;
+use crate::protocol::channel::{ChannelMessage, ChannelReport, CHANNEL_COUNT, FIRST_CHANNEL};
+
+/// 转发节点上报信道质量的间隔（毫秒）
+pub const CHANNEL_REPORT_INTERVAL_MS: u64 = 60_000;
+
+/// 主节点评估是否需要换信道的间隔（毫秒）
+pub const CHANNEL_EVAL_INTERVAL_MS: u64 = 5 * 60_000;
+
+/// 报告超过该时间未更新视为失效（毫秒）
+pub const CHANNEL_REPORT_MAX_AGE_MS: u64 = 3 * CHANNEL_REPORT_INTERVAL_MS;
+
+/// 当前信道的平均CRC错误率超过该值（千分比）时考虑换信道
+pub const CRC_MIGRATE_PERMILLE: u16 = 50;
+
+/// 能量检测高于该值（dBm）的信道视为有干扰
+pub const BUSY_ENERGY_DBM: i8 = -75;
+
+/// 新信道的能量至少要比当前信道低这么多（dB）才值得切换
+pub const MIGRATE_MARGIN_DB: i8 = 10;
+
+/// 通告发出到全网切换的提前量（毫秒），让通告有时间逐跳扩散
+pub const SWITCH_LEAD_MS: u64 = 30_000;
+
+/// 切换后中继在新旧信道之间交替监听的时长（毫秒）
+pub const SWITCH_GRACE_MS: u32 = 60_000;
+
+/// 主节点最多保存的报告节点数
+pub const MAX_CHANNEL_REPORTS: usize = 16;
+
+/// 收到的报告及其时间
+#[derive(Clone, Copy)]
+struct SurveyEntry {
+    report: ChannelReport,
+    received_at: u64,
+}
+
+/// 主节点汇总的信道质量，每个转发节点保留最近一次的报告
+pub struct ChannelSurvey {
+    reports: [Option<SurveyEntry>; MAX_CHANNEL_REPORTS],
+}
+
+impl ChannelSurvey {
+    /// 创建空的汇总
+    pub fn new() -> Self {
+        Self {
+            reports: [None; MAX_CHANNEL_REPORTS],
+        }
+    }
+    
+    /// 记录一份报告，替换同一节点的旧报告；已满时替换最旧的报告
+    pub fn record(&mut self, report: &ChannelReport, current_time: u64) {
+        let entry = SurveyEntry { report: *report, received_at: current_time };
+        
+        let index = self.reports.iter()
+            .position(|slot| matches!(slot, Some(e) if e.report.reporter == report.reporter))
+            .or_else(|| self.reports.iter().position(|slot| slot.is_none()))
+            .unwrap_or_else(|| {
+                self.reports.iter()
+                    .enumerate()
+                    .min_by_key(|(_, slot)| slot.map_or(0, |e| e.received_at))
+                    .map_or(0, |(index, _)| index)
+            });
+        self.reports[index] = Some(entry);
+    }
+    
+    /// 移除过期的报告
+    pub fn expire(&mut self, current_time: u64) {
+        for slot in self.reports.iter_mut() {
+            if matches!(slot, Some(e) if current_time.saturating_sub(e.received_at) > CHANNEL_REPORT_MAX_AGE_MS) {
+                *slot = None;
+            }
+        }
+    }
+    
+    /// 有效的报告数
+    pub fn len(&self) -> usize {
+        self.reports.iter().flatten().count()
+    }
+    
+    /// 是否没有报告
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// 清空报告，换信道后旧信道上的测量不再有意义
+    pub fn clear(&mut self) {
+        self.reports = [None; MAX_CHANNEL_REPORTS];
+    }
+    
+    fn entries(&self) -> impl Iterator<Item = &ChannelReport> {
+        self.reports.iter().flatten().map(|entry| &entry.report)
+    }
+    
+    /// 各节点在指定信道上测得的最高能量，没有节点测量时返回None
+    pub fn worst_energy(&self, channel: u8) -> Option<i8> {
+        self.entries().filter_map(|report| report.energy_on(channel)).max()
+    }
+    
+    /// 在当前信道上测量的节点的平均CRC错误率
+    pub fn crc_permille(&self, channel: u8) -> Option<u16> {
+        let (sum, count) = self.entries()
+            .filter(|report| report.channel == channel)
+            .fold((0u32, 0u32), |(sum, count), report| (sum + report.crc_permille as u32, count + 1));
+        (count > 0).then(|| (sum / count) as u16)
+    }
+    
+    /// 当前信道质量变差时选出干扰最小的信道，不需要或没有更好的信道时返回None
+    ///
+    /// 以各节点测得的最高能量衡量信道，整个网络都要换过去，最差的位置决定信道好坏。
+    pub fn choose(&self, current: u8) -> Option<u8> {
+        let current_energy = self.worst_energy(current);
+        let crc_degraded = self.crc_permille(current).map_or(false, |crc| crc > CRC_MIGRATE_PERMILLE);
+        let busy = current_energy.map_or(false, |energy| energy > BUSY_ENERGY_DBM);
+        if !crc_degraded && !busy {
+            return None;
+        }
+        
+        let (channel, energy) = (FIRST_CHANNEL..FIRST_CHANNEL + CHANNEL_COUNT as u8)
+            .filter(|channel| *channel != current)
+            .filter_map(|channel| self.worst_energy(channel).map(|energy| (channel, energy)))
+            .min_by_key(|(_, energy)| *energy)?;
+        
+        let clean = energy <= BUSY_ENERGY_DBM;
+        let better = current_energy.map_or(true, |current| energy.saturating_add(MIGRATE_MARGIN_DB) <= current);
+        (clean && better).then_some(channel)
+    }
+}
+
+/// 一次进行中的信道切换
+///
+/// 切换前留在旧信道；切换后的宽限期内中继每次调用在新旧信道之间交替，
+/// 让错过通告的节点仍能通过旧信道收到转发的通告；宽限期结束后只用新信道。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelMigration {
+    /// 旧信道
+    pub from: u8,
+    /// 新信道
+    pub to: u8,
+    /// 切换的网络时间
+    pub switch_at: u64,
+    grace_until: u64,
+    on_old: bool,
+}
+
+impl ChannelMigration {
+    /// 创建切换，不转发的节点宽限期为0
+    pub fn new(from: u8, to: u8, switch_at: u64, grace_ms: u32) -> Self {
+        Self {
+            from,
+            to,
+            switch_at,
+            grace_until: switch_at + grace_ms as u64,
+            on_old: false,
+        }
+    }
+    
+    /// 当前应该使用的信道
+    pub fn tune(&mut self, network_now: u64) -> u8 {
+        if network_now < self.switch_at {
+            return self.from;
+        }
+        if network_now < self.grace_until {
+            self.on_old = !self.on_old;
+            return if self.on_old { self.from } else { self.to };
+        }
+        self.to
+    }
+    
+    /// 是否处于宽限期
+    pub fn in_grace(&self, network_now: u64) -> bool {
+        network_now >= self.switch_at && network_now < self.grace_until
+    }
+    
+    /// 切换和宽限期都已结束
+    pub fn is_complete(&self, network_now: u64) -> bool {
+        network_now >= self.grace_until
+    }
+}
+
+/// 不转发的节点（客户端和服务器）跟随主节点的切换通告，到切换时间直接换到新信道
+pub struct ChannelFollower {
+    migration: Option<ChannelMigration>,
+}
+
+impl ChannelFollower {
+    /// 创建跟随者，没有进行中的切换
+    pub fn new() -> Self {
+        Self { migration: None }
+    }
+    
+    /// 处理收到的信道协调消息，通告的信道与当前信道相同时忽略
+    pub fn handle(&mut self, packet: &DataPacket, current: u8) {
+        if let Some(ChannelMessage::Switch { channel, switch_at, .. }) = ChannelMessage::deserialize(packet.data) {
+            if channel != current {
+                self.migration = Some(ChannelMigration::new(current, channel, switch_at, 0));
+            }
+        }
+    }
+    
+    /// 到切换时间时返回新信道，由主循环配置无线电并保存
+    pub fn poll(&mut self, network_now: u64) -> Option<u8> {
+        let migration = self.migration.filter(|migration| migration.is_complete(network_now))?;
+        self.migration = None;
+        Some(migration.to)
+    }
+}
+
+/// 按无线电统计计算当前信道的CRC错误率
+pub struct CrcSampler {
+    last_crc_errors: u32,
+    last_received: u32,
+}
+
+impl CrcSampler {
+    /// 创建采样器，第一次采样包括启动以来的所有帧
+    pub fn new() -> Self {
+        Self {
+            last_crc_errors: 0,
+            last_received: 0,
+        }
+    }
+    
+    /// 自上次采样以来CRC错误帧占所有到达帧的比例（千分比）
+    pub fn sample(&mut self, diagnostics: &RadioDiagnostics, received: u32) -> u16 {
+        let errors = diagnostics.crc_errors.wrapping_sub(self.last_crc_errors);
+        let good = received.wrapping_sub(self.last_received);
+        self.last_crc_errors = diagnostics.crc_errors;
+        self.last_received = received;
+        
+        let total = errors as u64 + good as u64;
+        if total == 0 {
+            return 0;
+        }
+        (errors as u64 * 1000 / total) as u16
+    }
+}
\ No newline at end of file
@@ -0,0 +1,145 @@
+use crate::protocol::NodeId;
+use crate::protocol::time_sync::TimeBeacon;
+
+/// 每一跳的传输和处理时延估计（毫秒），接收时补偿到信标时间上
+pub const HOP_DELAY_MS: u64 = 5;
+
+/// 时间信标的广播间隔（毫秒）
+pub const TIME_BEACON_INTERVAL_MS: u64 = 30000;
+
+/// 超过该误差直接跳变，否则逐步调整（毫秒）
+const STEP_THRESHOLD_MS: i64 = 1000;
+
+/// 小误差每次只修正的比例分母，避免时间来回跳动
+const SLEW_DIVISOR: i64 = 4;
+
+/// 多久没有收到信标视为失去同步（毫秒）
+const SYNC_TIMEOUT_MS: u64 = 5 * TIME_BEACON_INTERVAL_MS;
+
+/// 以主节点为基准的网络时钟，在本地单调时钟上叠加偏移
+///
+/// 租约到期和记录时间戳使用网络时间，不同节点之间可以直接比较。
+#[derive(Debug, Clone, Copy)]
+pub struct NetworkClock {
+    /// 网络时间减去本地时间
+    offset_ms: i64,
+    /// 当前跟随的主节点
+    master: Option<NodeId>,
+    /// 本节点是否是主节点
+    is_master: bool,
+    /// 最近接受的信标序号
+    sequence: u16,
+    /// 距主节点的跳数
+    hop_count: u8,
+    /// 最近一次同步的本地时间
+    synced_at: Option<u64>,
+}
+
+impl NetworkClock {
+    /// 创建未同步的时钟，此时网络时间等于本地时间
+    pub fn new() -> Self {
+        Self {
+            offset_ms: 0,
+            master: None,
+            is_master: false,
+            sequence: 0,
+            hop_count: 0,
+            synced_at: None,
+        }
+    }
+    
+    /// 当前网络时间
+    pub fn now(&self, local_time: u64) -> u64 {
+        (local_time as i64).saturating_add(self.offset_ms).max(0) as u64
+    }
+    
+    /// 是否与主节点保持同步，主节点自身始终同步
+    pub fn is_synced(&self, local_time: u64) -> bool {
+        self.is_master || matches!(self.synced_at, Some(at) if local_time.saturating_sub(at) < SYNC_TIMEOUT_MS)
+    }
+    
+    /// 当前跟随的主节点
+    pub fn master(&self) -> Option<NodeId> {
+        self.master
+    }
+    
+    /// 距主节点的跳数
+    pub fn hop_count(&self) -> u8 {
+        self.hop_count
+    }
+    
+    /// 成为时间基准，保持当前网络时间连续
+    pub fn become_master(&mut self, node_id: NodeId) {
+        if !self.is_master {
+            self.is_master = true;
+            self.master = Some(node_id);
+            self.hop_count = 0;
+        }
+    }
+    
+    /// 不再作为时间基准，等待新主节点的信标
+    pub fn resign_master(&mut self) {
+        self.is_master = false;
+    }
+    
+    /// 主节点生成下一轮时间信标
+    pub fn next_beacon(&mut self, local_time: u64) -> Option<TimeBeacon> {
+        if !self.is_master {
+            return None;
+        }
+        
+        self.sequence = self.sequence.wrapping_add(1);
+        Some(TimeBeacon {
+            master: self.master?,
+            sequence: self.sequence,
+            network_time: self.now(local_time),
+            hop_count: 0,
+        })
+    }
+    
+    /// 用收到的信标校准时钟，接受时返回需要继续转发的信标和时钟的跳变量
+    ///
+    /// 同一轮信标只接受第一次到达的；主节点变化时跟随新主节点。
+    /// 误差较大时直接跳变，调用方据跳变量平移已按网络时间记录的到期时间。
+    pub fn apply(&mut self, beacon: &TimeBeacon, local_time: u64) -> Option<(TimeBeacon, i64)> {
+        if self.is_master {
+            return None;
+        }
+        
+        let same_master = self.master == Some(beacon.master);
+        if same_master && self.synced_at.is_some() && !is_newer(beacon.sequence, self.sequence) {
+            return None;
+        }
+        
+        // 信标时间加上一跳的时延才是接收时刻的网络时间
+        let received = beacon.network_time + HOP_DELAY_MS;
+        let error = received as i64 - self.now(local_time) as i64;
+        let step = if self.synced_at.is_none() || !same_master || error.abs() > STEP_THRESHOLD_MS {
+            error
+        } else {
+            0
+        };
+        self.offset_ms += if step != 0 { step } else { error / SLEW_DIVISOR };
+        
+        self.master = Some(beacon.master);
+        self.sequence = beacon.sequence;
+        self.hop_count = beacon.hop_count.saturating_add(1);
+        self.synced_at = Some(local_time);
+        
+        let relay = TimeBeacon {
+            hop_count: self.hop_count,
+            ..*beacon
+        };
+        Some((relay, step))
+    }
+    
+    /// 转发前按本节点的网络时间重新填写信标时间
+    pub fn restamp(&self, beacon: &mut TimeBeacon, local_time: u64) {
+        beacon.network_time = self.now(local_time);
+    }
+}
+
+/// 序号回绕比较
+fn is_newer(sequence: u16, last: u16) -> bool {
+    sequence != last && sequence.wrapping_sub(last) < 0x8000
+}
\ No newline at end of file
@@ -0,0 +1,643 @@
+use crate::hal::{AirtimeConfig, Hardware, RadioInterface};
+use crate::hal::nvs::{keys, NvStorage};
+use crate::log::{self, Level};
+use crate::mgmt::Managed;
+use crate::protocol::{NodeId, NodeRole, ServiceType};
+use crate::protocol::data::{DEFAULT_TTL, MAX_TTL};
+use crate::protocol::payload::Reader;
+use crate::protocol::mgmt::{MgmtAttribute, MgmtStatus};
+
+/// 配置格式版本，格式变化时递增
+///
+/// 新字段追加在管理员列表之前，`from_bytes`按字段加入的版本读取，旧版本缺少的字段取默认值。
+const CONFIG_VERSION: u8 = 10;
+
+/// 管理员列表的最大长度
+pub const MAX_ADMINS: usize = 4;
+
+/// 信标间隔的下限（毫秒），过短会挤占信道
+pub const MIN_BEACON_INTERVAL_MS: u32 = 1000;
+
+/// 选举间隔的下限（毫秒）
+const MIN_ELECTION_INTERVAL_MS: u32 = 30000;
+
+/// 路由过期时间的下限（毫秒），需大于信标间隔才能在两次信标之间保留路由
+const MIN_ROUTE_EXPIRY_MS: u32 = 10000;
+
+/// 服务目录过期时间的下限（毫秒），需大于服务信标的间隔
+const MIN_SERVICE_EXPIRY_MS: u32 = 60000;
+
+/// 邻居保活间隔的下限（毫秒），过短时HELLO会挤占信道
+const MIN_KEEPALIVE_MS: u32 = 10000;
+
+/// 默认的电量路由权重：电量耗尽的中继相当于多出的无丢包跳数
+const DEFAULT_BATTERY_WEIGHT: u8 = 2;
+
+/// 默认的电量告急阈值（百分比）
+const DEFAULT_CRITICAL_BATTERY: u8 = 10;
+
+/// 默认可预留的路径带宽（kbps）：星闪2M空口扣除信标和控制流量的余量
+pub const DEFAULT_PATH_CAPACITY_KBPS: u16 = 1600;
+
+/// 服务类型数，按类型保存的设置各占一项
+pub const SERVICE_TYPE_COUNT: usize = 7;
+
+/// 自上次读取以来变化的配置项
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+pub struct ConfigChanges {
+    /// 信道、发射功率或占空比上限，设置时已重新配置无线电
+    pub radio: bool,
+    /// 信标间隔
+    pub beacon: bool,
+    /// 选举间隔
+    pub election: bool,
+    /// 路由过期时间、默认跳数限制或电量路由参数
+    pub routing: bool,
+    /// 管理员列表
+    pub admins: bool,
+    /// 服务目录评分权重、选择策略、目录代理或服务过期时间
+    pub directory: bool,
+    /// 邻居保活间隔
+    pub keepalive: bool,
+    /// 监听模式
+    pub monitor: bool,
+    /// 可预留的路径带宽
+    pub paths: bool,
+}
+
+impl ConfigChanges {
+    /// 是否没有任何变化
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 服务目录为QoS请求挑选服务器时各项指标的权重
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoreWeights {
+    /// 带宽余量
+    pub bandwidth: u8,
+    /// 延迟余量
+    pub latency: u8,
+    /// 可靠性余量
+    pub reliability: u8,
+    /// 负载（越低越好）
+    pub load: u8,
+    /// 电池电量
+    pub battery: u8,
+    /// 每多一跳扣除的分数
+    pub hop_penalty: u8,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            bandwidth: 40,
+            latency: 30,
+            reliability: 20,
+            load: 10,
+            battery: 5,
+            hop_penalty: 10,
+        }
+    }
+}
+
+impl ScoreWeights {
+    /// 序列化后的长度
+    pub const SIZE: usize = 6;
+    
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        [self.bandwidth, self.latency, self.reliability, self.load, self.battery, self.hop_penalty]
+    }
+    
+    /// 从字节解析
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes: [u8; Self::SIZE] = bytes.get(..Self::SIZE)?.try_into().ok()?;
+        Some(Self {
+            bandwidth: bytes[0],
+            latency: bytes[1],
+            reliability: bytes[2],
+            load: bytes[3],
+            battery: bytes[4],
+            hop_penalty: bytes[5],
+        })
+    }
+    
+    /// 按服务类型调整后的权重：音视频侧重延迟，存储侧重可靠性，其他类型不变
+    pub fn for_service(&self, service_type: ServiceType) -> Self {
+        match service_type {
+            ServiceType::VideoRelay | ServiceType::AudioRelay => Self {
+                latency: self.latency.saturating_mul(2),
+                reliability: self.reliability / 2,
+                ..*self
+            },
+            ServiceType::Storage => Self {
+                reliability: self.reliability.saturating_mul(2),
+                latency: self.latency / 2,
+                ..*self
+            },
+            _ => *self,
+        }
+    }
+}
+
+/// 同一服务类型有多个服务器时的选择策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SelectionPolicy {
+    /// 总是选得分最高的服务器
+    BestScore = 0,
+    /// 在满足QoS的服务器之间轮流分配，选当前会话最少的
+    RoundRobin = 1,
+    /// 按空闲负载加权随机选择，负载越低被选中的概率越大
+    WeightedRandom = 2,
+}
+
+impl SelectionPolicy {
+    /// 从策略字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SelectionPolicy::BestScore),
+            1 => Some(SelectionPolicy::RoundRobin),
+            2 => Some(SelectionPolicy::WeightedRandom),
+            _ => None,
+        }
+    }
+    
+    /// 各服务类型的默认策略：视频中继会话带宽大，在多个服务器之间分摊，其他类型选得分最高的
+    pub fn defaults() -> [Self; SERVICE_TYPE_COUNT] {
+        core::array::from_fn(|index| match ServiceType::from_u8(index as u8 + 1) {
+            Some(ServiceType::VideoRelay) => SelectionPolicy::RoundRobin,
+            _ => SelectionPolicy::BestScore,
+        })
+    }
+}
+
+/// 关注配置变化的子系统
+pub trait ConfigObserver {
+    /// 配置变化后由主循环调用
+    fn config_changed(&mut self, config: &NodeConfig, changes: ConfigChanges);
+}
+
+/// 节点的运行配置，启动时从非易失存储加载，通过远程管理或控制台修改后立即保存
+///
+/// 主循环通过[`NodeConfig::take_changes`]取得变化的配置项并通知各子系统。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeConfig {
+    /// 节点角色，由固件决定，只读
+    pub role: NodeRole,
+    /// 信标间隔（毫秒），0表示本节点不发送信标
+    pub beacon_interval_ms: u32,
+    /// 无线信道
+    pub channel: u8,
+    /// 发射功率（dBm）
+    pub tx_power: u8,
+    /// 主节点选举间隔（毫秒）
+    pub election_interval_ms: u32,
+    /// 路由多久未刷新视为失效（毫秒）
+    pub route_expiry_ms: u32,
+    /// 服务目录条目多久未刷新视为失效（毫秒）
+    pub service_expiry_ms: u32,
+    /// 邻居保活间隔（毫秒），验证过的邻居每隔这么久重新交换一次HELLO
+    pub keepalive_ms: u32,
+    /// 发射占空比上限（千分比），0表示不限制；亚GHz频段通常要求1%
+    pub duty_cycle_permille: u16,
+    /// 服务目录评分的基础权重，各服务类型在此基础上调整
+    pub score_weights: ScoreWeights,
+    /// 监听模式：节点不发送任何数据，只统计听到的流量，用于现场勘测和排障
+    pub monitor: bool,
+    /// 目录代理：转发节点不维护服务目录，服务请求交给主节点查询并缓存结果；当选主节点时仍维护完整目录。
+    /// 目录的内存只有以`directory-proxy`特性构建的中继才能省下，这样构建的中继总是代理查询，不参加竞选
+    pub directory_proxy: bool,
+    /// 本节点新建数据包的跳数限制，设置时立即生效
+    pub default_ttl: u8,
+    /// 电量路由权重：经由邻居转发时按其缺电比例增加的路由代价，以无丢包的一跳为单位，0表示不考虑电量
+    pub battery_weight: u8,
+    /// 电量告急阈值（百分比）：转发节点电量低于该值时在信标中声明不再为其他节点中继，0表示不启用
+    pub critical_battery: u8,
+    /// 转发节点可为经过的路径预留的总带宽（kbps），应低于空口速率并为信标和控制流量留出余量
+    pub path_capacity_kbps: u16,
+    /// 各服务类型的服务器选择策略，按服务类型的编号排列
+    pub selection_policies: [SelectionPolicy; SERVICE_TYPE_COUNT],
+    /// 允许修改配置的管理员，为空时任何通过认证的请求方都可以修改
+    pub admins: [Option<NodeId>; MAX_ADMINS],
+    changes: ConfigChanges,
+}
+
+impl NodeConfig {
+    /// 序列化后的长度
+    pub const SIZE: usize = 39 + SERVICE_TYPE_COUNT + 1 + 6 * MAX_ADMINS;
+    
+    /// 各角色的默认配置
+    pub fn defaults(role: NodeRole) -> Self {
+        let beacon_interval_ms = match role {
+            NodeRole::Server => 30000,
+            NodeRole::Forward => 60000,
+            // 客户端不发送信标
+            _ => 0,
+        };
+        
+        Self {
+            role,
+            beacon_interval_ms,
+            channel: 15,
+            tx_power: 20,
+            election_interval_ms: 300000,
+            route_expiry_ms: 300000,
+            service_expiry_ms: 300000,
+            keepalive_ms: 120000,
+            duty_cycle_permille: 0,
+            score_weights: ScoreWeights::default(),
+            monitor: false,
+            directory_proxy: false,
+            default_ttl: DEFAULT_TTL,
+            battery_weight: DEFAULT_BATTERY_WEIGHT,
+            critical_battery: DEFAULT_CRITICAL_BATTERY,
+            path_capacity_kbps: DEFAULT_PATH_CAPACITY_KBPS,
+            selection_policies: SelectionPolicy::defaults(),
+            admins: [None; MAX_ADMINS],
+            changes: ConfigChanges::default(),
+        }
+    }
+    
+    /// 无线电使用的占空比限制，未设置上限时返回None
+    pub fn airtime_config(&self) -> Option<AirtimeConfig> {
+        match self.duty_cycle_permille {
+            0 => None,
+            duty_cycle_permille => Some(AirtimeConfig {
+                duty_cycle_permille,
+                ..AirtimeConfig::default()
+            }),
+        }
+    }
+    
+    /// 取出自上次调用以来变化的配置项
+    pub fn take_changes(&mut self) -> ConfigChanges {
+        core::mem::take(&mut self.changes)
+    }
+    
+    /// 序列化为字节
+    ///
+    /// 格式：版本(1) 角色(1) 信标间隔(4) 信道(1) 功率(1) 选举间隔(4) 路由过期(4) 服务过期(4) 保活间隔(4) 占空比(2) 评分权重(6) 监听(1) 目录代理(1) 跳数限制(1) 电量权重(1) 告急阈值(1) 路径带宽(2) 选择策略(7) 管理员数(1) [管理员(6)]*
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0] = CONFIG_VERSION;
+        bytes[1] = self.role as u8;
+        bytes[2..6].copy_from_slice(&self.beacon_interval_ms.to_be_bytes());
+        bytes[6] = self.channel;
+        bytes[7] = self.tx_power;
+        bytes[8..12].copy_from_slice(&self.election_interval_ms.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.route_expiry_ms.to_be_bytes());
+        bytes[16..20].copy_from_slice(&self.service_expiry_ms.to_be_bytes());
+        bytes[20..24].copy_from_slice(&self.keepalive_ms.to_be_bytes());
+        bytes[24..26].copy_from_slice(&self.duty_cycle_permille.to_be_bytes());
+        bytes[26..32].copy_from_slice(&self.score_weights.to_bytes());
+        bytes[32] = self.monitor as u8;
+        bytes[33] = self.directory_proxy as u8;
+        bytes[34] = self.default_ttl;
+        bytes[35] = self.battery_weight;
+        bytes[36] = self.critical_battery;
+        bytes[37..39].copy_from_slice(&self.path_capacity_kbps.to_be_bytes());
+        for (byte, policy) in bytes[39..46].iter_mut().zip(self.selection_policies) {
+            *byte = policy as u8;
+        }
+        
+        let mut count = 0;
+        for admin in self.admins.iter().flatten() {
+            let offset = 47 + count * 6;
+            bytes[offset..offset + 6].copy_from_slice(&admin.0);
+            count += 1;
+        }
+        bytes[46] = count as u8;
+        bytes
+    }
+    
+    /// 从字节解析，角色与固件不一致时返回None
+    ///
+    /// 升级前写入的旧版本格式同样可以解析，新加入的字段取默认值，原有配置和管理员列表保留。
+    pub fn from_bytes(bytes: &[u8], role: NodeRole) -> Option<Self> {
+        let mut reader = Reader::new(bytes);
+        let version = reader.u8()?;
+        if version == 0 || version > CONFIG_VERSION || NodeRole::from_u8(reader.u8()?) != role {
+            return None;
+        }
+        
+        let mut config = Self::defaults(role);
+        config.beacon_interval_ms = reader.u32()?;
+        config.channel = reader.u8()?;
+        config.tx_power = reader.u8()?;
+        config.election_interval_ms = reader.u32()?;
+        config.route_expiry_ms = reader.u32()?;
+        // 以下字段依次在括号中的版本加入
+        if version >= 6 {
+            config.service_expiry_ms = reader.u32()?;
+            config.keepalive_ms = reader.u32()?;
+        }
+        if version >= 2 {
+            config.duty_cycle_permille = reader.u16()?;
+        }
+        if version >= 3 {
+            config.score_weights = ScoreWeights::from_bytes(reader.bytes(ScoreWeights::SIZE)?)?;
+        }
+        if version >= 4 {
+            config.monitor = reader.u8()? != 0;
+        }
+        if version >= 5 {
+            config.directory_proxy = reader.u8()? != 0;
+        }
+        if version >= 7 {
+            config.default_ttl = reader.u8()?.clamp(1, MAX_TTL);
+        }
+        if version >= 8 {
+            config.battery_weight = reader.u8()?;
+            config.critical_battery = reader.u8()?.min(100);
+        }
+        if version >= 9 {
+            config.path_capacity_kbps = reader.u16()?;
+        }
+        if version >= 10 {
+            for policy in config.selection_policies.iter_mut() {
+                *policy = SelectionPolicy::from_u8(reader.u8()?)?;
+            }
+        }
+        
+        let count = (reader.u8()? as usize).min(MAX_ADMINS);
+        for admin in config.admins.iter_mut().take(count) {
+            let mut id = [0u8; 6];
+            id.copy_from_slice(reader.bytes(6)?);
+            *admin = Some(NodeId(id));
+        }
+        
+        Some(config)
+    }
+    
+    /// 从非易失存储加载，不存在或无法解析时使用角色的默认配置
+    pub fn load<N: NvStorage>(nvs: &mut N, role: NodeRole) -> Self {
+        let mut buffer = [0u8; Self::SIZE];
+        match nvs.nvs_read(keys::NODE_CONFIG, &mut buffer) {
+            Ok(Some(len)) => Self::from_bytes(&buffer[..len], role).unwrap_or_else(|| Self::defaults(role)),
+            _ => Self::defaults(role),
+        }
+    }
+    
+    /// 写入非易失存储
+    pub fn store<N: NvStorage>(&self, nvs: &mut N) -> Result<(), N::Error> {
+        nvs.nvs_write(keys::NODE_CONFIG, &self.to_bytes())
+    }
+    
+    /// 设置一个配置项并保存，供远程管理和控制台共用
+    pub fn set<H: Hardware>(&mut self, hardware: &mut H, attribute: MgmtAttribute, value: &[u8]) -> Result<(), MgmtStatus> {
+        let read_u32 = |min: u32| -> Result<u32, MgmtStatus> {
+            let bytes: [u8; 4] = value.try_into().map_err(|_| MgmtStatus::InvalidValue)?;
+            let number = u32::from_be_bytes(bytes);
+            if number < min {
+                return Err(MgmtStatus::InvalidValue);
+            }
+            Ok(number)
+        };
+        
+        match attribute {
+            MgmtAttribute::BeaconInterval if self.beacon_interval_ms == 0 => return Err(MgmtStatus::Unsupported),
+            MgmtAttribute::BeaconInterval => {
+                self.beacon_interval_ms = read_u32(MIN_BEACON_INTERVAL_MS)?;
+                self.changes.beacon = true;
+            },
+            MgmtAttribute::TxPower | MgmtAttribute::Channel => {
+                if value.len() != 1 {
+                    return Err(MgmtStatus::InvalidValue);
+                }
+                let (channel, tx_power) = match attribute {
+                    MgmtAttribute::Channel => (value[0], self.tx_power),
+                    _ => (self.channel, value[0]),
+                };
+                hardware.get_radio().configure(channel, tx_power).map_err(|_| MgmtStatus::InvalidValue)?;
+                self.channel = channel;
+                self.tx_power = tx_power;
+                self.changes.radio = true;
+            },
+            MgmtAttribute::DutyCycle => {
+                let bytes: [u8; 2] = value.try_into().map_err(|_| MgmtStatus::InvalidValue)?;
+                let duty_cycle_permille = u16::from_be_bytes(bytes);
+                if duty_cycle_permille > 1000 {
+                    return Err(MgmtStatus::InvalidValue);
+                }
+                let previous = self.duty_cycle_permille;
+                self.duty_cycle_permille = duty_cycle_permille;
+                if !hardware.get_radio().set_airtime_limit(self.airtime_config()) {
+                    self.duty_cycle_permille = previous;
+                    return Err(MgmtStatus::Unsupported);
+                }
+                self.changes.radio = true;
+            },
+            MgmtAttribute::ElectionInterval => {
+                self.election_interval_ms = read_u32(MIN_ELECTION_INTERVAL_MS)?;
+                self.changes.election = true;
+            },
+            MgmtAttribute::RouteExpiry => {
+                self.route_expiry_ms = read_u32(MIN_ROUTE_EXPIRY_MS)?;
+                self.changes.routing = true;
+            },
+            MgmtAttribute::ServiceExpiry => {
+                self.service_expiry_ms = read_u32(MIN_SERVICE_EXPIRY_MS)?;
+                self.changes.directory = true;
+            },
+            MgmtAttribute::KeepaliveInterval => {
+                self.keepalive_ms = read_u32(MIN_KEEPALIVE_MS)?;
+                self.changes.keepalive = true;
+            },
+            MgmtAttribute::DefaultTtl => {
+                let ttl = match value {
+                    [ttl] if (1..=MAX_TTL).contains(ttl) => *ttl,
+                    _ => return Err(MgmtStatus::InvalidValue),
+                };
+                hardware.set_default_ttl(ttl);
+                self.default_ttl = ttl;
+                self.changes.routing = true;
+            },
+            MgmtAttribute::BatteryRouting => {
+                let (weight, critical) = match value {
+                    [weight, critical] if *critical <= 100 => (*weight, *critical),
+                    _ => return Err(MgmtStatus::InvalidValue),
+                };
+                self.battery_weight = weight;
+                self.critical_battery = critical;
+                self.changes.routing = true;
+            },
+            // 只有转发节点为经过的路径预留带宽
+            MgmtAttribute::PathCapacity if self.role != NodeRole::Forward => return Err(MgmtStatus::Unsupported),
+            MgmtAttribute::PathCapacity => {
+                let bytes: [u8; 2] = value.try_into().map_err(|_| MgmtStatus::InvalidValue)?;
+                self.path_capacity_kbps = u16::from_be_bytes(bytes);
+                self.changes.paths = true;
+            },
+            // 只有转发节点为客户端挑选服务器
+            MgmtAttribute::SelectionPolicy if self.role != NodeRole::Forward => return Err(MgmtStatus::Unsupported),
+            MgmtAttribute::SelectionPolicy => {
+                let (service_type, policy) = match value {
+                    [service_type, policy] => (ServiceType::from_u8(*service_type), SelectionPolicy::from_u8(*policy)),
+                    _ => return Err(MgmtStatus::InvalidValue),
+                };
+                let (service_type, policy) = service_type.zip(policy).ok_or(MgmtStatus::InvalidValue)?;
+                self.selection_policies[service_type as usize - 1] = policy;
+                self.changes.directory = true;
+            },
+            MgmtAttribute::ScoreWeights => {
+                if value.len() != ScoreWeights::SIZE {
+                    return Err(MgmtStatus::InvalidValue);
+                }
+                self.score_weights = ScoreWeights::from_bytes(value).ok_or(MgmtStatus::InvalidValue)?;
+                self.changes.directory = true;
+            },
+            MgmtAttribute::Monitor => {
+                self.monitor = match value {
+                    [0] => false,
+                    [1] => true,
+                    _ => return Err(MgmtStatus::InvalidValue),
+                };
+                self.changes.monitor = true;
+            },
+            // 只有转发节点维护服务目录
+            MgmtAttribute::DirectoryProxy if self.role != NodeRole::Forward => return Err(MgmtStatus::Unsupported),
+            MgmtAttribute::DirectoryProxy => {
+                self.directory_proxy = match value {
+                    [0] => false,
+                    [1] => true,
+                    _ => return Err(MgmtStatus::InvalidValue),
+                };
+                self.changes.directory = true;
+            },
+            MgmtAttribute::Admins => {
+                if value.len() % 6 != 0 || value.len() / 6 > MAX_ADMINS {
+                    return Err(MgmtStatus::InvalidValue);
+                }
+                self.admins = [None; MAX_ADMINS];
+                for (admin, id) in self.admins.iter_mut().zip(value.chunks_exact(6)) {
+                    let mut bytes = [0u8; 6];
+                    bytes.copy_from_slice(id);
+                    *admin = Some(NodeId(bytes));
+                }
+                self.changes.admins = true;
+            },
+            // 日志级别只在运行时生效，不保存
+            MgmtAttribute::LogLevel => {
+                let level = value.first().and_then(|&level| Level::from_u8(level)).ok_or(MgmtStatus::InvalidValue)?;
+                log::set_level(level);
+                return Ok(());
+            },
+            MgmtAttribute::Role | MgmtAttribute::RoutingTable => return Err(MgmtStatus::ReadOnly),
+        }
+        
+        // 保存失败时配置仍然生效，只是重启后恢复旧值
+        let _ = self.store(hardware.get_nvs());
+        Ok(())
+    }
+}
+
+impl Managed for NodeConfig {
+    fn get_attribute<H: Hardware>(
+        &mut self,
+        _hardware: &mut H,
+        attribute: MgmtAttribute,
+        _arg: &[u8],
+        out: &mut [u8]
+    ) -> Result<usize, MgmtStatus> {
+        match attribute {
+            MgmtAttribute::BeaconInterval if self.beacon_interval_ms == 0 => Err(MgmtStatus::Unsupported),
+            MgmtAttribute::BeaconInterval => {
+                out[0..4].copy_from_slice(&self.beacon_interval_ms.to_be_bytes());
+                Ok(4)
+            },
+            MgmtAttribute::TxPower => {
+                out[0] = self.tx_power;
+                Ok(1)
+            },
+            MgmtAttribute::Channel => {
+                out[0] = self.channel;
+                Ok(1)
+            },
+            MgmtAttribute::LogLevel => {
+                out[0] = log::level() as u8;
+                Ok(1)
+            },
+            MgmtAttribute::ElectionInterval => {
+                out[0..4].copy_from_slice(&self.election_interval_ms.to_be_bytes());
+                Ok(4)
+            },
+            MgmtAttribute::DutyCycle => {
+                out[0..2].copy_from_slice(&self.duty_cycle_permille.to_be_bytes());
+                Ok(2)
+            },
+            MgmtAttribute::RouteExpiry => {
+                out[0..4].copy_from_slice(&self.route_expiry_ms.to_be_bytes());
+                Ok(4)
+            },
+            MgmtAttribute::ServiceExpiry => {
+                out[0..4].copy_from_slice(&self.service_expiry_ms.to_be_bytes());
+                Ok(4)
+            },
+            MgmtAttribute::KeepaliveInterval => {
+                out[0..4].copy_from_slice(&self.keepalive_ms.to_be_bytes());
+                Ok(4)
+            },
+            MgmtAttribute::ScoreWeights => {
+                out[..ScoreWeights::SIZE].copy_from_slice(&self.score_weights.to_bytes());
+                Ok(ScoreWeights::SIZE)
+            },
+            MgmtAttribute::Monitor => {
+                out[0] = self.monitor as u8;
+                Ok(1)
+            },
+            MgmtAttribute::DirectoryProxy => {
+                out[0] = self.directory_proxy as u8;
+                Ok(1)
+            },
+            MgmtAttribute::DefaultTtl => {
+                out[0] = self.default_ttl;
+                Ok(1)
+            },
+            MgmtAttribute::BatteryRouting => {
+                out[0] = self.battery_weight;
+                out[1] = self.critical_battery;
+                Ok(2)
+            },
+            MgmtAttribute::PathCapacity => {
+                out[0..2].copy_from_slice(&self.path_capacity_kbps.to_be_bytes());
+                Ok(2)
+            },
+            MgmtAttribute::SelectionPolicy => {
+                for (byte, policy) in out.iter_mut().zip(self.selection_policies) {
+                    *byte = policy as u8;
+                }
+                Ok(SERVICE_TYPE_COUNT)
+            },
+            MgmtAttribute::Admins => {
+                let mut len = 0;
+                for admin in self.admins.iter().flatten() {
+                    out[len..len + 6].copy_from_slice(&admin.0);
+                    len += 6;
+                }
+                Ok(len)
+            },
+            MgmtAttribute::Role => {
+                out[0] = self.role as u8;
+                Ok(1)
+            },
+            MgmtAttribute::RoutingTable => Err(MgmtStatus::Unsupported),
+        }
+    }
+    
+    fn set_attribute<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        attribute: MgmtAttribute,
+        value: &[u8]
+    ) -> Result<(), MgmtStatus> {
+        self.set(hardware, attribute, value)
+    }
+    
+    fn is_admin(&self, node: NodeId) -> bool {
+        self.admins.iter().all(|admin| admin.is_none()) || self.admins.contains(&Some(node))
+    }
+}
\ No newline at end of file
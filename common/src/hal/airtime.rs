@@ -0,0 +1,147 @@
+/// 占空比的统计窗口（毫秒），法规按小时计算
+pub const AIRTIME_WINDOW_MS: u64 = 3_600_000;
+
+/// 窗口划分的桶数，每桶5分钟
+const AIRTIME_BUCKETS: usize = 12;
+
+/// 每桶的时长（毫秒）
+const BUCKET_MS: u64 = AIRTIME_WINDOW_MS / AIRTIME_BUCKETS as u64;
+
+/// 已用比例超过该值（千分比）时视为预算将尽，转发节点开始让出批量流量
+pub const NEARLY_EXHAUSTED_PERMILLE: u32 = 800;
+
+/// 发射时间的计算参数和占空比上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AirtimeConfig {
+    /// 空口速率（bit/s）
+    pub bitrate_bps: u32,
+    /// 每帧固定开销（微秒），包括前导码和帧定界
+    pub overhead_us: u32,
+    /// 占空比上限（千分比），例如10表示1%
+    pub duty_cycle_permille: u16,
+}
+
+impl Default for AirtimeConfig {
+    fn default() -> Self {
+        Self {
+            bitrate_bps: 250_000,
+            overhead_us: 200,
+            duty_cycle_permille: 10,
+        }
+    }
+}
+
+impl AirtimeConfig {
+    /// 发送指定长度的帧占用的空口时间（微秒）
+    pub fn airtime_us(&self, len: usize) -> u32 {
+        let payload_us = (len as u64 * 8 * 1_000_000) / self.bitrate_bps.max(1) as u64;
+        self.overhead_us.saturating_add(payload_us.min(u32::MAX as u64) as u32)
+    }
+    
+    /// 每个窗口允许的发射时间（毫秒）
+    pub fn budget_ms(&self) -> u32 {
+        (AIRTIME_WINDOW_MS * self.duty_cycle_permille as u64 / 1000) as u32
+    }
+}
+
+/// 最近一个窗口的发射时间统计，用于遥测和转发节点的调度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AirtimeStatus {
+    /// 已用的发射时间（毫秒）
+    pub used_ms: u32,
+    /// 窗口内允许的发射时间（毫秒）
+    pub budget_ms: u32,
+}
+
+impl AirtimeStatus {
+    /// 已用比例（千分比）
+    pub fn utilization_permille(&self) -> u32 {
+        if self.budget_ms == 0 {
+            return 1000;
+        }
+        (self.used_ms as u64 * 1000 / self.budget_ms as u64).min(u32::MAX as u64) as u32
+    }
+    
+    /// 剩余的发射时间（毫秒）
+    pub fn remaining_ms(&self) -> u32 {
+        self.budget_ms.saturating_sub(self.used_ms)
+    }
+    
+    /// 预算是否将尽
+    pub fn is_nearly_exhausted(&self) -> bool {
+        self.utilization_permille() >= NEARLY_EXHAUSTED_PERMILLE
+    }
+}
+
+/// 按滑动窗口累计发射时间并执行占空比上限，供无线电后端使用
+pub struct AirtimeLimiter {
+    config: AirtimeConfig,
+    /// 每桶累计的发射时间（微秒）
+    buckets: [u32; AIRTIME_BUCKETS],
+    /// 当前桶的序号
+    current: usize,
+    /// 当前桶的起始时间
+    bucket_start: u64,
+}
+
+impl AirtimeLimiter {
+    /// 创建限制器
+    pub fn new(config: AirtimeConfig) -> Self {
+        Self {
+            config,
+            buckets: [0; AIRTIME_BUCKETS],
+            current: 0,
+            bucket_start: 0,
+        }
+    }
+    
+    /// 当前配置
+    pub fn config(&self) -> AirtimeConfig {
+        self.config
+    }
+    
+    /// 推进窗口，清空已移出窗口的桶
+    fn advance(&mut self, now: u64) {
+        let elapsed = now.saturating_sub(self.bucket_start) / BUCKET_MS;
+        if elapsed == 0 {
+            return;
+        }
+        
+        for _ in 0..elapsed.min(AIRTIME_BUCKETS as u64) {
+            self.current = (self.current + 1) % AIRTIME_BUCKETS;
+            self.buckets[self.current] = 0;
+        }
+        self.bucket_start += elapsed * BUCKET_MS;
+    }
+    
+    fn used_us(&self) -> u64 {
+        self.buckets.iter().map(|&us| us as u64).sum()
+    }
+    
+    /// 预算足够时记录一帧的发射时间并返回true，否则不记录并返回false
+    pub fn try_consume(&mut self, len: usize, now: u64) -> bool {
+        self.advance(now);
+        let airtime = self.config.airtime_us(len);
+        if self.used_us() + airtime as u64 > self.config.budget_ms() as u64 * 1000 {
+            return false;
+        }
+        self.buckets[self.current] = self.buckets[self.current].saturating_add(airtime);
+        true
+    }
+    
+    /// 无条件记录一帧的发射时间，用于不可推迟的重传
+    pub fn record(&mut self, len: usize, now: u64) {
+        self.advance(now);
+        let airtime = self.config.airtime_us(len);
+        self.buckets[self.current] = self.buckets[self.current].saturating_add(airtime);
+    }
+    
+    /// 最近一个窗口的统计
+    pub fn status(&mut self, now: u64) -> AirtimeStatus {
+        self.advance(now);
+        AirtimeStatus {
+            used_ms: (self.used_us() / 1000) as u32,
+            budget_ms: self.config.budget_ms(),
+        }
+    }
+}
\ No newline at end of file
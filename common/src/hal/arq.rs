@@ -0,0 +1,146 @@
+use crate::metrics::{self, Counter};
+use crate::pool::{NodePool, PacketBuf};
+use crate::protocol::NodeId;
+
+/// 同时等待链路确认的最大帧数，超过后放弃最早的帧
+pub const LINK_ARQ_WINDOW: usize = 4;
+
+/// 接收端记住的最近帧数，用于丢弃重传造成的重复帧
+const SEEN_FRAMES: usize = 16;
+
+/// 重复帧的判定窗口（毫秒），之后同一序号视为新帧
+const DUPLICATE_WINDOW_MS: u64 = 2000;
+
+/// 逐跳重传参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkArqConfig {
+    /// 等待链路确认的时间（毫秒）
+    pub ack_timeout_ms: u32,
+    /// 最大重传次数
+    pub max_retries: u8,
+}
+
+impl Default for LinkArqConfig {
+    fn default() -> Self {
+        Self {
+            ack_timeout_ms: 50,
+            max_retries: 2,
+        }
+    }
+}
+
+/// 等待链路确认的帧，帧内容保存在缓冲池中
+struct PendingLinkFrame {
+    destination: NodeId,
+    sequence: u8,
+    frame: PacketBuf<'static>,
+    deadline: u64,
+    retries: u8,
+}
+
+/// 链路层ARQ状态，供支持逐跳确认的无线电后端使用
+///
+/// 后端在发送单播帧时分配序号并登记，接收方收到后立即回复链路确认并过滤重复帧，
+/// 超时未确认的帧由后端在下次收发时重传，上层协议感知不到这一过程。
+pub struct LinkArq {
+    config: LinkArqConfig,
+    /// 等待确认的帧保存在本节点的缓冲池中
+    pool: &'static NodePool,
+    next_sequence: u8,
+    pending: [Option<PendingLinkFrame>; LINK_ARQ_WINDOW],
+    seen: [Option<(NodeId, u8, u64)>; SEEN_FRAMES],
+}
+
+impl LinkArq {
+    /// 创建链路层ARQ状态，等待确认的帧从指定的缓冲池取缓冲区
+    pub fn new(config: LinkArqConfig, pool: &'static NodePool) -> Self {
+        Self {
+            config,
+            pool,
+            next_sequence: 0,
+            pending: Default::default(),
+            seen: [None; SEEN_FRAMES],
+        }
+    }
+    
+    /// 登记刚发出的单播帧，返回分配的链路序号；缓冲池耗尽时帧照常发出但不会重传
+    pub fn track(&mut self, destination: NodeId, frame: &[u8], now: u64) -> u8 {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        
+        let frame = match self.pool.store(frame) {
+            Some(frame) => frame,
+            None => return sequence,
+        };
+        let pending = PendingLinkFrame {
+            destination,
+            sequence,
+            frame,
+            deadline: now + self.config.ack_timeout_ms as u64,
+            retries: 0,
+        };
+        
+        // 窗口已满时放弃最早的帧，由上层的端到端机制恢复
+        let slot = self.pending.iter().position(|entry| entry.is_none())
+            .unwrap_or_else(|| {
+                metrics::increment(Counter::LinkFailures);
+                (0..LINK_ARQ_WINDOW).min_by_key(|&i| self.pending[i].as_ref().map_or(0, |frame| frame.deadline)).unwrap_or(0)
+            });
+        self.pending[slot] = Some(pending);
+        
+        sequence
+    }
+    
+    /// 处理收到的链路确认，返回是否匹配某个等待中的帧
+    pub fn acknowledge(&mut self, from: NodeId, sequence: u8) -> bool {
+        for entry in self.pending.iter_mut() {
+            if matches!(entry, Some(frame) if frame.destination == from && frame.sequence == sequence) {
+                *entry = None;
+                return true;
+            }
+        }
+        false
+    }
+    
+    /// 重传超时的帧，超过最大重传次数的帧直接放弃
+    pub fn poll<F: FnMut(NodeId, u8, &[u8])>(&mut self, now: u64, mut resend: F) {
+        for entry in self.pending.iter_mut() {
+            let frame = match entry {
+                Some(frame) if now >= frame.deadline => frame,
+                _ => continue,
+            };
+            
+            if frame.retries >= self.config.max_retries {
+                metrics::increment(Counter::LinkFailures);
+                *entry = None;
+                continue;
+            }
+            
+            frame.retries += 1;
+            frame.deadline = now + self.config.ack_timeout_ms as u64;
+            metrics::increment(Counter::LinkRetransmissions);
+            resend(frame.destination, frame.sequence, &frame.frame);
+        }
+    }
+    
+    /// 接收端检查帧是否为重传造成的重复，不重复时记住该帧
+    pub fn is_duplicate(&mut self, source: NodeId, sequence: u8, now: u64) -> bool {
+        let duplicate = self.seen.iter().flatten().any(|&(node, seen, at)| {
+            node == source && seen == sequence && now - at < DUPLICATE_WINDOW_MS
+        });
+        if duplicate {
+            metrics::increment(Counter::LinkDuplicates);
+            return true;
+        }
+        
+        let slot = self.seen.iter().position(|entry| entry.is_none())
+            .unwrap_or_else(|| (0..SEEN_FRAMES).min_by_key(|&i| self.seen[i].map_or(0, |(_, _, at)| at)).unwrap_or(0));
+        self.seen[slot] = Some((source, sequence, now));
+        false
+    }
+    
+    /// 等待链路确认的帧数
+    pub fn pending(&self) -> usize {
+        self.pending.iter().flatten().count()
+    }
+}
\ No newline at end of file
@@ -1,21 +1,27 @@
-#[repr(C)]
-pub struct NearlinkConfig {
-    channel: u8,
-    tx_power: i8,
-    pan_id: u16,
-}
+use core::cell::Cell;
+
+use embedded_hal::blocking::i2c;
+// 日志经RTT输出
+use defmt_rtt as _;
+
+use crate::hal::{FirmwareStorage, FlashStorage, NvStorage, RadioDiagnostics, RadioInterface, ResetCause};
+use crate::hal::nearlink::{FfiSdk, Nearlink, NearlinkConfig, NlError, DATA_SECTOR_SIZE};
+use crate::metrics::{self, Counter};
+use crate::protocol::{Beacon, DataPacket, NodeId, MAX_PACKET_SIZE};
 
-extern "C" {
-    fn nl_init(config: *const NearlinkConfig) -> i32;
-    fn nl_send(dest: *const u8, data: *const u8, len: usize) -> i32;
-    fn nl_recv(buf: *mut u8, max_len: usize, actual_len: *mut usize) -> i32;
-    fn nl_configure(channel: u8, tx_power: i8) -> i32;
+/// SDK句柄，所有句柄共用同一个调用标志
+fn sdk() -> Nearlink<FfiSdk> {
+    Nearlink::new(FfiSdk)
 }
 
 pub struct BearPiHal {
     config: NearlinkConfig,
-    rx_buffer: [u8; 256],
+    sdk: Nearlink<FfiSdk>,
+    /// 已接收但还没有取走的一帧
+    rx_buffer: [u8; MAX_PACKET_SIZE],
     rx_len: usize,
+    /// 上次读取的SDK统计，用于计算增量
+    last_diagnostics: Cell<RadioDiagnostics>,
 }
 
 impl BearPiHal {
@@ -28,82 +34,275 @@ impl BearPiHal {
         
         let mut hal = Self {
             config,
-            rx_buffer: [0; 256],
+            sdk: sdk(),
+            rx_buffer: [0; MAX_PACKET_SIZE],
             rx_len: 0,
+            last_diagnostics: Cell::new(RadioDiagnostics::default()),
         };
         
         // 初始化硬件
-        unsafe {
-            nl_init(&hal.config as *const NearlinkConfig);
-        }
+        let _ = hal.sdk.init(&hal.config);
         
         hal
     }
     
-    pub fn configure(&mut self, channel: u8, tx_power: i8) -> Result<(), HalError> {
-        unsafe {
-            let ret = nl_configure(channel, tx_power);
-            if ret == 0 {
-                self.config.channel = channel;
-                self.config.tx_power = tx_power;
+    pub fn configure(&mut self, channel: u8, tx_power: i8) -> Result<(), NlError> {
+        self.sdk.configure(channel, tx_power)?;
+        self.config.channel = channel;
+        self.config.tx_power = tx_power;
+        Ok(())
+    }
+    
+    /// 复位MCU，不会返回
+    pub fn system_reset(&mut self) -> ! {
+        cortex_m::peripheral::SCB::sys_reset()
+    }
+    
+    /// 上次复位的原因，由SDK读取复位状态寄存器
+    pub fn reset_cause(&self) -> ResetCause {
+        match sdk().reset_cause() {
+            Ok(0) => ResetCause::PowerOn,
+            Ok(1) => ResetCause::Software,
+            Ok(2) => ResetCause::Watchdog,
+            _ => ResetCause::Unknown,
+        }
+    }
+    
+    /// 没有待取走的帧时从SDK接收一帧
+    fn poll_frame(&mut self) -> Result<(), NlError> {
+        if self.rx_len > 0 {
+            return Ok(());
+        }
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        match self.recv(&mut buffer) {
+            Ok(len) => {
+                self.rx_buffer = buffer;
+                self.rx_len = len;
                 Ok(())
-            } else {
-                Err(HalError::ConfigFailed)
-            }
+            },
+            Err(NlError::NoData) => Ok(()),
+            Err(e) => Err(e),
         }
     }
+    
+    /// 待取走的帧是信标时返回它
+    fn pending_beacon(&self) -> Option<Beacon> {
+        if self.rx_len != Beacon::SIZE {
+            return None;
+        }
+        Beacon::from_bytes(&self.rx_buffer[..self.rx_len])
+    }
+    
+    /// 从调试串口读取数据，没有数据时返回0
+    pub fn console_read(&mut self, buf: &mut [u8]) -> Result<usize, NlError> {
+        self.sdk.uart_read(buf)
+    }
+    
+    /// 向调试串口写入数据
+    pub fn console_write(&mut self, data: &[u8]) -> Result<(), NlError> {
+        self.sdk.uart_write(data)
+    }
 }
 
 impl HalInterface for BearPiHal {
-    fn send(&mut self, dest: &[u8; 6], data: &[u8]) -> Result<(), HalError> {
-        unsafe {
-            let ret = nl_send(dest.as_ptr(), data.as_ptr(), data.len());
-            if ret == 0 {
+    fn send(&mut self, dest: &[u8; 6], data: &[u8]) -> Result<(), NlError> {
+        match self.sdk.send(dest, data) {
+            Ok(()) => {
+                metrics::increment(Counter::RadioTx);
                 Ok(())
-            } else {
-                Err(HalError::SendFailed)
-            }
+            },
+            Err(e) => {
+                metrics::increment(Counter::RadioTxErrors);
+                Err(e)
+            },
         }
     }
     
-    fn recv(&mut self, buf: &mut [u8]) -> Result<usize, HalError> {
-        let mut actual_len: usize = 0;
-        
-        unsafe {
-            let ret = nl_recv(buf.as_mut_ptr(), buf.len(), &mut actual_len as *mut usize);
-            
-            if ret == 0 {
-                Ok(actual_len)
-            } else if ret == -1 {
-                // 没有数据可接收
-                Err(HalError::NoData)
-            } else {
-                // 其他错误
-                Err(HalError::RecvFailed)
-            }
-        }
+    fn recv(&mut self, buf: &mut [u8]) -> Result<usize, NlError> {
+        let len = self.sdk.recv(buf)?;
+        metrics::increment(Counter::RadioRx);
+        Ok(len)
     }
     
-    fn get_timestamp_ms(&self) -> Result<u64, HalError> {
-        // 获取系统时间戳
-        extern "C" {
-            fn nl_get_timestamp() -> u64;
-        }
-        
-        unsafe {
-            Ok(nl_get_timestamp())
+    fn get_timestamp_ms(&self) -> Result<u64, NlError> {
+        sdk().timestamp_ms()
+    }
+    
+    fn delay_ms(&mut self, ms: u32) -> Result<(), NlError> {
+        self.sdk.delay_ms(ms)
+    }
+}
+
+impl RadioInterface for BearPiHal {
+    type Error = NlError;
+    
+    fn send_beacon(&mut self, beacon: &Beacon) -> Result<(), Self::Error> {
+        self.send(&NodeId::BROADCAST.0, &beacon.to_bytes())
+    }
+    
+    fn send_data<'a>(&mut self, packet: &DataPacket<'a>) -> Result<(), Self::Error> {
+        self.send_data_to(NodeId(packet.header.destination), packet)
+    }
+    
+    fn send_data_to<'a>(&mut self, next_hop: NodeId, packet: &DataPacket<'a>) -> Result<(), Self::Error> {
+        let mut frame = [0u8; MAX_PACKET_SIZE];
+        let len = packet.encode(&mut frame).ok_or(NlError::BadLength)?;
+        self.send(&next_hop.0, &frame[..len])
+    }
+    
+    fn receive_beacon(&mut self) -> Result<Option<Beacon>, Self::Error> {
+        self.poll_frame()?;
+        let beacon = self.pending_beacon();
+        if beacon.is_some() {
+            self.rx_len = 0;
         }
+        Ok(beacon)
     }
     
-    fn delay_ms(&mut self, ms: u32) -> Result<(), HalError> {
-        // 延时函数
-        extern "C" {
-            fn nl_delay_ms(ms: u32);
+    fn receive_data<'a>(&mut self, buffer: &'a mut [u8]) -> Result<Option<DataPacket<'a>>, Self::Error> {
+        self.poll_frame()?;
+        if self.rx_len == 0 || self.pending_beacon().is_some() {
+            return Ok(None);
         }
         
-        unsafe {
-            nl_delay_ms(ms);
-            Ok(())
+        let len = core::mem::take(&mut self.rx_len);
+        if len > buffer.len() {
+            metrics::increment(Counter::RxOverruns);
+            return Ok(None);
         }
+        buffer[..len].copy_from_slice(&self.rx_buffer[..len]);
+        let buffer: &'a [u8] = buffer;
+        Ok(DataPacket::parse(&buffer[..len]))
+    }
+    
+    fn configure(&mut self, channel: u8, power: u8) -> Result<(), Self::Error> {
+        let tx_power = i8::try_from(power).map_err(|_| NlError::InvalidArgument)?;
+        BearPiHal::configure(self, channel, tx_power)
+    }
+    
+    fn get_rssi(&self) -> Result<i8, Self::Error> {
+        // SDK不提供信号强度
+        Ok(0)
+    }
+    
+    /// 读取SDK的累计统计，新增的部分计入全局指标；读取失败时返回上次的读数
+    fn diagnostics(&self) -> RadioDiagnostics {
+        let last = self.last_diagnostics.get();
+        let stats = match sdk().radio_stats() {
+            Ok(stats) => stats,
+            Err(_) => return last,
+        };
+        let current = RadioDiagnostics {
+            frames_sent: stats.tx_frames,
+            hw_retries: stats.tx_retries,
+            cca_failures: stats.cca_failures,
+            crc_errors: stats.crc_errors,
+            rx_overruns: stats.rx_overruns,
+        };
+        current.since(&last).publish();
+        self.last_diagnostics.set(current);
+        current
+    }
+}
+
+/// 基于SDK OTA分区的固件暂存区，切换由SDK引导程序完成
+pub struct BearPiFirmware;
+
+impl FirmwareStorage for BearPiFirmware {
+    type Error = NlError;
+    
+    fn staging_capacity(&self) -> usize {
+        sdk().ota_capacity()
+    }
+    
+    fn erase_staging(&mut self) -> Result<(), NlError> {
+        sdk().ota_erase()
+    }
+    
+    fn write_staging(&mut self, offset: u32, data: &[u8]) -> Result<(), NlError> {
+        sdk().ota_write(offset, data)
+    }
+    
+    fn read_staging(&mut self, offset: u32, buffer: &mut [u8]) -> Result<usize, NlError> {
+        sdk().ota_read(offset, buffer)
+    }
+    
+    fn mark_for_boot(&mut self, size: u32, crc: u32) -> Result<(), NlError> {
+        sdk().ota_mark_boot(size, crc)
+    }
+}
+
+/// 基于SDK数据分区的闪存，句柄不带状态，可以任意克隆
+#[derive(Clone, Copy)]
+pub struct BearPiFlash;
+
+impl FlashStorage for BearPiFlash {
+    type Error = NlError;
+    
+    fn sector_size(&self) -> usize {
+        DATA_SECTOR_SIZE
+    }
+    
+    fn sector_count(&self) -> Result<usize, NlError> {
+        Ok(sdk().data_capacity()? / DATA_SECTOR_SIZE)
+    }
+    
+    fn erase_sector(&mut self, sector: usize) -> Result<(), NlError> {
+        sdk().data_erase_sector(sector as u32)
+    }
+    
+    fn flash_write(&mut self, offset: u32, data: &[u8]) -> Result<(), NlError> {
+        sdk().data_write(offset, data)
+    }
+    
+    fn flash_read(&self, offset: u32, buffer: &mut [u8]) -> Result<(), NlError> {
+        sdk().data_read(offset, buffer)
+    }
+}
+
+/// 基于SDK NV分区的非易失存储
+pub struct BearPiNvs;
+
+impl NvStorage for BearPiNvs {
+    type Error = NlError;
+    
+    fn nvs_read(&mut self, key: u16, buffer: &mut [u8]) -> Result<Option<usize>, NlError> {
+        sdk().nvs_read(key, buffer)
+    }
+    
+    fn nvs_write(&mut self, key: u16, data: &[u8]) -> Result<(), NlError> {
+        sdk().nvs_write(key, data)
+    }
+    
+    fn nvs_erase(&mut self, key: u16) -> Result<(), NlError> {
+        sdk().nvs_erase(key)
+    }
+}
+
+/// 基于SDK I2C驱动的传感器总线
+pub struct BearPiI2c;
+
+impl i2c::Write for BearPiI2c {
+    type Error = NlError;
+    
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), NlError> {
+        sdk().i2c_write(address, bytes)
+    }
+}
+
+impl i2c::Read for BearPiI2c {
+    type Error = NlError;
+    
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), NlError> {
+        sdk().i2c_read(address, buffer)
+    }
+}
+
+impl i2c::WriteRead for BearPiI2c {
+    type Error = NlError;
+    
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), NlError> {
+        i2c::Write::write(self, address, bytes)?;
+        i2c::Read::read(self, address, buffer)
     }
 }
\ No newline at end of file
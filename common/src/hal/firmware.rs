@@ -0,0 +1,19 @@
+/// 固件暂存区接口，新镜像先写入暂存区，校验通过后交给引导程序切换
+pub trait FirmwareStorage {
+    type Error;
+    
+    /// 暂存区容量（字节）
+    fn staging_capacity(&self) -> usize;
+    
+    /// 擦除暂存区，开始接收新镜像前调用
+    fn erase_staging(&mut self) -> Result<(), Self::Error>;
+    
+    /// 在指定偏移处写入镜像数据
+    fn write_staging(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error>;
+    
+    /// 从指定偏移处读取镜像数据，返回实际读取的长度
+    fn read_staging(&mut self, offset: u32, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+    
+    /// 标记暂存区中的镜像在下次启动时由引导程序切换
+    fn mark_for_boot(&mut self, size: u32, crc: u32) -> Result<(), Self::Error>;
+}
\ No newline at end of file
@@ -0,0 +1,21 @@
+/// 数据分区接口，服务器把传感器记录直接追加到闪存，断电后历史数据仍在
+///
+/// 分区按扇区擦除，擦除后各字节为0xFF；写入只能把位从1变为0，已写入的位置擦除前不能改回。
+pub trait FlashStorage {
+    type Error;
+    
+    /// 扇区大小（字节），擦除的最小单位
+    fn sector_size(&self) -> usize;
+    
+    /// 分区中的扇区数，底层暂时不可用（如SDK忙）时返回错误
+    fn sector_count(&self) -> Result<usize, Self::Error>;
+    
+    /// 擦除一个扇区
+    fn erase_sector(&mut self, sector: usize) -> Result<(), Self::Error>;
+    
+    /// 在分区内的偏移处写入
+    fn flash_write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error>;
+    
+    /// 从分区内的偏移处读取，填满缓冲区
+    fn flash_read(&self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error>;
+}
\ No newline at end of file
@@ -1,7 +1,88 @@
+pub mod airtime;
+pub mod arq;
+#[cfg(feature = "bearpi")]
 pub mod bearpi_hi2821;
+pub mod firmware;
+pub mod flash;
+pub mod nearlink;
+pub mod nvs;
 pub mod simulator;
 
+use embedded_hal::blocking::i2c;
+
+use crate::metrics::{self, Counter};
+use crate::pool::NodePool;
 use crate::protocol::{Beacon, DataPacket, NodeId};
+use crate::security::SecurityContext;
+
+pub use airtime::{AirtimeConfig, AirtimeStatus};
+pub use arq::LinkArqConfig;
+pub use firmware::FirmwareStorage;
+pub use flash::FlashStorage;
+pub use nvs::NvStorage;
+
+/// 上次复位的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetCause {
+    /// 上电或掉电恢复
+    PowerOn,
+    /// 软件请求的复位
+    Software,
+    /// 看门狗超时
+    Watchdog,
+    /// 硬件后端不提供复位原因
+    Unknown,
+}
+
+/// 主循环读取无线电收发统计的间隔
+pub const RADIO_DIAGNOSTICS_INTERVAL_MS: u64 = 10_000;
+
+/// 无线电底层的收发统计，自启动起累计
+///
+/// 硬件重传、信道忙和CRC错误偏高说明是链路问题，与路由无关。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RadioDiagnostics {
+    /// 发出的帧数，包括硬件重传
+    pub frames_sent: u32,
+    /// 硬件自动重传次数
+    pub hw_retries: u32,
+    /// 发送前信道检测为忙而放弃的次数
+    pub cca_failures: u32,
+    /// CRC校验失败而丢弃的帧数
+    pub crc_errors: u32,
+    /// 接收缓冲区已满而丢弃的帧数
+    pub rx_overruns: u32,
+}
+
+impl RadioDiagnostics {
+    /// 相对于较早读数的增量，计数回绕时按回绕处理
+    pub fn since(&self, earlier: &RadioDiagnostics) -> RadioDiagnostics {
+        RadioDiagnostics {
+            frames_sent: self.frames_sent.wrapping_sub(earlier.frames_sent),
+            hw_retries: self.hw_retries.wrapping_sub(earlier.hw_retries),
+            cca_failures: self.cca_failures.wrapping_sub(earlier.cca_failures),
+            crc_errors: self.crc_errors.wrapping_sub(earlier.crc_errors),
+            rx_overruns: self.rx_overruns.wrapping_sub(earlier.rx_overruns),
+        }
+    }
+    
+    /// 把增量计入全局指标，用于只能读取累计值的后端；发送帧数已由发送路径计入
+    pub fn publish(&self) {
+        metrics::add(Counter::RadioRetries, self.hw_retries);
+        metrics::add(Counter::CcaFailures, self.cca_failures);
+        metrics::add(Counter::CrcErrors, self.crc_errors);
+        metrics::add(Counter::RxOverruns, self.rx_overruns);
+    }
+}
+
+/// 一帧数据的链路地址，转发的帧头部保留端到端的源和目标，链路地址是这一跳的收发双方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkAddress {
+    /// 发出这一帧的节点，即上一跳
+    pub source: NodeId,
+    /// 这一帧的下一跳，广播帧为[`NodeId::BROADCAST`]
+    pub destination: NodeId,
+}
 
 /// 无线电接口抽象
 pub trait RadioInterface {
@@ -13,6 +94,14 @@ pub trait RadioInterface {
     /// 发送数据包
     fn send_data<'a>(&mut self, packet: &DataPacket<'a>) -> Result<(), Self::Error>;
     
+    /// 把数据包发给指定的下一跳，头部的源和目标保持不变
+    ///
+    /// 默认按头部的目标发送，用于链路地址总是头部目标的后端。
+    fn send_data_to<'a>(&mut self, next_hop: NodeId, packet: &DataPacket<'a>) -> Result<(), Self::Error> {
+        let _ = next_hop;
+        self.send_data(packet)
+    }
+    
     /// 接收信标
     fn receive_beacon(&mut self) -> Result<Option<Beacon>, Self::Error>;
     
@@ -24,19 +113,101 @@ pub trait RadioInterface {
     
     /// 获取当前信号强度
     fn get_rssi(&self) -> Result<i8, Self::Error>;
+    
+    /// 底层收发统计，后端看不到的项为0
+    ///
+    /// 主循环每隔[`RADIO_DIAGNOSTICS_INTERVAL_MS`]调用一次，只能读取累计值的后端在调用时把新增的部分计入全局指标。
+    fn diagnostics(&self) -> RadioDiagnostics;
+    
+    /// 指定信道上的能量检测值（dBm），后端不支持时返回None
+    ///
+    /// 测量其他信道时可能短暂离开当前信道，期间到达的帧会丢失。
+    fn energy_detect(&mut self, channel: u8) -> Option<i8> {
+        let _ = channel;
+        None
+    }
+    
+    /// 开启或关闭单播帧的逐跳确认和重传，后端不支持时返回false
+    fn set_link_arq(&mut self, config: Option<LinkArqConfig>) -> bool {
+        let _ = config;
+        false
+    }
+    
+    /// 设置发射占空比上限，None表示不限制；后端不支持时返回false
+    ///
+    /// 超过上限时发送返回错误，由上层按发送失败处理。
+    fn set_airtime_limit(&mut self, config: Option<AirtimeConfig>) -> bool {
+        config.is_none()
+    }
+    
+    /// 最近一小时的发射时间统计，没有设置上限或后端不统计时返回None
+    fn airtime(&mut self) -> Option<AirtimeStatus> {
+        None
+    }
+    
+    /// 进入或退出只收不发的监听状态，后端不支持时返回false
+    ///
+    /// 监听时发送一律返回错误，也不回复链路确认。
+    fn set_listen_only(&mut self, listen_only: bool) -> bool {
+        !listen_only
+    }
+    
+    /// 开启或关闭混杂模式，后端不支持时返回false
+    ///
+    /// 默认只接收发给本节点和广播地址的帧，混杂模式下也接收发给其他节点的单播帧。
+    fn set_promiscuous(&mut self, promiscuous: bool) -> bool {
+        !promiscuous
+    }
+    
+    /// 最近收到的数据帧的链路地址，后端不区分链路地址和头部地址时返回None
+    fn last_link(&self) -> Option<LinkAddress> {
+        None
+    }
 }
 
 /// 硬件抽象层接口
 pub trait Hardware {
     type Error;
     type Radio: RadioInterface;
+    type Nvs: NvStorage;
+    type Firmware: FirmwareStorage;
+    /// 数据分区的句柄可以克隆，存储后端持有一份独立使用
+    type Flash: FlashStorage + Clone;
+    type I2c: i2c::Write + i2c::Read + i2c::WriteRead;
     
     /// 获取本节点ID
     fn get_node_id(&self) -> NodeId;
     
+    /// 设置本节点ID，用于启动时加载已配置的节点身份
+    fn set_node_id(&mut self, node_id: NodeId);
+    
     /// 获取无线电接口
     fn get_radio(&mut self) -> &mut Self::Radio;
     
+    /// 获取非易失存储
+    fn get_nvs(&mut self) -> &mut Self::Nvs;
+    
+    /// 获取固件暂存区
+    fn get_firmware(&mut self) -> &mut Self::Firmware;
+    
+    /// 获取数据分区
+    fn get_flash(&mut self) -> &mut Self::Flash;
+    
+    /// 获取传感器I2C总线
+    fn get_i2c(&mut self) -> &mut Self::I2c;
+    
+    /// 获取安全上下文，收发数据包时用于加密和认证
+    fn get_security(&mut self) -> &mut SecurityContext;
+    
+    /// 本节点的数据包缓冲池，发送队列、链路层重传和收发用的缓冲区都从这里取
+    fn packet_pool(&self) -> &'static NodePool;
+    
+    /// 本节点新建数据包的跳数限制
+    fn default_ttl(&self) -> u8;
+    
+    /// 设置本节点新建数据包的跳数限制，超出范围时截断到1..=MAX_TTL
+    fn set_default_ttl(&mut self, ttl: u8);
+    
     /// 获取电池电量百分比
     fn get_battery_level(&self) -> Result<u8, Self::Error>;
     
@@ -51,4 +222,18 @@ pub trait Hardware {
     
     /// 退出低功耗模式
     fn exit_low_power_mode(&mut self) -> Result<(), Self::Error>;
+    
+    /// 复位系统，真实硬件上不会返回，模拟器中只记录复位请求
+    fn system_reset(&mut self) -> Result<(), Self::Error>;
+    
+    /// 上次复位的原因
+    fn reset_cause(&self) -> ResetCause {
+        ResetCause::Unknown
+    }
+    
+    /// 从串口控制台读取数据，没有数据时返回0
+    fn console_read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+    
+    /// 向串口控制台写入数据
+    fn console_write(&mut self, data: &[u8]) -> Result<(), Self::Error>;
 } 
\ No newline at end of file
@@ -0,0 +1,427 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::protocol::MAX_PACKET_SIZE;
+
+/// 数据分区的扇区大小，与SDK一致
+pub const DATA_SECTOR_SIZE: usize = 4096;
+
+/// 无线模块初始化参数，与SDK的结构体布局一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct NearlinkConfig {
+    pub channel: u8,
+    pub tx_power: i8,
+    pub pan_id: u16,
+}
+
+/// 无线模块的累计收发统计，与SDK的结构体布局一致
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct NearlinkRadioStats {
+    pub tx_frames: u32,
+    pub tx_retries: u32,
+    pub cca_failures: u32,
+    pub crc_errors: u32,
+    pub rx_overruns: u32,
+}
+
+/// SDK调用错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+pub enum NlError {
+    /// 没有可接收的数据
+    NoData,
+    /// 参数超出范围，没有调用SDK
+    InvalidArgument,
+    /// SDK正在被另一处调用（例如中断中重入），没有调用SDK
+    Busy,
+    /// SDK报告的长度超出了缓冲区
+    BadLength,
+    /// SDK返回的其他错误码
+    Sdk(i32),
+}
+
+impl NlError {
+    /// SDK约定：0为成功，-1为没有数据或键不存在，其他负数为错误
+    fn from_code(ret: i32) -> Self {
+        match ret {
+            -1 => NlError::NoData,
+            ret => NlError::Sdk(ret),
+        }
+    }
+    
+    fn check(ret: i32) -> Result<(), NlError> {
+        match ret {
+            0 => Ok(()),
+            ret => Err(Self::from_code(ret)),
+        }
+    }
+}
+
+/// NearLink SDK的C接口
+///
+/// 方法与`nl_*`函数一一对应，返回值保持SDK的原始约定，不做任何检查；
+/// 检查统一由[`Nearlink`]完成，测试中可以换成模拟实现。
+pub trait NearlinkSdk {
+    /// 串行化SDK调用的标志，所有访问同一SDK的句柄必须共用
+    fn guard(&self) -> &AtomicBool;
+    fn init(&mut self, config: &NearlinkConfig) -> i32;
+    fn send(&mut self, dest: &[u8; 6], data: &[u8]) -> i32;
+    fn recv(&mut self, buf: &mut [u8], actual_len: &mut usize) -> i32;
+    fn configure(&mut self, channel: u8, tx_power: i8) -> i32;
+    fn radio_stats(&mut self, stats: &mut NearlinkRadioStats) -> i32;
+    fn uart_read(&mut self, buf: &mut [u8]) -> i32;
+    fn uart_write(&mut self, data: &[u8]) -> i32;
+    fn nvs_read(&mut self, key: u16, buf: &mut [u8], actual_len: &mut usize) -> i32;
+    fn nvs_write(&mut self, key: u16, data: &[u8]) -> i32;
+    fn nvs_erase(&mut self, key: u16) -> i32;
+    fn ota_capacity(&mut self) -> usize;
+    fn ota_erase(&mut self) -> i32;
+    fn ota_write(&mut self, offset: u32, data: &[u8]) -> i32;
+    fn ota_read(&mut self, offset: u32, buf: &mut [u8]) -> i32;
+    fn ota_mark_boot(&mut self, size: u32, crc: u32) -> i32;
+    fn data_capacity(&mut self) -> usize;
+    fn data_erase_sector(&mut self, sector: u32) -> i32;
+    fn data_write(&mut self, offset: u32, data: &[u8]) -> i32;
+    fn data_read(&mut self, offset: u32, buf: &mut [u8]) -> i32;
+    fn i2c_write(&mut self, addr: u8, data: &[u8]) -> i32;
+    fn i2c_read(&mut self, addr: u8, buf: &mut [u8]) -> i32;
+    fn reset_cause(&mut self) -> u8;
+    fn timestamp_ms(&mut self) -> u64;
+    fn delay_ms(&mut self, ms: u32);
+}
+
+/// SDK的安全封装：检查缓冲区长度和偏移、把返回值转换为[`NlError`]，并拒绝重入的调用
+///
+/// 所有方法都不会panic，上层不需要接触`unsafe`。
+pub struct Nearlink<S: NearlinkSdk> {
+    sdk: S,
+}
+
+impl<S: NearlinkSdk> Nearlink<S> {
+    pub fn new(sdk: S) -> Self {
+        Self { sdk }
+    }
+    
+    /// 持有调用标志时执行一次SDK调用
+    fn call<T>(&mut self, f: impl FnOnce(&mut S) -> T) -> Result<T, NlError> {
+        if self.sdk.guard().swap(true, Ordering::Acquire) {
+            return Err(NlError::Busy);
+        }
+        let result = f(&mut self.sdk);
+        self.sdk.guard().store(false, Ordering::Release);
+        Ok(result)
+    }
+    
+    /// 调用返回读取长度的函数，长度不能超过缓冲区
+    fn read_len(&mut self, max_len: usize, f: impl FnOnce(&mut S) -> i32) -> Result<usize, NlError> {
+        match self.call(f)? {
+            ret if ret < 0 => Err(NlError::from_code(ret)),
+            ret if ret as usize > max_len => Err(NlError::BadLength),
+            ret => Ok(ret as usize),
+        }
+    }
+    
+    pub fn init(&mut self, config: &NearlinkConfig) -> Result<(), NlError> {
+        NlError::check(self.call(|sdk| sdk.init(config))?)
+    }
+    
+    pub fn configure(&mut self, channel: u8, tx_power: i8) -> Result<(), NlError> {
+        NlError::check(self.call(|sdk| sdk.configure(channel, tx_power))?)
+    }
+    
+    /// 发送一帧，帧长不能超过无线帧的上限
+    pub fn send(&mut self, dest: &[u8; 6], data: &[u8]) -> Result<(), NlError> {
+        if data.is_empty() || data.len() > MAX_PACKET_SIZE {
+            return Err(NlError::InvalidArgument);
+        }
+        NlError::check(self.call(|sdk| sdk.send(dest, data))?)
+    }
+    
+    /// 接收一帧，返回长度；没有数据时返回[`NlError::NoData`]
+    pub fn recv(&mut self, buf: &mut [u8]) -> Result<usize, NlError> {
+        let max_len = buf.len();
+        let mut actual_len = 0;
+        NlError::check(self.call(|sdk| sdk.recv(buf, &mut actual_len))?)?;
+        if actual_len > max_len {
+            return Err(NlError::BadLength);
+        }
+        Ok(actual_len)
+    }
+    
+    /// 读取无线模块自初始化起的收发统计
+    pub fn radio_stats(&mut self) -> Result<NearlinkRadioStats, NlError> {
+        let mut stats = NearlinkRadioStats::default();
+        NlError::check(self.call(|sdk| sdk.radio_stats(&mut stats))?)?;
+        Ok(stats)
+    }
+    
+    /// 读取调试串口，没有数据时返回0
+    pub fn uart_read(&mut self, buf: &mut [u8]) -> Result<usize, NlError> {
+        let max_len = buf.len();
+        self.read_len(max_len, |sdk| sdk.uart_read(buf))
+    }
+    
+    pub fn uart_write(&mut self, data: &[u8]) -> Result<(), NlError> {
+        NlError::check(self.call(|sdk| sdk.uart_write(data))?)
+    }
+    
+    /// 读取一个键，不存在时返回None
+    pub fn nvs_read(&mut self, key: u16, buf: &mut [u8]) -> Result<Option<usize>, NlError> {
+        let max_len = buf.len();
+        let mut actual_len = 0;
+        match NlError::check(self.call(|sdk| sdk.nvs_read(key, buf, &mut actual_len))?) {
+            Ok(()) if actual_len > max_len => Err(NlError::BadLength),
+            Ok(()) => Ok(Some(actual_len)),
+            Err(NlError::NoData) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    pub fn nvs_write(&mut self, key: u16, data: &[u8]) -> Result<(), NlError> {
+        NlError::check(self.call(|sdk| sdk.nvs_write(key, data))?)
+    }
+    
+    pub fn nvs_erase(&mut self, key: u16) -> Result<(), NlError> {
+        NlError::check(self.call(|sdk| sdk.nvs_erase(key))?)
+    }
+    
+    /// OTA分区容量，SDK忙时返回0
+    pub fn ota_capacity(&mut self) -> usize {
+        self.call(|sdk| sdk.ota_capacity()).unwrap_or(0)
+    }
+    
+    pub fn ota_erase(&mut self) -> Result<(), NlError> {
+        NlError::check(self.call(|sdk| sdk.ota_erase())?)
+    }
+    
+    /// 写入OTA分区，范围不能超出分区
+    pub fn ota_write(&mut self, offset: u32, data: &[u8]) -> Result<(), NlError> {
+        self.check_ota_range(offset, data.len())?;
+        NlError::check(self.call(|sdk| sdk.ota_write(offset, data))?)
+    }
+    
+    /// 读取OTA分区，返回实际读取的长度
+    pub fn ota_read(&mut self, offset: u32, buf: &mut [u8]) -> Result<usize, NlError> {
+        self.check_ota_range(offset, 0)?;
+        let max_len = buf.len();
+        self.read_len(max_len, |sdk| sdk.ota_read(offset, buf))
+    }
+    
+    pub fn ota_mark_boot(&mut self, size: u32, crc: u32) -> Result<(), NlError> {
+        self.check_ota_range(0, size as usize)?;
+        NlError::check(self.call(|sdk| sdk.ota_mark_boot(size, crc))?)
+    }
+    
+    /// SDK忙时返回[`NlError::Busy`]，不当作越界
+    fn check_ota_range(&mut self, offset: u32, len: usize) -> Result<(), NlError> {
+        let capacity = self.call(|sdk| sdk.ota_capacity())?;
+        match (offset as usize).checked_add(len) {
+            Some(end) if end <= capacity => Ok(()),
+            _ => Err(NlError::InvalidArgument),
+        }
+    }
+    
+    /// 数据分区容量，SDK忙时返回[`NlError::Busy`]
+    pub fn data_capacity(&mut self) -> Result<usize, NlError> {
+        self.call(|sdk| sdk.data_capacity())
+    }
+    
+    pub fn data_erase_sector(&mut self, sector: u32) -> Result<(), NlError> {
+        let offset = sector.checked_mul(DATA_SECTOR_SIZE as u32).ok_or(NlError::InvalidArgument)?;
+        self.check_data_range(offset, DATA_SECTOR_SIZE)?;
+        NlError::check(self.call(|sdk| sdk.data_erase_sector(sector))?)
+    }
+    
+    /// 写入数据分区，范围不能超出分区
+    pub fn data_write(&mut self, offset: u32, data: &[u8]) -> Result<(), NlError> {
+        self.check_data_range(offset, data.len())?;
+        NlError::check(self.call(|sdk| sdk.data_write(offset, data))?)
+    }
+    
+    /// 读取数据分区，填满缓冲区
+    pub fn data_read(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), NlError> {
+        self.check_data_range(offset, buf.len())?;
+        NlError::check(self.call(|sdk| sdk.data_read(offset, buf))?)
+    }
+    
+    /// SDK忙时返回[`NlError::Busy`]，不当作越界
+    fn check_data_range(&mut self, offset: u32, len: usize) -> Result<(), NlError> {
+        let capacity = self.data_capacity()?;
+        match (offset as usize).checked_add(len) {
+            Some(end) if end <= capacity => Ok(()),
+            _ => Err(NlError::InvalidArgument),
+        }
+    }
+    
+    /// I2C地址为7位
+    pub fn i2c_write(&mut self, addr: u8, data: &[u8]) -> Result<(), NlError> {
+        if addr > 0x7F {
+            return Err(NlError::InvalidArgument);
+        }
+        NlError::check(self.call(|sdk| sdk.i2c_write(addr, data))?)
+    }
+    
+    pub fn i2c_read(&mut self, addr: u8, buf: &mut [u8]) -> Result<(), NlError> {
+        if addr > 0x7F {
+            return Err(NlError::InvalidArgument);
+        }
+        NlError::check(self.call(|sdk| sdk.i2c_read(addr, buf))?)
+    }
+    
+    /// 复位原因寄存器的原始值
+    pub fn reset_cause(&mut self) -> Result<u8, NlError> {
+        self.call(|sdk| sdk.reset_cause())
+    }
+    
+    pub fn timestamp_ms(&mut self) -> Result<u64, NlError> {
+        self.call(|sdk| sdk.timestamp_ms())
+    }
+    
+    pub fn delay_ms(&mut self, ms: u32) -> Result<(), NlError> {
+        self.call(|sdk| sdk.delay_ms(ms))
+    }
+}
+
+/// 调用真实SDK的实现，所有句柄共用同一个调用标志
+#[cfg(feature = "bearpi")]
+pub struct FfiSdk;
+
+#[cfg(feature = "bearpi")]
+static SDK_GUARD: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "bearpi")]
+mod ffi {
+    use super::{NearlinkConfig, NearlinkRadioStats};
+    
+    extern "C" {
+        pub fn nl_init(config: *const NearlinkConfig) -> i32;
+        pub fn nl_send(dest: *const u8, data: *const u8, len: usize) -> i32;
+        pub fn nl_recv(buf: *mut u8, max_len: usize, actual_len: *mut usize) -> i32;
+        pub fn nl_configure(channel: u8, tx_power: i8) -> i32;
+        pub fn nl_radio_stats(stats: *mut NearlinkRadioStats) -> i32;
+        pub fn nl_uart_read(buf: *mut u8, max_len: usize) -> i32;
+        pub fn nl_uart_write(data: *const u8, len: usize) -> i32;
+        pub fn nl_nvs_read(key: u16, buf: *mut u8, max_len: usize, actual_len: *mut usize) -> i32;
+        pub fn nl_nvs_write(key: u16, data: *const u8, len: usize) -> i32;
+        pub fn nl_nvs_erase(key: u16) -> i32;
+        pub fn nl_ota_capacity() -> usize;
+        pub fn nl_ota_erase() -> i32;
+        pub fn nl_ota_write(offset: u32, data: *const u8, len: usize) -> i32;
+        pub fn nl_ota_read(offset: u32, buf: *mut u8, len: usize) -> i32;
+        pub fn nl_ota_mark_boot(size: u32, crc: u32) -> i32;
+        pub fn nl_data_capacity() -> usize;
+        pub fn nl_data_erase_sector(sector: u32) -> i32;
+        pub fn nl_data_write(offset: u32, data: *const u8, len: usize) -> i32;
+        pub fn nl_data_read(offset: u32, buf: *mut u8, len: usize) -> i32;
+        pub fn nl_i2c_write(addr: u8, data: *const u8, len: usize) -> i32;
+        pub fn nl_i2c_read(addr: u8, buf: *mut u8, len: usize) -> i32;
+        pub fn nl_reset_cause() -> u8;
+        pub fn nl_get_timestamp() -> u64;
+        pub fn nl_delay_ms(ms: u32);
+    }
+}
+
+// 指针和长度都来自有效的切片或引用，SDK只在调用期间访问它们
+#[cfg(feature = "bearpi")]
+impl NearlinkSdk for FfiSdk {
+    fn guard(&self) -> &AtomicBool {
+        &SDK_GUARD
+    }
+    
+    fn init(&mut self, config: &NearlinkConfig) -> i32 {
+        unsafe { ffi::nl_init(config) }
+    }
+    
+    fn send(&mut self, dest: &[u8; 6], data: &[u8]) -> i32 {
+        unsafe { ffi::nl_send(dest.as_ptr(), data.as_ptr(), data.len()) }
+    }
+    
+    fn recv(&mut self, buf: &mut [u8], actual_len: &mut usize) -> i32 {
+        unsafe { ffi::nl_recv(buf.as_mut_ptr(), buf.len(), actual_len) }
+    }
+    
+    fn configure(&mut self, channel: u8, tx_power: i8) -> i32 {
+        unsafe { ffi::nl_configure(channel, tx_power) }
+    }
+    
+    fn radio_stats(&mut self, stats: &mut NearlinkRadioStats) -> i32 {
+        unsafe { ffi::nl_radio_stats(stats) }
+    }
+    
+    fn uart_read(&mut self, buf: &mut [u8]) -> i32 {
+        unsafe { ffi::nl_uart_read(buf.as_mut_ptr(), buf.len()) }
+    }
+    
+    fn uart_write(&mut self, data: &[u8]) -> i32 {
+        unsafe { ffi::nl_uart_write(data.as_ptr(), data.len()) }
+    }
+    
+    fn nvs_read(&mut self, key: u16, buf: &mut [u8], actual_len: &mut usize) -> i32 {
+        unsafe { ffi::nl_nvs_read(key, buf.as_mut_ptr(), buf.len(), actual_len) }
+    }
+    
+    fn nvs_write(&mut self, key: u16, data: &[u8]) -> i32 {
+        unsafe { ffi::nl_nvs_write(key, data.as_ptr(), data.len()) }
+    }
+    
+    fn nvs_erase(&mut self, key: u16) -> i32 {
+        unsafe { ffi::nl_nvs_erase(key) }
+    }
+    
+    fn ota_capacity(&mut self) -> usize {
+        unsafe { ffi::nl_ota_capacity() }
+    }
+    
+    fn ota_erase(&mut self) -> i32 {
+        unsafe { ffi::nl_ota_erase() }
+    }
+    
+    fn ota_write(&mut self, offset: u32, data: &[u8]) -> i32 {
+        unsafe { ffi::nl_ota_write(offset, data.as_ptr(), data.len()) }
+    }
+    
+    fn ota_read(&mut self, offset: u32, buf: &mut [u8]) -> i32 {
+        unsafe { ffi::nl_ota_read(offset, buf.as_mut_ptr(), buf.len()) }
+    }
+    
+    fn ota_mark_boot(&mut self, size: u32, crc: u32) -> i32 {
+        unsafe { ffi::nl_ota_mark_boot(size, crc) }
+    }
+    
+    fn data_capacity(&mut self) -> usize {
+        unsafe { ffi::nl_data_capacity() }
+    }
+    
+    fn data_erase_sector(&mut self, sector: u32) -> i32 {
+        unsafe { ffi::nl_data_erase_sector(sector) }
+    }
+    
+    fn data_write(&mut self, offset: u32, data: &[u8]) -> i32 {
+        unsafe { ffi::nl_data_write(offset, data.as_ptr(), data.len()) }
+    }
+    
+    fn data_read(&mut self, offset: u32, buf: &mut [u8]) -> i32 {
+        unsafe { ffi::nl_data_read(offset, buf.as_mut_ptr(), buf.len()) }
+    }
+    
+    fn i2c_write(&mut self, addr: u8, data: &[u8]) -> i32 {
+        unsafe { ffi::nl_i2c_write(addr, data.as_ptr(), data.len()) }
+    }
+    
+    fn i2c_read(&mut self, addr: u8, buf: &mut [u8]) -> i32 {
+        unsafe { ffi::nl_i2c_read(addr, buf.as_mut_ptr(), buf.len()) }
+    }
+    
+    fn reset_cause(&mut self) -> u8 {
+        unsafe { ffi::nl_reset_cause() }
+    }
+    
+    fn timestamp_ms(&mut self) -> u64 {
+        unsafe { ffi::nl_get_timestamp() }
+    }
+    
+    fn delay_ms(&mut self, ms: u32) {
+        unsafe { ffi::nl_delay_ms(ms) }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,124 @@
+use crate::protocol::NodeId;
+
+/// 非易失存储中使用的键
+pub mod keys {
+    /// 最近一次重启原因
+    pub const REBOOT_BREADCRUMB: u16 = 0x0001;
+    /// 客户端配置
+    pub const CLIENT_SETTINGS: u16 = 0x0002;
+    /// 入网时配置的网络密钥
+    pub const NETWORK_KEY: u16 = 0x0003;
+    /// 已预留的安全发送计数器上限
+    pub const SECURITY_COUNTER: u16 = 0x0004;
+    /// 固件更新的接收进度，用于断点续传
+    pub const OTA_PROGRESS: u16 = 0x0005;
+    /// 远程管理请求的下一个序号
+    pub const MGMT_SEQUENCE: u16 = 0x0006;
+    /// 节点运行配置
+    pub const NODE_CONFIG: u16 = 0x0007;
+    /// 各对端已预留的重放计数器上限
+    pub const SECURITY_PEERS: u16 = 0x0008;
+    /// 客户端最近成功使用的中继
+    pub const RELAY_CACHE: u16 = 0x0009;
+    /// 客户端本地样本日志的页索引
+    pub const SAMPLE_LOG_META: u16 = 0x000A;
+    /// 转发节点路由表的检查点
+    pub const ROUTING_TABLE: u16 = 0x000B;
+    /// 转发节点邻居表的检查点
+    pub const NEIGHBOR_TABLE: u16 = 0x000C;
+    /// 转发节点服务目录的检查点
+    pub const SERVICE_DIRECTORY: u16 = 0x000D;
+    /// 管理代理各请求方已接受的最大序号
+    pub const MGMT_REPLAY: u16 = 0x000E;
+    /// 离线缓冲溢出到闪存的批量，占用从该键开始的连续键
+    pub const OFFLINE_SPILL_BASE: u16 = 0x0100;
+    /// 本地样本日志的页，占用从该键开始的连续键
+    pub const SAMPLE_LOG_BASE: u16 = 0x0200;
+}
+
+/// 非易失存储接口（键值形式）
+pub trait NvStorage {
+    type Error;
+    
+    /// 读取键对应的值，返回实际长度，键不存在时返回None
+    fn nvs_read(&mut self, key: u16, buffer: &mut [u8]) -> Result<Option<usize>, Self::Error>;
+    
+    /// 写入键值
+    fn nvs_write(&mut self, key: u16, data: &[u8]) -> Result<(), Self::Error>;
+    
+    /// 删除键
+    fn nvs_erase(&mut self, key: u16) -> Result<(), Self::Error>;
+}
+
+/// 重启原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RebootReason {
+    /// 收到远程重启命令
+    Command = 0x01,
+    /// 固件更新完成后切换镜像
+    FirmwareUpdate = 0x02,
+}
+
+/// 重启前写入的记录，启动后用于确认重启原因
+#[derive(Debug, Clone, Copy)]
+pub struct RebootBreadcrumb {
+    /// 重启原因
+    pub reason: RebootReason,
+    /// 发出重启命令的节点
+    pub requested_by: NodeId,
+    /// 重启时的时间戳
+    pub timestamp: u64,
+}
+
+impl RebootBreadcrumb {
+    /// 序列化后的长度
+    pub const SIZE: usize = 15;
+    
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0] = self.reason as u8;
+        bytes[1..7].copy_from_slice(&self.requested_by.0);
+        bytes[7..15].copy_from_slice(&self.timestamp.to_be_bytes());
+        bytes
+    }
+    
+    /// 从字节解析
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::SIZE {
+            return None;
+        }
+        
+        let reason = match bytes[0] {
+            0x01 => RebootReason::Command,
+            0x02 => RebootReason::FirmwareUpdate,
+            _ => return None,
+        };
+        
+        let mut requested_by = [0u8; 6];
+        requested_by.copy_from_slice(&bytes[1..7]);
+        
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&bytes[7..15]);
+        
+        Some(Self {
+            reason,
+            requested_by: NodeId(requested_by),
+            timestamp: u64::from_be_bytes(timestamp),
+        })
+    }
+    
+    /// 写入非易失存储
+    pub fn store<N: NvStorage>(&self, nvs: &mut N) -> Result<(), N::Error> {
+        nvs.nvs_write(keys::REBOOT_BREADCRUMB, &self.to_bytes())
+    }
+    
+    /// 读取并清除上次重启留下的记录
+    pub fn take<N: NvStorage>(nvs: &mut N) -> Option<Self> {
+        let mut buffer = [0u8; Self::SIZE];
+        let len = nvs.nvs_read(keys::REBOOT_BREADCRUMB, &mut buffer).ok()??;
+        let _ = nvs.nvs_erase(keys::REBOOT_BREADCRUMB);
+        Self::from_bytes(&buffer[..len])
+    }
+}
\ No newline at end of file
@@ -1,10 +1,29 @@
-use std::collections::VecDeque;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 use std::thread;
 
-use crate::hal::{Hardware, RadioInterface};
-use crate::protocol::{Beacon, DataPacket, NodeId};
+use embedded_hal::blocking::i2c;
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::hal::{
+    AirtimeConfig, AirtimeStatus, FirmwareStorage, FlashStorage, Hardware, LinkAddress, LinkArqConfig, NvStorage, RadioDiagnostics,
+    RadioInterface,
+};
+use crate::hal::airtime::AirtimeLimiter;
+use crate::hal::arq::LinkArq;
+use crate::link_budget::LinkBudget;
+use crate::metrics::{self, Counter, Gauge};
+use crate::pool::{self, NodePool};
+use crate::protocol::{Beacon, DataPacket, NodeId, PacketType, MAX_PACKET_SIZE};
+use crate::protocol::data::{DataHeader, DEFAULT_TTL, MAX_TTL};
+use crate::security::{SecurityContext, SECURE_FLAG};
+use crate::utils::checksum::calculate_crc8;
+use crate::{info, warn};
 
 /// 模拟器错误类型
 #[derive(Debug)]
@@ -12,166 +31,1724 @@ pub enum SimulatorError {
     RadioError,
     TimerError,
     ConfigError,
+    /// 超过发射占空比上限
+    AirtimeExceeded,
+    /// 处于只收不发的监听状态
+    ListenOnly,
+    /// 节点处于低功耗模式，无线电关闭
+    Asleep,
+}
+
+/// 虚拟时钟，多个模拟节点共享，由测试按步推进，结果不依赖真实时间
+#[derive(Clone, Default)]
+pub struct SimClock {
+    now: Arc<AtomicU64>,
+}
+
+impl SimClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 当前虚拟时间（毫秒）
+    pub fn now(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+    
+    /// 推进虚拟时间
+    pub fn advance(&self, ms: u64) {
+        self.now.fetch_add(ms, Ordering::SeqCst);
+    }
+}
+
+/// 各自在线程中运行的模拟节点共享的虚拟时间
+///
+/// 每个节点线程是一个参与者，延时时登记唤醒时间并阻塞；所有参与者都在延时中时，虚拟时间直接跳到最早的唤醒时间。
+/// 运行几分钟虚拟时间的场景只花节点实际计算的时间，计时结果也不受机器负载影响。
+#[derive(Clone, Default)]
+pub struct SimScheduler {
+    clock: SimClock,
+    state: Arc<(Mutex<SchedulerState>, Condvar)>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    /// 不在延时中的参与者数
+    running: usize,
+    /// 延时中的参与者的唤醒时间
+    waiting: Vec<u64>,
+    /// 已停止推进，之后的延时不再返回
+    stopped: bool,
+    /// 已关闭，延时中的参与者以[`SimShutdown`]展开退出
+    shutdown: bool,
+}
+
+/// 调度器关闭时节点线程展开携带的负载，运行节点的线程捕获后正常退出
+#[derive(Debug)]
+pub struct SimShutdown;
+
+/// 调度器的一个参与者，丢弃时退出，不再阻止虚拟时间推进
+pub struct SimParticipant {
+    scheduler: SimScheduler,
+}
+
+impl Drop for SimParticipant {
+    fn drop(&mut self) {
+        let mut state = self.scheduler.lock();
+        state.running -= 1;
+        self.scheduler.advance(&state);
+    }
+}
+
+impl SimScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 调度器推进的虚拟时钟
+    pub fn clock(&self) -> SimClock {
+        self.clock.clone()
+    }
+    
+    fn lock(&self) -> MutexGuard<'_, SchedulerState> {
+        self.state.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    
+    /// 登记一个参与者；在启动参与者的线程之前调用，虚拟时间不会在它开始运行之前推进
+    pub fn join(&self) -> SimParticipant {
+        self.lock().running += 1;
+        SimParticipant { scheduler: self.clone() }
+    }
+    
+    /// 停止推进虚拟时间，所有参与者停在下一次延时中
+    pub fn stop(&self) {
+        self.lock().stopped = true;
+    }
+    
+    /// 关闭调度器，延时中和之后进入延时的参与者都以[`SimShutdown`]展开，节点线程随之退出
+    pub fn shutdown(&self) {
+        let mut state = self.lock();
+        state.stopped = true;
+        state.shutdown = true;
+        self.state.1.notify_all();
+    }
+    
+    /// 调度器是否已关闭
+    pub fn is_shutdown(&self) -> bool {
+        self.lock().shutdown
+    }
+    
+    /// 没有参与者在运行时把时间推进到最早的唤醒时间，并唤醒所有参与者检查自己是否到期
+    fn advance(&self, state: &SchedulerState) {
+        if state.running > 0 || state.stopped {
+            return;
+        }
+        if let Some(&next) = state.waiting.iter().min() {
+            let now = self.clock.now();
+            if next > now {
+                self.clock.advance(next - now);
+            }
+        }
+        self.state.1.notify_all();
+    }
+    
+    /// 当前参与者延时指定的虚拟时间
+    pub fn sleep(&self, ms: u64) {
+        self.sleep_until(self.clock.now() + ms);
+    }
+    
+    /// 当前参与者延时到指定的虚拟时间
+    pub fn sleep_until(&self, wake_at: u64) {
+        let mut state = self.lock();
+        state.running -= 1;
+        state.waiting.push(wake_at);
+        self.advance(&state);
+        
+        // 同一时刻到期的其他参与者还没有重新登记为运行时，时间不会越过它们的唤醒时间
+        while !state.shutdown && (state.stopped || self.clock.now() < wake_at) {
+            state = self.state.1.wait(state).unwrap_or_else(PoisonError::into_inner);
+            self.advance(&state);
+        }
+        
+        if let Some(index) = state.waiting.iter().position(|&time| time == wake_at) {
+            state.waiting.swap_remove(index);
+        }
+        state.running += 1;
+        
+        // 重新计为运行后再展开，参与者丢弃时的计数保持一致
+        if state.shutdown {
+            drop(state);
+            std::panic::resume_unwind(std::boxed::Box::new(SimShutdown));
+        }
+    }
+}
+
+/// 节点睡眠期间到达的帧如何处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepRx {
+    /// 无线电关闭，发给睡眠节点的帧丢失
+    Drop,
+    /// 信道保存发给睡眠节点的帧，醒来后再收到，模拟上游代为缓存下行数据
+    Buffer,
+}
+
+/// 一条链路的损伤参数，默认不丢包、无延迟、无误码
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LinkImpairment {
+    /// 丢包概率（0.0-1.0）
+    pub loss: f32,
+    /// 固定延迟（毫秒）
+    pub delay_ms: u32,
+    /// 在固定延迟之上附加的随机延迟上限（毫秒），会造成乱序
+    pub jitter_ms: u32,
+    /// 误码率，每个比特独立翻转的概率
+    pub bit_error_rate: f32,
+}
+
+impl LinkImpairment {
+    /// 一帧到达指定接收方的时间
+    ///
+    /// 随机延迟由入队时抽取的随机数和接收方ID决定，各接收方不同，同一接收方每次检查得到相同的延迟。
+    fn arrival(&self, sent_at: u64, draw: u64, receiver: NodeId) -> u64 {
+        let draw = receiver.0.iter().fold(draw, |x, &byte| (x ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3));
+        sent_at + self.delay_ms as u64 + (draw >> 32) % (self.jitter_ms as u64 + 1)
+    }
+}
+
+/// 模拟信道的损伤配置，作用于信标和数据帧，丢包、延迟和误码按每个接收方分别判定
+#[derive(Debug, Clone, Default)]
+pub struct SimChannelConfig {
+    /// 没有单独设置的链路使用的参数
+    pub default: LinkImpairment,
+    /// 按（源节点，接收节点）单独设置的链路参数
+    pub links: HashMap<(NodeId, NodeId), LinkImpairment>,
+    /// 随机数种子，种子相同时丢包、延迟和误码的序列相同
+    pub seed: u64,
+}
+
+impl SimChannelConfig {
+    /// 从源节点到接收节点的链路参数
+    pub fn link(&self, source: NodeId, receiver: NodeId) -> LinkImpairment {
+        self.links.get(&(source, receiver)).copied().unwrap_or(self.default)
+    }
+}
+
+/// 信道损伤的配置和随机数状态
+struct Impairment {
+    config: SimChannelConfig,
+    /// xorshift64状态
+    state: u64,
+}
+
+impl Impairment {
+    fn new(config: SimChannelConfig) -> Self {
+        let state = config.seed.max(1);
+        Self { config, state }
+    }
+    
+    fn next(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+    
+    /// 以指定概率返回true
+    fn chance(&mut self, probability: f32) -> bool {
+        if probability <= 0.0 {
+            return false;
+        }
+        ((self.next() >> 40) as f32 / (1u64 << 24) as f32) < probability
+    }
+    
+    /// 按误码率翻转比特
+    fn corrupt(&mut self, frame: &mut [u8], bit_error_rate: f32) {
+        if bit_error_rate <= 0.0 {
+            return;
+        }
+        for byte in frame.iter_mut() {
+            for bit in 0..8 {
+                if self.chance(bit_error_rate) {
+                    *byte ^= 1 << bit;
+                }
+            }
+        }
+    }
+}
+
+/// 信道队列中的信标
+struct QueuedBeacon {
+    source: NodeId,
+    beacon: Beacon,
+    /// 已经收到或丢失的节点，所有听得到的节点都收到后移出队列
+    delivered: Vec<NodeId>,
+    /// 进入信道的时间
+    sent_at: u64,
+    /// 决定随机延迟的随机数，入队时抽取
+    draw: u64,
+}
+
+/// 信道队列中的数据帧
+struct QueuedFrame {
+    source: NodeId,
+    /// 单播帧的下一跳，广播和无法解析的帧为None
+    destination: Option<NodeId>,
+    /// 已经收到或错过的节点：广播帧的所有接收方和混杂模式下旁听的节点
+    delivered: Vec<NodeId>,
+    data: Vec<u8>,
+    /// 链路序号，开启逐跳重传的发送方才带
+    sequence: Option<u8>,
+    /// 进入信道的时间
+    sent_at: u64,
+    /// 决定随机延迟的随机数，入队时抽取，同一帧每次检查得到相同的延迟
+    draw: u64,
+}
+
+/// 共享通信通道，用于在多个模拟节点之间传递消息
+#[derive(Clone)]
+pub struct SimChannel {
+    beacons: Arc<Mutex<VecDeque<QueuedBeacon>>>,
+    packets: Arc<Mutex<VecDeque<QueuedFrame>>>,
+    /// 链路确认（确认方，被确认的发送方，链路序号）
+    link_acks: Arc<Mutex<VecDeque<(NodeId, NodeId, u8)>>>,
+    /// 组播传输，None表示只在本进程内传递
+    multicast: Option<MulticastLink>,
+    /// 睡眠期间到达的帧的处理方式
+    sleep_rx: SleepRx,
+    /// 监听信道上每一帧的接收端，用于可视化和测试
+    taps: Arc<Mutex<Vec<mpsc::Sender<SimFrame>>>>,
+    /// 嗅探器的接收端，帧附带出现在信道上的时间
+    sniffers: Arc<Mutex<Vec<mpsc::Sender<CapturedFrame>>>>,
+    /// 嗅探时间戳和信道延迟使用的虚拟时钟，None时使用通道创建以来的真实时间
+    clock: Arc<Mutex<Option<SimClock>>>,
+    started: Instant,
+    /// 各无线信道上的干扰能量（dBm），没有设置的信道为底噪
+    noise: Arc<Mutex<HashMap<u8, i8>>>,
+    /// 数据帧的丢包、延迟和误码
+    impairment: Arc<Mutex<Impairment>>,
+    /// 各节点的位置（米），用于按距离计算接收信号强度
+    positions: Arc<Mutex<HashMap<NodeId, (f32, f32)>>>,
+    /// 计算接收信号强度的路径损耗模型
+    path_loss: Arc<Mutex<LinkBudget>>,
+    /// 接入本进程信道的无线电及其数量（重启时新旧实例可能短暂并存），广播帧交给其中每一个听得到的节点
+    listeners: Arc<Mutex<HashMap<NodeId, usize>>>,
+    /// 处于混杂模式的无线电，下一跳听不到的单播帧仍然交给其中听得到的节点
+    promiscuous: Arc<Mutex<HashSet<NodeId>>>,
+    /// 能听到彼此的（源节点，接收节点），None表示所有节点互相可达
+    connectivity: Arc<Mutex<Option<HashSet<(NodeId, NodeId)>>>>,
+    /// 最终目标收到的数据帧数，按（最初的发送方，目标节点）统计
+    deliveries: Arc<Mutex<HashMap<(NodeId, NodeId), u32>>>,
+    /// 数据帧最初的发送方，按（目标，包类型，包ID，流ID）对应；转发节点重写包头中的源节点
+    origins: Arc<Mutex<HashMap<FrameKey, NodeId>>>,
+}
+
+/// 同一个端到端数据包经各跳转发时不变的字段：（目标，包类型，包ID，流ID）
+type FrameKey = ([u8; 6], u8, u16, u16);
+
+fn frame_key(header: &DataHeader) -> FrameKey {
+    (header.destination, header.packet_type, header.packet_id, header.flow_id)
+}
+
+/// 模拟信道的底噪（dBm）
+pub const SIM_NOISE_FLOOR_DBM: i8 = -100;
+
+/// 信道上出现的一帧的副本
+#[derive(Debug, Clone)]
+pub enum SimFrame {
+    /// 信标：（源节点，信标）
+    Beacon(NodeId, Beacon),
+    /// 数据帧：（源节点，编码后的帧）
+    Data(NodeId, Vec<u8>),
+}
+
+/// 嗅探器捕获的一帧
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    /// 帧出现在信道上的时间（毫秒）
+    pub timestamp: u64,
+    pub frame: SimFrame,
+}
+
+impl CapturedFrame {
+    /// 线上格式的字节
+    pub fn bytes(&self) -> Vec<u8> {
+        match &self.frame {
+            SimFrame::Beacon(_, beacon) => beacon.to_bytes().to_vec(),
+            SimFrame::Data(_, frame) => frame.clone(),
+        }
+    }
+    
+    /// 发出该帧的节点
+    pub fn source(&self) -> NodeId {
+        match &self.frame {
+            SimFrame::Beacon(source, _) | SimFrame::Data(source, _) => *source,
+        }
+    }
+    
+    /// 数据帧的包类型，信标和无法解析的帧返回None
+    pub fn packet_type(&self) -> Option<PacketType> {
+        match &self.frame {
+            SimFrame::Beacon(..) => None,
+            SimFrame::Data(_, frame) => DataHeader::from_bytes(frame)
+                .and_then(|header| PacketType::from_u8(header.packet_type & !SECURE_FLAG)),
+        }
+    }
+}
+
+/// 混杂模式嗅探器，收集信道上出现的每一个信标和数据帧
+pub struct SimSniffer {
+    receiver: mpsc::Receiver<CapturedFrame>,
+    frames: Vec<CapturedFrame>,
+}
+
+impl SimSniffer {
+    /// 到目前为止捕获的所有帧，按出现顺序排列
+    pub fn frames(&mut self) -> &[CapturedFrame] {
+        self.frames.extend(self.receiver.try_iter());
+        &self.frames
+    }
+    
+    /// 清空已捕获的帧
+    pub fn clear(&mut self) {
+        self.frames.extend(self.receiver.try_iter());
+        self.frames.clear();
+    }
+    
+    /// 以文本形式导出捕获的帧，见[`text_dump`]
+    pub fn dump(&mut self) -> String {
+        text_dump(self.frames())
+    }
+}
+
+/// 类似抓包文件的文本导出：每帧一行摘要，之后是带偏移的十六进制字节
+///
+/// 摘要行为 时间(秒) 源 > 目标 类型 长度，十六进制部分与`text2pcap`的输入格式相同。
+pub fn text_dump(frames: &[CapturedFrame]) -> String {
+    let mut out = String::new();
+    for captured in frames {
+        let bytes = captured.bytes();
+        let timestamp = format!("{}.{:03}", captured.timestamp / 1000, captured.timestamp % 1000);
+        match &captured.frame {
+            SimFrame::Beacon(source, beacon) => {
+                let _ = writeln!(out, "{} {} > * Beacon hops={} seq={} len={}",
+                    timestamp, source, beacon.hop_count, beacon.sequence(), bytes.len());
+            },
+            SimFrame::Data(source, frame) => {
+                let _ = write!(out, "{} {} > ", timestamp, source);
+                match DataHeader::from_bytes(frame) {
+                    Some(header) => {
+                        let destination = NodeId(header.destination);
+                        if destination.is_broadcast() {
+                            out.push('*');
+                        } else {
+                            let _ = write!(out, "{}", destination);
+                        }
+                        match captured.packet_type() {
+                            Some(packet_type) => {
+                                let _ = write!(out, " {:?}", packet_type);
+                            },
+                            None => {
+                                let _ = write!(out, " 0x{:02x}", header.packet_type & !SECURE_FLAG);
+                            },
+                        }
+                        if header.packet_type & SECURE_FLAG != 0 {
+                            out.push_str(" secure");
+                        }
+                    },
+                    None => out.push_str("? truncated"),
+                }
+                let _ = writeln!(out, " len={}", bytes.len());
+            },
+        }
+        for (offset, chunk) in bytes.chunks(16).enumerate() {
+            let _ = write!(out, "{:04x} ", offset * 16);
+            for byte in chunk {
+                let _ = write!(out, " {:02x}", byte);
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// 组播消息类型
+const MULTICAST_BEACON: u8 = 0x01;
+const MULTICAST_FRAME: u8 = 0x02;
+const MULTICAST_LINK_FRAME: u8 = 0x03;
+const MULTICAST_LINK_ACK: u8 = 0x04;
+
+/// 局域网组播传输，独立启动的模拟器进程和第三方工具加入同一组播组即组成同一个虚拟网络
+///
+/// 每个数据报是一条消息：
+/// - 信标：`0x01` 源(6) 信标(24)
+/// - 数据帧：`0x02` 源(6) 下一跳(6) 帧
+/// - 带链路序号的数据帧：`0x03` 源(6) 下一跳(6) 序号(1) 帧
+/// - 链路确认：`0x04` 确认方(6) 被确认方(6) 序号(1)
+///
+/// 发出的数据报经组播回环也回到本进程，由接收线程统一放入本地队列，同一进程中的节点不会收到重复的帧。
+#[derive(Clone)]
+struct MulticastLink {
+    socket: Arc<UdpSocket>,
+    group: SocketAddrV4,
+}
+
+/// 组播消息中的下一跳，广播地址表示广播帧
+fn link_destination(bytes: &[u8]) -> Option<NodeId> {
+    let mut id = [0u8; 6];
+    id.copy_from_slice(bytes);
+    Some(NodeId(id)).filter(|destination| !destination.is_broadcast())
+}
+
+impl MulticastLink {
+    /// 发送一条消息：类型(1) 节点ID(6) 其余部分
+    fn send(&self, kind: u8, node: NodeId, parts: &[&[u8]]) {
+        let mut datagram = Vec::with_capacity(MAX_PACKET_SIZE + 8);
+        datagram.push(kind);
+        datagram.extend_from_slice(&node.0);
+        for part in parts {
+            datagram.extend_from_slice(part);
+        }
+        if let Err(e) = self.socket.send_to(&datagram, self.group) {
+            warn!("组播发送失败: {}", e);
+        }
+    }
+}
+
+impl SimChannel {
+    pub fn new() -> Self {
+        Self {
+            beacons: Arc::new(Mutex::new(VecDeque::new())),
+            packets: Arc::new(Mutex::new(VecDeque::new())),
+            link_acks: Arc::new(Mutex::new(VecDeque::new())),
+            multicast: None,
+            sleep_rx: SleepRx::Drop,
+            taps: Arc::new(Mutex::new(Vec::new())),
+            sniffers: Arc::new(Mutex::new(Vec::new())),
+            clock: Arc::new(Mutex::new(None)),
+            started: Instant::now(),
+            noise: Arc::new(Mutex::new(HashMap::new())),
+            impairment: Arc::new(Mutex::new(Impairment::new(SimChannelConfig::default()))),
+            positions: Arc::new(Mutex::new(HashMap::new())),
+            path_loss: Arc::new(Mutex::new(LinkBudget::default())),
+            listeners: Arc::new(Mutex::new(HashMap::new())),
+            promiscuous: Arc::new(Mutex::new(HashSet::new())),
+            connectivity: Arc::new(Mutex::new(None)),
+            deliveries: Arc::new(Mutex::new(HashMap::new())),
+            origins: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    
+    /// 设置睡眠期间到达的帧的处理方式，默认丢弃
+    pub fn with_sleep_rx(mut self, sleep_rx: SleepRx) -> Self {
+        self.sleep_rx = sleep_rx;
+        self
+    }
+    
+    /// 加入局域网组播组的通道，例如`239.255.77.1:47000`
+    pub fn multicast(group: SocketAddrV4) -> io::Result<Self> {
+        // 同一主机上的多个进程绑定同一端口
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, group.port()).into())?;
+        socket.join_multicast_v4(group.ip(), &Ipv4Addr::UNSPECIFIED)?;
+        socket.set_multicast_loop_v4(true)?;
+        let socket: UdpSocket = socket.into();
+        
+        let local = Self::new();
+        let receiver = local.clone();
+        let incoming = socket.try_clone()?;
+        thread::spawn(move || receiver.receive_multicast(incoming));
+        
+        Ok(Self {
+            multicast: Some(MulticastLink { socket: Arc::new(socket), group }),
+            ..local
+        })
+    }
+    
+    /// 设置了`AETHER_SIM_MULTICAST`（组播地址:端口）时加入组播组，否则只在本进程内传递
+    pub fn from_env() -> Self {
+        let group = match std::env::var("AETHER_SIM_MULTICAST") {
+            Ok(group) => group,
+            Err(_) => return Self::new(),
+        };
+        
+        let channel = group.parse::<SocketAddrV4>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+            .and_then(Self::multicast);
+        match channel {
+            Ok(channel) => {
+                info!("模拟信道加入组播组 {}", group);
+                channel
+            },
+            Err(e) => {
+                warn!("加入组播组 {} 失败，只在本进程内传递: {}", group, e);
+                Self::new()
+            },
+        }
+    }
+    
+    /// 接收线程：把组播组中的消息放入本地队列，无法解析的数据报直接丢弃
+    fn receive_multicast(self, socket: UdpSocket) {
+        let mut buffer = [0u8; MAX_PACKET_SIZE + 16];
+        loop {
+            let len = match socket.recv_from(&mut buffer) {
+                Ok((len, _)) => len,
+                Err(e) => {
+                    warn!("组播接收失败: {}", e);
+                    return;
+                },
+            };
+            if len < 7 {
+                continue;
+            }
+            
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&buffer[1..7]);
+            let node = NodeId(id);
+            let body = &buffer[7..len];
+            match buffer[0] {
+                MULTICAST_BEACON => {
+                    if let Some(beacon) = Beacon::from_bytes(body) {
+                        self.push_beacon(node, beacon);
+                    }
+                },
+                MULTICAST_FRAME if body.len() >= 6 => {
+                    self.push_link_frame(node, link_destination(&body[..6]), &body[6..], None);
+                },
+                MULTICAST_LINK_FRAME if body.len() >= 7 => {
+                    self.push_link_frame(node, link_destination(&body[..6]), &body[7..], Some(body[6]));
+                },
+                MULTICAST_LINK_ACK if body.len() == 7 => {
+                    let mut to = [0u8; 6];
+                    to.copy_from_slice(&body[..6]);
+                    self.push_link_ack(node, NodeId(to), body[6]);
+                },
+                _ => {},
+            }
+        }
+    }
+    
+    /// 设置某个无线信道上的干扰能量，模拟外部干扰源；只影响能量检测，帧照常传递
+    pub fn set_noise(&self, channel: u8, energy_dbm: i8) {
+        if let Ok(mut noise) = self.noise.lock() {
+            noise.insert(channel, energy_dbm);
+        }
+    }
+    
+    /// 设置数据帧的丢包、延迟和误码，随机数从配置的种子重新开始
+    pub fn set_config(&self, config: SimChannelConfig) {
+        if let Ok(mut impairment) = self.impairment.lock() {
+            *impairment = Impairment::new(config);
+        }
+    }
+    
+    /// 单独设置从源节点到接收节点的链路参数
+    pub fn set_link(&self, source: NodeId, receiver: NodeId, link: LinkImpairment) {
+        if let Ok(mut impairment) = self.impairment.lock() {
+            impairment.config.links.insert((source, receiver), link);
+        }
+    }
+    
+    /// 设置节点的位置（米）
+    pub fn set_position(&self, node: NodeId, x: f32, y: f32) {
+        if let Ok(mut positions) = self.positions.lock() {
+            positions.insert(node, (x, y));
+        }
+    }
+    
+    /// 设置按距离计算接收信号强度的路径损耗模型，默认为[`LinkBudget::default`]
+    pub fn set_path_loss(&self, model: LinkBudget) {
+        if let Ok(mut path_loss) = self.path_loss.lock() {
+            *path_loss = model;
+        }
+    }
+    
+    /// 设置能听到彼此的（源节点，接收节点），None恢复为所有节点互相可达
+    ///
+    /// 用于构造必须经过转发才能到达的拓扑。
+    pub fn set_connectivity(&self, links: Option<HashSet<(NodeId, NodeId)>>) {
+        if let Ok(mut connectivity) = self.connectivity.lock() {
+            *connectivity = links;
+        }
+    }
+    
+    /// 在连通关系中加入一条双向链路；之前所有节点互相可达时，之后只有加入的链路可达
+    pub fn connect(&self, a: NodeId, b: NodeId) {
+        if let Ok(mut connectivity) = self.connectivity.lock() {
+            let links = connectivity.get_or_insert_with(HashSet::new);
+            links.insert((a, b));
+            links.insert((b, a));
+        }
+    }
+    
+    /// 记录数据帧最初的发送方，转发的帧沿用第一次发出时的发送方
+    fn record_origin(&self, sender: NodeId, frame: &[u8]) {
+        if let (Some(header), Ok(mut origins)) = (DataHeader::from_bytes(frame), self.origins.lock()) {
+            origins.entry(frame_key(&header)).or_insert(sender);
+        }
+    }
+    
+    /// 数据帧交给了以它为最终目标的节点时，按最初的发送方计入端到端送达
+    fn record_delivery(&self, receiver: NodeId, frame: &[u8]) {
+        let header = match DataHeader::from_bytes(frame) {
+            Some(header) if NodeId(header.destination) == receiver => header,
+            _ => return,
+        };
+        let origin = self.origins.lock().ok()
+            .and_then(|origins| origins.get(&frame_key(&header)).copied())
+            .unwrap_or(NodeId(header.source));
+        if let Ok(mut deliveries) = self.deliveries.lock() {
+            *deliveries.entry((origin, receiver)).or_insert(0) += 1;
+        }
+    }
+    
+    /// 目标节点的无线电收到的、由源节点发出并以目标节点为最终目标的数据帧数，经过几跳都算
+    pub fn delivered(&self, source: NodeId, destination: NodeId) -> u32 {
+        self.deliveries.lock().ok()
+            .and_then(|deliveries| deliveries.get(&(source, destination)).copied())
+            .unwrap_or(0)
+    }
+    
+    /// 接收节点能否听到源节点
+    pub fn can_hear(&self, source: NodeId, receiver: NodeId) -> bool {
+        source != receiver && self.connectivity.lock().map_or(true, |connectivity| {
+            connectivity.as_ref().map_or(true, |links| links.contains(&(source, receiver)))
+        })
+    }
+    
+    /// 登记或取消无线电的混杂模式
+    fn set_promiscuous(&self, node: NodeId, promiscuous: bool) {
+        if let Ok(mut nodes) = self.promiscuous.lock() {
+            if promiscuous {
+                nodes.insert(node);
+            } else {
+                nodes.remove(&node);
+            }
+        }
+    }
+    
+    /// 听得到源节点的混杂模式无线电是否都已收到
+    fn overheard_by_all(&self, source: NodeId, delivered: &[NodeId]) -> bool {
+        self.promiscuous.lock().map_or(true, |nodes| {
+            nodes.iter().all(|&node| delivered.contains(&node) || !self.can_hear(source, node))
+        })
+    }
+    
+    /// 登记接入信道的无线电
+    fn register(&self, node: NodeId) {
+        if let Ok(mut listeners) = self.listeners.lock() {
+            *listeners.entry(node).or_insert(0) += 1;
+        }
+    }
+    
+    /// 取消登记
+    fn unregister(&self, node: NodeId) {
+        if let Ok(mut listeners) = self.listeners.lock() {
+            if let Some(count) = listeners.get_mut(&node) {
+                *count -= 1;
+                if *count == 0 {
+                    listeners.remove(&node);
+                }
+            }
+        }
+    }
+    
+    /// 本进程中没有其他听得到源节点的无线电，或者都已经收到
+    ///
+    /// 没有登记任何无线电时（直接操作信道的测试）第一次被取走即算送达。
+    fn heard_by_all(&self, source: NodeId, delivered: &[NodeId]) -> bool {
+        let listeners: Vec<NodeId> = match self.listeners.lock() {
+            Ok(listeners) => listeners.keys().copied().collect(),
+            Err(_) => return true,
+        };
+        listeners.iter().all(|&node| delivered.contains(&node) || !self.can_hear(source, node))
+    }
+    
+    /// 帧能否被本进程中的节点收到，收不到的帧在信道入口直接丢弃
+    ///
+    /// 单播帧要求下一跳在本进程中且听得到源节点，或者有混杂模式的节点听得到；广播帧要求至少有一个节点听得到。
+    fn reaches(&self, source: NodeId, destination: Option<NodeId>) -> bool {
+        let listeners: Vec<NodeId> = match self.listeners.lock() {
+            Ok(listeners) => listeners.keys().copied().collect(),
+            Err(_) => return true,
+        };
+        match destination {
+            Some(destination) => self.next_hop_hears(source, destination) || !self.overheard_by_all(source, &[]),
+            None => listeners.is_empty() || listeners.iter().any(|&node| self.can_hear(source, node)),
+        }
+    }
+    
+    /// 单播帧的下一跳在本进程中且听得到源节点；没有登记任何无线电时只看连通关系
+    fn next_hop_hears(&self, source: NodeId, destination: NodeId) -> bool {
+        let registered = self.listeners.lock()
+            .map_or(true, |listeners| listeners.is_empty() || listeners.contains_key(&destination));
+        registered && self.can_hear(source, destination)
+    }
+    
+    /// 按对数距离路径损耗模型计算接收节点收到源节点信号的强度（dBm），任一节点没有位置时返回None
+    ///
+    /// 不足1米按1米计算。
+    pub fn rssi(&self, source: NodeId, receiver: NodeId) -> Option<i8> {
+        let positions = self.positions.lock().ok()?;
+        let (sx, sy) = *positions.get(&source)?;
+        let (rx, ry) = *positions.get(&receiver)?;
+        let model = *self.path_loss.lock().ok()?;
+        
+        let distance = ((sx - rx).powi(2) + (sy - ry).powi(2)).sqrt().max(1.0);
+        let loss = model.reference_loss_db + 10.0 * model.path_loss_exponent * distance.log10();
+        let rssi = (model.tx_power_dbm as f32 - loss).round();
+        Some(rssi.clamp(i8::MIN as f32, i8::MAX as f32) as i8)
+    }
+    
+    /// 某个无线信道上的能量
+    pub fn noise(&self, channel: u8) -> i8 {
+        self.noise.lock().ok()
+            .and_then(|noise| noise.get(&channel).copied())
+            .unwrap_or(SIM_NOISE_FLOOR_DBM)
+    }
+    
+    /// 监听信道，之后进入本地队列的每一帧都复制一份发给返回的接收端
+    ///
+    /// 组播信道中包括其他进程发出的帧；接收端被丢弃后自动停止复制。
+    pub fn tap(&self) -> mpsc::Receiver<SimFrame> {
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut taps) = self.taps.lock() {
+            taps.push(sender);
+        }
+        receiver
+    }
+    
+    /// 挂接混杂模式嗅探器，之后信道上出现的每一帧都附带时间戳复制给嗅探器
+    ///
+    /// 与[`SimChannel::tap`]一样包括组播信道中其他进程发出的帧；嗅探器被丢弃后自动停止复制。
+    pub fn attach_sniffer(&self) -> SimSniffer {
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut sniffers) = self.sniffers.lock() {
+            sniffers.push(sender);
+        }
+        SimSniffer { receiver, frames: Vec::new() }
+    }
+    
+    /// 嗅探时间戳和信道延迟改用虚拟时钟，与节点的虚拟时间一致
+    pub fn set_clock(&self, clock: SimClock) {
+        if let Ok(mut current) = self.clock.lock() {
+            *current = Some(clock);
+        }
+    }
+    
+    fn now_ms(&self) -> u64 {
+        match self.clock.lock().ok().and_then(|clock| clock.clone()) {
+            Some(clock) => clock.now(),
+            None => self.started.elapsed().as_millis() as u64,
+        }
+    }
+    
+    fn copy_to_taps(&self, frame: SimFrame) {
+        if let Ok(mut sniffers) = self.sniffers.lock() {
+            if !sniffers.is_empty() {
+                let captured = CapturedFrame { timestamp: self.now_ms(), frame: frame.clone() };
+                sniffers.retain(|sniffer| sniffer.send(captured.clone()).is_ok());
+            }
+        }
+        if let Ok(mut taps) = self.taps.lock() {
+            taps.retain(|tap| tap.send(frame.clone()).is_ok());
+        }
+    }
+    
+    pub fn push_beacon(&self, source: NodeId, beacon: Beacon) {
+        if let Some(link) = &self.multicast {
+            link.send(MULTICAST_BEACON, source, &[&beacon.to_bytes()]);
+            return;
+        }
+        
+        self.copy_to_taps(SimFrame::Beacon(source, beacon));
+        if !self.reaches(source, None) {
+            return;
+        }
+        let draw = self.impairment.lock().map_or(0, |mut impairment| impairment.next());
+        let queued = QueuedBeacon { source, beacon, delivered: Vec::new(), sent_at: self.now_ms(), draw };
+        if let Ok(mut beacons) = self.beacons.lock() {
+            beacons.push_back(queued);
+        }
+    }
+    
+    /// 放入一帧，下一跳为头部的目标
+    pub fn push_packet(&self, source: NodeId, data: &[u8], len: usize) {
+        let destination = DataHeader::from_bytes(data)
+            .map(|header| NodeId(header.destination))
+            .filter(|destination| !destination.is_broadcast());
+        self.push_link_frame(source, destination, &data[..len], None);
+    }
+    
+    /// 放入发给下一跳的帧，None为广播；带链路序号的帧接收方需要回复链路确认
+    pub fn push_link_frame(&self, source: NodeId, destination: Option<NodeId>, frame: &[u8], sequence: Option<u8>) {
+        if let Some(link) = &self.multicast {
+            let next_hop = destination.unwrap_or(NodeId::BROADCAST);
+            match sequence {
+                Some(sequence) => link.send(MULTICAST_LINK_FRAME, source, &[&next_hop.0, &[sequence], frame]),
+                None => link.send(MULTICAST_FRAME, source, &[&next_hop.0, frame]),
+            }
+            return;
+        }
+        
+        self.copy_to_taps(SimFrame::Data(source, frame.to_vec()));
+        self.record_origin(source, frame);
+        if !self.reaches(source, destination) {
+            return;
+        }
+        
+        let draw = self.impairment.lock().map_or(0, |mut impairment| impairment.next());
+        let queued = QueuedFrame {
+            source,
+            destination,
+            delivered: Vec::new(),
+            data: frame.to_vec(),
+            sequence,
+            sent_at: self.now_ms(),
+            draw,
+        };
+        if let Ok(mut packets) = self.packets.lock() {
+            packets.push_back(queued);
+            metrics::set(Gauge::RxQueue, packets.len() as u32);
+        }
+    }
+    
+    /// 回复链路确认
+    pub fn push_link_ack(&self, from: NodeId, to: NodeId, sequence: u8) {
+        if let Some(link) = &self.multicast {
+            link.send(MULTICAST_LINK_ACK, from, &[&to.0, &[sequence]]);
+            return;
+        }
+        
+        if let Ok(mut acks) = self.link_acks.lock() {
+            acks.push_back((from, to, sequence));
+        }
+    }
+    
+    /// 取出发给指定节点的一个链路确认：（确认方，链路序号）
+    pub fn take_link_ack(&self, dest: NodeId) -> Option<(NodeId, u8)> {
+        let mut acks = self.link_acks.lock().ok()?;
+        let index = acks.iter().position(|(_, to, _)| *to == dest)?;
+        acks.remove(index).map(|(from, _, sequence)| (from, sequence))
+    }
+    
+    pub fn get_beacon(&self, dest: NodeId) -> Option<Beacon> {
+        self.get_link_beacon(dest).map(|(_, beacon)| beacon)
+    }
+    
+    /// 取出一个信标：（发出信标的节点，信标），转发的信标中源节点与发出节点不同
+    ///
+    /// 每个听得到的节点各收到一次，所有节点都收到后移出队列。与数据帧一样按信道配置延迟、丢弃或加入误码，
+    /// 误码后无法解析的信标按丢失处理。
+    pub fn get_link_beacon(&self, dest: NodeId) -> Option<(NodeId, Beacon)> {
+        let now = self.now_ms();
+        let mut impairment = self.impairment.lock().ok()?;
+        let mut beacons = self.beacons.lock().ok()?;
+        
+        let mut i = 0;
+        while i < beacons.len() {
+            // 忽略自己发送的、听不到的、已经收到过的和还在路上的信标
+            let queued = &beacons[i];
+            let link = impairment.config.link(queued.source, dest);
+            if !self.can_hear(queued.source, dest) || queued.delivered.contains(&dest)
+                || now < link.arrival(queued.sent_at, queued.draw, dest) {
+                i += 1;
+                continue;
+            }
+            
+            let queued = &mut beacons[i];
+            queued.delivered.push(dest);
+            let (source, mut bytes) = (queued.source, queued.beacon.to_bytes());
+            if self.heard_by_all(source, &queued.delivered) {
+                beacons.remove(i);
+            } else {
+                i += 1;
+            }
+            if impairment.chance(link.loss) {
+                continue;
+            }
+            
+            impairment.corrupt(&mut bytes, link.bit_error_rate);
+            if let Some(beacon) = Beacon::from_bytes(&bytes) {
+                return Some((source, beacon));
+            }
+        }
+        None
+    }
+    
+    /// 丢弃发给指定节点的所有帧，排队中的广播帧算作该节点已经错过，返回丢弃的数量
+    pub fn discard_frames_for(&self, dest: NodeId) -> usize {
+        let mut packets = match self.packets.lock() {
+            Ok(packets) => packets,
+            Err(_) => return 0,
+        };
+        
+        let mut dropped = 0;
+        packets.retain_mut(|frame| {
+            match frame.destination {
+                Some(destination) if destination == dest => {},
+                Some(_) => return true,
+                None => {
+                    if !self.can_hear(frame.source, dest) || frame.delivered.contains(&dest) {
+                        return true;
+                    }
+                    frame.delivered.push(dest);
+                    if !self.heard_by_all(frame.source, &frame.delivered) {
+                        dropped += 1;
+                        return true;
+                    }
+                },
+            }
+            dropped += 1;
+            false
+        });
+        metrics::set(Gauge::RxQueue, packets.len() as u32);
+        dropped
+    }
+    
+    pub fn get_packet(&self, dest: NodeId, buffer: &mut [u8]) -> Option<usize> {
+        self.get_link_frame(dest, buffer).map(|(_, len, _)| len)
+    }
+    
+    /// 取出一帧：（源节点，长度，链路序号），只接收发给本节点的单播帧和广播帧
+    pub fn get_link_frame(&self, dest: NodeId, buffer: &mut [u8]) -> Option<(NodeId, usize, Option<u8>)> {
+        self.receive_frame(dest, false, buffer).map(|(link, len, sequence)| (link.source, len, sequence))
+    }
+    
+    /// 取出一帧：（链路地址，长度，链路序号）
+    ///
+    /// 按信道配置和接收方，延迟未到的帧留在队列中，交出的帧可能带有误码。单播帧由下一跳取走，
+    /// 广播帧每个听得到的节点各收到一次；混杂模式下还能旁听发给其他节点的单播帧，旁听不会取走帧，
+    /// 下一跳听不到的单播帧在旁听的节点都收到后移出队列。
+    pub fn receive_frame(&self, dest: NodeId, promiscuous: bool, buffer: &mut [u8]) -> Option<(LinkAddress, usize, Option<u8>)> {
+        let now = self.now_ms();
+        let mut impairment = self.impairment.lock().ok()?;
+        let mut packets = self.packets.lock().ok()?;
+        
+        // 找到第一个已经到达的数据包
+        let mut i = 0;
+        while i < packets.len() {
+            let frame = &packets[i];
+            // 忽略自己发送的、听不到的、已经收到过的和发给其他节点的数据包
+            let addressed = frame.destination.map_or(true, |destination| destination == dest);
+            if !self.can_hear(frame.source, dest) || frame.delivered.contains(&dest) || !(addressed || promiscuous)
+                || frame.data.len() > buffer.len() {
+                i += 1;
+                continue;
+            }
+            
+            let link = impairment.config.link(frame.source, dest);
+            if now < link.arrival(frame.sent_at, frame.draw, dest) {
+                i += 1;
+                continue;
+            }
+            
+            // 单播帧由下一跳取走，广播帧所有节点都收到后移出队列
+            let frame = &mut packets[i];
+            frame.delivered.push(dest);
+            let (source, sequence, len) = (frame.source, frame.sequence, frame.data.len());
+            let address = LinkAddress { source, destination: frame.destination.unwrap_or(NodeId::BROADCAST) };
+            let lost = impairment.chance(link.loss);
+            if !lost {
+                buffer[..len].copy_from_slice(&frame.data);
+            }
+            let done = match frame.destination {
+                Some(destination) => {
+                    destination == dest
+                        || (!self.next_hop_hears(source, destination) && self.overheard_by_all(source, &frame.delivered))
+                },
+                None => self.heard_by_all(source, &frame.delivered),
+            };
+            if done {
+                packets.remove(i);
+                metrics::set(Gauge::RxQueue, packets.len() as u32);
+            } else {
+                i += 1;
+            }
+            if lost {
+                continue;
+            }
+            
+            impairment.corrupt(&mut buffer[..len], link.bit_error_rate);
+            self.record_delivery(dest, &buffer[..len]);
+            return Some((address, len, sequence));
+        }
+        None
+    }
+}
+
+/// 模拟无线电接口
+pub struct SimRadio {
+    channel: u8,
+    power: u8,
+    sim_channel: SimChannel,
+    node_id: NodeId,
+    /// 逐跳重传状态，接收方向总是回复确认并过滤重复帧
+    link_arq: LinkArq,
+    /// 发送单播帧时是否等待链路确认
+    link_arq_enabled: bool,
+    /// 发射占空比限制，None表示不限制
+    airtime: Option<AirtimeLimiter>,
+    /// 只收不发
+    listen_only: bool,
+    /// 节点处于低功耗模式，不收不发
+    asleep: bool,
+    started: Instant,
+    /// 虚拟时钟，None时使用真实时间
+    clock: Option<SimClock>,
+    /// 本节点的收发统计；模拟信道没有载波侦听，队列也不限长度，信道忙和接收溢出总是0
+    diagnostics: RadioDiagnostics,
+    /// 最近一帧的发出节点，用于计算接收信号强度
+    last_source: Option<NodeId>,
+    /// 最近一个数据帧的链路地址
+    last_link: Option<LinkAddress>,
+    /// 混杂模式，旁听发给其他节点的单播帧
+    promiscuous: bool,
+    /// 本节点的缓冲池，各模拟节点互不影响
+    pool: &'static NodePool,
+}
+
+impl SimRadio {
+    pub fn new(sim_channel: SimChannel, node_id: NodeId) -> Self {
+        sim_channel.register(node_id);
+        let pool = pool::node_pool();
+        Self {
+            channel: 11,
+            power: 20,
+            sim_channel,
+            node_id,
+            link_arq: LinkArq::new(LinkArqConfig::default(), pool),
+            link_arq_enabled: false,
+            airtime: None,
+            listen_only: false,
+            asleep: false,
+            started: Instant::now(),
+            clock: None,
+            diagnostics: RadioDiagnostics::default(),
+            last_source: None,
+            last_link: None,
+            promiscuous: false,
+            pool,
+        }
+    }
+    
+    fn now_ms(&self) -> u64 {
+        match &self.clock {
+            Some(clock) => clock.now(),
+            None => self.started.elapsed().as_millis() as u64,
+        }
+    }
+    
+    /// 处理收到的链路确认并重传超时的帧，每次收发时调用
+    fn service_link_arq(&mut self) {
+        let now = self.now_ms();
+        while let Some((from, sequence)) = self.sim_channel.take_link_ack(self.node_id) {
+            self.link_arq.acknowledge(from, sequence);
+        }
+        
+        // 监听时连重传也不发送，未确认的帧等退出监听后再重传
+        if self.listen_only {
+            return;
+        }
+        
+        // 重传不能推迟，发射时间照样计入占空比；逐跳重传相当于硬件的自动重传
+        let (sim_channel, node_id, airtime) = (&self.sim_channel, self.node_id, &mut self.airtime);
+        let diagnostics = &mut self.diagnostics;
+        self.link_arq.poll(now, |destination, sequence, frame| {
+            if let Some(limiter) = airtime.as_mut() {
+                limiter.record(frame.len(), now);
+            }
+            sim_channel.push_link_frame(node_id, Some(destination), frame, Some(sequence));
+            diagnostics.frames_sent += 1;
+            diagnostics.hw_retries += 1;
+            metrics::increment(Counter::RadioTx);
+            metrics::increment(Counter::RadioRetries);
+        });
+    }
+    
+    /// 进入或退出睡眠；按丢弃方式处理时，睡眠期间发给本节点的帧在醒来时丢弃
+    fn set_asleep(&mut self, asleep: bool) {
+        if self.asleep && !asleep && self.sim_channel.sleep_rx == SleepRx::Drop {
+            let dropped = self.sim_channel.discard_frames_for(self.node_id);
+            if dropped > 0 {
+                info!("节点 {} 睡眠期间丢失 {} 帧", self.node_id, dropped);
+            }
+        }
+        self.asleep = asleep;
+    }
+    
+    /// 按占空比上限登记一帧的发射时间，超过上限时返回错误
+    fn consume_airtime(&mut self, len: usize) -> Result<(), SimulatorError> {
+        let now = self.now_ms();
+        if let Some(limiter) = self.airtime.as_mut() {
+            if !limiter.try_consume(len, now) {
+                metrics::increment(Counter::AirtimeDeferred);
+                return Err(SimulatorError::AirtimeExceeded);
+            }
+            metrics::set(Gauge::AirtimeUsed, limiter.status(now).used_ms);
+        }
+        Ok(())
+    }
+}
+
+impl RadioInterface for SimRadio {
+    type Error = SimulatorError;
+    
+    fn send_beacon(&mut self, beacon: &Beacon) -> Result<(), Self::Error> {
+        if self.asleep {
+            return Err(SimulatorError::Asleep);
+        }
+        if self.listen_only {
+            return Err(SimulatorError::ListenOnly);
+        }
+        self.consume_airtime(Beacon::SIZE)?;
+        self.sim_channel.push_beacon(self.node_id, *beacon);
+        self.diagnostics.frames_sent += 1;
+        metrics::increment(Counter::BeaconsTx);
+        Ok(())
+    }
+    
+    fn send_data<'a>(&mut self, packet: &DataPacket<'a>) -> Result<(), Self::Error> {
+        self.send_data_to(NodeId(packet.header.destination), packet)
+    }
+    
+    fn send_data_to<'a>(&mut self, next_hop: NodeId, packet: &DataPacket<'a>) -> Result<(), Self::Error> {
+        if self.asleep {
+            return Err(SimulatorError::Asleep);
+        }
+        if self.listen_only {
+            return Err(SimulatorError::ListenOnly);
+        }
+        
+        // 模拟发送数据，实际上是将数据放入共享通道；编码用的缓冲区取自缓冲池
+        let mut buffer = self.pool.acquire().ok_or(SimulatorError::RadioError)?;
+        let total_len = packet.encode(buffer.as_mut_slice()).ok_or(SimulatorError::RadioError)?;
+        buffer.set_len(total_len);
+        
+        self.service_link_arq();
+        self.consume_airtime(total_len)?;
+        let destination = Some(next_hop).filter(|next_hop| !next_hop.is_broadcast());
+        match destination {
+            Some(next_hop) if self.link_arq_enabled => {
+                let sequence = self.link_arq.track(next_hop, &buffer, self.now_ms());
+                self.sim_channel.push_link_frame(self.node_id, destination, &buffer, Some(sequence));
+            },
+            _ => self.sim_channel.push_link_frame(self.node_id, destination, &buffer, None),
+        }
+        self.diagnostics.frames_sent += 1;
+        metrics::increment(Counter::RadioTx);
+        Ok(())
+    }
+    
+    fn receive_beacon(&mut self) -> Result<Option<Beacon>, Self::Error> {
+        if self.asleep {
+            return Ok(None);
+        }
+        let beacon = self.sim_channel.get_link_beacon(self.node_id);
+        if let Some((source, _)) = beacon {
+            self.last_source = Some(source);
+            metrics::increment(Counter::BeaconsRx);
+        }
+        Ok(beacon.map(|(_, beacon)| beacon))
+    }
+    
+    fn receive_data<'a>(&mut self, buffer: &'a mut [u8]) -> Result<Option<DataPacket<'a>>, Self::Error> {
+        // 睡眠时无线电关闭，帧留在信道中，醒来时按信道的设置丢弃或交给上层
+        if self.asleep {
+            return Ok(None);
+        }
+        self.service_link_arq();
+        
+        if let Some((link, len, sequence)) = self.sim_channel.receive_frame(self.node_id, self.promiscuous, buffer) {
+            let source = link.source;
+            self.last_source = Some(source);
+            self.last_link = Some(link);
+            // 带链路序号的帧立即确认，重传造成的重复帧不交给上层；监听时不确认，重传的帧也照样交给上层统计
+            if let Some(sequence) = sequence.filter(|_| !self.listen_only) {
+                self.sim_channel.push_link_ack(self.node_id, source, sequence);
+                if self.link_arq.is_duplicate(source, sequence, self.now_ms()) {
+                    return Ok(None);
+                }
+            }
+            
+            let buffer: &'a [u8] = buffer;
+            // 解析不了的帧相当于空口上损坏的帧
+            let packet = match DataPacket::parse(&buffer[..len]) {
+                Some(packet) => packet,
+                None => {
+                    self.diagnostics.crc_errors += 1;
+                    metrics::increment(Counter::CrcErrors);
+                    return Ok(None);
+                },
+            };
+            
+            // 校验和在接收的统一校验中检查并计数
+            metrics::increment(Counter::RadioRx);
+            
+            Ok(Some(packet))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    fn configure(&mut self, channel: u8, power: u8) -> Result<(), Self::Error> {
+        if channel < 11 || channel > 26 {
+            return Err(SimulatorError::ConfigError);
+        }
+        
+        if power > 30 {
+            return Err(SimulatorError::ConfigError);
+        }
+        
+        self.channel = channel;
+        self.power = power;
+        Ok(())
+    }
+    
+    fn get_rssi(&self) -> Result<i8, Self::Error> {
+        // 收发双方都设置了位置时按距离计算最近一帧的信号强度
+        if let Some(rssi) = self.last_source.and_then(|source| self.sim_channel.rssi(source, self.node_id)) {
+            return Ok(rssi);
+        }
+        
+        // 否则随机模拟一个合理的RSSI值
+        let rssi = -70 - (std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().subsec_nanos() % 20) as i8;
+        Ok(rssi)
+    }
+    
+    fn diagnostics(&self) -> RadioDiagnostics {
+        self.diagnostics
+    }
+    
+    fn energy_detect(&mut self, channel: u8) -> Option<i8> {
+        if !(11..=26).contains(&channel) {
+            return None;
+        }
+        Some(self.sim_channel.noise(channel))
+    }
+    
+    fn set_link_arq(&mut self, config: Option<LinkArqConfig>) -> bool {
+        if let Some(config) = config {
+            self.link_arq = LinkArq::new(config, self.pool);
+        }
+        self.link_arq_enabled = config.is_some();
+        true
+    }
+    
+    fn set_airtime_limit(&mut self, config: Option<AirtimeConfig>) -> bool {
+        self.airtime = config.map(AirtimeLimiter::new);
+        metrics::set(Gauge::AirtimeBudget, config.map_or(0, |config| config.budget_ms()));
+        true
+    }
+    
+    fn airtime(&mut self) -> Option<AirtimeStatus> {
+        let now = self.now_ms();
+        let status = self.airtime.as_mut().map(|limiter| limiter.status(now))?;
+        metrics::set(Gauge::AirtimeUsed, status.used_ms);
+        Some(status)
+    }
+    
+    fn set_listen_only(&mut self, listen_only: bool) -> bool {
+        self.listen_only = listen_only;
+        true
+    }
+    
+    fn set_promiscuous(&mut self, promiscuous: bool) -> bool {
+        self.promiscuous = promiscuous;
+        self.sim_channel.set_promiscuous(self.node_id, promiscuous);
+        true
+    }
+    
+    fn last_link(&self) -> Option<LinkAddress> {
+        self.last_link
+    }
 }
 
-/// 共享通信通道，用于在多个模拟节点之间传递消息
+impl Drop for SimRadio {
+    fn drop(&mut self) {
+        self.sim_channel.unregister(self.node_id);
+        if self.promiscuous {
+            self.sim_channel.set_promiscuous(self.node_id, false);
+        }
+    }
+}
+
+/// 模拟串口控制台，从标准输入读取命令行
 #[derive(Clone)]
-pub struct SimChannel {
-    beacons: Arc<Mutex<VecDeque<(NodeId, Beacon)>>>,
-    packets: Arc<Mutex<VecDeque<(NodeId, Vec<u8>, usize)>>>,
+pub struct SimConsole {
+    input: Arc<Mutex<VecDeque<u8>>>,
 }
 
-impl SimChannel {
-    pub fn new() -> Self {
-        Self {
-            beacons: Arc::new(Mutex::new(VecDeque::new())),
-            packets: Arc::new(Mutex::new(VecDeque::new())),
-        }
+impl SimConsole {
+    /// 创建控制台并启动标准输入读取线程
+    pub fn stdin() -> Self {
+        let console = Self {
+            input: Arc::new(Mutex::new(VecDeque::new())),
+        };
+        
+        let input = console.input.clone();
+        thread::spawn(move || {
+            let stdin = std::io::stdin();
+            let mut line = String::new();
+            while let Ok(n) = stdin.read_line(&mut line) {
+                if n == 0 {
+                    break;
+                }
+                if let Ok(mut input) = input.lock() {
+                    input.extend(line.as_bytes());
+                }
+                line.clear();
+            }
+        });
+        
+        console
     }
     
-    pub fn push_beacon(&self, source: NodeId, beacon: Beacon) {
-        if let Ok(mut beacons) = self.beacons.lock() {
-            beacons.push_back((source, beacon));
+    /// 创建不连接标准输入的控制台，供测试注入输入
+    pub fn detached() -> Self {
+        Self {
+            input: Arc::new(Mutex::new(VecDeque::new())),
         }
     }
     
-    pub fn push_packet(&self, source: NodeId, data: &[u8], len: usize) {
-        if let Ok(mut packets) = self.packets.lock() {
-            packets.push_back((source, data.to_vec(), len));
+    /// 注入控制台输入
+    pub fn push_input(&self, data: &[u8]) {
+        if let Ok(mut input) = self.input.lock() {
+            input.extend(data);
         }
     }
     
-    pub fn get_beacon(&self, dest: NodeId) -> Option<Beacon> {
-        if let Ok(mut beacons) = self.beacons.lock() {
-            // 找到第一个目标为广播或特定目标的信标
-            for i in 0..beacons.len() {
-                let (src, beacon) = &beacons[i];
-                // 忽略自己发送的信标
-                if *src != dest {
-                    let b = *beacon;
-                    beacons.remove(i);
-                    return Some(b);
+    fn read(&self, buffer: &mut [u8]) -> usize {
+        let mut count = 0;
+        if let Ok(mut input) = self.input.lock() {
+            while count < buffer.len() {
+                match input.pop_front() {
+                    Some(byte) => {
+                        buffer[count] = byte;
+                        count += 1;
+                    },
+                    None => break,
                 }
             }
         }
-        None
+        count
+    }
+}
+
+/// 模拟非易失存储，克隆后共享同一份数据，可在模拟重启后保留
+#[derive(Clone)]
+pub struct SimNvs {
+    entries: Arc<Mutex<HashMap<u16, Vec<u8>>>>,
+}
+
+impl SimNvs {
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
     }
+}
+
+impl NvStorage for SimNvs {
+    type Error = SimulatorError;
     
-    pub fn get_packet(&self, dest: NodeId, buffer: &mut [u8]) -> Option<usize> {
-        if let Ok(mut packets) = self.packets.lock() {
-            // 找到第一个目标为广播或特定目标的数据包
-            for i in 0..packets.len() {
-                let (src, data, len) = &packets[i];
-                // 忽略自己发送的数据包
-                if *src != dest && *len <= buffer.len() {
-                    buffer[..*len].copy_from_slice(&data[..*len]);
-                    let len_copy = *len;
-                    packets.remove(i);
-                    return Some(len_copy);
-                }
-            }
+    fn nvs_read(&mut self, key: u16, buffer: &mut [u8]) -> Result<Option<usize>, Self::Error> {
+        let entries = self.entries.lock().map_err(|_| SimulatorError::ConfigError)?;
+        match entries.get(&key) {
+            Some(value) => {
+                let len = value.len().min(buffer.len());
+                buffer[..len].copy_from_slice(&value[..len]);
+                Ok(Some(len))
+            },
+            None => Ok(None),
         }
-        None
+    }
+    
+    fn nvs_write(&mut self, key: u16, data: &[u8]) -> Result<(), Self::Error> {
+        let mut entries = self.entries.lock().map_err(|_| SimulatorError::ConfigError)?;
+        entries.insert(key, data.to_vec());
+        Ok(())
+    }
+    
+    fn nvs_erase(&mut self, key: u16) -> Result<(), Self::Error> {
+        let mut entries = self.entries.lock().map_err(|_| SimulatorError::ConfigError)?;
+        entries.remove(&key);
+        Ok(())
     }
 }
 
-/// 模拟无线电接口
-pub struct SimRadio {
-    channel: u8,
-    power: u8,
-    sim_channel: SimChannel,
-    node_id: NodeId,
+/// 模拟固件暂存区的容量
+pub const SIM_FIRMWARE_CAPACITY: usize = 256 * 1024;
+
+/// 模拟固件暂存区，可在节点之间共享以便检查写入的镜像
+#[derive(Clone)]
+pub struct SimFirmware {
+    staging: Arc<Mutex<Vec<u8>>>,
+    boot_image: Arc<Mutex<Option<(u32, u32)>>>,
 }
 
-impl SimRadio {
-    pub fn new(sim_channel: SimChannel, node_id: NodeId) -> Self {
+impl SimFirmware {
+    pub fn new() -> Self {
         Self {
-            channel: 11,
-            power: 20,
-            sim_channel,
-            node_id,
+            staging: Arc::new(Mutex::new(Vec::new())),
+            boot_image: Arc::new(Mutex::new(None)),
+        }
+    }
+    
+    /// 直接把镜像放入暂存区，服务器用它作为分发源
+    pub fn load_image(&mut self, image: &[u8]) {
+        if let Ok(mut staging) = self.staging.lock() {
+            staging.clear();
+            staging.extend_from_slice(image);
         }
     }
+    
+    /// 已标记待切换的镜像（长度，CRC）
+    pub fn boot_image(&self) -> Option<(u32, u32)> {
+        self.boot_image.lock().ok().and_then(|image| *image)
+    }
 }
 
-impl RadioInterface for SimRadio {
+impl FirmwareStorage for SimFirmware {
     type Error = SimulatorError;
     
-    fn send_beacon(&mut self, beacon: &Beacon) -> Result<(), Self::Error> {
-        self.sim_channel.push_beacon(self.node_id, *beacon);
+    fn staging_capacity(&self) -> usize {
+        SIM_FIRMWARE_CAPACITY
+    }
+    
+    fn erase_staging(&mut self) -> Result<(), Self::Error> {
+        let mut staging = self.staging.lock().map_err(|_| SimulatorError::ConfigError)?;
+        staging.clear();
         Ok(())
     }
     
-    fn send_data<'a>(&mut self, packet: &DataPacket<'a>) -> Result<(), Self::Error> {
-        // 模拟发送数据，实际上是将数据放入共享通道
-        let header = unsafe {
-            std::slice::from_raw_parts(
-                &packet.header as *const _ as *const u8,
-                std::mem::size_of::<crate::protocol::data::DataHeader>(),
-            )
-        };
-        
-        let total_len = header.len() + packet.data.len();
-        let mut buffer = vec![0u8; total_len];
-        
-        buffer[..header.len()].copy_from_slice(header);
-        buffer[header.len()..].copy_from_slice(packet.data);
+    fn write_staging(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+        let end = offset as usize + data.len();
+        if end > SIM_FIRMWARE_CAPACITY {
+            return Err(SimulatorError::ConfigError);
+        }
         
-        self.sim_channel.push_packet(self.node_id, &buffer, total_len);
+        let mut staging = self.staging.lock().map_err(|_| SimulatorError::ConfigError)?;
+        if staging.len() < end {
+            staging.resize(end, 0xFF);
+        }
+        staging[offset as usize..end].copy_from_slice(data);
         Ok(())
     }
     
-    fn receive_beacon(&mut self) -> Result<Option<Beacon>, Self::Error> {
-        let beacon = self.sim_channel.get_beacon(self.node_id);
-        Ok(beacon)
+    fn read_staging(&mut self, offset: u32, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        let staging = self.staging.lock().map_err(|_| SimulatorError::ConfigError)?;
+        let start = (offset as usize).min(staging.len());
+        let len = (staging.len() - start).min(buffer.len());
+        buffer[..len].copy_from_slice(&staging[start..start + len]);
+        Ok(len)
     }
     
-    fn receive_data<'a>(&mut self, buffer: &'a mut [u8]) -> Result<Option<DataPacket<'a>>, Self::Error> {
-        if let Some(len) = self.sim_channel.get_packet(self.node_id, buffer) {
-            if len < std::mem::size_of::<crate::protocol::data::DataHeader>() {
-                return Ok(None);
-            }
-            
-            let header_size = std::mem::size_of::<crate::protocol::data::DataHeader>();
-            let header = unsafe {
-                &*(buffer.as_ptr() as *const crate::protocol::data::DataHeader)
-            };
-            
-            let data_len = header.data_length as usize;
-            if header_size + data_len > len {
-                return Ok(None);
-            }
-            
-            let data = &buffer[header_size..header_size + data_len];
-            let packet = DataPacket {
-                header: *header,
-                data,
-            };
-            
-            Ok(Some(packet))
-        } else {
-            Ok(None)
+    fn mark_for_boot(&mut self, size: u32, crc: u32) -> Result<(), Self::Error> {
+        let mut boot_image = self.boot_image.lock().map_err(|_| SimulatorError::ConfigError)?;
+        *boot_image = Some((size, crc));
+        Ok(())
+    }
+}
+
+/// 模拟数据分区的扇区大小
+pub const SIM_FLASH_SECTOR_SIZE: usize = 4096;
+
+/// 模拟数据分区的扇区数
+pub const SIM_FLASH_SECTORS: usize = 16;
+
+/// 模拟NOR闪存数据分区，写入只能把位清零；克隆后共享同一份数据，可在模拟重启后保留
+#[derive(Clone)]
+pub struct SimFlash {
+    sector_size: usize,
+    data: Arc<Mutex<Vec<u8>>>,
+    erase_counts: Arc<Mutex<Vec<u32>>>,
+}
+
+impl SimFlash {
+    pub fn new() -> Self {
+        Self::with_geometry(SIM_FLASH_SECTOR_SIZE, SIM_FLASH_SECTORS)
+    }
+    
+    /// 按指定的扇区大小和扇区数创建，测试中用小分区覆盖扇区轮换
+    pub fn with_geometry(sector_size: usize, sectors: usize) -> Self {
+        Self {
+            sector_size,
+            data: Arc::new(Mutex::new(vec![0xFF; sector_size * sectors])),
+            erase_counts: Arc::new(Mutex::new(vec![0; sectors])),
         }
     }
     
-    fn configure(&mut self, channel: u8, power: u8) -> Result<(), Self::Error> {
-        if channel < 11 || channel > 26 {
+    /// 各扇区被擦除的次数
+    pub fn erase_counts(&self) -> Vec<u32> {
+        self.erase_counts.lock().map(|counts| counts.clone()).unwrap_or_default()
+    }
+}
+
+impl FlashStorage for SimFlash {
+    type Error = SimulatorError;
+    
+    fn sector_size(&self) -> usize {
+        self.sector_size
+    }
+    
+    fn sector_count(&self) -> Result<usize, Self::Error> {
+        self.erase_counts.lock().map(|counts| counts.len()).map_err(|_| SimulatorError::ConfigError)
+    }
+    
+    fn erase_sector(&mut self, sector: usize) -> Result<(), Self::Error> {
+        let mut data = self.data.lock().map_err(|_| SimulatorError::ConfigError)?;
+        let mut erase_counts = self.erase_counts.lock().map_err(|_| SimulatorError::ConfigError)?;
+        let count = erase_counts.get_mut(sector).ok_or(SimulatorError::ConfigError)?;
+        *count += 1;
+        data[sector * self.sector_size..(sector + 1) * self.sector_size].fill(0xFF);
+        Ok(())
+    }
+    
+    fn flash_write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let mut data = self.data.lock().map_err(|_| SimulatorError::ConfigError)?;
+        let start = offset as usize;
+        let target = data.get_mut(start..start + bytes.len()).ok_or(SimulatorError::ConfigError)?;
+        // 与NOR闪存一样只能把位从1写成0
+        for (byte, value) in target.iter_mut().zip(bytes) {
+            *byte &= value;
+        }
+        Ok(())
+    }
+    
+    fn flash_read(&self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let data = self.data.lock().map_err(|_| SimulatorError::ConfigError)?;
+        let start = offset as usize;
+        let source = data.get(start..start + buffer.len()).ok_or(SimulatorError::ConfigError)?;
+        buffer.copy_from_slice(source);
+        Ok(())
+    }
+}
+
+/// 模拟I2C总线上SHT3x温湿度传感器的地址
+pub const SIM_SHT3X_ADDRESS: u8 = 0x44;
+
+/// 模拟I2C总线，挂载一个SHT3x温湿度传感器
+pub struct SimI2c {
+    /// 是否有已触发、尚未读取的测量
+    measurement_pending: bool,
+}
+
+impl SimI2c {
+    pub fn new() -> Self {
+        Self {
+            measurement_pending: false,
+        }
+    }
+    
+    /// 生成一次测量结果（温度、湿度原始值及CRC）
+    fn measurement(&self) -> [u8; 6] {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        
+        // 温度在20-30°C之间波动，湿度在50-80%之间波动
+        let temperature = 20.0 + ((now % 100) as f32) / 10.0;
+        let humidity = 50.0 + ((now % 60) as f32) / 2.0;
+        
+        let raw_temp = (((temperature + 45.0) / 175.0) * 65535.0) as u16;
+        let raw_hum = ((humidity / 100.0) * 65535.0) as u16;
+        
+        let mut data = [0u8; 6];
+        data[0..2].copy_from_slice(&raw_temp.to_be_bytes());
+        data[2] = calculate_crc8(&data[0..2]);
+        data[3..5].copy_from_slice(&raw_hum.to_be_bytes());
+        data[5] = calculate_crc8(&data[3..5]);
+        data
+    }
+}
+
+impl i2c::Write for SimI2c {
+    type Error = SimulatorError;
+    
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        if address != SIM_SHT3X_ADDRESS {
+            // 总线上没有该地址的设备
             return Err(SimulatorError::ConfigError);
         }
         
-        if power > 30 {
+        // 单次测量命令
+        if bytes.len() >= 2 && bytes[0] == 0x24 {
+            self.measurement_pending = true;
+        }
+        Ok(())
+    }
+}
+
+impl i2c::Read for SimI2c {
+    type Error = SimulatorError;
+    
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        if address != SIM_SHT3X_ADDRESS || !self.measurement_pending {
+            // 没有可读数据时传感器不应答
             return Err(SimulatorError::ConfigError);
         }
         
-        self.channel = channel;
-        self.power = power;
+        self.measurement_pending = false;
+        let data = self.measurement();
+        let len = buffer.len().min(data.len());
+        buffer[..len].copy_from_slice(&data[..len]);
         Ok(())
     }
+}
+
+impl i2c::WriteRead for SimI2c {
+    type Error = SimulatorError;
     
-    fn get_rssi(&self) -> Result<i8, Self::Error> {
-        // 随机模拟一个合理的RSSI值
-        let rssi = -70 - (std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().subsec_nanos() % 20) as i8;
-        Ok(rssi)
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
+        i2c::Write::write(self, address, bytes)?;
+        i2c::Read::read(self, address, buffer)
+    }
+}
+
+/// 启动模拟器的统计收集线程，每隔一段时间打印这段时间内的指标增量
+pub fn spawn_metrics_collector(label: &'static str, interval: Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut last = metrics::snapshot();
+        loop {
+            thread::sleep(interval);
+            let current = metrics::snapshot();
+            info!("[{}] 最近 {}s 的运行指标:\n{}", label, interval.as_secs(), current.since(&last));
+            last = current;
+        }
+    })
+}
+
+/// 模拟电池：按工作状态的电流累计消耗的电量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatteryModel {
+    /// 电池容量（mAh）
+    pub capacity_mah: u32,
+    /// 工作时的电流（µA）
+    pub active_ua: u32,
+    /// 低功耗模式下的电流（µA）
+    pub sleep_ua: u32,
+}
+
+impl Default for BatteryModel {
+    fn default() -> Self {
+        Self {
+            capacity_mah: 1000,
+            active_ua: 10_000,
+            sleep_ua: 10,
+        }
+    }
+}
+
+impl BatteryModel {
+    /// 电池容量（µA·ms）
+    fn capacity(&self) -> u64 {
+        self.capacity_mah as u64 * 1000 * 3_600_000
     }
 }
 
@@ -180,7 +1757,22 @@ pub struct SimHardware {
     node_id: NodeId,
     radio: SimRadio,
     start_time: Instant,
-    battery_level: u8,
+    battery: BatteryModel,
+    /// 已消耗的电量（µA·ms）
+    consumed: u64,
+    /// 低功耗模式下定时唤醒的时间
+    wake_at: Option<u64>,
+    console: Option<SimConsole>,
+    nvs: SimNvs,
+    firmware: SimFirmware,
+    flash: SimFlash,
+    i2c: SimI2c,
+    security: SecurityContext,
+    /// 本节点新建数据包的跳数限制
+    ttl: u8,
+    reset_requested: bool,
+    /// 多线程运行时推进虚拟时间的调度器
+    scheduler: Option<SimScheduler>,
 }
 
 impl SimHardware {
@@ -189,59 +1781,220 @@ impl SimHardware {
             node_id,
             radio: SimRadio::new(sim_channel, node_id),
             start_time: Instant::now(),
-            battery_level: 100,
+            battery: BatteryModel::default(),
+            consumed: 0,
+            wake_at: None,
+            console: None,
+            nvs: SimNvs::new(),
+            firmware: SimFirmware::new(),
+            flash: SimFlash::new(),
+            i2c: SimI2c::new(),
+            security: SecurityContext::new(),
+            ttl: DEFAULT_TTL,
+            reset_requested: false,
+            scheduler: None,
         }
     }
     
+    /// 使用已有的非易失存储创建节点，用于模拟断电重启后数据仍然存在
+    pub fn with_nvs(node_id: NodeId, sim_channel: SimChannel, nvs: SimNvs) -> Self {
+        let mut hardware = Self::new(node_id, sim_channel);
+        hardware.nvs = nvs;
+        hardware
+    }
+    
+    /// 获取并清除复位请求，模拟器入口据此重新启动节点主循环
+    pub fn take_reset_request(&mut self) -> bool {
+        let requested = self.reset_requested;
+        self.reset_requested = false;
+        requested
+    }
+    
+    /// 连接模拟串口控制台
+    pub fn attach_console(&mut self, console: SimConsole) {
+        self.console = Some(console);
+    }
+    
+    /// 使用共享的虚拟时钟，时间戳读取虚拟时间，延时直接推进时钟而不真正等待
+    pub fn with_clock(mut self, clock: SimClock) -> Self {
+        self.radio.clock = Some(clock);
+        self
+    }
+    
+    /// 在自己的线程中按调度器的虚拟时间运行，延时阻塞到其他节点也都进入延时
+    ///
+    /// 调用线程需要持有调度器的参与者。
+    pub fn with_scheduler(mut self, scheduler: SimScheduler) -> Self {
+        self.radio.clock = Some(scheduler.clock());
+        self.scheduler = Some(scheduler);
+        self
+    }
+    
+    /// 设置节点在模拟场地中的位置（米），接收信号强度按与发送方的距离计算
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.radio.sim_channel.set_position(self.node_id, x, y);
+    }
+    
+    /// 使用指定的电池参数
+    pub fn with_battery(mut self, battery: BatteryModel) -> Self {
+        self.battery = battery;
+        self
+    }
+    
     // 模拟电池消耗
     pub fn simulate_battery_drain(&mut self, percent: u8) {
-        if self.battery_level > percent {
-            self.battery_level -= percent;
-        } else {
-            self.battery_level = 0;
-        }
+        self.consumed = self.consumed.saturating_add(self.battery.capacity() / 100 * percent as u64);
+    }
+    
+    /// 已消耗的电量（µA·ms）
+    pub fn consumed_charge(&self) -> u64 {
+        self.consumed
+    }
+    
+    /// 是否处于低功耗模式
+    pub fn is_asleep(&self) -> bool {
+        self.radio.asleep
+    }
+    
+    /// 低功耗模式下经过指定时间后由定时器唤醒
+    pub fn schedule_wake(&mut self, after_ms: u32) {
+        let now = self.get_timestamp_ms().unwrap_or(0);
+        self.wake_at = Some(now + after_ms as u64);
+    }
+    
+    /// 按当前工作状态累计一段时间的耗电
+    fn consume(&mut self, ms: u64) {
+        let current = if self.radio.asleep { self.battery.sleep_ua } else { self.battery.active_ua };
+        self.consumed = self.consumed.saturating_add(current as u64 * ms);
     }
 }
 
 impl Hardware for SimHardware {
     type Error = SimulatorError;
     type Radio = SimRadio;
+    type Nvs = SimNvs;
+    type Firmware = SimFirmware;
+    type Flash = SimFlash;
+    type I2c = SimI2c;
     
     fn get_node_id(&self) -> NodeId {
         self.node_id
     }
     
+    fn set_node_id(&mut self, node_id: NodeId) {
+        self.radio.sim_channel.unregister(self.radio.node_id);
+        self.radio.sim_channel.register(node_id);
+        if self.radio.promiscuous {
+            self.radio.sim_channel.set_promiscuous(self.radio.node_id, false);
+            self.radio.sim_channel.set_promiscuous(node_id, true);
+        }
+        self.node_id = node_id;
+        self.radio.node_id = node_id;
+    }
+    
     fn get_radio(&mut self) -> &mut Self::Radio {
         &mut self.radio
     }
     
+    fn get_nvs(&mut self) -> &mut Self::Nvs {
+        &mut self.nvs
+    }
+    
+    fn get_firmware(&mut self) -> &mut Self::Firmware {
+        &mut self.firmware
+    }
+    
+    fn get_flash(&mut self) -> &mut Self::Flash {
+        &mut self.flash
+    }
+    
+    fn get_i2c(&mut self) -> &mut Self::I2c {
+        &mut self.i2c
+    }
+    
+    fn get_security(&mut self) -> &mut SecurityContext {
+        &mut self.security
+    }
+    
+    fn packet_pool(&self) -> &'static NodePool {
+        self.radio.pool
+    }
+    
+    fn default_ttl(&self) -> u8 {
+        self.ttl
+    }
+    
+    fn set_default_ttl(&mut self, ttl: u8) {
+        self.ttl = ttl.clamp(1, MAX_TTL);
+    }
+    
     fn get_battery_level(&self) -> Result<u8, Self::Error> {
-        Ok(self.battery_level)
+        let capacity = self.battery.capacity().max(1);
+        let remaining = capacity.saturating_sub(self.consumed);
+        Ok((remaining * 100 / capacity) as u8)
     }
     
     fn get_timestamp_ms(&self) -> Result<u64, Self::Error> {
+        if let Some(clock) = &self.radio.clock {
+            return Ok(clock.now());
+        }
         let elapsed = self.start_time.elapsed();
         Ok(elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64)
     }
     
     fn delay_ms(&mut self, ms: u32) -> Result<(), Self::Error> {
-        thread::sleep(Duration::from_millis(ms as u64));
-        // 模拟延迟也会消耗电池
-        if ms > 1000 {
-            self.simulate_battery_drain(1);
+        let start = self.get_timestamp_ms().unwrap_or(0);
+        match (&self.scheduler, &self.radio.clock) {
+            (Some(scheduler), _) => scheduler.sleep(ms as u64),
+            (None, Some(clock)) => clock.advance(ms as u64),
+            (None, None) => thread::sleep(Duration::from_millis(ms as u64)),
+        }
+        
+        // 睡眠期间定时器到期时醒来，之后按工作电流计算
+        let end = start + ms as u64;
+        match self.wake_at {
+            Some(wake_at) if self.radio.asleep && wake_at <= end => {
+                self.consume(wake_at.saturating_sub(start));
+                self.exit_low_power_mode()?;
+                self.consume(end - wake_at.max(start));
+            },
+            _ => self.consume(ms as u64),
         }
         Ok(())
     }
     
     fn enter_low_power_mode(&mut self) -> Result<(), Self::Error> {
-        // 模拟器中仅记录一下
-        println!("Node {:?} entered low power mode", self.node_id);
+        // 无线电关闭，耗电降到睡眠电流，由定时器或显式调用唤醒
+        self.radio.set_asleep(true);
         Ok(())
     }
     
     fn exit_low_power_mode(&mut self) -> Result<(), Self::Error> {
-        // 模拟器中仅记录一下
-        println!("Node {:?} exited low power mode", self.node_id);
+        self.wake_at = None;
+        self.radio.set_asleep(false);
+        Ok(())
+    }
+    
+    fn system_reset(&mut self) -> Result<(), Self::Error> {
+        // 模拟器中无法真正复位，只记录请求，由入口函数重新启动主循环
+        info!("节点 {} 请求系统复位", self.node_id);
+        self.reset_requested = true;
+        Ok(())
+    }
+    
+    fn console_read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        match &self.console {
+            Some(console) => Ok(console.read(buffer)),
+            None => Ok(0),
+        }
+    }
+    
+    fn console_write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        use std::io::Write;
+        
+        let mut stdout = std::io::stdout();
+        stdout.write_all(data).map_err(|_| SimulatorError::ConfigError)?;
+        let _ = stdout.flush();
         Ok(())
     }
 } 
\ No newline at end of file
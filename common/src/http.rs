@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::string::{String, ToString};
+use std::vec::Vec;
+use std::{format, vec};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::metrics::{self, Counter, Gauge};
+use crate::protocol::NodeId;
+
+/// 请求头的最大长度
+const MAX_REQUEST_LEN: usize = 4096;
+
+/// 节点发布的JSON文档，按路径索引
+///
+/// 与指标相同，状态是进程内的全局量，主循环直接发布，不需要传递句柄。
+static DOCUMENTS: Mutex<BTreeMap<&'static str, String>> = Mutex::new(BTreeMap::new());
+
+/// 按节点区分的仪表，例如网关从信标得到的各节点电量
+static NODE_GAUGES: Mutex<BTreeMap<(&'static str, [u8; 6]), i64>> = Mutex::new(BTreeMap::new());
+
+/// 已启动的服务器地址
+static STARTED: OnceLock<String> = OnceLock::new();
+
+/// 在后台线程启动HTTP服务器，重复调用时不会再次绑定
+///
+/// 内置`/stats`返回JSON指标，`/metrics`返回Prometheus文本格式的指标，`/`列出所有路径，
+/// 其余路径返回节点通过[`publish`]发布的文档。
+pub fn start(addr: &str) -> io::Result<()> {
+    if STARTED.get().is_some() {
+        return Ok(());
+    }
+    
+    let listener = TcpListener::bind(addr)?;
+    let _ = STARTED.set(addr.into());
+    
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // 单个连接出错不影响后续请求
+            let _ = handle(stream);
+        }
+    });
+    
+    Ok(())
+}
+
+/// 发布或替换指定路径的JSON文档
+pub fn publish(path: &'static str, json: String) {
+    if let Ok(mut documents) = DOCUMENTS.lock() {
+        documents.insert(path, json);
+    }
+}
+
+/// 设置按节点区分的仪表，在`/metrics`中输出为`linknebula_node_{name}{node="..."}`
+pub fn set_node_gauge(name: &'static str, node: NodeId, value: i64) {
+    if let Ok(mut gauges) = NODE_GAUGES.lock() {
+        gauges.insert((name, node.0), value);
+    }
+}
+
+/// 节点ID的JSON字符串，12位小写十六进制
+pub fn node_json(node: NodeId) -> String {
+    let mut out = String::with_capacity(14);
+    out.push('"');
+    for byte in node.0.iter() {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out.push('"');
+    out
+}
+
+/// 指标快照的JSON
+pub fn metrics_json() -> String {
+    let snapshot = metrics::snapshot();
+    let counters: Vec<String> = Counter::ALL.iter()
+        .map(|counter| format!("\"{}\":{}", counter.key(), snapshot.counter(*counter)))
+        .collect();
+    let gauges: Vec<String> = Gauge::ALL.iter()
+        .map(|gauge| format!("\"{}\":{}", gauge.key(), snapshot.gauge(*gauge)))
+        .collect();
+    
+    format!("{{\"counters\":{{{}}},\"gauges\":{{{}}}}}", counters.join(","), gauges.join(","))
+}
+
+/// Prometheus文本格式的指标
+pub fn prometheus_text() -> String {
+    let mut out = String::new();
+    let _ = metrics::snapshot().write_prometheus(&mut out);
+    
+    if let Ok(gauges) = NODE_GAUGES.lock() {
+        let mut last_name = "";
+        for ((name, node), value) in gauges.iter() {
+            if *name != last_name {
+                let _ = writeln!(out, "# TYPE linknebula_node_{} gauge", name);
+                last_name = name;
+            }
+            let _ = write!(out, "linknebula_node_{}{{node=\"", name);
+            for byte in node.iter() {
+                let _ = write!(out, "{:02x}", byte);
+            }
+            let _ = writeln!(out, "\"}} {}", value);
+        }
+    }
+    
+    out
+}
+
+/// 处理一个连接，只支持GET
+fn handle(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    
+    let mut request = Vec::new();
+    let mut chunk = [0u8; 512];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let len = stream.read(&mut chunk)?;
+        if len == 0 || request.len() + len > MAX_REQUEST_LEN {
+            break;
+        }
+        request.extend_from_slice(&chunk[..len]);
+    }
+    
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.lines().next().unwrap_or("").split(' ');
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = target.split('?').next().unwrap_or("");
+    
+    let mut content_type = "application/json";
+    let (status, body) = if method != "GET" {
+        ("405 Method Not Allowed", "{\"error\":\"method not allowed\"}".into())
+    } else {
+        match path {
+            "/" => ("200 OK", index_json()),
+            "/stats" => ("200 OK", metrics_json()),
+            "/metrics" => {
+                content_type = "text/plain; version=0.0.4; charset=utf-8";
+                ("200 OK", prometheus_text())
+            },
+            _ => match DOCUMENTS.lock().ok().and_then(|documents| documents.get(path).cloned()) {
+                Some(document) => ("200 OK", document),
+                None => ("404 Not Found", "{\"error\":\"not found\"}".into()),
+            },
+        }
+    };
+    
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    )?;
+    stream.flush()
+}
+
+/// 可查询的路径列表
+fn index_json() -> String {
+    let mut paths = vec!["\"/stats\"".to_string(), "\"/metrics\"".to_string()];
+    if let Ok(documents) = DOCUMENTS.lock() {
+        paths.extend(documents.keys().map(|path| format!("\"{}\"", path)));
+    }
+    format!("{{\"paths\":[{}]}}", paths.join(","))
+}
\ No newline at end of file
@@ -1,8 +1,23 @@
 #![no_std]
 #![cfg_attr(feature = "bearpi", no_main)]
 
+pub mod beacon_interval;
+pub mod channel_plan;
+pub mod clock;
+pub mod config;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod link_budget;
+pub mod log;
 pub mod protocol;
 pub mod hal;
+pub mod metrics;
+pub mod mgmt;
+pub mod monitor;
+pub mod ota;
+pub mod pool;
+pub mod security;
+pub mod topology;
 pub mod utils;
 
 // 重新导出核心模块
@@ -0,0 +1,131 @@
+/// 对数距离路径损耗模型的参数
+///
+/// 路径损耗 PL(d) = PL(1m) + 10·n·log10(d)，由接收信号强度和发射功率反推距离和链路余量。
+/// 结果只是粗略估计，墙体和多径会带来数倍的误差，适合部署时判断“还能再走多远”。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkBudget {
+    /// 发射功率（dBm）
+    pub tx_power_dbm: i8,
+    /// 接收灵敏度（dBm），低于该值无法解调
+    pub sensitivity_dbm: i8,
+    /// 1米处的路径损耗（dB）
+    pub reference_loss_db: f32,
+    /// 路径损耗指数，自由空间为2，室内通常为2.5-4
+    pub path_loss_exponent: f32,
+}
+
+impl Default for LinkBudget {
+    fn default() -> Self {
+        Self {
+            tx_power_dbm: 20,
+            sensitivity_dbm: -95,
+            reference_loss_db: 40.0,
+            path_loss_exponent: 2.7,
+        }
+    }
+}
+
+/// 一次链路估计的结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkEstimate {
+    /// 使用的接收信号强度（dBm）
+    pub rssi_dbm: i8,
+    /// 路径损耗（dB）
+    pub path_loss_db: i16,
+    /// 高于接收灵敏度的余量（dB），负数表示链路不可用
+    pub margin_db: i16,
+    /// 估计距离（米）
+    pub distance_m: f32,
+}
+
+impl LinkBudget {
+    /// 使用指定发射功率的默认模型
+    pub fn with_tx_power(tx_power_dbm: i8) -> Self {
+        Self {
+            tx_power_dbm,
+            ..Self::default()
+        }
+    }
+    
+    /// 路径损耗（dB）
+    pub fn path_loss(&self, rssi_dbm: i8) -> i16 {
+        self.tx_power_dbm as i16 - rssi_dbm as i16
+    }
+    
+    /// 链路余量（dB）
+    pub fn margin(&self, rssi_dbm: i8) -> i16 {
+        rssi_dbm as i16 - self.sensitivity_dbm as i16
+    }
+    
+    /// 余量恰好为指定值时的接收信号强度（dBm）
+    pub fn rssi_for_margin(&self, margin_db: i16) -> i8 {
+        (self.sensitivity_dbm as i16 + margin_db).clamp(i8::MIN as i16, i8::MAX as i16) as i8
+    }
+    
+    /// 按路径损耗估计距离（米）
+    pub fn distance(&self, path_loss_db: f32) -> f32 {
+        let exponent = (path_loss_db - self.reference_loss_db) / (10.0 * self.path_loss_exponent);
+        pow10(exponent)
+    }
+    
+    /// 保持指定余量时能到达的最远距离（米），部署时下一个中继应放在这个距离之内
+    pub fn max_distance(&self, margin_db: i16) -> f32 {
+        let rssi = self.rssi_for_margin(margin_db);
+        self.distance(self.path_loss(rssi) as f32)
+    }
+    
+    /// 由一次接收信号强度估计链路
+    pub fn estimate(&self, rssi_dbm: i8) -> LinkEstimate {
+        let path_loss_db = self.path_loss(rssi_dbm);
+        LinkEstimate {
+            rssi_dbm,
+            path_loss_db,
+            margin_db: self.margin(rssi_dbm),
+            distance_m: self.distance(path_loss_db as f32),
+        }
+    }
+    
+    /// 由多次采样估计链路，先在dBm上取平均；没有采样时返回None
+    pub fn estimate_samples(&self, samples: &[i8]) -> Option<LinkEstimate> {
+        average_rssi(samples).map(|rssi| self.estimate(rssi))
+    }
+}
+
+/// 接收信号强度的平均值（dBm），四舍五入
+pub fn average_rssi(samples: &[i8]) -> Option<i8> {
+    if samples.is_empty() {
+        return None;
+    }
+    
+    let sum: i32 = samples.iter().map(|&rssi| rssi as i32).sum();
+    let count = samples.len() as i32;
+    let half = if sum < 0 { -count / 2 } else { count / 2 };
+    Some(((sum + half) / count) as i8)
+}
+
+/// 计算10的x次方，no_std下没有浮点数学库，整数部分连乘，小数部分用泰勒级数
+fn pow10(x: f32) -> f32 {
+    const LN_10: f32 = core::f32::consts::LN_10;
+    
+    let whole = x as i32 - if x < 0.0 && x != (x as i32) as f32 { 1 } else { 0 };
+    let fraction = x - whole as f32;
+    
+    // e^(fraction·ln10)，fraction在[0, 1)内，12项足够精确
+    let y = fraction * LN_10;
+    let mut term = 1.0f32;
+    let mut sum = 1.0f32;
+    for n in 1..12 {
+        term *= y / n as f32;
+        sum += term;
+    }
+    
+    let mut scale = 1.0f32;
+    for _ in 0..whole.unsigned_abs().min(38) {
+        scale *= 10.0;
+    }
+    if whole < 0 {
+        sum / scale
+    } else {
+        sum * scale
+    }
+}
\ No newline at end of file
@@ -0,0 +1,149 @@
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// 日志级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Level {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+
+impl Level {
+    /// 从级别字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Level::Error),
+            2 => Some(Level::Warn),
+            3 => Some(Level::Info),
+            4 => Some(Level::Debug),
+            _ => None,
+        }
+    }
+}
+
+/// 编译期确定的最高日志级别，默认info
+///
+/// 由构建时的环境变量AETHER_LOG设置（error/warn/info/debug）。
+/// 嵌入式目标使用defmt输出，级别由构建时的DEFMT_LOG环境变量控制。
+pub const MAX_LEVEL: Level = parse_level(option_env!("AETHER_LOG"));
+
+const fn parse_level(value: Option<&str>) -> Level {
+    match value {
+        Some(value) => match value.as_bytes() {
+            b"error" => Level::Error,
+            b"warn" => Level::Warn,
+            b"debug" => Level::Debug,
+            _ => Level::Info,
+        },
+        None => Level::Info,
+    }
+}
+
+/// 运行时的日志级别，可通过远程管理调整，不会超过编译期的最高级别
+static LEVEL: AtomicU8 = AtomicU8::new(MAX_LEVEL as u8);
+
+/// 设置运行时日志级别
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// 当前运行时日志级别
+pub fn level() -> Level {
+    Level::from_u8(LEVEL.load(Ordering::Relaxed)).unwrap_or(MAX_LEVEL)
+}
+
+/// 指定级别的日志是否输出
+#[doc(hidden)]
+pub fn enabled(level: Level) -> bool {
+    level <= MAX_LEVEL && level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// 模拟器后端：按级别过滤后输出到标准输出，附带调用处的模块路径作为目标
+///
+/// 编译期级别是常量，低于MAX_LEVEL的调用整体被优化掉。
+#[cfg(not(feature = "bearpi"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log {
+    ($level:expr, $tag:literal, $($arg:tt)+) => {
+        if $crate::log::enabled($level) {
+            ::std::println!("[{} {}] {}", $tag, ::core::module_path!(), ::core::format_args!($($arg)+));
+        }
+    };
+}
+
+/// 错误日志
+#[cfg(not(feature = "bearpi"))]
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => { $crate::__log!($crate::log::Level::Error, "ERROR", $($arg)+) };
+}
+
+/// 警告日志
+#[cfg(not(feature = "bearpi"))]
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => { $crate::__log!($crate::log::Level::Warn, "WARN", $($arg)+) };
+}
+
+/// 信息日志
+#[cfg(not(feature = "bearpi"))]
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::__log!($crate::log::Level::Info, "INFO", $($arg)+) };
+}
+
+/// 调试日志
+#[cfg(not(feature = "bearpi"))]
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::__log!($crate::log::Level::Debug, "DEBUG", $($arg)+) };
+}
+
+// 嵌入式后端：格式字符串由defmt在编译期驻留，只传输索引和参数，经RTT输出
+
+/// 错误日志
+#[cfg(feature = "bearpi")]
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        if $crate::log::enabled($crate::log::Level::Error) {
+            ::defmt::error!($($arg)+)
+        }
+    };
+}
+
+/// 警告日志
+#[cfg(feature = "bearpi")]
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        if $crate::log::enabled($crate::log::Level::Warn) {
+            ::defmt::warn!($($arg)+)
+        }
+    };
+}
+
+/// 信息日志
+#[cfg(feature = "bearpi")]
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        if $crate::log::enabled($crate::log::Level::Info) {
+            ::defmt::info!($($arg)+)
+        }
+    };
+}
+
+/// 调试日志
+#[cfg(feature = "bearpi")]
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        if $crate::log::enabled($crate::log::Level::Debug) {
+            ::defmt::debug!($($arg)+)
+        }
+    };
+}
\ No newline at end of file
@@ -0,0 +1,524 @@
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::protocol::wire::{get_u32be, put_u32be};
+
+/// 累加型计数器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Counter {
+    /// 无线电发送的数据包
+    RadioTx = 0,
+    /// 无线电接收的数据包
+    RadioRx = 1,
+    /// 无线电发送失败
+    RadioTxErrors = 2,
+    /// 校验和错误的数据包
+    ChecksumErrors = 3,
+    /// 发送的信标
+    BeaconsTx = 4,
+    /// 接收的信标
+    BeaconsRx = 5,
+    /// 可靠发送的帧
+    ReliableSent = 6,
+    /// 重传次数
+    Retransmissions = 7,
+    /// 已确认的帧
+    Delivered = 8,
+    /// 重传耗尽仍未确认的帧
+    DeliveryFailures = 9,
+    /// 新学习到的路由
+    RoutesLearned = 10,
+    /// 查不到路由的次数
+    RouteMisses = 11,
+    /// 分配的服务租约
+    LeasesGranted = 12,
+    /// 到期回收的服务租约
+    LeasesExpired = 13,
+    /// 写入存储的记录
+    RecordsStored = 14,
+    /// 存储已满时被覆盖的记录
+    RecordsOverwritten = 15,
+    /// 按保留策略移除的记录
+    RecordsEvicted = 16,
+    /// 未通过安全校验而丢弃的包
+    SecurityRejected = 17,
+    /// 发起的主节点选举
+    ElectionsStarted = 18,
+    /// 主节点变更次数
+    MasterChanges = 19,
+    /// 链路层逐跳重传次数
+    LinkRetransmissions = 20,
+    /// 逐跳重传耗尽仍未确认的帧
+    LinkFailures = 21,
+    /// 链路层丢弃的重复帧
+    LinkDuplicates = 22,
+    /// 超过占空比上限而未发送的帧
+    AirtimeDeferred = 23,
+    /// 版本、长度或分片字段不合法而丢弃的包
+    MalformedPackets = 24,
+    /// 缓冲池耗尽而未能取得缓冲区的次数
+    PoolExhausted = 25,
+    /// 无线电硬件自动重传次数
+    RadioRetries = 26,
+    /// 信道检测为忙而未发送的次数
+    CcaFailures = 27,
+    /// CRC校验失败的无线帧
+    CrcErrors = 28,
+    /// 接收缓冲区溢出丢弃的无线帧
+    RxOverruns = 29,
+    /// 全网切换无线信道的次数
+    ChannelSwitches = 30,
+    /// 服务器丢弃的重传重复上传
+    DuplicatesSuppressed = 31,
+    /// 跳数耗尽而未转发的数据包
+    TtlExpired = 32,
+    /// 带宽不足而拒绝建立的路径
+    PathsRejected = 33,
+    /// 重新广播的泛洪包
+    FloodsRelayed = 34,
+    /// 重复到达而丢弃的泛洪包
+    FloodDuplicates = 35,
+    /// 转发给下一跳的数据包
+    PacketsForwarded = 36,
+    /// 转发节点因队列满、没有路由或跳数耗尽而丢弃的数据包
+    PacketsDropped = 37,
+}
+
+/// 计数器个数
+pub const COUNTER_COUNT: usize = 38;
+
+/// 反映当前状态的仪表
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Gauge {
+    /// 路由表项数
+    Routes = 0,
+    /// 服务目录条目数
+    DirectoryEntries = 1,
+    /// 有效的服务租约数
+    ActiveLeases = 2,
+    /// 等待确认的帧数
+    PendingFrames = 3,
+    /// 无线接收队列中的包数
+    RxQueue = 4,
+    /// 本节点电池电量 (0-100%)
+    BatteryLevel = 5,
+    /// 最近一小时的发射时间（毫秒）
+    AirtimeUsed = 6,
+    /// 每小时允许的发射时间（毫秒），0表示不限制
+    AirtimeBudget = 7,
+    /// 发送调度器中等待发送的包数
+    TxQueue = 8,
+    /// 缓冲池中已取出的缓冲区数
+    PoolInUse = 9,
+    /// 已建立路径预留的带宽（kbps）
+    ReservedBandwidth = 10,
+}
+
+/// 仪表个数
+pub const GAUGE_COUNT: usize = 11;
+
+impl Counter {
+    /// 按编号顺序排列的所有计数器
+    pub const ALL: [Counter; COUNTER_COUNT] = [
+        Counter::RadioTx,
+        Counter::RadioRx,
+        Counter::RadioTxErrors,
+        Counter::ChecksumErrors,
+        Counter::BeaconsTx,
+        Counter::BeaconsRx,
+        Counter::ReliableSent,
+        Counter::Retransmissions,
+        Counter::Delivered,
+        Counter::DeliveryFailures,
+        Counter::RoutesLearned,
+        Counter::RouteMisses,
+        Counter::LeasesGranted,
+        Counter::LeasesExpired,
+        Counter::RecordsStored,
+        Counter::RecordsOverwritten,
+        Counter::RecordsEvicted,
+        Counter::SecurityRejected,
+        Counter::ElectionsStarted,
+        Counter::MasterChanges,
+        Counter::LinkRetransmissions,
+        Counter::LinkFailures,
+        Counter::LinkDuplicates,
+        Counter::AirtimeDeferred,
+        Counter::MalformedPackets,
+        Counter::PoolExhausted,
+        Counter::RadioRetries,
+        Counter::CcaFailures,
+        Counter::CrcErrors,
+        Counter::RxOverruns,
+        Counter::ChannelSwitches,
+        Counter::DuplicatesSuppressed,
+        Counter::TtlExpired,
+        Counter::PathsRejected,
+        Counter::FloodsRelayed,
+        Counter::FloodDuplicates,
+        Counter::PacketsForwarded,
+        Counter::PacketsDropped,
+    ];
+    
+    /// 显示名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Counter::RadioTx => "无线发送",
+            Counter::RadioRx => "无线接收",
+            Counter::RadioTxErrors => "发送失败",
+            Counter::ChecksumErrors => "校验错误",
+            Counter::BeaconsTx => "发送信标",
+            Counter::BeaconsRx => "接收信标",
+            Counter::ReliableSent => "可靠发送",
+            Counter::Retransmissions => "重传",
+            Counter::Delivered => "已确认",
+            Counter::DeliveryFailures => "确认失败",
+            Counter::RoutesLearned => "新增路由",
+            Counter::RouteMisses => "路由缺失",
+            Counter::LeasesGranted => "分配租约",
+            Counter::LeasesExpired => "租约到期",
+            Counter::RecordsStored => "存储记录",
+            Counter::RecordsOverwritten => "覆盖记录",
+            Counter::RecordsEvicted => "过期移除",
+            Counter::SecurityRejected => "安全校验失败",
+            Counter::ElectionsStarted => "发起选举",
+            Counter::MasterChanges => "主节点变更",
+            Counter::LinkRetransmissions => "逐跳重传",
+            Counter::LinkFailures => "逐跳失败",
+            Counter::LinkDuplicates => "重复帧",
+            Counter::AirtimeDeferred => "占空比受限",
+            Counter::MalformedPackets => "格式错误",
+            Counter::PoolExhausted => "缓冲池耗尽",
+            Counter::RadioRetries => "硬件重传",
+            Counter::CcaFailures => "信道忙",
+            Counter::CrcErrors => "CRC错误",
+            Counter::RxOverruns => "接收溢出",
+            Counter::ChannelSwitches => "信道切换",
+            Counter::DuplicatesSuppressed => "重复上传",
+            Counter::TtlExpired => "跳数耗尽",
+            Counter::PathsRejected => "拒绝路径",
+            Counter::FloodsRelayed => "泛洪转发",
+            Counter::FloodDuplicates => "泛洪重复",
+            Counter::PacketsForwarded => "转发数据包",
+            Counter::PacketsDropped => "丢弃数据包",
+        }
+    }
+    
+    /// 机器可读的名称，用于导出
+    pub fn key(&self) -> &'static str {
+        match self {
+            Counter::RadioTx => "radio_tx",
+            Counter::RadioRx => "radio_rx",
+            Counter::RadioTxErrors => "radio_tx_errors",
+            Counter::ChecksumErrors => "checksum_errors",
+            Counter::BeaconsTx => "beacons_tx",
+            Counter::BeaconsRx => "beacons_rx",
+            Counter::ReliableSent => "reliable_sent",
+            Counter::Retransmissions => "retransmissions",
+            Counter::Delivered => "delivered",
+            Counter::DeliveryFailures => "delivery_failures",
+            Counter::RoutesLearned => "routes_learned",
+            Counter::RouteMisses => "route_misses",
+            Counter::LeasesGranted => "leases_granted",
+            Counter::LeasesExpired => "leases_expired",
+            Counter::RecordsStored => "records_stored",
+            Counter::RecordsOverwritten => "records_overwritten",
+            Counter::RecordsEvicted => "records_evicted",
+            Counter::SecurityRejected => "security_rejected",
+            Counter::ElectionsStarted => "elections_started",
+            Counter::MasterChanges => "master_changes",
+            Counter::LinkRetransmissions => "link_retransmissions",
+            Counter::LinkFailures => "link_failures",
+            Counter::LinkDuplicates => "link_duplicates",
+            Counter::AirtimeDeferred => "airtime_deferred",
+            Counter::MalformedPackets => "malformed_packets",
+            Counter::PoolExhausted => "pool_exhausted",
+            Counter::RadioRetries => "radio_retries",
+            Counter::CcaFailures => "cca_failures",
+            Counter::CrcErrors => "crc_errors",
+            Counter::RxOverruns => "rx_overruns",
+            Counter::ChannelSwitches => "channel_switches",
+            Counter::DuplicatesSuppressed => "duplicates_suppressed",
+            Counter::TtlExpired => "ttl_expired",
+            Counter::PathsRejected => "paths_rejected",
+            Counter::FloodsRelayed => "floods_relayed",
+            Counter::FloodDuplicates => "flood_duplicates",
+            Counter::PacketsForwarded => "packets_forwarded",
+            Counter::PacketsDropped => "packets_dropped",
+        }
+    }
+}
+
+impl Gauge {
+    /// 按编号顺序排列的所有仪表
+    pub const ALL: [Gauge; GAUGE_COUNT] = [
+        Gauge::Routes,
+        Gauge::DirectoryEntries,
+        Gauge::ActiveLeases,
+        Gauge::PendingFrames,
+        Gauge::RxQueue,
+        Gauge::BatteryLevel,
+        Gauge::AirtimeUsed,
+        Gauge::AirtimeBudget,
+        Gauge::TxQueue,
+        Gauge::PoolInUse,
+        Gauge::ReservedBandwidth,
+    ];
+    
+    /// 显示名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gauge::Routes => "路由数",
+            Gauge::DirectoryEntries => "目录条目",
+            Gauge::ActiveLeases => "有效租约",
+            Gauge::PendingFrames => "待确认帧",
+            Gauge::RxQueue => "接收队列",
+            Gauge::BatteryLevel => "电池电量",
+            Gauge::AirtimeUsed => "发射时间",
+            Gauge::AirtimeBudget => "发射预算",
+            Gauge::TxQueue => "发送队列",
+            Gauge::PoolInUse => "缓冲池占用",
+            Gauge::ReservedBandwidth => "预留带宽",
+        }
+    }
+    
+    /// 机器可读的名称，用于导出
+    pub fn key(&self) -> &'static str {
+        match self {
+            Gauge::Routes => "routes",
+            Gauge::DirectoryEntries => "directory_entries",
+            Gauge::ActiveLeases => "active_leases",
+            Gauge::PendingFrames => "pending_frames",
+            Gauge::RxQueue => "rx_queue",
+            Gauge::BatteryLevel => "battery_level",
+            Gauge::AirtimeUsed => "airtime_used_ms",
+            Gauge::AirtimeBudget => "airtime_budget_ms",
+            Gauge::TxQueue => "tx_queue",
+            Gauge::PoolInUse => "pool_in_use",
+            Gauge::ReservedBandwidth => "reserved_kbps",
+        }
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU32 = AtomicU32::new(0);
+
+static COUNTERS: [AtomicU32; COUNTER_COUNT] = [ZERO; COUNTER_COUNT];
+static GAUGES: [AtomicU32; GAUGE_COUNT] = [ZERO; GAUGE_COUNT];
+
+/// 模拟器中一个节点自己的指标
+///
+/// 同一进程中的多个模拟节点共用全局指标，全局指标是所有节点的合计。节点线程绑定自己的指标后，
+/// 该线程更新的指标同时计入这里，读取指标和快照也改为读取这里，节点上报的统计只包含本节点。
+#[cfg(feature = "simulator")]
+pub struct NodeMetrics {
+    counters: [AtomicU32; COUNTER_COUNT],
+    gauges: [AtomicU32; GAUGE_COUNT],
+}
+
+#[cfg(feature = "simulator")]
+std::thread_local! {
+    static NODE_METRICS: core::cell::RefCell<Option<std::sync::Arc<NodeMetrics>>> = const { core::cell::RefCell::new(None) };
+}
+
+#[cfg(feature = "simulator")]
+impl NodeMetrics {
+    /// 创建一组节点指标，节点线程和读取结果的一方各持有一份，都释放后回收
+    pub fn new() -> std::sync::Arc<Self> {
+        std::sync::Arc::new(Self {
+            counters: [ZERO; COUNTER_COUNT],
+            gauges: [ZERO; GAUGE_COUNT],
+        })
+    }
+    
+    /// 把当前线程绑定到这组指标，在节点线程开始运行固件之前调用，线程退出时解除绑定
+    pub fn bind(self: &std::sync::Arc<Self>) {
+        NODE_METRICS.with(|node| *node.borrow_mut() = Some(self.clone()));
+    }
+    
+    /// 读取计数器
+    pub fn get(&self, counter: Counter) -> u32 {
+        self.counters[counter as usize].load(Ordering::Relaxed)
+    }
+    
+    /// 读取所有指标的快照
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        read_snapshot(&self.counters, &self.gauges)
+    }
+}
+
+/// 在当前线程绑定的节点指标上执行f，没有绑定时返回None
+#[cfg(feature = "simulator")]
+fn with_node_metrics<R>(f: impl FnOnce(&NodeMetrics) -> R) -> Option<R> {
+    NODE_METRICS.with(|node| node.borrow().as_deref().map(f))
+}
+
+/// 计数器加一
+///
+/// 指标是全局的静态原子量，各层直接递增，不需要传递句柄。
+pub fn increment(counter: Counter) {
+    add(counter, 1);
+}
+
+/// 计数器加n，溢出后回绕
+pub fn add(counter: Counter, n: u32) {
+    COUNTERS[counter as usize].fetch_add(n, Ordering::Relaxed);
+    #[cfg(feature = "simulator")]
+    with_node_metrics(|node| node.counters[counter as usize].fetch_add(n, Ordering::Relaxed));
+}
+
+/// 设置仪表的当前值
+pub fn set(gauge: Gauge, value: u32) {
+    GAUGES[gauge as usize].store(value, Ordering::Relaxed);
+    #[cfg(feature = "simulator")]
+    with_node_metrics(|node| node.gauges[gauge as usize].store(value, Ordering::Relaxed));
+}
+
+/// 读取计数器，模拟节点的线程读取本节点的计数器
+pub fn get(counter: Counter) -> u32 {
+    #[cfg(feature = "simulator")]
+    if let Some(value) = with_node_metrics(|node| node.get(counter)) {
+        return value;
+    }
+    COUNTERS[counter as usize].load(Ordering::Relaxed)
+}
+
+/// 清零所有指标
+pub fn reset() {
+    for value in COUNTERS.iter().chain(GAUGES.iter()) {
+        value.store(0, Ordering::Relaxed);
+    }
+}
+
+/// 读取所有指标的快照，模拟节点的线程读取本节点的指标
+pub fn snapshot() -> MetricsSnapshot {
+    #[cfg(feature = "simulator")]
+    if let Some(snapshot) = with_node_metrics(NodeMetrics::snapshot) {
+        return snapshot;
+    }
+    read_snapshot(&COUNTERS, &GAUGES)
+}
+
+fn read_snapshot(counters: &[AtomicU32; COUNTER_COUNT], gauges: &[AtomicU32; GAUGE_COUNT]) -> MetricsSnapshot {
+    let mut snapshot = MetricsSnapshot {
+        counters: [0; COUNTER_COUNT],
+        gauges: [0; GAUGE_COUNT],
+    };
+    
+    for (value, counter) in snapshot.counters.iter_mut().zip(counters.iter()) {
+        *value = counter.load(Ordering::Relaxed);
+    }
+    for (value, gauge) in snapshot.gauges.iter_mut().zip(gauges.iter()) {
+        *value = gauge.load(Ordering::Relaxed);
+    }
+    
+    snapshot
+}
+
+/// 某一时刻的指标值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub counters: [u32; COUNTER_COUNT],
+    pub gauges: [u32; GAUGE_COUNT],
+}
+
+impl MetricsSnapshot {
+    /// 序列化后的长度
+    pub const SIZE: usize = 2 + (COUNTER_COUNT + GAUGE_COUNT) * 4;
+    
+    /// 计数器的值
+    pub fn counter(&self, counter: Counter) -> u32 {
+        self.counters[counter as usize]
+    }
+    
+    /// 仪表的值
+    pub fn gauge(&self, gauge: Gauge) -> u32 {
+        self.gauges[gauge as usize]
+    }
+    
+    /// 相对于较早快照的计数器增量，仪表取当前值
+    pub fn since(&self, earlier: &MetricsSnapshot) -> MetricsSnapshot {
+        let mut delta = *self;
+        for (value, before) in delta.counters.iter_mut().zip(earlier.counters.iter()) {
+            *value = value.wrapping_sub(*before);
+        }
+        delta
+    }
+    
+    /// 序列化，缓冲区不足时返回0
+    ///
+    /// 格式：计数器数(1) [计数器(4)]* 仪表数(1) [仪表(4)]*
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        if buffer.len() < Self::SIZE {
+            return 0;
+        }
+        
+        let mut offset = 0;
+        buffer[offset] = COUNTER_COUNT as u8;
+        offset += 1;
+        for value in self.counters.iter() {
+            put_u32be(&mut buffer[offset..offset + 4], *value);
+            offset += 4;
+        }
+        
+        buffer[offset] = GAUGE_COUNT as u8;
+        offset += 1;
+        for value in self.gauges.iter() {
+            put_u32be(&mut buffer[offset..offset + 4], *value);
+            offset += 4;
+        }
+        
+        offset
+    }
+    
+    /// 反序列化
+    ///
+    /// 按各自携带的个数读取，来自旧版本的快照缺少的指标为0，新版本多出的指标忽略。
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        let mut snapshot = MetricsSnapshot {
+            counters: [0; COUNTER_COUNT],
+            gauges: [0; GAUGE_COUNT],
+        };
+        
+        let mut offset = 0;
+        for values in [&mut snapshot.counters[..], &mut snapshot.gauges[..]] {
+            let count = *buffer.get(offset)? as usize;
+            let fields = buffer.get(offset + 1..offset + 1 + count * 4)?;
+            for (value, bytes) in values.iter_mut().zip(fields.chunks_exact(4)) {
+                *value = get_u32be(bytes);
+            }
+            offset += 1 + count * 4;
+        }
+        
+        Some(snapshot)
+    }
+    
+    /// 以Prometheus文本格式输出，指标名带`linknebula_`前缀，计数器带`_total`后缀
+    pub fn write_prometheus<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        for counter in Counter::ALL.iter() {
+            writeln!(out, "# HELP linknebula_{}_total {}", counter.key(), counter.name())?;
+            writeln!(out, "# TYPE linknebula_{}_total counter", counter.key())?;
+            writeln!(out, "linknebula_{}_total {}", counter.key(), self.counter(*counter))?;
+        }
+        for gauge in Gauge::ALL.iter() {
+            writeln!(out, "# HELP linknebula_{} {}", gauge.key(), gauge.name())?;
+            writeln!(out, "# TYPE linknebula_{} gauge", gauge.key())?;
+            writeln!(out, "linknebula_{} {}", gauge.key(), self.gauge(*gauge))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for counter in Counter::ALL.iter() {
+            writeln!(f, "  {}: {}", counter.name(), self.counter(*counter))?;
+        }
+        for gauge in Gauge::ALL.iter() {
+            writeln!(f, "  {}: {}", gauge.name(), self.gauge(*gauge))?;
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
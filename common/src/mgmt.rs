@@ -0,0 +1,246 @@
+use crate::hal::Hardware;
+use crate::hal::nvs::{keys, NvStorage};
+use crate::metrics::{self, Counter};
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::mgmt::{send_mgmt, verify_mgmt, MgmtAttribute, MgmtMessage, MgmtOp, MgmtStatus, MAX_MGMT_VALUE};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u32be, put_u32be};
+use crate::warn;
+
+/// 跟踪重放序号的请求方数
+pub const MAX_REQUESTERS: usize = 4;
+
+/// 保存的重放窗口长度：个数(1) 每个请求方：节点ID(6) 序号(4)
+const REPLAY_STATE_LEN: usize = 1 + 10 * MAX_REQUESTERS;
+
+/// 可远程管理的节点，按属性读取和设置运行参数
+pub trait Managed {
+    /// 读取属性，值写入out并返回长度；arg为请求中的参数
+    fn get_attribute<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        attribute: MgmtAttribute,
+        arg: &[u8],
+        out: &mut [u8]
+    ) -> Result<usize, MgmtStatus>;
+    
+    /// 设置属性，立即生效
+    fn set_attribute<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        attribute: MgmtAttribute,
+        value: &[u8]
+    ) -> Result<(), MgmtStatus>;
+    
+    /// 请求方是否允许修改属性，默认允许所有通过认证的请求方
+    fn is_admin(&self, _node: NodeId) -> bool {
+        true
+    }
+}
+
+/// 管理代理：校验发给本节点的管理请求，执行后把结果应答给请求方
+///
+/// 发往广播地址的定时参数设置在全网生效：各节点执行后不应答，由中继继续广播，
+/// 重复到达的副本按序号丢弃。各请求方的序号保存在非易失存储中，重启后旧请求仍被拒绝。
+pub struct MgmtAgent {
+    /// 各请求方最近接受的序号，最近使用的在前
+    last_sequences: [Option<(NodeId, u32)>; MAX_REQUESTERS],
+}
+
+impl MgmtAgent {
+    /// 恢复上次保存的各请求方序号
+    pub fn new<N: NvStorage>(nvs: &mut N) -> Self {
+        let mut last_sequences = [None; MAX_REQUESTERS];
+        let mut bytes = [0u8; REPLAY_STATE_LEN];
+        if let Ok(Some(len)) = nvs.nvs_read(keys::MGMT_REPLAY, &mut bytes) {
+            let count = (bytes[0] as usize).min(MAX_REQUESTERS);
+            let entries = bytes.get(1..len.min(REPLAY_STATE_LEN)).unwrap_or(&[]).chunks_exact(10).take(count);
+            for (slot, entry) in last_sequences.iter_mut().zip(entries) {
+                let mut node = [0u8; 6];
+                node.copy_from_slice(&entry[0..6]);
+                *slot = Some((NodeId(node), get_u32be(&entry[6..10])));
+            }
+        }
+        
+        Self { last_sequences }
+    }
+    
+    /// 处理发给本节点的管理请求，不是发给本节点的管理包返回false，由调用方转发
+    ///
+    /// 配置了网络密钥时请求必须通过认证且序号递增；未配置时只允许读取。
+    /// 首次收到的广播设置执行后同样返回false，由中继继续广播。
+    pub fn handle<H: Hardware, M: Managed>(&mut self, hardware: &mut H, packet: &DataPacket, node: &mut M) -> bool {
+        if packet.header.packet_type != PacketType::Mgmt as u8 {
+            return false;
+        }
+        let (request, _) = match MgmtMessage::deserialize(packet.data) {
+            Some(parsed) => parsed,
+            None => return true,
+        };
+        let broadcast = request.target.is_broadcast();
+        if !broadcast && request.target != hardware.get_node_id() {
+            return false;
+        }
+        if request.op == MgmtOp::Response {
+            return true;
+        }
+        
+        let authenticated = hardware.get_security().is_enabled();
+        let attribute = MgmtAttribute::from_u8(request.attribute);
+        // 广播只用于设置定时参数，且必须通过认证
+        if broadcast && !(authenticated && request.op == MgmtOp::Set && matches!(attribute, Some(a) if a.is_timer())) {
+            return true;
+        }
+        if authenticated {
+            if !verify_mgmt(hardware, packet.data) {
+                metrics::increment(Counter::SecurityRejected);
+                return true;
+            }
+            if !self.accept_sequence(hardware.get_nvs(), request.origin, request.sequence) {
+                // 广播经多条路径重复到达是正常的，不计为重放
+                if !broadcast {
+                    metrics::increment(Counter::SecurityRejected);
+                }
+                return true;
+            }
+        }
+        
+        if let (true, Some(attribute)) = (broadcast, attribute) {
+            let result = if node.is_admin(request.origin) {
+                node.set_attribute(hardware, attribute, request.value)
+            } else {
+                Err(MgmtStatus::Unauthorized)
+            };
+            if let Err(status) = result {
+                warn!("全网设置 {:?} 未生效: {:?}", attribute, status);
+            }
+            return false;
+        }
+        
+        let mut value = [0u8; MAX_MGMT_VALUE];
+        let result = match (attribute, request.op) {
+            (None, _) => Err(MgmtStatus::UnknownAttribute),
+            (Some(_), MgmtOp::Set) if !authenticated || !node.is_admin(request.origin) => Err(MgmtStatus::Unauthorized),
+            (Some(attribute), MgmtOp::Set) => node.set_attribute(hardware, attribute, request.value).map(|_| 0),
+            (Some(attribute), _) => node.get_attribute(hardware, attribute, request.value, &mut value),
+        };
+        
+        let (status, len) = match result {
+            Ok(len) => (MgmtStatus::Ok, len),
+            Err(status) => (status, 0),
+        };
+        let response = MgmtMessage {
+            op: MgmtOp::Response,
+            origin: hardware.get_node_id(),
+            target: request.origin,
+            sequence: request.sequence,
+            attribute: request.attribute,
+            status,
+            value: &value[..len],
+        };
+        
+        // 应答发回上一跳，由中继转发给请求方
+        let _ = send_mgmt(hardware, NodeId(packet.header.source), &response);
+        true
+    }
+    
+    /// 检查并记录请求序号，必须大于该请求方上次的序号
+    ///
+    /// 序号写入非易失存储后才接受请求。表满时替换最久未使用的请求方，
+    /// 新的管理节点不会因为表被占满而永远无法管理本节点；被替换的请求方的旧请求会重新变得可以重放。
+    fn accept_sequence<N: NvStorage>(&mut self, nvs: &mut N, origin: NodeId, sequence: u32) -> bool {
+        let mut sequences = self.last_sequences;
+        let position = sequences.iter().position(|entry| matches!(entry, Some((node, _)) if *node == origin));
+        match position {
+            Some(index) => {
+                if matches!(sequences[index], Some((_, last)) if sequence <= last) {
+                    return false;
+                }
+                sequences[..=index].rotate_right(1);
+            },
+            None => {
+                if let Some((evicted, _)) = sequences[MAX_REQUESTERS - 1] {
+                    warn!("管理请求方已满，替换最久未使用的 {}", evicted);
+                }
+                sequences.rotate_right(1);
+            },
+        }
+        sequences[0] = Some((origin, sequence));
+        
+        let mut bytes = [0u8; REPLAY_STATE_LEN];
+        let mut count = 0;
+        for (node, sequence) in sequences.iter().flatten() {
+            let offset = 1 + 10 * count;
+            bytes[offset..offset + 6].copy_from_slice(&node.0);
+            put_u32be(&mut bytes[offset + 6..offset + 10], *sequence);
+            count += 1;
+        }
+        bytes[0] = count as u8;
+        if nvs.nvs_write(keys::MGMT_REPLAY, &bytes[..1 + 10 * count]).is_err() {
+            warn!("保存管理请求序号失败，拒绝请求");
+            return false;
+        }
+        
+        self.last_sequences = sequences;
+        true
+    }
+}
+
+/// 管理请求方，序号保存在非易失存储中，重启后继续递增
+pub struct MgmtRequester {
+    next_sequence: u32,
+}
+
+impl MgmtRequester {
+    /// 恢复上次保存的序号
+    pub fn new<N: NvStorage>(nvs: &mut N) -> Self {
+        let mut bytes = [0u8; 4];
+        let next_sequence = match nvs.nvs_read(keys::MGMT_SEQUENCE, &mut bytes) {
+            Ok(Some(4)) => get_u32be(&bytes),
+            _ => 1,
+        };
+        
+        Self { next_sequence }
+    }
+    
+    /// 经由via向目标发送读取或设置请求，返回请求序号
+    pub fn request<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        target: NodeId,
+        via: NodeId,
+        op: MgmtOp,
+        attribute: MgmtAttribute,
+        value: &[u8]
+    ) -> Result<u32, ReliableError> {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        // 先保存序号再发送，重启后不会重复使用
+        let mut bytes = [0u8; 4];
+        put_u32be(&mut bytes, self.next_sequence);
+        let _ = hardware.get_nvs().nvs_write(keys::MGMT_SEQUENCE, &bytes);
+        
+        let request = MgmtMessage {
+            op,
+            origin: hardware.get_node_id(),
+            target,
+            sequence,
+            attribute: attribute as u8,
+            status: MgmtStatus::Ok,
+            value,
+        };
+        send_mgmt(hardware, via, &request)?;
+        
+        Ok(sequence)
+    }
+    
+    /// 向全网广播定时参数的设置，各节点执行后不应答，返回请求序号
+    pub fn broadcast<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        attribute: MgmtAttribute,
+        value: &[u8]
+    ) -> Result<u32, ReliableError> {
+        self.request(hardware, NodeId::BROADCAST, NodeId::BROADCAST, MgmtOp::Set, attribute, value)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,142 @@
+use core::fmt;
+
+use crate::protocol::{Beacon, DataPacket, NodeId, PacketType};
+use crate::security::SECURE_FLAG;
+
+/// 最多统计的节点数，超出时替换最久没有听到的节点
+pub const MAX_MONITORED_NODES: usize = 32;
+
+/// 按类型字节统计的槽位数，超出范围的类型计入槽位0
+const TYPE_SLOTS: usize = 32;
+
+/// 监听到的一个节点的流量
+#[derive(Debug, Clone, Copy)]
+pub struct NodeTraffic {
+    /// 发送方
+    pub node: NodeId,
+    /// 各包类型的包数，下标为类型字节
+    pub packets: [u32; TYPE_SLOTS],
+    /// 负载字节数
+    pub bytes: u32,
+    /// 其中受链路保护的包数
+    pub secured: u32,
+    /// 最近一次的接收信号强度
+    pub rssi: i8,
+    /// 第一次听到的时间
+    pub first_seen: u64,
+    /// 最近一次听到的时间
+    pub last_seen: u64,
+}
+
+impl NodeTraffic {
+    fn new(node: NodeId, now: u64) -> Self {
+        Self {
+            node,
+            packets: [0; TYPE_SLOTS],
+            bytes: 0,
+            secured: 0,
+            rssi: 0,
+            first_seen: now,
+            last_seen: now,
+        }
+    }
+    
+    /// 总包数
+    pub fn total(&self) -> u32 {
+        self.packets.iter().sum()
+    }
+}
+
+/// 被动监听的流量统计，按节点和包类型计数
+///
+/// 监听节点从不发送，只解析听到的信标和数据包头部，受保护的包不需要解密。
+pub struct TrafficMonitor {
+    nodes: [Option<NodeTraffic>; MAX_MONITORED_NODES],
+    /// 开始统计的时间
+    since: u64,
+}
+
+impl TrafficMonitor {
+    /// 创建空的统计
+    pub fn new(now: u64) -> Self {
+        Self {
+            nodes: [None; MAX_MONITORED_NODES],
+            since: now,
+        }
+    }
+    
+    /// 清空统计并重新开始计时
+    pub fn clear(&mut self, now: u64) {
+        *self = Self::new(now);
+    }
+    
+    /// 记录一个听到的数据包
+    pub fn observe_data(&mut self, packet: &DataPacket, rssi: i8, now: u64) {
+        let packet_type = packet.header.packet_type;
+        let entry = self.entry(NodeId(packet.header.source), now);
+        entry.packets[slot(packet_type & !SECURE_FLAG)] += 1;
+        entry.bytes = entry.bytes.saturating_add(packet.data.len() as u32);
+        if packet_type & SECURE_FLAG != 0 {
+            entry.secured += 1;
+        }
+        entry.rssi = rssi;
+    }
+    
+    /// 记录一个听到的信标
+    pub fn observe_beacon(&mut self, beacon: &Beacon, rssi: i8, now: u64) {
+        let entry = self.entry(NodeId(beacon.source), now);
+        entry.packets[slot(PacketType::Beacon as u8)] += 1;
+        entry.bytes = entry.bytes.saturating_add(Beacon::SIZE as u32);
+        entry.rssi = rssi;
+    }
+    
+    /// 遍历听到的节点
+    pub fn nodes(&self) -> impl Iterator<Item = &NodeTraffic> {
+        self.nodes.iter().flatten()
+    }
+    
+    /// 节点的统计，不存在时新建，表满时替换最久没有听到的节点
+    fn entry(&mut self, node: NodeId, now: u64) -> &mut NodeTraffic {
+        let index = self.nodes.iter().position(|entry| matches!(entry, Some(traffic) if traffic.node == node))
+            .or_else(|| self.nodes.iter().position(|entry| entry.is_none()))
+            .unwrap_or_else(|| {
+                self.nodes.iter().enumerate()
+                    .min_by_key(|(_, entry)| entry.map_or(0, |traffic| traffic.last_seen))
+                    .map_or(0, |(index, _)| index)
+            });
+        
+        if !matches!(self.nodes[index], Some(traffic) if traffic.node == node) {
+            self.nodes[index] = Some(NodeTraffic::new(node, now));
+        }
+        
+        let entry = self.nodes[index].as_mut().unwrap();
+        entry.last_seen = now;
+        entry
+    }
+    
+    /// 输出统计报告，时间按`now`换算为多久之前
+    pub fn report<W: fmt::Write>(&self, out: &mut W, now: u64) -> fmt::Result {
+        writeln!(out, "监听 {} 秒，听到 {} 个节点", now.saturating_sub(self.since) / 1000, self.nodes().count())?;
+        for traffic in self.nodes() {
+            write!(out, "{}  RSSI {}  {} 包 {} 字节（加密 {}）  {} 秒前:",
+                   traffic.node, traffic.rssi, traffic.total(), traffic.bytes, traffic.secured,
+                   now.saturating_sub(traffic.last_seen) / 1000)?;
+            for (packet_type, &count) in traffic.packets.iter().enumerate().filter(|(_, &count)| count > 0) {
+                match PacketType::from_u8(packet_type as u8) {
+                    Some(packet_type) => write!(out, " {:?}×{}", packet_type, count)?,
+                    None => write!(out, " 未知×{}", count)?,
+                }
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// 类型字节对应的统计槽位
+fn slot(packet_type: u8) -> usize {
+    match packet_type as usize {
+        slot if slot < TYPE_SLOTS => slot,
+        _ => 0,
+    }
+}
\ No newline at end of file
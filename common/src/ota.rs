@@ -0,0 +1,336 @@
+use sha2::{Digest, Sha256};
+
+use crate::hal::{FirmwareStorage, Hardware};
+use crate::hal::nvs::{keys, NvStorage};
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::ota::{send_ota, OtaBody, OtaMessage, OtaStatus, OTA_CHUNK_SIZE, OTA_DIGEST_LEN, OTA_TAG_LEN};
+use crate::protocol::wire::{get_u32be, put_u32be};
+use crate::security::{sign_ota, verify_ota};
+use crate::utils::checksum::update_crc32;
+
+/// 当前运行的固件版本
+pub const FIRMWARE_VERSION: u32 = 1;
+
+/// 请求分块后等待的时间（毫秒），超时后重新请求
+const CHUNK_TIMEOUT_MS: u64 = 3000;
+
+/// 同一分块的最大请求次数，超过后暂停传输，等待服务器重新通知
+const MAX_CHUNK_REQUESTS: u8 = 5;
+
+/// 每收到多少个分块保存一次接收进度
+const PROGRESS_CHECKPOINT: u32 = 8;
+
+/// 接收进度记录长度：版本(4) 长度(4) CRC(4) 偏移(4) 服务器(6) 摘要(32)
+const PROGRESS_LEN: usize = 22 + OTA_DIGEST_LEN;
+
+/// 处理固件更新消息的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtaEvent {
+    /// 不是发给本节点的更新消息
+    Ignored,
+    /// 发给本节点但未通过认证的消息，已丢弃
+    Rejected,
+    /// 传输正在进行
+    Progress { offset: u32, size: u32 },
+    /// 镜像已校验并标记，重启后生效
+    Installed { version: u32 },
+    /// 更新失败，已通知服务器
+    Failed(OtaStatus),
+    /// 多次请求没有回应，传输暂停，进度保留
+    Stalled,
+}
+
+/// 正在接收的镜像
+#[derive(Debug, Clone, Copy)]
+struct Transfer {
+    server: NodeId,
+    version: u32,
+    size: u32,
+    crc: u32,
+    /// 服务器通知的镜像摘要，收齐后与暂存区比对
+    digest: [u8; OTA_DIGEST_LEN],
+    /// 下一个需要的偏移
+    offset: u32,
+    /// 朝向服务器的上一跳，应答沿此发回
+    next_hop: Option<NodeId>,
+    /// 上次请求分块的时间
+    requested_at: u64,
+    /// 当前分块的请求次数
+    requests: u8,
+}
+
+impl Transfer {
+    fn to_bytes(&self) -> [u8; PROGRESS_LEN] {
+        let mut bytes = [0u8; PROGRESS_LEN];
+        put_u32be(&mut bytes[0..4], self.version);
+        put_u32be(&mut bytes[4..8], self.size);
+        put_u32be(&mut bytes[8..12], self.crc);
+        put_u32be(&mut bytes[12..16], self.offset);
+        bytes[16..22].copy_from_slice(&self.server.0);
+        bytes[22..PROGRESS_LEN].copy_from_slice(&self.digest);
+        bytes
+    }
+    
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < PROGRESS_LEN {
+            return None;
+        }
+        
+        let read_u32 = |offset: usize| get_u32be(&bytes[offset..offset + 4]);
+        let mut server = [0u8; 6];
+        server.copy_from_slice(&bytes[16..22]);
+        let mut digest = [0u8; OTA_DIGEST_LEN];
+        digest.copy_from_slice(&bytes[22..PROGRESS_LEN]);
+        
+        Some(Self {
+            server: NodeId(server),
+            version: read_u32(0),
+            size: read_u32(4),
+            crc: read_u32(8),
+            digest,
+            offset: read_u32(12),
+            next_hop: None,
+            requested_at: 0,
+            requests: 0,
+        })
+    }
+    
+    fn is_same_image(&self, other: &Transfer) -> bool {
+        self.server == other.server
+            && self.version == other.version
+            && self.size == other.size
+            && self.crc == other.crc
+            && self.digest == other.digest
+    }
+}
+
+/// 节点侧的固件接收端：按偏移拉取分块写入暂存区，定期保存进度，收齐后校验并标记启动
+pub struct OtaReceiver {
+    current_version: u32,
+    transfer: Option<Transfer>,
+}
+
+impl OtaReceiver {
+    /// 创建接收端，并恢复上次中断的传输进度
+    pub fn new<N: NvStorage>(current_version: u32, nvs: &mut N) -> Self {
+        let mut bytes = [0u8; PROGRESS_LEN];
+        let transfer = match nvs.nvs_read(keys::OTA_PROGRESS, &mut bytes) {
+            Ok(Some(PROGRESS_LEN)) => Transfer::from_bytes(&bytes),
+            _ => None,
+        };
+        
+        Self {
+            current_version,
+            transfer,
+        }
+    }
+    
+    /// 是否正在接收镜像
+    pub fn is_active(&self) -> bool {
+        matches!(self.transfer, Some(t) if t.next_hop.is_some())
+    }
+    
+    /// 处理收到的固件更新包
+    ///
+    /// 通知和分块都要先通过认证，确认来自持有网络密钥的服务器后才会擦写暂存区。
+    pub fn handle<H: Hardware>(&mut self, hardware: &mut H, packet: &DataPacket, current_time: u64) -> OtaEvent {
+        if packet.header.packet_type != PacketType::Ota as u8 {
+            return OtaEvent::Ignored;
+        }
+        let message = match OtaMessage::deserialize(packet.data) {
+            Some(message) if message.target == hardware.get_node_id() => message,
+            _ => return OtaEvent::Ignored,
+        };
+        if !verify_ota(hardware, &message) {
+            return OtaEvent::Rejected;
+        }
+        let previous_hop = NodeId(packet.header.source);
+        
+        match message.body {
+            OtaBody::Offer { size, crc, digest } => {
+                let transfer = Transfer {
+                    server: message.origin,
+                    version: message.version,
+                    size,
+                    crc,
+                    digest,
+                    offset: 0,
+                    next_hop: Some(previous_hop),
+                    requested_at: 0,
+                    requests: 0,
+                };
+                self.handle_offer(hardware, transfer, current_time)
+            },
+            OtaBody::Chunk { offset, data } => self.handle_chunk(hardware, &message, previous_hop, offset, data, current_time),
+            _ => OtaEvent::Ignored,
+        }
+    }
+    
+    /// 超时未收到分块时重新请求
+    pub fn poll<H: Hardware>(&mut self, hardware: &mut H, current_time: u64) -> OtaEvent {
+        let transfer = match self.transfer.as_mut() {
+            Some(transfer) if transfer.next_hop.is_some() => transfer,
+            _ => return OtaEvent::Ignored,
+        };
+        if current_time.saturating_sub(transfer.requested_at) < CHUNK_TIMEOUT_MS {
+            return OtaEvent::Progress { offset: transfer.offset, size: transfer.size };
+        }
+        
+        if transfer.requests >= MAX_CHUNK_REQUESTS {
+            // 保存进度后暂停，服务器重新通知时从这里继续
+            transfer.next_hop = None;
+            let bytes = transfer.to_bytes();
+            let _ = hardware.get_nvs().nvs_write(keys::OTA_PROGRESS, &bytes);
+            return OtaEvent::Stalled;
+        }
+        
+        let transfer = *transfer;
+        self.request_chunk(hardware, transfer, current_time)
+    }
+    
+    fn handle_offer<H: Hardware>(&mut self, hardware: &mut H, mut transfer: Transfer, current_time: u64) -> OtaEvent {
+        if transfer.version == self.current_version {
+            self.report(hardware, &transfer, OtaStatus::UpToDate);
+            return OtaEvent::Failed(OtaStatus::UpToDate);
+        }
+        if transfer.size == 0 || transfer.size as usize > hardware.get_firmware().staging_capacity() {
+            self.report(hardware, &transfer, OtaStatus::TooLarge);
+            return OtaEvent::Failed(OtaStatus::TooLarge);
+        }
+        
+        match self.transfer {
+            // 同一镜像：从保存的偏移继续
+            Some(existing) if existing.is_same_image(&transfer) => {
+                transfer.offset = existing.offset;
+            },
+            // 新镜像：擦除暂存区从头开始
+            _ => {
+                if hardware.get_firmware().erase_staging().is_err() {
+                    self.report(hardware, &transfer, OtaStatus::StorageError);
+                    return OtaEvent::Failed(OtaStatus::StorageError);
+                }
+                let _ = hardware.get_nvs().nvs_write(keys::OTA_PROGRESS, &transfer.to_bytes());
+            },
+        }
+        
+        self.request_chunk(hardware, transfer, current_time)
+    }
+    
+    fn handle_chunk<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        message: &OtaMessage,
+        previous_hop: NodeId,
+        offset: u32,
+        data: &[u8],
+        current_time: u64
+    ) -> OtaEvent {
+        let mut transfer = match self.transfer {
+            Some(transfer) if transfer.server == message.origin
+                && transfer.version == message.version
+                && transfer.next_hop.is_some() => transfer,
+            _ => return OtaEvent::Ignored,
+        };
+        
+        // 重复或乱序的分块直接忽略，等待超时重新请求
+        if offset != transfer.offset || offset as usize + data.len() > transfer.size as usize {
+            return OtaEvent::Progress { offset: transfer.offset, size: transfer.size };
+        }
+        
+        if hardware.get_firmware().write_staging(offset, data).is_err() {
+            return self.fail(hardware, &transfer, OtaStatus::StorageError);
+        }
+        transfer.offset += data.len() as u32;
+        transfer.next_hop = Some(previous_hop);
+        transfer.requests = 0;
+        
+        if transfer.offset < transfer.size {
+            if (transfer.offset / OTA_CHUNK_SIZE as u32) % PROGRESS_CHECKPOINT == 0 {
+                let _ = hardware.get_nvs().nvs_write(keys::OTA_PROGRESS, &transfer.to_bytes());
+            }
+            return self.request_chunk(hardware, transfer, current_time);
+        }
+        
+        // 收齐后从暂存区读回校验，摘要不符的镜像不会被标记启动
+        match image_digest(hardware, transfer.size) {
+            Some((crc, _)) if crc != transfer.crc => return self.fail(hardware, &transfer, OtaStatus::CrcMismatch),
+            Some((_, digest)) if digest != transfer.digest => return self.fail(hardware, &transfer, OtaStatus::DigestMismatch),
+            Some(_) => {},
+            None => return self.fail(hardware, &transfer, OtaStatus::StorageError),
+        }
+        if hardware.get_firmware().mark_for_boot(transfer.size, transfer.crc).is_err() {
+            return self.fail(hardware, &transfer, OtaStatus::StorageError);
+        }
+        
+        let _ = hardware.get_nvs().nvs_erase(keys::OTA_PROGRESS);
+        self.transfer = None;
+        self.report(hardware, &transfer, OtaStatus::Installed);
+        OtaEvent::Installed { version: transfer.version }
+    }
+    
+    /// 请求当前偏移处的分块
+    fn request_chunk<H: Hardware>(&mut self, hardware: &mut H, mut transfer: Transfer, current_time: u64) -> OtaEvent {
+        transfer.requested_at = current_time;
+        transfer.requests += 1;
+        self.transfer = Some(transfer);
+        
+        let mut request = OtaMessage {
+            origin: hardware.get_node_id(),
+            target: transfer.server,
+            version: transfer.version,
+            body: OtaBody::Request { offset: transfer.offset },
+            tag: [0; OTA_TAG_LEN],
+        };
+        sign_ota(hardware, &mut request);
+        if let Some(next_hop) = transfer.next_hop {
+            // 发送失败时等待超时再试
+            let _ = send_ota(hardware, next_hop, 0, &request);
+        }
+        
+        OtaEvent::Progress { offset: transfer.offset, size: transfer.size }
+    }
+    
+    /// 放弃当前传输并通知服务器
+    fn fail<H: Hardware>(&mut self, hardware: &mut H, transfer: &Transfer, status: OtaStatus) -> OtaEvent {
+        let _ = hardware.get_nvs().nvs_erase(keys::OTA_PROGRESS);
+        self.transfer = None;
+        self.report(hardware, transfer, status);
+        OtaEvent::Failed(status)
+    }
+    
+    /// 向服务器报告更新结果
+    fn report<H: Hardware>(&self, hardware: &mut H, transfer: &Transfer, status: OtaStatus) {
+        let mut result = OtaMessage {
+            origin: hardware.get_node_id(),
+            target: transfer.server,
+            version: transfer.version,
+            body: OtaBody::Result { status },
+            tag: [0; OTA_TAG_LEN],
+        };
+        sign_ota(hardware, &mut result);
+        if let Some(next_hop) = transfer.next_hop {
+            let _ = send_ota(hardware, next_hop, 0, &result);
+        }
+    }
+}
+
+/// 计算暂存区中镜像的CRC-32和SHA-256摘要，读取失败时返回None
+pub fn image_digest<H: Hardware>(hardware: &mut H, size: u32) -> Option<(u32, [u8; OTA_DIGEST_LEN])> {
+    let mut crc = 0xFFFF_FFFF;
+    let mut sha = Sha256::new();
+    let mut block = [0u8; OTA_CHUNK_SIZE];
+    let mut offset = 0u32;
+    
+    while offset < size {
+        let len = ((size - offset) as usize).min(block.len());
+        match hardware.get_firmware().read_staging(offset, &mut block[..len]) {
+            Ok(read) if read == len => {},
+            _ => return None,
+        }
+        crc = update_crc32(crc, &block[..len]);
+        sha.update(&block[..len]);
+        offset += len as u32;
+    }
+    
+    Some((!crc, sha.finalize().into()))
+}
\ No newline at end of file
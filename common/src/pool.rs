@@ -0,0 +1,140 @@
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use crate::hal::arq::LINK_ARQ_WINDOW;
+use crate::metrics::{self, Counter, Gauge};
+use crate::protocol::MAX_PACKET_SIZE;
+
+/// 池中每个缓冲区的长度，可容纳一个完整的数据包
+pub const PACKET_BUFFER_LEN: usize = MAX_PACKET_SIZE;
+
+/// 转发节点的发送队列和等待路由的包最多占用的缓冲区数，转发节点在编译期检查队列容量不超过这个值
+pub const FORWARD_QUEUED_BUFFERS: usize = 32;
+
+/// 每个节点缓冲池的缓冲区个数：排队转发的包和链路层重传窗口，再加上接收、编码响应和无线电发送各占一个
+pub const POOL_SIZE: usize = FORWARD_QUEUED_BUFFERS + LINK_ARQ_WINDOW + 3;
+
+/// 每个节点使用的缓冲池
+pub type NodePool = PacketPool<POOL_SIZE>;
+
+/// 4字节对齐的缓冲区，可直接用于DMA传输
+#[repr(align(4))]
+struct Slot(UnsafeCell<[u8; PACKET_BUFFER_LEN]>);
+
+/// 固定大小的数据包缓冲池，不使用堆
+///
+/// 每个缓冲区由一个原子标志保护，取得句柄的一方独占访问；句柄显式释放或被丢弃时归还缓冲区。
+pub struct PacketPool<const N: usize> {
+    slots: [Slot; N],
+    used: [AtomicBool; N],
+    in_use: AtomicU32,
+}
+
+// 缓冲区只通过独占的句柄访问，句柄的唯一性由原子标志保证
+unsafe impl<const N: usize> Sync for PacketPool<N> {}
+
+impl<const N: usize> PacketPool<N> {
+    /// 创建所有缓冲区都空闲的池
+    #[allow(clippy::declare_interior_mutable_const)]
+    pub const fn new() -> Self {
+        const FREE: AtomicBool = AtomicBool::new(false);
+        const EMPTY: Slot = Slot(UnsafeCell::new([0; PACKET_BUFFER_LEN]));
+        Self {
+            slots: [EMPTY; N],
+            used: [FREE; N],
+            in_use: AtomicU32::new(0),
+        }
+    }
+    
+    /// 取出一个空闲缓冲区，有效长度为0；池已耗尽时计数并返回None
+    pub fn acquire(&self) -> Option<PacketBuf<'_>> {
+        for (slot, used) in self.slots.iter().zip(self.used.iter()) {
+            if used.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+                let in_use = self.in_use.fetch_add(1, Ordering::Relaxed) + 1;
+                metrics::set(Gauge::PoolInUse, in_use);
+                return Some(PacketBuf { slot, used, in_use: &self.in_use, len: 0 });
+            }
+        }
+        metrics::increment(Counter::PoolExhausted);
+        None
+    }
+    
+    /// 取出一个缓冲区并复制数据，数据超过缓冲区长度或池已耗尽时返回None
+    pub fn store(&self, data: &[u8]) -> Option<PacketBuf<'_>> {
+        if data.len() > PACKET_BUFFER_LEN {
+            return None;
+        }
+        let mut buffer = self.acquire()?;
+        buffer.as_mut_slice()[..data.len()].copy_from_slice(data);
+        buffer.set_len(data.len());
+        Some(buffer)
+    }
+    
+    /// 已取出的缓冲区数
+    pub fn in_use(&self) -> usize {
+        self.in_use.load(Ordering::Relaxed) as usize
+    }
+    
+    /// 空闲的缓冲区数
+    pub fn available(&self) -> usize {
+        N - self.in_use()
+    }
+}
+
+/// 池中缓冲区的句柄，解引用为有效数据
+pub struct PacketBuf<'a> {
+    slot: &'a Slot,
+    used: &'a AtomicBool,
+    in_use: &'a AtomicU32,
+    len: usize,
+}
+
+impl<'a> PacketBuf<'a> {
+    /// 整个缓冲区，用于接收或编码
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { &mut *self.slot.0.get() }
+    }
+    
+    /// 设置有效数据长度
+    pub fn set_len(&mut self, len: usize) {
+        assert!(len <= PACKET_BUFFER_LEN);
+        self.len = len;
+    }
+    
+    /// 归还缓冲区，与丢弃句柄相同
+    pub fn release(self) {}
+}
+
+impl Deref for PacketBuf<'_> {
+    type Target = [u8];
+    
+    fn deref(&self) -> &[u8] {
+        unsafe { &(*self.slot.0.get())[..self.len] }
+    }
+}
+
+impl DerefMut for PacketBuf<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let len = self.len;
+        &mut self.as_mut_slice()[..len]
+    }
+}
+
+impl Drop for PacketBuf<'_> {
+    fn drop(&mut self) {
+        let in_use = self.in_use.fetch_sub(1, Ordering::Relaxed) - 1;
+        self.used.store(false, Ordering::Release);
+        metrics::set(Gauge::PoolInUse, in_use);
+    }
+}
+
+/// 固件的缓冲池，每个设备只运行一个节点，由硬件抽象层交给上层使用
+pub static PACKET_POOL: NodePool = PacketPool::new();
+
+/// 为模拟节点创建独立的缓冲池
+///
+/// 池中缓冲区的句柄随发送队列和重传窗口跨越主循环的各轮，因此池在进程结束前不释放。
+#[cfg(feature = "simulator")]
+pub fn node_pool() -> &'static NodePool {
+    std::boxed::Box::leak(std::boxed::Box::new(PacketPool::new()))
+}
\ No newline at end of file
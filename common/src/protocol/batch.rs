@@ -0,0 +1,146 @@
+use crate::protocol::payload::PayloadType;
+use crate::protocol::reliable::MAX_FRAME_PAYLOAD;
+use crate::protocol::wire::{get_i16be, get_u16be, get_u32be, put_i16be, put_u16be, put_u32be};
+
+/// 批量传感器数据的负载类型标识
+pub const BATCH_PAYLOAD_TYPE: u8 = PayloadType::Batch as u8;
+
+/// 批量头部长度：类型(1) 服务ID(4) 样本数(1)
+pub const BATCH_HEADER_LEN: usize = 6;
+
+/// 单个样本的编码长度
+pub const BATCH_SAMPLE_LEN: usize = 8;
+
+/// 单帧最多容纳的样本数
+pub const MAX_BATCH_SAMPLES: usize = (MAX_FRAME_PAYLOAD - BATCH_HEADER_LEN) / BATCH_SAMPLE_LEN;
+
+/// 批量中的单个样本
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatchSample {
+    /// 采样时刻距发送时刻的秒数
+    pub age_secs: u16,
+    /// 温度 (°C)
+    pub temperature: f32,
+    /// 湿度 (%)
+    pub humidity: f32,
+    /// 气压 (Pa)
+    pub pressure: f32,
+}
+
+/// 本地累积的一批传感器样本
+#[derive(Clone, Copy)]
+pub struct SampleBatch {
+    /// 样本及其采样时间戳（毫秒）
+    samples: [Option<(u64, BatchSample)>; MAX_BATCH_SAMPLES],
+    count: usize,
+}
+
+impl SampleBatch {
+    pub fn new() -> Self {
+        Self {
+            samples: [None; MAX_BATCH_SAMPLES],
+            count: 0,
+        }
+    }
+    
+    /// 添加一个样本，批量已满时返回false
+    pub fn push(&mut self, timestamp: u64, temperature: f32, humidity: f32, pressure: f32) -> bool {
+        if self.count >= MAX_BATCH_SAMPLES {
+            return false;
+        }
+        
+        self.samples[self.count] = Some((timestamp, BatchSample {
+            age_secs: 0,
+            temperature,
+            humidity,
+            pressure,
+        }));
+        self.count += 1;
+        true
+    }
+    
+    /// 样本数
+    pub fn len(&self) -> usize {
+        self.count
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+    
+    /// 最早样本的采样时间
+    pub fn oldest_timestamp(&self) -> Option<u64> {
+        self.samples[0].map(|(timestamp, _)| timestamp)
+    }
+    
+    /// 遍历样本及其采样时间戳
+    pub fn iter(&self) -> impl Iterator<Item = (u64, BatchSample)> + '_ {
+        self.samples[..self.count].iter().flatten().copied()
+    }
+    
+    /// 清空批量
+    pub fn clear(&mut self) {
+        self.samples = [None; MAX_BATCH_SAMPLES];
+        self.count = 0;
+    }
+    
+    /// 序列化批量，样本时间转换为相对发送时刻的秒数，返回写入长度
+    ///
+    /// 格式：类型(1) 服务ID(4) 样本数(1) [距今秒数(2) 温度*100(2) 湿度*100(2) 气压/10(2)]*
+    pub fn serialize(&self, service_id: u32, now: u64, buffer: &mut [u8]) -> usize {
+        let count = self.count.min(buffer.len().saturating_sub(BATCH_HEADER_LEN) / BATCH_SAMPLE_LEN);
+        if buffer.len() < BATCH_HEADER_LEN {
+            return 0;
+        }
+        
+        buffer[0] = BATCH_PAYLOAD_TYPE;
+        put_u32be(&mut buffer[1..5], service_id);
+        buffer[5] = count as u8;
+        
+        let mut offset = BATCH_HEADER_LEN;
+        for (timestamp, sample) in self.samples[..count].iter().flatten() {
+            let age_secs = (now.saturating_sub(*timestamp) / 1000).min(u16::MAX as u64) as u16;
+            let temperature = round(sample.temperature * 100.0) as i16;
+            let humidity = round(sample.humidity * 100.0) as u16;
+            let pressure = round(sample.pressure / 10.0) as u16;
+            
+            put_u16be(&mut buffer[offset..offset + 2], age_secs);
+            put_i16be(&mut buffer[offset + 2..offset + 4], temperature);
+            put_u16be(&mut buffer[offset + 4..offset + 6], humidity);
+            put_u16be(&mut buffer[offset + 6..offset + 8], pressure);
+            offset += BATCH_SAMPLE_LEN;
+        }
+        
+        offset
+    }
+}
+
+/// 四舍五入，直接截断会把0.29这类值编码成28
+fn round(value: f32) -> f32 {
+    if value < 0.0 { value - 0.5 } else { value + 0.5 }
+}
+
+/// 解析批量负载，返回服务ID和样本迭代器
+pub fn deserialize_batch(buffer: &[u8]) -> Option<(u32, impl Iterator<Item = BatchSample> + '_)> {
+    if buffer.len() < BATCH_HEADER_LEN || buffer[0] != BATCH_PAYLOAD_TYPE {
+        return None;
+    }
+    
+    let service_id = get_u32be(&buffer[1..5]);
+    let count = buffer[5] as usize;
+    let end = BATCH_HEADER_LEN + count * BATCH_SAMPLE_LEN;
+    if buffer.len() < end {
+        return None;
+    }
+    
+    let samples = buffer[BATCH_HEADER_LEN..end]
+        .chunks_exact(BATCH_SAMPLE_LEN)
+        .map(|chunk| BatchSample {
+            age_secs: get_u16be(&chunk[0..2]),
+            temperature: get_i16be(&chunk[2..4]) as f32 / 100.0,
+            humidity: get_u16be(&chunk[4..6]) as f32 / 100.0,
+            pressure: get_u16be(&chunk[6..8]) as f32 * 10.0,
+        });
+    
+    Some((service_id, samples))
+}
\ No newline at end of file
@@ -1,9 +1,20 @@
 use crate::protocol::{NodeId, PacketType, PROTOCOL_VERSION};
+use crate::protocol::wire::{get_u16le, put_u16le};
 use crate::utils::calculate_checksum;
 
+/// 信标最多被转发的跳数，超过后不再转发
+pub const MAX_BEACON_HOPS: u8 = 3;
+
+/// 信标认证码长度
+pub const BEACON_TAG_LEN: usize = 8;
+
+/// 信标标志：发送方电量告急，不再为其他节点中继
+pub const BEACON_FLAG_RELAY_UNWILLING: u8 = 0x01;
+
 /// 网络信标包，用于发现和维护网络拓扑
+///
+/// 线上格式按字段顺序排列，校验和为小端，收发都经过[`Beacon::to_bytes`]和[`Beacon::from_bytes`]。
 #[derive(Debug, Clone, Copy)]
-#[repr(C, packed)]
 pub struct Beacon {
     /// 协议版本
     pub version: u8,
@@ -19,11 +30,43 @@ pub struct Beacon {
     pub hop_count: u8,
     /// 预留字段
     pub reserved: [u8; 3],
+    /// 网络密钥派生的认证码，不覆盖跳数，转发时不需要重新计算；未配置网络密钥时为0
+    pub tag: [u8; BEACON_TAG_LEN],
     /// 校验和
     pub checksum: u16,
 }
 
+/// 节点角色，保存在信标预留字段的第0字节；第1字节为信标序号，用于转发去重；第2字节为标志位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+#[repr(u8)]
+pub enum NodeRole {
+    /// 未声明角色（旧版本节点）
+    Unknown = 0x00,
+    /// 客户端
+    Client = 0x01,
+    /// 转发节点
+    Forward = 0x02,
+    /// 服务器
+    Server = 0x03,
+}
+
+impl NodeRole {
+    /// 从角色字节解析，未知值视为未声明
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0x01 => NodeRole::Client,
+            0x02 => NodeRole::Forward,
+            0x03 => NodeRole::Server,
+            _ => NodeRole::Unknown,
+        }
+    }
+}
+
 impl Beacon {
+    /// 线上长度
+    pub const SIZE: usize = 24;
+    
     pub fn new(source: NodeId, battery_level: u8, rssi: i8) -> Self {
         let mut beacon = Self {
             version: PROTOCOL_VERSION,
@@ -33,6 +76,7 @@ impl Beacon {
             rssi,
             hop_count: 0,
             reserved: [0; 3],
+            tag: [0; BEACON_TAG_LEN],
             checksum: 0, // 临时值
         };
         
@@ -41,27 +85,122 @@ impl Beacon {
         beacon
     }
     
+    /// 创建声明了节点角色的信标
+    pub fn with_role(source: NodeId, battery_level: u8, rssi: i8, role: NodeRole) -> Self {
+        let mut beacon = Self::new(source, battery_level, rssi);
+        beacon.reserved[0] = role as u8;
+        beacon.update_checksum();
+        beacon
+    }
+    
+    /// 发送方声明的节点角色
+    pub fn role(&self) -> NodeRole {
+        NodeRole::from_u8(self.reserved[0])
+    }
+    
+    /// 信标序号，发送方每发一次信标加一，旧版本节点恒为0
+    pub fn sequence(&self) -> u8 {
+        self.reserved[1]
+    }
+    
+    /// 设置信标序号
+    pub fn set_sequence(&mut self, sequence: u8) {
+        self.reserved[1] = sequence;
+        self.update_checksum();
+    }
+    
+    /// 发送方是否声明不再为其他节点中继（电量告急）
+    pub fn relay_unwilling(&self) -> bool {
+        self.reserved[2] & BEACON_FLAG_RELAY_UNWILLING != 0
+    }
+    
+    /// 设置是否不再为其他节点中继
+    pub fn set_relay_unwilling(&mut self, unwilling: bool) {
+        if unwilling {
+            self.reserved[2] |= BEACON_FLAG_RELAY_UNWILLING;
+        } else {
+            self.reserved[2] &= !BEACON_FLAG_RELAY_UNWILLING;
+        }
+        self.update_checksum();
+    }
+    
+    /// 设置认证码
+    pub fn set_tag(&mut self, tag: [u8; BEACON_TAG_LEN]) {
+        self.tag = tag;
+        self.update_checksum();
+    }
+    
+    /// 认证码覆盖的内容：跳数、认证码和校验和置0后的线上格式
+    pub fn signed_bytes(&self) -> [u8; Self::SIZE] {
+        let mut copy = *self;
+        copy.hop_count = 0;
+        copy.tag = [0; BEACON_TAG_LEN];
+        copy.checksum = 0;
+        copy.to_bytes()
+    }
+    
+    /// 转发用的信标副本，跳数加一；已达到最大跳数时返回None
+    pub fn relayed(&self) -> Option<Self> {
+        if self.hop_count >= MAX_BEACON_HOPS {
+            return None;
+        }
+        
+        let mut beacon = *self;
+        beacon.hop_count += 1;
+        beacon.update_checksum();
+        Some(beacon)
+    }
+    
+    /// 序列化为线上格式
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0] = self.version;
+        bytes[1] = self.packet_type;
+        bytes[2..8].copy_from_slice(&self.source);
+        bytes[8] = self.battery_level;
+        bytes[9] = self.rssi as u8;
+        bytes[10] = self.hop_count;
+        bytes[11..14].copy_from_slice(&self.reserved);
+        bytes[14..22].copy_from_slice(&self.tag);
+        put_u16le(&mut bytes[22..24], self.checksum);
+        bytes
+    }
+    
+    /// 从原始字节解析信标，长度或类型不符时返回None
+    pub fn from_bytes(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() != Self::SIZE || buffer[1] != PacketType::Beacon as u8 {
+            return None;
+        }
+        
+        let mut source = [0u8; 6];
+        source.copy_from_slice(&buffer[2..8]);
+        let mut reserved = [0u8; 3];
+        reserved.copy_from_slice(&buffer[11..14]);
+        let mut tag = [0u8; BEACON_TAG_LEN];
+        tag.copy_from_slice(&buffer[14..22]);
+        
+        Some(Self {
+            version: buffer[0],
+            packet_type: buffer[1],
+            source,
+            battery_level: buffer[8],
+            rssi: buffer[9] as i8,
+            hop_count: buffer[10],
+            reserved,
+            tag,
+            checksum: get_u16le(&buffer[22..24]),
+        })
+    }
+    
     pub fn update_checksum(&mut self) {
         // 设置校验和为0进行计算
         self.checksum = 0;
-        let data = unsafe {
-            core::slice::from_raw_parts(
-                self as *const Self as *const u8,
-                core::mem::size_of::<Self>(),
-            )
-        };
-        self.checksum = calculate_checksum(data);
+        self.checksum = calculate_checksum(&self.to_bytes());
     }
     
     pub fn is_valid(&self) -> bool {
         let mut copy = *self;
         copy.checksum = 0;
-        let data = unsafe {
-            core::slice::from_raw_parts(
-                &copy as *const Self as *const u8,
-                core::mem::size_of::<Self>(),
-            )
-        };
-        calculate_checksum(data) == self.checksum
+        calculate_checksum(&copy.to_bytes()) == self.checksum
     }
 } 
\ No newline at end of file
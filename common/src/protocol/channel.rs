@@ -0,0 +1,164 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u16be, get_u32be, get_u64be, put_u16be, put_u32be, put_u64be};
+use crate::security::send_secure;
+
+/// 可用的第一个信道
+pub const FIRST_CHANNEL: u8 = 11;
+
+/// 可用信道数（11-26）
+pub const CHANNEL_COUNT: usize = 16;
+
+/// 没有测量的信道的能量值
+pub const ENERGY_UNKNOWN: i8 = i8::MIN;
+
+/// 信道质量报告长度：类型(1) 主节点(6) 报告节点(6) 当前信道(1) CRC错误率(2) [能量(1)]*16
+pub const CHANNEL_REPORT_LEN: usize = 16 + CHANNEL_COUNT;
+
+/// 切换通告长度：类型(1) 主节点(6) 切换ID(2) 新信道(1) 切换时间(8) 宽限期(4)
+pub const CHANNEL_SWITCH_LEN: usize = 22;
+
+/// 信道协调消息类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ChannelMessageType {
+    /// 转发节点的信道质量报告
+    Report = 0x01,
+    /// 主节点的信道切换通告
+    Switch = 0x02,
+}
+
+/// 信道号是否在可用范围内
+pub fn is_valid_channel(channel: u8) -> bool {
+    (FIRST_CHANNEL..FIRST_CHANNEL + CHANNEL_COUNT as u8).contains(&channel)
+}
+
+/// 转发节点测量的信道质量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelReport {
+    /// 报告发往的主节点
+    pub master: NodeId,
+    /// 报告节点
+    pub reporter: NodeId,
+    /// 测量时所在的信道
+    pub channel: u8,
+    /// 当前信道上的CRC错误率（千分比）
+    pub crc_permille: u16,
+    /// 各信道的能量检测值（dBm），[`ENERGY_UNKNOWN`]表示没有测量
+    pub energy: [i8; CHANNEL_COUNT],
+}
+
+impl ChannelReport {
+    /// 指定信道的能量检测值
+    pub fn energy_on(&self, channel: u8) -> Option<i8> {
+        if !is_valid_channel(channel) {
+            return None;
+        }
+        match self.energy[(channel - FIRST_CHANNEL) as usize] {
+            ENERGY_UNKNOWN => None,
+            energy => Some(energy),
+        }
+    }
+}
+
+/// 信道协调消息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelMessage {
+    /// 发往主节点的信道质量报告
+    Report(ChannelReport),
+    /// 主节点广播的切换通告，转发节点对每个切换ID只转发一次
+    ///
+    /// 所有节点在网络时间`switch_at`切换到新信道，之后的`grace_ms`内中继在新旧信道之间交替监听。
+    Switch { master: NodeId, switch_id: u16, channel: u8, switch_at: u64, grace_ms: u32 },
+}
+
+impl ChannelMessage {
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        match self {
+            ChannelMessage::Report(report) => {
+                if buffer.len() < CHANNEL_REPORT_LEN {
+                    return 0;
+                }
+                buffer[0] = ChannelMessageType::Report as u8;
+                buffer[1..7].copy_from_slice(&report.master.0);
+                buffer[7..13].copy_from_slice(&report.reporter.0);
+                buffer[13] = report.channel;
+                put_u16be(&mut buffer[14..16], report.crc_permille);
+                for (byte, energy) in buffer[16..CHANNEL_REPORT_LEN].iter_mut().zip(report.energy.iter()) {
+                    *byte = *energy as u8;
+                }
+                CHANNEL_REPORT_LEN
+            },
+            ChannelMessage::Switch { master, switch_id, channel, switch_at, grace_ms } => {
+                if buffer.len() < CHANNEL_SWITCH_LEN {
+                    return 0;
+                }
+                buffer[0] = ChannelMessageType::Switch as u8;
+                buffer[1..7].copy_from_slice(&master.0);
+                put_u16be(&mut buffer[7..9], switch_id);
+                buffer[9] = *channel;
+                put_u64be(&mut buffer[10..18], switch_at);
+                put_u32be(&mut buffer[18..22], grace_ms);
+                CHANNEL_SWITCH_LEN
+            },
+        }
+    }
+    
+    /// 反序列化，截断或信道超出范围时返回None
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        let read_node = |offset: usize| {
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&buffer[offset..offset + 6]);
+            NodeId(id)
+        };
+        
+        match *buffer.first()? {
+            t if t == ChannelMessageType::Report as u8 => {
+                if buffer.len() < CHANNEL_REPORT_LEN || !is_valid_channel(buffer[13]) {
+                    return None;
+                }
+                let mut energy = [ENERGY_UNKNOWN; CHANNEL_COUNT];
+                for (energy, byte) in energy.iter_mut().zip(buffer[16..CHANNEL_REPORT_LEN].iter()) {
+                    *energy = *byte as i8;
+                }
+                Some(ChannelMessage::Report(ChannelReport {
+                    master: read_node(1),
+                    reporter: read_node(7),
+                    channel: buffer[13],
+                    crc_permille: get_u16be(&buffer[14..16]),
+                    energy,
+                }))
+            },
+            t if t == ChannelMessageType::Switch as u8 => {
+                if buffer.len() < CHANNEL_SWITCH_LEN || !is_valid_channel(buffer[9]) {
+                    return None;
+                }
+                Some(ChannelMessage::Switch {
+                    master: read_node(1),
+                    switch_id: get_u16be(&buffer[7..9]),
+                    channel: buffer[9],
+                    switch_at: get_u64be(&buffer[10..18]),
+                    grace_ms: get_u32be(&buffer[18..22]),
+                })
+            },
+            _ => None,
+        }
+    }
+}
+
+/// 发送信道协调消息
+pub fn send_channel_message<H: Hardware>(
+    hardware: &mut H,
+    destination: NodeId,
+    message: &ChannelMessage
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; CHANNEL_REPORT_LEN];
+    let len = message.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, destination, PacketType::ChannelPlan, 0, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
@@ -0,0 +1,755 @@
+use crate::protocol::NodeId;
+use crate::protocol::data::MAX_REASSEMBLED_SIZE;
+use crate::protocol::payload::PayloadType;
+use crate::protocol::wire::{get_f32be, get_u16be, get_u32be, get_u64be, put_f32be, put_u16be, put_u32be, put_u64be};
+use crate::security::{MANAGEMENT_TAG_LEN, MAX_SECURE_PAYLOAD};
+
+/// 命令数据包的负载类型标识（应用负载第0字节）
+pub const COMMAND_PAYLOAD_TYPE: u8 = PayloadType::Command as u8;
+
+/// 特权命令末尾附带的认证码长度
+pub const COMMAND_TAG_LEN: usize = MANAGEMENT_TAG_LEN;
+
+/// 命令类型，服务器和客户端共用同一套编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+#[repr(u8)]
+pub enum CommandType {
+    /// 查询传感器数据：参数为空或见`QueryParams`，可附带`QueryPage`；结果分片应答，见`QueryChunk`
+    Query = 0x01,
+    /// 配置参数
+    Configure = 0x02,
+    /// 清空数据
+    Clear = 0x03,
+    /// 重启设备
+    Reboot = 0x04,
+    /// 查询运行统计
+    Stats = 0x05,
+    /// 读取本地闪存日志：起始序号(4) 最多样本数(1)
+    ReadLog = 0x06,
+    /// 查询每个节点的最新记录：参数为空时返回所有节点，或节点ID(6)只查该节点
+    Latest = 0x07,
+    /// 按时间窗口聚合节点的一个测量值：见`AggregateParams`
+    Aggregate = 0x08,
+}
+
+impl CommandType {
+    /// 从命令类型字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(CommandType::Query),
+            0x02 => Some(CommandType::Configure),
+            0x03 => Some(CommandType::Clear),
+            0x04 => Some(CommandType::Reboot),
+            0x05 => Some(CommandType::Stats),
+            0x06 => Some(CommandType::ReadLog),
+            0x07 => Some(CommandType::Latest),
+            0x08 => Some(CommandType::Aggregate),
+            _ => None,
+        }
+    }
+    
+    /// 是否为特权命令：参数之后附带认证码，见[`crate::security::command_tag`]
+    pub fn is_privileged(&self) -> bool {
+        matches!(self, CommandType::Configure | CommandType::Reboot)
+    }
+}
+
+/// 命令执行结果，作为响应的第一个字节
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+#[repr(u8)]
+pub enum CommandStatus {
+    /// 执行成功
+    Ok = 0x01,
+    /// 参数错误
+    InvalidParameter = 0x02,
+    /// 本节点不支持该命令
+    Unsupported = 0x03,
+    /// 参数已生效但保存失败
+    PersistFailed = 0x04,
+    /// 重启或修改身份、密钥的命令未通过认证
+    Unauthorized = 0x05,
+}
+
+impl CommandStatus {
+    /// 从响应中的状态字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(CommandStatus::Ok),
+            0x02 => Some(CommandStatus::InvalidParameter),
+            0x03 => Some(CommandStatus::Unsupported),
+            0x04 => Some(CommandStatus::PersistFailed),
+            0x05 => Some(CommandStatus::Unauthorized),
+            _ => None,
+        }
+    }
+}
+
+/// 客户端可配置参数，配置命令参数格式为 [参数ID(1) 值]*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ConfigParam {
+    /// 采样间隔：毫秒(4)
+    SampleInterval = 0x01,
+    /// 服务质量：服务类型(1) 最小带宽(2) 最大延迟(2) 可靠性(1)
+    Qos = 0x02,
+    /// 目标服务器：节点ID(6)
+    Server = 0x03,
+    /// 无线信道：信道(1)
+    Channel = 0x04,
+    /// 节点身份：节点ID(6)，重启后生效
+    NodeIdentity = 0x05,
+    /// 网络密钥：密钥(16)，重启后生效；不接受全零密钥，无线下发不能关闭链路保护
+    NetworkKey = 0x06,
+}
+
+impl ConfigParam {
+    /// 从参数ID解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(ConfigParam::SampleInterval),
+            0x02 => Some(ConfigParam::Qos),
+            0x03 => Some(ConfigParam::Server),
+            0x04 => Some(ConfigParam::Channel),
+            0x05 => Some(ConfigParam::NodeIdentity),
+            0x06 => Some(ConfigParam::NetworkKey),
+            _ => None,
+        }
+    }
+    
+    /// 参数值的长度
+    pub fn value_len(&self) -> usize {
+        match self {
+            ConfigParam::SampleInterval => 4,
+            ConfigParam::Qos => 6,
+            ConfigParam::Server => 6,
+            ConfigParam::Channel => 1,
+            ConfigParam::NodeIdentity => 6,
+            ConfigParam::NetworkKey => 16,
+        }
+    }
+}
+
+/// 查询参数长度：起始时间(8) 结束时间(8) 降采样方式(1) 降采样参数(2)
+pub const QUERY_PARAMS_LEN: usize = 19;
+
+/// 查询时在服务器上对记录降采样，长时间的历史也能通过窄带链路浏览
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Downsample {
+    /// 返回范围内的全部记录
+    None,
+    /// 每N分钟最多一条记录
+    PerMinutes(u16),
+    /// 在记录覆盖的时间范围内均匀选取最多M条记录
+    MaxRecords(u16),
+}
+
+/// 查询命令的参数
+///
+/// 参数为空时查询全部记录；只有起始和结束时间时不降采样。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryParams {
+    /// 起始时间（网络时间，毫秒，含）
+    pub start_time: u64,
+    /// 结束时间（网络时间，毫秒，含）
+    pub end_time: u64,
+    /// 降采样方式
+    pub downsample: Downsample,
+}
+
+impl QueryParams {
+    /// 查询全部记录
+    pub const ALL: Self = Self { start_time: 0, end_time: u64::MAX, downsample: Downsample::None };
+    
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> [u8; QUERY_PARAMS_LEN] {
+        let (mode, value) = match self.downsample {
+            Downsample::None => (0, 0),
+            Downsample::PerMinutes(minutes) => (1, minutes),
+            Downsample::MaxRecords(max) => (2, max),
+        };
+        
+        let mut bytes = [0u8; QUERY_PARAMS_LEN];
+        put_u64be(&mut bytes[0..8], self.start_time);
+        put_u64be(&mut bytes[8..16], self.end_time);
+        bytes[16] = mode;
+        put_u16be(&mut bytes[17..19], value);
+        bytes
+    }
+    
+    /// 从命令参数解析，格式错误或降采样参数为0时返回None
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.is_empty() {
+            return Some(Self::ALL);
+        }
+        if bytes.len() != 16 && bytes.len() != QUERY_PARAMS_LEN {
+            return None;
+        }
+        
+        let downsample = match bytes.get(16..QUERY_PARAMS_LEN) {
+            Some(params) => match (params[0], get_u16be(&params[1..3])) {
+                (0, _) => Downsample::None,
+                (1, minutes) if minutes > 0 => Downsample::PerMinutes(minutes),
+                (2, max) if max > 0 => Downsample::MaxRecords(max),
+                _ => return None,
+            },
+            _ => Downsample::None,
+        };
+        
+        Some(Self {
+            start_time: get_u64be(&bytes[0..8]),
+            end_time: get_u64be(&bytes[8..16]),
+            downsample,
+        })
+    }
+}
+
+/// 分页参数长度：查询令牌(2) 起始偏移(4)
+pub const QUERY_PAGE_LEN: usize = 6;
+
+/// 带分页的查询参数长度：查询参数(19) 分页参数(6)
+pub const PAGED_QUERY_PARAMS_LEN: usize = QUERY_PARAMS_LEN + QUERY_PAGE_LEN;
+
+/// 查询结果的分页参数，附在查询参数之后
+///
+/// 令牌由请求方选择，服务器在每个分片中原样带回；偏移为结果中的字节偏移，续传时从该处继续发送。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueryPage {
+    pub token: u16,
+    pub offset: u32,
+}
+
+impl QueryPage {
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> [u8; QUERY_PAGE_LEN] {
+        let mut bytes = [0u8; QUERY_PAGE_LEN];
+        put_u16be(&mut bytes[0..2], self.token);
+        put_u32be(&mut bytes[2..6], self.offset);
+        bytes
+    }
+    
+    /// 从字节解析
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != QUERY_PAGE_LEN {
+            return None;
+        }
+        
+        Some(Self {
+            token: get_u16be(&bytes[0..2]),
+            offset: get_u32be(&bytes[2..6]),
+        })
+    }
+}
+
+/// 序列化带分页的查询参数
+pub fn paged_query_bytes(params: &QueryParams, page: &QueryPage) -> [u8; PAGED_QUERY_PARAMS_LEN] {
+    let mut bytes = [0u8; PAGED_QUERY_PARAMS_LEN];
+    bytes[..QUERY_PARAMS_LEN].copy_from_slice(&params.to_bytes());
+    bytes[QUERY_PARAMS_LEN..].copy_from_slice(&page.to_bytes());
+    bytes
+}
+
+/// 解析查询参数和可选的分页参数，没有分页参数时从令牌0的开头查询
+pub fn parse_paged_query(bytes: &[u8]) -> Option<(QueryParams, QueryPage)> {
+    // 查询参数本身为0、16或19字节，加上分页参数后的长度互不重叠
+    match bytes.len().checked_sub(QUERY_PAGE_LEN) {
+        Some(params_len @ (0 | 16 | QUERY_PARAMS_LEN)) => {
+            let (params, page) = bytes.split_at(params_len);
+            Some((QueryParams::from_bytes(params)?, QueryPage::from_bytes(page)?))
+        },
+        _ => Some((QueryParams::from_bytes(bytes)?, QueryPage::default())),
+    }
+}
+
+/// 查询分片头部长度：命令类型(1) 状态(1) 查询令牌(2) 偏移(4) 标志(1)
+pub const QUERY_CHUNK_HEADER_LEN: usize = 9;
+
+/// 单个查询分片最多携带的结果字节数
+pub const MAX_QUERY_CHUNK_DATA: usize = MAX_SECURE_PAYLOAD - QUERY_CHUNK_HEADER_LEN;
+
+/// 分片标志：之后还有数据
+pub const QUERY_CHUNK_MORE: u8 = 0x01;
+
+/// 分片标志：本次应答的最后一个分片，剩余数据需要带偏移重新查询
+pub const QUERY_CHUNK_PAUSED: u8 = 0x02;
+
+/// 查询结果的一个分片
+///
+/// 查询结果可能有几KB，超出一个数据包；服务器把结果按字节偏移切开连续发送，
+/// 请求方按偏移拼接，丢失分片后从缺口处续传而不必重新接收整个结果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryChunk<'a> {
+    /// 请求中的查询令牌
+    pub token: u16,
+    /// 分片数据在结果中的字节偏移
+    pub offset: u32,
+    /// 之后还有数据
+    pub more: bool,
+    /// 服务器在此暂停发送，等待请求方续传
+    pub paused: bool,
+    pub data: &'a [u8],
+}
+
+impl<'a> QueryChunk<'a> {
+    /// 序列化为应答负载，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let len = QUERY_CHUNK_HEADER_LEN + self.data.len();
+        if buffer.len() < len {
+            return 0;
+        }
+        
+        buffer[0] = CommandType::Query as u8;
+        buffer[1] = CommandStatus::Ok as u8;
+        put_u16be(&mut buffer[2..4], self.token);
+        put_u32be(&mut buffer[4..8], self.offset);
+        buffer[8] = 0;
+        if self.more {
+            buffer[8] |= QUERY_CHUNK_MORE;
+        }
+        if self.paused {
+            buffer[8] |= QUERY_CHUNK_PAUSED;
+        }
+        buffer[QUERY_CHUNK_HEADER_LEN..len].copy_from_slice(self.data);
+        len
+    }
+    
+    /// 解析查询分片；状态不是成功时返回None
+    pub fn deserialize(data: &'a [u8]) -> Option<Self> {
+        if data.len() < QUERY_CHUNK_HEADER_LEN
+            || data[0] != CommandType::Query as u8
+            || data[1] != CommandStatus::Ok as u8 {
+            return None;
+        }
+        
+        Some(Self {
+            token: get_u16be(&data[2..4]),
+            offset: get_u32be(&data[4..8]),
+            more: data[8] & QUERY_CHUNK_MORE != 0,
+            paused: data[8] & QUERY_CHUNK_PAUSED != 0,
+            data: &data[QUERY_CHUNK_HEADER_LEN..],
+        })
+    }
+}
+
+/// 分片查询应答的接收进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryProgress {
+    /// 分片已按顺序接收，后续分片仍在发送
+    Receiving,
+    /// 服务器暂停发送或中间的分片丢失，需要带`QueryReassembler::next_page`重新查询
+    Resume,
+    /// 全部结果已收齐
+    Complete,
+    /// 服务器拒绝了查询
+    Rejected(CommandStatus),
+    /// 结果超出接收缓冲区
+    Overflow,
+    /// 不属于本次查询、重复或格式错误的应答
+    Ignored,
+}
+
+/// 在请求方重组分片的查询结果，数据写入调用方提供的缓冲区
+///
+/// 缓冲区可以是借用的切片（no_std客户端）或自有的数组、`Vec`（网关跨多次接收保存查询状态）。
+/// 只按顺序接收分片：出现缺口时报告一次续传，之后的乱序分片被忽略，直到续传的分片到达；
+/// 续传请求也丢失时，调用方超时后同样用`next_page`重新查询。
+pub struct QueryReassembler<B> {
+    token: u16,
+    buffer: B,
+    /// 从结果开头连续收到的字节数
+    received: usize,
+    /// 已报告缺口，等待续传
+    stalled: bool,
+    complete: bool,
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> QueryReassembler<B> {
+    /// 开始接收令牌对应的查询结果
+    pub fn new(token: u16, buffer: B) -> Self {
+        Self { token, buffer, received: 0, stalled: false, complete: false }
+    }
+    
+    /// 查询令牌
+    pub fn token(&self) -> u16 {
+        self.token
+    }
+    
+    /// 续传请求的分页参数，从已连续收到的数据之后开始
+    pub fn next_page(&self) -> QueryPage {
+        QueryPage { token: self.token, offset: self.received as u32 }
+    }
+    
+    /// 已收齐的结果，未完成时返回None
+    pub fn data(&self) -> Option<&[u8]> {
+        self.complete.then(|| &self.buffer.as_ref()[..self.received])
+    }
+    
+    /// 处理一个查询应答
+    pub fn accept(&mut self, response: &[u8]) -> QueryProgress {
+        if self.complete {
+            return QueryProgress::Ignored;
+        }
+        if let &[command, status] = response {
+            return match CommandStatus::from_u8(status) {
+                Some(status) if command == CommandType::Query as u8 && status != CommandStatus::Ok => {
+                    QueryProgress::Rejected(status)
+                },
+                _ => QueryProgress::Ignored,
+            };
+        }
+        
+        let chunk = match QueryChunk::deserialize(response) {
+            Some(chunk) if chunk.token == self.token => chunk,
+            _ => return QueryProgress::Ignored,
+        };
+        let offset = chunk.offset as usize;
+        if offset < self.received {
+            return QueryProgress::Ignored;
+        }
+        if offset > self.received {
+            if self.stalled {
+                return QueryProgress::Ignored;
+            }
+            self.stalled = true;
+            return QueryProgress::Resume;
+        }
+        
+        let end = offset + chunk.data.len();
+        let buffer = self.buffer.as_mut();
+        if end > buffer.len() {
+            return QueryProgress::Overflow;
+        }
+        buffer[offset..end].copy_from_slice(chunk.data);
+        self.received = end;
+        self.stalled = false;
+        
+        if !chunk.more {
+            self.complete = true;
+            QueryProgress::Complete
+        } else if chunk.paused {
+            QueryProgress::Resume
+        } else {
+            QueryProgress::Receiving
+        }
+    }
+}
+
+/// 日志响应头部长度：命令类型(1) 状态(1) 首个样本序号(4) 样本数(1)
+pub const LOG_RESPONSE_HEADER_LEN: usize = 7;
+
+/// 单个日志响应最多容纳的样本数，超过一帧的响应分片发送，由接收端重组
+pub const MAX_LOG_RESPONSE_SAMPLES: usize = (MAX_REASSEMBLED_SIZE - LOG_RESPONSE_HEADER_LEN) / LoggedSample::SIZE;
+
+const _: () = assert!(MAX_LOG_RESPONSE_SAMPLES <= u8::MAX as usize);
+
+/// 本地闪存日志中的一个样本
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoggedSample {
+    /// 采样时间戳（毫秒）
+    pub timestamp: u64,
+    /// 温度 (°C)
+    pub temperature: f32,
+    /// 湿度 (%)
+    pub humidity: f32,
+    /// 气压 (Pa)
+    pub pressure: f32,
+}
+
+impl LoggedSample {
+    /// 编码长度：时间戳(8) 温度(4) 湿度(4) 气压(4)
+    pub const SIZE: usize = 20;
+    
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        put_u64be(&mut bytes[0..8], self.timestamp);
+        put_f32be(&mut bytes[8..12], self.temperature);
+        put_f32be(&mut bytes[12..16], self.humidity);
+        put_f32be(&mut bytes[16..20], self.pressure);
+        bytes
+    }
+    
+    /// 从字节解析
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::SIZE {
+            return None;
+        }
+        
+        Some(Self {
+            timestamp: get_u64be(&bytes[0..8]),
+            temperature: get_f32be(&bytes[8..12]),
+            humidity: get_f32be(&bytes[12..16]),
+            pressure: get_f32be(&bytes[16..20]),
+        })
+    }
+}
+
+/// 解析日志响应，返回首个样本序号和样本迭代器；状态不是成功时返回None
+///
+/// 样本数为0表示从请求的序号起已没有更多样本。
+pub fn deserialize_log_response(data: &[u8]) -> Option<(u32, impl Iterator<Item = LoggedSample> + '_)> {
+    if data.len() < LOG_RESPONSE_HEADER_LEN
+        || data[0] != CommandType::ReadLog as u8
+        || data[1] != CommandStatus::Ok as u8 {
+        return None;
+    }
+    
+    let first_seq = get_u32be(&data[2..6]);
+    let count = data[6] as usize;
+    let end = LOG_RESPONSE_HEADER_LEN + count * LoggedSample::SIZE;
+    if data.len() < end {
+        return None;
+    }
+    
+    let samples = data[LOG_RESPONSE_HEADER_LEN..end]
+        .chunks_exact(LoggedSample::SIZE)
+        .filter_map(LoggedSample::from_bytes);
+    
+    Some((first_seq, samples))
+}
+
+/// 最新值响应中每条记录的长度：节点ID(6) 时间戳(8) 温度(2) 湿度(2) 气压(2)
+pub const LATEST_RECORD_LEN: usize = 20;
+
+/// 单个最新值响应最多容纳的记录数，超出的节点需要按节点ID单独查询
+pub const MAX_LATEST_RECORDS: usize = (MAX_SECURE_PAYLOAD - 2) / LATEST_RECORD_LEN;
+
+/// 解析最新值响应 命令类型(1) 状态(1) 记录*，返回来源节点和样本；状态不是成功时返回None
+pub fn deserialize_latest_response(data: &[u8]) -> Option<impl Iterator<Item = (NodeId, LoggedSample)> + '_> {
+    if data.len() < 2
+        || data[0] != CommandType::Latest as u8
+        || data[1] != CommandStatus::Ok as u8 {
+        return None;
+    }
+    deserialize_records(&data[2..])
+}
+
+/// 解析连续的存储记录，查询结果和最新值响应使用同样的记录格式；长度不是整条记录时返回None
+pub fn deserialize_records(data: &[u8]) -> Option<impl Iterator<Item = (NodeId, LoggedSample)> + '_> {
+    if data.len() % LATEST_RECORD_LEN != 0 {
+        return None;
+    }
+    
+    let records = data.chunks_exact(LATEST_RECORD_LEN).map(|record| {
+        let mut node_id = [0u8; 6];
+        node_id.copy_from_slice(&record[0..6]);
+        let read_u16 = |offset: usize| get_u16be(&record[offset..offset + 2]) as f32;
+        
+        (NodeId::new(node_id), LoggedSample {
+            timestamp: get_u64be(&record[6..14]),
+            temperature: read_u16(14) / 100.0,
+            humidity: read_u16(16) / 100.0,
+            pressure: read_u16(18) * 100.0,
+        })
+    });
+    
+    Some(records)
+}
+
+/// 聚合查询参数长度：节点ID(6) 测量值(1) 起始时间(8) 结束时间(8) 窗口方式(1) 窗口参数(2) 窗口偏移(2)
+pub const AGGREGATE_PARAMS_LEN: usize = 28;
+
+/// 不带窗口偏移的聚合查询参数长度，旧请求方从第一个窗口开始
+const AGGREGATE_PARAMS_LEN_NO_OFFSET: usize = AGGREGATE_PARAMS_LEN - 2;
+
+/// 聚合响应头部长度：命令类型(1) 状态(1) 节点ID(6) 测量值(1) 窗口偏移(2) 后续(1)
+pub const AGGREGATE_RESPONSE_HEADER_LEN: usize = 12;
+
+/// 单个聚合响应最多容纳的窗口数
+pub const MAX_AGGREGATE_BUCKETS: usize = (MAX_SECURE_PAYLOAD - AGGREGATE_RESPONSE_HEADER_LEN) / AggregateBucket::SIZE;
+
+/// 聚合的测量值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Measurement {
+    /// 温度 (°C)
+    Temperature = 0x01,
+    /// 湿度 (%)
+    Humidity = 0x02,
+    /// 气压 (Pa)
+    Pressure = 0x03,
+}
+
+impl Measurement {
+    /// 从测量值字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Measurement::Temperature),
+            0x02 => Some(Measurement::Humidity),
+            0x03 => Some(Measurement::Pressure),
+            _ => None,
+        }
+    }
+    
+    /// 从一条记录的三个测量值中取出本测量值
+    pub fn select(&self, temperature: f32, humidity: f32, pressure: f32) -> f32 {
+        match self {
+            Measurement::Temperature => temperature,
+            Measurement::Humidity => humidity,
+            Measurement::Pressure => pressure,
+        }
+    }
+}
+
+/// 聚合的时间窗口
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateWindow {
+    /// 与整分钟对齐的固定窗口，例如60为每小时一个窗口
+    Minutes(u16),
+    /// 把记录覆盖的时间范围等分为最多N个窗口
+    Points(u16),
+}
+
+/// 聚合查询的参数：按时间窗口统计一个节点某个测量值的最小、最大和平均值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregateParams {
+    /// 记录的来源节点
+    pub node_id: NodeId,
+    /// 统计的测量值
+    pub measurement: Measurement,
+    /// 起始时间（网络时间，毫秒，含）
+    pub start_time: u64,
+    /// 结束时间（网络时间，毫秒，含）
+    pub end_time: u64,
+    /// 时间窗口
+    pub window: AggregateWindow,
+    /// 跳过的窗口数，结果超出一个数据包时请求方带上已收到的窗口数取回后续窗口
+    pub offset: u16,
+}
+
+impl AggregateParams {
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> [u8; AGGREGATE_PARAMS_LEN] {
+        let (mode, value) = match self.window {
+            AggregateWindow::Minutes(minutes) => (1, minutes),
+            AggregateWindow::Points(points) => (2, points),
+        };
+        
+        let mut bytes = [0u8; AGGREGATE_PARAMS_LEN];
+        bytes[0..6].copy_from_slice(&self.node_id.0);
+        bytes[6] = self.measurement as u8;
+        put_u64be(&mut bytes[7..15], self.start_time);
+        put_u64be(&mut bytes[15..23], self.end_time);
+        bytes[23] = mode;
+        put_u16be(&mut bytes[24..26], value);
+        put_u16be(&mut bytes[26..28], self.offset);
+        bytes
+    }
+    
+    /// 从命令参数解析，没有窗口偏移时从第一个窗口开始；格式错误或窗口参数为0时返回None
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let offset = match bytes.len() {
+            AGGREGATE_PARAMS_LEN => get_u16be(&bytes[26..28]),
+            AGGREGATE_PARAMS_LEN_NO_OFFSET => 0,
+            _ => return None,
+        };
+        
+        let mut node_id = [0u8; 6];
+        node_id.copy_from_slice(&bytes[0..6]);
+        let window = match (bytes[23], get_u16be(&bytes[24..26])) {
+            (1, minutes) if minutes > 0 => AggregateWindow::Minutes(minutes),
+            (2, points) if points > 0 => AggregateWindow::Points(points),
+            _ => return None,
+        };
+        
+        Some(Self {
+            node_id: NodeId(node_id),
+            measurement: Measurement::from_u8(bytes[6])?,
+            start_time: get_u64be(&bytes[7..15]),
+            end_time: get_u64be(&bytes[15..23]),
+            window,
+            offset,
+        })
+    }
+}
+
+/// 一个时间窗口内的统计结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AggregateBucket {
+    /// 窗口起始时间（网络时间，毫秒）
+    pub start_time: u64,
+    /// 窗口内的记录数
+    pub count: u16,
+    pub min: f32,
+    pub max: f32,
+    pub avg: f32,
+}
+
+impl AggregateBucket {
+    /// 编码长度：起始时间(8) 记录数(2) 最小值(4) 最大值(4) 平均值(4)
+    pub const SIZE: usize = 22;
+    
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        put_u64be(&mut bytes[0..8], self.start_time);
+        put_u16be(&mut bytes[8..10], self.count);
+        put_f32be(&mut bytes[10..14], self.min);
+        put_f32be(&mut bytes[14..18], self.max);
+        put_f32be(&mut bytes[18..22], self.avg);
+        bytes
+    }
+    
+    /// 从字节解析
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::SIZE {
+            return None;
+        }
+        
+        Some(Self {
+            start_time: get_u64be(&bytes[0..8]),
+            count: get_u16be(&bytes[8..10]),
+            min: get_f32be(&bytes[10..14]),
+            max: get_f32be(&bytes[14..18]),
+            avg: get_f32be(&bytes[18..22]),
+        })
+    }
+}
+
+/// 聚合响应的头部
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregatePage {
+    /// 记录的来源节点
+    pub node_id: NodeId,
+    pub measurement: Measurement,
+    /// 响应中第一个窗口在整个结果中的序号
+    pub offset: u16,
+    /// 之后还有窗口，需要带新的偏移再次查询
+    pub more: bool,
+}
+
+impl AggregatePage {
+    /// 序列化为 节点ID(6) 测量值(1) 窗口偏移(2) 后续(1)，接在命令类型和状态之后
+    pub fn to_bytes(&self) -> [u8; AGGREGATE_RESPONSE_HEADER_LEN - 2] {
+        let mut bytes = [0u8; AGGREGATE_RESPONSE_HEADER_LEN - 2];
+        bytes[0..6].copy_from_slice(&self.node_id.0);
+        bytes[6] = self.measurement as u8;
+        put_u16be(&mut bytes[7..9], self.offset);
+        bytes[9] = self.more as u8;
+        bytes
+    }
+}
+
+/// 解析聚合响应 命令类型(1) 状态(1) 节点ID(6) 测量值(1) 窗口偏移(2) 后续(1) 窗口*，返回头部和各窗口；
+/// 状态不是成功时返回None
+pub fn deserialize_aggregate_response(data: &[u8]) -> Option<(AggregatePage, impl Iterator<Item = AggregateBucket> + '_)> {
+    if data.len() < AGGREGATE_RESPONSE_HEADER_LEN
+        || data[0] != CommandType::Aggregate as u8
+        || data[1] != CommandStatus::Ok as u8
+        || (data.len() - AGGREGATE_RESPONSE_HEADER_LEN) % AggregateBucket::SIZE != 0 {
+        return None;
+    }
+    
+    let mut node_id = [0u8; 6];
+    node_id.copy_from_slice(&data[2..8]);
+    let page = AggregatePage {
+        node_id: NodeId::new(node_id),
+        measurement: Measurement::from_u8(data[8])?,
+        offset: get_u16be(&data[9..11]),
+        more: data[11] != 0,
+    };
+    let buckets = data[AGGREGATE_RESPONSE_HEADER_LEN..].chunks_exact(AggregateBucket::SIZE).filter_map(AggregateBucket::from_bytes);
+    
+    Some((page, buckets))
+}
\ No newline at end of file
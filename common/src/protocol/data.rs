@@ -1,9 +1,12 @@
 use crate::protocol::{NodeId, PacketType, PROTOCOL_VERSION, MAX_PACKET_SIZE};
+use crate::protocol::reliable::MAX_FRAME_PAYLOAD;
+use crate::protocol::wire::{get_u16le, put_u16le};
 use crate::utils::calculate_checksum;
 
 /// 数据包头部
+///
+/// 线上格式按字段顺序排列，多字节字段为小端，收发都经过[`DataHeader::to_bytes`]和[`DataHeader::from_bytes`]。
 #[derive(Debug, Clone, Copy)]
-#[repr(C, packed)]
 pub struct DataHeader {
     /// 协议版本
     pub version: u8,
@@ -19,12 +22,96 @@ pub struct DataHeader {
     pub total_fragments: u8,
     /// 当前分片索引
     pub fragment_index: u8,
+    /// 剩余跳数，每经过一个转发节点减1，减到0时丢弃，防止路由环路中的包无限循环
+    pub ttl: u8,
     /// 数据长度
     pub data_length: u16,
+    /// 所属会话的流ID，转发节点据此分类而不解析负载；0表示不属于任何会话
+    pub flow_id: u16,
     /// 校验和
     pub checksum: u16,
 }
 
+impl DataHeader {
+    /// 线上长度
+    pub const SIZE: usize = 25;
+    
+    /// 序列化为线上格式
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0] = self.version;
+        bytes[1] = self.packet_type;
+        bytes[2..8].copy_from_slice(&self.source);
+        bytes[8..14].copy_from_slice(&self.destination);
+        put_u16le(&mut bytes[14..16], self.packet_id);
+        bytes[16] = self.total_fragments;
+        bytes[17] = self.fragment_index;
+        bytes[18] = self.ttl;
+        put_u16le(&mut bytes[19..21], self.data_length);
+        put_u16le(&mut bytes[21..23], self.flow_id);
+        put_u16le(&mut bytes[23..25], self.checksum);
+        bytes
+    }
+    
+    /// 从线上格式解析，缓冲区不足头部长度时返回None
+    pub fn from_bytes(buffer: &[u8]) -> Option<Self> {
+        let buffer = buffer.get(..Self::SIZE)?;
+        let mut source = [0u8; 6];
+        source.copy_from_slice(&buffer[2..8]);
+        let mut destination = [0u8; 6];
+        destination.copy_from_slice(&buffer[8..14]);
+        
+        Some(Self {
+            version: buffer[0],
+            packet_type: buffer[1],
+            source,
+            destination,
+            packet_id: get_u16le(&buffer[14..16]),
+            total_fragments: buffer[16],
+            fragment_index: buffer[17],
+            ttl: buffer[18],
+            data_length: get_u16le(&buffer[19..21]),
+            flow_id: get_u16le(&buffer[21..23]),
+            checksum: get_u16le(&buffer[23..25]),
+        })
+    }
+}
+
+/// 新建数据包的默认跳数限制
+pub const DEFAULT_TTL: u8 = 16;
+
+/// 可配置的最大跳数限制
+pub const MAX_TTL: u8 = 64;
+
+/// 新建数据包的跳数占位值，由[`send_secure`](crate::security::send_secure)在发送时换成本节点配置的跳数限制
+///
+/// 转发节点在跳数减到0时丢弃数据包，线上不会出现这个值。
+pub const UNSET_TTL: u8 = 0;
+
+/// 不属于任何会话的流ID
+pub const NO_FLOW: u16 = 0;
+
+/// 服务ID对应的流ID，取低16位
+pub fn flow_id_of(service_id: u32) -> u16 {
+    service_id as u16
+}
+
+/// 收到的数据包未通过校验的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+pub enum PacketError {
+    /// 协议版本不支持
+    Version,
+    /// 头部的数据长度与实际负载不符或超过最大包长
+    Length,
+    /// 分片索引超出总分片数
+    Fragment,
+    /// 源地址为广播地址
+    Source,
+    /// 校验和错误
+    Checksum,
+}
+
 /// 数据包，采用零拷贝设计
 #[derive(Debug)]
 pub struct DataPacket<'a> {
@@ -34,17 +121,30 @@ pub struct DataPacket<'a> {
 
 impl<'a> DataPacket<'a> {
     pub fn new(source: NodeId, destination: NodeId, packet_id: u16, data: &'a [u8]) -> Self {
-        assert!(data.len() <= MAX_PACKET_SIZE - core::mem::size_of::<DataHeader>());
+        Self::with_type(source, destination, PacketType::Data, packet_id, data)
+    }
+    
+    /// 创建指定类型的数据包
+    pub fn with_type(
+        source: NodeId,
+        destination: NodeId,
+        packet_type: PacketType,
+        packet_id: u16,
+        data: &'a [u8]
+    ) -> Self {
+        assert!(data.len() <= MAX_PACKET_SIZE - DataHeader::SIZE);
         
         let mut header = DataHeader {
             version: PROTOCOL_VERSION,
-            packet_type: PacketType::Data as u8,
+            packet_type: packet_type as u8,
             source: source.0,
             destination: destination.0,
             packet_id,
             total_fragments: 1,
             fragment_index: 0,
+            ttl: UNSET_TTL,
             data_length: data.len() as u16,
+            flow_id: NO_FLOW,
             checksum: 0, // 临时值
         };
         
@@ -53,40 +153,369 @@ impl<'a> DataPacket<'a> {
         packet
     }
     
+    /// 标记所属会话的流ID并重新计算校验和
+    pub fn with_flow(mut self, flow_id: u16) -> Self {
+        self.header.flow_id = flow_id;
+        self.update_checksum();
+        self
+    }
+    
+    /// 设置剩余跳数并重新计算校验和，转发节点重建数据包时用来沿用递减后的跳数
+    pub fn with_ttl(mut self, ttl: u8) -> Self {
+        self.header.ttl = ttl;
+        self.update_checksum();
+        self
+    }
+    
+    /// 从接收缓冲区解析数据包，长度字段越界或缓冲区截断时返回None
+    pub fn parse(buffer: &'a [u8]) -> Option<Self> {
+        let header_size = DataHeader::SIZE;
+        let header = DataHeader::from_bytes(buffer)?;
+        
+        let data_len = header.data_length as usize;
+        if data_len > MAX_PACKET_SIZE - header_size || header_size + data_len > buffer.len() {
+            return None;
+        }
+        
+        Some(Self {
+            header,
+            data: &buffer[header_size..header_size + data_len],
+        })
+    }
+    
+    /// 按线上格式写入头部和负载，返回总长度；缓冲区不足时返回None
+    pub fn encode(&self, buffer: &mut [u8]) -> Option<usize> {
+        let len = DataHeader::SIZE + self.data.len();
+        let buffer = buffer.get_mut(..len)?;
+        buffer[..DataHeader::SIZE].copy_from_slice(&self.header.to_bytes());
+        buffer[DataHeader::SIZE..].copy_from_slice(self.data);
+        Some(len)
+    }
+    
     pub fn update_checksum(&mut self) {
         // 设置校验和为0进行计算
         self.header.checksum = 0;
         
         // 首先计算头部的校验和
-        let header_data = unsafe {
-            core::slice::from_raw_parts(
-                &self.header as *const DataHeader as *const u8,
-                core::mem::size_of::<DataHeader>(),
-            )
-        };
+        let header_data = self.header.to_bytes();
         
         // 然后包含数据部分
-        let mut checksum = calculate_checksum(header_data);
+        let mut checksum = calculate_checksum(&header_data);
         let data_checksum = calculate_checksum(self.data);
         
         // 合并校验和
         self.header.checksum = checksum ^ data_checksum;
     }
     
+    /// 处理前的统一校验：版本、长度、分片字段、源地址和校验和
+    ///
+    /// 头部的跳数限制由转发节点在转发时递减和检查。
+    pub fn validate(&self) -> Result<(), PacketError> {
+        if self.header.version != PROTOCOL_VERSION {
+            return Err(PacketError::Version);
+        }
+        if self.header.data_length as usize != self.data.len() || self.data.len() > MAX_PACKET_SIZE - DataHeader::SIZE {
+            return Err(PacketError::Length);
+        }
+        if self.header.total_fragments == 0 || self.header.fragment_index >= self.header.total_fragments {
+            return Err(PacketError::Fragment);
+        }
+        if NodeId(self.header.source) == NodeId::BROADCAST {
+            return Err(PacketError::Source);
+        }
+        if !self.is_valid() {
+            return Err(PacketError::Checksum);
+        }
+        
+        Ok(())
+    }
+    
+    /// 是否为大负载的一个分片，需要经过[`Reassembler`]重组
+    pub fn is_fragment(&self) -> bool {
+        self.header.total_fragments > 1
+    }
+    
     pub fn is_valid(&self) -> bool {
         let mut header_copy = self.header;
         header_copy.checksum = 0;
         
-        let header_data = unsafe {
-            core::slice::from_raw_parts(
-                &header_copy as *const DataHeader as *const u8,
-                core::mem::size_of::<DataHeader>(),
-            )
-        };
+        let header_data = header_copy.to_bytes();
         
-        let header_checksum = calculate_checksum(header_data);
+        let header_checksum = calculate_checksum(&header_data);
         let data_checksum = calculate_checksum(self.data);
         
         (header_checksum ^ data_checksum) == self.header.checksum
     }
-} 
\ No newline at end of file
+} 
+
+/// 单个分片最多携带的负载，预留了安全保护的开销
+pub const MAX_FRAGMENT_PAYLOAD: usize = MAX_FRAME_PAYLOAD;
+
+/// 默认重组器能重组的负载最大长度
+pub const MAX_REASSEMBLED_SIZE: usize = 2048;
+
+/// 负载最多拆成的分片数，分片索引按位记录在重组器的32位位图中
+pub const MAX_FRAGMENTS_PER_PAYLOAD: usize = 32;
+
+/// 分片器能拆分的负载最大长度，接收端的重组器按各自的缓冲区大小进一步限制
+pub const MAX_FRAGMENTED_SIZE: usize = MAX_FRAGMENTS_PER_PAYLOAD * MAX_FRAGMENT_PAYLOAD;
+
+/// 默认重组器接受的最大分片数
+pub const MAX_FRAGMENTS: usize = (MAX_REASSEMBLED_SIZE + MAX_FRAGMENT_PAYLOAD - 1) / MAX_FRAGMENT_PAYLOAD;
+
+/// 默认重组器同时重组的最大负载数
+pub const MAX_REASSEMBLIES: usize = 2;
+
+/// 未收齐的负载默认保留多久（毫秒），超时后丢弃已收到的分片
+pub const REASSEMBLY_TIMEOUT_MS: u64 = 5000;
+
+/// 把超过单帧长度的负载拆成多个数据包，各分片共用包ID，按分片索引排列
+///
+/// 除最后一个分片外每个分片都携带[`MAX_FRAGMENT_PAYLOAD`]字节，接收端据此计算偏移。
+pub struct Fragmenter<'a> {
+    source: NodeId,
+    destination: NodeId,
+    packet_type: PacketType,
+    packet_id: u16,
+    flow_id: u16,
+    payload: &'a [u8],
+    next_index: u8,
+    total: u8,
+}
+
+impl<'a> Fragmenter<'a> {
+    /// 创建分片器，负载为空或超过[`MAX_FRAGMENTED_SIZE`]时返回None
+    pub fn new(
+        source: NodeId,
+        destination: NodeId,
+        packet_type: PacketType,
+        packet_id: u16,
+        payload: &'a [u8]
+    ) -> Option<Self> {
+        if payload.is_empty() || payload.len() > MAX_FRAGMENTED_SIZE {
+            return None;
+        }
+        
+        Some(Self {
+            source,
+            destination,
+            packet_type,
+            packet_id,
+            flow_id: NO_FLOW,
+            payload,
+            next_index: 0,
+            total: payload.len().div_ceil(MAX_FRAGMENT_PAYLOAD) as u8,
+        })
+    }
+    
+    /// 标记所属会话的流ID
+    pub fn with_flow(mut self, flow_id: u16) -> Self {
+        self.flow_id = flow_id;
+        self
+    }
+    
+    /// 分片总数
+    pub fn total(&self) -> u8 {
+        self.total
+    }
+}
+
+impl<'a> Iterator for Fragmenter<'a> {
+    type Item = DataPacket<'a>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_index >= self.total {
+            return None;
+        }
+        
+        let start = self.next_index as usize * MAX_FRAGMENT_PAYLOAD;
+        let end = (start + MAX_FRAGMENT_PAYLOAD).min(self.payload.len());
+        let mut packet = DataPacket::with_type(self.source, self.destination, self.packet_type,
+                                               self.packet_id, &self.payload[start..end]);
+        packet.header.total_fragments = self.total;
+        packet.header.fragment_index = self.next_index;
+        packet.header.flow_id = self.flow_id;
+        packet.update_checksum();
+        
+        self.next_index += 1;
+        Some(packet)
+    }
+}
+
+/// 重组缓冲区
+#[derive(Clone, Copy)]
+struct Reassembly<const SIZE: usize> {
+    /// 是否正在重组，收齐或超时后缓冲区可以重用
+    active: bool,
+    source: NodeId,
+    packet_id: u16,
+    packet_type: u8,
+    flow_id: u16,
+    total: u8,
+    /// 已收到的分片，按索引置位
+    received: u32,
+    /// 最后一个分片到达后才知道总长度
+    len: usize,
+    /// 收到第一个分片的时间
+    started_at: u64,
+    /// 收到第一个分片后最多等待多久
+    timeout_ms: u64,
+    buffer: [u8; SIZE],
+}
+
+impl<const SIZE: usize> Reassembly<SIZE> {
+    const EMPTY: Self = Self {
+        active: false,
+        source: NodeId::BROADCAST,
+        packet_id: 0,
+        packet_type: 0,
+        flow_id: NO_FLOW,
+        total: 0,
+        received: 0,
+        len: 0,
+        started_at: 0,
+        timeout_ms: REASSEMBLY_TIMEOUT_MS,
+        buffer: [0; SIZE],
+    };
+}
+
+/// 分片重组，按源节点和包ID区分不同的负载
+///
+/// 缓冲区大小和同时重组的负载数由类型参数决定，默认为[`MAX_REASSEMBLED_SIZE`]和[`MAX_REASSEMBLIES`]。
+pub struct Reassembler<const SIZE: usize = MAX_REASSEMBLED_SIZE, const SLOTS: usize = MAX_REASSEMBLIES> {
+    slots: [Reassembly<SIZE>; SLOTS],
+    /// 超时或被新负载挤占而丢弃的负载数
+    dropped: u32,
+}
+
+impl Reassembler {
+    /// 创建默认大小的空重组器
+    pub fn new() -> Self {
+        Self::empty()
+    }
+}
+
+impl<const SIZE: usize, const SLOTS: usize> Reassembler<SIZE, SLOTS> {
+    /// 缓冲区能容纳的最大分片数
+    const MAX_TOTAL: usize = {
+        let total = SIZE.div_ceil(MAX_FRAGMENT_PAYLOAD);
+        assert!(total <= MAX_FRAGMENTS_PER_PAYLOAD);
+        total
+    };
+    
+    /// 按类型参数的大小创建空重组器
+    pub fn empty() -> Self {
+        Self {
+            slots: [Reassembly::EMPTY; SLOTS],
+            dropped: 0,
+        }
+    }
+    
+    /// 处理一个分片，收齐时返回携带完整负载的数据包，在下次调用前有效
+    ///
+    /// 分片字段不一致或负载超出缓冲区的包被丢弃；重组表满时替换最早开始的负载。
+    pub fn push(&mut self, packet: &DataPacket, now: u64) -> Option<DataPacket<'_>> {
+        self.push_with_timeout(packet, now, REASSEMBLY_TIMEOUT_MS)
+    }
+    
+    /// 同[`Reassembler::push`]，新负载未收齐时最多等待`timeout_ms`
+    pub fn push_with_timeout(&mut self, packet: &DataPacket, now: u64, timeout_ms: u64) -> Option<DataPacket<'_>> {
+        self.expire(now);
+        
+        let header = &packet.header;
+        let total = header.total_fragments;
+        let index = header.fragment_index;
+        let last = index + 1 == total;
+        let offset = index as usize * MAX_FRAGMENT_PAYLOAD;
+        if total as usize > Self::MAX_TOTAL || index >= total
+            || packet.data.len() > MAX_FRAGMENT_PAYLOAD
+            || (!last && packet.data.len() != MAX_FRAGMENT_PAYLOAD)
+            || offset + packet.data.len() > SIZE {
+            return None;
+        }
+        
+        let source = NodeId(header.source);
+        let slot = match self.slots.iter().position(|r| r.active && r.source == source && r.packet_id == header.packet_id) {
+            Some(slot) => slot,
+            None => {
+                let slot = match self.slots.iter().position(|r| !r.active) {
+                    Some(slot) => slot,
+                    None => {
+                        let oldest = self.slots.iter()
+                            .enumerate()
+                            .min_by_key(|(_, r)| r.started_at)
+                            .map(|(slot, _)| slot)?;
+                        self.dropped += 1;
+                        oldest
+                    },
+                };
+                let reassembly = &mut self.slots[slot];
+                reassembly.active = true;
+                reassembly.source = source;
+                reassembly.packet_id = header.packet_id;
+                reassembly.packet_type = header.packet_type;
+                reassembly.flow_id = header.flow_id;
+                reassembly.total = total;
+                reassembly.received = 0;
+                reassembly.len = 0;
+                reassembly.started_at = now;
+                reassembly.timeout_ms = timeout_ms;
+                slot
+            },
+        };
+        
+        let reassembly = &mut self.slots[slot];
+        if reassembly.total != total || reassembly.packet_type != header.packet_type {
+            return None;
+        }
+        
+        reassembly.buffer[offset..offset + packet.data.len()].copy_from_slice(packet.data);
+        reassembly.received |= 1 << index;
+        if last {
+            reassembly.len = offset + packet.data.len();
+        }
+        
+        if reassembly.received.count_ones() != total as u32 {
+            return None;
+        }
+        
+        // 完整的包沿用最后收到的分片的头部
+        reassembly.active = false;
+        let mut header = packet.header;
+        header.total_fragments = 1;
+        header.fragment_index = 0;
+        header.data_length = reassembly.len as u16;
+        let mut complete = DataPacket { header, data: &reassembly.buffer[..reassembly.len] };
+        complete.update_checksum();
+        Some(complete)
+    }
+    
+    /// 丢弃超时未收齐的负载
+    pub fn expire(&mut self, now: u64) {
+        for reassembly in self.slots.iter_mut() {
+            if reassembly.active && now.saturating_sub(reassembly.started_at) > reassembly.timeout_ms {
+                reassembly.active = false;
+                self.dropped += 1;
+            }
+        }
+    }
+    
+    /// 丢弃属于指定流的未收齐负载，会话关闭时调用
+    pub fn discard_flow(&mut self, flow_id: u16) {
+        for reassembly in self.slots.iter_mut() {
+            if reassembly.active && reassembly.flow_id == flow_id {
+                reassembly.active = false;
+            }
+        }
+    }
+    
+    /// 正在重组的负载数
+    pub fn pending(&self) -> usize {
+        self.slots.iter().filter(|r| r.active).count()
+    }
+    
+    /// 超时或被新负载挤占而丢弃的负载数
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
\ No newline at end of file
@@ -0,0 +1,193 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u64be, put_u64be};
+use crate::security::send_secure;
+
+/// 回显负载长度：发起方(6) 目标(6) 发送时间(8) 跳数(1)
+pub const ECHO_PAYLOAD_LEN: usize = 21;
+
+/// 路径追踪每跳记录的长度：节点(6) 接收信号强度(1)
+pub const TRACE_HOP_LEN: usize = 7;
+
+/// 路径追踪最多记录的跳数，更远的节点只累计跳数
+pub const MAX_TRACE_HOPS: usize = 16;
+
+/// 带路径追踪的最大回显负载长度：回显负载 记录数(1) 每跳记录
+pub const MAX_ECHO_PAYLOAD_LEN: usize = ECHO_PAYLOAD_LEN + 1 + MAX_TRACE_HOPS * TRACE_HOP_LEN;
+
+/// 路径追踪中的一跳
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceHop {
+    /// 经过的节点
+    pub node_id: NodeId,
+    /// 该节点收到请求时的信号强度（dBm）
+    pub rssi: i8,
+}
+
+/// 请求沿途经过的节点，按到达顺序排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trace {
+    hops: [TraceHop; MAX_TRACE_HOPS],
+    len: usize,
+}
+
+impl Trace {
+    /// 创建空的路径记录
+    pub fn new() -> Self {
+        Self {
+            hops: [TraceHop { node_id: NodeId::BROADCAST, rssi: 0 }; MAX_TRACE_HOPS],
+            len: 0,
+        }
+    }
+    
+    /// 追加一跳，记录已满时返回false
+    pub fn push(&mut self, node_id: NodeId, rssi: i8) -> bool {
+        if self.len >= MAX_TRACE_HOPS {
+            return false;
+        }
+        
+        self.hops[self.len] = TraceHop { node_id, rssi };
+        self.len += 1;
+        true
+    }
+    
+    /// 已记录的各跳
+    pub fn hops(&self) -> &[TraceHop] {
+        &self.hops[..self.len]
+    }
+}
+
+/// 回显请求/应答的负载
+///
+/// 中继逐跳改写包头中的源和目标，因此端到端的发起方和目标记录在负载中。
+/// 请求沿途每经过一个节点跳数加一，应答原样带回请求路径的跳数。
+/// 路径追踪模式下每个节点还在负载末尾追加自己的ID和收到请求时的信号强度。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Echo {
+    /// 发起测量的节点
+    pub origin: NodeId,
+    /// 被测量的目标节点
+    pub target: NodeId,
+    /// 发起方发送时的时间戳（毫秒）
+    pub sent_at: u64,
+    /// 请求经过的跳数
+    pub hop_count: u8,
+    /// 路径追踪记录，None表示普通回显
+    pub trace: Option<Trace>,
+}
+
+impl Echo {
+    /// 请求经过一个节点：跳数加一，路径追踪模式下记录该节点
+    pub fn record_hop(&mut self, node_id: NodeId, rssi: i8) {
+        self.hop_count = self.hop_count.saturating_add(1);
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(node_id, rssi);
+        }
+    }
+    
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let hops = self.trace.as_ref().map(|trace| trace.hops()).unwrap_or(&[]);
+        let len = match self.trace {
+            Some(_) => ECHO_PAYLOAD_LEN + 1 + hops.len() * TRACE_HOP_LEN,
+            None => ECHO_PAYLOAD_LEN,
+        };
+        if buffer.len() < len {
+            return 0;
+        }
+        
+        buffer[0..6].copy_from_slice(&self.origin.0);
+        buffer[6..12].copy_from_slice(&self.target.0);
+        put_u64be(&mut buffer[12..20], self.sent_at);
+        buffer[20] = self.hop_count;
+        
+        if self.trace.is_some() {
+            buffer[ECHO_PAYLOAD_LEN] = hops.len() as u8;
+            let records = buffer[ECHO_PAYLOAD_LEN + 1..len].chunks_exact_mut(TRACE_HOP_LEN);
+            for (record, hop) in records.zip(hops) {
+                record[..6].copy_from_slice(&hop.node_id.0);
+                record[6] = hop.rssi as u8;
+            }
+        }
+        
+        len
+    }
+    
+    /// 反序列化
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < ECHO_PAYLOAD_LEN {
+            return None;
+        }
+        
+        let mut origin = [0u8; 6];
+        origin.copy_from_slice(&buffer[0..6]);
+        let mut target = [0u8; 6];
+        target.copy_from_slice(&buffer[6..12]);
+        
+        // 普通回显没有记录数字节
+        let trace = match buffer.get(ECHO_PAYLOAD_LEN) {
+            Some(&count) => {
+                let count = count as usize;
+                if count > MAX_TRACE_HOPS {
+                    return None;
+                }
+                let records = buffer.get(ECHO_PAYLOAD_LEN + 1..ECHO_PAYLOAD_LEN + 1 + count * TRACE_HOP_LEN)?;
+                
+                let mut trace = Trace::new();
+                for record in records.chunks_exact(TRACE_HOP_LEN) {
+                    let mut node_id = [0u8; 6];
+                    node_id.copy_from_slice(&record[..6]);
+                    trace.push(NodeId(node_id), record[6] as i8);
+                }
+                Some(trace)
+            },
+            None => None,
+        };
+        
+        Some(Self {
+            origin: NodeId(origin),
+            target: NodeId(target),
+            sent_at: get_u64be(&buffer[12..20]),
+            hop_count: buffer[20],
+            trace,
+        })
+    }
+}
+
+/// 向下一跳发送回显请求或应答
+pub fn send_echo<H: Hardware>(
+    hardware: &mut H,
+    next_hop: NodeId,
+    packet_type: PacketType,
+    packet_id: u16,
+    echo: &Echo
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; MAX_ECHO_PAYLOAD_LEN];
+    let len = echo.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, next_hop, packet_type, packet_id, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
+
+/// 处理发给本节点的回显请求，应答发回上一跳，由中继转发给发起方
+///
+/// 不是发给本节点的回显请求或应答发送失败时返回false。
+pub fn answer_echo<H: Hardware>(hardware: &mut H, request: &DataPacket) -> bool {
+    if request.header.packet_type != PacketType::EchoRequest as u8 {
+        return false;
+    }
+    
+    let node_id = hardware.get_node_id();
+    let mut echo = match Echo::deserialize(request.data) {
+        Some(echo) if echo.target == node_id => echo,
+        _ => return false,
+    };
+    let rssi = hardware.get_radio().get_rssi().unwrap_or(0);
+    echo.record_hop(node_id, rssi);
+    
+    let previous_hop = NodeId(request.header.source);
+    send_echo(hardware, previous_hop, PacketType::EchoReply, request.header.packet_id, &echo).is_ok()
+}
\ No newline at end of file
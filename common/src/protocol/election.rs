@@ -0,0 +1,103 @@
+use crate::protocol::NodeId;
+use crate::protocol::wire::{get_u16be, put_u16be};
+
+/// 选举开始和回应消息长度：类型(1) 选举ID(2) 优先级(1) 电池电量(1) 负载(1)
+pub const ELECTION_VOTE_LEN: usize = 6;
+
+/// 选举结果消息长度：类型(1) 选举ID(2) 主服务器(6)
+pub const ELECTION_RESULT_LEN: usize = 9;
+
+/// 选举协议消息类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ElectionMessageType {
+    /// 开始选举
+    ElectionStart = 0x01,
+    /// 竞选回应
+    ElectionResponse = 0x02,
+    /// 选举结果广播
+    ElectionResult = 0x03,
+}
+
+/// 候选节点在选举中声明的条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Candidacy {
+    /// 配置的优先级，越大越优先
+    pub priority: u8,
+    /// 电池电量（0-100%）
+    pub battery_level: u8,
+    /// 负载（0-100%）
+    pub load: u8,
+}
+
+/// 主服务器选举消息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElectionMessage {
+    /// 发起选举并声明自身条件
+    Start { election_id: u16, candidacy: Candidacy },
+    /// 其他节点对选举的回应，携带自身条件供发起方比较
+    Response { election_id: u16, candidacy: Candidacy },
+    /// 选举结果
+    Result { election_id: u16, master: NodeId },
+}
+
+impl ElectionMessage {
+    /// 选举ID
+    pub fn election_id(&self) -> u16 {
+        match self {
+            ElectionMessage::Start { election_id, .. }
+            | ElectionMessage::Response { election_id, .. }
+            | ElectionMessage::Result { election_id, .. } => *election_id,
+        }
+    }
+    
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let (message_type, len) = match self {
+            ElectionMessage::Start { .. } => (ElectionMessageType::ElectionStart, ELECTION_VOTE_LEN),
+            ElectionMessage::Response { .. } => (ElectionMessageType::ElectionResponse, ELECTION_VOTE_LEN),
+            ElectionMessage::Result { .. } => (ElectionMessageType::ElectionResult, ELECTION_RESULT_LEN),
+        };
+        if buffer.len() < len {
+            return 0;
+        }
+        
+        buffer[0] = message_type as u8;
+        put_u16be(&mut buffer[1..3], self.election_id());
+        match self {
+            ElectionMessage::Start { candidacy, .. } | ElectionMessage::Response { candidacy, .. } => {
+                buffer[3] = candidacy.priority;
+                buffer[4] = candidacy.battery_level;
+                buffer[5] = candidacy.load;
+            },
+            ElectionMessage::Result { master, .. } => {
+                buffer[3..9].copy_from_slice(&master.0);
+            },
+        }
+        
+        len
+    }
+    
+    /// 反序列化，类型未知或长度不足时返回None
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < ELECTION_VOTE_LEN {
+            return None;
+        }
+        
+        let election_id = get_u16be(&buffer[1..3]);
+        let candidacy = Candidacy { priority: buffer[3], battery_level: buffer[4], load: buffer[5] };
+        match buffer[0] {
+            0x01 => Some(ElectionMessage::Start { election_id, candidacy }),
+            0x02 => Some(ElectionMessage::Response { election_id, candidacy }),
+            0x03 => {
+                if buffer.len() < ELECTION_RESULT_LEN {
+                    return None;
+                }
+                let mut master = [0u8; 6];
+                master.copy_from_slice(&buffer[3..9]);
+                Some(ElectionMessage::Result { election_id, master: NodeId(master) })
+            },
+            _ => None,
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,318 @@
+use crate::hal::Hardware;
+use crate::metrics::{self, Counter};
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u16be, get_u32be, get_u64be, put_u16be, put_u32be, put_u64be};
+use crate::security::{send_secure, SecurityError};
+use crate::warn;
+
+/// 错误报告负载长度：来源(6) 错误码(1) 次数(2) 网络时间(8) 附加信息(4)
+pub const ERROR_REPORT_LEN: usize = 21;
+
+/// 同一错误码两次报告的最小间隔（毫秒），期间发生的次数合并到下一次报告
+pub const ERROR_REPORT_INTERVAL_MS: u64 = 60000;
+
+/// 错误码个数
+pub const ERROR_CODE_COUNT: usize = 9;
+
+/// 错误类别，即错误码的高4位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+#[repr(u8)]
+pub enum ErrorCategory {
+    /// 无线电
+    Radio = 0x1,
+    /// 路由和投递
+    Routing = 0x2,
+    /// 存储
+    Storage = 0x3,
+    /// 安全
+    Security = 0x4,
+    /// 系统
+    System = 0x5,
+}
+
+/// 统一的错误码，高4位为类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ErrorCode {
+    /// 无线电发送失败
+    RadioTxFailed = 0x10,
+    /// 查不到目的地的路由
+    RouteLost = 0x20,
+    /// 重传耗尽仍未确认
+    DeliveryFailed = 0x21,
+    /// 存储已满，旧记录被覆盖
+    StorageFull = 0x30,
+    /// 非易失存储写入失败
+    NvsWriteFailed = 0x31,
+    /// 认证失败或收到未保护的包
+    CryptoFailure = 0x40,
+    /// 计数器未递增，疑似重放
+    ReplayRejected = 0x41,
+    /// 看门狗复位后启动
+    WatchdogReset = 0x50,
+    /// 固件更新失败
+    OtaFailed = 0x51,
+}
+
+impl ErrorCode {
+    /// 所有错误码
+    pub const ALL: [ErrorCode; ERROR_CODE_COUNT] = [
+        ErrorCode::RadioTxFailed,
+        ErrorCode::RouteLost,
+        ErrorCode::DeliveryFailed,
+        ErrorCode::StorageFull,
+        ErrorCode::NvsWriteFailed,
+        ErrorCode::CryptoFailure,
+        ErrorCode::ReplayRejected,
+        ErrorCode::WatchdogReset,
+        ErrorCode::OtaFailed,
+    ];
+    
+    /// 从字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|code| *code as u8 == value)
+    }
+    
+    /// 错误类别
+    pub fn category(&self) -> ErrorCategory {
+        match *self as u8 >> 4 {
+            0x1 => ErrorCategory::Radio,
+            0x2 => ErrorCategory::Routing,
+            0x3 => ErrorCategory::Storage,
+            0x4 => ErrorCategory::Security,
+            _ => ErrorCategory::System,
+        }
+    }
+    
+    /// 显示名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorCode::RadioTxFailed => "无线发送失败",
+            ErrorCode::RouteLost => "路由丢失",
+            ErrorCode::DeliveryFailed => "投递失败",
+            ErrorCode::StorageFull => "存储已满",
+            ErrorCode::NvsWriteFailed => "NVS写入失败",
+            ErrorCode::CryptoFailure => "安全校验失败",
+            ErrorCode::ReplayRejected => "疑似重放",
+            ErrorCode::WatchdogReset => "看门狗复位",
+            ErrorCode::OtaFailed => "固件更新失败",
+        }
+    }
+    
+    /// 机器可读的名称，用于导出
+    pub fn key(&self) -> &'static str {
+        match self {
+            ErrorCode::RadioTxFailed => "radio_tx_failed",
+            ErrorCode::RouteLost => "route_lost",
+            ErrorCode::DeliveryFailed => "delivery_failed",
+            ErrorCode::StorageFull => "storage_full",
+            ErrorCode::NvsWriteFailed => "nvs_write_failed",
+            ErrorCode::CryptoFailure => "crypto_failure",
+            ErrorCode::ReplayRejected => "replay_rejected",
+            ErrorCode::WatchdogReset => "watchdog_reset",
+            ErrorCode::OtaFailed => "ota_failed",
+        }
+    }
+    
+    /// 在ALL中的位置
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|code| code == self).unwrap_or(0)
+    }
+}
+
+impl From<SecurityError> for ErrorCode {
+    fn from(error: SecurityError) -> Self {
+        match error {
+            SecurityError::Replay => ErrorCode::ReplayRejected,
+            SecurityError::SendFailed => ErrorCode::RadioTxFailed,
+            SecurityError::Unprotected | SecurityError::Malformed | SecurityError::AuthFailed => ErrorCode::CryptoFailure,
+        }
+    }
+}
+
+impl From<ReliableError> for ErrorCode {
+    fn from(error: ReliableError) -> Self {
+        match error {
+            ReliableError::SendFailed => ErrorCode::RadioTxFailed,
+            ReliableError::WindowFull | ReliableError::PayloadTooLarge => ErrorCode::DeliveryFailed,
+        }
+    }
+}
+
+/// 发往主节点的错误报告
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorReport {
+    /// 发生错误的节点，转发时保持不变
+    pub origin: NodeId,
+    /// 错误码
+    pub code: ErrorCode,
+    /// 自上次报告以来的发生次数
+    pub occurrences: u16,
+    /// 报告时的网络时间（毫秒）
+    pub timestamp: u64,
+    /// 附加信息，含义由错误码决定
+    pub detail: u32,
+}
+
+impl ErrorReport {
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        if buffer.len() < ERROR_REPORT_LEN {
+            return 0;
+        }
+        
+        buffer[0..6].copy_from_slice(&self.origin.0);
+        buffer[6] = self.code as u8;
+        put_u16be(&mut buffer[7..9], self.occurrences);
+        put_u64be(&mut buffer[9..17], self.timestamp);
+        put_u32be(&mut buffer[17..21], self.detail);
+        
+        ERROR_REPORT_LEN
+    }
+    
+    /// 反序列化，错误码未知时返回None
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < ERROR_REPORT_LEN {
+            return None;
+        }
+        
+        let mut origin = [0u8; 6];
+        origin.copy_from_slice(&buffer[0..6]);
+        
+        Some(Self {
+            origin: NodeId(origin),
+            code: ErrorCode::from_u8(buffer[6])?,
+            occurrences: get_u16be(&buffer[7..9]),
+            timestamp: get_u64be(&buffer[9..17]),
+            detail: get_u32be(&buffer[17..21]),
+        })
+    }
+}
+
+/// 发送错误报告
+pub fn send_error_report<H: Hardware>(
+    hardware: &mut H,
+    destination: NodeId,
+    report: &ErrorReport
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; ERROR_REPORT_LEN];
+    report.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, destination, PacketType::ErrorReport, 0, &data);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
+
+/// 由指标增量推断的错误
+const WATCHED_COUNTERS: [(Counter, ErrorCode); 5] = [
+    (Counter::RadioTxErrors, ErrorCode::RadioTxFailed),
+    (Counter::RouteMisses, ErrorCode::RouteLost),
+    (Counter::DeliveryFailures, ErrorCode::DeliveryFailed),
+    (Counter::RecordsOverwritten, ErrorCode::StorageFull),
+    (Counter::SecurityRejected, ErrorCode::CryptoFailure),
+];
+
+/// 单个错误码的待报告状态
+#[derive(Debug, Clone, Copy, Default)]
+struct PendingError {
+    /// 尚未报告的次数
+    occurrences: u16,
+    /// 最近一次的附加信息
+    detail: u32,
+    /// 上次报告的时间
+    last_sent: Option<u64>,
+}
+
+/// 限速的错误上报
+///
+/// 各层的失败大多已经反映在指标中，这里按计数器的增量记录错误，其余错误通过[`ErrorReporter::record`]记录。
+/// 同一错误码在间隔内只报告一次，期间的次数合并；还不知道主节点时先累计。
+pub struct ErrorReporter {
+    pending: [PendingError; ERROR_CODE_COUNT],
+    /// 上次检查时的计数器值
+    watched: [u32; WATCHED_COUNTERS.len()],
+}
+
+impl ErrorReporter {
+    /// 创建错误上报，从当前的指标值开始计算增量
+    pub fn new() -> Self {
+        let mut watched = [0; WATCHED_COUNTERS.len()];
+        for (value, (counter, _)) in watched.iter_mut().zip(WATCHED_COUNTERS.iter()) {
+            *value = metrics::get(*counter);
+        }
+        
+        Self {
+            pending: [PendingError::default(); ERROR_CODE_COUNT],
+            watched,
+        }
+    }
+    
+    /// 记录一次错误
+    pub fn record(&mut self, code: ErrorCode, detail: u32) {
+        let pending = &mut self.pending[code.index()];
+        pending.occurrences = pending.occurrences.saturating_add(1);
+        pending.detail = detail;
+    }
+    
+    /// 尚未报告的错误次数
+    pub fn pending(&self, code: ErrorCode) -> u16 {
+        self.pending[code.index()].occurrences
+    }
+    
+    /// 按指标增量记录错误，并把到了间隔的错误报告给sink
+    ///
+    /// sink是本节点时只写日志；返回本次发出的报告数。
+    pub fn poll<H: Hardware>(&mut self, hardware: &mut H, sink: Option<NodeId>, network_time: u64) -> usize {
+        for (last, (counter, code)) in self.watched.iter_mut().zip(WATCHED_COUNTERS.iter()) {
+            let current = metrics::get(*counter);
+            let delta = current.wrapping_sub(*last);
+            *last = current;
+            if delta > 0 {
+                let pending = &mut self.pending[code.index()];
+                pending.occurrences = pending.occurrences.saturating_add(delta.min(u16::MAX as u32) as u16);
+                pending.detail = current;
+            }
+        }
+        
+        let sink = match sink {
+            Some(sink) => sink,
+            None => return 0,
+        };
+        let node_id = hardware.get_node_id();
+        
+        let mut sent = 0;
+        for (pending, code) in self.pending.iter_mut().zip(ErrorCode::ALL.iter()) {
+            let due = match pending.last_sent {
+                Some(at) => network_time.saturating_sub(at) >= ERROR_REPORT_INTERVAL_MS,
+                None => true,
+            };
+            if pending.occurrences == 0 || !due {
+                continue;
+            }
+            
+            let report = ErrorReport {
+                origin: node_id,
+                code: *code,
+                occurrences: pending.occurrences,
+                timestamp: network_time,
+                detail: pending.detail,
+            };
+            
+            if sink == node_id {
+                warn!("{}: {} 次，附加信息 {}", code.name(), report.occurrences, report.detail);
+            } else if send_error_report(hardware, sink, &report).is_err() {
+                // 发送失败时保留次数，下次再试
+                continue;
+            }
+            
+            pending.occurrences = 0;
+            pending.last_sent = Some(network_time);
+            sent += 1;
+        }
+        
+        sent
+    }
+}
\ No newline at end of file
@@ -0,0 +1,82 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u16be, put_u16be};
+use crate::security::{send_secure, MAX_SECURE_PAYLOAD};
+
+/// 泛洪包头长度：源节点(6) 序号(2) 原始类型(1)
+pub const FLOOD_HEADER_LEN: usize = 9;
+
+/// 泛洪包能携带的最大负载
+pub const MAX_FLOOD_PAYLOAD: usize = MAX_SECURE_PAYLOAD - FLOOD_HEADER_LEN;
+
+/// 全网泛洪的消息
+///
+/// 每一跳都以本节点身份重新广播（安全层按发送方分配随机数和重放计数），
+/// 因此发起节点和序号保存在负载中，各节点据此去重。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flood<'a> {
+    /// 发起泛洪的节点
+    pub origin: NodeId,
+    /// 发起节点分配的序号
+    pub sequence: u16,
+    /// 被泛洪消息的原始包类型
+    pub packet_type: u8,
+    /// 原始负载
+    pub payload: &'a [u8],
+}
+
+impl<'a> Flood<'a> {
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let len = FLOOD_HEADER_LEN + self.payload.len();
+        if buffer.len() < len {
+            return 0;
+        }
+        
+        buffer[0..6].copy_from_slice(&self.origin.0);
+        put_u16be(&mut buffer[6..8], self.sequence);
+        buffer[8] = self.packet_type;
+        buffer[FLOOD_HEADER_LEN..len].copy_from_slice(self.payload);
+        len
+    }
+    
+    /// 反序列化，长度不足时返回None
+    pub fn deserialize(buffer: &'a [u8]) -> Option<Self> {
+        if buffer.len() < FLOOD_HEADER_LEN {
+            return None;
+        }
+        
+        let mut origin = [0u8; 6];
+        origin.copy_from_slice(&buffer[0..6]);
+        Some(Self {
+            origin: NodeId(origin),
+            sequence: get_u16be(&buffer[6..8]),
+            packet_type: buffer[8],
+            payload: &buffer[FLOOD_HEADER_LEN..],
+        })
+    }
+    
+    /// 还原为原始数据包，源地址为发起节点，目的地址为广播
+    pub fn inner(&self) -> DataPacket<'a> {
+        let mut packet = DataPacket::new(self.origin, NodeId::BROADCAST, self.sequence, self.payload);
+        packet.header.packet_type = self.packet_type;
+        packet.update_checksum();
+        packet
+    }
+}
+
+/// 广播一个泛洪包，ttl为剩余跳数
+pub fn send_flood<H: Hardware>(hardware: &mut H, flood: &Flood, ttl: u8) -> Result<(), ReliableError> {
+    let mut data = [0u8; MAX_SECURE_PAYLOAD];
+    let len = flood.serialize(&mut data);
+    if len == 0 {
+        return Err(ReliableError::PayloadTooLarge);
+    }
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, NodeId::BROADCAST, PacketType::Flood, flood.sequence, &data[..len])
+        .with_ttl(ttl);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
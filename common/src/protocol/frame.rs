@@ -0,0 +1,96 @@
+use crate::protocol::data::{MAX_FRAGMENTED_SIZE, MAX_FRAGMENT_PAYLOAD};
+use crate::protocol::payload::PayloadType;
+use crate::protocol::wire::{get_u32be, put_u32be};
+
+/// 视频帧的负载类型标识
+pub const FRAME_PAYLOAD_TYPE: u8 = PayloadType::VideoFrame as u8;
+
+/// 视频帧负载头部长度：类型(1) 服务ID(4) 帧序号(2)
+pub const VIDEO_FRAME_HEADER_LEN: usize = 7;
+
+/// 单个视频帧的最大长度
+pub const MAX_VIDEO_FRAME_SIZE: usize = 4096;
+
+/// 带头部的视频帧负载最大长度，整帧经[`Fragmenter`](crate::protocol::data::Fragmenter)拆成数据包分片发送
+pub const MAX_VIDEO_PAYLOAD: usize = VIDEO_FRAME_HEADER_LEN + MAX_VIDEO_FRAME_SIZE;
+
+const _: () = assert!(MAX_VIDEO_PAYLOAD <= MAX_FRAGMENTED_SIZE);
+
+/// 视频质量档位通知的负载类型标识
+pub const VIDEO_TIER_PAYLOAD_TYPE: u8 = PayloadType::VideoTier as u8;
+
+/// 档位通知负载长度：类型(1) 服务ID(4) 档位(1)
+pub const VIDEO_TIER_NOTICE_LEN: usize = 6;
+
+/// 视频质量档位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoTier {
+    /// 两帧之间的最短间隔（毫秒）
+    pub frame_interval_ms: u16,
+    /// 每帧长度（字节）
+    pub frame_size: u16,
+}
+
+impl VideoTier {
+    /// 该档位需要的带宽（kbps，即每毫秒比特数）
+    pub fn bitrate_kbps(&self) -> u32 {
+        (self.frame_size as u32 * 8).div_ceil(self.frame_interval_ms.max(1) as u32)
+    }
+    
+    /// 每帧的分片数
+    pub fn fragment_count(&self) -> usize {
+        (VIDEO_FRAME_HEADER_LEN + self.frame_size as usize).div_ceil(MAX_FRAGMENT_PAYLOAD)
+    }
+}
+
+/// 视频质量档位表，按质量从低到高排列，档位号即下标
+pub const VIDEO_TIERS: [VideoTier; 5] = [
+    VideoTier { frame_interval_ms: 2000, frame_size: 300 },
+    VideoTier { frame_interval_ms: 1000, frame_size: 600 },
+    VideoTier { frame_interval_ms: 500, frame_size: 1200 },
+    VideoTier { frame_interval_ms: 250, frame_size: 2048 },
+    VideoTier { frame_interval_ms: 200, frame_size: MAX_VIDEO_FRAME_SIZE as u16 },
+];
+
+/// 新会话开始时使用的档位
+pub const DEFAULT_VIDEO_TIER: u8 = 2;
+
+/// 客户端切换视频质量档位后通知服务器，服务器据此调整重组的预期
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoTierNotice {
+    /// 服务（会话）ID
+    pub service_id: u32,
+    /// 新档位，`VIDEO_TIERS`的下标
+    pub tier: u8,
+}
+
+impl VideoTierNotice {
+    /// 序列化通知
+    pub fn serialize(&self) -> [u8; VIDEO_TIER_NOTICE_LEN] {
+        let mut buffer = [0u8; VIDEO_TIER_NOTICE_LEN];
+        buffer[0] = VIDEO_TIER_PAYLOAD_TYPE;
+        put_u32be(&mut buffer[1..5], self.service_id);
+        buffer[5] = self.tier;
+        buffer
+    }
+    
+    /// 解析通知，档位超出档位表时返回None
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < VIDEO_TIER_NOTICE_LEN || buffer[0] != VIDEO_TIER_PAYLOAD_TYPE {
+            return None;
+        }
+        if buffer[5] as usize >= VIDEO_TIERS.len() {
+            return None;
+        }
+        
+        Some(Self {
+            service_id: get_u32be(&buffer[1..5]),
+            tier: buffer[5],
+        })
+    }
+    
+    /// 通知中的档位参数
+    pub fn video_tier(&self) -> VideoTier {
+        VIDEO_TIERS[self.tier as usize]
+    }
+}
\ No newline at end of file
@@ -0,0 +1,45 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::get_u32be;
+use crate::security::send_secure;
+
+/// HELLO负载长度：随机数(4)
+pub const HELLO_PAYLOAD_LEN: usize = 4;
+
+/// 向邻居发送HELLO或HELLO-ACK
+///
+/// 听到邻居信标的节点发送HELLO，邻居用同一随机数回复HELLO-ACK，
+/// 收到应答才说明两个方向的链路都可用。
+pub fn send_hello<H: Hardware>(
+    hardware: &mut H,
+    neighbor: NodeId,
+    packet_type: PacketType,
+    nonce: u32
+) -> Result<(), ReliableError> {
+    let data = nonce.to_be_bytes();
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, neighbor, packet_type, 0, &data);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
+
+/// HELLO或HELLO-ACK中的随机数
+pub fn hello_nonce(packet: &DataPacket) -> Option<u32> {
+    packet.data.get(..HELLO_PAYLOAD_LEN).map(get_u32be)
+}
+
+/// 应答发给本节点的HELLO，不是HELLO或应答发送失败时返回false
+pub fn answer_hello<H: Hardware>(hardware: &mut H, request: &DataPacket) -> bool {
+    if request.header.packet_type != PacketType::Hello as u8 || NodeId(request.header.destination) != hardware.get_node_id() {
+        return false;
+    }
+    
+    let nonce = match hello_nonce(request) {
+        Some(nonce) => nonce,
+        None => return false,
+    };
+    
+    let neighbor = NodeId(request.header.source);
+    send_hello(hardware, neighbor, PacketType::HelloAck, nonce).is_ok()
+}
\ No newline at end of file
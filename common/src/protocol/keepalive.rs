@@ -0,0 +1,60 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::data::flow_id_of;
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u16be, get_u32be, put_u16be, put_u32be};
+use crate::security::send_secure;
+
+/// 路径保活负载长度：服务ID(4) 序号(2)
+pub const KEEPALIVE_PAYLOAD_LEN: usize = 6;
+
+/// 路径保活及其确认
+///
+/// 客户端定期向中继发送保活，中继仍持有该服务的路径时原样回复确认，并刷新路径的空闲计时。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathKeepAlive {
+    pub service_id: u32,
+    pub sequence: u16,
+}
+
+impl PathKeepAlive {
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        if buffer.len() < KEEPALIVE_PAYLOAD_LEN {
+            return 0;
+        }
+        
+        put_u32be(&mut buffer[0..4], self.service_id);
+        put_u16be(&mut buffer[4..6], self.sequence);
+        KEEPALIVE_PAYLOAD_LEN
+    }
+    
+    /// 反序列化，长度不足时返回None
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < KEEPALIVE_PAYLOAD_LEN {
+            return None;
+        }
+        
+        Some(Self {
+            service_id: get_u32be(&buffer[0..4]),
+            sequence: get_u16be(&buffer[4..6]),
+        })
+    }
+}
+
+/// 发送路径保活或保活确认
+pub fn send_keepalive<H: Hardware>(
+    hardware: &mut H,
+    destination: NodeId,
+    packet_type: PacketType,
+    keepalive: &PathKeepAlive
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; KEEPALIVE_PAYLOAD_LEN];
+    let len = keepalive.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, destination, packet_type, 0, &data[..len])
+        .with_flow(flow_id_of(keepalive.service_id));
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
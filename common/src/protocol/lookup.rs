@@ -0,0 +1,115 @@
+use crate::hal::Hardware;
+use crate::protocol::{
+    deserialize_service_request, serialize_service_request, DataPacket, NodeId, PacketType,
+    ServiceRequest, SERVICE_REQUEST_LEN,
+};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u16be, put_u16be};
+use crate::security::send_secure;
+
+/// 目录查询长度：类型(1) 查询节点(6) 主节点(6) 服务请求(13)
+pub const LOOKUP_QUERY_LEN: usize = 13 + SERVICE_REQUEST_LEN;
+
+/// 目录应答长度：类型(1) 查询节点(6) 服务器(6) 有效期(2) 服务请求(13)
+pub const LOOKUP_ANSWER_LEN: usize = 15 + SERVICE_REQUEST_LEN;
+
+/// 目录查询消息类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LookupMessageType {
+    /// 代理节点向主节点查询
+    Query = 0x01,
+    /// 主节点的查询结果
+    Answer = 0x02,
+}
+
+/// 代理目录的查询消息
+///
+/// 不保存完整服务目录的转发节点把服务请求发给主节点，由主节点的目录挑选服务器。
+/// 中继逐跳改写包头，因此查询节点和主节点记录在负载中。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookupMessage {
+    /// 发往主节点的查询，携带客户端的原始请求
+    Query { origin: NodeId, master: NodeId, request: ServiceRequest },
+    /// 发回查询节点的结果，server为None表示没有满足要求的服务器
+    Answer { origin: NodeId, server: Option<NodeId>, ttl_secs: u16, request: ServiceRequest },
+}
+
+impl LookupMessage {
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        match self {
+            LookupMessage::Query { origin, master, request } => {
+                if buffer.len() < LOOKUP_QUERY_LEN {
+                    return 0;
+                }
+                buffer[0] = LookupMessageType::Query as u8;
+                buffer[1..7].copy_from_slice(&origin.0);
+                buffer[7..13].copy_from_slice(&master.0);
+                serialize_service_request(request, &mut buffer[13..]);
+                LOOKUP_QUERY_LEN
+            },
+            LookupMessage::Answer { origin, server, ttl_secs, request } => {
+                if buffer.len() < LOOKUP_ANSWER_LEN {
+                    return 0;
+                }
+                buffer[0] = LookupMessageType::Answer as u8;
+                buffer[1..7].copy_from_slice(&origin.0);
+                buffer[7..13].copy_from_slice(&server.unwrap_or(NodeId::BROADCAST).0);
+                put_u16be(&mut buffer[13..15], ttl_secs);
+                serialize_service_request(request, &mut buffer[15..]);
+                LOOKUP_ANSWER_LEN
+            },
+        }
+    }
+    
+    /// 反序列化
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        let read_node = |offset: usize| {
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&buffer[offset..offset + 6]);
+            NodeId(id)
+        };
+        
+        match *buffer.first()? {
+            t if t == LookupMessageType::Query as u8 => {
+                if buffer.len() < LOOKUP_QUERY_LEN {
+                    return None;
+                }
+                Some(LookupMessage::Query {
+                    origin: read_node(1),
+                    master: read_node(7),
+                    request: deserialize_service_request(&buffer[13..])?,
+                })
+            },
+            t if t == LookupMessageType::Answer as u8 => {
+                if buffer.len() < LOOKUP_ANSWER_LEN {
+                    return None;
+                }
+                let server = read_node(7);
+                Some(LookupMessage::Answer {
+                    origin: read_node(1),
+                    server: if server.is_broadcast() { None } else { Some(server) },
+                    ttl_secs: get_u16be(&buffer[13..15]),
+                    request: deserialize_service_request(&buffer[15..])?,
+                })
+            },
+            _ => None,
+        }
+    }
+}
+
+/// 发送目录查询消息
+pub fn send_lookup<H: Hardware>(
+    hardware: &mut H,
+    next_hop: NodeId,
+    message: &LookupMessage
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; LOOKUP_ANSWER_LEN];
+    let len = message.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, next_hop, PacketType::DirectoryLookup, 0, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
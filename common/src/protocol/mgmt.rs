@@ -0,0 +1,265 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::{ReliableError, MAX_FRAME_PAYLOAD};
+use crate::protocol::wire::{get_u32be, put_u32be};
+use crate::security::{management_tag, send_secure, MANAGEMENT_TAG_LEN};
+
+/// 管理消息头部长度：操作(1) 发起方(6) 目标(6) 序号(4) 属性(1) 状态(1) 值长度(1)
+pub const MGMT_HEADER_LEN: usize = 20;
+
+/// 属性值的最大长度
+pub const MAX_MGMT_VALUE: usize = 160;
+
+const _: () = assert!(MGMT_HEADER_LEN + MAX_MGMT_VALUE + MANAGEMENT_TAG_LEN <= MAX_FRAME_PAYLOAD);
+
+/// 管理操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MgmtOp {
+    /// 读取属性，值字段为可选参数
+    Get = 0x01,
+    /// 设置属性
+    Set = 0x02,
+    /// 对读取或设置的应答
+    Response = 0x03,
+}
+
+impl MgmtOp {
+    /// 从操作字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(MgmtOp::Get),
+            0x02 => Some(MgmtOp::Set),
+            0x03 => Some(MgmtOp::Response),
+            _ => None,
+        }
+    }
+}
+
+/// 可管理的属性
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+#[repr(u8)]
+pub enum MgmtAttribute {
+    /// 信标间隔：毫秒(4)
+    BeaconInterval = 0x01,
+    /// 发射功率：dBm(1)
+    TxPower = 0x02,
+    /// 无线信道：信道(1)
+    Channel = 0x03,
+    /// 路由表（只读）：参数为起始序号(1)，应答为总数(1) [目的(6) 下一跳(6) 度量(1)]*
+    RoutingTable = 0x04,
+    /// 运行时日志级别：级别(1)，1=error 2=warn 3=info 4=debug
+    LogLevel = 0x05,
+    /// 主节点选举间隔：毫秒(4)
+    ElectionInterval = 0x06,
+    /// 路由过期时间：毫秒(4)
+    RouteExpiry = 0x07,
+    /// 管理员列表：[节点ID(6)]*，为空时任何通过认证的请求方都可以设置属性
+    Admins = 0x08,
+    /// 节点角色（只读）：角色(1)
+    Role = 0x09,
+    /// 发射占空比上限：千分比(2)，0表示不限制
+    DutyCycle = 0x0A,
+    /// 服务目录评分权重：带宽(1) 延迟(1) 可靠性(1) 负载(1) 电量(1) 每跳扣分(1)
+    ScoreWeights = 0x0B,
+    /// 监听模式：开关(1)，1表示只收不发并统计听到的流量
+    Monitor = 0x0C,
+    /// 目录代理：开关(1)，1表示不保存完整服务目录，向主节点查询并缓存结果
+    DirectoryProxy = 0x0D,
+    /// 服务目录条目过期时间：毫秒(4)
+    ServiceExpiry = 0x0E,
+    /// 邻居保活间隔：毫秒(4)，到期后重新交换HELLO验证链路
+    KeepaliveInterval = 0x0F,
+    /// 新建数据包的跳数限制：跳数(1)，1-64
+    DefaultTtl = 0x10,
+    /// 电量路由：权重(1) 告急阈值（百分比）(1)，0表示不启用对应功能
+    BatteryRouting = 0x11,
+    /// 转发节点可预留的路径带宽：kbps(2)
+    PathCapacity = 0x12,
+    /// 服务器选择策略：设置为服务类型(1) 策略(1)，读取为各服务类型的策略(7)；0=得分最高 1=轮流 2=加权随机
+    SelectionPolicy = 0x13,
+}
+
+impl MgmtAttribute {
+    /// 从属性ID解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(MgmtAttribute::BeaconInterval),
+            0x02 => Some(MgmtAttribute::TxPower),
+            0x03 => Some(MgmtAttribute::Channel),
+            0x04 => Some(MgmtAttribute::RoutingTable),
+            0x05 => Some(MgmtAttribute::LogLevel),
+            0x06 => Some(MgmtAttribute::ElectionInterval),
+            0x07 => Some(MgmtAttribute::RouteExpiry),
+            0x08 => Some(MgmtAttribute::Admins),
+            0x09 => Some(MgmtAttribute::Role),
+            0x0A => Some(MgmtAttribute::DutyCycle),
+            0x0B => Some(MgmtAttribute::ScoreWeights),
+            0x0C => Some(MgmtAttribute::Monitor),
+            0x0D => Some(MgmtAttribute::DirectoryProxy),
+            0x0E => Some(MgmtAttribute::ServiceExpiry),
+            0x0F => Some(MgmtAttribute::KeepaliveInterval),
+            0x10 => Some(MgmtAttribute::DefaultTtl),
+            0x11 => Some(MgmtAttribute::BatteryRouting),
+            0x12 => Some(MgmtAttribute::PathCapacity),
+            0x13 => Some(MgmtAttribute::SelectionPolicy),
+            _ => None,
+        }
+    }
+    
+    /// 是否为协议定时参数，这些属性可以发往广播地址在全网统一设置
+    pub fn is_timer(&self) -> bool {
+        matches!(self,
+            MgmtAttribute::BeaconInterval
+            | MgmtAttribute::ElectionInterval
+            | MgmtAttribute::RouteExpiry
+            | MgmtAttribute::ServiceExpiry
+            | MgmtAttribute::KeepaliveInterval)
+    }
+}
+
+/// 管理操作结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+#[repr(u8)]
+pub enum MgmtStatus {
+    /// 成功
+    Ok = 0x00,
+    /// 未知属性
+    UnknownAttribute = 0x01,
+    /// 属性只读
+    ReadOnly = 0x02,
+    /// 值无效
+    InvalidValue = 0x03,
+    /// 请求未通过认证、请求方不在管理员列表中，或未配置网络密钥时尝试设置
+    Unauthorized = 0x04,
+    /// 本节点不支持该属性
+    Unsupported = 0x05,
+}
+
+impl MgmtStatus {
+    /// 从状态字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(MgmtStatus::Ok),
+            0x01 => Some(MgmtStatus::UnknownAttribute),
+            0x02 => Some(MgmtStatus::ReadOnly),
+            0x03 => Some(MgmtStatus::InvalidValue),
+            0x04 => Some(MgmtStatus::Unauthorized),
+            0x05 => Some(MgmtStatus::Unsupported),
+            _ => None,
+        }
+    }
+}
+
+/// 管理消息
+///
+/// 端到端的发起方和目标记录在负载中，由中继按目标转发。
+/// 负载末尾附带认证码，见[`crate::security::management_tag`]。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MgmtMessage<'a> {
+    /// 操作
+    pub op: MgmtOp,
+    /// 发起方
+    pub origin: NodeId,
+    /// 目标节点
+    pub target: NodeId,
+    /// 请求序号，应答沿用请求的序号，目标节点据此拒绝重放
+    pub sequence: u32,
+    /// 属性ID，保留原始值以便对未知属性应答
+    pub attribute: u8,
+    /// 结果，请求中为Ok
+    pub status: MgmtStatus,
+    /// 属性值或参数
+    pub value: &'a [u8],
+}
+
+impl<'a> MgmtMessage<'a> {
+    /// 序列化（不含认证码），缓冲区不足或值过长时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let len = MGMT_HEADER_LEN + self.value.len();
+        if self.value.len() > MAX_MGMT_VALUE || buffer.len() < len {
+            return 0;
+        }
+        
+        buffer[0] = self.op as u8;
+        buffer[1..7].copy_from_slice(&self.origin.0);
+        buffer[7..13].copy_from_slice(&self.target.0);
+        put_u32be(&mut buffer[13..17], self.sequence);
+        buffer[17] = self.attribute;
+        buffer[18] = self.status as u8;
+        buffer[19] = self.value.len() as u8;
+        buffer[MGMT_HEADER_LEN..len].copy_from_slice(self.value);
+        
+        len
+    }
+    
+    /// 反序列化，返回消息和其后的认证码
+    pub fn deserialize(buffer: &'a [u8]) -> Option<(Self, &'a [u8])> {
+        if buffer.len() < MGMT_HEADER_LEN {
+            return None;
+        }
+        
+        let value_len = buffer[19] as usize;
+        let end = MGMT_HEADER_LEN + value_len;
+        if value_len > MAX_MGMT_VALUE || buffer.len() < end {
+            return None;
+        }
+        
+        let mut origin = [0u8; 6];
+        origin.copy_from_slice(&buffer[1..7]);
+        let mut target = [0u8; 6];
+        target.copy_from_slice(&buffer[7..13]);
+        
+        let message = Self {
+            op: MgmtOp::from_u8(buffer[0])?,
+            origin: NodeId(origin),
+            target: NodeId(target),
+            sequence: get_u32be(&buffer[13..17]),
+            attribute: buffer[17],
+            status: MgmtStatus::from_u8(buffer[18])?,
+            value: &buffer[MGMT_HEADER_LEN..end],
+        };
+        
+        Some((message, &buffer[end..]))
+    }
+}
+
+/// 附加认证码后向下一跳发送管理消息，未配置网络密钥时认证码为全零
+pub fn send_mgmt<H: Hardware>(
+    hardware: &mut H,
+    next_hop: NodeId,
+    message: &MgmtMessage
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; MGMT_HEADER_LEN + MAX_MGMT_VALUE + MANAGEMENT_TAG_LEN];
+    let len = message.serialize(&mut data);
+    if len == 0 {
+        return Err(ReliableError::PayloadTooLarge);
+    }
+    
+    if let Some(tag) = management_tag(hardware.get_security(), &data[..len]) {
+        data[len..len + MANAGEMENT_TAG_LEN].copy_from_slice(&tag);
+    }
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, next_hop, PacketType::Mgmt, 0, &data[..len + MANAGEMENT_TAG_LEN]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
+
+/// 校验管理消息的认证码，未配置网络密钥时返回false
+pub fn verify_mgmt<H: Hardware>(hardware: &mut H, data: &[u8]) -> bool {
+    let (message, tag) = match MgmtMessage::deserialize(data) {
+        Some(parsed) => parsed,
+        None => return false,
+    };
+    let len = MGMT_HEADER_LEN + message.value.len();
+    
+    match management_tag(hardware.get_security(), &data[..len]) {
+        // 逐字节累积差异，比较时间与内容无关
+        Some(expected) => tag.len() >= MANAGEMENT_TAG_LEN
+            && tag.iter().zip(expected.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0,
+        None => false,
+    }
+}
\ No newline at end of file
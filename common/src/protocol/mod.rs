@@ -1,17 +1,19 @@
 #![no_std]
-use zerocopy::{AsBytes, FromBytes};
+use core::fmt;
+use core::str::FromStr;
+use self::wire::{get_u16be, get_u16le, get_u32be, get_u32le, put_u16be, put_u16le, put_u32be, put_u32le};
 
 /// 网络层统一封包格式
-#[repr(C, packed)]
-#[derive(AsBytes, FromBytes)]
+///
+/// 与数据包头部相同，收发都经过[`NetworkPacket::to_bytes`]和[`NetworkPacket::from_bytes`]，不依赖结构体的内存布局。
+#[derive(Clone, Copy)]
 pub struct NetworkPacket {
     pub header: PacketHeader,
-    pub payload: [u8; 252], // 总长度256字节
+    pub payload: [u8; 252],
 }
 
 /// 协议头部定义
-#[repr(C, packed)]
-#[derive(AsBytes, FromBytes)]
+#[derive(Debug, Clone, Copy)]
 pub struct PacketHeader {
     pub magic: u16,        // 0xAA55
     pub version: u8,       // 0x01
@@ -22,39 +24,49 @@ pub struct PacketHeader {
     pub checksum: u32,
 }
 
-/// 信标负载结构，用于零拷贝从NetworkPacket中提取
-#[repr(C, packed)]
-#[derive(Debug, Clone, Copy)]
-pub struct BeaconPayload {
-    /// 协议版本
-    pub version: u8,
-    /// 包类型
-    pub packet_type: u8,
-    /// 源节点ID
-    pub source: [u8; 6],
-    /// 电池电量（百分比）
-    pub battery_level: u8,
-    /// 信号强度指示
-    pub rssi: i8,
-    /// 路由跳数
-    pub hop_count: u8,
-    /// 预留字段
-    pub reserved: [u8; 3],
-    /// 校验和
-    pub checksum: u16,
-}
-
+pub mod batch;
 pub mod beacon;
+pub mod channel;
+pub mod command;
 pub mod data;
+pub mod echo;
+pub mod election;
+pub mod error_report;
+pub mod flood;
+pub mod frame;
+pub mod hello;
+pub mod keepalive;
+pub mod lookup;
+pub mod mgmt;
+pub mod ota;
+pub mod path;
+pub mod payload;
+pub mod reliable;
+pub mod route_advert;
+pub mod route_discovery;
+pub mod route_table;
+pub mod service_advert;
+pub mod service_beacon;
+pub mod slip;
+pub mod stats;
+pub mod tdma;
+pub mod time_sync;
+pub mod topology;
+/// 线上字节序：信标和数据包头部的多字节字段为小端，与已部署的小端节点按内存布局发出的字节相同；
+/// 各类负载中的字段为大端（网络字节序）。收发都显式转换，不依赖主机字节序
+pub mod wire;
 
-pub use beacon::Beacon;
+pub use beacon::{Beacon, NodeRole, MAX_BEACON_HOPS};
 pub use data::DataPacket;
 
 // 协议常量和公共类型定义
 pub const MAX_PACKET_SIZE: usize = 256;
-pub const PROTOCOL_VERSION: u8 = 1;
+/// 版本2在数据包头部加入了流ID，版本3加入了跳数限制，版本4在信标中加入了认证码，
+/// 版本5的视频帧改为整帧按数据包头部的分片字段拆分，版本6的固件更新消息加入了认证码和镜像摘要
+pub const PROTOCOL_VERSION: u8 = 6;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
 #[repr(u8)]
 pub enum PacketType {
     Beacon = 0x01,
@@ -65,9 +77,83 @@ pub enum PacketType {
     ServiceResponse = 0x06, // 服务响应
     PathEstablish = 0x07,  // 路径建立
     PathConfirm = 0x08,    // 路径确认
+    ServiceRenew = 0x09,   // 服务租约续期
+    Congestion = 0x0A,     // 拥塞通知
+    ServiceHandover = 0x0B, // 服务切换到新的中继
+    EchoRequest = 0x0C,    // 回显请求
+    EchoReply = 0x0D,      // 回显应答
+    Ota = 0x0E,            // 固件更新
+    Mgmt = 0x0F,           // 远程管理
+    TimeSync = 0x10,       // 网络时间信标
+    Topology = 0x11,       // 拓扑收集
+    ErrorReport = 0x12,    // 错误报告
+    Hello = 0x13,          // 邻居双向验证请求
+    HelloAck = 0x14,       // 邻居双向验证应答
+    SlotRequest = 0x15,    // 发送时隙申请
+    DirectoryLookup = 0x16, // 代理目录向主节点查询
+    ChannelPlan = 0x17,    // 信道质量报告和信道切换
+    RouteAdvert = 0x18,    // 距离向量路由通告
+    ServiceAdvertisement = 0x19, // 转发节点之间的服务目录同步
+    ServiceBeacon = 0x1A,  // 服务器广播的服务能力
+    ServiceClose = 0x1B,   // 客户端关闭服务，拆除中继路径
+    PathKeepAlive = 0x1C,  // 客户端的路径保活
+    PathKeepAliveAck = 0x1D, // 中继的路径保活确认
+    Flood = 0x1E,          // 全网泛洪，负载中携带原始类型
+    StatsRequest = 0x1F,   // 远程读取计数器
+    StatsResponse = 0x20,  // 计数器快照应答
+    RouteRequest = 0x21,   // 按需路由发现的请求
+    RouteReply = 0x22,     // 路由发现应答
+    RouteTableRequest = 0x23, // 远程读取路由表
+    RouteTableResponse = 0x24, // 路由表快照应答
+}
+
+impl PacketType {
+    /// 从头部中的类型字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(PacketType::Beacon),
+            0x02 => Some(PacketType::Data),
+            0x03 => Some(PacketType::Ack),
+            0x04 => Some(PacketType::Control),
+            0x05 => Some(PacketType::ServiceRequest),
+            0x06 => Some(PacketType::ServiceResponse),
+            0x07 => Some(PacketType::PathEstablish),
+            0x08 => Some(PacketType::PathConfirm),
+            0x09 => Some(PacketType::ServiceRenew),
+            0x0A => Some(PacketType::Congestion),
+            0x0B => Some(PacketType::ServiceHandover),
+            0x0C => Some(PacketType::EchoRequest),
+            0x0D => Some(PacketType::EchoReply),
+            0x0E => Some(PacketType::Ota),
+            0x0F => Some(PacketType::Mgmt),
+            0x10 => Some(PacketType::TimeSync),
+            0x11 => Some(PacketType::Topology),
+            0x12 => Some(PacketType::ErrorReport),
+            0x13 => Some(PacketType::Hello),
+            0x14 => Some(PacketType::HelloAck),
+            0x15 => Some(PacketType::SlotRequest),
+            0x16 => Some(PacketType::DirectoryLookup),
+            0x17 => Some(PacketType::ChannelPlan),
+            0x18 => Some(PacketType::RouteAdvert),
+            0x19 => Some(PacketType::ServiceAdvertisement),
+            0x1A => Some(PacketType::ServiceBeacon),
+            0x1B => Some(PacketType::ServiceClose),
+            0x1C => Some(PacketType::PathKeepAlive),
+            0x1D => Some(PacketType::PathKeepAliveAck),
+            0x1E => Some(PacketType::Flood),
+            0x1F => Some(PacketType::StatsRequest),
+            0x20 => Some(PacketType::StatsResponse),
+            0x21 => Some(PacketType::RouteRequest),
+            0x22 => Some(PacketType::RouteReply),
+            0x23 => Some(PacketType::RouteTableRequest),
+            0x24 => Some(PacketType::RouteTableResponse),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
 pub struct NodeId(pub [u8; 6]);
 
 impl NodeId {
@@ -80,10 +166,104 @@ impl NodeId {
     pub fn is_broadcast(&self) -> bool {
         self.0 == Self::BROADCAST.0
     }
+    
+    /// 简短显示形式，只显示最后两个字节，用于日志中的频繁输出
+    pub fn short(&self) -> ShortNodeId {
+        ShortNodeId(*self)
+    }
+}
+
+/// 冒号分隔的小写十六进制，与MAC地址的写法相同，例如 `f1:f2:f3:f4:f5:f6`
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// 节点ID解析错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseNodeIdError;
+
+impl fmt::Display for ParseNodeIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("节点ID应为6个字节的十六进制，例如 f1:f2:f3:f4:f5:f6")
+    }
 }
 
+/// 接受冒号分隔的写法和不带分隔的12位十六进制，大小写均可
+impl FromStr for NodeId {
+    type Err = ParseNodeIdError;
+    
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut id = [0u8; 6];
+        if text.contains(':') {
+            let mut parts = text.split(':');
+            for byte in id.iter_mut() {
+                *byte = parse_hex_byte(parts.next())?;
+            }
+            if parts.next().is_some() {
+                return Err(ParseNodeIdError);
+            }
+        } else {
+            if text.len() != 12 {
+                return Err(ParseNodeIdError);
+            }
+            for (i, byte) in id.iter_mut().enumerate() {
+                *byte = parse_hex_byte(text.get(i * 2..i * 2 + 2))?;
+            }
+        }
+        Ok(Self(id))
+    }
+}
+
+/// 解析两位十六进制数字
+fn parse_hex_byte(part: Option<&str>) -> Result<u8, ParseNodeIdError> {
+    let part = part.filter(|part| part.len() == 2 && part.bytes().all(|b| b.is_ascii_hexdigit()))
+        .ok_or(ParseNodeIdError)?;
+    u8::from_str_radix(part, 16).map_err(|_| ParseNodeIdError)
+}
+
+/// 节点ID的简短显示形式，例如 `…f5:f6`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+pub struct ShortNodeId(pub NodeId);
+
+impl fmt::Display for ShortNodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "…{:02x}:{:02x}", self.0.0[4], self.0.0[5])
+    }
+}
+
+/// 服务请求和响应的线格式版本
+pub const SERVICE_PROTOCOL_VERSION: u8 = 2;
+
+/// 版本字节的最高位置1，与首字节为服务类型的v1请求区分
+const SERVICE_VERSION_FLAG: u8 = 0x80;
+
+/// 服务请求负载长度：版本(1) 请求ID(2) 类型(1) 带宽(2) 延迟(2) 可靠性(1) 过期时间(4)
+pub const SERVICE_REQUEST_LEN: usize = 13;
+
+/// v1服务请求负载长度：类型(1) 带宽(2) 延迟(2) 可靠性(1) 过期时间高16位(2)
+pub const SERVICE_REQUEST_V1_LEN: usize = 8;
+
+/// 加入版本字节之前曾短暂使用的10字节请求：v1请求之后补上过期时间的低16位(2)
+pub const SERVICE_REQUEST_V1_FULL_EXPIRY_LEN: usize = 10;
+
+/// 服务响应负载长度：版本(1) 请求ID(2) 服务ID(4) 服务器(6) 状态(1)
+pub const SERVICE_RESPONSE_LEN: usize = 14;
+
+/// v1服务响应负载长度：服务ID(4) 服务器(6) 状态(1)
+pub const SERVICE_RESPONSE_V1_LEN: usize = 11;
+
 // 服务类型定义
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
 #[repr(u8)]
 pub enum ServiceType {
     Storage = 0x01,       // 存储服务
@@ -95,8 +275,24 @@ pub enum ServiceType {
     SensorCollection = 0x07, // 传感器数据收集
 }
 
+impl ServiceType {
+    /// 从类型字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(ServiceType::Storage),
+            0x02 => Some(ServiceType::Processing),
+            0x03 => Some(ServiceType::Gateway),
+            0x04 => Some(ServiceType::VideoRelay),
+            0x05 => Some(ServiceType::AudioRelay),
+            0x06 => Some(ServiceType::DataRelay),
+            0x07 => Some(ServiceType::SensorCollection),
+            _ => None,
+        }
+    }
+}
+
 // 服务质量要求
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct QosRequirements {
     pub min_bandwidth: u16,  // 最小带宽要求 (kbps)
     pub max_latency: u16,    // 最大延迟 (ms)
@@ -104,23 +300,48 @@ pub struct QosRequirements {
 }
 
 // 服务请求包
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ServiceRequest {
+    pub request_id: u16,                // 请求ID，响应中原样带回（0表示不关联，v1请求解析为0）
     pub service_type: ServiceType,      // 请求的服务类型
     pub qos: QosRequirements,           // 服务质量要求
     pub expiry_time: u32,               // 服务过期时间 (秒)
 }
 
 // 服务响应包
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct ServiceResponse {
+    pub request_id: u16,                // 对应请求的请求ID
     pub service_id: u32,                // 服务ID
     pub server_node_id: NodeId,         // 服务器节点ID
     pub status: u8,                     // 状态(0=成功, 1=失败, 2=部分满足)
 }
 
+// 服务租约续期请求
+#[derive(Debug, PartialEq, Eq)]
+pub struct ServiceRenewal {
+    pub service_id: u32,                // 需要续期的服务ID
+    pub expiry_time: u32,               // 新的租期 (秒)
+}
+
+// 服务切换请求，客户端漫游到新中继时保留原服务ID
+#[derive(Debug, PartialEq, Eq)]
+pub struct ServiceHandover {
+    pub service_id: u32,                // 原服务ID
+    pub server_node_id: NodeId,         // 原服务器节点ID
+    pub request: ServiceRequest,        // 服务类型、QoS要求和租期
+}
+
+// 服务关闭请求，中继和服务器据此释放路径和会话状态
+#[derive(Debug, PartialEq, Eq)]
+pub struct ServiceClose {
+    pub service_id: u32,                // 要关闭的服务ID
+    pub reason: u8,                     // 关闭原因（0=正常关闭）
+}
+
 // 路径建立状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
 #[repr(u8)]
 pub enum PathStatus {
     Success = 0x00,        // 成功建立
@@ -130,11 +351,71 @@ pub enum PathStatus {
     ServerBusy = 0x04,     // 服务器忙
 }
 
+impl PacketHeader {
+    /// 线上长度
+    pub const SIZE: usize = 21;
+    
+    /// 序列化为线上格式，多字节字段为小端
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        put_u16le(&mut bytes[0..2], self.magic);
+        bytes[2] = self.version;
+        bytes[3] = self.packet_type as u8;
+        bytes[4] = self.ttl;
+        bytes[5..11].copy_from_slice(&self.src_mac);
+        bytes[11..17].copy_from_slice(&self.dest_mac);
+        put_u32le(&mut bytes[17..21], self.checksum);
+        bytes
+    }
+    
+    /// 从线上格式解析，缓冲区不足头部长度或包类型未知时返回None
+    pub fn from_bytes(buffer: &[u8]) -> Option<Self> {
+        let buffer = buffer.get(..Self::SIZE)?;
+        let mut src_mac = [0u8; 6];
+        src_mac.copy_from_slice(&buffer[5..11]);
+        let mut dest_mac = [0u8; 6];
+        dest_mac.copy_from_slice(&buffer[11..17]);
+        
+        Some(Self {
+            magic: get_u16le(&buffer[0..2]),
+            version: buffer[2],
+            packet_type: PacketType::from_u8(buffer[3])?,
+            ttl: buffer[4],
+            src_mac,
+            dest_mac,
+            checksum: get_u32le(&buffer[17..21]),
+        })
+    }
+}
+
 impl NetworkPacket {
-    /// 零拷贝转换信标包
-    pub fn as_beacon(&self) -> Option<&BeaconPayload> {
+    /// 线上长度
+    pub const SIZE: usize = PacketHeader::SIZE + 252;
+    
+    /// 序列化为线上格式
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[..PacketHeader::SIZE].copy_from_slice(&self.header.to_bytes());
+        bytes[PacketHeader::SIZE..].copy_from_slice(&self.payload);
+        bytes
+    }
+    
+    /// 从线上格式解析，不足一个完整的包时返回None
+    pub fn from_bytes(buffer: &[u8]) -> Option<Self> {
+        let buffer = buffer.get(..Self::SIZE)?;
+        let mut payload = [0u8; 252];
+        payload.copy_from_slice(&buffer[PacketHeader::SIZE..]);
+        
+        Some(Self {
+            header: PacketHeader::from_bytes(buffer)?,
+            payload,
+        })
+    }
+    
+    /// 解析负载中的信标
+    pub fn as_beacon(&self) -> Option<Beacon> {
         if self.header.packet_type == PacketType::Beacon {
-            Some(unsafe { &*(&self.payload as *const _ as *const BeaconPayload) })
+            Beacon::from_bytes(&self.payload[..Beacon::SIZE])
         } else {
             None
         }
@@ -142,57 +423,70 @@ impl NetworkPacket {
 }
 
 // 序列化/反序列化工具函数
+/// 按当前版本序列化服务请求
 pub fn serialize_service_request(request: &ServiceRequest, buffer: &mut [u8]) -> usize {
-    if buffer.len() < 8 {
+    if buffer.len() < SERVICE_REQUEST_LEN {
         return 0;
     }
     
-    buffer[0] = request.service_type as u8;
+    buffer[0] = SERVICE_VERSION_FLAG | SERVICE_PROTOCOL_VERSION;
+    put_u16be(&mut buffer[1..3], request.request_id);
+    buffer[3] = request.service_type as u8;
     
     // 序列化QoS需求
-    let bandwidth_bytes = request.qos.min_bandwidth.to_be_bytes();
-    buffer[1] = bandwidth_bytes[0];
-    buffer[2] = bandwidth_bytes[1];
-    
-    let latency_bytes = request.qos.max_latency.to_be_bytes();
-    buffer[3] = latency_bytes[0];
-    buffer[4] = latency_bytes[1];
-    
-    buffer[5] = request.qos.reliability;
+    put_u16be(&mut buffer[4..6], request.qos.min_bandwidth);
+    put_u16be(&mut buffer[6..8], request.qos.max_latency);
+    buffer[8] = request.qos.reliability;
     
     // 序列化过期时间
-    let expiry_bytes = request.expiry_time.to_be_bytes();
-    buffer[6] = expiry_bytes[0];
-    buffer[7] = expiry_bytes[1];
+    put_u32be(&mut buffer[9..13], request.expiry_time);
     
-    8
+    SERVICE_REQUEST_LEN
 }
 
+/// 解析服务请求，兼容没有版本字节和请求ID的v1格式
+///
+/// 没有版本字节的请求按长度区分：8字节为v1，10字节带完整的过期时间，高16位与v1位置相同。
 pub fn deserialize_service_request(buffer: &[u8]) -> Option<ServiceRequest> {
-    if buffer.len() < 8 {
+    let first = *buffer.first()?;
+    if first & SERVICE_VERSION_FLAG == 0 {
+        let mut request = deserialize_service_request_v1(buffer)?;
+        if buffer.len() >= SERVICE_REQUEST_V1_FULL_EXPIRY_LEN {
+            request.expiry_time = get_u32be(&buffer[6..10]);
+        }
+        return Some(request);
+    }
+    if first != SERVICE_VERSION_FLAG | SERVICE_PROTOCOL_VERSION || buffer.len() < SERVICE_REQUEST_LEN {
         return None;
     }
     
-    let service_type = match buffer[0] {
-        0x01 => ServiceType::Storage,
-        0x02 => ServiceType::Processing,
-        0x03 => ServiceType::Gateway,
-        0x04 => ServiceType::VideoRelay,
-        0x05 => ServiceType::AudioRelay,
-        0x06 => ServiceType::DataRelay,
-        0x07 => ServiceType::SensorCollection,
-        _ => return None,
-    };
+    let body = &buffer[3..];
+    let mut request = deserialize_service_request_v1(body)?;
+    request.request_id = get_u16be(&buffer[1..3]);
+    request.expiry_time = get_u32be(&body[6..10]);
+    Some(request)
+}
+
+/// 解析v1服务请求：类型(1) 带宽(2) 延迟(2) 可靠性(1) 过期时间高16位(2)
+///
+/// v1只发送过期时间的高16位，按原来的解码方式补零，与旧服务器得到的值相同。
+fn deserialize_service_request_v1(buffer: &[u8]) -> Option<ServiceRequest> {
+    if buffer.len() < SERVICE_REQUEST_V1_LEN {
+        return None;
+    }
+    
+    let service_type = ServiceType::from_u8(buffer[0])?;
     
     // 反序列化QoS需求
-    let min_bandwidth = u16::from_be_bytes([buffer[1], buffer[2]]);
-    let max_latency = u16::from_be_bytes([buffer[3], buffer[4]]);
+    let min_bandwidth = get_u16be(&buffer[1..3]);
+    let max_latency = get_u16be(&buffer[3..5]);
     let reliability = buffer[5];
     
     // 反序列化过期时间
     let expiry_time = u32::from_be_bytes([buffer[6], buffer[7], 0, 0]);
     
     Some(ServiceRequest {
+        request_id: 0,
         service_type,
         qos: QosRequirements {
             min_bandwidth,
@@ -203,17 +497,32 @@ pub fn deserialize_service_request(buffer: &[u8]) -> Option<ServiceRequest> {
     })
 }
 
+/// 序列化服务响应
+///
+/// 请求ID为0（v1请求或续期）时按v1格式输出，旧客户端只认识11字节的响应。
 pub fn serialize_service_response(response: &ServiceResponse, buffer: &mut [u8]) -> usize {
-    if buffer.len() < 11 {
+    if response.request_id == 0 {
+        return serialize_service_response_v1(response, buffer);
+    }
+    if buffer.len() < SERVICE_RESPONSE_LEN {
+        return 0;
+    }
+    
+    buffer[0] = SERVICE_VERSION_FLAG | SERVICE_PROTOCOL_VERSION;
+    put_u16be(&mut buffer[1..3], response.request_id);
+    serialize_service_response_v1(response, &mut buffer[3..]);
+    
+    SERVICE_RESPONSE_LEN
+}
+
+/// 序列化v1服务响应：服务ID(4) 服务器(6) 状态(1)
+fn serialize_service_response_v1(response: &ServiceResponse, buffer: &mut [u8]) -> usize {
+    if buffer.len() < SERVICE_RESPONSE_V1_LEN {
         return 0;
     }
     
     // 序列化服务ID
-    let service_id_bytes = response.service_id.to_be_bytes();
-    buffer[0] = service_id_bytes[0];
-    buffer[1] = service_id_bytes[1];
-    buffer[2] = service_id_bytes[2];
-    buffer[3] = service_id_bytes[3];
+    put_u32be(&mut buffer[0..4], response.service_id);
     
     // 序列化服务器节点ID
     buffer[4..10].copy_from_slice(&response.server_node_id.0);
@@ -221,16 +530,34 @@ pub fn serialize_service_response(response: &ServiceResponse, buffer: &mut [u8])
     // 序列化状态
     buffer[10] = response.status;
     
-    11
+    SERVICE_RESPONSE_V1_LEN
 }
 
+/// 解析服务响应，兼容v1格式
+///
+/// v1响应首字节是服务ID的高位，无法靠版本位区分，按长度判断：v1响应恰好11字节。
 pub fn deserialize_service_response(buffer: &[u8]) -> Option<ServiceResponse> {
-    if buffer.len() < 11 {
+    if buffer.len() < SERVICE_RESPONSE_LEN {
+        return deserialize_service_response_v1(buffer);
+    }
+    if buffer[0] != SERVICE_VERSION_FLAG | SERVICE_PROTOCOL_VERSION {
+        return None;
+    }
+    
+    let request_id = get_u16be(&buffer[1..3]);
+    let mut response = deserialize_service_response_v1(&buffer[3..])?;
+    response.request_id = request_id;
+    Some(response)
+}
+
+/// 解析v1服务响应：服务ID(4) 服务器(6) 状态(1)
+fn deserialize_service_response_v1(buffer: &[u8]) -> Option<ServiceResponse> {
+    if buffer.len() < SERVICE_RESPONSE_V1_LEN {
         return None;
     }
     
     // 反序列化服务ID
-    let service_id = u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+    let service_id = get_u32be(&buffer[0..4]);
     
     // 反序列化服务器节点ID
     let mut server_node_id = [0u8; 6];
@@ -240,8 +567,89 @@ pub fn deserialize_service_response(buffer: &[u8]) -> Option<ServiceResponse> {
     let status = buffer[10];
     
     Some(ServiceResponse {
+        request_id: 0,
         service_id,
         server_node_id: NodeId(server_node_id),
         status,
     })
+}
+
+pub fn serialize_service_renewal(renewal: &ServiceRenewal, buffer: &mut [u8]) -> usize {
+    if buffer.len() < 8 {
+        return 0;
+    }
+    
+    put_u32be(&mut buffer[0..4], renewal.service_id);
+    put_u32be(&mut buffer[4..8], renewal.expiry_time);
+    
+    8
+}
+
+pub fn deserialize_service_renewal(buffer: &[u8]) -> Option<ServiceRenewal> {
+    if buffer.len() < 8 {
+        return None;
+    }
+    
+    let service_id = get_u32be(&buffer[0..4]);
+    let expiry_time = get_u32be(&buffer[4..8]);
+    
+    Some(ServiceRenewal {
+        service_id,
+        expiry_time,
+    })
+}
+
+pub fn serialize_service_close(close: &ServiceClose, buffer: &mut [u8]) -> usize {
+    if buffer.len() < 6 {
+        return 0;
+    }
+    
+    put_u32be(&mut buffer[0..4], close.service_id);
+    buffer[4] = close.reason;
+    buffer[5] = 0; // 预留
+    
+    6
+}
+
+pub fn deserialize_service_close(buffer: &[u8]) -> Option<ServiceClose> {
+    if buffer.len() < 6 {
+        return None;
+    }
+    
+    Some(ServiceClose {
+        service_id: get_u32be(&buffer[0..4]),
+        reason: buffer[4],
+    })
+}
+
+pub fn serialize_service_handover(handover: &ServiceHandover, buffer: &mut [u8]) -> usize {
+    if buffer.len() < 10 + SERVICE_REQUEST_LEN {
+        return 0;
+    }
+    
+    put_u32be(&mut buffer[0..4], handover.service_id);
+    buffer[4..10].copy_from_slice(&handover.server_node_id.0);
+    
+    match serialize_service_request(&handover.request, &mut buffer[10..]) {
+        0 => 0,
+        len => 10 + len,
+    }
+}
+
+pub fn deserialize_service_handover(buffer: &[u8]) -> Option<ServiceHandover> {
+    // 内嵌的请求可能是v1格式，长度由请求解析再检查
+    if buffer.len() < 10 + SERVICE_REQUEST_V1_LEN {
+        return None;
+    }
+    
+    let service_id = get_u32be(&buffer[0..4]);
+    let mut server_node_id = [0u8; 6];
+    server_node_id.copy_from_slice(&buffer[4..10]);
+    let request = deserialize_service_request(&buffer[10..])?;
+    
+    Some(ServiceHandover {
+        service_id,
+        server_node_id: NodeId(server_node_id),
+        request,
+    })
 }
\ No newline at end of file
@@ -0,0 +1,209 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::{ReliableError, MAX_FRAME_PAYLOAD};
+use crate::protocol::wire::{get_u32be, put_u32be};
+use crate::security::send_secure;
+
+/// 固件更新消息公共头部长度：消息类型(1) 发起方(6) 目标(6) 镜像版本(4) 认证码(8)
+pub const OTA_HEADER_LEN: usize = 25;
+
+/// 单个镜像分块的数据长度
+pub const OTA_CHUNK_SIZE: usize = 128;
+
+/// 固件更新消息认证码长度
+pub const OTA_TAG_LEN: usize = 8;
+
+/// 镜像摘要（SHA-256）长度
+pub const OTA_DIGEST_LEN: usize = 32;
+
+/// 固件更新消息的最大长度
+pub const MAX_OTA_MESSAGE: usize = OTA_HEADER_LEN + 4 + OTA_CHUNK_SIZE;
+
+const _: () = assert!(MAX_OTA_MESSAGE <= MAX_FRAME_PAYLOAD);
+
+/// 固件更新结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+#[repr(u8)]
+pub enum OtaStatus {
+    /// 镜像已校验并标记为下次启动
+    Installed = 0x00,
+    /// 接收完成但CRC不符
+    CrcMismatch = 0x01,
+    /// 镜像超出暂存区容量
+    TooLarge = 0x02,
+    /// 暂存区读写失败
+    StorageError = 0x03,
+    /// 节点已运行该版本
+    UpToDate = 0x04,
+    /// 接收完成但摘要与通知不符
+    DigestMismatch = 0x05,
+}
+
+impl OtaStatus {
+    /// 从状态字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(OtaStatus::Installed),
+            0x01 => Some(OtaStatus::CrcMismatch),
+            0x02 => Some(OtaStatus::TooLarge),
+            0x03 => Some(OtaStatus::StorageError),
+            0x04 => Some(OtaStatus::UpToDate),
+            0x05 => Some(OtaStatus::DigestMismatch),
+            _ => None,
+        }
+    }
+}
+
+/// 固件更新消息内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtaBody<'a> {
+    /// 服务器通知节点有新镜像：长度(4) CRC-32(4) SHA-256(32)
+    Offer { size: u32, crc: u32, digest: [u8; OTA_DIGEST_LEN] },
+    /// 节点请求从指定偏移开始的分块：偏移(4)
+    Request { offset: u32 },
+    /// 镜像分块：偏移(4) 数据
+    Chunk { offset: u32, data: &'a [u8] },
+    /// 节点报告更新结果：状态(1)
+    Result { status: OtaStatus },
+}
+
+/// 固件更新消息
+///
+/// 与回显相同，中继逐跳改写包头，端到端的发起方和目标记录在负载中。
+/// 由节点按偏移拉取分块，中断后可以从已保存的偏移继续。
+/// 发起方用网络密钥计算认证码，中继原样转发，见[`crate::security::ota_tag`]。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OtaMessage<'a> {
+    /// 发送这条消息的端点
+    pub origin: NodeId,
+    /// 消息的最终接收端点
+    pub target: NodeId,
+    /// 镜像版本
+    pub version: u32,
+    /// 消息内容
+    pub body: OtaBody<'a>,
+    /// 认证码
+    pub tag: [u8; OTA_TAG_LEN],
+}
+
+impl<'a> OtaMessage<'a> {
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let body_len = match self.body {
+            OtaBody::Offer { .. } => 8 + OTA_DIGEST_LEN,
+            OtaBody::Request { .. } => 4,
+            OtaBody::Chunk { data, .. } => 4 + data.len(),
+            OtaBody::Result { .. } => 1,
+        };
+        if buffer.len() < OTA_HEADER_LEN + body_len {
+            return 0;
+        }
+        
+        buffer[0] = match self.body {
+            OtaBody::Offer { .. } => 0x01,
+            OtaBody::Request { .. } => 0x02,
+            OtaBody::Chunk { .. } => 0x03,
+            OtaBody::Result { .. } => 0x04,
+        };
+        buffer[1..7].copy_from_slice(&self.origin.0);
+        buffer[7..13].copy_from_slice(&self.target.0);
+        put_u32be(&mut buffer[13..17], self.version);
+        buffer[17..OTA_HEADER_LEN].copy_from_slice(&self.tag);
+        
+        let body = &mut buffer[OTA_HEADER_LEN..];
+        match self.body {
+            OtaBody::Offer { size, crc, digest } => {
+                put_u32be(&mut body[0..4], size);
+                put_u32be(&mut body[4..8], crc);
+                body[8..8 + OTA_DIGEST_LEN].copy_from_slice(&digest);
+            },
+            OtaBody::Request { offset } => {
+                put_u32be(&mut body[0..4], offset);
+            },
+            OtaBody::Chunk { offset, data } => {
+                put_u32be(&mut body[0..4], offset);
+                body[4..4 + data.len()].copy_from_slice(data);
+            },
+            OtaBody::Result { status } => {
+                body[0] = status as u8;
+            },
+        }
+        
+        OTA_HEADER_LEN + body_len
+    }
+    
+    /// 反序列化
+    pub fn deserialize(buffer: &'a [u8]) -> Option<Self> {
+        if buffer.len() < OTA_HEADER_LEN {
+            return None;
+        }
+        
+        let mut origin = [0u8; 6];
+        origin.copy_from_slice(&buffer[1..7]);
+        let mut target = [0u8; 6];
+        target.copy_from_slice(&buffer[7..13]);
+        let version = get_u32be(&buffer[13..17]);
+        let mut tag = [0u8; OTA_TAG_LEN];
+        tag.copy_from_slice(&buffer[17..OTA_HEADER_LEN]);
+        
+        let body = &buffer[OTA_HEADER_LEN..];
+        let read_u32 = |offset: usize| -> Option<u32> {
+            if body.len() < offset + 4 {
+                return None;
+            }
+            Some(get_u32be(&body[offset..offset + 4]))
+        };
+        
+        let body = match buffer[0] {
+            0x01 => {
+                let mut digest = [0u8; OTA_DIGEST_LEN];
+                digest.copy_from_slice(body.get(8..8 + OTA_DIGEST_LEN)?);
+                OtaBody::Offer { size: read_u32(0)?, crc: read_u32(4)?, digest }
+            },
+            0x02 => OtaBody::Request { offset: read_u32(0)? },
+            0x03 => {
+                let offset = read_u32(0)?;
+                let data = &body[4..];
+                if data.is_empty() || data.len() > OTA_CHUNK_SIZE {
+                    return None;
+                }
+                OtaBody::Chunk { offset, data }
+            },
+            0x04 => OtaBody::Result { status: OtaStatus::from_u8(*body.first()?)? },
+            _ => return None,
+        };
+        
+        Some(Self {
+            origin: NodeId(origin),
+            target: NodeId(target),
+            version,
+            body,
+            tag,
+        })
+    }
+    
+    /// 认证码覆盖的内容：认证码置0后的线上格式，缓冲区不足时返回0
+    pub fn signed_bytes(&self, buffer: &mut [u8]) -> usize {
+        Self { tag: [0; OTA_TAG_LEN], ..*self }.serialize(buffer)
+    }
+}
+
+/// 向下一跳发送固件更新消息，认证码由发起方填写，转发时原样保留
+pub fn send_ota<H: Hardware>(
+    hardware: &mut H,
+    next_hop: NodeId,
+    packet_id: u16,
+    message: &OtaMessage
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; MAX_OTA_MESSAGE];
+    let len = message.serialize(&mut data);
+    if len == 0 {
+        return Err(ReliableError::PayloadTooLarge);
+    }
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, next_hop, PacketType::Ota, packet_id, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
@@ -0,0 +1,38 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType, PathStatus};
+use crate::security::send_secure;
+
+/// 路径建立负载的最小长度：客户端ID(6) 服务类型(1) 最小带宽(2) 最大延迟(2) 可靠性(1)，其后可带服务ID(4)
+pub const PATH_ESTABLISH_MIN_LEN: usize = 12;
+
+/// 路径确认负载长度：客户端ID(6) 状态(1) 跳数(1) 服务ID(4)
+pub const PATH_CONFIRM_LEN: usize = 12;
+
+/// 路径的终点应答发给本节点的路径建立，不是路径建立或发送失败时返回false
+///
+/// 确认沿用请求的包ID，发起路径建立的中继据此停止重传，再把确认转给客户端。
+pub fn answer_path_establish<H: Hardware>(hardware: &mut H, request: &DataPacket) -> bool {
+    let node_id = hardware.get_node_id();
+    if request.header.packet_type != PacketType::PathEstablish as u8
+        || NodeId(request.header.destination) != node_id
+        || request.data.len() < PATH_ESTABLISH_MIN_LEN {
+        return false;
+    }
+    
+    let mut confirm = [0u8; PATH_CONFIRM_LEN];
+    confirm[0..6].copy_from_slice(&request.data[0..6]);
+    confirm[6] = PathStatus::Success as u8;
+    confirm[7] = 1;
+    if let Some(service_id) = request.data.get(12..16) {
+        confirm[8..12].copy_from_slice(service_id);
+    }
+    
+    let packet = DataPacket::with_type(
+        node_id,
+        NodeId(request.header.source),
+        PacketType::PathConfirm,
+        request.header.packet_id,
+        &confirm
+    ).with_flow(request.header.flow_id);
+    send_secure(hardware, &packet).is_ok()
+}
\ No newline at end of file
@@ -0,0 +1,280 @@
+use crate::protocol::command::{paged_query_bytes, parse_paged_query, CommandType, QueryPage, QueryParams};
+use crate::protocol::frame::MAX_VIDEO_FRAME_SIZE;
+use crate::protocol::wire::{get_u16be, get_u32be};
+
+/// 应用负载类型，数据负载的第0字节
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+#[repr(u8)]
+pub enum PayloadType {
+    /// 单个传感器读数
+    SensorReading = 0x01,
+    /// 命令
+    Command = 0x02,
+    /// 数据查询
+    Query = 0x03,
+    /// 批量传感器数据
+    Batch = 0x04,
+    /// 视频帧
+    VideoFrame = 0x05,
+    /// 视频质量档位通知
+    VideoTier = 0x06,
+}
+
+impl PayloadType {
+    /// 从类型字节解析
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(PayloadType::SensorReading),
+            0x02 => Some(PayloadType::Command),
+            0x03 => Some(PayloadType::Query),
+            0x04 => Some(PayloadType::Batch),
+            0x05 => Some(PayloadType::VideoFrame),
+            0x06 => Some(PayloadType::VideoTier),
+            _ => None,
+        }
+    }
+    
+    /// 负载的类型，负载为空或类型未知时返回None
+    pub fn of(payload: &[u8]) -> Option<Self> {
+        payload.first().and_then(|&value| Self::from_u8(value))
+    }
+}
+
+/// 按顺序写入大端字段，缓冲区不足后的写入都被忽略
+pub struct Writer<'a> {
+    buffer: &'a mut [u8],
+    offset: usize,
+    overflow: bool,
+}
+
+impl<'a> Writer<'a> {
+    /// 从缓冲区开头写入
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, offset: 0, overflow: false }
+    }
+    
+    /// 写入字节串
+    pub fn put_bytes(&mut self, bytes: &[u8]) {
+        match self.buffer.get_mut(self.offset..self.offset + bytes.len()) {
+            Some(target) if !self.overflow => {
+                target.copy_from_slice(bytes);
+                self.offset += bytes.len();
+            },
+            _ => self.overflow = true,
+        }
+    }
+    
+    /// 写入u8
+    pub fn put_u8(&mut self, value: u8) {
+        self.put_bytes(&[value]);
+    }
+    
+    /// 写入大端u16
+    pub fn put_u16(&mut self, value: u16) {
+        self.put_bytes(&value.to_be_bytes());
+    }
+    
+    /// 写入大端u32
+    pub fn put_u32(&mut self, value: u32) {
+        self.put_bytes(&value.to_be_bytes());
+    }
+    
+    /// 写入大端f32
+    pub fn put_f32(&mut self, value: f32) {
+        self.put_bytes(&value.to_be_bytes());
+    }
+    
+    /// 写入的长度，缓冲区不足时返回0
+    pub fn finish(self) -> usize {
+        if self.overflow { 0 } else { self.offset }
+    }
+}
+
+/// 按顺序读取大端字段，长度不足时返回None
+pub struct Reader<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// 从负载开头读取
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer }
+    }
+    
+    /// 读取定长字节串
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.buffer.len() < len {
+            return None;
+        }
+        let (head, rest) = self.buffer.split_at(len);
+        self.buffer = rest;
+        Some(head)
+    }
+    
+    /// 读取剩余的所有字节
+    pub fn rest(&mut self) -> &'a [u8] {
+        core::mem::take(&mut self.buffer)
+    }
+    
+    /// 读取u8
+    pub fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+    
+    /// 读取大端u16
+    pub fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(get_u16be)
+    }
+    
+    /// 读取大端u32
+    pub fn u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(get_u32be)
+    }
+    
+    /// 读取大端f32
+    pub fn f32(&mut self) -> Option<f32> {
+        self.u32().map(f32::from_bits)
+    }
+}
+
+/// 带类型标识的应用负载
+///
+/// 实现只负责类型字节之后的字段，类型字节由[`encode`]和[`decode`]统一处理。
+pub trait Payload<'a>: Sized {
+    /// 负载类型
+    const TYPE: PayloadType;
+    
+    /// 写入类型字节之后的字段
+    fn encode_body(&self, writer: &mut Writer<'_>);
+    
+    /// 读取类型字节之后的字段，格式错误时返回None
+    fn decode_body(reader: &mut Reader<'a>) -> Option<Self>;
+}
+
+/// 编码负载，缓冲区不足时返回0
+pub fn encode<'a, P: Payload<'a>>(payload: &P, buffer: &mut [u8]) -> usize {
+    let mut writer = Writer::new(buffer);
+    writer.put_u8(P::TYPE as u8);
+    payload.encode_body(&mut writer);
+    writer.finish()
+}
+
+/// 解码负载，类型不符或格式错误时返回None
+pub fn decode<'a, P: Payload<'a>>(buffer: &'a [u8]) -> Option<P> {
+    let mut reader = Reader::new(buffer);
+    if reader.u8()? != P::TYPE as u8 {
+        return None;
+    }
+    P::decode_body(&mut reader)
+}
+
+/// 单个传感器读数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorReading {
+    /// 服务（会话）ID
+    pub service_id: u32,
+    /// 温度（°C）
+    pub temperature: f32,
+    /// 湿度（%）
+    pub humidity: f32,
+    /// 气压（Pa）
+    pub pressure: f32,
+}
+
+impl<'a> Payload<'a> for SensorReading {
+    const TYPE: PayloadType = PayloadType::SensorReading;
+    
+    fn encode_body(&self, writer: &mut Writer<'_>) {
+        writer.put_u32(self.service_id);
+        writer.put_f32(self.temperature);
+        writer.put_f32(self.humidity);
+        writer.put_f32(self.pressure);
+    }
+    
+    fn decode_body(reader: &mut Reader<'a>) -> Option<Self> {
+        Some(Self {
+            service_id: reader.u32()?,
+            temperature: reader.f32()?,
+            humidity: reader.f32()?,
+            pressure: reader.f32()?,
+        })
+    }
+}
+
+/// 一个完整的视频帧，超过单帧长度时由数据包头部的分片字段拆分和重组
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoFrame<'a> {
+    /// 服务（会话）ID
+    pub service_id: u32,
+    /// 帧序号
+    pub frame_number: u16,
+    /// 帧数据
+    pub data: &'a [u8],
+}
+
+impl<'a> Payload<'a> for VideoFrame<'a> {
+    const TYPE: PayloadType = PayloadType::VideoFrame;
+    
+    fn encode_body(&self, writer: &mut Writer<'_>) {
+        writer.put_u32(self.service_id);
+        writer.put_u16(self.frame_number);
+        writer.put_bytes(self.data);
+    }
+    
+    fn decode_body(reader: &mut Reader<'a>) -> Option<Self> {
+        let service_id = reader.u32()?;
+        let frame_number = reader.u16()?;
+        let data = reader.rest();
+        if data.is_empty() || data.len() > MAX_VIDEO_FRAME_SIZE {
+            return None;
+        }
+        
+        Some(Self { service_id, frame_number, data })
+    }
+}
+
+/// 命令，参数格式由命令类型决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command<'a> {
+    /// 命令类型
+    pub command: CommandType,
+    /// 命令参数
+    pub parameters: &'a [u8],
+}
+
+impl<'a> Payload<'a> for Command<'a> {
+    const TYPE: PayloadType = PayloadType::Command;
+    
+    fn encode_body(&self, writer: &mut Writer<'_>) {
+        writer.put_u8(self.command as u8);
+        writer.put_bytes(self.parameters);
+    }
+    
+    fn decode_body(reader: &mut Reader<'a>) -> Option<Self> {
+        Some(Self {
+            command: CommandType::from_u8(reader.u8()?)?,
+            parameters: reader.rest(),
+        })
+    }
+}
+
+/// 数据查询，参数为空时查询全部记录；结果按分页参数分片应答
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Query {
+    pub params: QueryParams,
+    pub page: QueryPage,
+}
+
+impl<'a> Payload<'a> for Query {
+    const TYPE: PayloadType = PayloadType::Query;
+    
+    fn encode_body(&self, writer: &mut Writer<'_>) {
+        writer.put_bytes(&paged_query_bytes(&self.params, &self.page));
+    }
+    
+    fn decode_body(reader: &mut Reader<'a>) -> Option<Self> {
+        let (params, page) = parse_paged_query(reader.rest())?;
+        Some(Self { params, page })
+    }
+}
\ No newline at end of file
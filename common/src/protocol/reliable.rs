@@ -0,0 +1,491 @@
+use crate::hal::Hardware;
+use crate::metrics::{self, Counter, Gauge};
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::data::flow_id_of;
+use crate::protocol::wire::get_u32be;
+use crate::security::{send_secure, MAX_SECURE_PAYLOAD};
+use crate::warn;
+
+/// 同时等待确认的最大帧数
+pub const MAX_PENDING_FRAMES: usize = 8;
+
+/// 单帧最大负载长度，预留了安全保护的开销
+pub const MAX_FRAME_PAYLOAD: usize = MAX_SECURE_PAYLOAD;
+
+/// 确认包负载长度：会话ID(4)
+pub const ACK_PAYLOAD_LEN: usize = 4;
+
+/// 接收端记住的最近收到的帧数
+pub const MAX_RECEIVED_FRAMES: usize = 16;
+
+/// 接收端记住一帧的时长，覆盖发送端全部重传的时间跨度（毫秒）
+pub const RECEIVE_WINDOW_MS: u64 = 60_000;
+
+/// 分片确认包负载长度：分片索引(1)
+///
+/// 分片中只有第一个分片带会话ID，接收端按头部的包ID和流ID确认各个分片。
+pub const FRAGMENT_ACK_PAYLOAD_LEN: usize = 1;
+
+/// 重传参数
+#[derive(Debug, Clone, Copy)]
+pub struct RetryConfig {
+    /// 首次重传超时（毫秒）
+    pub initial_timeout_ms: u32,
+    /// 最大超时（毫秒）
+    pub max_timeout_ms: u32,
+    /// 每次重传后超时的倍数
+    pub backoff_factor: u8,
+    /// 最大重传次数
+    pub max_retries: u8,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            initial_timeout_ms: 500,
+            max_timeout_ms: 8000,
+            backoff_factor: 2,
+            max_retries: 4,
+        }
+    }
+}
+
+/// 发送结果事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryEvent {
+    /// 对端已确认
+    Delivered { session_id: u32, packet_id: u16 },
+    /// 超过最大重传次数仍未确认
+    Failed { session_id: u32, packet_id: u16, destination: NodeId },
+}
+
+/// 接收结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reception {
+    /// 第一次收到，交给上层处理
+    New,
+    /// 确认丢失后发送端重传的副本，已重新确认，上层不应再处理
+    Duplicate,
+}
+
+/// 可靠发送错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+pub enum ReliableError {
+    /// 等待确认的帧已满
+    WindowFull,
+    /// 负载超过单帧长度
+    PayloadTooLarge,
+    /// 无线电发送失败
+    SendFailed,
+}
+
+/// 等待确认的帧
+#[derive(Clone, Copy)]
+struct PendingFrame {
+    session_id: u32,
+    destination: NodeId,
+    packet_type: PacketType,
+    packet_id: u16,
+    /// 分片总数和索引，不是分片时为1和0
+    total_fragments: u8,
+    fragment_index: u8,
+    payload: [u8; MAX_FRAME_PAYLOAD],
+    len: usize,
+    /// 下次重传的时间
+    deadline: u64,
+    /// 当前超时
+    timeout_ms: u32,
+    /// 已重传次数
+    retries: u8,
+}
+
+/// 带确认和有限重传的发送端
+pub struct ReliableSender {
+    frames: [Option<PendingFrame>; MAX_PENDING_FRAMES],
+    next_packet_id: u16,
+    config: RetryConfig,
+}
+
+impl ReliableSender {
+    /// 创建新的可靠发送端
+    pub fn new(config: RetryConfig) -> Self {
+        Self {
+            frames: [None; MAX_PENDING_FRAMES],
+            next_packet_id: 1,
+            config,
+        }
+    }
+    
+    /// 分配下一个包ID（跳过0，0保留给不需要确认的包），分片发送的负载各分片共用一个
+    pub fn allocate_packet_id(&mut self) -> u16 {
+        let id = self.next_packet_id;
+        self.next_packet_id = self.next_packet_id.wrapping_add(1);
+        if self.next_packet_id == 0 {
+            self.next_packet_id = 1;
+        }
+        id
+    }
+    
+    /// 发送一帧数据并等待确认，返回分配的包ID
+    pub fn send<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        session_id: u32,
+        destination: NodeId,
+        payload: &[u8],
+        current_time: u64
+    ) -> Result<u16, ReliableError> {
+        self.send_typed(hardware, session_id, destination, PacketType::Data, payload, current_time)
+    }
+    
+    /// 发送指定类型的一帧并等待确认，返回分配的包ID
+    ///
+    /// 对端用确认包或沿用包ID的应答（服务响应、路径确认）确认，收到应答时调用`complete`。
+    pub fn send_typed<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        session_id: u32,
+        destination: NodeId,
+        packet_type: PacketType,
+        payload: &[u8],
+        current_time: u64
+    ) -> Result<u16, ReliableError> {
+        if payload.len() > MAX_FRAME_PAYLOAD {
+            return Err(ReliableError::PayloadTooLarge);
+        }
+        
+        let slot = self.frames.iter().position(|frame| frame.is_none())
+            .ok_or(ReliableError::WindowFull)?;
+        
+        let packet_id = self.allocate_packet_id();
+        let mut frame = PendingFrame {
+            session_id,
+            destination,
+            packet_type,
+            packet_id,
+            total_fragments: 1,
+            fragment_index: 0,
+            payload: [0; MAX_FRAME_PAYLOAD],
+            len: payload.len(),
+            deadline: current_time + self.config.initial_timeout_ms as u64,
+            timeout_ms: self.config.initial_timeout_ms,
+            retries: 0,
+        };
+        frame.payload[..payload.len()].copy_from_slice(payload);
+        
+        self.start(hardware, slot, frame)?;
+        Ok(packet_id)
+    }
+    
+    /// 发送[`Fragmenter`](crate::protocol::data::Fragmenter)拆出的一个分片并等待确认
+    ///
+    /// 分片沿用拆分时的包ID、类型和分片字段，对端用分片确认包逐个确认。
+    pub fn send_fragment<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        session_id: u32,
+        fragment: &DataPacket,
+        current_time: u64
+    ) -> Result<(), ReliableError> {
+        if fragment.data.len() > MAX_FRAME_PAYLOAD {
+            return Err(ReliableError::PayloadTooLarge);
+        }
+        let packet_type = PacketType::from_u8(fragment.header.packet_type).ok_or(ReliableError::SendFailed)?;
+        
+        let slot = self.frames.iter().position(|frame| frame.is_none())
+            .ok_or(ReliableError::WindowFull)?;
+        
+        let mut frame = PendingFrame {
+            session_id,
+            destination: NodeId(fragment.header.destination),
+            packet_type,
+            packet_id: fragment.header.packet_id,
+            total_fragments: fragment.header.total_fragments,
+            fragment_index: fragment.header.fragment_index,
+            payload: [0; MAX_FRAME_PAYLOAD],
+            len: fragment.data.len(),
+            deadline: current_time + self.config.initial_timeout_ms as u64,
+            timeout_ms: self.config.initial_timeout_ms,
+            retries: 0,
+        };
+        frame.payload[..fragment.data.len()].copy_from_slice(fragment.data);
+        
+        self.start(hardware, slot, frame)
+    }
+    
+    /// 首次发出一帧并放入发送窗口
+    fn start<H: Hardware>(&mut self, hardware: &mut H, slot: usize, frame: PendingFrame) -> Result<(), ReliableError> {
+        transmit(hardware, &frame)?;
+        self.frames[slot] = Some(frame);
+        metrics::increment(Counter::ReliableSent);
+        metrics::set(Gauge::PendingFrames, self.pending() as u32);
+        Ok(())
+    }
+    
+    /// 处理收到的确认包和分片确认包
+    pub fn handle_ack(&mut self, packet: &DataPacket) -> Option<DeliveryEvent> {
+        if packet.header.packet_type != PacketType::Ack as u8 {
+            return None;
+        }
+        
+        let packet_id = packet.header.packet_id;
+        match packet.data.len() {
+            FRAGMENT_ACK_PAYLOAD_LEN => self.remove(|frame| {
+                frame.total_fragments > 1
+                    && frame.packet_id == packet_id
+                    && frame.fragment_index == packet.data[0]
+                    && flow_id_of(frame.session_id) == packet.header.flow_id
+            }),
+            len if len >= ACK_PAYLOAD_LEN => self.complete(get_u32be(&packet.data[0..4]), packet_id),
+            _ => None,
+        }
+    }
+    
+    /// 对端已应答指定的帧，不再重传
+    pub fn complete(&mut self, session_id: u32, packet_id: u16) -> Option<DeliveryEvent> {
+        self.remove(|frame| frame.total_fragments <= 1 && frame.packet_id == packet_id && frame.session_id == session_id)
+    }
+    
+    /// 移除第一个满足条件的帧，报告已送达
+    fn remove<F: Fn(&PendingFrame) -> bool>(&mut self, acknowledged: F) -> Option<DeliveryEvent> {
+        let entry = self.frames.iter_mut().find(|entry| entry.as_ref().is_some_and(&acknowledged))?;
+        let frame = entry.take()?;
+        metrics::increment(Counter::Delivered);
+        metrics::set(Gauge::PendingFrames, self.pending() as u32);
+        Some(DeliveryEvent::Delivered { session_id: frame.session_id, packet_id: frame.packet_id })
+    }
+    
+    /// 重传超时的帧，超过最大重传次数的帧通过回调报告失败
+    pub fn poll<H: Hardware, F: FnMut(DeliveryEvent)>(
+        &mut self,
+        hardware: &mut H,
+        current_time: u64,
+        on_event: F
+    ) {
+        self.retransmit(hardware, None, current_time, on_event);
+    }
+    
+    /// 只重传指定会话中超时的帧，其他会话的帧留给主循环的`poll`
+    pub fn poll_session<H: Hardware, F: FnMut(DeliveryEvent)>(
+        &mut self,
+        hardware: &mut H,
+        session_id: u32,
+        current_time: u64,
+        on_event: F
+    ) {
+        self.retransmit(hardware, Some(session_id), current_time, on_event);
+    }
+    
+    fn retransmit<H: Hardware, F: FnMut(DeliveryEvent)>(
+        &mut self,
+        hardware: &mut H,
+        session_id: Option<u32>,
+        current_time: u64,
+        mut on_event: F
+    ) {
+        for entry in self.frames.iter_mut() {
+            let expired = match entry {
+                Some(frame) => current_time >= frame.deadline && session_id.map_or(true, |id| id == frame.session_id),
+                None => false,
+            };
+            if !expired {
+                continue;
+            }
+            
+            let frame = entry.as_mut().unwrap();
+            if frame.retries >= self.config.max_retries {
+                metrics::increment(Counter::DeliveryFailures);
+                on_event(DeliveryEvent::Failed {
+                    session_id: frame.session_id,
+                    packet_id: frame.packet_id,
+                    destination: frame.destination,
+                });
+                *entry = None;
+                continue;
+            }
+            
+            // 指数退避
+            frame.retries += 1;
+            frame.timeout_ms = frame.timeout_ms
+                .saturating_mul(self.config.backoff_factor as u32)
+                .min(self.config.max_timeout_ms);
+            frame.deadline = current_time + frame.timeout_ms as u64;
+            
+            // 发送失败时等待下一次超时再试
+            metrics::increment(Counter::Retransmissions);
+            let _ = transmit(hardware, frame);
+        }
+        
+        metrics::set(Gauge::PendingFrames, self.pending() as u32);
+    }
+    
+    /// 指定会话中等待确认的帧数
+    pub fn pending_for_session(&self, session_id: u32) -> usize {
+        self.frames.iter()
+            .flatten()
+            .filter(|frame| frame.session_id == session_id)
+            .count()
+    }
+    
+    /// 所有等待确认的帧数
+    pub fn pending(&self) -> usize {
+        self.frames.iter().flatten().count()
+    }
+    
+    /// 放弃指定会话的所有未确认帧
+    pub fn cancel_session(&mut self, session_id: u32) {
+        for entry in self.frames.iter_mut() {
+            if let Some(frame) = entry {
+                if frame.session_id == session_id {
+                    *entry = None;
+                }
+            }
+        }
+        
+        metrics::set(Gauge::PendingFrames, self.pending() as u32);
+    }
+}
+
+/// 接收端记住的一帧
+#[derive(Clone, Copy)]
+struct ReceivedFrame {
+    source: NodeId,
+    session_id: u32,
+    packet_id: u16,
+    /// 收到的时间，用于过期
+    received_at: u64,
+}
+
+/// 与[`ReliableSender`]配对的接收端：确认收到的每个副本，并识别确认丢失后重传的重复帧
+///
+/// 重复帧同样要确认，否则发送端会一直重传到失败。按来源、会话ID和包ID识别重复，包ID为0的帧不需要确认也不会重传。
+pub struct ReliableReceiver {
+    recent: [Option<ReceivedFrame>; MAX_RECEIVED_FRAMES],
+    /// 记录已满时下一个被覆盖的位置
+    next: usize,
+}
+
+impl ReliableReceiver {
+    /// 创建新的接收端
+    pub fn new() -> Self {
+        Self {
+            recent: [None; MAX_RECEIVED_FRAMES],
+            next: 0,
+        }
+    }
+    
+    /// 确认收到的帧，并判断它是否是重复帧
+    pub fn receive<H: Hardware>(&mut self, hardware: &mut H, packet: &DataPacket, session_id: u32, now: u64) -> Reception {
+        let packet_id = packet.header.packet_id;
+        if packet_id == 0 {
+            return Reception::New;
+        }
+        
+        let source = NodeId(packet.header.source);
+        if let Err(e) = send_ack(hardware, source, packet_id, session_id) {
+            warn!("确认 {} 的包 {} 失败: {:?}", source, packet_id, e);
+        }
+        
+        for entry in self.recent.iter_mut() {
+            if matches!(entry, Some(frame) if now.saturating_sub(frame.received_at) > RECEIVE_WINDOW_MS) {
+                *entry = None;
+            }
+        }
+        let duplicate = self.recent.iter().flatten().any(|frame| {
+            frame.source == source && frame.session_id == session_id && frame.packet_id == packet_id
+        });
+        if duplicate {
+            return Reception::Duplicate;
+        }
+        
+        let index = match self.recent.iter().position(|entry| entry.is_none()) {
+            Some(index) => index,
+            None => {
+                let index = self.next;
+                self.next = (self.next + 1) % MAX_RECEIVED_FRAMES;
+                index
+            },
+        };
+        self.recent[index] = Some(ReceivedFrame { source, session_id, packet_id, received_at: now });
+        Reception::New
+    }
+}
+
+impl Default for ReliableReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 发送一帧
+fn transmit<H: Hardware>(hardware: &mut H, frame: &PendingFrame) -> Result<(), ReliableError> {
+    let node_id = hardware.get_node_id();
+    let mut packet = DataPacket::with_type(
+        node_id,
+        frame.destination,
+        frame.packet_type,
+        frame.packet_id,
+        &frame.payload[..frame.len]
+    );
+    packet.header.total_fragments = frame.total_fragments;
+    packet.header.fragment_index = frame.fragment_index;
+    let packet = packet.with_flow(flow_id_of(frame.session_id));
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
+
+/// 向发送方回复确认
+pub fn send_ack<H: Hardware>(
+    hardware: &mut H,
+    destination: NodeId,
+    packet_id: u16,
+    session_id: u32
+) -> Result<(), ReliableError> {
+    let ack_data = session_id.to_be_bytes();
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(
+        node_id,
+        destination,
+        PacketType::Ack,
+        packet_id,
+        &ack_data
+    ).with_flow(flow_id_of(session_id));
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
+
+/// 向发送方确认会话数据的一个分片，沿用分片的包ID和流ID
+pub fn send_fragment_ack<H: Hardware>(hardware: &mut H, fragment: &DataPacket) -> Result<(), ReliableError> {
+    let ack_data = [fragment.header.fragment_index];
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(
+        node_id,
+        NodeId(fragment.header.source),
+        PacketType::Ack,
+        fragment.header.packet_id,
+        &ack_data
+    ).with_flow(fragment.header.flow_id);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
+
+/// 通知发送方某会话的数据因转发拥塞被丢弃，负载与确认包相同
+pub fn send_congestion_notice<H: Hardware>(
+    hardware: &mut H,
+    destination: NodeId,
+    packet_id: u16,
+    session_id: u32
+) -> Result<(), ReliableError> {
+    let notice_data = session_id.to_be_bytes();
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(
+        node_id,
+        destination,
+        PacketType::Congestion,
+        packet_id,
+        &notice_data
+    ).with_flow(flow_id_of(session_id));
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
@@ -0,0 +1,138 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u16be, put_u16be};
+use crate::security::{send_secure, MAX_SECURE_PAYLOAD};
+
+/// 不可达的跳数，通告中用于毒化失效的路由
+pub const INFINITE_HOPS: u8 = 16;
+
+/// ETX的定点单位，该值表示每帧期望发送一次，即无丢包的一跳
+pub const ETX_SCALE: u16 = 10;
+
+/// 通告头部长度：条目数(1)
+pub const ROUTE_ADVERT_HEADER_LEN: usize = 1;
+
+/// 每条路由的长度：目的地(6) 下一跳(6) 跳数(1) 信号强度(1) ETX(2)
+pub const ROUTE_ADVERT_ENTRY_LEN: usize = 16;
+
+/// 单个通告最多携带的路由数，受单帧负载长度限制
+pub const MAX_ADVERT_ROUTES: usize = (MAX_SECURE_PAYLOAD - ROUTE_ADVERT_HEADER_LEN) / ROUTE_ADVERT_ENTRY_LEN;
+
+/// 通告中的一条路由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AdvertisedRoute {
+    /// 目的地
+    pub destination: NodeId,
+    /// 通告方的下一跳，接收方据此做水平分割：经由自己的路由不学习
+    pub next_hop: NodeId,
+    /// 通告方到目的地的跳数，[`INFINITE_HOPS`]表示路由已失效
+    pub hops: u8,
+    /// 路径上最弱一跳的信号强度（dBm）
+    pub rssi: i8,
+    /// 通告方到目的地路径上各跳ETX之和，以[`ETX_SCALE`]为单位
+    pub etx: u16,
+}
+
+impl AdvertisedRoute {
+    /// 是否是被毒化的失效路由
+    pub fn is_poisoned(&self) -> bool {
+        self.hops >= INFINITE_HOPS
+    }
+}
+
+/// 距离向量路由通告，转发节点定期向邻居广播自己的路由表，路由失效时立即触发
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteAdvert {
+    entries: [AdvertisedRoute; MAX_ADVERT_ROUTES],
+    len: usize,
+}
+
+impl RouteAdvert {
+    /// 创建空通告
+    pub fn new() -> Self {
+        Self {
+            entries: [AdvertisedRoute::default(); MAX_ADVERT_ROUTES],
+            len: 0,
+        }
+    }
+    
+    /// 添加一条路由，已满时返回false
+    pub fn push(&mut self, route: AdvertisedRoute) -> bool {
+        if self.len >= MAX_ADVERT_ROUTES {
+            return false;
+        }
+        self.entries[self.len] = route;
+        self.len += 1;
+        true
+    }
+    
+    /// 通告中的路由
+    pub fn entries(&self) -> &[AdvertisedRoute] {
+        &self.entries[..self.len]
+    }
+    
+    /// 是否没有路由
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    
+    /// 序列化，缓冲区不足时返回0
+    ///
+    /// 格式：条目数(1) [目的地(6) 下一跳(6) 跳数(1) 信号强度(1) ETX(2)]*
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let len = ROUTE_ADVERT_HEADER_LEN + self.len * ROUTE_ADVERT_ENTRY_LEN;
+        if buffer.len() < len {
+            return 0;
+        }
+        
+        buffer[0] = self.len as u8;
+        for (i, route) in self.entries().iter().enumerate() {
+            let offset = ROUTE_ADVERT_HEADER_LEN + i * ROUTE_ADVERT_ENTRY_LEN;
+            buffer[offset..offset + 6].copy_from_slice(&route.destination.0);
+            buffer[offset + 6..offset + 12].copy_from_slice(&route.next_hop.0);
+            buffer[offset + 12] = route.hops;
+            buffer[offset + 13] = route.rssi as u8;
+            put_u16be(&mut buffer[offset + 14..offset + 16], route.etx);
+        }
+        len
+    }
+    
+    /// 反序列化，截断或条目数超出上限时返回None
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        let count = *buffer.first()? as usize;
+        if count > MAX_ADVERT_ROUTES || buffer.len() < ROUTE_ADVERT_HEADER_LEN + count * ROUTE_ADVERT_ENTRY_LEN {
+            return None;
+        }
+        
+        let read_node = |offset: usize| {
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&buffer[offset..offset + 6]);
+            NodeId(id)
+        };
+        
+        let mut advert = Self::new();
+        for i in 0..count {
+            let offset = ROUTE_ADVERT_HEADER_LEN + i * ROUTE_ADVERT_ENTRY_LEN;
+            advert.push(AdvertisedRoute {
+                destination: read_node(offset),
+                next_hop: read_node(offset + 6),
+                hops: buffer[offset + 12],
+                rssi: buffer[offset + 13] as i8,
+                etx: get_u16be(&buffer[offset + 14..offset + 16]),
+            });
+        }
+        Some(advert)
+    }
+}
+
+/// 向所有邻居广播路由通告
+pub fn send_route_advert<H: Hardware>(hardware: &mut H, advert: &RouteAdvert) -> Result<(), ReliableError> {
+    let mut data = [0u8; MAX_SECURE_PAYLOAD];
+    let len = advert.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, NodeId::BROADCAST, PacketType::RouteAdvert, 0, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
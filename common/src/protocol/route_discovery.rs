@@ -0,0 +1,133 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::flood::Flood;
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::route_advert::{AdvertisedRoute, ROUTE_ADVERT_ENTRY_LEN};
+use crate::protocol::wire::{get_u16be, put_u16be};
+use crate::security::send_secure;
+
+/// 路由请求负载长度：目标(6) 发起时的跳数(1)
+pub const ROUTE_REQUEST_LEN: usize = 7;
+
+/// 路由应答负载长度：发起方(6) 应答方到目标的路由
+pub const ROUTE_REPLY_LEN: usize = 6 + ROUTE_ADVERT_ENTRY_LEN;
+
+/// 按需路由发现的请求和应答
+///
+/// 请求随泛洪到达各转发节点，发起方和序号由泛洪包携带，收到的节点据此建立到发起方的反向路由；
+/// 应答沿反向路由逐跳单播回发起方，每一跳据此建立到目标的正向路由。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscoveryMessage {
+    /// 查找到达目标的路由，发起时的跳数用于计算请求经过的跳数
+    Request { target: NodeId, initial_ttl: u8 },
+    /// 发送方到目标的路由，发回给发起方
+    Reply { origin: NodeId, route: AdvertisedRoute },
+}
+
+impl DiscoveryMessage {
+    /// 对应的包类型
+    pub fn packet_type(&self) -> PacketType {
+        match self {
+            DiscoveryMessage::Request { .. } => PacketType::RouteRequest,
+            DiscoveryMessage::Reply { .. } => PacketType::RouteReply,
+        }
+    }
+    
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        match self {
+            DiscoveryMessage::Request { target, initial_ttl } => {
+                if buffer.len() < ROUTE_REQUEST_LEN {
+                    return 0;
+                }
+                buffer[0..6].copy_from_slice(&target.0);
+                buffer[6] = *initial_ttl;
+                ROUTE_REQUEST_LEN
+            },
+            DiscoveryMessage::Reply { origin, route } => {
+                if buffer.len() < ROUTE_REPLY_LEN {
+                    return 0;
+                }
+                buffer[0..6].copy_from_slice(&origin.0);
+                buffer[6..12].copy_from_slice(&route.destination.0);
+                buffer[12..18].copy_from_slice(&route.next_hop.0);
+                buffer[18] = route.hops;
+                buffer[19] = route.rssi as u8;
+                put_u16be(&mut buffer[20..22], route.etx);
+                ROUTE_REPLY_LEN
+            },
+        }
+    }
+    
+    /// 按包类型反序列化
+    pub fn deserialize(packet_type: u8, buffer: &[u8]) -> Option<Self> {
+        let read_node = |offset: usize| {
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&buffer[offset..offset + 6]);
+            NodeId(id)
+        };
+        
+        match PacketType::from_u8(packet_type)? {
+            PacketType::RouteRequest if buffer.len() >= ROUTE_REQUEST_LEN => Some(DiscoveryMessage::Request {
+                target: read_node(0),
+                initial_ttl: buffer[6],
+            }),
+            PacketType::RouteReply if buffer.len() >= ROUTE_REPLY_LEN => Some(DiscoveryMessage::Reply {
+                origin: read_node(0),
+                route: AdvertisedRoute {
+                    destination: read_node(6),
+                    next_hop: read_node(12),
+                    hops: buffer[18],
+                    rssi: buffer[19] as i8,
+                    etx: get_u16be(&buffer[20..22]),
+                },
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// 向反向路由的下一跳发送路由应答
+pub fn send_route_reply<H: Hardware>(
+    hardware: &mut H,
+    next_hop: NodeId,
+    packet_id: u16,
+    message: &DiscoveryMessage
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; ROUTE_REPLY_LEN];
+    let len = message.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, next_hop, message.packet_type(), packet_id, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
+
+/// 本节点作为目标时应答的路由：到自己0跳，无丢包
+pub fn local_route(node_id: NodeId) -> AdvertisedRoute {
+    AdvertisedRoute { destination: node_id, next_hop: node_id, hops: 0, rssi: i8::MAX, etx: 0 }
+}
+
+/// 服务器和客户端处理泛洪到达的路由请求：查找的是本节点时向转发请求的上一跳应答
+///
+/// 终端节点不转发泛洪，从不同转发节点听到的副本各应答一次，发起方据此得到多条候选路由。
+/// 不是查找本节点的路由请求或应答发送失败时返回false。
+pub fn answer_route_request<H: Hardware>(hardware: &mut H, packet: &DataPacket) -> bool {
+    if packet.header.packet_type != PacketType::Flood as u8 {
+        return false;
+    }
+    let flood = match Flood::deserialize(packet.data) {
+        Some(flood) => flood,
+        None => return false,
+    };
+    
+    let node_id = hardware.get_node_id();
+    match DiscoveryMessage::deserialize(flood.packet_type, flood.payload) {
+        Some(DiscoveryMessage::Request { target, .. }) if target == node_id => {},
+        _ => return false,
+    }
+    
+    let reply = DiscoveryMessage::Reply { origin: flood.origin, route: local_route(node_id) };
+    let previous_hop = NodeId(packet.header.source);
+    send_route_reply(hardware, previous_hop, flood.sequence, &reply).is_ok()
+}
\ No newline at end of file
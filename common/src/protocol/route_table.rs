@@ -0,0 +1,207 @@
+use core::fmt;
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::route_advert::{ETX_SCALE, INFINITE_HOPS};
+use crate::protocol::wire::{get_u16be, put_u16be};
+use crate::security::{send_secure, MAX_SECURE_PAYLOAD};
+
+/// 路由表查询负载长度：发起方(6) 目标(6) 最多条目数(1)
+pub const ROUTE_TABLE_REQUEST_LEN: usize = 13;
+
+/// 路由表应答头部长度：发起方(6) 目标(6) 路由总数(1) 条目数(1)
+pub const ROUTE_TABLE_RESPONSE_HEADER_LEN: usize = 14;
+
+/// 每条路由的长度：目的地(6) 下一跳(6) 度量(1) 跳数(1) ETX(2) 存在时间(2)
+pub const ROUTE_TABLE_ENTRY_LEN: usize = 18;
+
+/// 单个应答最多携带的路由数，受单帧负载长度限制
+pub const MAX_ROUTE_TABLE_ENTRIES: usize = (MAX_SECURE_PAYLOAD - ROUTE_TABLE_RESPONSE_HEADER_LEN) / ROUTE_TABLE_ENTRY_LEN;
+
+/// 路由表中的一条路由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RouteTableEntry {
+    /// 目的地
+    pub destination: NodeId,
+    /// 下一跳
+    pub next_hop: NodeId,
+    /// 路径上最弱一跳的信号强度（dBm）
+    pub metric: i8,
+    /// 跳数，[`INFINITE_HOPS`]表示路由已失效
+    pub hops: u8,
+    /// 路径上各跳ETX之和，以[`ETX_SCALE`]为单位
+    pub etx: u16,
+    /// 距上次刷新的时间（秒）
+    pub age_secs: u16,
+}
+
+/// 节点路由表的快照，路由多于单帧容量时只携带一部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteTableDump {
+    entries: [RouteTableEntry; MAX_ROUTE_TABLE_ENTRIES],
+    len: usize,
+    /// 路由表中的路由总数
+    pub total: u8,
+}
+
+impl RouteTableDump {
+    /// 创建空快照
+    pub fn new(total: u8) -> Self {
+        Self {
+            entries: [RouteTableEntry::default(); MAX_ROUTE_TABLE_ENTRIES],
+            len: 0,
+            total,
+        }
+    }
+    
+    /// 添加一条路由，已满时返回false
+    pub fn push(&mut self, entry: RouteTableEntry) -> bool {
+        if self.len >= MAX_ROUTE_TABLE_ENTRIES {
+            return false;
+        }
+        self.entries[self.len] = entry;
+        self.len += 1;
+        true
+    }
+    
+    /// 快照中的路由
+    pub fn entries(&self) -> &[RouteTableEntry] {
+        &self.entries[..self.len]
+    }
+}
+
+impl fmt::Display for RouteTableDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  共 {} 条路由，显示 {} 条", self.total, self.len)?;
+        for entry in self.entries() {
+            if entry.hops >= INFINITE_HOPS {
+                writeln!(f, "  {} 经 {}  不可达  {} 秒前", entry.destination, entry.next_hop, entry.age_secs)?;
+            } else {
+                writeln!(f, "  {} 经 {}  {} 跳  ETX {:.1}  {} dBm  {} 秒前", entry.destination, entry.next_hop, entry.hops,
+                    entry.etx as f32 / ETX_SCALE as f32, entry.metric, entry.age_secs)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 远程读取节点路由表的查询和应答
+///
+/// 与计数器查询相同，端到端的发起方和目标记录在负载中，查询发往目标，应答沿反向发回发起方。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteTableMessage {
+    /// 发起方查询目标节点的路由表，最多返回指定条数
+    Request { origin: NodeId, target: NodeId, max_entries: u8 },
+    /// 目标节点回复的路由表快照
+    Response { origin: NodeId, target: NodeId, dump: RouteTableDump },
+}
+
+impl RouteTableMessage {
+    /// 发起查询的节点
+    pub fn origin(&self) -> NodeId {
+        match self {
+            RouteTableMessage::Request { origin, .. } | RouteTableMessage::Response { origin, .. } => *origin,
+        }
+    }
+    
+    /// 被查询的节点
+    pub fn target(&self) -> NodeId {
+        match self {
+            RouteTableMessage::Request { target, .. } | RouteTableMessage::Response { target, .. } => *target,
+        }
+    }
+    
+    /// 对应的包类型
+    pub fn packet_type(&self) -> PacketType {
+        match self {
+            RouteTableMessage::Request { .. } => PacketType::RouteTableRequest,
+            RouteTableMessage::Response { .. } => PacketType::RouteTableResponse,
+        }
+    }
+    
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let len = match self {
+            RouteTableMessage::Request { .. } => ROUTE_TABLE_REQUEST_LEN,
+            RouteTableMessage::Response { dump, .. } => ROUTE_TABLE_RESPONSE_HEADER_LEN + dump.len * ROUTE_TABLE_ENTRY_LEN,
+        };
+        if buffer.len() < len {
+            return 0;
+        }
+        
+        buffer[0..6].copy_from_slice(&self.origin().0);
+        buffer[6..12].copy_from_slice(&self.target().0);
+        match self {
+            RouteTableMessage::Request { max_entries, .. } => buffer[12] = *max_entries,
+            RouteTableMessage::Response { dump, .. } => {
+                buffer[12] = dump.total;
+                buffer[13] = dump.len as u8;
+                for (i, entry) in dump.entries().iter().enumerate() {
+                    let offset = ROUTE_TABLE_RESPONSE_HEADER_LEN + i * ROUTE_TABLE_ENTRY_LEN;
+                    buffer[offset..offset + 6].copy_from_slice(&entry.destination.0);
+                    buffer[offset + 6..offset + 12].copy_from_slice(&entry.next_hop.0);
+                    buffer[offset + 12] = entry.metric as u8;
+                    buffer[offset + 13] = entry.hops;
+                    put_u16be(&mut buffer[offset + 14..offset + 16], entry.etx);
+                    put_u16be(&mut buffer[offset + 16..offset + 18], entry.age_secs);
+                }
+            },
+        }
+        len
+    }
+    
+    /// 按包类型反序列化，截断或条目数超出上限时返回None
+    pub fn deserialize(packet_type: u8, buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < ROUTE_TABLE_REQUEST_LEN {
+            return None;
+        }
+        
+        let read_node = |offset: usize| {
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&buffer[offset..offset + 6]);
+            NodeId(id)
+        };
+        let (origin, target) = (read_node(0), read_node(6));
+        
+        match PacketType::from_u8(packet_type)? {
+            PacketType::RouteTableRequest => Some(RouteTableMessage::Request { origin, target, max_entries: buffer[12] }),
+            PacketType::RouteTableResponse => {
+                let count = *buffer.get(13)? as usize;
+                if count > MAX_ROUTE_TABLE_ENTRIES || buffer.len() < ROUTE_TABLE_RESPONSE_HEADER_LEN + count * ROUTE_TABLE_ENTRY_LEN {
+                    return None;
+                }
+                
+                let mut dump = RouteTableDump::new(buffer[12]);
+                for i in 0..count {
+                    let offset = ROUTE_TABLE_RESPONSE_HEADER_LEN + i * ROUTE_TABLE_ENTRY_LEN;
+                    dump.push(RouteTableEntry {
+                        destination: read_node(offset),
+                        next_hop: read_node(offset + 6),
+                        metric: buffer[offset + 12] as i8,
+                        hops: buffer[offset + 13],
+                        etx: get_u16be(&buffer[offset + 14..offset + 16]),
+                        age_secs: get_u16be(&buffer[offset + 16..offset + 18]),
+                    });
+                }
+                Some(RouteTableMessage::Response { origin, target, dump })
+            },
+            _ => None,
+        }
+    }
+}
+
+/// 向下一跳发送路由表查询或应答
+pub fn send_route_table<H: Hardware>(
+    hardware: &mut H,
+    next_hop: NodeId,
+    packet_id: u16,
+    message: &RouteTableMessage
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; MAX_SECURE_PAYLOAD];
+    let len = message.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, next_hop, message.packet_type(), packet_id, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
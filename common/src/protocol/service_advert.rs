@@ -0,0 +1,143 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType, ServiceType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u32be, put_u32be};
+use crate::security::{send_secure, MAX_SECURE_PAYLOAD};
+
+/// 服务通告最多传播的跳数，超过后邻居不再学习
+pub const MAX_SERVICE_HOPS: u8 = 8;
+
+/// 通告头部长度：条目数(1)
+pub const SERVICE_ADVERT_HEADER_LEN: usize = 1;
+
+/// 每个服务的长度：服务器(6) 类型(1) 负载(1) 跳数(1) 电池电量(1) 信号强度(1) 源时间(4)
+pub const SERVICE_ADVERT_ENTRY_LEN: usize = 15;
+
+/// 单个通告最多携带的服务数，受单帧负载长度限制
+pub const MAX_ADVERT_SERVICES: usize = (MAX_SECURE_PAYLOAD - SERVICE_ADVERT_HEADER_LEN) / SERVICE_ADVERT_ENTRY_LEN;
+
+/// 通告中的一个服务
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdvertisedService {
+    /// 提供服务的服务器
+    pub server: NodeId,
+    pub service_type: ServiceType,
+    /// 服务器负载（0-100%）
+    pub load: u8,
+    /// 通告方到服务器的跳数
+    pub hops: u8,
+    /// 服务器电池电量（0-100%）
+    pub battery_level: u8,
+    /// 最先听到服务器信标的转发节点测得的信号强度（dBm）
+    pub rssi: i8,
+    /// 最先听到服务器信标时的网络时间（秒），转发时保持不变，用于判断新旧和抑制环路
+    pub origin_time: u32,
+}
+
+impl AdvertisedService {
+    const EMPTY: Self = Self {
+        server: NodeId([0; 6]),
+        service_type: ServiceType::Storage,
+        load: 0,
+        hops: 0,
+        battery_level: 0,
+        rssi: 0,
+        origin_time: 0,
+    };
+}
+
+/// 服务目录通告，转发节点定期向邻居广播自己目录中的服务
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServiceAdvertisement {
+    entries: [AdvertisedService; MAX_ADVERT_SERVICES],
+    len: usize,
+}
+
+impl ServiceAdvertisement {
+    /// 创建空通告
+    pub fn new() -> Self {
+        Self {
+            entries: [AdvertisedService::EMPTY; MAX_ADVERT_SERVICES],
+            len: 0,
+        }
+    }
+    
+    /// 添加一个服务，已满时返回false
+    pub fn push(&mut self, service: AdvertisedService) -> bool {
+        if self.len >= MAX_ADVERT_SERVICES {
+            return false;
+        }
+        self.entries[self.len] = service;
+        self.len += 1;
+        true
+    }
+    
+    /// 通告中的服务
+    pub fn entries(&self) -> &[AdvertisedService] {
+        &self.entries[..self.len]
+    }
+    
+    /// 是否没有服务
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    
+    /// 序列化，缓冲区不足时返回0
+    ///
+    /// 格式：条目数(1) [服务器(6) 类型(1) 负载(1) 跳数(1) 电池电量(1) 信号强度(1) 源时间(4)]*
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let len = SERVICE_ADVERT_HEADER_LEN + self.len * SERVICE_ADVERT_ENTRY_LEN;
+        if buffer.len() < len {
+            return 0;
+        }
+        
+        buffer[0] = self.len as u8;
+        for (i, service) in self.entries().iter().enumerate() {
+            let offset = SERVICE_ADVERT_HEADER_LEN + i * SERVICE_ADVERT_ENTRY_LEN;
+            buffer[offset..offset + 6].copy_from_slice(&service.server.0);
+            buffer[offset + 6] = service.service_type as u8;
+            buffer[offset + 7] = service.load;
+            buffer[offset + 8] = service.hops;
+            buffer[offset + 9] = service.battery_level;
+            buffer[offset + 10] = service.rssi as u8;
+            put_u32be(&mut buffer[offset + 11..offset + 15], service.origin_time);
+        }
+        len
+    }
+    
+    /// 反序列化，截断、条目数超出上限或服务类型未知时返回None
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        let count = *buffer.first()? as usize;
+        if count > MAX_ADVERT_SERVICES || buffer.len() < SERVICE_ADVERT_HEADER_LEN + count * SERVICE_ADVERT_ENTRY_LEN {
+            return None;
+        }
+        
+        let mut advert = Self::new();
+        for i in 0..count {
+            let offset = SERVICE_ADVERT_HEADER_LEN + i * SERVICE_ADVERT_ENTRY_LEN;
+            let mut server = [0u8; 6];
+            server.copy_from_slice(&buffer[offset..offset + 6]);
+            advert.push(AdvertisedService {
+                server: NodeId(server),
+                service_type: ServiceType::from_u8(buffer[offset + 6])?,
+                load: buffer[offset + 7],
+                hops: buffer[offset + 8],
+                battery_level: buffer[offset + 9],
+                rssi: buffer[offset + 10] as i8,
+                origin_time: get_u32be(&buffer[offset + 11..offset + 15]),
+            });
+        }
+        Some(advert)
+    }
+}
+
+/// 向所有邻居广播服务目录通告
+pub fn send_service_advert<H: Hardware>(hardware: &mut H, advert: &ServiceAdvertisement) -> Result<(), ReliableError> {
+    let mut data = [0u8; MAX_SECURE_PAYLOAD];
+    let len = advert.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, NodeId::BROADCAST, PacketType::ServiceAdvertisement, 0, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
@@ -0,0 +1,99 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType, ServiceType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u16be, put_u16be};
+use crate::security::send_secure;
+
+/// 服务信标负载长度：服务集合(1) 负载(1) 电池电量(1) 最大带宽(2) 最小延迟(2) 可靠性(1)
+pub const SERVICE_BEACON_LEN: usize = 8;
+
+/// 服务器随信标广播的服务能力，直接听到的转发节点据此填写服务目录
+///
+/// 信标本身没有空间携带服务能力，因此单独作为数据包广播，只传一跳，更远的转发节点通过目录同步得知。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServiceBeacon {
+    /// 提供的服务，第n位对应类型值为n+1的服务
+    services: u8,
+    /// 负载（0-100%）
+    pub load: u8,
+    /// 电池电量（0-100%）
+    pub battery_level: u8,
+    /// 最大带宽（kbps）
+    pub max_bandwidth: u16,
+    /// 最小延迟（ms）
+    pub min_latency: u16,
+    /// 可靠性（0-100%）
+    pub reliability: u8,
+}
+
+impl ServiceBeacon {
+    /// 创建不提供任何服务的信标
+    pub fn new(load: u8, battery_level: u8, max_bandwidth: u16, min_latency: u16, reliability: u8) -> Self {
+        Self {
+            services: 0,
+            load,
+            battery_level,
+            max_bandwidth,
+            min_latency,
+            reliability,
+        }
+    }
+    
+    /// 声明提供某类服务
+    pub fn with_service(mut self, service_type: ServiceType) -> Self {
+        self.services |= 1 << (service_type as u8 - 1);
+        self
+    }
+    
+    /// 是否提供某类服务
+    pub fn supports(&self, service_type: ServiceType) -> bool {
+        self.services & (1 << (service_type as u8 - 1)) != 0
+    }
+    
+    /// 提供的所有服务
+    pub fn services(&self) -> impl Iterator<Item = ServiceType> + '_ {
+        (1..=8u8).filter_map(ServiceType::from_u8).filter(move |service_type| self.supports(*service_type))
+    }
+    
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        if buffer.len() < SERVICE_BEACON_LEN {
+            return 0;
+        }
+        
+        buffer[0] = self.services;
+        buffer[1] = self.load;
+        buffer[2] = self.battery_level;
+        put_u16be(&mut buffer[3..5], self.max_bandwidth);
+        put_u16be(&mut buffer[5..7], self.min_latency);
+        buffer[7] = self.reliability;
+        SERVICE_BEACON_LEN
+    }
+    
+    /// 反序列化，长度不足时返回None
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < SERVICE_BEACON_LEN {
+            return None;
+        }
+        
+        Some(Self {
+            services: buffer[0],
+            load: buffer[1],
+            battery_level: buffer[2],
+            max_bandwidth: get_u16be(&buffer[3..5]),
+            min_latency: get_u16be(&buffer[5..7]),
+            reliability: buffer[7],
+        })
+    }
+}
+
+/// 向所有邻居广播服务信标
+pub fn send_service_beacon<H: Hardware>(hardware: &mut H, beacon: &ServiceBeacon) -> Result<(), ReliableError> {
+    let mut data = [0u8; SERVICE_BEACON_LEN];
+    let len = beacon.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, NodeId::BROADCAST, PacketType::ServiceBeacon, 0, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
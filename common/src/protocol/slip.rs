@@ -0,0 +1,107 @@
+/// 帧结束符
+pub const SLIP_END: u8 = 0xC0;
+
+/// 转义符
+pub const SLIP_ESC: u8 = 0xDB;
+
+/// 转义后的帧结束符
+pub const SLIP_ESC_END: u8 = 0xDC;
+
+/// 转义后的转义符
+pub const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// 以SLIP编码一帧，前后各加一个结束符，缓冲区不足时返回0
+///
+/// 串口连接的无线模块和嗅探器都用SLIP在字节流中划分原始无线帧。
+pub fn encode(frame: &[u8], out: &mut [u8]) -> usize {
+    let mut len = 0;
+    let mut put = |byte: u8| -> bool {
+        if len >= out.len() {
+            return false;
+        }
+        out[len] = byte;
+        len += 1;
+        true
+    };
+    
+    if !put(SLIP_END) {
+        return 0;
+    }
+    for &byte in frame {
+        let ok = match byte {
+            SLIP_END => put(SLIP_ESC) && put(SLIP_ESC_END),
+            SLIP_ESC => put(SLIP_ESC) && put(SLIP_ESC_ESC),
+            _ => put(byte),
+        };
+        if !ok {
+            return 0;
+        }
+    }
+    if !put(SLIP_END) {
+        return 0;
+    }
+    
+    len
+}
+
+/// 逐字节解码SLIP字节流
+pub struct SlipDecoder<const N: usize> {
+    buffer: [u8; N],
+    len: usize,
+    escaped: bool,
+    /// 遇到非法转义或超长帧后丢弃到下一个结束符
+    discarding: bool,
+}
+
+impl<const N: usize> SlipDecoder<N> {
+    /// 创建解码器
+    pub fn new() -> Self {
+        Self {
+            buffer: [0; N],
+            len: 0,
+            escaped: false,
+            discarding: false,
+        }
+    }
+    
+    /// 输入一个字节，收到完整的一帧时返回帧内容
+    pub fn push(&mut self, byte: u8) -> Option<&[u8]> {
+        if byte == SLIP_END {
+            let len = self.len;
+            let complete = !self.discarding && len > 0;
+            self.len = 0;
+            self.escaped = false;
+            self.discarding = false;
+            // 连续的结束符之间没有数据，用于同步，不算一帧
+            return if complete { Some(&self.buffer[..len]) } else { None };
+        }
+        if self.discarding {
+            return None;
+        }
+        
+        let byte = if self.escaped {
+            self.escaped = false;
+            match byte {
+                SLIP_ESC_END => SLIP_END,
+                SLIP_ESC_ESC => SLIP_ESC,
+                _ => {
+                    self.discarding = true;
+                    return None;
+                },
+            }
+        } else if byte == SLIP_ESC {
+            self.escaped = true;
+            return None;
+        } else {
+            byte
+        };
+        
+        if self.len >= N {
+            self.discarding = true;
+            return None;
+        }
+        self.buffer[self.len] = byte;
+        self.len += 1;
+        None
+    }
+}
\ No newline at end of file
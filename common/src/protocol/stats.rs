@@ -0,0 +1,300 @@
+use core::fmt;
+
+use crate::hal::Hardware;
+use crate::metrics::{self, Counter, Gauge, MetricsSnapshot, COUNTER_COUNT, GAUGE_COUNT};
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::{ReliableError, MAX_FRAME_PAYLOAD};
+use crate::protocol::wire::{get_u32be, put_u32be};
+use crate::security::send_secure;
+
+/// 统计查询负载长度：发起方(6) 目标(6) 起始指标(1)
+pub const STATS_REQUEST_LEN: usize = 13;
+
+/// 旧版本的统计查询没有起始指标，按从头查询处理
+const LEGACY_STATS_REQUEST_LEN: usize = 12;
+
+/// 统计应答头部长度：发起方(6) 目标(6) 起始指标(1) 计数器数(1) 仪表数(1) 本页指标数(1)
+pub const STATS_RESPONSE_HEADER_LEN: usize = 16;
+
+/// 一页应答最多携带的指标数，指标超过一页时发起方按下一页的起始指标继续查询
+pub const STATS_PAGE_METRICS: usize = (MAX_FRAME_PAYLOAD - STATS_RESPONSE_HEADER_LEN) / 4;
+
+/// 统计应答负载的最大长度
+pub const STATS_RESPONSE_LEN: usize = STATS_RESPONSE_HEADER_LEN + STATS_PAGE_METRICS * 4;
+
+const _: () = assert!(STATS_RESPONSE_LEN <= MAX_FRAME_PAYLOAD);
+const _: () = assert!(COUNTER_COUNT + GAUGE_COUNT <= u8::MAX as usize);
+
+/// 指标快照的一页，计数器在前、仪表在后统一编号
+///
+/// 应答携带发送方的计数器数和仪表数，版本不同的节点之间按各自的编号对应，缺少的指标为0，多出的忽略。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsPage {
+    /// 本页第一个指标的编号
+    pub first: u8,
+    /// 发送方的计数器数
+    pub counter_count: u8,
+    /// 发送方的仪表数
+    pub gauge_count: u8,
+    len: u8,
+    values: [u32; STATS_PAGE_METRICS],
+}
+
+impl StatsPage {
+    /// 从快照中取出从指定编号开始的一页
+    pub fn of(snapshot: &MetricsSnapshot, first: u8) -> Self {
+        let metrics = snapshot.counters.iter().chain(snapshot.gauges.iter());
+        let mut page = Self {
+            first,
+            counter_count: COUNTER_COUNT as u8,
+            gauge_count: GAUGE_COUNT as u8,
+            len: 0,
+            values: [0; STATS_PAGE_METRICS],
+        };
+        for (slot, value) in page.values.iter_mut().zip(metrics.skip(first as usize)) {
+            *slot = *value;
+            page.len += 1;
+        }
+        page
+    }
+    
+    /// 本页的指标值
+    pub fn values(&self) -> &[u32] {
+        &self.values[..self.len as usize]
+    }
+    
+    /// 下一页的起始编号，本页已是最后一页时返回None
+    pub fn next(&self) -> Option<u8> {
+        let end = self.first as usize + self.len as usize;
+        let total = self.counter_count as usize + self.gauge_count as usize;
+        (self.len > 0 && end < total).then_some(end as u8)
+    }
+    
+    /// 把本页的指标写入快照
+    pub fn apply_to(&self, snapshot: &mut MetricsSnapshot) {
+        let counters = self.counter_count as usize;
+        for (i, value) in self.values().iter().enumerate() {
+            let index = self.first as usize + i;
+            let slot = if index < counters {
+                snapshot.counters.get_mut(index)
+            } else {
+                snapshot.gauges.get_mut(index - counters)
+            };
+            if let Some(slot) = slot {
+                *slot = *value;
+            }
+        }
+    }
+    
+    fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let len = 4 + self.len as usize * 4;
+        if buffer.len() < len {
+            return 0;
+        }
+        
+        buffer[0] = self.first;
+        buffer[1] = self.counter_count;
+        buffer[2] = self.gauge_count;
+        buffer[3] = self.len;
+        for (bytes, value) in buffer[4..len].chunks_exact_mut(4).zip(self.values()) {
+            put_u32be(bytes, *value);
+        }
+        len
+    }
+    
+    fn deserialize(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < 4 || buffer[3] as usize > STATS_PAGE_METRICS {
+            return None;
+        }
+        let (first, counter_count, gauge_count, len) = (buffer[0], buffer[1], buffer[2], buffer[3]);
+        
+        let mut values = [0u32; STATS_PAGE_METRICS];
+        for (value, bytes) in values.iter_mut().zip(buffer.get(4..4 + len as usize * 4)?.chunks_exact(4)) {
+            *value = get_u32be(bytes);
+        }
+        Some(Self { first, counter_count, gauge_count, len, values })
+    }
+}
+
+impl fmt::Display for StatsPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counters = self.counter_count as usize;
+        for (i, value) in self.values().iter().enumerate() {
+            let index = self.first as usize + i;
+            let name = match index.checked_sub(counters) {
+                None => Counter::ALL.get(index).map(Counter::name),
+                Some(gauge) => Gauge::ALL.get(gauge).map(Gauge::name),
+            };
+            match name {
+                Some(name) => writeln!(f, "  {}: {}", name, value)?,
+                None => writeln!(f, "  #{}: {}", index, value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 远程读取节点计数器的查询和应答
+///
+/// 中继逐跳改写包头中的源和目标，因此端到端的发起方和目标记录在负载中；
+/// 查询发往目标，应答沿反向发回发起方。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsMessage {
+    /// 发起方查询目标节点从指定编号开始的一页指标
+    Request { origin: NodeId, target: NodeId, first: u8 },
+    /// 目标节点回复的一页指标快照
+    Response { origin: NodeId, target: NodeId, page: StatsPage },
+}
+
+impl StatsMessage {
+    /// 发起查询的节点
+    pub fn origin(&self) -> NodeId {
+        match self {
+            StatsMessage::Request { origin, .. } | StatsMessage::Response { origin, .. } => *origin,
+        }
+    }
+    
+    /// 被查询的节点
+    pub fn target(&self) -> NodeId {
+        match self {
+            StatsMessage::Request { target, .. } | StatsMessage::Response { target, .. } => *target,
+        }
+    }
+    
+    /// 对应的包类型
+    pub fn packet_type(&self) -> PacketType {
+        match self {
+            StatsMessage::Request { .. } => PacketType::StatsRequest,
+            StatsMessage::Response { .. } => PacketType::StatsResponse,
+        }
+    }
+    
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        if buffer.len() < LEGACY_STATS_REQUEST_LEN {
+            return 0;
+        }
+        
+        buffer[0..6].copy_from_slice(&self.origin().0);
+        buffer[6..12].copy_from_slice(&self.target().0);
+        match self {
+            StatsMessage::Request { first, .. } => match buffer.get_mut(12) {
+                Some(slot) => {
+                    *slot = *first;
+                    STATS_REQUEST_LEN
+                },
+                None => 0,
+            },
+            StatsMessage::Response { page, .. } => match page.serialize(&mut buffer[12..]) {
+                0 => 0,
+                len => 12 + len,
+            },
+        }
+    }
+    
+    /// 按包类型反序列化
+    pub fn deserialize(packet_type: u8, buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < LEGACY_STATS_REQUEST_LEN {
+            return None;
+        }
+        
+        let mut origin = [0u8; 6];
+        origin.copy_from_slice(&buffer[0..6]);
+        let mut target = [0u8; 6];
+        target.copy_from_slice(&buffer[6..12]);
+        let (origin, target) = (NodeId(origin), NodeId(target));
+        
+        match PacketType::from_u8(packet_type)? {
+            PacketType::StatsRequest => Some(StatsMessage::Request {
+                origin,
+                target,
+                first: buffer.get(12).copied().unwrap_or(0),
+            }),
+            PacketType::StatsResponse => Some(StatsMessage::Response {
+                origin,
+                target,
+                page: StatsPage::deserialize(&buffer[12..])?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// 向下一跳发送统计查询或应答
+pub fn send_stats<H: Hardware>(
+    hardware: &mut H,
+    next_hop: NodeId,
+    packet_id: u16,
+    message: &StatsMessage
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; STATS_RESPONSE_LEN];
+    let len = message.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, next_hop, message.packet_type(), packet_id, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
+
+/// 处理发给本节点的统计查询，用当前指标快照中请求的一页应答上一跳，由中继转发给发起方
+///
+/// 不是发给本节点的查询或应答发送失败时返回false。
+pub fn answer_stats<H: Hardware>(hardware: &mut H, request: &DataPacket) -> bool {
+    let node_id = hardware.get_node_id();
+    let (origin, first) = match StatsMessage::deserialize(request.header.packet_type, request.data) {
+        Some(StatsMessage::Request { origin, target, first }) if target == node_id => (origin, first),
+        _ => return false,
+    };
+    
+    let response = StatsMessage::Response { origin, target: node_id, page: StatsPage::of(&metrics::snapshot(), first) };
+    let previous_hop = NodeId(request.header.source);
+    send_stats(hardware, previous_hop, request.header.packet_id, &response).is_ok()
+}
+
+/// 收到一页应答后的收集进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsProgress {
+    /// 所有指标都已收到
+    Complete(MetricsSnapshot),
+    /// 还需要从这个编号开始查询下一页
+    Next(u8),
+    /// 不属于正在收集的节点，忽略
+    Ignored,
+}
+
+/// 发起方收集远程节点逐页到达的指标
+pub struct StatsCollector {
+    /// 正在收集的目标节点
+    target: Option<NodeId>,
+    snapshot: MetricsSnapshot,
+}
+
+impl StatsCollector {
+    pub fn new() -> Self {
+        Self {
+            target: None,
+            snapshot: MetricsSnapshot { counters: [0; COUNTER_COUNT], gauges: [0; GAUGE_COUNT] },
+        }
+    }
+    
+    /// 处理一页应答，没有收齐时由调用方按返回的编号继续查询
+    ///
+    /// 第一页开始新的一轮收集，其他节点的后续页被忽略。
+    pub fn accept(&mut self, target: NodeId, page: &StatsPage) -> StatsProgress {
+        if page.first == 0 {
+            self.target = Some(target);
+            self.snapshot = MetricsSnapshot { counters: [0; COUNTER_COUNT], gauges: [0; GAUGE_COUNT] };
+        } else if self.target != Some(target) {
+            return StatsProgress::Ignored;
+        }
+        
+        page.apply_to(&mut self.snapshot);
+        match page.next() {
+            Some(next) => StatsProgress::Next(next),
+            None => {
+                self.target = None;
+                StatsProgress::Complete(self.snapshot)
+            },
+        }
+    }
+}
\ No newline at end of file
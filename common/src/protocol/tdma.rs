@@ -0,0 +1,244 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::time_sync::TIME_BEACON_LEN;
+use crate::protocol::wire::{get_u16be, put_u16be};
+use crate::security::send_secure;
+
+/// 每个超帧最多分配的时隙数
+pub const MAX_SLOTS: usize = 8;
+
+/// 默认时隙长度（毫秒）
+pub const DEFAULT_SLOT_MS: u16 = 200;
+
+/// 时隙表的最大长度：时隙长度(2) 时隙数(1) 每个时隙的所有者(6)
+pub const SLOT_TABLE_MAX_LEN: usize = 3 + 6 * MAX_SLOTS;
+
+/// 时隙申请负载长度：申请节点(6) 主节点(6)
+pub const SLOT_REQUEST_LEN: usize = 12;
+
+/// 时隙申请的重发间隔（毫秒），主节点据此保持登记
+pub const SLOT_REQUEST_INTERVAL_MS: u64 = 30000;
+
+/// 多久没有收到申请就收回时隙（毫秒）
+pub const SLOT_REGISTRATION_MS: u64 = 3 * SLOT_REQUEST_INTERVAL_MS;
+
+/// 主节点分配的发送时隙表，附在时间信标之后随信标逐跳广播
+///
+/// 超帧从网络时间的整数倍开始，第i个时隙占用`[i*slot_ms, (i+1)*slot_ms)`。
+/// 空表表示不启用时分，没有分到时隙的节点（包括旧版本节点）也不受限制。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotTable {
+    /// 时隙长度（毫秒）
+    pub slot_ms: u16,
+    /// 每个时隙的所有者，空闲时隙为None
+    pub owners: [Option<NodeId>; MAX_SLOTS],
+}
+
+impl SlotTable {
+    /// 未启用时分的空表
+    pub fn empty() -> Self {
+        Self {
+            slot_ms: DEFAULT_SLOT_MS,
+            owners: [None; MAX_SLOTS],
+        }
+    }
+    
+    /// 是否没有分配任何时隙
+    pub fn is_empty(&self) -> bool {
+        self.owners.iter().all(|owner| owner.is_none())
+    }
+    
+    /// 超帧中的时隙数，即最后一个已分配时隙的下一个位置
+    pub fn slot_count(&self) -> usize {
+        self.owners.iter().rposition(|owner| owner.is_some()).map_or(0, |last| last + 1)
+    }
+    
+    /// 超帧长度（毫秒）
+    pub fn superframe_ms(&self) -> u64 {
+        self.slot_count() as u64 * self.slot_ms as u64
+    }
+    
+    /// 节点分到的时隙
+    pub fn slot_of(&self, node: NodeId) -> Option<usize> {
+        self.owners.iter().position(|owner| *owner == Some(node))
+    }
+    
+    /// 距离节点下一个时隙开始还要等待的时间（毫秒），当前就在时隙内或不受时分限制时为0
+    pub fn wait_ms(&self, node: NodeId, network_time: u64) -> u64 {
+        let slot = match self.slot_of(node) {
+            Some(slot) if self.slot_ms > 0 => slot as u64,
+            _ => return 0,
+        };
+        
+        let superframe = self.superframe_ms();
+        let offset = network_time % superframe;
+        let start = slot * self.slot_ms as u64;
+        let end = start + self.slot_ms as u64;
+        if offset >= start && offset < end {
+            0
+        } else if offset < start {
+            start - offset
+        } else {
+            superframe - offset + start
+        }
+    }
+    
+    /// 节点此时是否可以发送大流量数据
+    pub fn may_send_bulk(&self, node: NodeId, network_time: u64) -> bool {
+        self.wait_ms(node, network_time) == 0
+    }
+    
+    /// 序列化，空表不占用字节，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        let count = self.slot_count();
+        let len = 3 + 6 * count;
+        if count == 0 || buffer.len() < len {
+            return 0;
+        }
+        
+        put_u16be(&mut buffer[0..2], self.slot_ms);
+        buffer[2] = count as u8;
+        for (i, owner) in self.owners[..count].iter().enumerate() {
+            let id = owner.unwrap_or(NodeId::BROADCAST);
+            buffer[3 + 6 * i..9 + 6 * i].copy_from_slice(&id.0);
+        }
+        
+        len
+    }
+    
+    /// 反序列化，没有时隙表时返回空表
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        let mut table = Self::empty();
+        if buffer.is_empty() {
+            return Some(table);
+        }
+        if buffer.len() < 3 {
+            return None;
+        }
+        
+        let count = buffer[2] as usize;
+        if count > MAX_SLOTS || buffer.len() < 3 + 6 * count {
+            return None;
+        }
+        
+        table.slot_ms = get_u16be(&buffer[0..2]);
+        for i in 0..count {
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&buffer[3 + 6 * i..9 + 6 * i]);
+            let id = NodeId(id);
+            table.owners[i] = if id.is_broadcast() { None } else { Some(id) };
+        }
+        
+        Some(table)
+    }
+    
+    /// 从时间信标负载中读取附带的时隙表，格式不符时按空表处理
+    pub fn from_time_sync(data: &[u8]) -> Self {
+        data.get(TIME_BEACON_LEN..)
+            .and_then(Self::deserialize)
+            .unwrap_or_else(Self::empty)
+    }
+}
+
+/// 时隙申请，负载携带申请节点和主节点，经转发节点逐跳送到主节点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotRequest {
+    /// 申请时隙的节点
+    pub node: NodeId,
+    /// 负责分配的主节点
+    pub master: NodeId,
+}
+
+impl SlotRequest {
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        if buffer.len() < SLOT_REQUEST_LEN {
+            return 0;
+        }
+        
+        buffer[0..6].copy_from_slice(&self.node.0);
+        buffer[6..12].copy_from_slice(&self.master.0);
+        
+        SLOT_REQUEST_LEN
+    }
+    
+    /// 反序列化
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < SLOT_REQUEST_LEN {
+            return None;
+        }
+        
+        let mut node = [0u8; 6];
+        node.copy_from_slice(&buffer[0..6]);
+        let mut master = [0u8; 6];
+        master.copy_from_slice(&buffer[6..12]);
+        
+        Some(Self {
+            node: NodeId(node),
+            master: NodeId(master),
+        })
+    }
+}
+
+/// 向下一跳发送时隙申请
+pub fn send_slot_request<H: Hardware>(
+    hardware: &mut H,
+    next_hop: NodeId,
+    request: &SlotRequest
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; SLOT_REQUEST_LEN];
+    request.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, next_hop, PacketType::SlotRequest, 0, &data);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
+
+/// 主节点的时隙分配
+///
+/// 节点按申请顺序占用空闲时隙，之后一直保持同一个时隙，直到超过登记时间没有再申请。
+pub struct SlotAllocator {
+    /// 每个时隙的所有者和最近一次申请的网络时间
+    registrations: [Option<(NodeId, u64)>; MAX_SLOTS],
+    slot_ms: u16,
+}
+
+impl SlotAllocator {
+    /// 创建空的分配表
+    pub fn new(slot_ms: u16) -> Self {
+        Self {
+            registrations: [None; MAX_SLOTS],
+            slot_ms,
+        }
+    }
+    
+    /// 登记或续期节点的时隙，时隙已满时返回None
+    pub fn register(&mut self, node: NodeId, now: u64) -> Option<usize> {
+        let slot = self.registrations.iter().position(|entry| matches!(entry, Some((owner, _)) if *owner == node))
+            .or_else(|| self.registrations.iter().position(|entry| entry.is_none()))?;
+        
+        self.registrations[slot] = Some((node, now));
+        Some(slot)
+    }
+    
+    /// 收回超过登记时间没有续期的时隙
+    pub fn expire(&mut self, now: u64) {
+        for entry in self.registrations.iter_mut() {
+            if matches!(entry, Some((_, at)) if now.saturating_sub(*at) > SLOT_REGISTRATION_MS) {
+                *entry = None;
+            }
+        }
+    }
+    
+    /// 当前的时隙表
+    pub fn table(&self) -> SlotTable {
+        let mut table = SlotTable::empty();
+        table.slot_ms = self.slot_ms;
+        for (owner, entry) in table.owners.iter_mut().zip(self.registrations.iter()) {
+            *owner = entry.map(|(node, _)| node);
+        }
+        table
+    }
+}
\ No newline at end of file
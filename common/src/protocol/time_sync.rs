@@ -0,0 +1,71 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::tdma::{SlotTable, SLOT_TABLE_MAX_LEN};
+use crate::protocol::wire::{get_u16be, get_u64be, put_u16be, put_u64be};
+use crate::security::send_secure;
+
+/// 时间信标负载长度：主节点(6) 序号(2) 网络时间(8) 跳数(1)
+pub const TIME_BEACON_LEN: usize = 17;
+
+/// 主节点发出的时间信标，经转发节点逐跳广播
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeBeacon {
+    /// 作为时间基准的主节点
+    pub master: NodeId,
+    /// 主节点每轮递增的序号，转发节点据此只转发一次
+    pub sequence: u16,
+    /// 发送时刻的网络时间（毫秒），转发时按本节点时钟重新填写
+    pub network_time: u64,
+    /// 距主节点的跳数
+    pub hop_count: u8,
+}
+
+impl TimeBeacon {
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        if buffer.len() < TIME_BEACON_LEN {
+            return 0;
+        }
+        
+        buffer[0..6].copy_from_slice(&self.master.0);
+        put_u16be(&mut buffer[6..8], self.sequence);
+        put_u64be(&mut buffer[8..16], self.network_time);
+        buffer[16] = self.hop_count;
+        
+        TIME_BEACON_LEN
+    }
+    
+    /// 反序列化
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        if buffer.len() < TIME_BEACON_LEN {
+            return None;
+        }
+        
+        let mut master = [0u8; 6];
+        master.copy_from_slice(&buffer[0..6]);
+        
+        Some(Self {
+            master: NodeId(master),
+            sequence: get_u16be(&buffer[6..8]),
+            network_time: get_u64be(&buffer[8..16]),
+            hop_count: buffer[16],
+        })
+    }
+}
+
+/// 广播时间信标，主节点分配的时隙表附在信标之后
+pub fn send_time_beacon<H: Hardware>(
+    hardware: &mut H,
+    beacon: &TimeBeacon,
+    slots: &SlotTable
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; TIME_BEACON_LEN + SLOT_TABLE_MAX_LEN];
+    let len = beacon.serialize(&mut data);
+    let len = len + slots.serialize(&mut data[len..]);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, NodeId::BROADCAST, PacketType::TimeSync, beacon.sequence, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
@@ -0,0 +1,206 @@
+use crate::hal::Hardware;
+use crate::protocol::{DataPacket, NodeId, PacketType};
+use crate::protocol::reliable::ReliableError;
+use crate::protocol::wire::{get_u16be, put_u16be};
+use crate::security::{send_secure, MAX_SECURE_PAYLOAD};
+
+/// 拓扑请求长度：类型(1) 主节点(6) 请求ID(2)
+pub const TOPOLOGY_REQUEST_LEN: usize = 9;
+
+/// 拓扑报告头部长度：类型(1) 主节点(6) 请求ID(2) 报告节点(6) 条目数(1)
+pub const TOPOLOGY_REPORT_HEADER_LEN: usize = 16;
+
+/// 每个路由条目的长度：目的地(6) 下一跳(6) 度量(1)
+pub const TOPOLOGY_ENTRY_LEN: usize = 13;
+
+/// 单个报告最多携带的路由条目数，受单帧负载长度限制
+pub const MAX_TOPOLOGY_ENTRIES: usize = (MAX_SECURE_PAYLOAD - TOPOLOGY_REPORT_HEADER_LEN) / TOPOLOGY_ENTRY_LEN;
+
+/// 拓扑消息类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TopologyMessageType {
+    /// 主节点请求各转发节点上报路由表
+    Request = 0x01,
+    /// 转发节点的路由表
+    Report = 0x02,
+}
+
+/// 路由表中的一条路由，目的地等于下一跳时表示直接邻居
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RouteEdge {
+    /// 目的地
+    pub destination: NodeId,
+    /// 下一跳
+    pub next_hop: NodeId,
+    /// 路由度量（信号强度，dBm）
+    pub metric: i8,
+}
+
+impl RouteEdge {
+    /// 是否是能直接听到的邻居
+    pub fn is_neighbor(&self) -> bool {
+        self.destination == self.next_hop
+    }
+}
+
+/// 转发节点上报的路由表
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopologyReport {
+    /// 发起收集的主节点
+    pub master: NodeId,
+    /// 请求ID
+    pub request_id: u16,
+    /// 报告节点
+    pub reporter: NodeId,
+    entries: [RouteEdge; MAX_TOPOLOGY_ENTRIES],
+    len: usize,
+}
+
+impl TopologyReport {
+    /// 创建空报告
+    pub fn new(master: NodeId, request_id: u16, reporter: NodeId) -> Self {
+        Self {
+            master,
+            request_id,
+            reporter,
+            entries: [RouteEdge::default(); MAX_TOPOLOGY_ENTRIES],
+            len: 0,
+        }
+    }
+    
+    /// 由路由表生成报告：（目的地，下一跳，度量），超出条目上限的部分被截断
+    pub fn from_routes<I>(master: NodeId, request_id: u16, reporter: NodeId, routes: I) -> Self
+    where
+        I: IntoIterator<Item = (NodeId, NodeId, i8)>,
+    {
+        let mut report = Self::new(master, request_id, reporter);
+        for (destination, next_hop, metric) in routes {
+            if !report.push(RouteEdge { destination, next_hop, metric }) {
+                break;
+            }
+        }
+        report
+    }
+    
+    /// 添加一条路由，已满时返回false
+    pub fn push(&mut self, edge: RouteEdge) -> bool {
+        if self.len >= MAX_TOPOLOGY_ENTRIES {
+            return false;
+        }
+        self.entries[self.len] = edge;
+        self.len += 1;
+        true
+    }
+    
+    /// 报告中的路由
+    pub fn entries(&self) -> &[RouteEdge] {
+        &self.entries[..self.len]
+    }
+}
+
+/// 拓扑收集消息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopologyMessage {
+    /// 主节点广播的收集请求，转发节点对每个请求ID只转发一次
+    Request { master: NodeId, request_id: u16 },
+    /// 发往主节点的路由表
+    Report(TopologyReport),
+}
+
+impl TopologyMessage {
+    /// 序列化，缓冲区不足时返回0
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        match self {
+            TopologyMessage::Request { master, request_id } => {
+                if buffer.len() < TOPOLOGY_REQUEST_LEN {
+                    return 0;
+                }
+                buffer[0] = TopologyMessageType::Request as u8;
+                buffer[1..7].copy_from_slice(&master.0);
+                put_u16be(&mut buffer[7..9], request_id);
+                TOPOLOGY_REQUEST_LEN
+            },
+            TopologyMessage::Report(report) => {
+                let len = TOPOLOGY_REPORT_HEADER_LEN + report.len * TOPOLOGY_ENTRY_LEN;
+                if buffer.len() < len {
+                    return 0;
+                }
+                buffer[0] = TopologyMessageType::Report as u8;
+                buffer[1..7].copy_from_slice(&report.master.0);
+                put_u16be(&mut buffer[7..9], report.request_id);
+                buffer[9..15].copy_from_slice(&report.reporter.0);
+                buffer[15] = report.len as u8;
+                
+                for (i, edge) in report.entries().iter().enumerate() {
+                    let offset = TOPOLOGY_REPORT_HEADER_LEN + i * TOPOLOGY_ENTRY_LEN;
+                    buffer[offset..offset + 6].copy_from_slice(&edge.destination.0);
+                    buffer[offset + 6..offset + 12].copy_from_slice(&edge.next_hop.0);
+                    buffer[offset + 12] = edge.metric as u8;
+                }
+                len
+            },
+        }
+    }
+    
+    /// 反序列化，截断或条目数超出上限时返回None
+    pub fn deserialize(buffer: &[u8]) -> Option<Self> {
+        let read_node = |offset: usize| {
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&buffer[offset..offset + 6]);
+            NodeId(id)
+        };
+        
+        match *buffer.first()? {
+            t if t == TopologyMessageType::Request as u8 => {
+                if buffer.len() < TOPOLOGY_REQUEST_LEN {
+                    return None;
+                }
+                Some(TopologyMessage::Request {
+                    master: read_node(1),
+                    request_id: get_u16be(&buffer[7..9]),
+                })
+            },
+            t if t == TopologyMessageType::Report as u8 => {
+                if buffer.len() < TOPOLOGY_REPORT_HEADER_LEN {
+                    return None;
+                }
+                let count = buffer[15] as usize;
+                if count > MAX_TOPOLOGY_ENTRIES || buffer.len() < TOPOLOGY_REPORT_HEADER_LEN + count * TOPOLOGY_ENTRY_LEN {
+                    return None;
+                }
+                
+                let mut report = TopologyReport::new(
+                    read_node(1),
+                    get_u16be(&buffer[7..9]),
+                    read_node(9),
+                );
+                for i in 0..count {
+                    let offset = TOPOLOGY_REPORT_HEADER_LEN + i * TOPOLOGY_ENTRY_LEN;
+                    report.push(RouteEdge {
+                        destination: read_node(offset),
+                        next_hop: read_node(offset + 6),
+                        metric: buffer[offset + 12] as i8,
+                    });
+                }
+                Some(TopologyMessage::Report(report))
+            },
+            _ => None,
+        }
+    }
+}
+
+/// 发送拓扑消息
+pub fn send_topology<H: Hardware>(
+    hardware: &mut H,
+    destination: NodeId,
+    message: &TopologyMessage
+) -> Result<(), ReliableError> {
+    let mut data = [0u8; MAX_SECURE_PAYLOAD];
+    let len = message.serialize(&mut data);
+    
+    let node_id = hardware.get_node_id();
+    let packet = DataPacket::with_type(node_id, destination, PacketType::Topology, 0, &data[..len]);
+    
+    send_secure(hardware, &packet).map_err(|_| ReliableError::SendFailed)
+}
\ No newline at end of file
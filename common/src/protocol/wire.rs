@@ -0,0 +1,71 @@
+/// 读取小端u16
+pub fn get_u16le(buffer: &[u8]) -> u16 {
+    u16::from_le_bytes([buffer[0], buffer[1]])
+}
+
+/// 写入小端u16
+pub fn put_u16le(buffer: &mut [u8], value: u16) {
+    buffer[..2].copy_from_slice(&value.to_le_bytes());
+}
+
+/// 读取小端u32
+pub fn get_u32le(buffer: &[u8]) -> u32 {
+    u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]])
+}
+
+/// 写入小端u32
+pub fn put_u32le(buffer: &mut [u8], value: u32) {
+    buffer[..4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// 读取大端u16
+pub fn get_u16be(buffer: &[u8]) -> u16 {
+    u16::from_be_bytes([buffer[0], buffer[1]])
+}
+
+/// 写入大端u16
+pub fn put_u16be(buffer: &mut [u8], value: u16) {
+    buffer[..2].copy_from_slice(&value.to_be_bytes());
+}
+
+/// 读取大端u32
+pub fn get_u32be(buffer: &[u8]) -> u32 {
+    u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]])
+}
+
+/// 写入大端u32
+pub fn put_u32be(buffer: &mut [u8], value: u32) {
+    buffer[..4].copy_from_slice(&value.to_be_bytes());
+}
+
+/// 读取大端i16
+pub fn get_i16be(buffer: &[u8]) -> i16 {
+    i16::from_be_bytes([buffer[0], buffer[1]])
+}
+
+/// 写入大端i16
+pub fn put_i16be(buffer: &mut [u8], value: i16) {
+    buffer[..2].copy_from_slice(&value.to_be_bytes());
+}
+
+/// 读取大端u64
+pub fn get_u64be(buffer: &[u8]) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buffer[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// 写入大端u64
+pub fn put_u64be(buffer: &mut [u8], value: u64) {
+    buffer[..8].copy_from_slice(&value.to_be_bytes());
+}
+
+/// 读取大端f32，按IEEE 754位模式传输
+pub fn get_f32be(buffer: &[u8]) -> f32 {
+    f32::from_bits(get_u32be(buffer))
+}
+
+/// 写入大端f32
+pub fn put_f32be(buffer: &mut [u8], value: f32) {
+    put_u32be(buffer, value.to_bits());
+}
\ No newline at end of file
@@ -0,0 +1,574 @@
+use chacha20poly1305::{AeadInPlace, ChaCha20Poly1305, Key, KeyInit, Nonce, Tag};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::hal::{Hardware, RadioInterface};
+use crate::hal::nvs::{keys, NvStorage};
+use crate::metrics::{self, Counter};
+use crate::protocol::{Beacon, DataPacket, NodeId, MAX_PACKET_SIZE};
+use crate::protocol::beacon::BEACON_TAG_LEN;
+use crate::protocol::command::COMMAND_TAG_LEN;
+use crate::protocol::ota::{OtaMessage, MAX_OTA_MESSAGE, OTA_TAG_LEN};
+use crate::protocol::data::{DataHeader, PacketError, UNSET_TTL};
+use crate::protocol::wire::{get_u32be, put_u16be, put_u32be};
+
+/// 网络密钥长度
+pub const NETWORK_KEY_LEN: usize = 16;
+
+/// 受保护的包在类型字节上设置的标志位
+pub const SECURE_FLAG: u8 = 0x80;
+
+/// 每个受保护的包增加的长度：计数器(4) 认证标签(16)
+pub const SECURE_OVERHEAD: usize = 4 + 16;
+
+/// 保护后单个包最多能携带的明文长度
+pub const MAX_SECURE_PAYLOAD: usize = MAX_PACKET_SIZE - DataHeader::SIZE - SECURE_OVERHEAD;
+
+/// 发送计数器每隔多少个包写一次闪存
+///
+/// 重启后从保存的值继续，保证同一密钥下的随机数不会重复。
+const COUNTER_CHECKPOINT: u32 = 1024;
+
+/// 管理消息认证码长度
+pub const MANAGEMENT_TAG_LEN: usize = 8;
+
+/// 缓存的链路会话密钥数
+const MAX_SESSION_KEYS: usize = 8;
+
+/// 跟踪重放计数器的对端数
+const MAX_PEERS: usize = 16;
+
+/// 对端的接收计数器每前进多少写一次闪存
+///
+/// 重启后只接受超过已保存上限的计数器，重启前收到的包无法被重放；
+/// 代价是对端在上限以内的新包会被拒绝，最多这么多个，由重传换用新计数器恢复。
+const RX_COUNTER_CHECKPOINT: u32 = 64;
+
+/// 对端重放计数器的存储长度：个数(1) 每个对端的节点ID(6) 预留上限(4)
+pub const PEER_COUNTERS_SIZE: usize = 1 + 10 * MAX_PEERS;
+
+/// 密钥派生的盐
+const KDF_SALT: &[u8] = b"AetherLink link key v1";
+
+/// 安全处理错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bearpi", derive(defmt::Format))]
+pub enum SecurityError {
+    /// 启用了保护但收到未保护的包
+    Unprotected,
+    /// 长度不足或超出单包容量
+    Malformed,
+    /// 计数器未递增，可能是重放
+    Replay,
+    /// 认证失败：密钥不符或内容被篡改
+    AuthFailed,
+    /// 无线电发送失败
+    SendFailed,
+}
+
+/// 一个对端的重放窗口
+#[derive(Clone, Copy)]
+struct PeerCounter {
+    peer: NodeId,
+    /// 最近接受的计数器
+    last: u32,
+    /// 已写入闪存的上限，重启后从这里开始接受
+    reserved: u32,
+}
+
+/// 派生的链路会话密钥
+#[derive(Clone, Copy)]
+struct SessionKey {
+    /// 链路两端，按字节序排列
+    peers: (NodeId, NodeId),
+    key: [u8; 32],
+}
+
+/// 节点的安全状态：网络密钥、派生的会话密钥、发送计数器和各对端的重放窗口
+pub struct SecurityContext {
+    network_key: Option<[u8; NETWORK_KEY_LEN]>,
+    session_keys: [Option<SessionKey>; MAX_SESSION_KEYS],
+    /// 下一个发送计数器
+    tx_counter: u32,
+    /// 已写入闪存的计数器上限
+    counter_reserved: u32,
+    /// 各对端的重放窗口
+    rx_counters: [Option<PeerCounter>; MAX_PEERS],
+    /// 有对端的计数器越过了已保存的上限，需要写入闪存
+    peers_dirty: bool,
+}
+
+impl SecurityContext {
+    /// 创建未配置密钥的上下文，此时收发不做保护
+    pub fn new() -> Self {
+        Self {
+            network_key: None,
+            session_keys: [None; MAX_SESSION_KEYS],
+            tx_counter: 0,
+            counter_reserved: 0,
+            rx_counters: [None; MAX_PEERS],
+            peers_dirty: false,
+        }
+    }
+    
+    /// 是否已配置网络密钥
+    pub fn is_enabled(&self) -> bool {
+        self.network_key.is_some()
+    }
+    
+    /// 设置或清除网络密钥，已派生的会话密钥随之失效
+    pub fn set_network_key(&mut self, key: Option<[u8; NETWORK_KEY_LEN]>) {
+        self.network_key = key;
+        self.session_keys = [None; MAX_SESSION_KEYS];
+        self.rx_counters = [None; MAX_PEERS];
+    }
+    
+    /// 链路两端的会话密钥，首次使用时由网络密钥派生
+    fn session_key(&mut self, a: NodeId, b: NodeId) -> Option<[u8; 32]> {
+        let network_key = self.network_key?;
+        let peers = if a.0 <= b.0 { (a, b) } else { (b, a) };
+        
+        if let Some(entry) = self.session_keys.iter().flatten().find(|entry| entry.peers == peers) {
+            return Some(entry.key);
+        }
+        
+        let key = derive_session_key(&network_key, peers.0, peers.1);
+        
+        // 缓存已满时替换第一个
+        let slot = self.session_keys.iter().position(|entry| entry.is_none()).unwrap_or(0);
+        self.session_keys[slot] = Some(SessionKey { peers, key });
+        
+        Some(key)
+    }
+    
+    /// 检查并记录对端的计数器，计数器必须严格递增
+    fn accept_counter(&mut self, source: NodeId, counter: u32) -> bool {
+        let reserved = counter.saturating_add(RX_COUNTER_CHECKPOINT);
+        
+        if let Some(entry) = self.rx_counters.iter_mut().flatten().find(|entry| entry.peer == source) {
+            if counter <= entry.last {
+                return false;
+            }
+            entry.last = counter;
+            if counter >= entry.reserved {
+                entry.reserved = reserved;
+                self.peers_dirty = true;
+            }
+            return true;
+        }
+        
+        let slot = self.rx_counters.iter().position(|entry| entry.is_none()).unwrap_or(0);
+        self.rx_counters[slot] = Some(PeerCounter { peer: source, last: counter, reserved });
+        self.peers_dirty = true;
+        true
+    }
+    
+    /// 是否有对端的计数器需要写入闪存
+    pub fn needs_checkpoint(&self) -> bool {
+        self.peers_dirty
+    }
+    
+    /// 序列化各对端已预留的计数器上限
+    pub fn peer_counters_to_bytes(&self) -> [u8; PEER_COUNTERS_SIZE] {
+        let mut bytes = [0u8; PEER_COUNTERS_SIZE];
+        let mut count = 0;
+        for entry in self.rx_counters.iter().flatten() {
+            let offset = 1 + 10 * count;
+            bytes[offset..offset + 6].copy_from_slice(&entry.peer.0);
+            bytes[offset + 6..offset + 10].copy_from_slice(&entry.reserved.to_be_bytes());
+            count += 1;
+        }
+        bytes[0] = count as u8;
+        bytes
+    }
+    
+    /// 恢复保存的计数器上限，之后只接受超过上限的计数器
+    pub fn restore_peer_counters(&mut self, bytes: &[u8]) {
+        let count = match bytes.first() {
+            Some(&count) => (count as usize).min(MAX_PEERS),
+            None => return,
+        };
+        if bytes.len() < 1 + 10 * count {
+            return;
+        }
+        
+        for (i, entry) in self.rx_counters.iter_mut().take(count).enumerate() {
+            let offset = 1 + 10 * i;
+            let mut peer = [0u8; 6];
+            peer.copy_from_slice(&bytes[offset..offset + 6]);
+            let reserved = u32::from_be_bytes([bytes[offset + 6], bytes[offset + 7], bytes[offset + 8], bytes[offset + 9]]);
+            *entry = Some(PeerCounter { peer: NodeId(peer), last: reserved, reserved });
+        }
+        self.peers_dirty = false;
+    }
+}
+
+/// 由网络密钥为一条链路派生会话密钥
+///
+/// 链路两端用同样的节点ID顺序派生，得到相同的密钥；不同链路的密钥互不相同。
+pub fn derive_session_key(network_key: &[u8; NETWORK_KEY_LEN], a: NodeId, b: NodeId) -> [u8; 32] {
+    let mut info = [0u8; 12];
+    info[0..6].copy_from_slice(&a.0);
+    info[6..12].copy_from_slice(&b.0);
+    
+    let mut key = [0u8; 32];
+    let hkdf = Hkdf::<Sha256>::new(Some(KDF_SALT), network_key);
+    // 输出长度固定为32字节，不会失败
+    let _ = hkdf.expand(&info, &mut key);
+    key
+}
+
+/// 计算管理消息的认证码，未配置网络密钥时返回None
+///
+/// 链路保护在每一跳都会重新加密，管理请求另外用网络密钥派生的管理密钥做HMAC-SHA256，
+/// 经过多跳转发后目标节点仍能确认请求来自持有网络密钥的节点。
+pub fn management_tag(context: &SecurityContext, data: &[u8]) -> Option<[u8; MANAGEMENT_TAG_LEN]> {
+    let digest = network_hmac(context, b"management", data)?;
+    
+    let mut tag = [0u8; MANAGEMENT_TAG_LEN];
+    tag.copy_from_slice(&digest[..MANAGEMENT_TAG_LEN]);
+    Some(tag)
+}
+
+/// 计算信标的认证码，未配置网络密钥时返回None
+///
+/// 信标不经过链路保护，用网络密钥派生的信标密钥做HMAC-SHA256，没有网络密钥的节点无法伪造信标。
+/// 认证码不覆盖跳数，转发节点原样转发认证码。
+pub fn beacon_tag(context: &SecurityContext, beacon: &Beacon) -> Option<[u8; BEACON_TAG_LEN]> {
+    let digest = network_hmac(context, b"beacon", &beacon.signed_bytes())?;
+    
+    let mut tag = [0u8; BEACON_TAG_LEN];
+    tag.copy_from_slice(&digest[..BEACON_TAG_LEN]);
+    Some(tag)
+}
+
+/// 计算特权命令的认证码，未配置网络密钥或命令过长时返回None
+///
+/// 与管理请求相同用网络密钥派生的密钥做HMAC-SHA256，覆盖目标节点ID和不含认证码的命令负载，
+/// 发给其他节点的命令不能转用到本节点。
+pub fn command_tag(context: &SecurityContext, target: NodeId, command: &[u8]) -> Option<[u8; COMMAND_TAG_LEN]> {
+    if command.len() > MAX_SECURE_PAYLOAD {
+        return None;
+    }
+    let mut bytes = [0u8; 6 + MAX_SECURE_PAYLOAD];
+    bytes[0..6].copy_from_slice(&target.0);
+    bytes[6..6 + command.len()].copy_from_slice(command);
+    let digest = network_hmac(context, b"command", &bytes[..6 + command.len()])?;
+    
+    let mut tag = [0u8; COMMAND_TAG_LEN];
+    tag.copy_from_slice(&digest[..COMMAND_TAG_LEN]);
+    Some(tag)
+}
+
+/// 计算固件更新消息的认证码，未配置网络密钥时返回None
+///
+/// 固件更新消息经多跳转发，由发起方用网络密钥派生的更新密钥做HMAC-SHA256，中继原样转发认证码，
+/// 节点据此确认镜像通知和分块来自持有网络密钥的服务器后才写入闪存。
+pub fn ota_tag(context: &SecurityContext, message: &OtaMessage) -> Option<[u8; OTA_TAG_LEN]> {
+    let mut bytes = [0u8; MAX_OTA_MESSAGE];
+    let len = message.signed_bytes(&mut bytes);
+    let digest = network_hmac(context, b"ota", &bytes[..len])?;
+    
+    let mut tag = [0u8; OTA_TAG_LEN];
+    tag.copy_from_slice(&digest[..OTA_TAG_LEN]);
+    Some(tag)
+}
+
+/// 用网络密钥按用途派生的密钥计算HMAC-SHA256，未配置网络密钥时返回None
+fn network_hmac(context: &SecurityContext, purpose: &[u8], data: &[u8]) -> Option<[u8; 32]> {
+    let network_key = context.network_key?;
+    
+    let mut key = [0u8; 32];
+    let hkdf = Hkdf::<Sha256>::new(Some(KDF_SALT), &network_key);
+    let _ = hkdf.expand(purpose, &mut key);
+    
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&key).ok()?;
+    mac.update(data);
+    Some(mac.finalize().into_bytes().into())
+}
+
+/// 发送前为信标填写认证码；未配置网络密钥时不做处理
+pub fn sign_beacon<H: Hardware>(hardware: &mut H, beacon: &mut Beacon) {
+    if let Some(tag) = beacon_tag(hardware.get_security(), beacon) {
+        beacon.set_tag(tag);
+    }
+}
+
+/// 校验收到的信标的认证码，未配置网络密钥时全部接受；未通过的信标计入安全拒绝
+pub fn verify_beacon<H: Hardware>(hardware: &mut H, beacon: &Beacon) -> bool {
+    match beacon_tag(hardware.get_security(), beacon) {
+        None => true,
+        // 逐字节累积差异，比较时间与内容无关
+        Some(expected) if beacon.tag.iter().zip(expected.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0 => true,
+        Some(_) => {
+            metrics::increment(Counter::SecurityRejected);
+            false
+        },
+    }
+}
+
+/// 校验发给本节点的特权命令末尾的认证码，未配置网络密钥时返回false；未通过的命令计入安全拒绝
+pub fn verify_command<H: Hardware>(hardware: &mut H, data: &[u8]) -> bool {
+    if data.len() < COMMAND_TAG_LEN {
+        return false;
+    }
+    let (command, tag) = data.split_at(data.len() - COMMAND_TAG_LEN);
+    let node_id = hardware.get_node_id();
+    
+    match command_tag(hardware.get_security(), node_id, command) {
+        // 逐字节累积差异，比较时间与内容无关
+        Some(expected) if tag.iter().zip(expected.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0 => true,
+        _ => {
+            metrics::increment(Counter::SecurityRejected);
+            false
+        },
+    }
+}
+
+/// 发送前为固件更新消息填写认证码；未配置网络密钥时不做处理
+pub fn sign_ota<H: Hardware>(hardware: &mut H, message: &mut OtaMessage) {
+    if let Some(tag) = ota_tag(hardware.get_security(), message) {
+        message.tag = tag;
+    }
+}
+
+/// 校验收到的固件更新消息的认证码
+///
+/// 与信标不同，未配置网络密钥时一律拒绝：无法确认来源的镜像不能写入闪存。未通过的消息计入安全拒绝。
+pub fn verify_ota<H: Hardware>(hardware: &mut H, message: &OtaMessage) -> bool {
+    match ota_tag(hardware.get_security(), message) {
+        // 逐字节累积差异，比较时间与内容无关
+        Some(expected) if message.tag.iter().zip(expected.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0 => true,
+        _ => {
+            metrics::increment(Counter::SecurityRejected);
+            false
+        },
+    }
+}
+
+/// 读取已配置的网络密钥
+pub fn load_network_key<N: NvStorage>(nvs: &mut N) -> Option<[u8; NETWORK_KEY_LEN]> {
+    let mut key = [0u8; NETWORK_KEY_LEN];
+    match nvs.nvs_read(keys::NETWORK_KEY, &mut key) {
+        Ok(Some(NETWORK_KEY_LEN)) => Some(key),
+        _ => None,
+    }
+}
+
+/// 启动时从非易失存储恢复网络密钥、发送计数器和各对端的重放窗口
+///
+/// 计数器从上次预留的上限继续，发送时再预留下一段。
+pub fn restore<H: Hardware>(hardware: &mut H) {
+    let key = load_network_key(hardware.get_nvs());
+    let mut bytes = [0u8; 4];
+    let counter = match hardware.get_nvs().nvs_read(keys::SECURITY_COUNTER, &mut bytes) {
+        Ok(Some(4)) => u32::from_be_bytes(bytes),
+        _ => 0,
+    };
+    let mut peers = [0u8; PEER_COUNTERS_SIZE];
+    let peers_len = hardware.get_nvs().nvs_read(keys::SECURITY_PEERS, &mut peers).ok().flatten().unwrap_or(0);
+    
+    let security = hardware.get_security();
+    if key.is_some() {
+        security.set_network_key(key);
+    }
+    security.tx_counter = counter;
+    security.counter_reserved = counter;
+    security.restore_peer_counters(&peers[..peers_len]);
+}
+
+/// 把各对端的计数器上限写入非易失存储，写入失败时返回false并在下次接收时重试
+fn checkpoint_peers<H: Hardware>(hardware: &mut H) -> bool {
+    let bytes = hardware.get_security().peer_counters_to_bytes();
+    if hardware.get_nvs().nvs_write(keys::SECURITY_PEERS, &bytes).is_err() {
+        return false;
+    }
+    hardware.get_security().peers_dirty = false;
+    true
+}
+
+/// 配置网络密钥（入网），写入非易失存储后立即生效
+pub fn provision<H: Hardware>(hardware: &mut H, key: [u8; NETWORK_KEY_LEN]) -> bool {
+    if hardware.get_nvs().nvs_write(keys::NETWORK_KEY, &key).is_err() {
+        return false;
+    }
+    hardware.get_security().set_network_key(Some(key));
+    true
+}
+
+/// 加密并认证一个数据包，结果写入out并返回保护后的包
+///
+/// 负载格式：计数器(4) 密文 认证标签(16)。随机数由源节点ID和计数器组成，
+/// 包类型、源、目标和包ID作为附加认证数据。
+pub fn secure_wrap<'b>(
+    context: &mut SecurityContext,
+    packet: &DataPacket,
+    out: &'b mut [u8]
+) -> Result<DataPacket<'b>, SecurityError> {
+    let len = packet.data.len();
+    if len > MAX_SECURE_PAYLOAD || out.len() < len + SECURE_OVERHEAD {
+        return Err(SecurityError::Malformed);
+    }
+    
+    let mut header = packet.header;
+    header.packet_type |= SECURE_FLAG;
+    header.data_length = (len + SECURE_OVERHEAD) as u16;
+    
+    let source = NodeId(header.source);
+    let destination = NodeId(header.destination);
+    let key = context.session_key(source, destination).ok_or(SecurityError::Unprotected)?;
+    
+    let counter = context.tx_counter;
+    context.tx_counter = context.tx_counter.wrapping_add(1);
+    
+    put_u32be(&mut out[0..4], counter);
+    out[4..4 + len].copy_from_slice(packet.data);
+    
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    let tag = cipher
+        .encrypt_in_place_detached(&nonce(source, counter), &associated_data(&header), &mut out[4..4 + len])
+        .map_err(|_| SecurityError::Malformed)?;
+    out[4 + len..4 + len + 16].copy_from_slice(&tag);
+    
+    let mut wrapped = DataPacket { header, data: &out[..len + SECURE_OVERHEAD] };
+    wrapped.update_checksum();
+    Ok(wrapped)
+}
+
+/// 校验并解密一个受保护的包，明文写回data的前部，成功后更新包头并返回明文长度
+pub fn secure_unwrap(
+    context: &mut SecurityContext,
+    header: &mut DataHeader,
+    data: &mut [u8]
+) -> Result<usize, SecurityError> {
+    if header.packet_type & SECURE_FLAG == 0 {
+        return Err(SecurityError::Unprotected);
+    }
+    if data.len() < SECURE_OVERHEAD {
+        return Err(SecurityError::Malformed);
+    }
+    
+    let len = data.len() - SECURE_OVERHEAD;
+    let source = NodeId(header.source);
+    let destination = NodeId(header.destination);
+    let counter = get_u32be(&data[0..4]);
+    let key = context.session_key(source, destination).ok_or(SecurityError::Unprotected)?;
+    
+    let tag = *Tag::from_slice(&data[4 + len..]);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    cipher
+        .decrypt_in_place_detached(&nonce(source, counter), &associated_data(header), &mut data[4..4 + len], &tag)
+        .map_err(|_| SecurityError::AuthFailed)?;
+    
+    // 认证通过后才更新重放窗口，伪造的包不能推进计数器
+    if !context.accept_counter(source, counter) {
+        return Err(SecurityError::Replay);
+    }
+    
+    data.copy_within(4..4 + len, 0);
+    header.packet_type &= !SECURE_FLAG;
+    header.data_length = len as u16;
+    
+    Ok(len)
+}
+
+/// 随机数：源节点ID(6) 计数器(4) 补零(2)
+fn nonce(source: NodeId, counter: u32) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[0..6].copy_from_slice(&source.0);
+    put_u32be(&mut nonce[6..10], counter);
+    *Nonce::from_slice(&nonce)
+}
+
+/// 附加认证数据：类型(1) 源(6) 目标(6) 包ID(2) 流ID(2)
+fn associated_data(header: &DataHeader) -> [u8; 17] {
+    let mut aad = [0u8; 17];
+    aad[0] = header.packet_type | SECURE_FLAG;
+    aad[1..7].copy_from_slice(&header.source);
+    aad[7..13].copy_from_slice(&header.destination);
+    put_u16be(&mut aad[13..15], header.packet_id);
+    put_u16be(&mut aad[15..17], header.flow_id);
+    aad
+}
+
+/// 保护后发送数据包；未配置网络密钥时原样发送
+pub fn send_secure<H: Hardware>(hardware: &mut H, packet: &DataPacket) -> Result<(), SecurityError> {
+    send_secure_to(hardware, NodeId(packet.header.destination), packet)
+}
+
+/// 保护后把数据包发给指定的下一跳，转发时头部保留端到端的源和目标
+pub fn send_secure_to<H: Hardware>(hardware: &mut H, next_hop: NodeId, packet: &DataPacket) -> Result<(), SecurityError> {
+    // 本节点新建的包在这里填入配置的跳数限制，转发的包沿用递减后的跳数
+    let stamped;
+    let packet = if packet.header.ttl == UNSET_TTL {
+        stamped = DataPacket { header: packet.header, data: packet.data }.with_ttl(hardware.default_ttl());
+        &stamped
+    } else {
+        packet
+    };
+    
+    if !hardware.get_security().is_enabled() {
+        return hardware.get_radio().send_data_to(next_hop, packet).map_err(|_| SecurityError::SendFailed);
+    }
+    
+    // 计数器用完预留的一段前先预留下一段，预留失败时不发送以免重启后重复使用随机数
+    let counter = hardware.get_security().tx_counter;
+    if counter >= hardware.get_security().counter_reserved {
+        let reserved = counter.saturating_add(COUNTER_CHECKPOINT);
+        if hardware.get_nvs().nvs_write(keys::SECURITY_COUNTER, &reserved.to_be_bytes()).is_err() {
+            return Err(SecurityError::SendFailed);
+        }
+        hardware.get_security().counter_reserved = reserved;
+    }
+    
+    let mut out = [0u8; MAX_PACKET_SIZE];
+    let wrapped = secure_wrap(hardware.get_security(), packet, &mut out)?;
+    
+    hardware.get_radio().send_data_to(next_hop, &wrapped).map_err(|_| SecurityError::SendFailed)
+}
+
+/// 接收并校验数据包；未配置网络密钥时原样返回，未通过校验的包被丢弃
+///
+/// 所有处理函数收到的包都先经过这里，格式和校验和错误的包不会交给上层。
+pub fn receive_secure<'a, H: Hardware>(hardware: &mut H, buffer: &'a mut [u8]) -> Option<DataPacket<'a>> {
+    let base = buffer.as_ptr() as usize;
+    let (mut header, start, len) = {
+        let packet = hardware.get_radio().receive_data(buffer).ok()??;
+        match packet.validate() {
+            Ok(()) => {},
+            Err(PacketError::Checksum) => {
+                metrics::increment(Counter::ChecksumErrors);
+                return None;
+            },
+            Err(_) => {
+                metrics::increment(Counter::MalformedPackets);
+                return None;
+            },
+        }
+        (packet.header, packet.data.as_ptr() as usize - base, packet.data.len())
+    };
+    
+    let security = hardware.get_security();
+    if !security.is_enabled() {
+        return Some(DataPacket { header, data: &buffer[start..start + len] });
+    }
+    
+    match secure_unwrap(security, &mut header, &mut buffer[start..start + len]) {
+        Ok(plain_len) => {
+            // 计数器越过预留上限时先写入闪存再交给上层，重启后已处理过的包不能被重放
+            if hardware.get_security().needs_checkpoint() && !checkpoint_peers(hardware) {
+                metrics::increment(Counter::SecurityRejected);
+                return None;
+            }
+            
+            let mut packet = DataPacket { header, data: &buffer[start..start + plain_len] };
+            packet.update_checksum();
+            Some(packet)
+        },
+        Err(_) => {
+            metrics::increment(Counter::SecurityRejected);
+            None
+        },
+    }
+}
\ No newline at end of file
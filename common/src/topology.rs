@@ -0,0 +1,140 @@
+use core::fmt;
+
+use crate::protocol::NodeId;
+use crate::protocol::topology::{RouteEdge, TopologyReport};
+
+/// 主节点最多保存的报告节点数
+pub const MAX_TOPOLOGY_NODES: usize = 16;
+
+/// 报告超过该时间未更新视为节点已离开（毫秒）
+pub const TOPOLOGY_MAX_AGE_MS: u64 = 5 * 60 * 1000;
+
+/// 收到的报告及其时间
+#[derive(Clone, Copy)]
+struct ReportEntry {
+    report: TopologyReport,
+    received_at: u64,
+}
+
+/// 主节点汇总的网络拓扑，每个转发节点保留最近一次的报告
+///
+/// 直接邻居表示谁能听到谁，其余路由表示当前生效的转发路径，可以导出为DOT或JSON用于可视化。
+pub struct TopologyMap {
+    reports: [Option<ReportEntry>; MAX_TOPOLOGY_NODES],
+}
+
+impl TopologyMap {
+    /// 创建空的拓扑
+    pub fn new() -> Self {
+        Self {
+            reports: [None; MAX_TOPOLOGY_NODES],
+        }
+    }
+    
+    /// 记录一份报告，替换同一节点的旧报告；已满时替换最旧的报告
+    pub fn update(&mut self, report: &TopologyReport, current_time: u64) {
+        let entry = ReportEntry { report: *report, received_at: current_time };
+        
+        let index = self.reports.iter()
+            .position(|slot| matches!(slot, Some(e) if e.report.reporter == report.reporter))
+            .or_else(|| self.reports.iter().position(|slot| slot.is_none()))
+            .unwrap_or_else(|| self.oldest());
+        self.reports[index] = Some(entry);
+    }
+    
+    /// 移除过期的报告，返回移除数量
+    pub fn expire(&mut self, current_time: u64) -> usize {
+        let mut expired = 0;
+        for slot in self.reports.iter_mut() {
+            if matches!(slot, Some(e) if current_time.saturating_sub(e.received_at) > TOPOLOGY_MAX_AGE_MS) {
+                *slot = None;
+                expired += 1;
+            }
+        }
+        expired
+    }
+    
+    /// 已汇总的报告节点数
+    pub fn len(&self) -> usize {
+        self.reports.iter().flatten().count()
+    }
+    
+    /// 是否还没有任何报告
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// 遍历所有路由：（报告节点，路由）
+    pub fn edges(&self) -> impl Iterator<Item = (NodeId, &RouteEdge)> {
+        self.reports.iter()
+            .flatten()
+            .flat_map(|entry| entry.report.entries().iter().map(move |edge| (entry.report.reporter, edge)))
+    }
+    
+    /// 以Graphviz DOT格式输出
+    ///
+    /// 实线为直接邻居，标注信号强度；虚线为经下一跳到达目的地的路由。
+    pub fn write_dot<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        writeln!(out, "digraph linknebula {{")?;
+        for entry in self.reports.iter().flatten() {
+            writeln!(out, "  \"{}\" [shape=box];", HexId(entry.report.reporter))?;
+        }
+        for (reporter, edge) in self.edges() {
+            if edge.is_neighbor() {
+                writeln!(out, "  \"{}\" -> \"{}\" [label=\"{} dBm\"];",
+                         HexId(reporter), HexId(edge.destination), edge.metric)?;
+            } else {
+                writeln!(out, "  \"{}\" -> \"{}\" [style=dashed, label=\"via {}\"];",
+                         HexId(reporter), HexId(edge.destination), HexId(edge.next_hop))?;
+            }
+        }
+        writeln!(out, "}}")
+    }
+    
+    /// 以JSON格式输出：`{"nodes":[..],"links":[{"from","to","via","metric","neighbor"}]}`
+    pub fn write_json<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        write!(out, "{{\"nodes\":[")?;
+        for (i, entry) in self.reports.iter().flatten().enumerate() {
+            if i > 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "{{\"id\":\"{}\",\"updated_at\":{}}}", HexId(entry.report.reporter), entry.received_at)?;
+        }
+        write!(out, "],\"links\":[")?;
+        for (i, (reporter, edge)) in self.edges().enumerate() {
+            if i > 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "{{\"from\":\"{}\",\"to\":\"{}\",\"via\":\"{}\",\"metric\":{},\"neighbor\":{}}}",
+                   HexId(reporter), HexId(edge.destination), HexId(edge.next_hop), edge.metric, edge.is_neighbor())?;
+        }
+        write!(out, "]}}")
+    }
+    
+    /// 最早收到的报告位置
+    fn oldest(&self) -> usize {
+        let mut index = 0;
+        let mut oldest = u64::MAX;
+        for (i, slot) in self.reports.iter().enumerate() {
+            if let Some(entry) = slot {
+                if entry.received_at < oldest {
+                    oldest = entry.received_at;
+                    index = i;
+                }
+            }
+        }
+        index
+    }
+}
+
+/// 节点ID显示为12位小写十六进制
+struct HexId(NodeId);
+
+impl fmt::Display for HexId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -24,6 +24,51 @@ pub fn verify_checksum(data: &[u8], checksum: u16) -> bool {
     calculate_checksum(data) == checksum
 }
 
+/// 计算CRC-8校验（多项式0x31，初始值0xFF），用于Sensirion等I2C传感器
+pub fn calculate_crc8(data: &[u8]) -> u8 {
+    const POLY: u8 = 0x31;
+    
+    let mut crc: u8 = 0xFF;
+    
+    for byte in data {
+        crc ^= *byte;
+        for _ in 0..8 {
+            if (crc & 0x80) != 0 {
+                crc = (crc << 1) ^ POLY;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+    
+    crc
+}
+
+/// 累加计算CRC-32（IEEE 802.3，反射多项式0xEDB88320），用于分段校验较大的数据
+///
+/// 初始值传入0xFFFFFFFF，全部数据处理完后取反得到结果。
+pub fn update_crc32(mut crc: u32, data: &[u8]) -> u32 {
+    const POLY: u32 = 0xEDB88320;
+    
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            if (crc & 1) != 0 {
+                crc = (crc >> 1) ^ POLY;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    
+    crc
+}
+
+/// 计算CRC-32校验，用于固件镜像
+pub fn calculate_crc32(data: &[u8]) -> u32 {
+    !update_crc32(0xFFFF_FFFF, data)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -46,4 +91,20 @@ mod tests {
         assert!(verify_checksum(&data, checksum));
         assert!(!verify_checksum(&data, checksum + 1));
     }
+    
+    #[test]
+    fn test_crc8() {
+        // SHT3x数据手册中的示例：0xBEEF -> 0x92
+        assert_eq!(calculate_crc8(&[0xBE, 0xEF]), 0x92);
+    }
+    
+    #[test]
+    fn test_crc32() {
+        // 标准测试向量 "123456789" -> 0xCBF43926
+        assert_eq!(calculate_crc32(b"123456789"), 0xCBF43926);
+        
+        // 分段累加与一次计算结果相同
+        let crc = update_crc32(update_crc32(0xFFFF_FFFF, b"1234"), b"56789");
+        assert_eq!(!crc, 0xCBF43926);
+    }
 } 
\ No newline at end of file
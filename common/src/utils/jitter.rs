@@ -0,0 +1,20 @@
+use crate::protocol::NodeId;
+
+/// 生成[0, max)范围内的伪随机抖动，以节点ID和调用方给出的种子为输入，各节点的抖动互不相同
+///
+/// 种子通常取当前时间，需要区分同一时刻的多个事件时再混入事件本身的字段。max为0时返回0。
+pub fn jitter(node_id: NodeId, seed: u32, max: u32) -> u32 {
+    if max == 0 {
+        return 0;
+    }
+    
+    let id = node_id.0;
+    let mut x = seed ^ u32::from_be_bytes([id[2], id[3], id[4], id[5]]);
+    
+    // xorshift32
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    
+    x % max
+}
\ No newline at end of file
@@ -0,0 +1,7 @@
+pub mod aligned_buffer;
+pub mod checksum;
+pub mod jitter;
+
+pub use aligned_buffer::AlignedBuffer;
+pub use checksum::calculate_checksum;
+pub use jitter::jitter;
\ No newline at end of file
@@ -0,0 +1,69 @@
+use common::protocol::{Beacon, NodeId, NodeRole};
+
+/// 去重缓存记录的信标数
+const SEEN_CACHE_SIZE: usize = 16;
+
+/// 去重窗口（毫秒），同一节点同一序号的信标在窗口内只处理一次；
+/// 旧版本节点的序号恒为0，窗口过后才视为新一轮信标
+const DUPLICATE_WINDOW_MS: u64 = 3000;
+
+/// 最近处理过的信标
+#[derive(Debug, Clone, Copy)]
+struct SeenBeacon {
+    source: NodeId,
+    sequence: u8,
+    seen_at: u64,
+}
+
+/// 服务器信标的多跳转发：跳数加一后重新广播，超过最大跳数或重复到达的不再转发，
+/// 使离服务器多跳的节点也能发现服务
+pub struct BeaconRelay {
+    node_id: NodeId,
+    seen: [Option<SeenBeacon>; SEEN_CACHE_SIZE],
+}
+
+impl BeaconRelay {
+    /// 创建信标转发器
+    pub fn new(node_id: NodeId) -> Self {
+        Self {
+            node_id,
+            seen: [None; SEEN_CACHE_SIZE],
+        }
+    }
+    
+    /// 记录收到的信标，返回是否为本轮第一次到达
+    ///
+    /// 重复到达的副本经过的跳数通常更多，调用方应直接丢弃。
+    pub fn accept(&mut self, beacon: &Beacon, now: u64) -> bool {
+        let source = NodeId(beacon.source);
+        if source == self.node_id {
+            return false;
+        }
+        
+        let sequence = beacon.sequence();
+        let duplicate = self.seen.iter().flatten().any(|seen| {
+            seen.source == source && seen.sequence == sequence && now - seen.seen_at < DUPLICATE_WINDOW_MS
+        });
+        if duplicate {
+            return false;
+        }
+        
+        // 同一节点只保留最新一条，缓存满时替换最旧的
+        let slot = self.seen.iter().position(|entry| matches!(entry, Some(seen) if seen.source == source))
+            .or_else(|| self.seen.iter().position(|entry| entry.is_none()))
+            .or_else(|| (0..SEEN_CACHE_SIZE).min_by_key(|&i| self.seen[i].map_or(0, |seen| seen.seen_at)));
+        if let Some(slot) = slot {
+            self.seen[slot] = Some(SeenBeacon { source, sequence, seen_at: now });
+        }
+        
+        true
+    }
+    
+    /// 需要转发的信标副本：只转发服务器信标，已达到最大跳数的不再转发
+    pub fn relay(&self, beacon: &Beacon) -> Option<Beacon> {
+        if beacon.role() != NodeRole::Server {
+            return None;
+        }
+        beacon.relayed()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,197 @@
+use common::channel_plan::{
+    ChannelMigration, ChannelSurvey, CrcSampler, CHANNEL_EVAL_INTERVAL_MS, CHANNEL_REPORT_INTERVAL_MS,
+    SWITCH_GRACE_MS, SWITCH_LEAD_MS,
+};
+use common::config::NodeConfig;
+use common::hal::{Hardware, RadioInterface};
+use common::metrics::{self, Counter};
+use common::protocol::{DataPacket, NodeId};
+use common::protocol::channel::{
+    send_channel_message, ChannelMessage, ChannelReport, CHANNEL_COUNT, ENERGY_UNKNOWN, FIRST_CHANNEL,
+};
+use common::protocol::mgmt::MgmtAttribute;
+use common::{info, warn};
+use crate::routing::RoutingTable;
+use crate::routing::dynamic_forwarding::ForwardingEngine;
+
+/// 信道协调：转发节点定期向主节点报告信道质量，主节点发现干扰时选出新信道并广播切换通告
+///
+/// 通告逐跳扩散，全网在同一网络时间切换；切换后的宽限期内中继在新旧信道之间交替，
+/// 并在旧信道上重发通告，让错过通告的节点也能跟上。
+pub struct ChannelPlanner {
+    /// 主节点汇总的信道质量
+    survey: ChannelSurvey,
+    /// 本节点作为主节点时的切换ID
+    switch_id: u16,
+    /// 最近处理过的通告，每个通告只转发一次
+    last_switch: Option<(NodeId, u16)>,
+    /// 进行中的切换及其通告
+    migration: Option<(ChannelMigration, ChannelMessage)>,
+    crc: CrcSampler,
+    report_timer: u64,
+    eval_timer: u64,
+}
+
+impl ChannelPlanner {
+    /// 创建信道协调代理
+    pub fn new() -> Self {
+        Self {
+            survey: ChannelSurvey::new(),
+            switch_id: 0,
+            last_switch: None,
+            migration: None,
+            crc: CrcSampler::new(),
+            report_timer: 0,
+            eval_timer: 0,
+        }
+    }
+    
+    /// 定期测量和上报；主节点按间隔评估是否换信道；推进进行中的切换
+    pub fn poll<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        forwarding_engine: &ForwardingEngine,
+        config: &mut NodeConfig,
+        master: Option<NodeId>,
+        now: u64,
+        network_now: u64
+    ) {
+        if self.migration.is_some() {
+            self.advance(hardware, config, network_now);
+            return;
+        }
+        
+        let master = match master {
+            Some(master) => master,
+            None => return,
+        };
+        let node_id = hardware.get_node_id();
+        
+        if now - self.report_timer > CHANNEL_REPORT_INTERVAL_MS {
+            self.report_timer = now;
+            let report = self.measure(hardware, master, config.channel);
+            if master == node_id {
+                self.survey.record(&report, now);
+            } else {
+                let next_hop = forwarding_engine.get_next_hop(master).unwrap_or(master);
+                if let Err(e) = send_channel_message(hardware, next_hop, &ChannelMessage::Report(report)) {
+                    warn!("发送信道质量报告失败: {:?}", e);
+                }
+            }
+        }
+        
+        if master == node_id && now - self.eval_timer > CHANNEL_EVAL_INTERVAL_MS {
+            self.eval_timer = now;
+            self.survey.expire(now);
+            if let Some(channel) = self.survey.choose(config.channel) {
+                self.switch_id = self.switch_id.wrapping_add(1);
+                info!("信道 {} 干扰严重，{} 秒后全网切换到信道 {}", config.channel, SWITCH_LEAD_MS / 1000, channel);
+                let announcement = ChannelMessage::Switch {
+                    master: node_id,
+                    switch_id: self.switch_id,
+                    channel,
+                    switch_at: network_now + SWITCH_LEAD_MS,
+                    grace_ms: SWITCH_GRACE_MS,
+                };
+                self.last_switch = Some((node_id, self.switch_id));
+                self.start(hardware, config.channel, announcement);
+            }
+        }
+    }
+    
+    /// 处理信道协调消息
+    pub fn handle<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        forwarding_engine: &mut ForwardingEngine,
+        config: &NodeConfig,
+        packet: &DataPacket,
+        now: u64
+    ) {
+        let node_id = hardware.get_node_id();
+        
+        match ChannelMessage::deserialize(packet.data) {
+            Some(ChannelMessage::Report(report)) => {
+                if report.master == node_id {
+                    self.survey.record(&report, now);
+                } else if NodeId(packet.header.destination) == node_id {
+                    // 本节点是去往主节点的中间一跳
+                    let next_hop = forwarding_engine.get_next_hop(report.master).unwrap_or(report.master);
+                    if let Err(e) = send_channel_message(hardware, next_hop, &ChannelMessage::Report(report)) {
+                        warn!("转发信道质量报告失败: {:?}", e);
+                    }
+                }
+            },
+            Some(announcement @ ChannelMessage::Switch { master, switch_id, channel, .. }) => {
+                if master == node_id || self.last_switch == Some((master, switch_id)) {
+                    return;
+                }
+                self.last_switch = Some((master, switch_id));
+                if channel == config.channel {
+                    return;
+                }
+                info!("主节点 {} 通告切换到信道 {}", master, channel);
+                self.start(hardware, config.channel, announcement);
+            },
+            None => {},
+        }
+    }
+    
+    /// 开始切换并继续广播通告
+    fn start<H: Hardware>(&mut self, hardware: &mut H, current: u8, announcement: ChannelMessage) {
+        if let ChannelMessage::Switch { channel, switch_at, grace_ms, .. } = announcement {
+            self.migration = Some((ChannelMigration::new(current, channel, switch_at, grace_ms), announcement));
+            if let Err(e) = send_channel_message(hardware, NodeId::BROADCAST, &announcement) {
+                warn!("广播信道切换通告失败: {:?}", e);
+            }
+        }
+    }
+    
+    /// 按切换进度调谐无线电，宽限期内每次回到旧信道时重发通告，结束后保存新信道
+    fn advance<H: Hardware>(&mut self, hardware: &mut H, config: &mut NodeConfig, network_now: u64) {
+        let (migration, announcement) = match self.migration.as_mut() {
+            Some(entry) => entry,
+            None => return,
+        };
+        
+        if migration.is_complete(network_now) {
+            let channel = migration.to;
+            self.migration = None;
+            self.survey.clear();
+            // 保存后重启仍在新信道上
+            if config.set(hardware, MgmtAttribute::Channel, &[channel]).is_err() {
+                warn!("切换到信道 {} 失败", channel);
+            } else {
+                info!("已切换到信道 {}", channel);
+                metrics::increment(Counter::ChannelSwitches);
+            }
+            return;
+        }
+        
+        let channel = migration.tune(network_now);
+        let revisit_old = migration.in_grace(network_now) && channel == migration.from;
+        let announcement = *announcement;
+        let _ = hardware.get_radio().configure(channel, config.tx_power);
+        if revisit_old {
+            if let Err(e) = send_channel_message(hardware, NodeId::BROADCAST, &announcement) {
+                warn!("在旧信道上重发切换通告失败: {:?}", e);
+            }
+        }
+    }
+    
+    /// 测量当前信道的CRC错误率和各信道的能量，测量完回到当前信道
+    fn measure<H: Hardware>(&mut self, hardware: &mut H, master: NodeId, current: u8) -> ChannelReport {
+        let node_id = hardware.get_node_id();
+        let radio = hardware.get_radio();
+        let crc_permille = self.crc.sample(&radio.diagnostics(), metrics::get(Counter::RadioRx));
+        
+        let mut energy = [ENERGY_UNKNOWN; CHANNEL_COUNT];
+        for (offset, value) in energy.iter_mut().enumerate() {
+            if let Some(measured) = radio.energy_detect(FIRST_CHANNEL + offset as u8) {
+                *value = measured.max(ENERGY_UNKNOWN + 1);
+            }
+        }
+        
+        ChannelReport { master, reporter: node_id, channel: current, crc_permille, energy }
+    }
+}
\ No newline at end of file
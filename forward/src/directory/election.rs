@@ -1,20 +1,57 @@
-use common::protocol::{NodeId, DataPacket};
+use core::cmp::Ordering;
+use common::protocol::{NodeId, DataPacket, PacketType};
+use common::protocol::election::{Candidacy, ElectionMessage};
+use common::clock::TIME_BEACON_INTERVAL_MS;
 use common::hal::Hardware;
+use common::metrics::{self, Counter};
+use common::security::{receive_secure, send_secure};
 use common::utils::AlignedBuffer;
-use crate::directory::ServiceType;
+use common::{info, warn};
+use crate::routing::flooding::FloodRelay;
 
-/// 选举协议消息类型
+/// 发起选举后收集回应的时间（毫秒）
+pub const ELECTION_WINDOW_MS: u64 = 5000;
+
+/// 多久没有听到主节点的信标视为主节点失效，重新选举（毫秒）
+pub const MASTER_TIMEOUT_MS: u64 = 3 * TIME_BEACON_INTERVAL_MS;
+
+/// 一次选举最多记录的候选节点数，包括本节点
+const MAX_CANDIDATES: usize = 16;
+
+/// 选举中的一个候选节点
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ElectionMessageType {
-    /// 开始选举
-    ElectionStart = 0x01,
-    /// 竞选回应
-    ElectionResponse = 0x02,
-    /// 选举结果广播
-    ElectionResult = 0x03,
+pub struct Candidate {
+    pub node_id: NodeId,
+    pub candidacy: Candidacy,
+}
+
+impl Candidate {
+    /// 比较两个候选节点：依次比较优先级、电池电量和负载，都相同时节点ID较小者胜出
+    pub fn rank(&self, other: &Candidate) -> Ordering {
+        self.candidacy.priority.cmp(&other.candidacy.priority)
+            .then(self.candidacy.battery_level.cmp(&other.candidacy.battery_level))
+            .then(other.candidacy.load.cmp(&self.candidacy.load))
+            .then(other.node_id.0.cmp(&self.node_id.0))
+    }
+}
+
+/// 解析选举消息，截断、类型未知或不是应用数据的包直接忽略
+fn parse_message(packet: &DataPacket) -> Option<(NodeId, ElectionMessage)> {
+    if packet.header.packet_type != PacketType::Data as u8 {
+        return None;
+    }
+    ElectionMessage::deserialize(packet.data).map(|message| (NodeId(packet.header.source), message))
+}
+
+/// 从收集到的候选节点中选出主服务器
+pub fn elect<I: IntoIterator<Item = Candidate>>(candidates: I) -> Option<Candidate> {
+    candidates.into_iter().max_by(|a, b| a.rank(b))
 }
 
 /// 主服务器选举协议实现
+///
+/// 发起方泛洪自身条件，其他节点都回应自身条件；收集窗口结束后发起方选出最优节点并泛洪结果。
+/// 跟随的主节点长时间没有信标时重新选举。
 pub struct ElectionProtocol {
     /// 本节点ID
     node_id: NodeId,
@@ -24,6 +61,17 @@ pub struct ElectionProtocol {
     state: ElectionState,
     /// 当前主服务器
     current_master: Option<NodeId>,
+    /// 本轮选举收集到的候选节点
+    candidates: [Option<Candidate>; MAX_CANDIDATES],
+    /// 本轮选举结束收集的时间
+    deadline: u64,
+    /// 最近一次听到主节点信标的时间
+    master_heard_at: u64,
+    /// 本节点的电池电量和负载
+    battery_level: u8,
+    load: u8,
+    /// 本节点能否当选，不能当选时不作为候选者，只跟随选举结果
+    eligible: bool,
     /// 接收缓冲区
     buffer: AlignedBuffer<256>,
 }
@@ -47,186 +95,240 @@ impl ElectionProtocol {
             election_id: 0,
             state: ElectionState::Idle,
             current_master: None,
+            candidates: [None; MAX_CANDIDATES],
+            deadline: 0,
+            master_heard_at: 0,
+            battery_level: 100,
+            load: 0,
+            eligible: true,
             buffer: AlignedBuffer::new(),
         }
     }
     
-    /// 发起选举
-    pub fn initiate_election<H: Hardware>(&mut self, hardware: &mut H) {
-        println!("发起主服务器选举");
+    /// 更新本节点在选举中声明的电池电量和负载
+    pub fn set_condition(&mut self, battery_level: u8, load: u8) {
+        self.battery_level = battery_level;
+        self.load = load;
+    }
+    
+    /// 设置本节点能否当选主节点，不带服务目录的中继无法回答目录查询，不能当选
+    pub fn set_eligible(&mut self, eligible: bool) {
+        self.eligible = eligible;
+    }
+    
+    /// 本节点在选举中声明的条件
+    pub fn candidacy(&self) -> Candidacy {
+        Candidacy {
+            priority: self.get_priority(),
+            battery_level: self.battery_level,
+            load: self.load,
+        }
+    }
+    
+    /// 发起选举，之后由[`ElectionProtocol::poll`]在收集窗口结束时决定结果
+    pub fn initiate_election<H: Hardware>(&mut self, hardware: &mut H, floods: &mut FloodRelay, now: u64) {
+        info!("发起主服务器选举");
+        metrics::increment(Counter::ElectionsStarted);
         
-        // 增加选举ID
+        // 增加选举ID，本节点是第一个候选者
         self.election_id = self.election_id.wrapping_add(1);
         self.state = ElectionState::Electing;
+        self.deadline = now + ELECTION_WINDOW_MS;
+        self.candidates = [None; MAX_CANDIDATES];
+        if self.eligible {
+            self.record(Candidate { node_id: self.node_id, candidacy: self.candidacy() });
+        }
         
         // 创建选举消息
-        let mut election_msg = [0u8; 4];
-        election_msg[0] = ElectionMessageType::ElectionStart as u8;
-        election_msg[1] = (self.election_id >> 8) as u8;
-        election_msg[2] = (self.election_id & 0xFF) as u8;
-        election_msg[3] = self.get_priority();
+        let mut election_msg = [0u8; 6];
+        let len = ElectionMessage::Start {
+            election_id: self.election_id,
+            candidacy: self.candidacy(),
+        }.serialize(&mut election_msg);
         
-        // 广播选举消息
-        let packet = DataPacket::new(
-            self.node_id,
-            NodeId::BROADCAST,
-            self.election_id,
-            &election_msg
-        );
-        
-        let radio = hardware.get_radio();
-        if let Err(e) = radio.send_data(&packet) {
-            println!("发送选举消息失败: {:?}", e);
+        // 泛洪选举消息，不相邻的节点也能参与
+        if let Err(e) = floods.originate(hardware, PacketType::Data, &election_msg[..len], now) {
+            warn!("发送选举消息失败: {:?}", e);
         }
-        
-        // 等待一段时间收集响应
-        let _ = hardware.delay_ms(5000);
-        
-        // 结束选举并广播结果
-        self.finish_election(hardware);
     }
     
-    /// 结束选举并广播结果
-    fn finish_election<H: Hardware>(&mut self, hardware: &mut H) {
-        // 这里应该根据收集到的响应确定最佳主服务器
-        // 简化实现：假设自己是主服务器
-        self.current_master = Some(self.node_id);
-        self.state = ElectionState::Completed;
-        
-        // 广播选举结果
-        let mut result_msg = [0u8; 10];
-        result_msg[0] = ElectionMessageType::ElectionResult as u8;
-        result_msg[1] = (self.election_id >> 8) as u8;
-        result_msg[2] = (self.election_id & 0xFF) as u8;
-        
-        // 复制主服务器节点ID
-        if let Some(master) = self.current_master {
-            result_msg[3..9].copy_from_slice(&master.0);
+    /// 收集窗口结束时决定选举结果；跟随的主节点失效时重新选举
+    pub fn poll<H: Hardware>(&mut self, hardware: &mut H, floods: &mut FloodRelay, now: u64) {
+        match self.state {
+            ElectionState::Electing if now >= self.deadline => self.finish_election(hardware, floods, now),
+            ElectionState::Completed => {
+                let following = self.current_master.map_or(false, |master| master != self.node_id);
+                if following && now.saturating_sub(self.master_heard_at) > MASTER_TIMEOUT_MS {
+                    warn!("长时间没有主节点 {:?} 的信标，重新选举", self.current_master);
+                    self.initiate_election(hardware, floods, now);
+                }
+            },
+            _ => {},
+        }
+    }
+    
+    /// 听到某节点的信标，是当前主节点时刷新其存活时间
+    pub fn observe_beacon(&mut self, source: NodeId, now: u64) {
+        if self.current_master == Some(source) {
+            self.master_heard_at = now;
         }
+    }
+    
+    /// 记录候选节点，同一节点只保留最新的条件；已满时丢弃
+    fn record(&mut self, candidate: Candidate) {
+        let slot = self.candidates.iter()
+            .position(|entry| matches!(entry, Some(c) if c.node_id == candidate.node_id))
+            .or_else(|| self.candidates.iter().position(|entry| entry.is_none()));
+        match slot {
+            Some(index) => self.candidates[index] = Some(candidate),
+            None => warn!("候选节点已满，忽略 {}", candidate.node_id),
+        }
+    }
+    
+    /// 本轮选举收集到的候选节点
+    pub fn candidates(&self) -> impl Iterator<Item = &Candidate> {
+        self.candidates.iter().flatten()
+    }
+    
+    /// 结束选举并泛洪结果
+    fn finish_election<H: Hardware>(&mut self, hardware: &mut H, floods: &mut FloodRelay, now: u64) {
+        let count = self.candidates().count();
+        let winner = match elect(self.candidates().copied()) {
+            Some(winner) => winner.node_id,
+            None if self.eligible => self.node_id,
+            None => {
+                // 没有候选者时保持原来的主节点，等下一轮选举
+                warn!("没有可以当选的候选节点");
+                self.state = ElectionState::Idle;
+                return;
+            },
+        };
         
-        // 广播结果
-        let packet = DataPacket::new(
-            self.node_id,
-            NodeId::BROADCAST,
-            self.election_id,
-            &result_msg
-        );
+        self.set_master(winner, now);
+        self.state = ElectionState::Completed;
         
-        let radio = hardware.get_radio();
-        if let Err(e) = radio.send_data(&packet) {
-            println!("发送选举结果失败: {:?}", e);
+        // 泛洪选举结果
+        let mut result_msg = [0u8; 9];
+        let len = ElectionMessage::Result {
+            election_id: self.election_id,
+            master: winner,
+        }.serialize(&mut result_msg);
+        
+        if let Err(e) = floods.originate(hardware, PacketType::Data, &result_msg[..len], now) {
+            warn!("发送选举结果失败: {:?}", e);
         } else {
-            println!("选举完成，主服务器: {:?}", self.current_master);
+            info!("选举完成，{} 个候选节点，主服务器: {}", count, winner);
         }
     }
     
     /// 处理选举消息
-    pub fn process_messages<H: Hardware>(&mut self, hardware: &mut H) {
-        let radio = hardware.get_radio();
+    pub fn process_messages<H: Hardware>(&mut self, hardware: &mut H, now: u64) {
         let buffer = self.buffer.as_mut_slice();
         
-        if let Ok(Some(packet)) = radio.receive_data(buffer) {
-            // 确保数据包至少有一个字节
-            if packet.data.is_empty() {
-                return;
-            }
-            
-            match packet.data[0] {
-                x if x == ElectionMessageType::ElectionStart as u8 => {
-                    self.handle_election_start(hardware, &packet);
-                },
-                x if x == ElectionMessageType::ElectionResponse as u8 => {
-                    self.handle_election_response(hardware, &packet);
-                },
-                x if x == ElectionMessageType::ElectionResult as u8 => {
-                    self.handle_election_result(hardware, &packet);
-                },
-                _ => {
-                    // 忽略未知消息类型
-                }
-            }
+        let message = match receive_secure(hardware, buffer) {
+            Some(packet) => parse_message(&packet),
+            None => None,
+        };
+        
+        self.handle_message(hardware, message, now);
+    }
+    
+    /// 处理经泛洪到达的选举消息，源地址为发起节点
+    pub fn handle_packet<H: Hardware>(&mut self, hardware: &mut H, packet: &DataPacket, now: u64) {
+        let message = parse_message(packet);
+        self.handle_message(hardware, message, now);
+    }
+    
+    /// 按消息类型分别处理
+    fn handle_message<H: Hardware>(&mut self, hardware: &mut H, message: Option<(NodeId, ElectionMessage)>, now: u64) {
+        match message {
+            Some((source, ElectionMessage::Start { election_id, candidacy })) => {
+                self.handle_election_start(hardware, source, election_id, candidacy);
+            },
+            Some((source, ElectionMessage::Response { election_id, candidacy })) => {
+                self.handle_election_response(source, election_id, candidacy);
+            },
+            Some((_, ElectionMessage::Result { election_id, master })) => {
+                self.handle_election_result(election_id, master, now);
+            },
+            None => {}
         }
     }
     
     /// 处理选举启动消息
-    fn handle_election_start<H: Hardware>(&mut self, hardware: &mut H, packet: &DataPacket) {
-        if packet.data.len() < 4 {
-            return; // 消息格式错误
+    fn handle_election_start<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        source: NodeId,
+        election_id: u16,
+        candidacy: Candidacy
+    ) {
+        info!("收到来自 {} 的选举消息，选举ID: {}", source, election_id);
+        
+        // 自己也在选举时，发起方同样是候选者；否则加入这一轮选举，只接受它的结果
+        if self.state == ElectionState::Electing {
+            self.record(Candidate { node_id: source, candidacy });
+        } else {
+            self.election_id = election_id;
         }
         
-        // 提取选举ID
-        let election_id = ((packet.data[1] as u16) << 8) | (packet.data[2] as u16);
-        let sender_priority = packet.data[3];
-        let source = NodeId(packet.header.source);
+        // 回应自身条件，由发起方统一比较；不能当选的节点不回应
+        if !self.eligible {
+            return;
+        }
+        let mut response = [0u8; 6];
+        let len = ElectionMessage::Response {
+            election_id,
+            candidacy: self.candidacy(),
+        }.serialize(&mut response);
         
-        println!("收到来自 {:?} 的选举消息，选举ID: {}", source, election_id);
+        let response_packet = DataPacket::new(
+            self.node_id,
+            source,
+            election_id,
+            &response[..len]
+        );
         
-        // 如果发送方优先级高于自己，只发送响应
-        if sender_priority > self.get_priority() {
-            // 发送选举响应
-            let mut response = [0u8; 4];
-            response[0] = ElectionMessageType::ElectionResponse as u8;
-            response[1] = packet.data[1]; // 选举ID高字节
-            response[2] = packet.data[2]; // 选举ID低字节
-            response[3] = self.get_priority();
-            
-            let response_packet = DataPacket::new(
-                self.node_id,
-                source,
-                election_id,
-                &response
-            );
-            
-            let radio = hardware.get_radio();
-            if let Err(e) = radio.send_data(&response_packet) {
-                println!("发送选举响应失败: {:?}", e);
-            }
-        } else {
-            // 如果自己优先级更高，发起新一轮选举
-            if self.state != ElectionState::Electing {
-                self.initiate_election(hardware);
-            }
+        if let Err(e) = send_secure(hardware, &response_packet) {
+            warn!("发送选举响应失败: {:?}", e);
         }
     }
     
     /// 处理选举响应消息
-    fn handle_election_response<H: Hardware>(&mut self, hardware: &mut H, packet: &DataPacket) {
-        if packet.data.len() < 4 || self.state != ElectionState::Electing {
-            return; // 消息格式错误或当前不在选举状态
-        }
-        
-        // 提取选举ID
-        let election_id = ((packet.data[1] as u16) << 8) | (packet.data[2] as u16);
-        
-        // 检查是否是当前选举
-        if election_id != self.election_id {
+    fn handle_election_response(&mut self, source: NodeId, election_id: u16, candidacy: Candidacy) {
+        // 检查是否在选举中且是当前选举
+        if self.state != ElectionState::Electing || election_id != self.election_id {
             return;
         }
         
-        // 实际实现中，这里应该记录所有响应，用于后续确定最佳主服务器
-        println!("收到来自 {:?} 的选举响应", NodeId(packet.header.source));
+        info!("收到来自 {} 的选举响应", source);
+        self.record(Candidate { node_id: source, candidacy });
     }
     
     /// 处理选举结果消息
-    fn handle_election_result<H: Hardware>(&mut self, hardware: &mut H, packet: &DataPacket) {
-        if packet.data.len() < 9 {
-            return; // 消息格式错误
+    fn handle_election_result(&mut self, election_id: u16, master_id: NodeId, now: u64) {
+        // 不是当前这一轮的结果可能是过时或重放的消息
+        if election_id != self.election_id {
+            warn!("忽略选举ID为 {} 的结果，当前选举ID: {}", election_id, self.election_id);
+            return;
         }
         
-        // 提取选举ID和主服务器ID
-        let election_id = ((packet.data[1] as u16) << 8) | (packet.data[2] as u16);
-        let master_id = NodeId([
-            packet.data[3], packet.data[4], packet.data[5],
-            packet.data[6], packet.data[7], packet.data[8]
-        ]);
-        
-        println!("收到选举结果，主服务器为: {:?}", master_id);
+        info!("收到选举结果，主服务器为: {}", master_id);
         
         // 更新主服务器
-        self.current_master = Some(master_id);
+        self.set_master(master_id, now);
         self.state = ElectionState::Completed;
     }
     
+    /// 记录新的主服务器，统计主节点变更
+    fn set_master(&mut self, master_id: NodeId, now: u64) {
+        if self.current_master != Some(master_id) {
+            metrics::increment(Counter::MasterChanges);
+        }
+        self.current_master = Some(master_id);
+        self.master_heard_at = now;
+    }
+    
     /// 获取本节点优先级
     fn get_priority(&self) -> u8 {
         // 简化实现：使用节点ID的第一个字节作为优先级
@@ -237,4 +339,4 @@ impl ElectionProtocol {
     pub fn get_master(&self) -> Option<NodeId> {
         self.current_master
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
@@ -0,0 +1,88 @@
+use common::hal::Hardware;
+use common::protocol::{DataPacket, NodeId};
+use common::protocol::service_advert::{send_service_advert, ServiceAdvertisement};
+use common::{info, warn};
+use crate::directory::service_directory::NetworkServiceDirectory;
+
+/// 定期向邻居同步服务目录的间隔（毫秒）
+pub const GOSSIP_INTERVAL_MS: u64 = 30_000;
+
+/// 学到新服务后提前同步，两次同步之间的最短间隔（毫秒）
+pub const MIN_TRIGGERED_GOSSIP_MS: u64 = 1000;
+
+/// 转发节点之间的服务目录同步
+///
+/// 各转发节点定期把目录中的服务广播给邻居，邻居合并后在下一轮继续广播，
+/// 连在其他转发节点上的客户端也能找到远处的服务器。
+/// 目录代理不保存完整目录，不参与同步。
+pub struct DirectoryGossip {
+    last_sent: Option<u64>,
+    /// 学到了新服务，下一轮尽快同步
+    triggered: bool,
+}
+
+impl DirectoryGossip {
+    /// 创建同步状态，第一次轮询时立即同步
+    pub fn new() -> Self {
+        Self {
+            last_sent: None,
+            triggered: false,
+        }
+    }
+    
+    /// 到达同步间隔或学到新服务时广播目录，返回发出的通告数
+    pub fn poll<H: Hardware>(&mut self, hardware: &mut H, directory: &NetworkServiceDirectory, now: u64) -> usize {
+        let due = match self.last_sent {
+            None => true,
+            Some(last_sent) => {
+                let elapsed = now.saturating_sub(last_sent);
+                elapsed > GOSSIP_INTERVAL_MS || (self.triggered && elapsed > MIN_TRIGGERED_GOSSIP_MS)
+            },
+        };
+        if !due {
+            return 0;
+        }
+        self.last_sent = Some(now);
+        self.triggered = false;
+        
+        // 超出单个通告容量时分多个通告发送
+        let mut sent = 0;
+        let mut advert = ServiceAdvertisement::new();
+        for service in directory.advertised_services() {
+            if !advert.push(service) {
+                sent += send(hardware, &advert);
+                advert = ServiceAdvertisement::new();
+                advert.push(service);
+            }
+        }
+        if !advert.is_empty() {
+            sent += send(hardware, &advert);
+        }
+        sent
+    }
+    
+    /// 合并邻居的服务通告，返回新增或更新的服务数；时间为网络时间
+    pub fn handle(&mut self, directory: &mut NetworkServiceDirectory, packet: &DataPacket, network_now: u64) -> usize {
+        let advert = match ServiceAdvertisement::deserialize(packet.data) {
+            Some(advert) => advert,
+            None => return 0,
+        };
+        
+        let learned = directory.learn_services(advert.entries(), network_now);
+        if learned > 0 {
+            info!("从 {} 的服务通告学到 {} 个服务", NodeId(packet.header.source), learned);
+            self.triggered = true;
+        }
+        learned
+    }
+}
+
+fn send<H: Hardware>(hardware: &mut H, advert: &ServiceAdvertisement) -> usize {
+    match send_service_advert(hardware, advert) {
+        Ok(()) => 1,
+        Err(e) => {
+            warn!("发送服务通告失败: {:?}", e);
+            0
+        },
+    }
+}
\ No newline at end of file
@@ -0,0 +1,167 @@
+use common::metrics::{self, Counter, Gauge};
+use common::protocol::{NodeId, ServiceType};
+use common::info;
+
+/// 最多同时跟踪的服务租约数
+const MAX_LEASES: usize = 16;
+
+/// 已分配给客户端的服务租约
+#[derive(Debug, Clone, Copy)]
+pub struct ServiceLease {
+    /// 服务ID
+    pub service_id: u32,
+    /// 客户端节点
+    pub client: NodeId,
+    /// 提供服务的节点
+    pub server: NodeId,
+    /// 服务类型
+    pub service_type: ServiceType,
+    /// 分配租约的服务请求ID，0表示不关联请求
+    pub request_id: u16,
+    /// 租约到期时间
+    pub expires_at: u64,
+}
+
+/// 服务租约表，到期未续期的服务会被回收
+pub struct LeaseTable {
+    leases: [Option<ServiceLease>; MAX_LEASES],
+}
+
+impl LeaseTable {
+    /// 创建空的租约表
+    pub fn new() -> Self {
+        Self {
+            leases: [None; MAX_LEASES],
+        }
+    }
+    
+    /// 分配新租约，租约表已满时替换最早到期的租约
+    pub fn grant(
+        &mut self,
+        service_id: u32,
+        client: NodeId,
+        server: NodeId,
+        service_type: ServiceType,
+        request_id: u16,
+        expiry_secs: u32,
+        current_time: u64
+    ) {
+        let lease = ServiceLease {
+            service_id,
+            client,
+            server,
+            service_type,
+            request_id,
+            expires_at: current_time + expiry_secs as u64 * 1000,
+        };
+        
+        let index = self.leases.iter().position(|entry| entry.is_none())
+            .unwrap_or_else(|| self.earliest_expiry());
+        self.leases[index] = Some(lease);
+        
+        metrics::increment(Counter::LeasesGranted);
+        metrics::set(Gauge::ActiveLeases, self.len() as u32);
+    }
+    
+    /// 客户端以该请求ID请求同类服务分配到的租约，客户端没有收到响应时会以同一请求ID重传请求
+    pub fn find_request(&self, client: NodeId, request_id: u16, service_type: ServiceType) -> Option<ServiceLease> {
+        if request_id == 0 {
+            return None;
+        }
+        self.leases.iter().flatten()
+            .find(|lease| lease.client == client && lease.request_id == request_id && lease.service_type == service_type)
+            .copied()
+    }
+    
+    /// 续期租约，只有原客户端可以续期，成功返回续期后的租约
+    pub fn renew(
+        &mut self,
+        service_id: u32,
+        client: NodeId,
+        expiry_secs: u32,
+        current_time: u64
+    ) -> Option<ServiceLease> {
+        for entry in self.leases.iter_mut().flatten() {
+            if entry.service_id == service_id && entry.client == client {
+                entry.expires_at = current_time + expiry_secs as u64 * 1000;
+                return Some(*entry);
+            }
+        }
+        None
+    }
+    
+    /// 释放租约
+    pub fn release(&mut self, service_id: u32) -> Option<ServiceLease> {
+        for entry in self.leases.iter_mut() {
+            if let Some(lease) = entry {
+                if lease.service_id == service_id {
+                    let released = *lease;
+                    *entry = None;
+                    metrics::set(Gauge::ActiveLeases, self.len() as u32);
+                    return Some(released);
+                }
+            }
+        }
+        None
+    }
+    
+    /// 回收到期的租约，返回回收数量
+    pub fn expire(&mut self, current_time: u64) -> usize {
+        let mut expired = 0;
+        for entry in self.leases.iter_mut() {
+            if let Some(lease) = entry {
+                if current_time >= lease.expires_at {
+                    info!("服务 {} 的租约已到期，客户端: {}", lease.service_id, lease.client);
+                    *entry = None;
+                    expired += 1;
+                }
+            }
+        }
+        
+        metrics::add(Counter::LeasesExpired, expired as u32);
+        metrics::set(Gauge::ActiveLeases, self.len() as u32);
+        expired
+    }
+    
+    /// 网络时钟跳变后平移所有到期时间，保持各租约的剩余时间不变
+    pub fn shift(&mut self, delta_ms: i64) {
+        for lease in self.leases.iter_mut().flatten() {
+            lease.expires_at = (lease.expires_at as i64).saturating_add(delta_ms).max(0) as u64;
+        }
+    }
+    
+    /// 遍历所有有效租约
+    pub fn iter(&self) -> impl Iterator<Item = &ServiceLease> {
+        self.leases.iter().flatten()
+    }
+    
+    /// 由服务器承载的该类型服务的有效租约数
+    pub fn sessions(&self, server: NodeId, service_type: ServiceType) -> usize {
+        self.iter().filter(|lease| lease.server == server && lease.service_type == service_type).count()
+    }
+    
+    /// 当前有效租约数
+    pub fn len(&self) -> usize {
+        self.leases.iter().flatten().count()
+    }
+    
+    /// 租约表占用比例（0-100%），作为本节点在选举中声明的负载
+    pub fn load(&self) -> u8 {
+        (self.len() * 100 / MAX_LEASES) as u8
+    }
+    
+    /// 最早到期的租约位置
+    fn earliest_expiry(&self) -> usize {
+        let mut index = 0;
+        let mut earliest = u64::MAX;
+        for (i, entry) in self.leases.iter().enumerate() {
+            if let Some(lease) = entry {
+                if lease.expires_at < earliest {
+                    earliest = lease.expires_at;
+                    index = i;
+                }
+            }
+        }
+        index
+    }
+}
\ No newline at end of file
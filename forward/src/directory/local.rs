@@ -0,0 +1,65 @@
+use common::config::NodeConfig;
+use crate::directory::service_directory::NetworkServiceDirectory;
+
+/// 本节点的服务目录
+///
+/// 以`directory-proxy`特性构建的中继不带服务目录，这里不占用内存：服务请求都交给主节点查询，
+/// 本节点也不参加主节点竞选。完整构建的中继按配置决定是否维护目录，当选主节点时总是维护。
+pub struct LocalDirectory {
+    #[cfg(not(feature = "directory-proxy"))]
+    directory: Option<NetworkServiceDirectory>,
+}
+
+#[cfg(not(feature = "directory-proxy"))]
+impl LocalDirectory {
+    /// 构建时是否带服务目录
+    pub const AVAILABLE: bool = true;
+    
+    /// 按配置创建，目录代理模式下不维护目录
+    pub fn new(config: &NodeConfig) -> Self {
+        Self {
+            directory: (!config.directory_proxy).then(|| NetworkServiceDirectory::from_config(config)),
+        }
+    }
+    
+    /// 按需要创建或释放目录，有变化时返回true
+    pub fn keep(&mut self, keep: bool, config: &NodeConfig) -> bool {
+        match (keep, self.directory.is_some()) {
+            (true, false) => self.directory = Some(NetworkServiceDirectory::from_config(config)),
+            (false, true) => self.directory = None,
+            _ => return false,
+        }
+        true
+    }
+    
+    pub fn get(&self) -> Option<&NetworkServiceDirectory> {
+        self.directory.as_ref()
+    }
+    
+    pub fn get_mut(&mut self) -> Option<&mut NetworkServiceDirectory> {
+        self.directory.as_mut()
+    }
+}
+
+#[cfg(feature = "directory-proxy")]
+impl LocalDirectory {
+    /// 构建时是否带服务目录
+    pub const AVAILABLE: bool = false;
+    
+    pub fn new(_config: &NodeConfig) -> Self {
+        Self {}
+    }
+    
+    /// 不带目录的构建无法维护目录
+    pub fn keep(&mut self, _keep: bool, _config: &NodeConfig) -> bool {
+        false
+    }
+    
+    pub fn get(&self) -> Option<&NetworkServiceDirectory> {
+        None
+    }
+    
+    pub fn get_mut(&mut self) -> Option<&mut NetworkServiceDirectory> {
+        None
+    }
+}
\ No newline at end of file
@@ -1,4 +1,8 @@
 pub mod election;
+pub mod gossip;
+pub mod lease_table;
+pub mod local;
+pub mod proxy;
 pub mod service_directory;
 
 use common::protocol::NodeId;
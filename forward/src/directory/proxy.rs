@@ -0,0 +1,168 @@
+use common::protocol::{NodeId, ServiceRequest};
+use common::info;
+
+/// 缓存的查询结果数
+const CACHE_SIZE: usize = 8;
+
+/// 同时等待主节点应答的服务请求数
+const MAX_PENDING: usize = 4;
+
+/// 等待主节点应答的时间（毫秒），超时后回复客户端失败，由客户端重试
+pub const LOOKUP_TIMEOUT_MS: u64 = 3000;
+
+/// 主节点应答的有效期（秒），过期后重新查询，使各中继的结果与主节点的目录保持一致
+pub const ANSWER_TTL_SECS: u16 = 30;
+
+/// 缓存的主节点查询结果，按服务类型和QoS要求区分
+#[derive(Debug, Clone, Copy)]
+struct CachedAnswer {
+    request: ServiceRequest,
+    /// None表示主节点没有满足要求的服务器
+    server: Option<NodeId>,
+    expires_at: u64,
+}
+
+/// 客户端的服务请求
+#[derive(Debug, Clone, Copy)]
+pub struct ServiceQuery {
+    /// 发出请求的客户端
+    pub client: NodeId,
+    /// 客户端请求的包ID，响应沿用
+    pub packet_id: u16,
+    /// 客户端的原始请求
+    pub request: ServiceRequest,
+}
+
+/// 等待主节点应答的服务请求
+#[derive(Debug, Clone, Copy)]
+struct PendingLookup {
+    query: ServiceQuery,
+    sent_at: u64,
+}
+
+/// 服务请求暂缓的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deferred {
+    /// 需要向主节点发送查询
+    Query,
+    /// 相同的查询已在等待应答，不必重复发送
+    Waiting,
+    /// 等待队列已满
+    Full,
+}
+
+/// 代理服务目录
+///
+/// 内存较小的中继不保存完整服务目录，服务请求交给主节点查询，应答按有效期缓存。
+/// 主节点变化时缓存全部作废，避免不同中继给出不一致的结果。
+pub struct DirectoryProxy {
+    cache: [Option<CachedAnswer>; CACHE_SIZE],
+    pending: [Option<PendingLookup>; MAX_PENDING],
+    master: Option<NodeId>,
+}
+
+/// 两个请求是否可以共用同一个查询结果
+fn same_query(a: &ServiceRequest, b: &ServiceRequest) -> bool {
+    a.service_type == b.service_type && a.qos == b.qos
+}
+
+impl DirectoryProxy {
+    /// 创建空的代理目录
+    pub fn new() -> Self {
+        Self {
+            cache: [None; CACHE_SIZE],
+            pending: [None; MAX_PENDING],
+            master: None,
+        }
+    }
+    
+    /// 当前主节点
+    pub fn master(&self) -> Option<NodeId> {
+        self.master
+    }
+    
+    /// 跟随当前主节点，主节点变化时清空缓存
+    pub fn set_master(&mut self, master: Option<NodeId>) {
+        if self.master != master {
+            if self.master.is_some() {
+                info!("主节点变为 {:?}，清空目录查询缓存", master);
+            }
+            self.cache = [None; CACHE_SIZE];
+            self.master = master;
+        }
+    }
+    
+    /// 缓存中未过期的结果，外层None表示需要查询主节点
+    pub fn lookup(&self, request: &ServiceRequest, current_time: u64) -> Option<Option<NodeId>> {
+        self.cache.iter()
+            .flatten()
+            .find(|entry| same_query(&entry.request, request) && current_time < entry.expires_at)
+            .map(|entry| entry.server)
+    }
+    
+    /// 暂存等待主节点应答的服务请求
+    pub fn defer(&mut self, query: &ServiceQuery, current_time: u64) -> Deferred {
+        let waiting = self.pending.iter().flatten().any(|pending| same_query(&pending.query.request, &query.request));
+        
+        let slot = match self.pending.iter_mut().find(|entry| entry.is_none()) {
+            Some(slot) => slot,
+            None => return Deferred::Full,
+        };
+        *slot = Some(PendingLookup { query: *query, sent_at: current_time });
+        
+        if waiting { Deferred::Waiting } else { Deferred::Query }
+    }
+    
+    /// 收到主节点的应答：缓存结果，并对所有等待同一查询的请求调用回调
+    pub fn complete<F: FnMut(&ServiceQuery, Option<NodeId>)>(
+        &mut self,
+        request: &ServiceRequest,
+        server: Option<NodeId>,
+        ttl_secs: u16,
+        current_time: u64,
+        mut on_answer: F
+    ) {
+        let answer = CachedAnswer {
+            request: *request,
+            server,
+            expires_at: current_time + ttl_secs as u64 * 1000,
+        };
+        
+        // 替换同一查询的旧结果，否则占用空位或最早过期的位置
+        let index = self.cache.iter().position(|entry| matches!(entry, Some(cached) if same_query(&cached.request, request)))
+            .or_else(|| self.cache.iter().position(|entry| entry.is_none()))
+            .unwrap_or_else(|| {
+                let mut index = 0;
+                let mut earliest = u64::MAX;
+                for (i, cached) in self.cache.iter().enumerate() {
+                    if let Some(cached) = cached {
+                        if cached.expires_at < earliest {
+                            earliest = cached.expires_at;
+                            index = i;
+                        }
+                    }
+                }
+                index
+            });
+        self.cache[index] = Some(answer);
+        
+        for entry in self.pending.iter_mut() {
+            if matches!(entry, Some(pending) if same_query(&pending.query.request, request)) {
+                if let Some(pending) = entry.take() {
+                    on_answer(&pending.query, server);
+                }
+            }
+        }
+    }
+    
+    /// 移除等待超时的请求，通过回调交给调用方回复失败
+    pub fn expire<F: FnMut(&ServiceQuery)>(&mut self, current_time: u64, mut on_timeout: F) {
+        for entry in self.pending.iter_mut() {
+            if matches!(entry, Some(pending) if current_time.saturating_sub(pending.sent_at) >= LOOKUP_TIMEOUT_MS) {
+                if let Some(pending) = entry.take() {
+                    on_timeout(&pending.query);
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
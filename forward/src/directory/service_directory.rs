@@ -1,4 +1,9 @@
+use common::config::{ConfigChanges, ConfigObserver, NodeConfig, ScoreWeights, SERVICE_TYPE_COUNT};
+use common::hal::nvs::{keys, NvStorage};
+use common::metrics::{set as set_gauge, Gauge};
 use common::protocol::{NodeId, ServiceType, QosRequirements};
+use common::protocol::service_advert::{AdvertisedService, MAX_SERVICE_HOPS};
+use common::protocol::wire::{get_u16be, put_u16be};
 use crate::directory::ServiceDirectory;
 use core::fmt;
 
@@ -8,11 +13,33 @@ pub struct ServiceEntry {
     pub node_id: NodeId,
     pub service_type: ServiceType,
     pub load: u8,                // 服务器负载 (0-100%)
+    pub hops: u8,                // 到服务器的跳数，0表示直接听到信标
     pub capabilities: Capabilities,
-    pub last_update_time: u64,   // 最后更新时间戳
+    pub last_update_time: u64,   // 最后听到服务器信标的网络时间，从邻居学到的服务为通告中的源时间
     pub metrics: ServiceMetrics,
 }
 
+// 选择策略随运行配置保存，可通过远程管理按服务类型设置
+pub use common::config::SelectionPolicy;
+
+// 目录最多保存的服务数
+const MAX_SERVICES: usize = 32;
+
+// 主节点为目录代理挑选服务器后记录的会话数，代理持有真正的租约
+const MAX_REMOTE_SESSIONS: usize = 16;
+
+// 主节点回答代理查询时记录的会话，按客户端请求的有效期计入服务器的会话数
+#[derive(Clone, Copy)]
+struct RemoteSession {
+    server: NodeId,
+    service_type: ServiceType,
+    expires_at: u64,
+}
+
+// 检查点中每个服务的长度：节点ID(6) 类型(1) 负载(1) 跳数(1) 带宽(2) 延迟(2) 可靠性(1) 电量(1)
+// 成功率(1) 响应时间(2) 信号强度(1)
+const CHECKPOINT_SERVICE_LEN: usize = 19;
+
 // 服务器能力
 #[derive(Clone, Copy)]
 pub struct Capabilities {
@@ -36,42 +63,43 @@ impl fmt::Debug for ServiceEntry {
             .field("node_id", &self.node_id)
             .field("service_type", &self.service_type)
             .field("load", &self.load)
+            .field("hops", &self.hops)
             .field("last_update_time", &self.last_update_time)
             .finish()
     }
 }
 
 impl ServiceEntry {
-    // 评分函数 - 评估服务条目与QoS需求的匹配程度
-    pub fn score(&self, qos: &QosRequirements) -> u16 {
+    // 评分函数 - 按权重评估服务条目与QoS需求的匹配程度
+    pub fn score(&self, qos: &QosRequirements, weights: &ScoreWeights) -> u16 {
         let mut score: u16 = 0;
         
         // 带宽评分 (高于要求的带宽给更高分)
         if self.capabilities.max_bandwidth >= qos.min_bandwidth {
-            score += 40 * (1 + (self.capabilities.max_bandwidth - qos.min_bandwidth).min(1000) / 100) as u16;
+            score += weights.bandwidth as u16 * (1 + (self.capabilities.max_bandwidth - qos.min_bandwidth).min(1000) / 100);
         } else {
             return 0; // 不满足最低带宽要求
         }
         
         // 延迟评分 (低于要求的延迟给更高分)
         if self.capabilities.min_latency <= qos.max_latency {
-            score += 30 * (1 + (qos.max_latency - self.capabilities.min_latency).min(500) / 50) as u16;
+            score += weights.latency as u16 * (1 + (qos.max_latency - self.capabilities.min_latency).min(500) / 50);
         } else {
             return 0; // 不满足最大延迟要求
         }
         
         // 可靠性评分
         if self.capabilities.reliability >= qos.reliability {
-            score += 20 * (1 + (self.capabilities.reliability - qos.reliability).min(50) / 10) as u16;
+            score += weights.reliability as u16 * (1 + (self.capabilities.reliability - qos.reliability).min(50) / 10) as u16;
         } else {
             return 0; // 不满足可靠性要求
         }
         
         // 负载评分 (负载越低越好)
-        score += 10 * (100 - self.load as u16) / 10;
+        score += weights.load as u16 * (100 - self.load as u16) / 10;
         
         // 电池电量评分 (电量越高越好)
-        score += 5 * self.capabilities.battery_level as u16 / 10;
+        score += weights.battery as u16 * self.capabilities.battery_level as u16 / 10;
         
         // 信号强度评分
         let signal_factor = if self.metrics.signal_strength > -60 {
@@ -85,45 +113,157 @@ impl ServiceEntry {
         };
         score += signal_factor;
         
-        score
+        // 跳数评分 (跳数越多，路径越长越不可靠)
+        score.saturating_sub(weights.hop_penalty as u16 * self.hops as u16)
     }
 }
 
 // 网络服务目录实现
 pub struct NetworkServiceDirectory {
-    services: [Option<ServiceEntry>; 32], // 最多32个服务
+    services: [Option<ServiceEntry>; MAX_SERVICES],
     service_count: usize,
     last_cleanup_time: u64,
+    weights: ScoreWeights,         // 基础评分权重，按服务类型调整后使用
+    service_expiry_ms: u64,        // 服务多久没有更新视为失效
+    policies: [SelectionPolicy; SERVICE_TYPE_COUNT], // 各服务类型的选择策略
+    remote_sessions: [Option<RemoteSession>; MAX_REMOTE_SESSIONS], // 为代理查询挑选的会话
+    dirty: bool,                   // 上次写入检查点后是否有服务加入或被移除
 }
 
 impl NetworkServiceDirectory {
-    // 创建新的服务目录
+    // 创建使用默认评分权重的服务目录
     pub fn new() -> Self {
+        Self::with_weights(ScoreWeights::default())
+    }
+    
+    // 创建使用指定评分权重的服务目录
+    pub fn with_weights(weights: ScoreWeights) -> Self {
         Self {
-            services: [None; 32],
+            services: [None; MAX_SERVICES],
             service_count: 0,
             last_cleanup_time: 0,
+            weights,
+            service_expiry_ms: 300_000, // 5分钟
+            policies: SelectionPolicy::defaults(),
+            remote_sessions: [None; MAX_REMOTE_SESSIONS],
+            dirty: false,
         }
     }
     
-    // 定期清理过期的服务（超过5分钟没有更新）
-    pub fn cleanup(&mut self, current_time: u64) {
-        const SERVICE_EXPIRY_MS: u64 = 300_000; // 5分钟
+    // 按运行配置中的评分权重、过期时间和选择策略创建服务目录
+    pub fn from_config(config: &NodeConfig) -> Self {
+        let mut directory = Self::with_weights(config.score_weights);
+        directory.service_expiry_ms = config.service_expiry_ms as u64;
+        directory.policies = config.selection_policies;
+        directory
+    }
+    
+    // 修改基础评分权重
+    pub fn set_weights(&mut self, weights: ScoreWeights) {
+        self.weights = weights;
+    }
+    
+    // 设置服务类型的选择策略
+    pub fn set_policy(&mut self, service_type: ServiceType, policy: SelectionPolicy) {
+        self.policies[service_type as usize - 1] = policy;
+    }
+    
+    // 服务类型的选择策略
+    pub fn policy(&self, service_type: ServiceType) -> SelectionPolicy {
+        self.policies[service_type as usize - 1]
+    }
+    
+    // 服务有增删时写入检查点，返回是否写入
+    //
+    // 只刷新负载和时间不写，避免频繁擦写闪存。
+    pub fn checkpoint<N: NvStorage>(&mut self, nvs: &mut N) -> Result<bool, N::Error> {
+        if !self.dirty {
+            return Ok(false);
+        }
         
+        let mut bytes = [0u8; 1 + CHECKPOINT_SERVICE_LEN * MAX_SERVICES];
+        let mut count = 0;
+        for service in self.services() {
+            let chunk = &mut bytes[1 + count * CHECKPOINT_SERVICE_LEN..1 + (count + 1) * CHECKPOINT_SERVICE_LEN];
+            chunk[0..6].copy_from_slice(&service.node_id.0);
+            chunk[6] = service.service_type as u8;
+            chunk[7] = service.load;
+            chunk[8] = service.hops;
+            put_u16be(&mut chunk[9..11], service.capabilities.max_bandwidth);
+            put_u16be(&mut chunk[11..13], service.capabilities.min_latency);
+            chunk[13] = service.capabilities.reliability;
+            chunk[14] = service.capabilities.battery_level;
+            chunk[15] = service.metrics.success_rate;
+            put_u16be(&mut chunk[16..18], service.metrics.avg_response_time);
+            chunk[18] = service.metrics.signal_strength as u8;
+            count += 1;
+        }
+        bytes[0] = count as u8;
+        
+        nvs.nvs_write(keys::SERVICE_DIRECTORY, &bytes[..1 + count * CHECKPOINT_SERVICE_LEN])?;
+        self.dirty = false;
+        Ok(true)
+    }
+    
+    // 启动时从检查点恢复服务，返回恢复的服务数
+    //
+    // 恢复的服务以恢复时间作为最后更新时间，服务器在一个过期时间内没有再发信标或被邻居通告时照常清理。
+    pub fn restore<N: NvStorage>(&mut self, nvs: &mut N, current_time: u64) -> usize {
+        let mut bytes = [0u8; 1 + CHECKPOINT_SERVICE_LEN * MAX_SERVICES];
+        let len = match nvs.nvs_read(keys::SERVICE_DIRECTORY, &mut bytes) {
+            Ok(Some(len)) => len,
+            _ => return 0,
+        };
+        
+        let count = (bytes[0] as usize).min(MAX_SERVICES).min(len.saturating_sub(1) / CHECKPOINT_SERVICE_LEN);
+        let mut restored = 0;
+        for chunk in bytes[1..].chunks_exact(CHECKPOINT_SERVICE_LEN).take(count) {
+            let service_type = match ServiceType::from_u8(chunk[6]) {
+                Some(service_type) => service_type,
+                None => continue,
+            };
+            let mut node_id = [0u8; 6];
+            node_id.copy_from_slice(&chunk[0..6]);
+            let capabilities = Capabilities {
+                max_bandwidth: get_u16be(&chunk[9..11]),
+                min_latency: get_u16be(&chunk[11..13]),
+                reliability: chunk[13],
+                battery_level: chunk[14],
+            };
+            let metrics = ServiceMetrics {
+                success_rate: chunk[15],
+                avg_response_time: get_u16be(&chunk[16..18]),
+                signal_strength: chunk[18] as i8,
+            };
+            if self.update_service(NodeId(node_id), service_type, chunk[7], capabilities, metrics,
+                                   chunk[8].min(MAX_SERVICE_HOPS), current_time) {
+                restored += 1;
+            }
+        }
+        
+        // 刚从检查点读出的内容不必再写回
+        self.dirty = false;
+        restored
+    }
+    
+    // 定期清理过期的服务（默认超过5分钟没有更新）
+    pub fn cleanup(&mut self, current_time: u64) {
         // 每30秒执行一次清理
-        if current_time - self.last_cleanup_time < 30_000 {
+        if current_time.saturating_sub(self.last_cleanup_time) < 30_000 {
             return;
         }
         
         for entry in self.services.iter_mut() {
             if let Some(service) = entry {
-                if current_time - service.last_update_time > SERVICE_EXPIRY_MS {
+                if current_time.saturating_sub(service.last_update_time) > self.service_expiry_ms {
                     *entry = None;
                     self.service_count -= 1;
+                    self.dirty = true;
                 }
             }
         }
         
+        set_gauge(Gauge::DirectoryEntries, self.service_count as u32);
         self.last_cleanup_time = current_time;
     }
     
@@ -145,24 +285,122 @@ impl NetworkServiceDirectory {
     
     // 查找最适合满足QoS需求的服务
     pub fn find_best_service(&self, service_type: ServiceType, qos: &QosRequirements) -> Option<&ServiceEntry> {
+        let weights = self.weights.for_service(service_type);
+        self.find_best_service_with(service_type, |service| service.score(qos, &weights))
+    }
+    
+    // 按调用者提供的评分函数查找得分最高的服务，得分为0的服务视为不可用，不分配内存
+    pub fn find_best_service_with<F: FnMut(&ServiceEntry) -> u16>(
+        &self,
+        service_type: ServiceType,
+        mut score: F
+    ) -> Option<&ServiceEntry> {
         let mut best_service: Option<&ServiceEntry> = None;
         let mut best_score: u16 = 0;
         
-        for entry in self.services.iter() {
-            if let Some(service) = entry {
-                if service.service_type == service_type {
-                    let score = service.score(qos);
-                    if score > best_score {
-                        best_score = score;
-                        best_service = Some(service);
-                    }
-                }
+        for service in self.get_services_by_type(service_type) {
+            let score = score(service);
+            if score > best_score {
+                best_score = score;
+                best_service = Some(service);
             }
         }
         
         best_service
     }
     
+    // 按服务类型的选择策略为一个新会话挑选服务器
+    //
+    // 只在满足QoS（得分不为0）的服务器之间选择；entropy为调用者提供的随机数，只有加权随机策略使用。
+    // sessions返回服务器当前由本节点租约承载的该类型会话数，轮流策略据此在服务器之间分摊进行中的会话，
+    // 会话结束或租约到期后自然不再计入。
+    pub fn select_service<F: Fn(NodeId) -> usize>(
+        &self,
+        service_type: ServiceType,
+        qos: &QosRequirements,
+        entropy: u32,
+        current_time: u64,
+        sessions: F
+    ) -> Option<NodeId> {
+        let weights = self.weights.for_service(service_type);
+        let index = match self.policy(service_type) {
+            SelectionPolicy::BestScore => self.select_index(service_type, |service| service.score(qos, &weights) as u32),
+            SelectionPolicy::RoundRobin => {
+                // 进行中的会话少的优先，会话数相同时选得分高的
+                let active = |service: &ServiceEntry| {
+                    sessions(service.node_id) + self.remote_sessions(service.node_id, service_type, current_time)
+                };
+                let fewest = self.qualified(service_type, qos, &weights).map(|(_, service)| active(service)).min()?;
+                self.select_index(service_type, |service| match service.score(qos, &weights) {
+                    score if score > 0 && active(service) == fewest => score as u32,
+                    _ => 0,
+                })
+            },
+            SelectionPolicy::WeightedRandom => {
+                // 权重为空闲负载加一，满载的服务器仍有很小的机会被选中
+                let weight = |service: &ServiceEntry| 101 - service.load.min(100) as u32;
+                let total: u32 = self.qualified(service_type, qos, &weights).map(|(_, service)| weight(service)).sum();
+                let mut pick = entropy % total.max(1);
+                self.qualified(service_type, qos, &weights)
+                    .find(|(_, service)| match pick.checked_sub(weight(service)) {
+                        Some(rest) => {
+                            pick = rest;
+                            false
+                        },
+                        None => true,
+                    })
+                    .map(|(index, _)| index)
+            },
+        }?;
+        
+        self.services[index].as_ref().map(|service| service.node_id)
+    }
+    
+    // 主节点为目录代理挑选服务器后记录会话，代理的租约本节点看不到，按客户端请求的有效期计数
+    pub fn record_remote_session(&mut self, server: NodeId, service_type: ServiceType, expires_at: u64) {
+        let session = RemoteSession { server, service_type, expires_at };
+        let index = self.remote_sessions.iter()
+            .position(|entry| entry.is_none())
+            .or_else(|| (0..MAX_REMOTE_SESSIONS).min_by_key(|&i| self.remote_sessions[i].map_or(0, |s| s.expires_at)))
+            .unwrap_or(0);
+        self.remote_sessions[index] = Some(session);
+    }
+    
+    // 为代理查询挑选的、尚未到期的会话数
+    fn remote_sessions(&self, server: NodeId, service_type: ServiceType, current_time: u64) -> usize {
+        self.remote_sessions.iter()
+            .flatten()
+            .filter(|session| session.server == server && session.service_type == service_type && current_time < session.expires_at)
+            .count()
+    }
+    
+    // 满足QoS的服务及其位置
+    fn qualified<'a>(
+        &'a self,
+        service_type: ServiceType,
+        qos: &'a QosRequirements,
+        weights: &'a ScoreWeights
+    ) -> impl Iterator<Item = (usize, &'a ServiceEntry)> + 'a {
+        self.services.iter()
+            .enumerate()
+            .filter_map(|(index, entry)| entry.as_ref().map(|service| (index, service)))
+            .filter(move |(_, service)| service.service_type == service_type && service.score(qos, weights) > 0)
+    }
+    
+    // 按评分函数选得分最高的服务的位置，得分为0的服务视为不可用
+    fn select_index<F: FnMut(&ServiceEntry) -> u32>(&self, service_type: ServiceType, mut score: F) -> Option<usize> {
+        let mut best: Option<(usize, u32)> = None;
+        for (index, entry) in self.services.iter().enumerate() {
+            if let Some(service) = entry.as_ref().filter(|service| service.service_type == service_type) {
+                let score = score(service);
+                if score > best.map_or(0, |(_, best_score)| best_score) {
+                    best = Some((index, score));
+                }
+            }
+        }
+        best.map(|(index, _)| index)
+    }
+    
     // 更新服务条目（添加新服务或更新现有服务）
     pub fn update_service(
         &mut self, 
@@ -171,6 +409,7 @@ impl NetworkServiceDirectory {
         load: u8,
         capabilities: Capabilities,
         metrics: ServiceMetrics,
+        hops: u8,
         current_time: u64
     ) -> bool {
         // 检查是否存在相同的服务条目
@@ -180,6 +419,7 @@ impl NetworkServiceDirectory {
                 service.load = load;
                 service.capabilities = capabilities;
                 service.metrics = metrics;
+                service.hops = hops;
                 service.last_update_time = current_time;
             }
             return true;
@@ -191,11 +431,14 @@ impl NetworkServiceDirectory {
                 node_id,
                 service_type,
                 load,
+                hops,
                 capabilities,
                 metrics,
                 last_update_time: current_time,
             });
             self.service_count += 1;
+            self.dirty = true;
+            set_gauge(Gauge::DirectoryEntries, self.service_count as u32);
             return true;
         }
         
@@ -203,19 +446,87 @@ impl NetworkServiceDirectory {
         false
     }
     
-    // 获取所有与特定服务类型匹配的服务
-    pub fn get_services_by_type(&self, service_type: ServiceType) -> Vec<&ServiceEntry> {
-        let mut result = Vec::new();
-        
-        for entry in self.services.iter() {
-            if let Some(service) = entry {
-                if service.service_type == service_type {
-                    result.push(service);
+    // 向邻居通告的服务，跳数已达上限的服务不再传播
+    pub fn advertised_services(&self) -> impl Iterator<Item = AdvertisedService> + '_ {
+        self.services()
+            .filter(|service| service.hops < MAX_SERVICE_HOPS)
+            .map(|service| AdvertisedService {
+                server: service.node_id,
+                service_type: service.service_type,
+                load: service.load,
+                hops: service.hops,
+                battery_level: service.capabilities.battery_level,
+                rssi: service.metrics.signal_strength,
+                origin_time: (service.last_update_time / 1000) as u32,
+            })
+    }
+    
+    // 合并邻居通告的服务，返回新增或更新的服务数
+    //
+    // 只接受源时间比本地条目新的服务，源时间相同时只接受跳数更少的；
+    // 环路上转回来的通告源时间不变、跳数更多，不会刷新本地条目，服务器消失后条目照常过期。
+    pub fn learn_services(&mut self, services: &[AdvertisedService], current_time: u64) -> usize {
+        let mut learned = 0;
+        for advertised in services {
+            let hops = advertised.hops.saturating_add(1);
+            let origin_time = advertised.origin_time as u64 * 1000;
+            if hops > MAX_SERVICE_HOPS || current_time.saturating_sub(origin_time) > self.service_expiry_ms {
+                continue;
+            }
+            
+            if let Some(index) = self.find_service_index(advertised.server, advertised.service_type) {
+                if let Some(existing) = &self.services[index] {
+                    let newer = origin_time > existing.last_update_time
+                        || (origin_time == existing.last_update_time && hops < existing.hops);
+                    if !newer {
+                        continue;
+                    }
                 }
             }
+            
+            // 通告只带负载、电量和信号强度，其余能力与直接听到信标时一样取默认值
+            let capabilities = Capabilities {
+                max_bandwidth: 1000,
+                min_latency: 100,
+                reliability: 90,
+                battery_level: advertised.battery_level,
+            };
+            let metrics = ServiceMetrics {
+                success_rate: 100,
+                avg_response_time: 50,
+                signal_strength: advertised.rssi,
+            };
+            if self.update_service(advertised.server, advertised.service_type, advertised.load,
+                                   capabilities, metrics, hops, origin_time) {
+                learned += 1;
+            }
+        }
+        learned
+    }
+    
+    // 遍历所有与特定服务类型匹配的服务
+    pub fn get_services_by_type(&self, service_type: ServiceType) -> impl Iterator<Item = &ServiceEntry> {
+        self.services().filter(move |service| service.service_type == service_type)
+    }
+    
+    // 遍历目录中的所有服务
+    pub fn services(&self) -> impl Iterator<Item = &ServiceEntry> {
+        self.services.iter().flatten()
+    }
+    
+    // 依次访问目录中的所有服务，供不便持有迭代器的调用者使用
+    pub fn for_each_service<F: FnMut(&ServiceEntry)>(&self, f: F) {
+        self.services().for_each(f);
+    }
+}
+
+impl ConfigObserver for NetworkServiceDirectory {
+    fn config_changed(&mut self, config: &NodeConfig, changes: ConfigChanges) {
+        if changes.directory {
+            self.weights = config.score_weights;
+            self.service_expiry_ms = config.service_expiry_ms as u64;
+            self.policies = config.selection_policies;
         }
-        
-        result
     }
 }
 
@@ -241,26 +552,22 @@ impl ServiceDirectory for NetworkServiceDirectory {
             0, // 初始负载为0
             capabilities,
             metrics,
+            0, // 本地注册的服务距离为0跳
             0 // 当前时间由调用者提供
         );
     }
     
     fn find_service(&self, service_type: ServiceType) -> Option<NodeId> {
         // 简化版本，只考虑服务类型匹配，不考虑QoS
-        for entry in self.services.iter() {
-            if let Some(service) = entry {
-                if service.service_type == service_type {
-                    return Some(service.node_id);
-                }
-            }
-        }
-        None
+        self.get_services_by_type(service_type).next().map(|service| service.node_id)
     }
     
     fn remove_service(&mut self, node_id: NodeId, service_type: ServiceType) {
         if let Some(index) = self.find_service_index(node_id, service_type) {
             self.services[index] = None;
             self.service_count -= 1;
+            self.dirty = true;
+            set_gauge(Gauge::DirectoryEntries, self.service_count as u32);
         }
     }
     
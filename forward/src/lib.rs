@@ -0,0 +1,1998 @@
+#![cfg_attr(not(feature = "simulator"), no_std)]
+
+pub mod routing;
+pub mod beacon_relay;
+pub mod channel_plan;
+pub mod directory;
+pub mod management;
+pub mod neighbors;
+pub mod path_table;
+pub mod relay_gate;
+pub mod scheduler;
+pub mod topology;
+
+use common::protocol::{Beacon, DataPacket, NodeId, NodeRole, ServiceType, ServiceRequest, ServiceResponse, QosRequirements, PathStatus};
+use common::protocol::{PacketType, deserialize_service_request, serialize_service_response, deserialize_service_renewal};
+use common::protocol::{deserialize_service_close, deserialize_service_handover};
+use common::protocol::batch::BATCH_PAYLOAD_TYPE;
+use common::protocol::data::flow_id_of;
+use common::protocol::frame::FRAME_PAYLOAD_TYPE;
+use common::protocol::echo::{answer_echo, send_echo, Echo};
+use common::protocol::error_report::{send_error_report, ErrorCode, ErrorReport, ErrorReporter};
+use common::protocol::hello::answer_hello;
+use common::protocol::keepalive::{send_keepalive, PathKeepAlive};
+use common::protocol::lookup::{send_lookup, LookupMessage};
+use common::hal::{Hardware, ResetCause, RADIO_DIAGNOSTICS_INTERVAL_MS};
+use common::hal::nvs::{RebootBreadcrumb, RebootReason};
+use common::clock::{NetworkClock, TIME_BEACON_INTERVAL_MS};
+use common::beacon_interval::AdaptiveBeacon;
+use common::config::{ConfigChanges, ConfigObserver, NodeConfig};
+use common::mgmt::MgmtAgent;
+use common::ota::{OtaEvent, OtaReceiver, FIRMWARE_VERSION};
+use common::protocol::mgmt::MgmtMessage;
+use common::protocol::ota::{send_ota, OtaMessage};
+use common::protocol::tdma::{send_slot_request, SlotAllocator, SlotRequest, SlotTable, DEFAULT_SLOT_MS, SLOT_REQUEST_INTERVAL_MS};
+use common::protocol::time_sync::{send_time_beacon, TimeBeacon};
+use common::protocol::wire::{get_u16be, get_u32be, put_u16be, put_u32be};
+use common::protocol::path::{answer_path_establish, PATH_CONFIRM_LEN};
+use common::protocol::reliable::{send_congestion_notice, DeliveryEvent, ReliableError, ReliableSender, RetryConfig};
+use common::protocol::route_advert::{send_route_advert, RouteAdvert};
+use common::protocol::route_discovery::{DiscoveryMessage, ROUTE_REQUEST_LEN};
+use common::protocol::route_table::{send_route_table, RouteTableMessage};
+use common::protocol::stats::{answer_stats, send_stats, StatsMessage};
+use common::protocol::service_beacon::ServiceBeacon;
+use common::security::{self, receive_secure, send_secure, send_secure_to};
+use common::pool::PacketBuf;
+use common::metrics::{self, Counter, Gauge};
+use common::{info, warn};
+use routing::discovery::{handle_route_reply, handle_route_request, RouteDiscovery};
+use routing::dynamic_forwarding::ForwardingEngine;
+use routing::flooding::FloodRelay;
+use routing::strategy::RoutingStrategy;
+use beacon_relay::BeaconRelay;
+use channel_plan::ChannelPlanner;
+use directory::election::ElectionProtocol;
+use directory::gossip::DirectoryGossip;
+use directory::lease_table::{LeaseTable, ServiceLease};
+use directory::local::LocalDirectory;
+use directory::proxy::{Deferred, DirectoryProxy, ServiceQuery, ANSWER_TTL_SECS};
+use directory::service_directory::{NetworkServiceDirectory, Capabilities, SelectionPolicy, ServiceMetrics};
+use management::ForwardNode;
+use neighbors::NeighborTable;
+use path_table::PathTable;
+use relay_gate::RelayGate;
+use scheduler::{QueuedPacket, TrafficClass, TxScheduler, TX_BURST};
+use topology::TopologyAgent;
+
+/// 清理过期路由并把路由表和邻居表写入检查点的间隔（毫秒）
+const ROUTE_CHECKPOINT_INTERVAL_MS: u64 = 60_000;
+
+/// 定期向邻居通告路由表的间隔（毫秒）
+const ROUTE_ADVERT_INTERVAL_MS: u64 = 30_000;
+
+/// 路由失效触发的通告之间的最短间隔（毫秒）
+const MIN_TRIGGERED_ADVERT_MS: u64 = 1000;
+
+/// HTTP接口上的状态更新间隔（毫秒）
+#[cfg(feature = "http")]
+const HTTP_PUBLISH_INTERVAL_MS: u64 = 5000;
+
+/// 转发节点主循环的状态，每次调用[`ForwardLoop::step`]执行一轮，模拟器和测试可以单步运行真实的主循环
+pub struct ForwardLoop {
+    config: NodeConfig,
+    forwarding_engine: ForwardingEngine,
+    relay_gate: RelayGate,
+    election: ElectionProtocol,
+    floods: FloodRelay,
+    service_directory: LocalDirectory,
+    directory_proxy: DirectoryProxy,
+    gossip: DirectoryGossip,
+    leases: LeaseTable,
+    paths: PathTable,
+    ota: OtaReceiver,
+    mgmt: MgmtAgent,
+    clock: NetworkClock,
+    errors: ErrorReporter,
+    topology: TopologyAgent,
+    channel_plan: ChannelPlanner,
+    scheduler: TxScheduler,
+    discovery: RouteDiscovery,
+    /// 路径建立和路径确认的可靠发送，会话为服务ID
+    control: ReliableSender,
+    slot_allocator: SlotAllocator,
+    slots: SlotTable,
+    neighbors: NeighborTable,
+    beacon_relay: BeaconRelay,
+    #[cfg(feature = "simulator")]
+    topology_export: Option<String>,
+    tx_buffer: PacketBuf<'static>,
+    beacon_schedule: AdaptiveBeacon,
+    beacon_timer: u64,
+    beacon_sequence: u8,
+    election_timer: u64,
+    directory_cleanup_timer: u64,
+    checkpoint_timer: u64,
+    advert_timer: u64,
+    time_beacon_timer: u64,
+    slot_request_timer: u64,
+    diagnostics_timer: u64,
+    #[cfg(feature = "http")]
+    http_timer: u64,
+}
+
+impl ForwardLoop {
+    /// 加载配置并初始化各子系统，缓冲池耗尽时返回None
+    pub fn start<H: Hardware>(hardware: &mut H) -> Option<Self> {
+        // 运行配置，从非易失存储加载，可通过远程管理调整
+        let config = NodeConfig::load(hardware.get_nvs(), NodeRole::Forward);
+        hardware.set_default_ttl(config.default_ttl);
+        
+        // 配置无线电；终端节点的包直接发往远处的目标，由听到的转发节点接力
+        let radio = hardware.get_radio();
+        let _ = radio.configure(config.channel, config.tx_power);
+        let _ = radio.set_airtime_limit(config.airtime_config());
+        if !radio.set_promiscuous(true) {
+            warn!("无线电不支持混杂模式，只能转发发给本节点的包");
+        }
+        
+        // 恢复网络密钥和发送计数器
+        security::restore(hardware);
+        
+        // 初始化转发引擎
+        let mut forwarding_engine = ForwardingEngine::new(hardware.get_node_id());
+        forwarding_engine.config_changed(&config, ConfigChanges { routing: true, ..ConfigChanges::default() });
+        
+        // 电量告急时停止中继
+        let mut relay_gate = RelayGate::new();
+        relay_gate.config_changed(&config, ConfigChanges { routing: true, ..ConfigChanges::default() });
+        
+        // 初始化选举协议和承载选举消息的泛洪转发，不带服务目录的构建不能当选主节点
+        let mut election = ElectionProtocol::new(hardware.get_node_id());
+        election.set_eligible(LocalDirectory::AVAILABLE);
+        let floods = FloodRelay::new(hardware.get_node_id());
+        
+        // 初始化服务目录；目录代理模式下不保存完整目录，服务请求交给主节点查询
+        let mut service_directory = LocalDirectory::new(&config);
+        let directory_proxy = DirectoryProxy::new();
+        
+        // 服务目录同步，使连在其他转发节点上的客户端也能找到本节点听到的服务器
+        let gossip = DirectoryGossip::new();
+        
+        // 初始化服务租约表
+        let leases = LeaseTable::new();
+        let paths = PathTable::new(config.path_capacity_kbps as u32);
+        
+        // 初始化固件接收端，恢复中断的更新进度
+        let ota = OtaReceiver::new(FIRMWARE_VERSION, hardware.get_nvs());
+        
+        // 初始化远程管理代理
+        let mgmt = MgmtAgent::new(hardware.get_nvs());
+        
+        // 网络时钟，以选举出的主节点为基准
+        let clock = NetworkClock::new();
+        
+        // 错误上报，发往主节点
+        let mut errors = ErrorReporter::new();
+        if hardware.reset_cause() == ResetCause::Watchdog {
+            errors.record(ErrorCode::WatchdogReset, 0);
+        }
+        
+        // 拓扑收集，主节点汇总各转发节点的路由表
+        let topology = TopologyAgent::new();
+        
+        // 信道协调，主节点发现干扰时组织全网换信道
+        let channel_plan = ChannelPlanner::new();
+        
+        // 转发流量的发送调度，控制流量优先，各会话加权轮转
+        let scheduler = TxScheduler::new();
+        
+        // 按需路由发现，没有路由的包等待应答建立路由后再转发
+        let discovery = RouteDiscovery::new();
+        
+        // 路径建立和路径确认等待对端应答，超时按退避重传
+        let control = ReliableSender::new(RetryConfig::default());
+        
+        // 时分发送：主节点分配时隙并随时间信标广播，其他节点在自己的时隙内发送会话数据
+        let slot_allocator = SlotAllocator::new(DEFAULT_SLOT_MS);
+        let slots = SlotTable::empty();
+        
+        // 邻居双向验证，只有验证过的邻居才作为下一跳
+        let mut neighbors = NeighborTable::new();
+        neighbors.config_changed(&config, ConfigChanges { keepalive: true, ..ConfigChanges::default() });
+        
+        // 从检查点恢复路由、邻居和服务目录，中继短暂掉电后不必等到路由表和目录重新建立才能转发
+        let boot_time = hardware.get_timestamp_ms().unwrap_or(0);
+        let restored_routes = forwarding_engine.restore(hardware.get_nvs(), boot_time);
+        let restored_neighbors = neighbors.restore(hardware.get_nvs());
+        if restored_routes > 0 || restored_neighbors > 0 {
+            info!("从检查点恢复 {} 条路由、{} 个邻居，等待信标确认", restored_routes, restored_neighbors);
+        }
+        if let Some(directory) = service_directory.get_mut() {
+            let restored_services = directory.restore(hardware.get_nvs(), clock.now(boot_time));
+            if restored_services > 0 {
+                info!("从检查点恢复 {} 个服务", restored_services);
+            }
+        }
+        
+        // 服务器信标的多跳转发
+        let beacon_relay = BeaconRelay::new(hardware.get_node_id());
+        
+        // 每轮收集开始时把汇总的拓扑写入文件，按扩展名选择DOT或JSON
+        #[cfg(feature = "simulator")]
+        let topology_export = std::env::var("AETHER_TOPOLOGY_EXPORT").ok();
+        
+        // 编码响应用的缓冲区在运行期间一直占用，取自缓冲池
+        let tx_buffer = match hardware.packet_pool().acquire() {
+            Some(buffer) => buffer,
+            None => {
+                warn!("缓冲池耗尽，转发节点无法启动");
+                return None;
+            },
+        };
+        // 信标间隔随听到的邻居数伸缩
+        let beacon_schedule = AdaptiveBeacon::new(config.beacon_interval_ms);
+        
+        info!("转发节点启动完成，开始执行主循环");
+        
+        Some(Self {
+            config,
+            forwarding_engine,
+            relay_gate,
+            election,
+            floods,
+            service_directory,
+            directory_proxy,
+            gossip,
+            leases,
+            paths,
+            ota,
+            mgmt,
+            clock,
+            errors,
+            topology,
+            channel_plan,
+            scheduler,
+            discovery,
+            control,
+            slot_allocator,
+            slots,
+            neighbors,
+            beacon_relay,
+            #[cfg(feature = "simulator")]
+            topology_export,
+            tx_buffer,
+            beacon_schedule,
+            beacon_timer: 0,
+            beacon_sequence: 0,
+            election_timer: 0,
+            directory_cleanup_timer: 0,
+            checkpoint_timer: boot_time,
+            advert_timer: 0,
+            time_beacon_timer: 0,
+            slot_request_timer: 0,
+            diagnostics_timer: 0,
+            #[cfg(feature = "http")]
+            http_timer: 0,
+        })
+    }
+    
+    /// 执行一轮主循环，返回到下一轮之前可以休眠的毫秒数；固件升级后需要重启时返回None
+    pub fn step<H: Hardware>(&mut self, hardware: &mut H) -> Option<u32> {
+        // 获取当前时间，租约到期按网络时间计算
+        let now = hardware.get_timestamp_ms().unwrap_or(0);
+        let network_now = self.clock.now(now);
+        
+        // 按信标间隔广播信标，邻居越多间隔越长
+        if now - self.beacon_timer > self.beacon_schedule.interval_ms(now) {
+            let battery_level = hardware.get_battery_level().unwrap_or(100);
+            match self.relay_gate.update(battery_level) {
+                Some(true) => warn!("电池电量 {}% 低于告急阈值，不再为其他节点中继", battery_level),
+                Some(false) => info!("电池电量恢复到 {}%，重新为其他节点中继", battery_level),
+                None => {},
+            }
+            send_beacon(hardware, self.beacon_sequence, battery_level, self.relay_gate.is_unwilling());
+            self.beacon_sequence = self.beacon_sequence.wrapping_add(1);
+            self.beacon_timer = now;
+        }
+        
+        // 按配置的间隔执行主服务器选举，收集窗口结束时决定结果，主节点失效时提前重新选举
+        self.election.set_condition(hardware.get_battery_level().unwrap_or(100), self.leases.load());
+        if now - self.election_timer > self.config.election_interval_ms as u64 {
+            self.election.initiate_election(hardware, &mut self.floods, now);
+            self.election_timer = now;
+        }
+        self.election.poll(hardware, &mut self.floods, now);
+        
+        // 本节点当选主节点时作为网络时间基准，定期广播时间信标
+        let node_id = hardware.get_node_id();
+        let is_master = self.election.get_master() == Some(node_id);
+        if is_master {
+            self.clock.become_master(node_id);
+        } else {
+            self.clock.resign_master();
+        }
+        if is_master {
+            self.slot_allocator.expire(network_now);
+            self.slots = self.slot_allocator.table();
+        }
+        
+        // 主节点要回答其他中继的目录查询，即使配置为目录代理也维护完整目录
+        let keep_directory = !self.config.directory_proxy || is_master;
+        if self.service_directory.keep(keep_directory, &self.config) {
+            if keep_directory {
+                info!("开始维护本地服务目录");
+            } else {
+                info!("释放本地服务目录，改为向主节点查询");
+            }
+        }
+        self.directory_proxy.set_master(self.clock.master());
+        self.directory_proxy.expire(network_now, |query| {
+            warn!("主节点未应答目录查询，拒绝 {} 的服务请求", query.client);
+            reply_service_request(hardware, &mut self.leases, &mut self.paths, &mut self.control, query, None, &mut self.tx_buffer, network_now);
+        });
+        if now - self.time_beacon_timer > TIME_BEACON_INTERVAL_MS {
+            if let Some(beacon) = self.clock.next_beacon(now) {
+                if let Err(e) = send_time_beacon(hardware, &beacon, &self.slots) {
+                    warn!("发送时间信标失败: {:?}", e);
+                }
+            }
+            self.time_beacon_timer = now;
+        }
+        
+        // 主节点定期收集网络拓扑
+        if self.topology.poll(hardware, &self.forwarding_engine, is_master, now) {
+            #[cfg(feature = "simulator")]
+            if let Some(path) = &self.topology_export {
+                export_topology(&self.topology, path);
+            }
+        }
+        
+        // 上报信道质量，主节点评估干扰并推进进行中的信道切换
+        self.channel_plan.poll(hardware, &self.forwarding_engine, &mut self.config, self.clock.master(), now, network_now);
+        
+        // 清理过期的服务条目，目录中的时间为网络时间，各转发节点之间可以比较
+        if now - self.directory_cleanup_timer > 30000 {
+            if let Some(directory) = self.service_directory.get_mut() {
+                directory.cleanup(network_now);
+            }
+            self.leases.expire(network_now);
+            self.paths.expire(network_now);
+            self.directory_cleanup_timer = now;
+        }
+        
+        // 清理过期路由，路由、邻居或服务有增删时写入检查点
+        if now - self.checkpoint_timer > ROUTE_CHECKPOINT_INTERVAL_MS {
+            self.forwarding_engine.cleanup(now);
+            if self.forwarding_engine.checkpoint(hardware.get_nvs()).is_err() {
+                warn!("写入路由表检查点失败");
+            }
+            if self.neighbors.checkpoint(hardware.get_nvs()).is_err() {
+                warn!("写入邻居表检查点失败");
+            }
+            if let Some(directory) = self.service_directory.get_mut() {
+                if directory.checkpoint(hardware.get_nvs()).is_err() {
+                    warn!("写入服务目录检查点失败");
+                }
+            }
+            self.checkpoint_timer = now;
+        }
+        
+        // 定期向邻居同步服务目录
+        if let Some(directory) = self.service_directory.get() {
+            self.gossip.poll(hardware, directory, now);
+        }
+        
+        // 定期向邻居通告路由表，有路由失效时尽快通告
+        let triggered = now - self.advert_timer > MIN_TRIGGERED_ADVERT_MS && self.forwarding_engine.take_triggered_update();
+        if triggered || now - self.advert_timer > ROUTE_ADVERT_INTERVAL_MS {
+            advertise_routes(hardware, &self.forwarding_engine);
+            self.advert_timer = now;
+        }
+        
+        // 路径建立和路径确认超时后重传；服务器一直不确认的路径收回带宽和租约，客户端等待超时后重新请求服务
+        let (paths, leases) = (&mut self.paths, &mut self.leases);
+        self.control.poll(hardware, network_now, |event| {
+            if let DeliveryEvent::Failed { session_id, destination, .. } = event {
+                if paths.get(session_id).map_or(false, |path| path.server == destination) {
+                    warn!("服务器 {} 没有确认服务 {} 的路径，释放预留", destination, session_id);
+                    paths.release(session_id);
+                    leases.release(session_id);
+                } else {
+                    warn!("{} 没有确认服务 {} 的路径确认", destination, session_id);
+                }
+            }
+        });
+        
+        // 路由请求超时后重发，多次没有应答的丢弃缓存的包
+        let dropped = self.discovery.poll(now, |target| request_route(hardware, &mut self.floods, target, now));
+        if dropped > 0 {
+            metrics::add(Counter::PacketsDropped, dropped as u32);
+            warn!("路由发现没有应答，丢弃 {} 个缓存的包", dropped);
+        }
+        
+        // 接收数据包，缓冲区每轮从缓冲池取出，处理完归还；池耗尽时本轮不接收
+        let mut rx_buffer = hardware.packet_pool().acquire();
+        let received = rx_buffer.as_mut().and_then(|buffer| receive_secure(hardware, buffer.as_mut_slice()))
+            .filter(|_| !relayed_elsewhere(hardware, &self.neighbors));
+        
+        if let Some(packet) = received {
+            // 处理各种数据包
+            match PacketType::from_u8(packet.header.packet_type) {
+                Some(PacketType::Data) => {
+                    self.paths.touch(packet.header.flow_id, network_now);
+                    if !self.relay_gate.is_unwilling() {
+                        handle_data_packet(hardware, &mut self.forwarding_engine, &mut self.scheduler, &mut self.discovery, &mut self.floods, &packet, now);
+                    }
+                },
+                Some(PacketType::ServiceRequest) => {
+                    if let Some(query) = parse_service_request(&packet) {
+                        if let Some(lease) = self.leases.find_request(query.client, query.request.request_id, query.request.service_type) {
+                            // 客户端没有收到响应而重传的请求，重发已分配的服务，不重复分配租约和路径
+                            resend_service_response(hardware, &lease, &query, &mut self.tx_buffer);
+                        } else if let Some(server) = find_server(hardware, self.service_directory.get_mut(), &mut self.directory_proxy,
+                                                                 &mut self.forwarding_engine, &self.leases, &query, network_now) {
+                            reply_service_request(hardware, &mut self.leases, &mut self.paths, &mut self.control, &query, server,
+                                                  &mut self.tx_buffer, network_now);
+                        }
+                    }
+                },
+                Some(PacketType::ServiceRenew) => {
+                    handle_service_renew(hardware, &mut self.leases, &packet, &mut self.tx_buffer, network_now);
+                },
+                Some(PacketType::ServiceHandover) => {
+                    handle_service_handover(hardware, &mut self.leases, &mut self.paths, &mut self.control, &packet, &mut self.tx_buffer, network_now);
+                },
+                Some(PacketType::PathKeepAlive) => {
+                    handle_keepalive(hardware, &self.leases, &mut self.paths, &packet, network_now);
+                },
+                Some(PacketType::ServiceClose) => {
+                    handle_service_close(hardware, &mut self.forwarding_engine, &mut self.leases, &mut self.paths, &packet);
+                },
+                Some(PacketType::PathEstablish) => {
+                    handle_path_establish(hardware, &mut self.forwarding_engine, &mut self.paths, &mut self.control, &packet, network_now);
+                },
+                Some(PacketType::PathConfirm) => {
+                    handle_path_confirm(hardware, &mut self.leases, &mut self.paths, &mut self.control, &packet, network_now);
+                },
+                Some(PacketType::Ack) if NodeId(packet.header.destination) == hardware.get_node_id() => {
+                    // 客户端对路径确认的确认
+                    self.control.handle_ack(&packet);
+                },
+                Some(PacketType::Hello) => {
+                    answer_hello(hardware, &packet);
+                },
+                Some(PacketType::HelloAck) => {
+                    if let Some((neighbor, link)) = self.neighbors.handle_ack(&packet, now) {
+                        info!("与 {} 的链路已验证为双向", neighbor);
+                        self.forwarding_engine.update_link(neighbor, link);
+                    }
+                },
+                Some(PacketType::EchoRequest) | Some(PacketType::EchoReply) => {
+                    handle_echo(hardware, &mut self.forwarding_engine, &packet);
+                },
+                Some(PacketType::StatsRequest) | Some(PacketType::StatsResponse) => {
+                    handle_stats(hardware, &mut self.forwarding_engine, &packet);
+                },
+                Some(PacketType::RouteTableRequest) | Some(PacketType::RouteTableResponse) => {
+                    handle_route_table(hardware, &mut self.forwarding_engine, &packet, now);
+                },
+                Some(PacketType::Mgmt) => {
+                    let mut node = ForwardNode { config: &mut self.config, forwarding_engine: &self.forwarding_engine };
+                    if !self.mgmt.handle(hardware, &packet, &mut node) {
+                        forward_mgmt(hardware, &mut self.forwarding_engine, &packet);
+                    }
+                },
+                Some(PacketType::Flood) => {
+                    // 第一次到达的泛洪继续广播，原始消息交给本地处理
+                    if let Some(flood) = self.floods.flood_packet(hardware, &packet, now) {
+                        let inner = flood.inner();
+                        match PacketType::from_u8(flood.packet_type) {
+                            Some(PacketType::Data) => self.election.handle_packet(hardware, &inner, now),
+                            Some(PacketType::Mgmt) => {
+                                let mut node = ForwardNode { config: &mut self.config, forwarding_engine: &self.forwarding_engine };
+                                self.mgmt.handle(hardware, &inner, &mut node);
+                            },
+                            Some(PacketType::RouteRequest) => {
+                                handle_route_request(hardware, &mut self.forwarding_engine, &self.neighbors, &packet, &inner, now);
+                            },
+                            _ => {},
+                        }
+                    }
+                },
+                Some(PacketType::RouteReply) => {
+                    handle_route_reply(hardware, &mut self.forwarding_engine, &self.neighbors, &mut self.discovery, &mut self.scheduler, &packet, now);
+                },
+                Some(PacketType::TimeSync) => {
+                    // 主节点的时间信标经多跳转发，不相邻的节点也据此判断主节点仍然存活
+                    if let Some(master) = handle_time_sync(hardware, &mut self.clock, &mut self.leases, &mut self.slots, &packet, now) {
+                        self.election.observe_beacon(master, now);
+                    }
+                },
+                Some(PacketType::DirectoryLookup) => {
+                    let answer = handle_lookup(hardware, &mut self.forwarding_engine, self.service_directory.get_mut(), &self.leases,
+                                               &self.clock, &packet, network_now);
+                    if let Some(LookupMessage::Answer { server, ttl_secs, request, .. }) = answer {
+                        // 缓存主节点的应答并回复等待中的客户端
+                        self.directory_proxy.complete(&request, server, ttl_secs, network_now, |query, server| {
+                            reply_service_request(hardware, &mut self.leases, &mut self.paths, &mut self.control, query, server,
+                                                  &mut self.tx_buffer, network_now);
+                        });
+                    }
+                },
+                Some(PacketType::SlotRequest) => {
+                    handle_slot_request(hardware, &mut self.forwarding_engine, &self.clock, &mut self.slot_allocator, &packet, network_now);
+                },
+                Some(PacketType::RouteAdvert) => {
+                    // 只从验证过双向链路的邻居学习路由
+                    let neighbor = NodeId(packet.header.source);
+                    let link = self.neighbors.link_quality(neighbor, now);
+                    if let (Some(link), Some(advert)) = (link, RouteAdvert::deserialize(packet.data)) {
+                        let learned = self.forwarding_engine.learn_routes(neighbor, link, advert.entries(), now);
+                        if learned > 0 {
+                            info!("从 {} 的路由通告学到 {} 条路由", neighbor, learned);
+                        }
+                    }
+                },
+                Some(PacketType::ServiceAdvertisement) => {
+                    if let Some(directory) = self.service_directory.get_mut() {
+                        self.gossip.handle(directory, &packet, network_now);
+                    }
+                },
+                Some(PacketType::ServiceBeacon) => {
+                    // 目录代理模式下没有本地目录
+                    if let Some(directory) = self.service_directory.get_mut() {
+                        handle_service_beacon(hardware, directory, &packet, network_now);
+                    }
+                },
+                Some(PacketType::Topology) => {
+                    self.topology.handle(hardware, &mut self.forwarding_engine, &packet, now);
+                },
+                Some(PacketType::ChannelPlan) => {
+                    self.channel_plan.handle(hardware, &mut self.forwarding_engine, &self.config, &packet, now);
+                },
+                Some(PacketType::ErrorReport) => {
+                    handle_error_report(hardware, &mut self.forwarding_engine, &self.clock, &packet);
+                },
+                Some(PacketType::Ota) => {
+                    if handle_ota(hardware, &mut self.forwarding_engine, &mut self.ota, &packet, now) {
+                        return None;
+                    }
+                },
+                _ => {
+                    // 处理其他类型的数据包
+                    handle_other_packet(hardware, &mut self.forwarding_engine, &mut self.scheduler, &mut self.discovery, &mut self.floods, &packet, now);
+                }
+            }
+        }
+        
+        // 有待转发的会话数据时申请时隙，主节点直接为自己登记
+        if self.scheduler.pending_data() > 0 && now - self.slot_request_timer > SLOT_REQUEST_INTERVAL_MS {
+            request_slot(hardware, &mut self.forwarding_engine, &self.clock, &mut self.slot_allocator, network_now);
+            self.slot_request_timer = now;
+        }
+        
+        // 按调度顺序发出排队的转发流量，会话数据等到本节点的时隙再发
+        let in_slot = self.slots.may_send_bulk(node_id, self.clock.now(hardware.get_timestamp_ms().unwrap_or(now)));
+        self.scheduler.poll(hardware, TX_BURST, in_slot);
+        
+        // 发出随机延时已到的泛洪重新广播
+        self.floods.poll(hardware, now);
+        
+        // 接收信标
+        // 未通过认证的信标可能来自没有网络密钥的节点，不能进入路由表和服务目录
+        if let Ok(Some(beacon)) = hardware.get_radio().receive_beacon() {
+            let trusted = beacon.is_valid() && security::verify_beacon(hardware, &beacon);
+            if trusted {
+                self.beacon_schedule.observe(&beacon, now);
+                self.election.observe_beacon(NodeId(beacon.source), now);
+            }
+            if trusted && self.beacon_relay.accept(&beacon, now) {
+                handle_beacon(hardware, &mut self.forwarding_engine, &mut self.neighbors, &beacon, now);
+                
+                // 服务器信标跳数加一后继续广播
+                if let Some(relayed) = self.beacon_relay.relay(&beacon) {
+                    if let Err(e) = hardware.get_radio().send_beacon(&relayed) {
+                        warn!("转发信标失败: {:?}", e);
+                    }
+                }
+            }
+        }
+        
+        // 处理选举消息
+        self.election.process_messages(hardware, now);
+        
+        // 重新请求超时的固件分块
+        if self.ota.poll(hardware, now) == OtaEvent::Stalled {
+            warn!("固件更新无响应，已暂停并保留进度");
+        }
+        
+        // 通知各子系统配置的变化
+        let changes = self.config.take_changes();
+        if !changes.is_empty() {
+            info!("运行配置已更新: {:?}", changes);
+            self.forwarding_engine.config_changed(&self.config, changes);
+            self.relay_gate.config_changed(&self.config, changes);
+            self.beacon_schedule.config_changed(&self.config, changes);
+            self.neighbors.config_changed(&self.config, changes);
+            self.paths.config_changed(&self.config, changes);
+            if let Some(directory) = self.service_directory.get_mut() {
+                directory.config_changed(&self.config, changes);
+            }
+        }
+        
+        // 定期读取无线电收发统计，只能读取累计值的后端借此更新指标
+        if now - self.diagnostics_timer > RADIO_DIAGNOSTICS_INTERVAL_MS {
+            hardware.get_radio().diagnostics();
+            self.diagnostics_timer = now;
+        }
+        
+        // 限速上报本节点的错误
+        self.errors.poll(hardware, self.clock.master(), network_now);
+        
+        // 更新HTTP接口上的目录、租约和路由表
+        #[cfg(feature = "http")]
+        if now - self.http_timer > HTTP_PUBLISH_INTERVAL_MS {
+            publish_status(self.service_directory.get(), &self.leases, &self.forwarding_engine, &self.topology);
+            self.http_timer = now;
+        }
+        
+        // 每1秒钟执行一轮，可以根据实际硬件调整；有会话数据等待时隙或泛洪等待重新广播时提前醒来
+        let mut delay_ms: u64 = 1000;
+        if self.scheduler.pending_data() > 0 {
+            let local_now = hardware.get_timestamp_ms().unwrap_or(now);
+            delay_ms = delay_ms.min(self.slots.wait_ms(node_id, self.clock.now(local_now)).max(1));
+        }
+        if let Some(wait_ms) = self.floods.wait_ms(now) {
+            delay_ms = delay_ms.min(wait_ms.max(1));
+        }
+        Some(delay_ms as u32)
+    }
+}
+
+/// 转发节点主循环，固件升级后需要重启或缓冲池耗尽时返回
+pub fn forward_main<H: Hardware>(hardware: &mut H) {
+    let mut node = match ForwardLoop::start(hardware) {
+        Some(node) => node,
+        None => return,
+    };
+    while let Some(delay_ms) = node.step(hardware) {
+        let _ = hardware.delay_ms(delay_ms);
+    }
+}
+
+/// 将服务目录、租约和路由表以JSON发布到HTTP接口
+#[cfg(feature = "http")]
+fn publish_status(
+    directory: Option<&NetworkServiceDirectory>,
+    leases: &LeaseTable,
+    forwarding_engine: &ForwardingEngine,
+    topology: &TopologyAgent
+) {
+    use common::http::{node_json, publish};
+    
+    // 目录代理模式下没有本地目录
+    let services: Vec<String> = directory.into_iter()
+        .flat_map(|directory| directory.services())
+        .map(|service| format!(
+            "{{\"node\":{},\"service_type\":\"{:?}\",\"load\":{},\"hops\":{},\"max_bandwidth\":{},\"min_latency\":{},\"reliability\":{},\"battery_level\":{},\"last_update\":{}}}",
+            node_json(service.node_id), service.service_type, service.load, service.hops,
+            service.capabilities.max_bandwidth, service.capabilities.min_latency,
+            service.capabilities.reliability, service.capabilities.battery_level,
+            service.last_update_time
+        ))
+        .collect();
+    publish("/directory", format!("[{}]", services.join(",")));
+    
+    let leases: Vec<String> = leases.iter()
+        .map(|lease| format!(
+            "{{\"service_id\":{},\"client\":{},\"server\":{},\"service_type\":\"{:?}\",\"expires_at\":{}}}",
+            lease.service_id, node_json(lease.client), node_json(lease.server),
+            lease.service_type, lease.expires_at
+        ))
+        .collect();
+    publish("/leases", format!("[{}]", leases.join(",")));
+    
+    let routes: Vec<String> = forwarding_engine.routes()
+        .map(|(destination, next_hop, metric)| format!(
+            "{{\"destination\":{},\"next_hop\":{},\"metric\":{}}}",
+            node_json(destination), node_json(next_hop), metric
+        ))
+        .collect();
+    publish("/routes", format!("[{}]", routes.join(",")));
+    
+    // 只有主节点的拓扑非空
+    if !topology.map().is_empty() {
+        let mut json = String::new();
+        let _ = topology.map().write_json(&mut json);
+        publish("/topology", json);
+    }
+}
+
+/// 将汇总的拓扑写入文件，扩展名为.dot时输出DOT，否则输出JSON
+#[cfg(feature = "simulator")]
+fn export_topology(topology: &TopologyAgent, path: &str) {
+    let mut text = String::new();
+    let _ = if path.ends_with(".dot") {
+        topology.map().write_dot(&mut text)
+    } else {
+        topology.map().write_json(&mut text)
+    };
+    
+    if let Err(e) = std::fs::write(path, text) {
+        warn!("写入拓扑文件 {} 失败: {}", path, e);
+    }
+}
+
+/// 发送本节点信标，序号供其他节点去重；电量告急时声明不再为其他节点中继
+fn send_beacon<H: Hardware>(hardware: &mut H, sequence: u8, battery_level: u8, relay_unwilling: bool) {
+    let node_id = hardware.get_node_id();
+    metrics::set(Gauge::BatteryLevel, battery_level as u32);
+    let rssi = hardware.get_radio().get_rssi().unwrap_or(-80);
+    
+    // 创建信标
+    let mut beacon = Beacon::with_role(node_id, battery_level, rssi, NodeRole::Forward);
+    beacon.set_sequence(sequence);
+    beacon.set_relay_unwilling(relay_unwilling);
+    security::sign_beacon(hardware, &mut beacon);
+    
+    // 发送信标
+    let radio = hardware.get_radio();
+    if let Err(e) = radio.send_beacon(&beacon) {
+        warn!("发送信标失败: {:?}", e);
+    } else {
+        info!("发送转发节点信标，电池电量: {}%", battery_level);
+    }
+}
+
+/// 处理接收到的信标
+fn handle_beacon<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    neighbors: &mut NeighborTable,
+    beacon: &Beacon,
+    current_time: u64
+) {
+    if beacon.is_valid() {
+        let source = NodeId(beacon.source);
+        
+        // 只有直接听到的信标才说明与发送方相邻，转发来的信标不更新路由表；
+        // 单向可达的链路会吞掉转发的流量，验证为双向后才安装路由，代价取邻居表估计的ETX
+        if beacon.hop_count == 0 {
+            let link_rssi = hardware.get_radio().get_rssi().unwrap_or(beacon.rssi);
+            if let Some(link) = neighbors.observe(hardware, beacon, link_rssi, current_time) {
+                forwarding_engine.update_link(source, link);
+            }
+        }
+        
+        info!("接收到来自 {} 的信标，跳数: {}, 信号强度: {}, 电池电量: {}%",
+            source, beacon.hop_count, beacon.rssi, beacon.battery_level);
+    }
+}
+
+/// 处理直接听到的服务器服务信标，按声明的服务和能力更新服务目录
+fn handle_service_beacon<H: Hardware>(
+    hardware: &mut H,
+    service_directory: &mut NetworkServiceDirectory,
+    packet: &DataPacket,
+    network_now: u64
+) {
+    let beacon = match ServiceBeacon::deserialize(packet.data) {
+        Some(beacon) => beacon,
+        None => return,
+    };
+    
+    let server = NodeId(packet.header.source);
+    let capabilities = Capabilities {
+        max_bandwidth: beacon.max_bandwidth,
+        min_latency: beacon.min_latency,
+        reliability: beacon.reliability,
+        battery_level: beacon.battery_level,
+    };
+    let metrics = ServiceMetrics {
+        success_rate: beacon.reliability,
+        avg_response_time: beacon.min_latency,
+        signal_strength: hardware.get_radio().get_rssi().unwrap_or(-80),
+    };
+    
+    // 服务信标只传一跳，发送方就是相邻的服务器
+    for service_type in beacon.services() {
+        service_directory.update_service(server, service_type, beacon.load, capabilities, metrics, 0, network_now);
+    }
+}
+
+/// 处理接收到的数据包
+fn handle_data_packet<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    scheduler: &mut TxScheduler,
+    discovery: &mut RouteDiscovery,
+    floods: &mut FloodRelay,
+    packet: &DataPacket,
+    now: u64
+) {
+    let source = NodeId(packet.header.source);
+    let destination = NodeId(packet.header.destination);
+    
+    info!("接收到来自 {} 发往 {} 的数据包，大小: {} 字节",
+        source.short(), destination.short(), packet.data.len());
+    
+    // 转发数据包
+    if !destination.is_broadcast() && destination != hardware.get_node_id() {
+        // 发射预算将尽时让出批量数据，把剩余的空口时间留给控制流量
+        let nearly_exhausted = hardware.get_radio().airtime().map_or(false, |status| status.is_nearly_exhausted());
+        if nearly_exhausted && is_bulk(packet) {
+            metrics::increment(Counter::AirtimeDeferred);
+            notify_congestion(hardware, packet);
+            return;
+        }
+        
+        let ttl = match forward_ttl(packet) {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        
+        // 下一跳在查到路由后填写，头部保留端到端的目标
+        let queued = QueuedPacket::new(hardware.packet_pool(), destination, PacketType::Data, packet.header.packet_id, packet.data)
+            .map(|queued| queued.with_destination(destination)
+                .with_flow(packet.header.flow_id)
+                .with_fragment(packet.header.total_fragments, packet.header.fragment_index)
+                .with_ttl(ttl));
+        let class = TrafficClass::of_data(packet);
+        
+        if let Some(next_hop) = forwarding_engine.get_next_hop(destination) {
+            info!("转发数据包到下一跳: {}", next_hop.short());
+            forwarding_engine.route_used(destination, now);
+            
+            // 放入发送调度器，队列满时通知发送方降速
+            let accepted = queued.map_or(false, |mut queued| {
+                queued.next_hop = next_hop;
+                scheduler.enqueue(class, queued).is_ok()
+            });
+            if accepted {
+                metrics::increment(Counter::PacketsForwarded);
+            } else {
+                metrics::increment(Counter::PacketsDropped);
+                warn!("发送队列已满，丢弃发往 {} 的数据包", destination);
+                notify_congestion(hardware, packet);
+            }
+        } else if forwarding_engine.strategy().discovers_on_demand() {
+            hold_for_route(hardware, discovery, floods, destination, class, queued, now);
+        } else {
+            metrics::increment(Counter::PacketsDropped);
+            warn!("未找到到达 {} 的路由，丢弃数据包", destination);
+        }
+    }
+}
+
+/// 旁听到的帧是否由其他转发节点发给了它选好的下一跳，这样的帧由下一跳接力，本节点不处理
+fn relayed_elsewhere<H: Hardware>(hardware: &mut H, neighbors: &NeighborTable) -> bool {
+    let node_id = hardware.get_node_id();
+    hardware.get_radio().last_link().map_or(false, |link| {
+        !link.destination.is_broadcast() && link.destination != node_id && neighbors.is_forwarder(link.source)
+    })
+}
+
+/// 转发前递减跳数，跳数耗尽时记录并返回None，防止路由环路中的包无限循环
+fn forward_ttl(packet: &DataPacket) -> Option<u8> {
+    let ttl = packet.header.ttl.saturating_sub(1);
+    if ttl == 0 {
+        metrics::increment(Counter::TtlExpired);
+        metrics::increment(Counter::PacketsDropped);
+        warn!("发往 {} 的数据包跳数耗尽，丢弃", NodeId(packet.header.destination));
+        return None;
+    }
+    Some(ttl)
+}
+
+/// 缓存没有路由的包并发起路由发现，缓存已满或缓冲池耗尽时丢弃
+fn hold_for_route<H: Hardware>(
+    hardware: &mut H,
+    discovery: &mut RouteDiscovery,
+    floods: &mut FloodRelay,
+    destination: NodeId,
+    class: TrafficClass,
+    queued: Option<QueuedPacket>,
+    now: u64
+) {
+    match queued.map(|queued| discovery.hold(destination, class, queued, now)) {
+        Some(Ok(true)) => request_route(hardware, floods, destination, now),
+        Some(Ok(false)) => {},
+        _ => {
+            metrics::increment(Counter::PacketsDropped);
+            warn!("未找到到达 {} 的路由且无法缓存，丢弃数据包", destination);
+        },
+    }
+}
+
+/// 泛洪路由请求，查找到达目标的路由
+fn request_route<H: Hardware>(hardware: &mut H, floods: &mut FloodRelay, target: NodeId, now: u64) {
+    let request = DiscoveryMessage::Request { target, initial_ttl: hardware.default_ttl() };
+    let mut payload = [0u8; ROUTE_REQUEST_LEN];
+    let len = request.serialize(&mut payload);
+    
+    match floods.originate(hardware, PacketType::RouteRequest, &payload[..len], now) {
+        Ok(_) => info!("发起到 {} 的路由发现", target),
+        Err(e) => warn!("发送到 {} 的路由请求失败: {:?}", target, e),
+    }
+}
+
+/// 是否为可以推迟的批量数据：视频帧和批量采样，以及大负载的分片
+fn is_bulk(packet: &DataPacket) -> bool {
+    packet.is_fragment() || matches!(packet.data.first(), Some(&FRAME_PAYLOAD_TYPE) | Some(&BATCH_PAYLOAD_TYPE))
+}
+
+/// 处理回显请求和应答：发给本节点的请求直接应答，其余按负载中的端到端地址转发
+fn handle_echo<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    packet: &DataPacket
+) {
+    if answer_echo(hardware, packet) {
+        info!("已应答来自 {} 的回显请求", NodeId(packet.header.source));
+        return;
+    }
+    
+    let mut echo = match Echo::deserialize(packet.data) {
+        Some(echo) => echo,
+        None => return,
+    };
+    
+    // 请求发往目标并累计跳数，路径追踪时记录本节点和收到请求的信号强度；应答发回发起方
+    let (packet_type, toward) = if packet.header.packet_type == PacketType::EchoRequest as u8 {
+        let rssi = hardware.get_radio().get_rssi().unwrap_or(0);
+        echo.record_hop(hardware.get_node_id(), rssi);
+        (PacketType::EchoRequest, echo.target)
+    } else {
+        (PacketType::EchoReply, echo.origin)
+    };
+    
+    match forwarding_engine.get_next_hop(toward) {
+        Some(next_hop) => {
+            if let Err(e) = send_echo(hardware, next_hop, packet_type, packet.header.packet_id, &echo) {
+                warn!("转发回显包失败: {:?}", e);
+            }
+        },
+        None => warn!("未找到到达 {} 的路由，丢弃回显包", toward),
+    }
+}
+
+/// 处理计数器查询：发给本节点的查询直接应答，其余按负载中的端到端地址转发
+fn handle_stats<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    packet: &DataPacket
+) {
+    if answer_stats(hardware, packet) {
+        info!("已应答来自 {} 的计数器查询", NodeId(packet.header.source));
+        return;
+    }
+    
+    let message = match StatsMessage::deserialize(packet.header.packet_type, packet.data) {
+        Some(message) => message,
+        None => return,
+    };
+    
+    // 查询发往目标，应答发回发起方
+    let toward = match message {
+        StatsMessage::Request { target, .. } => target,
+        StatsMessage::Response { origin, .. } => origin,
+    };
+    
+    match forwarding_engine.get_next_hop(toward) {
+        Some(next_hop) => {
+            if let Err(e) = send_stats(hardware, next_hop, packet.header.packet_id, &message) {
+                warn!("转发计数器查询失败: {:?}", e);
+            }
+        },
+        None => warn!("未找到到达 {} 的路由，丢弃计数器查询", toward),
+    }
+}
+
+/// 处理路由表查询和应答：发给本节点的查询用路由表快照应答上一跳，其余按负载中的端到端地址转发
+fn handle_route_table<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    packet: &DataPacket,
+    now: u64
+) {
+    let message = match RouteTableMessage::deserialize(packet.header.packet_type, packet.data) {
+        Some(message) => message,
+        None => return,
+    };
+    
+    let node_id = hardware.get_node_id();
+    if let RouteTableMessage::Request { origin, target, max_entries } = message {
+        if target == node_id {
+            let dump = forwarding_engine.dump(max_entries as usize, now);
+            let response = RouteTableMessage::Response { origin, target, dump };
+            let previous_hop = NodeId(packet.header.source);
+            match send_route_table(hardware, previous_hop, packet.header.packet_id, &response) {
+                Ok(()) => info!("已应答来自 {} 的路由表查询", origin),
+                Err(e) => warn!("发送路由表应答失败: {:?}", e),
+            }
+            return;
+        }
+    }
+    
+    // 查询发往目标，应答发回发起方
+    let toward = match message {
+        RouteTableMessage::Request { target, .. } => target,
+        RouteTableMessage::Response { origin, .. } => origin,
+    };
+    
+    match forwarding_engine.get_next_hop(toward) {
+        Some(next_hop) => {
+            if let Err(e) = send_route_table(hardware, next_hop, packet.header.packet_id, &message) {
+                warn!("转发路由表查询失败: {:?}", e);
+            }
+        },
+        None => warn!("未找到到达 {} 的路由，丢弃路由表查询", toward),
+    }
+}
+
+/// 处理固件更新包：发给本节点的交给接收端，其余按负载中的端到端地址转发
+///
+/// 新镜像安装完成并请求复位后返回true。
+fn handle_ota<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    ota: &mut OtaReceiver,
+    packet: &DataPacket,
+    now: u64
+) -> bool {
+    match ota.handle(hardware, packet, now) {
+        OtaEvent::Ignored => {},
+        OtaEvent::Installed { version } => {
+            info!("固件版本 {} 已安装，重启切换", version);
+            let breadcrumb = RebootBreadcrumb {
+                reason: RebootReason::FirmwareUpdate,
+                requested_by: NodeId(packet.header.source),
+                timestamp: now,
+            };
+            let _ = breadcrumb.store(hardware.get_nvs());
+            return hardware.system_reset().is_ok();
+        },
+        OtaEvent::Failed(status) => {
+            warn!("固件更新失败: {:?}", status);
+            return false;
+        },
+        OtaEvent::Rejected => {
+            warn!("固件更新消息未通过认证，已丢弃");
+            return false;
+        },
+        _ => return false,
+    }
+    
+    let message = match OtaMessage::deserialize(packet.data) {
+        Some(message) => message,
+        None => return false,
+    };
+    
+    match forwarding_engine.get_next_hop(message.target) {
+        Some(next_hop) => {
+            if let Err(e) = send_ota(hardware, next_hop, packet.header.packet_id, &message) {
+                warn!("转发固件更新包失败: {:?}", e);
+            }
+        },
+        None => warn!("未找到到达 {} 的路由，丢弃固件更新包", message.target),
+    }
+    false
+}
+
+/// 用时间信标校准网络时钟，并按本节点时钟重新填写后继续广播；接受信标时返回其主节点
+fn handle_time_sync<H: Hardware>(
+    hardware: &mut H,
+    clock: &mut NetworkClock,
+    leases: &mut LeaseTable,
+    slots: &mut SlotTable,
+    packet: &DataPacket,
+    now: u64
+) -> Option<NodeId> {
+    let beacon = TimeBeacon::deserialize(packet.data)?;
+    let (mut relay, step) = clock.apply(&beacon, now)?;
+    
+    if step != 0 {
+        info!("网络时钟跳变 {}ms，主节点: {}，跳数: {}", step, beacon.master, clock.hop_count());
+        leases.shift(step);
+    }
+    
+    // 主节点随信标下发的时隙表，原样继续广播
+    *slots = SlotTable::from_time_sync(packet.data);
+    
+    // 发送前重新读取时间，补偿本节点的处理时延
+    let now = hardware.get_timestamp_ms().unwrap_or(now);
+    clock.restamp(&mut relay, now);
+    if let Err(e) = send_time_beacon(hardware, &relay, slots) {
+        warn!("转发时间信标失败: {:?}", e);
+    }
+    Some(beacon.master)
+}
+
+/// 向主节点申请发送时隙，本节点就是主节点时直接登记
+fn request_slot<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    clock: &NetworkClock,
+    slot_allocator: &mut SlotAllocator,
+    network_now: u64
+) {
+    let master = match clock.master() {
+        Some(master) => master,
+        None => return,
+    };
+    
+    let node_id = hardware.get_node_id();
+    if master == node_id {
+        slot_allocator.register(node_id, network_now);
+        return;
+    }
+    
+    let next_hop = forwarding_engine.get_next_hop(master).unwrap_or(master);
+    if let Err(e) = send_slot_request(hardware, next_hop, &SlotRequest { node: node_id, master }) {
+        warn!("发送时隙申请失败: {:?}", e);
+    }
+}
+
+/// 主节点为申请的节点分配时隙，中间节点继续发往主节点
+fn handle_slot_request<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    clock: &NetworkClock,
+    slot_allocator: &mut SlotAllocator,
+    packet: &DataPacket,
+    network_now: u64
+) {
+    let request = match SlotRequest::deserialize(packet.data) {
+        Some(request) => request,
+        None => return,
+    };
+    
+    let node_id = hardware.get_node_id();
+    if request.master == node_id {
+        if clock.master() != Some(node_id) {
+            warn!("已不是主节点，忽略 {} 的时隙申请", request.node);
+            return;
+        }
+        match slot_allocator.register(request.node, network_now) {
+            Some(slot) => info!("节点 {} 使用时隙 {}", request.node, slot),
+            None => warn!("时隙已分配完，节点 {} 继续竞争发送", request.node),
+        }
+    } else if NodeId(packet.header.destination) == node_id {
+        let next_hop = forwarding_engine.get_next_hop(request.master).unwrap_or(request.master);
+        if let Err(e) = send_slot_request(hardware, next_hop, &request) {
+            warn!("转发时隙申请失败: {:?}", e);
+        }
+    }
+}
+
+/// 主节点记录其他节点的错误报告，中间节点继续发往主节点
+fn handle_error_report<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    clock: &NetworkClock,
+    packet: &DataPacket
+) {
+    let report = match ErrorReport::deserialize(packet.data) {
+        Some(report) => report,
+        None => return,
+    };
+    let master = match clock.master() {
+        Some(master) => master,
+        None => return,
+    };
+    
+    let node_id = hardware.get_node_id();
+    if master == node_id {
+        warn!("节点 {} 报告错误 {}（{:?}）: {} 次，附加信息 {}，时间 {}",
+              report.origin, report.code.name(), report.code.category(),
+              report.occurrences, report.detail, report.timestamp);
+    } else if NodeId(packet.header.destination) == node_id {
+        let next_hop = forwarding_engine.get_next_hop(master).unwrap_or(master);
+        if let Err(e) = send_error_report(hardware, next_hop, &report) {
+            warn!("转发错误报告失败: {:?}", e);
+        }
+    }
+}
+
+/// 广播路由表，超出单个通告容量时分多个通告发送
+fn advertise_routes<H: Hardware>(hardware: &mut H, forwarding_engine: &ForwardingEngine) {
+    let mut advert = RouteAdvert::new();
+    for route in forwarding_engine.advertised_routes() {
+        if !advert.push(route) {
+            if let Err(e) = send_route_advert(hardware, &advert) {
+                warn!("发送路由通告失败: {:?}", e);
+            }
+            advert = RouteAdvert::new();
+            advert.push(route);
+        }
+    }
+    
+    if !advert.is_empty() {
+        if let Err(e) = send_route_advert(hardware, &advert) {
+            warn!("发送路由通告失败: {:?}", e);
+        }
+    }
+}
+
+/// 按负载中的目标转发管理包，负载原样转发以保留端到端的认证码；全网设置继续广播
+fn forward_mgmt<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    packet: &DataPacket
+) {
+    let target = match MgmtMessage::deserialize(packet.data) {
+        Some((message, _)) => message.target,
+        None => return,
+    };
+    let next_hop = if target.is_broadcast() {
+        Some(NodeId::BROADCAST)
+    } else {
+        forwarding_engine.get_next_hop(target)
+    };
+    
+    let ttl = match forward_ttl(packet) {
+        Some(ttl) => ttl,
+        None => return,
+    };
+    
+    match next_hop {
+        Some(next_hop) => {
+            let node_id = hardware.get_node_id();
+            let forward_packet = DataPacket::with_type(node_id, next_hop, PacketType::Mgmt,
+                                                       packet.header.packet_id, packet.data).with_ttl(ttl);
+            if let Err(e) = send_secure(hardware, &forward_packet) {
+                warn!("转发管理包失败: {:?}", e);
+            }
+        },
+        None => warn!("未找到到达 {} 的路由，丢弃管理包", target),
+    }
+}
+
+/// 转发失败时通知数据源降低发送速率，负载字节1-4为会话（服务）ID
+///
+/// 分片中只有第一个分片带负载头部，其余分片不通知。
+fn notify_congestion<H: Hardware>(hardware: &mut H, packet: &DataPacket) {
+    if packet.data.len() < 5 || packet.header.fragment_index != 0 {
+        return;
+    }
+    
+    let source = NodeId(packet.header.source);
+    let session_id = get_u32be(&packet.data[1..5]);
+    
+    if let Err(e) = send_congestion_notice(hardware, source, packet.header.packet_id, session_id) {
+        warn!("发送拥塞通知失败: {:?}", e);
+    }
+}
+
+/// 解析服务请求数据包
+fn parse_service_request(packet: &DataPacket) -> Option<ServiceQuery> {
+    let client = NodeId(packet.header.source);
+    
+    info!("接收到来自 {} 的服务请求", client);
+    
+    // 反序列化服务请求
+    let request = match deserialize_service_request(packet.data) {
+        Some(request) => request,
+        None => {
+            warn!("无法解析服务请求数据");
+            return None;
+        }
+    };
+    info!("请求的服务类型: {:?}", request.service_type);
+    
+    Some(ServiceQuery { client, packet_id: packet.header.packet_id, request })
+}
+
+/// 为服务请求挑选服务器，返回None表示已转向主节点查询，应答到达后再回复客户端
+///
+/// 本地有完整服务目录时直接挑选；目录代理模式下使用缓存的主节点查询结果。
+fn find_server<H: Hardware>(
+    hardware: &mut H,
+    service_directory: Option<&mut NetworkServiceDirectory>,
+    directory_proxy: &mut DirectoryProxy,
+    forwarding_engine: &mut ForwardingEngine,
+    leases: &LeaseTable,
+    query: &ServiceQuery,
+    current_time: u64
+) -> Option<Option<NodeId>> {
+    // 查询服务目录，按该服务类型的选择策略挑选服务提供者，轮流策略按本节点租约中进行的会话分摊
+    if let Some(directory) = service_directory {
+        let entropy = current_time as u32 ^ query.packet_id as u32;
+        let service_type = query.request.service_type;
+        return Some(directory.select_service(service_type, &query.request.qos, entropy, current_time,
+                                             |server| leases.sessions(server, service_type)));
+    }
+    if let Some(server) = directory_proxy.lookup(&query.request, current_time) {
+        return Some(server);
+    }
+    
+    let master = match directory_proxy.master() {
+        Some(master) => master,
+        None => {
+            warn!("尚未选出主节点，拒绝 {} 的服务请求", query.client);
+            return Some(None);
+        }
+    };
+    
+    match directory_proxy.defer(query, current_time) {
+        Deferred::Query => {
+            let message = LookupMessage::Query { origin: hardware.get_node_id(), master, request: query.request };
+            let next_hop = forwarding_engine.get_next_hop(master).unwrap_or(master);
+            // 发送失败时等待超时后回复客户端
+            if let Err(e) = send_lookup(hardware, next_hop, &message) {
+                warn!("向主节点发送目录查询失败: {:?}", e);
+            }
+            None
+        },
+        Deferred::Waiting => None,
+        Deferred::Full => {
+            warn!("等待应答的目录查询过多，拒绝 {} 的服务请求", query.client);
+            Some(None)
+        },
+    }
+}
+
+/// 按挑选的服务器回复服务请求，成功时记录租约并建立中继路径
+fn reply_service_request<H: Hardware>(
+    hardware: &mut H,
+    leases: &mut LeaseTable,
+    paths: &mut PathTable,
+    control: &mut ReliableSender,
+    query: &ServiceQuery,
+    server: Option<NodeId>,
+    tx_buffer: &mut PacketBuf,
+    current_time: u64
+) {
+    let source = query.client;
+    let service_request = &query.request;
+    
+    if let Some(server) = server {
+        info!("找到最佳服务提供者: {}", server);
+        
+        // 创建服务响应
+        let service_response = ServiceResponse {
+            request_id: service_request.request_id,
+            service_id: current_time as u32, // 使用时间戳作为服务ID
+            server_node_id: server,
+            status: 0, // 成功
+        };
+        
+        // 记录租约，客户端需要在到期前续期
+        leases.grant(
+            service_response.service_id,
+            source,
+            server,
+            service_request.service_type,
+            service_request.request_id,
+            service_request.expiry_time,
+            current_time
+        );
+        
+        // 序列化响应
+        let tx_data = tx_buffer.as_mut_slice();
+        let response_len = serialize_service_response(&service_response, tx_data);
+        
+        if response_len > 0 {
+            // 创建响应数据包
+            let node_id = hardware.get_node_id();
+            let response_packet = DataPacket::with_type(
+                node_id,
+                source,
+                PacketType::ServiceResponse,
+                query.packet_id,
+                &tx_data[..response_len]
+            ).with_flow(flow_id_of(service_response.service_id));
+            
+            // 发送响应
+            if let Err(e) = send_secure(hardware, &response_packet) {
+                warn!("发送服务响应失败: {:?}", e);
+            } else {
+                info!("已发送服务响应给 {}", source);
+            }
+            
+            // 向最佳服务器发送路径建立请求
+            establish_path(hardware, leases, paths, source, server, 
+                          service_response.service_id, service_request.service_type,
+                          &service_request.qos, control, current_time);
+        }
+    } else {
+        warn!("未找到匹配的服务提供者");
+        
+        // 创建失败响应
+        let service_response = ServiceResponse {
+            request_id: service_request.request_id,
+            service_id: 0,
+            server_node_id: NodeId::BROADCAST, // 使用广播地址表示未找到
+            status: 1, // 失败
+        };
+        
+        // 序列化响应
+        let tx_data = tx_buffer.as_mut_slice();
+        let response_len = serialize_service_response(&service_response, tx_data);
+        
+        if response_len > 0 {
+            // 创建响应数据包
+            let node_id = hardware.get_node_id();
+            let response_packet = DataPacket::with_type(
+                node_id,
+                source,
+                PacketType::ServiceResponse,
+                query.packet_id,
+                &tx_data[..response_len]
+            );
+            
+            // 发送响应
+            if let Err(e) = send_secure(hardware, &response_packet) {
+                warn!("发送服务失败响应失败: {:?}", e);
+            }
+        }
+    }
+}
+
+/// 重发已分配服务的成功响应，客户端重传的服务请求不重复分配租约和路径
+fn resend_service_response<H: Hardware>(
+    hardware: &mut H,
+    lease: &ServiceLease,
+    query: &ServiceQuery,
+    tx_buffer: &mut PacketBuf
+) {
+    let service_response = ServiceResponse {
+        request_id: query.request.request_id,
+        service_id: lease.service_id,
+        server_node_id: lease.server,
+        status: 0, // 成功
+    };
+    
+    let tx_data = tx_buffer.as_mut_slice();
+    let response_len = serialize_service_response(&service_response, tx_data);
+    if response_len == 0 {
+        return;
+    }
+    
+    let node_id = hardware.get_node_id();
+    let response_packet = DataPacket::with_type(
+        node_id,
+        query.client,
+        PacketType::ServiceResponse,
+        query.packet_id,
+        &tx_data[..response_len]
+    ).with_flow(flow_id_of(lease.service_id));
+    
+    if let Err(e) = send_secure(hardware, &response_packet) {
+        warn!("重发服务响应失败: {:?}", e);
+    } else {
+        info!("重发服务 {} 的响应给 {}", lease.service_id, query.client);
+    }
+}
+
+/// 处理目录查询：主节点用本地目录回答，中间节点逐跳转发，发给本节点的应答返回给调用方
+fn handle_lookup<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    service_directory: Option<&mut NetworkServiceDirectory>,
+    leases: &LeaseTable,
+    clock: &NetworkClock,
+    packet: &DataPacket,
+    current_time: u64
+) -> Option<LookupMessage> {
+    let message = LookupMessage::deserialize(packet.data)?;
+    
+    let node_id = hardware.get_node_id();
+    match message {
+        LookupMessage::Query { origin, master, request } if master == node_id => {
+            let directory = match service_directory {
+                Some(directory) if clock.master() == Some(node_id) => directory,
+                _ => {
+                    warn!("已不是主节点，忽略 {} 的目录查询", origin);
+                    return None;
+                }
+            };
+            
+            let entropy = hardware.get_timestamp_ms().unwrap_or(0) as u32 ^ packet.header.packet_id as u32;
+            let service_type = request.service_type;
+            let server = directory.select_service(service_type, &request.qos, entropy, current_time,
+                                                  |server| leases.sessions(server, service_type));
+            
+            // 代理的会话记在主节点上，之后的查询才能分摊到其他服务器；需要分摊的策略不让代理缓存应答，
+            // 否则一个有效期内代理的所有会话都会落到同一个服务器
+            let ttl_secs = match directory.policy(service_type) {
+                SelectionPolicy::BestScore => ANSWER_TTL_SECS,
+                _ => {
+                    if let Some(server) = server {
+                        directory.record_remote_session(server, service_type, current_time + request.expiry_time as u64 * 1000);
+                    }
+                    0
+                },
+            };
+            let answer = LookupMessage::Answer { origin, server, ttl_secs, request };
+            let next_hop = forwarding_engine.get_next_hop(origin).unwrap_or(origin);
+            if let Err(e) = send_lookup(hardware, next_hop, &answer) {
+                warn!("发送目录应答失败: {:?}", e);
+            }
+        },
+        LookupMessage::Answer { origin, .. } if origin == node_id => return Some(message),
+        _ if NodeId(packet.header.destination) == node_id => {
+            let target = match message {
+                LookupMessage::Query { master, .. } => master,
+                LookupMessage::Answer { origin, .. } => origin,
+            };
+            let next_hop = forwarding_engine.get_next_hop(target).unwrap_or(target);
+            if let Err(e) = send_lookup(hardware, next_hop, &message) {
+                warn!("转发目录查询失败: {:?}", e);
+            }
+        },
+        _ => {},
+    }
+    
+    None
+}
+
+/// 处理服务租约续期请求
+fn handle_service_renew<H: Hardware>(
+    hardware: &mut H,
+    leases: &mut LeaseTable,
+    packet: &DataPacket,
+    tx_buffer: &mut PacketBuf,
+    current_time: u64
+) {
+    let source = NodeId(packet.header.source);
+    
+    let renewal = match deserialize_service_renewal(packet.data) {
+        Some(renewal) => renewal,
+        None => {
+            warn!("无法解析续期请求数据");
+            return;
+        }
+    };
+    
+    // 续期成功时返回原服务信息，租约不存在时返回失败，客户端需要重新请求服务
+    let service_response = match leases.renew(renewal.service_id, source, renewal.expiry_time, current_time) {
+        Some(lease) => {
+            info!("服务 {} 的租约已续期 {} 秒", lease.service_id, renewal.expiry_time);
+            ServiceResponse {
+                request_id: 0, // 续期请求不携带请求ID
+                service_id: lease.service_id,
+                server_node_id: lease.server,
+                status: 0, // 成功
+            }
+        },
+        None => {
+            info!("服务 {} 的租约不存在或已过期", renewal.service_id);
+            ServiceResponse {
+                request_id: 0,
+                service_id: renewal.service_id,
+                server_node_id: NodeId::BROADCAST,
+                status: 1, // 失败
+            }
+        }
+    };
+    
+    let tx_data = tx_buffer.as_mut_slice();
+    let response_len = serialize_service_response(&service_response, tx_data);
+    
+    if response_len > 0 {
+        let node_id = hardware.get_node_id();
+        let response_packet = DataPacket::with_type(
+            node_id,
+            source,
+            PacketType::ServiceResponse,
+            packet.header.packet_id,
+            &tx_data[..response_len]
+        );
+        
+        if let Err(e) = send_secure(hardware, &response_packet) {
+            warn!("发送续期响应失败: {:?}", e);
+        }
+    }
+}
+
+/// 处理客户端漫游到本节点的服务切换请求，沿用原服务ID和服务器
+fn handle_service_handover<H: Hardware>(
+    hardware: &mut H,
+    leases: &mut LeaseTable,
+    paths: &mut PathTable,
+    control: &mut ReliableSender,
+    packet: &DataPacket,
+    tx_buffer: &mut PacketBuf,
+    current_time: u64
+) {
+    let source = NodeId(packet.header.source);
+    
+    let handover = match deserialize_service_handover(packet.data) {
+        Some(handover) => handover,
+        None => {
+            warn!("无法解析服务切换请求数据");
+            return;
+        }
+    };
+    
+    info!("客户端 {} 将服务 {} 切换到本节点", source, handover.service_id);
+    
+    // 同一服务可能曾经由本节点中继过，先释放旧租约
+    leases.release(handover.service_id);
+    leases.grant(
+        handover.service_id,
+        source,
+        handover.server_node_id,
+        handover.request.service_type,
+        handover.request.request_id,
+        handover.request.expiry_time,
+        current_time
+    );
+    
+    let service_response = ServiceResponse {
+        request_id: handover.request.request_id,
+        service_id: handover.service_id,
+        server_node_id: handover.server_node_id,
+        status: 0, // 成功
+    };
+    
+    let tx_data = tx_buffer.as_mut_slice();
+    let response_len = serialize_service_response(&service_response, tx_data);
+    
+    if response_len > 0 {
+        let node_id = hardware.get_node_id();
+        let response_packet = DataPacket::with_type(
+            node_id,
+            source,
+            PacketType::ServiceResponse,
+            packet.header.packet_id,
+            &tx_data[..response_len]
+        );
+        
+        if let Err(e) = send_secure(hardware, &response_packet) {
+            warn!("发送服务切换响应失败: {:?}", e);
+            return;
+        }
+    }
+    
+    // 经由本节点重新建立到原服务器的路径
+    establish_path(hardware, leases, paths, source, handover.server_node_id, handover.service_id,
+                   handover.request.service_type, &handover.request.qos, control, current_time);
+}
+
+/// 应答客户端的路径保活：本节点仍为其中继该服务时回复确认并刷新路径的空闲计时，否则不应答
+fn handle_keepalive<H: Hardware>(
+    hardware: &mut H,
+    leases: &LeaseTable,
+    paths: &mut PathTable,
+    packet: &DataPacket,
+    current_time: u64
+) {
+    let source = NodeId(packet.header.source);
+    let keepalive = match PathKeepAlive::deserialize(packet.data) {
+        Some(keepalive) => keepalive,
+        None => return,
+    };
+    
+    if !leases.iter().any(|lease| lease.service_id == keepalive.service_id && lease.client == source) {
+        warn!("没有服务 {} 的租约，不应答 {} 的路径保活", keepalive.service_id, source);
+        return;
+    }
+    
+    paths.touch(flow_id_of(keepalive.service_id), current_time);
+    if let Err(e) = send_keepalive(hardware, source, PacketType::PathKeepAliveAck, &keepalive) {
+        warn!("发送路径保活确认失败: {:?}", e);
+    }
+}
+
+/// 处理服务关闭请求：释放本节点的租约和路径预留，并沿路径通知服务器释放会话状态
+fn handle_service_close<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    leases: &mut LeaseTable,
+    paths: &mut PathTable,
+    packet: &DataPacket
+) {
+    let source = NodeId(packet.header.source);
+    let destination = NodeId(packet.header.destination);
+    
+    let close = match deserialize_service_close(packet.data) {
+        Some(close) => close,
+        None => {
+            warn!("无法解析服务关闭请求数据");
+            return;
+        }
+    };
+    
+    if destination != hardware.get_node_id() {
+        // 经过本节点发往服务器的通知，释放本节点的预留后继续转发
+        paths.release(close.service_id);
+        
+        let ttl = match forward_ttl(packet) {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        
+        match forwarding_engine.get_next_hop(destination) {
+            Some(next_hop) => {
+                let node_id = hardware.get_node_id();
+                let forward_packet = DataPacket::with_type(
+                    node_id,
+                    next_hop,
+                    PacketType::ServiceClose,
+                    packet.header.packet_id,
+                    packet.data
+                ).with_flow(packet.header.flow_id).with_ttl(ttl);
+                
+                if let Err(e) = send_secure(hardware, &forward_packet) {
+                    warn!("转发服务关闭请求失败: {:?}", e);
+                }
+            },
+            None => warn!("未找到到达 {} 的路由，服务关闭请求未送达", destination),
+        }
+        return;
+    }
+    
+    // 客户端发来的请求，只有租约的客户端可以关闭服务
+    if !leases.iter().any(|lease| lease.service_id == close.service_id && lease.client == source) {
+        warn!("{} 没有服务 {} 的租约，忽略关闭请求", source, close.service_id);
+        return;
+    }
+    let lease = match leases.release(close.service_id) {
+        Some(lease) => lease,
+        None => return,
+    };
+    paths.release(close.service_id);
+    info!("客户端 {} 关闭服务 {}", source, close.service_id);
+    
+    // 通知服务器释放会话状态，与路径建立请求一样直接发往服务器
+    let node_id = hardware.get_node_id();
+    let close_packet = DataPacket::with_type(
+        node_id,
+        lease.server,
+        PacketType::ServiceClose,
+        packet.header.packet_id,
+        packet.data
+    ).with_flow(packet.header.flow_id);
+    
+    if let Err(e) = send_secure(hardware, &close_packet) {
+        warn!("通知服务器关闭服务失败: {:?}", e);
+    }
+}
+
+/// 建立中继路径，本节点带宽不足时直接通知客户端并收回租约，会话不再计入服务器的负担
+///
+/// 路径建立经可靠发送端发出，收到服务器的路径确认前按退避重传。
+fn establish_path<H: Hardware>(
+    hardware: &mut H,
+    leases: &mut LeaseTable,
+    paths: &mut PathTable,
+    client: NodeId,
+    server: NodeId,
+    service_id: u32,
+    service_type: ServiceType,
+    qos: &QosRequirements,
+    control: &mut ReliableSender,
+    current_time: u64
+) {
+    info!("建立从 {} 到 {} 的中继路径", client, server);
+    
+    if paths.reserve(service_id, client, server, qos.min_bandwidth, current_time) != PathStatus::Success {
+        leases.release(service_id);
+        reject_path(hardware, control, client, service_id, current_time);
+        return;
+    }
+    
+    // 创建路径建立请求数据
+    let mut path_data = [0u8; 20];
+    
+    // 填充路径建立请求
+    // 0-5: 客户端节点ID
+    path_data[0..6].copy_from_slice(&client.0);
+    
+    // 6: 服务类型
+    path_data[6] = service_type as u8;
+    
+    // 7-8: 最小带宽
+    put_u16be(&mut path_data[7..9], qos.min_bandwidth);
+    
+    // 9-10: 最大延迟
+    put_u16be(&mut path_data[9..11], qos.max_latency);
+    
+    // 11: 可靠性
+    path_data[11] = qos.reliability;
+    
+    // 12-15: 服务ID，由服务器在路径确认中原样带回
+    put_u32be(&mut path_data[12..16], service_id);
+    
+    // 发送路径建立请求，会话为服务ID
+    match control.send_typed(hardware, service_id, server, PacketType::PathEstablish, &path_data, current_time) {
+        Ok(_) => info!("已发送路径建立请求给服务器 {}", server),
+        Err(e) => warn!("发送路径建立请求失败: {:?}", e),
+    }
+}
+
+/// 处理路径建立数据包
+fn handle_path_establish<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    paths: &mut PathTable,
+    control: &mut ReliableSender,
+    packet: &DataPacket,
+    current_time: u64
+) {
+    let source = NodeId(packet.header.source);
+    let destination = NodeId(packet.header.destination);
+    
+    info!("接收到来自 {} 的路径建立请求", source);
+    
+    if destination != hardware.get_node_id() {
+        // 如果不是发给本节点的，转发
+        let ttl = match forward_ttl(packet) {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        
+        // 中继的路径同样占用本节点的带宽，不足时拒绝而不是继续转发；路径确认沿原路返回给上一跳
+        if packet.data.len() >= 16 {
+            let mut client_id = [0u8; 6];
+            client_id.copy_from_slice(&packet.data[0..6]);
+            let client = NodeId(client_id);
+            let min_bandwidth = get_u16be(&packet.data[7..9]);
+            let service_id = get_u32be(&packet.data[12..16]);
+            
+            if paths.reserve(service_id, client, destination, min_bandwidth, current_time) != PathStatus::Success {
+                reject_path(hardware, control, client, service_id, current_time);
+                return;
+            }
+            paths.set_upstream(service_id, source);
+        }
+        
+        if let Some(next_hop) = forwarding_engine.get_next_hop(destination) {
+            // 头部保留端到端的目标和包类型，发给下一跳
+            let node_id = hardware.get_node_id();
+            let forward_packet = DataPacket::with_type(
+                node_id,
+                destination,
+                PacketType::PathEstablish,
+                packet.header.packet_id,
+                packet.data
+            ).with_flow(packet.header.flow_id).with_ttl(ttl);
+            
+            // 发送转发的数据包
+            if let Err(e) = send_secure_to(hardware, next_hop, &forward_packet) {
+                warn!("转发路径建立请求失败: {:?}", e);
+            } else {
+                info!("已转发路径建立请求到 {}", next_hop);
+            }
+        }
+    } else if answer_path_establish(hardware, packet) {
+        // 本节点是路径的终点
+        info!("已发送路径确认给 {}", source);
+    } else {
+        warn!("发送路径确认失败");
+    }
+}
+
+/// 本节点带宽不足，向客户端回复资源不足的路径确认，客户端确认前重传
+fn reject_path<H: Hardware>(hardware: &mut H, control: &mut ReliableSender, client: NodeId, service_id: u32, current_time: u64) {
+    // 客户端ID(6) 状态(1) 跳数(1) 服务ID(4)
+    let mut confirm_data = [0u8; PATH_CONFIRM_LEN];
+    confirm_data[0..6].copy_from_slice(&client.0);
+    confirm_data[6] = PathStatus::NoResource as u8;
+    put_u32be(&mut confirm_data[8..12], service_id);
+    
+    if let Err(e) = control.send_typed(hardware, service_id, client, PacketType::PathConfirm, &confirm_data, current_time) {
+        warn!("发送路径拒绝失败: {:?}", e);
+    }
+}
+
+/// 处理路径确认数据包
+///
+/// 本节点发起的路径建立就此完成，停止重传，确认经可靠发送端转给客户端；
+/// 中间的中继把确认转给转来路径建立的上一跳。
+fn handle_path_confirm<H: Hardware>(
+    hardware: &mut H,
+    leases: &mut LeaseTable,
+    paths: &mut PathTable,
+    control: &mut ReliableSender,
+    packet: &DataPacket,
+    current_time: u64
+) {
+    let source = NodeId(packet.header.source);
+    
+    info!("接收到来自 {} 的路径确认", source);
+    
+    if packet.data.len() >= PATH_CONFIRM_LEN {
+        // 提取客户端ID
+        let mut client_id = [0u8; 6];
+        client_id.copy_from_slice(&packet.data[0..6]);
+        let client = NodeId(client_id);
+        
+        // 提取路径状态
+        let status = packet.data[6];
+        
+        // 提取跳数
+        let hops = packet.data[7];
+        
+        let service_id = get_u32be(&packet.data[8..12]);
+        let upstream = paths.get(service_id).map_or(client, |path| path.upstream);
+        control.complete(service_id, packet.header.packet_id);
+        
+        info!("路径确认：客户端={:?}, 状态={}, 跳数={}", client, status, hops);
+        
+        // 路径建立失败时释放本节点为其预留的带宽和租约
+        if status != PathStatus::Success as u8 {
+            paths.release(service_id);
+            leases.release(service_id);
+        }
+        
+        // 更新跳数后转给上一跳
+        let mut forward_data = [0u8; PATH_CONFIRM_LEN];
+        forward_data.copy_from_slice(&packet.data[..PATH_CONFIRM_LEN]);
+        forward_data[7] = hops + 1; // 增加跳数
+        
+        let result = if upstream == client {
+            control.send_typed(hardware, service_id, client, PacketType::PathConfirm, &forward_data, current_time).map(|_| ())
+        } else {
+            let node_id = hardware.get_node_id();
+            let confirm_packet = DataPacket::with_type(
+                node_id,
+                upstream,
+                PacketType::PathConfirm,
+                packet.header.packet_id,
+                &forward_data
+            ).with_flow(packet.header.flow_id);
+            send_secure(hardware, &confirm_packet).map_err(|_| ReliableError::SendFailed)
+        };
+        
+        match result {
+            Ok(()) => info!("已转发路径确认给 {}", upstream),
+            Err(e) => warn!("转发路径确认给 {} 失败: {:?}", upstream, e),
+        }
+    }
+}
+
+/// 处理其他类型的数据包
+fn handle_other_packet<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    scheduler: &mut TxScheduler,
+    discovery: &mut RouteDiscovery,
+    floods: &mut FloodRelay,
+    packet: &DataPacket,
+    now: u64
+) {
+    let source = NodeId(packet.header.source);
+    let destination = NodeId(packet.header.destination);
+    
+    info!("接收到来自 {} 发往 {} 的其他类型数据包，类型: {:?}",
+        source, destination, packet.header.packet_type);
+    
+    // 如果不是发给本节点的，尝试转发
+    if destination != hardware.get_node_id() && !destination.is_broadcast() {
+        let ttl = match forward_ttl(packet) {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        
+        // 其他类型按控制流量优先发送，下一跳在查到路由后填写，头部保留端到端的目标和包类型
+        let packet_type = PacketType::from_u8(packet.header.packet_type).unwrap_or(PacketType::Data);
+        let queued = QueuedPacket::new(hardware.packet_pool(), destination, packet_type, packet.header.packet_id, packet.data)
+            .map(|queued| queued.with_destination(destination)
+                .with_flow(packet.header.flow_id)
+                .with_fragment(packet.header.total_fragments, packet.header.fragment_index)
+                .with_ttl(ttl));
+        
+        if let Some(next_hop) = forwarding_engine.get_next_hop(destination) {
+            forwarding_engine.route_used(destination, now);
+            let accepted = queued.map_or(false, |mut queued| {
+                queued.next_hop = next_hop;
+                scheduler.enqueue(TrafficClass::Control, queued).is_ok()
+            });
+            if accepted {
+                metrics::increment(Counter::PacketsForwarded);
+            } else {
+                metrics::increment(Counter::PacketsDropped);
+                warn!("发送队列已满，丢弃发往 {} 的数据包", destination);
+                notify_congestion(hardware, packet);
+            }
+        } else if forwarding_engine.strategy().discovers_on_demand() {
+            hold_for_route(hardware, discovery, floods, destination, TrafficClass::Control, queued, now);
+        } else {
+            metrics::increment(Counter::PacketsDropped);
+            warn!("未找到到达 {} 的路由，丢弃数据包", destination);
+        }
+    }
+} 
\ No newline at end of file
@@ -0,0 +1,60 @@
+use common::hal::Hardware;
+use common::config::NodeConfig;
+use common::mgmt::Managed;
+use common::protocol::NodeId;
+use common::protocol::mgmt::{MgmtAttribute, MgmtStatus};
+use crate::routing::dynamic_forwarding::ForwardingEngine;
+
+/// 路由表导出时每个表项的长度：目的(6) 下一跳(6) 度量(1)
+const ROUTE_ENTRY_LEN: usize = 13;
+
+/// 转发节点的可管理状态：运行配置加只读的路由表
+pub struct ForwardNode<'a> {
+    pub config: &'a mut NodeConfig,
+    pub forwarding_engine: &'a ForwardingEngine,
+}
+
+impl<'a> Managed for ForwardNode<'a> {
+    fn get_attribute<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        attribute: MgmtAttribute,
+        arg: &[u8],
+        out: &mut [u8]
+    ) -> Result<usize, MgmtStatus> {
+        if attribute != MgmtAttribute::RoutingTable {
+            return self.config.get_attribute(hardware, attribute, arg, out);
+        }
+        
+        // 路由表可能放不进一个应答，按参数中的起始序号分页读取
+        let start = arg.first().copied().unwrap_or(0) as usize;
+        let mut len = 1;
+        let mut total = 0u8;
+        for (index, (destination, next_hop, metric)) in self.forwarding_engine.routes().enumerate() {
+            total = total.saturating_add(1);
+            if index < start || len + ROUTE_ENTRY_LEN > out.len() {
+                continue;
+            }
+            out[len..len + 6].copy_from_slice(&destination.0);
+            out[len + 6..len + 12].copy_from_slice(&next_hop.0);
+            out[len + 12] = metric as u8;
+            len += ROUTE_ENTRY_LEN;
+        }
+        out[0] = total;
+        
+        Ok(len)
+    }
+    
+    fn set_attribute<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        attribute: MgmtAttribute,
+        value: &[u8]
+    ) -> Result<(), MgmtStatus> {
+        self.config.set_attribute(hardware, attribute, value)
+    }
+    
+    fn is_admin(&self, node: NodeId) -> bool {
+        self.config.is_admin(node)
+    }
+}
\ No newline at end of file
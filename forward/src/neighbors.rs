@@ -0,0 +1,310 @@
+use common::config::{ConfigChanges, ConfigObserver, NodeConfig};
+use common::hal::Hardware;
+use common::hal::nvs::{keys, NvStorage};
+use common::protocol::{Beacon, DataPacket, NodeId, NodeRole, PacketType};
+use common::protocol::hello::{hello_nonce, send_hello};
+use common::protocol::route_advert::ETX_SCALE;
+use common::warn;
+
+/// 同时跟踪的最大邻居数
+pub const MAX_NEIGHBORS: usize = 16;
+
+/// 未收到应答时重发HELLO的间隔（毫秒）
+const HELLO_RETRY_MS: u64 = 5000;
+
+/// 默认的保活间隔（毫秒）：验证后多久开始重新验证，重新验证期间链路仍视为可用
+const DEFAULT_KEEPALIVE_MS: u64 = 120_000;
+
+/// 验证的有效期是保活间隔的倍数（以半个间隔计），容许重新验证失败后再重试一段时间
+const VERIFIED_TTL_HALF_INTERVALS: u64 = 5;
+
+/// 检查点中每个邻居的长度：节点ID(6) 信号强度(1)
+const CHECKPOINT_NEIGHBOR_LEN: usize = 7;
+
+/// 每个邻居保留的信号强度样本数
+const RSSI_HISTORY_LEN: usize = 8;
+
+/// 信标投递率的统计窗口：应收到的信标数达到该值后两个计数都减半，旧的统计逐渐淡出
+const DELIVERY_WINDOW: u8 = 16;
+
+/// 相邻两个信标的序号相差超过该值时视为对方重启，不计为丢失
+const MAX_SEQUENCE_GAP: u8 = 32;
+
+/// 到一个邻居的链路质量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkQuality {
+    /// 最近几次信标的平均信号强度（dBm）
+    pub rssi: i8,
+    /// 信标投递率（百分比）
+    pub delivery: u8,
+    /// 每成功传输一帧期望的发送次数（ETX），以[`ETX_SCALE`]为单位
+    pub etx: u16,
+    /// 邻居最近一次信标报告的电池电量（百分比）
+    pub battery: u8,
+    /// 邻居电量告急，声明不再为其他节点中继
+    pub relay_unwilling: bool,
+}
+
+/// 由投递率估计ETX
+///
+/// ETX = 1 / (正向投递率 × 反向投递率)。对方不报告它收到了多少本节点的信标，
+/// 这里假设链路对称，两个方向的投递率相同。
+fn estimate_etx(delivery: u8) -> u16 {
+    let delivery = delivery.max(1) as u32;
+    (ETX_SCALE as u32 * 100 * 100 / (delivery * delivery)).min(u16::MAX as u32) as u16
+}
+
+/// 邻居的验证状态和链路统计
+#[derive(Debug, Clone, Copy)]
+struct Neighbor {
+    node: NodeId,
+    /// 最近几次信标的信号强度，环形存放
+    rssi_history: [i8; RSSI_HISTORY_LEN],
+    /// 历史中的样本数
+    rssi_samples: u8,
+    /// 下一个样本写入的位置
+    rssi_next: u8,
+    /// 最近一次信标的序号
+    last_sequence: Option<u8>,
+    /// 最近一次信标报告的电池电量
+    battery: u8,
+    /// 最近一次信标是否声明不再中继
+    relay_unwilling: bool,
+    /// 信标声明的节点角色
+    role: NodeRole,
+    /// 统计窗口内收到的信标数
+    beacons_received: u8,
+    /// 统计窗口内应收到的信标数，按序号间隔计算
+    beacons_expected: u8,
+    /// 最近一次HELLO的随机数
+    nonce: u32,
+    /// 最近一次发送HELLO的时间
+    hello_sent_at: Option<u64>,
+    /// 最近一次收到匹配应答的时间
+    verified_at: Option<u64>,
+}
+
+impl Neighbor {
+    fn new(node: NodeId) -> Self {
+        Self {
+            node,
+            rssi_history: [0; RSSI_HISTORY_LEN],
+            rssi_samples: 0,
+            rssi_next: 0,
+            last_sequence: None,
+            battery: 100,
+            relay_unwilling: false,
+            role: NodeRole::Unknown,
+            beacons_received: 0,
+            beacons_expected: 0,
+            nonce: 0,
+            hello_sent_at: None,
+            verified_at: None,
+        }
+    }
+    
+    fn record_rssi(&mut self, rssi: i8) {
+        self.rssi_history[self.rssi_next as usize] = rssi;
+        self.rssi_next = (self.rssi_next + 1) % RSSI_HISTORY_LEN as u8;
+        self.rssi_samples = (self.rssi_samples + 1).min(RSSI_HISTORY_LEN as u8);
+    }
+    
+    /// 记录收到的信标序号；序号不变（旧版本节点恒为0）时无法判断丢失，按没有丢失计
+    fn record_sequence(&mut self, sequence: u8) {
+        let gap = match self.last_sequence.map(|last| sequence.wrapping_sub(last)) {
+            Some(gap) if gap > 0 && gap <= MAX_SEQUENCE_GAP => gap,
+            _ => 1,
+        };
+        self.last_sequence = Some(sequence);
+        self.beacons_expected += gap;
+        self.beacons_received += 1;
+        if self.beacons_expected >= DELIVERY_WINDOW {
+            self.beacons_expected = self.beacons_expected.div_ceil(2);
+            self.beacons_received = self.beacons_received.div_ceil(2);
+        }
+    }
+    
+    fn quality(&self) -> LinkQuality {
+        let rssi = match self.rssi_samples as usize {
+            0 => 0,
+            samples => {
+                let sum: i32 = self.rssi_history[..samples].iter().map(|&rssi| rssi as i32).sum();
+                (sum / samples as i32) as i8
+            },
+        };
+        let delivery = match self.beacons_expected {
+            0 => 100,
+            expected => (self.beacons_received as u16 * 100 / expected as u16) as u8,
+        };
+        
+        LinkQuality {
+            rssi,
+            delivery,
+            etx: estimate_etx(delivery),
+            battery: self.battery,
+            relay_unwilling: self.relay_unwilling,
+        }
+    }
+    
+    fn is_verified(&self, now: u64, keepalive_ms: u64) -> bool {
+        let ttl = keepalive_ms * VERIFIED_TTL_HALF_INTERVALS / 2;
+        matches!(self.verified_at, Some(at) if now - at < ttl)
+    }
+    
+    fn needs_hello(&self, now: u64, keepalive_ms: u64) -> bool {
+        let stale = self.verified_at.map_or(true, |at| now - at >= keepalive_ms);
+        let retry = self.hello_sent_at.map_or(true, |at| now - at >= HELLO_RETRY_MS);
+        stale && retry
+    }
+}
+
+/// 邻居双向验证：直接听到信标只说明对方到本节点的链路可用，
+/// 向对方发送HELLO并收到HELLO-ACK后才把对方视为可用的下一跳
+///
+/// 同时按信标统计每个邻居的信号强度历史和投递率，估计的ETX作为路由代价。
+pub struct NeighborTable {
+    neighbors: [Option<Neighbor>; MAX_NEIGHBORS],
+    next_nonce: u32,
+    /// 保活间隔（毫秒）
+    keepalive_ms: u64,
+    /// 上次写入检查点后是否有邻居加入或被替换
+    dirty: bool,
+}
+
+impl NeighborTable {
+    /// 创建空的邻居表
+    pub fn new() -> Self {
+        Self {
+            neighbors: [None; MAX_NEIGHBORS],
+            next_nonce: 1,
+            keepalive_ms: DEFAULT_KEEPALIVE_MS,
+            dirty: false,
+        }
+    }
+    
+    /// 邻居有变化时写入检查点，返回是否写入
+    pub fn checkpoint<N: NvStorage>(&mut self, nvs: &mut N) -> Result<bool, N::Error> {
+        if !self.dirty {
+            return Ok(false);
+        }
+        
+        let mut bytes = [0u8; 1 + CHECKPOINT_NEIGHBOR_LEN * MAX_NEIGHBORS];
+        let mut count = 0;
+        for neighbor in self.neighbors.iter().flatten() {
+            let offset = 1 + count * CHECKPOINT_NEIGHBOR_LEN;
+            bytes[offset..offset + 6].copy_from_slice(&neighbor.node.0);
+            bytes[offset + 6] = neighbor.quality().rssi as u8;
+            count += 1;
+        }
+        bytes[0] = count as u8;
+        
+        nvs.nvs_write(keys::NEIGHBOR_TABLE, &bytes[..1 + count * CHECKPOINT_NEIGHBOR_LEN])?;
+        self.dirty = false;
+        Ok(true)
+    }
+    
+    /// 启动时从检查点恢复邻居及其信号强度，返回恢复的邻居数
+    ///
+    /// 恢复的邻居都未验证，下次听到信标时重新交换HELLO；投递率从头统计。
+    pub fn restore<N: NvStorage>(&mut self, nvs: &mut N) -> usize {
+        let mut bytes = [0u8; 1 + CHECKPOINT_NEIGHBOR_LEN * MAX_NEIGHBORS];
+        let len = match nvs.nvs_read(keys::NEIGHBOR_TABLE, &mut bytes) {
+            Ok(Some(len)) => len,
+            _ => return 0,
+        };
+        
+        let count = (bytes[0] as usize).min(MAX_NEIGHBORS).min(len.saturating_sub(1) / CHECKPOINT_NEIGHBOR_LEN);
+        for (entry, chunk) in self.neighbors.iter_mut().zip(bytes[1..].chunks_exact(CHECKPOINT_NEIGHBOR_LEN).take(count)) {
+            let mut node = [0u8; 6];
+            node.copy_from_slice(&chunk[0..6]);
+            let mut neighbor = Neighbor::new(NodeId(node));
+            neighbor.record_rssi(chunk[6] as i8);
+            *entry = Some(neighbor);
+        }
+        
+        count
+    }
+    
+    /// 直接听到邻居的信标时调用，记录信号强度、信标序号和电量；需要验证时向对方发送HELLO
+    ///
+    /// 链路已验证为双向时返回链路质量。
+    pub fn observe<H: Hardware>(&mut self, hardware: &mut H, beacon: &Beacon, rssi: i8, now: u64) -> Option<LinkQuality> {
+        let node = NodeId(beacon.source);
+        let index = self.find_or_insert(node, now)?;
+        
+        let mut neighbor = self.neighbors[index].unwrap();
+        neighbor.record_rssi(rssi);
+        neighbor.record_sequence(beacon.sequence());
+        neighbor.battery = beacon.battery_level;
+        neighbor.relay_unwilling = beacon.relay_unwilling();
+        neighbor.role = beacon.role();
+        if neighbor.needs_hello(now, self.keepalive_ms) {
+            // 随机数只用于匹配应答，包本身已有认证保护
+            neighbor.nonce = self.next_nonce ^ now as u32;
+            self.next_nonce = self.next_nonce.wrapping_add(1);
+            neighbor.hello_sent_at = Some(now);
+            if let Err(e) = send_hello(hardware, node, PacketType::Hello, neighbor.nonce) {
+                warn!("向 {} 发送HELLO失败: {:?}", node, e);
+            }
+        }
+        self.neighbors[index] = Some(neighbor);
+        
+        neighbor.is_verified(now, self.keepalive_ms).then(|| neighbor.quality())
+    }
+    
+    /// 处理HELLO-ACK，随机数匹配时标记链路为双向，返回该邻居和链路质量
+    pub fn handle_ack(&mut self, packet: &DataPacket, now: u64) -> Option<(NodeId, LinkQuality)> {
+        let node = NodeId(packet.header.source);
+        let nonce = hello_nonce(packet)?;
+        
+        let neighbor = self.neighbors.iter_mut()
+            .flatten()
+            .find(|neighbor| neighbor.node == node && neighbor.hello_sent_at.is_some() && neighbor.nonce == nonce)?;
+        neighbor.verified_at = Some(now);
+        neighbor.hello_sent_at = None;
+        Some((node, neighbor.quality()))
+    }
+    
+    /// 到指定邻居的链路是否已验证为双向
+    pub fn is_verified(&self, node: NodeId, now: u64) -> bool {
+        self.neighbors.iter()
+            .flatten()
+            .any(|neighbor| neighbor.node == node && neighbor.is_verified(now, self.keepalive_ms))
+    }
+    
+    /// 邻居是否为转发节点；转发节点把转发的包发给选好的下一跳，旁听到时不需要再转发
+    pub fn is_forwarder(&self, node: NodeId) -> bool {
+        self.neighbors.iter()
+            .flatten()
+            .any(|neighbor| neighbor.node == node && neighbor.role == NodeRole::Forward)
+    }
+    
+    /// 到已验证邻居的链路质量，未验证时返回None
+    pub fn link_quality(&self, node: NodeId, now: u64) -> Option<LinkQuality> {
+        self.neighbors.iter()
+            .flatten()
+            .find(|neighbor| neighbor.node == node && neighbor.is_verified(now, self.keepalive_ms))
+            .map(|neighbor| neighbor.quality())
+    }
+    
+    /// 查找邻居，不存在时占用空闲位置，表满时替换一个未验证的邻居
+    fn find_or_insert(&mut self, node: NodeId, now: u64) -> Option<usize> {
+        if let Some(index) = self.neighbors.iter().position(|entry| matches!(entry, Some(n) if n.node == node)) {
+            return Some(index);
+        }
+        
+        let index = self.neighbors.iter().position(|entry| entry.is_none())
+            .or_else(|| self.neighbors.iter().position(|entry| matches!(entry, Some(n) if !n.is_verified(now, self.keepalive_ms))))?;
+        self.dirty = true;
+        self.neighbors[index] = Some(Neighbor::new(node));
+        Some(index)
+    }
+}
+
+impl ConfigObserver for NeighborTable {
+    fn config_changed(&mut self, config: &NodeConfig, changes: ConfigChanges) {
+        if changes.keepalive {
+            self.keepalive_ms = config.keepalive_ms as u64;
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,167 @@
+use common::config::{ConfigChanges, ConfigObserver, NodeConfig};
+use common::metrics::{self, Counter, Gauge};
+use common::protocol::{NodeId, PathStatus};
+use common::protocol::data::{flow_id_of, NO_FLOW};
+use common::{info, warn};
+
+/// 最多同时经过本节点的路径数
+const MAX_PATHS: usize = 16;
+
+/// 路径多久没有数据经过视为已断开，回收预留的带宽（毫秒）
+pub const PATH_IDLE_TIMEOUT_MS: u64 = 60000;
+
+/// 经过本节点的一条路径及其预留的带宽
+#[derive(Debug, Clone, Copy)]
+pub struct PathReservation {
+    /// 服务ID
+    pub service_id: u32,
+    /// 客户端节点
+    pub client: NodeId,
+    /// 服务器节点
+    pub server: NodeId,
+    /// 路径确认沿路返回时的上一跳：发起路径建立的中继为客户端，中间的中继为转来路径建立的节点
+    pub upstream: NodeId,
+    /// 预留的带宽（kbps）
+    pub reserved_kbps: u16,
+    /// 最近一次有数据经过的时间
+    pub last_active: u64,
+}
+
+/// 路径表，按QoS要求的最小带宽做准入控制
+pub struct PathTable {
+    paths: [Option<PathReservation>; MAX_PATHS],
+    capacity_kbps: u32,
+}
+
+impl PathTable {
+    /// 创建可预留指定带宽的路径表
+    pub fn new(capacity_kbps: u32) -> Self {
+        Self {
+            paths: [None; MAX_PATHS],
+            capacity_kbps,
+        }
+    }
+    
+    /// 为路径预留带宽，同一服务的旧预留先释放；带宽不足或表已满时返回[`PathStatus::NoResource`]
+    pub fn reserve(
+        &mut self,
+        service_id: u32,
+        client: NodeId,
+        server: NodeId,
+        reserved_kbps: u16,
+        current_time: u64
+    ) -> PathStatus {
+        let existing = self.position(service_id);
+        let others = self.reserved_kbps() - existing.map_or(0, |index| self.paths[index].unwrap().reserved_kbps as u32);
+        
+        let slot = existing.or_else(|| self.paths.iter().position(|entry| entry.is_none()));
+        let slot = match slot {
+            Some(slot) if others + reserved_kbps as u32 <= self.capacity_kbps => slot,
+            _ => {
+                warn!("带宽不足，拒绝服务 {} 的路径：需要 {} kbps，已预留 {}/{} kbps",
+                      service_id, reserved_kbps, others, self.capacity_kbps);
+                metrics::increment(Counter::PathsRejected);
+                return PathStatus::NoResource;
+            },
+        };
+        
+        self.paths[slot] = Some(PathReservation {
+            service_id,
+            client,
+            server,
+            upstream: client,
+            reserved_kbps,
+            last_active: current_time,
+        });
+        metrics::set(Gauge::ReservedBandwidth, self.reserved_kbps());
+        PathStatus::Success
+    }
+    
+    /// 属于某个流的数据经过本节点，刷新对应路径的空闲计时
+    pub fn touch(&mut self, flow_id: u16, current_time: u64) {
+        if flow_id == NO_FLOW {
+            return;
+        }
+        for path in self.paths.iter_mut().flatten() {
+            if flow_id_of(path.service_id) == flow_id {
+                path.last_active = current_time;
+            }
+        }
+    }
+    
+    /// 记录路径确认应返回给哪个节点，默认为客户端
+    pub fn set_upstream(&mut self, service_id: u32, upstream: NodeId) {
+        if let Some(index) = self.position(service_id) {
+            if let Some(path) = self.paths[index].as_mut() {
+                path.upstream = upstream;
+            }
+        }
+    }
+    
+    /// 查找服务的路径
+    pub fn get(&self, service_id: u32) -> Option<&PathReservation> {
+        self.paths[self.position(service_id)?].as_ref()
+    }
+    
+    /// 拆除路径并释放预留的带宽
+    pub fn release(&mut self, service_id: u32) -> Option<PathReservation> {
+        let released = self.paths[self.position(service_id)?].take();
+        metrics::set(Gauge::ReservedBandwidth, self.reserved_kbps());
+        released
+    }
+    
+    /// 回收空闲超时的路径，返回回收数量
+    pub fn expire(&mut self, current_time: u64) -> usize {
+        let mut expired = 0;
+        for entry in self.paths.iter_mut() {
+            if let Some(path) = entry {
+                if current_time.saturating_sub(path.last_active) > PATH_IDLE_TIMEOUT_MS {
+                    info!("服务 {} 的路径空闲超时，释放 {} kbps", path.service_id, path.reserved_kbps);
+                    *entry = None;
+                    expired += 1;
+                }
+            }
+        }
+        
+        metrics::set(Gauge::ReservedBandwidth, self.reserved_kbps());
+        expired
+    }
+    
+    /// 已预留的总带宽（kbps）
+    pub fn reserved_kbps(&self) -> u32 {
+        self.paths.iter().flatten().map(|path| path.reserved_kbps as u32).sum()
+    }
+    
+    /// 调整可预留的总带宽，已有的预留保留到拆除或超时
+    pub fn set_capacity(&mut self, capacity_kbps: u32) {
+        self.capacity_kbps = capacity_kbps;
+    }
+    
+    /// 剩余可预留的带宽（kbps）
+    pub fn available_kbps(&self) -> u32 {
+        self.capacity_kbps.saturating_sub(self.reserved_kbps())
+    }
+    
+    /// 遍历所有路径
+    pub fn iter(&self) -> impl Iterator<Item = &PathReservation> {
+        self.paths.iter().flatten()
+    }
+    
+    /// 当前路径数
+    pub fn len(&self) -> usize {
+        self.paths.iter().flatten().count()
+    }
+    
+    /// 服务对应的路径位置
+    fn position(&self, service_id: u32) -> Option<usize> {
+        self.paths.iter().position(|entry| matches!(entry, Some(path) if path.service_id == service_id))
+    }
+}
+
+impl ConfigObserver for PathTable {
+    fn config_changed(&mut self, config: &NodeConfig, changes: ConfigChanges) {
+        if changes.paths {
+            self.set_capacity(config.path_capacity_kbps as u32);
+        }
+    }
+}
\ No newline at end of file
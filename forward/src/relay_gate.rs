@@ -0,0 +1,50 @@
+use common::config::{ConfigChanges, ConfigObserver, NodeConfig};
+
+/// 电量恢复到告急阈值以上多少个百分点后才重新中继
+pub const RELAY_RESUME_MARGIN: u8 = 5;
+
+/// 按电量决定本节点是否为其他节点中继
+///
+/// 转发靠旁听而不是按下一跳寻址，信标中的不中继标志只能让邻居的路由绕开本节点，
+/// 电量告急时本节点还要自己停止转发旁听到的包；恢复中继需要电量超过阈值加回差，
+/// 电量在阈值附近波动时不会反复切换。
+pub struct RelayGate {
+    critical_battery: u8,
+    unwilling: bool,
+}
+
+impl RelayGate {
+    /// 创建中继开关，告急阈值由配置设置
+    pub fn new() -> Self {
+        Self { critical_battery: 0, unwilling: false }
+    }
+    
+    /// 按当前电量更新状态，状态改变时返回新的状态（true为不再中继）
+    pub fn update(&mut self, battery_level: u8) -> Option<bool> {
+        let threshold = if self.unwilling {
+            self.critical_battery.saturating_add(RELAY_RESUME_MARGIN)
+        } else {
+            self.critical_battery
+        };
+        let unwilling = battery_level < threshold;
+        if unwilling == self.unwilling {
+            return None;
+        }
+        
+        self.unwilling = unwilling;
+        Some(unwilling)
+    }
+    
+    /// 是否已停止为其他节点中继
+    pub fn is_unwilling(&self) -> bool {
+        self.unwilling
+    }
+}
+
+impl ConfigObserver for RelayGate {
+    fn config_changed(&mut self, config: &NodeConfig, changes: ConfigChanges) {
+        if changes.routing {
+            self.critical_battery = config.critical_battery;
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,278 @@
+use common::hal::Hardware;
+use common::metrics::{self, Counter};
+use common::protocol::{DataPacket, NodeId};
+use common::protocol::route_advert::{AdvertisedRoute, ETX_SCALE};
+use common::protocol::route_discovery::{local_route, send_route_reply, DiscoveryMessage};
+use common::{info, warn};
+use crate::neighbors::{LinkQuality, NeighborTable};
+use crate::routing::RoutingTable;
+use crate::routing::dynamic_forwarding::ForwardingEngine;
+use crate::scheduler::{QueuedPacket, TrafficClass, TxScheduler};
+
+/// 等待路由时最多缓存的包数
+pub const MAX_PENDING_PACKETS: usize = 8;
+
+/// 同时查找路由的目的地数
+const MAX_DISCOVERIES: usize = 4;
+
+/// 等待路由应答的时间（毫秒），超时后重发请求
+pub const DISCOVERY_TIMEOUT_MS: u64 = 1000;
+
+/// 每个目的地最多发出的路由请求数，全部超时后丢弃缓存的包
+pub const DISCOVERY_ATTEMPTS: u8 = 3;
+
+/// 路由发现失败后对该目的地暂停发现的时间（毫秒），期间到该目的地的包直接丢弃，避免反复泛洪
+pub const DISCOVERY_HOLDDOWN_MS: u64 = 10_000;
+
+/// 等待路由的包
+struct PendingPacket {
+    destination: NodeId,
+    class: TrafficClass,
+    packet: QueuedPacket,
+}
+
+/// 进行中的路由发现
+#[derive(Debug, Clone, Copy)]
+struct Discovery {
+    target: NodeId,
+    requested_at: u64,
+    attempts: u8,
+}
+
+/// 路由发现失败、暂停发现的目的地
+#[derive(Debug, Clone, Copy)]
+struct HeldDown {
+    target: NodeId,
+    failed_at: u64,
+}
+
+/// 按需路由发现：没有路由的包先缓存，泛洪路由请求，收到应答建立路由后再发出
+pub struct RouteDiscovery {
+    pending: [Option<PendingPacket>; MAX_PENDING_PACKETS],
+    discoveries: [Option<Discovery>; MAX_DISCOVERIES],
+    held_down: [Option<HeldDown>; MAX_DISCOVERIES],
+}
+
+impl RouteDiscovery {
+    /// 创建空的路由发现状态
+    pub fn new() -> Self {
+        Self {
+            pending: core::array::from_fn(|_| None),
+            discoveries: [None; MAX_DISCOVERIES],
+            held_down: [None; MAX_DISCOVERIES],
+        }
+    }
+    
+    /// 缓存没有路由的包，返回是否需要发出路由请求；缓存已满或目的地刚发现失败时原样返回该包
+    pub fn hold(&mut self, destination: NodeId, class: TrafficClass, packet: QueuedPacket, now: u64) -> Result<bool, QueuedPacket> {
+        if self.is_held_down(destination, now) {
+            return Err(packet);
+        }
+        
+        let in_progress = self.is_discovering(destination);
+        let discovery_slot = self.discoveries.iter().position(|discovery| discovery.is_none());
+        let slot = match self.pending.iter().position(|pending| pending.is_none()) {
+            Some(slot) if in_progress || discovery_slot.is_some() => slot,
+            _ => return Err(packet),
+        };
+        
+        self.pending[slot] = Some(PendingPacket { destination, class, packet });
+        if let (false, Some(index)) = (in_progress, discovery_slot) {
+            self.discoveries[index] = Some(Discovery { target: destination, requested_at: now, attempts: 1 });
+            return Ok(true);
+        }
+        Ok(false)
+    }
+    
+    /// 是否正在查找到达目的地的路由
+    pub fn is_discovering(&self, target: NodeId) -> bool {
+        self.discoveries.iter().flatten().any(|discovery| discovery.target == target)
+    }
+    
+    /// 到目的地的路由发现是否刚失败、仍在暂停期内
+    pub fn is_held_down(&self, target: NodeId, now: u64) -> bool {
+        self.held_down.iter().flatten()
+            .any(|held| held.target == target && now.saturating_sub(held.failed_at) < DISCOVERY_HOLDDOWN_MS)
+    }
+    
+    /// 路由已建立：结束发现，缓存的包填写下一跳后按原来的流量类别交给send，返回交出的包数
+    pub fn resolve(&mut self, target: NodeId, next_hop: NodeId, mut send: impl FnMut(TrafficClass, QueuedPacket)) -> usize {
+        for discovery in self.discoveries.iter_mut() {
+            if matches!(discovery, Some(d) if d.target == target) {
+                *discovery = None;
+            }
+        }
+        
+        let mut released = 0;
+        for entry in self.pending.iter_mut() {
+            if matches!(entry, Some(pending) if pending.destination == target) {
+                if let Some(PendingPacket { class, mut packet, .. }) = entry.take() {
+                    packet.next_hop = next_hop;
+                    send(class, packet);
+                    released += 1;
+                }
+            }
+        }
+        released
+    }
+    
+    /// 检查超时的路由发现：还有次数的交给retry重发请求，次数用完的丢弃缓存的包并暂停该目的地的发现，返回丢弃的包数
+    pub fn poll(&mut self, now: u64, mut retry: impl FnMut(NodeId)) -> usize {
+        let mut dropped = 0;
+        
+        for entry in self.discoveries.iter_mut() {
+            let discovery = match entry {
+                Some(discovery) if now.saturating_sub(discovery.requested_at) >= DISCOVERY_TIMEOUT_MS => discovery,
+                _ => continue,
+            };
+            
+            if discovery.attempts < DISCOVERY_ATTEMPTS {
+                discovery.attempts += 1;
+                discovery.requested_at = now;
+                retry(discovery.target);
+                continue;
+            }
+            
+            let target = discovery.target;
+            *entry = None;
+            hold_down(&mut self.held_down, target, now);
+            for pending in self.pending.iter_mut() {
+                if matches!(pending, Some(p) if p.destination == target) {
+                    *pending = None;
+                    dropped += 1;
+                }
+            }
+        }
+        dropped
+    }
+}
+
+/// 记录发现失败的目的地，表满时替换最早失败的
+fn hold_down(held_down: &mut [Option<HeldDown>; MAX_DISCOVERIES], target: NodeId, now: u64) {
+    let slot = held_down.iter().position(|held| matches!(held, Some(h) if h.target == target))
+        .or_else(|| held_down.iter().position(|held| held.is_none()))
+        .or_else(|| (0..MAX_DISCOVERIES).min_by_key(|&i| held_down[i].map_or(0, |held| held.failed_at)));
+    if let Some(slot) = slot {
+        held_down[slot] = Some(HeldDown { target, failed_at: now });
+    }
+}
+
+/// 处理泛洪到达的路由请求：经上一跳建立到发起方的反向路由，
+/// 本节点就是目标或有到目标的路由时沿反向路由应答
+pub fn handle_route_request<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    neighbors: &NeighborTable,
+    packet: &DataPacket,
+    request: &DataPacket,
+    now: u64
+) {
+    let (target, initial_ttl) = match DiscoveryMessage::deserialize(request.header.packet_type, request.data) {
+        Some(DiscoveryMessage::Request { target, initial_ttl }) => (target, initial_ttl),
+        _ => return,
+    };
+    
+    // 泛洪每跳以转发节点身份重新广播，包头中的源就是上一跳；只经验证过双向链路的邻居建立路由
+    let origin = NodeId(request.header.source);
+    let previous_hop = NodeId(packet.header.source);
+    let link = match neighbors.link_quality(previous_hop, now) {
+        Some(link) => link,
+        None => return,
+    };
+    
+    // 请求每经过一跳剩余跳数减一，据此得到上一跳到发起方的跳数；各跳ETX未知，按无丢包估计
+    let hops = initial_ttl.saturating_sub(packet.header.ttl);
+    let reverse = AdvertisedRoute {
+        destination: origin,
+        next_hop: origin,
+        hops,
+        rssi: i8::MAX,
+        etx: hops as u16 * ETX_SCALE,
+    };
+    forwarding_engine.learn_discovered(previous_hop, link, reverse, now);
+    
+    let node_id = hardware.get_node_id();
+    let route = if target == node_id {
+        Some(local_route(node_id))
+    } else {
+        forwarding_engine.advertised_routes().find(|route| route.destination == target && !route.is_poisoned())
+    };
+    
+    if let Some(route) = route {
+        let reply = DiscoveryMessage::Reply { origin, route };
+        match send_route_reply(hardware, previous_hop, request.header.packet_id, &reply) {
+            Ok(()) => info!("应答 {} 查找 {} 的路由请求", origin, target),
+            Err(e) => warn!("发送路由应答失败: {:?}", e),
+        }
+    }
+}
+
+/// 处理路由应答：建立经发送方到目标的路由；本节点是发起方时发出等待该路由的包，否则沿反向路由继续转发
+pub fn handle_route_reply<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    neighbors: &NeighborTable,
+    discovery: &mut RouteDiscovery,
+    scheduler: &mut TxScheduler,
+    packet: &DataPacket,
+    now: u64
+) {
+    let (origin, route) = match DiscoveryMessage::deserialize(packet.header.packet_type, packet.data) {
+        Some(DiscoveryMessage::Reply { origin, route }) => (origin, route),
+        _ => return,
+    };
+    
+    // 终端节点不发信标，不在邻居表中；目标自己发来的应答说明它听到了本节点转发的请求，链路是双向的
+    let neighbor = NodeId(packet.header.source);
+    let link = match neighbors.link_quality(neighbor, now) {
+        Some(link) => link,
+        None if route.destination == neighbor && route.hops == 0 => {
+            let rssi = hardware.get_radio().get_rssi().unwrap_or(route.rssi);
+            LinkQuality { rssi, delivery: 100, etx: ETX_SCALE, battery: 100, relay_unwilling: false }
+        },
+        None => return,
+    };
+    
+    // 目标就是应答的邻居时安装直接路由，之后发往它的包不再触发发现
+    let target = route.destination;
+    let next_hop = if target == neighbor {
+        forwarding_engine.update_link(neighbor, link);
+        Some(neighbor)
+    } else {
+        forwarding_engine.learn_discovered(neighbor, link, route, now);
+        forwarding_engine.get_next_hop(target)
+    };
+    let next_hop = match next_hop {
+        Some(next_hop) => next_hop,
+        None => return,
+    };
+    
+    if origin == hardware.get_node_id() {
+        let released = discovery.resolve(target, next_hop, |class, queued| {
+            if scheduler.enqueue(class, queued).is_ok() {
+                metrics::increment(Counter::PacketsForwarded);
+            } else {
+                metrics::increment(Counter::PacketsDropped);
+            }
+        });
+        if released > 0 {
+            info!("到 {} 的路由已建立，下一跳 {}，发出 {} 个缓存的包", target, next_hop, released);
+        }
+        return;
+    }
+    
+    // 以本节点到目标的路由继续应答
+    let route = match forwarding_engine.advertised_routes().find(|route| route.destination == target && !route.is_poisoned()) {
+        Some(route) => route,
+        None => return,
+    };
+    match forwarding_engine.get_next_hop(origin) {
+        Some(reverse_hop) => {
+            let reply = DiscoveryMessage::Reply { origin, route };
+            if let Err(e) = send_route_reply(hardware, reverse_hop, packet.header.packet_id, &reply) {
+                warn!("转发路由应答失败: {:?}", e);
+            }
+        },
+        None => warn!("未找到到达 {} 的反向路由，丢弃路由应答", origin),
+    }
+}
\ No newline at end of file
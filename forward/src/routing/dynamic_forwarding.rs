@@ -1,6 +1,32 @@
 use core::fmt;
+use common::config::{ConfigChanges, ConfigObserver, NodeConfig};
+use common::hal::nvs::{keys, NvStorage};
+use common::metrics::{self, Counter, Gauge};
 use common::protocol::NodeId;
+use common::protocol::route_advert::{AdvertisedRoute, ETX_SCALE, INFINITE_HOPS};
+use common::protocol::route_table::{RouteTableDump, RouteTableEntry};
+use common::warn;
+use crate::neighbors::LinkQuality;
 use crate::routing::RoutingTable;
+use crate::routing::strategy::{DefaultStrategy, RouteCandidate, RoutingStrategy};
+
+/// 路由表容量
+pub const MAX_ROUTES: usize = 32;
+
+/// 从检查点恢复的路由多久没有被信标刷新就丢弃（毫秒）
+pub const UNCONFIRMED_ROUTE_TTL_MS: u64 = 120_000;
+
+/// 失效路由在通告中保留多久（毫秒），期间不从其他邻居重新学习，防止环路中的旧路由回流
+pub const POISON_HOLD_MS: u64 = 60_000;
+
+/// 检查点格式版本，格式改变时递增，恢复时丢弃不认识的格式
+const CHECKPOINT_VERSION: u8 = 2;
+
+/// 检查点头部长度：版本(1) 路由数(1)
+const CHECKPOINT_HEADER_LEN: usize = 2;
+
+/// 检查点中每条路由的长度：目的地(6) 下一跳(6) 度量(1) 跳数(1) ETX(2)
+const CHECKPOINT_ROUTE_LEN: usize = 16;
 
 /// 路由表项
 #[derive(Clone, Copy)]
@@ -9,10 +35,18 @@ struct RouteEntry {
     destination: NodeId,
     /// 下一跳节点ID
     next_hop: NodeId,
-    /// 路由度量（这里使用信号强度）
+    /// 路由度量：路径上最弱一跳的信号强度
     metric: i8,
+    /// 跳数，直接邻居为1
+    hops: u8,
+    /// 路径上各跳ETX之和，即路由代价
+    etx: u16,
+    /// 路由失效的时间，失效后在保持期内仍向邻居通告为不可达
+    poisoned_at: Option<u64>,
     /// 路由生命期时间戳
     timestamp: u64,
+    /// 是否在本次启动后被信标刷新过，从检查点恢复的路由在刷新前未确认
+    confirmed: bool,
 }
 
 impl fmt::Debug for RouteEntry {
@@ -21,46 +55,435 @@ impl fmt::Debug for RouteEntry {
             .field("destination", &self.destination)
             .field("next_hop", &self.next_hop)
             .field("metric", &self.metric)
+            .field("hops", &self.hops)
+            .field("etx", &self.etx)
+            .field("poisoned_at", &self.poisoned_at)
             .field("timestamp", &self.timestamp)
+            .field("confirmed", &self.confirmed)
             .finish()
     }
 }
 
+impl RouteEntry {
+    /// 路由是否可用
+    fn is_active(&self) -> bool {
+        self.poisoned_at.is_none()
+    }
+    
+    /// 交给路由策略比较的参数
+    fn candidate(&self) -> RouteCandidate {
+        RouteCandidate { next_hop: self.next_hop, hops: self.hops, etx: self.etx, rssi: self.metric }
+    }
+}
+
 /// 转发引擎，实现动态路由
-pub struct ForwardingEngine {
+///
+/// 直接邻居由信标和HELLO验证后安装，更远的目的地从邻居的路由通告中学习（距离向量）。
+/// 经由本节点的通告路由不学习（水平分割），失效的路由先毒化通告给邻居再删除。
+/// 学习哪些路由、路由如何维持、多条路由中选哪一条由路由策略决定，默认策略在编译时按特性选择。
+pub struct ForwardingEngine<S: RoutingStrategy = DefaultStrategy> {
     /// 本节点ID
     node_id: NodeId,
+    /// 路由策略
+    strategy: S,
     /// 路由表
-    routes: [Option<RouteEntry>; 32],
+    routes: [Option<RouteEntry>; MAX_ROUTES],
     /// 当前路由数
     route_count: usize,
     /// 内部计时器，用于清理过期路由
     cleanup_timer: u64,
+    /// 路由多久未刷新视为失效（毫秒）
+    route_expiry_ms: u64,
+    /// 经由缺电邻居转发时增加的路由代价，以无丢包的一跳为单位
+    battery_weight: u8,
+    /// 上次写入检查点后路由是否增删过
+    dirty: bool,
+    /// 有路由失效，需要立即通告
+    triggered: bool,
 }
 
 impl ForwardingEngine {
-    /// 创建新的转发引擎实例
+    /// 创建新的转发引擎实例，使用编译时选择的路由策略
     pub fn new(node_id: NodeId) -> Self {
+        Self::with_strategy(node_id, DefaultStrategy::default())
+    }
+}
+
+impl<S: RoutingStrategy> ForwardingEngine<S> {
+    /// 使用指定的路由策略创建转发引擎
+    pub fn with_strategy(node_id: NodeId, strategy: S) -> Self {
         Self {
             node_id,
-            routes: [None; 32],
+            strategy,
+            routes: [None; MAX_ROUTES],
             route_count: 0,
             cleanup_timer: 0,
+            route_expiry_ms: 300_000, // 5分钟
+            battery_weight: 0,
+            dirty: false,
+            triggered: false,
+        }
+    }
+    
+    /// 使用的路由策略
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+    
+    /// 转发经过到目的地的路由，策略按使用维持路由时刷新它
+    pub fn route_used(&mut self, destination: NodeId, current_time: u64) {
+        if !self.strategy.refreshes_on_use() {
+            return;
+        }
+        if let Some(route) = self.find_route(destination).and_then(|index| self.routes[index].as_mut()) {
+            if route.is_active() {
+                route.timestamp = current_time;
+            }
         }
     }
     
-    /// 周期性清理过期路由
+    /// 周期性清理过期路由，未确认的路由过期得更快
+    ///
+    /// 过期的路由和经由失效邻居的路由先被毒化，保持期过后才删除。
     pub fn cleanup(&mut self, current_time: u64) {
-        const ROUTE_EXPIRY_MS: u64 = 300_000; // 5分钟
+        self.cleanup_timer = current_time;
         
-        for entry in self.routes.iter_mut() {
-            if let Some(route) = entry {
-                if current_time - route.timestamp > ROUTE_EXPIRY_MS {
-                    *entry = None;
-                    self.route_count -= 1;
+        for index in 0..MAX_ROUTES {
+            let route = match self.routes[index] {
+                Some(route) => route,
+                None => continue,
+            };
+            match route.poisoned_at {
+                Some(at) if current_time.saturating_sub(at) > POISON_HOLD_MS => self.routes[index] = None,
+                Some(_) => {},
+                None => {
+                    let ttl = if route.confirmed {
+                        self.strategy.route_expiry_ms(self.route_expiry_ms)
+                    } else {
+                        UNCONFIRMED_ROUTE_TTL_MS
+                    };
+                    if current_time.saturating_sub(route.timestamp) > ttl {
+                        self.poison(index, current_time);
+                    }
+                },
+            }
+        }
+        
+        // 下一跳不再是可用邻居的路由随之失效
+        for index in 0..MAX_ROUTES {
+            let orphaned = match self.routes[index] {
+                Some(route) if route.is_active() && route.hops > 1 => !self.is_neighbor(route.next_hop),
+                _ => false,
+            };
+            if orphaned {
+                self.poison(index, current_time);
+            }
+        }
+        
+        metrics::set(Gauge::Routes, self.route_count as u32);
+    }
+    
+    /// 是否有到该节点的直接路由
+    fn is_neighbor(&self, node: NodeId) -> bool {
+        self.routes.iter()
+            .flatten()
+            .any(|route| route.is_active() && route.destination == node && route.hops == 1)
+    }
+    
+    /// 毒化一条可用路由，保持期内通告为不可达
+    fn poison(&mut self, index: usize, current_time: u64) {
+        if let Some(route) = self.routes[index].as_mut() {
+            if route.is_active() {
+                route.hops = INFINITE_HOPS;
+                route.poisoned_at = Some(current_time);
+                self.route_count -= 1;
+                self.dirty = true;
+                self.triggered = true;
+            }
+        }
+    }
+    
+    /// 从已验证邻居的路由通告中学习，link为到该邻居的链路质量，返回新增或改变下一跳的路由数
+    ///
+    /// 路由策略不从通告学习时忽略通告。
+    pub fn learn_routes(&mut self, neighbor: NodeId, link: LinkQuality, routes: &[AdvertisedRoute], current_time: u64) -> usize {
+        self.cleanup_timer = current_time;
+        if !self.strategy.learns_from_adverts() {
+            return 0;
+        }
+        self.merge_routes(neighbor, link, routes, current_time)
+    }
+    
+    /// 安装路由发现得到的经邻居的路由（路由请求的反向路由或路由应答），不受策略是否学习通告的限制
+    pub fn learn_discovered(&mut self, neighbor: NodeId, link: LinkQuality, route: AdvertisedRoute, current_time: u64) -> usize {
+        self.cleanup_timer = current_time;
+        self.merge_routes(neighbor, link, &[route], current_time)
+    }
+    
+    /// 按距离向量规则合并经邻居的路由，返回新增或改变下一跳的路由数
+    fn merge_routes(&mut self, neighbor: NodeId, link: LinkQuality, routes: &[AdvertisedRoute], current_time: u64) -> usize {
+        let mut changed = 0;
+        
+        for advertised in routes {
+            if advertised.destination == self.node_id || advertised.destination == neighbor {
+                continue;
+            }
+            // 邻居经由本节点到达的目的地对本节点不可达（水平分割），跳数达到上限的视为环路；
+            // 电量告急的邻居不再中继，经由它的路由都不可达
+            let hops = advertised.hops.saturating_add(1);
+            let unreachable = advertised.next_hop == self.node_id || hops >= INFINITE_HOPS || link.relay_unwilling;
+            let metric = advertised.rssi.min(link.rssi);
+            let etx = advertised.etx.saturating_add(link.etx).saturating_add(self.battery_penalty(link.battery));
+            
+            match self.find_route(advertised.destination) {
+                Some(index) => {
+                    let route = self.routes[index].unwrap();
+                    if !route.is_active() {
+                        // 保持期内不重新学习
+                        continue;
+                    }
+                    if route.next_hop == neighbor && route.hops > 1 {
+                        // 当前下一跳的通告总是采信，包括变差和失效
+                        if unreachable {
+                            self.poison(index, current_time);
+                        } else if let Some(route) = self.routes[index].as_mut() {
+                            route.hops = hops;
+                            route.metric = metric;
+                            route.etx = etx;
+                            route.timestamp = current_time;
+                            route.confirmed = true;
+                        }
+                    } else if !unreachable && self.strategy.prefers(&RouteCandidate { next_hop: neighbor, hops, etx, rssi: metric }, &route.candidate()) {
+                        self.routes[index] = Some(RouteEntry {
+                            next_hop: neighbor,
+                            metric,
+                            hops,
+                            etx,
+                            timestamp: current_time,
+                            confirmed: true,
+                            ..route
+                        });
+                        self.dirty = true;
+                        changed += 1;
+                    }
+                },
+                None if !unreachable => {
+                    if let Some(index) = self.find_free_slot() {
+                        self.routes[index] = Some(RouteEntry {
+                            destination: advertised.destination,
+                            next_hop: neighbor,
+                            metric,
+                            hops,
+                            etx,
+                            poisoned_at: None,
+                            timestamp: current_time,
+                            confirmed: true,
+                        });
+                        self.route_count += 1;
+                        self.dirty = true;
+                        changed += 1;
+                        metrics::increment(Counter::RoutesLearned);
+                    }
+                },
+                None => {},
+            }
+        }
+        
+        metrics::set(Gauge::Routes, self.route_count as u32);
+        changed
+    }
+    
+    /// 经由电量为battery的邻居中继的额外代价，按缺电比例线性增加
+    fn battery_penalty(&self, battery: u8) -> u16 {
+        let missing = 100 - battery.min(100) as u32;
+        (self.battery_weight as u32 * ETX_SCALE as u32 * missing / 100) as u16
+    }
+    
+    /// 向邻居通告的路由，包括保持期内的失效路由
+    pub fn advertised_routes(&self) -> impl Iterator<Item = AdvertisedRoute> + '_ {
+        self.routes.iter()
+            .flatten()
+            .map(|route| AdvertisedRoute {
+                destination: route.destination,
+                next_hop: route.next_hop,
+                hops: route.hops,
+                rssi: route.metric,
+                etx: route.etx,
+            })
+    }
+    
+    /// 是否有路由失效需要立即通告，取出后清除
+    pub fn take_triggered_update(&mut self) -> bool {
+        core::mem::take(&mut self.triggered)
+    }
+    
+    /// 尚未被信标刷新的恢复路由数
+    pub fn unconfirmed_count(&self) -> usize {
+        self.routes.iter().flatten().filter(|route| route.is_active() && !route.confirmed).count()
+    }
+    
+    /// 路由有增删时写入检查点，返回是否写入
+    ///
+    /// 只刷新时间和度量不写，避免频繁擦写闪存。
+    pub fn checkpoint<N: NvStorage>(&mut self, nvs: &mut N) -> Result<bool, N::Error> {
+        if !self.dirty {
+            return Ok(false);
+        }
+        
+        let mut bytes = [0u8; CHECKPOINT_HEADER_LEN + CHECKPOINT_ROUTE_LEN * MAX_ROUTES];
+        let mut count = 0;
+        for route in self.routes.iter().flatten().filter(|route| route.is_active()) {
+            let offset = CHECKPOINT_HEADER_LEN + count * CHECKPOINT_ROUTE_LEN;
+            bytes[offset..offset + 6].copy_from_slice(&route.destination.0);
+            bytes[offset + 6..offset + 12].copy_from_slice(&route.next_hop.0);
+            bytes[offset + 12] = route.metric as u8;
+            bytes[offset + 13] = route.hops;
+            bytes[offset + 14..offset + 16].copy_from_slice(&route.etx.to_be_bytes());
+            count += 1;
+        }
+        bytes[0] = CHECKPOINT_VERSION;
+        bytes[1] = count as u8;
+        
+        nvs.nvs_write(keys::ROUTING_TABLE, &bytes[..CHECKPOINT_HEADER_LEN + count * CHECKPOINT_ROUTE_LEN])?;
+        self.dirty = false;
+        Ok(true)
+    }
+    
+    /// 启动时从检查点恢复路由，恢复的路由在被信标刷新前标记为未确认，返回恢复的路由数
+    pub fn restore<N: NvStorage>(&mut self, nvs: &mut N, current_time: u64) -> usize {
+        let mut bytes = [0u8; CHECKPOINT_HEADER_LEN + CHECKPOINT_ROUTE_LEN * MAX_ROUTES];
+        let len = match nvs.nvs_read(keys::ROUTING_TABLE, &mut bytes) {
+            Ok(Some(len)) => len,
+            _ => return 0,
+        };
+        
+        // 升级前写入的旧格式无法可靠解析，直接丢弃，路由由信标重新学习
+        let count = bytes[1] as usize;
+        if len < CHECKPOINT_HEADER_LEN
+            || bytes[0] != CHECKPOINT_VERSION
+            || count > MAX_ROUTES
+            || len != CHECKPOINT_HEADER_LEN + count * CHECKPOINT_ROUTE_LEN {
+            warn!("路由检查点格式不符，忽略");
+            return 0;
+        }
+        let read_id = |offset: usize| {
+            let mut id = [0u8; 6];
+            id.copy_from_slice(&bytes[offset..offset + 6]);
+            NodeId(id)
+        };
+        
+        self.cleanup_timer = current_time;
+        for i in 0..count {
+            let offset = CHECKPOINT_HEADER_LEN + i * CHECKPOINT_ROUTE_LEN;
+            let destination = read_id(offset);
+            if destination == self.node_id || self.find_route(destination).is_some() {
+                continue;
+            }
+            if let Some(index) = self.find_free_slot() {
+                self.routes[index] = Some(RouteEntry {
+                    destination,
+                    next_hop: read_id(offset + 6),
+                    metric: bytes[offset + 12] as i8,
+                    hops: bytes[offset + 13].clamp(1, INFINITE_HOPS - 1),
+                    etx: u16::from_be_bytes([bytes[offset + 14], bytes[offset + 15]]).max(ETX_SCALE),
+                    poisoned_at: None,
+                    timestamp: current_time,
+                    confirmed: false,
+                });
+                self.route_count += 1;
+            }
+        }
+        
+        metrics::set(Gauge::Routes, self.route_count as u32);
+        self.unconfirmed_count()
+    }
+    
+    /// 遍历所有可用路由：（目的地，下一跳，度量）
+    pub fn routes(&self) -> impl Iterator<Item = (NodeId, NodeId, i8)> + '_ {
+        self.routes.iter()
+            .flatten()
+            .filter(|route| route.is_active())
+            .map(|route| (route.destination, route.next_hop, route.metric))
+    }
+    
+    /// 导出路由表快照供远程查看，最多max_entries条，包括保持期内的失效路由
+    pub fn dump(&self, max_entries: usize, current_time: u64) -> RouteTableDump {
+        let mut dump = RouteTableDump::new(self.routes.iter().flatten().count() as u8);
+        for route in self.routes.iter().flatten().take(max_entries) {
+            let age_secs = current_time.saturating_sub(route.timestamp) / 1000;
+            let pushed = dump.push(RouteTableEntry {
+                destination: route.destination,
+                next_hop: route.next_hop,
+                metric: route.metric,
+                hops: route.hops,
+                etx: route.etx,
+                age_secs: age_secs.min(u16::MAX as u64) as u16,
+            });
+            if !pushed {
+                break;
+            }
+        }
+        dump
+    }
+    
+    /// 按邻居的链路质量安装或刷新直接路由，代价为该链路的ETX
+    pub fn update_link(&mut self, destination: NodeId, link: LinkQuality) {
+        // 不要为自己添加路由
+        if destination == self.node_id {
+            return;
+        }
+        
+        let current_time = self.cleanup_timer;
+        let direct = RouteEntry {
+            destination,
+            next_hop: destination, // 直接路由
+            metric: link.rssi,
+            hops: 1,
+            etx: link.etx.max(ETX_SCALE),
+            poisoned_at: None,
+            timestamp: current_time,
+            confirmed: true,
+        };
+        
+        // 查找是否已存在该目的地的路由
+        if let Some(index) = self.find_route(destination) {
+            let route = self.routes[index].unwrap();
+            if route.is_active() && route.hops == 1 {
+                // 更新现有的直接路由
+                if let Some(route) = &mut self.routes[index] {
+                    route.metric = direct.metric;
+                    route.etx = direct.etx;
+                    route.timestamp = current_time;
+                    route.confirmed = true;
+                }
+            } else if !route.is_active() || !self.strategy.prefers(&route.candidate(), &direct.candidate()) {
+                // 直接听到的邻居不受保持期限制；多跳路由只在代价更高时被替换
+                if !route.is_active() {
+                    self.route_count += 1;
                 }
+                self.routes[index] = Some(direct);
+                self.dirty = true;
             }
+        } else {
+            // 添加新路由，表满时优先替换失效的路由
+            let index = self.find_free_slot()
+                .or_else(|| self.routes.iter().position(|entry| matches!(entry, Some(route) if !route.is_active())));
+            match index {
+                Some(index) => {
+                    self.routes[index] = Some(direct);
+                    self.route_count += 1;
+                    metrics::increment(Counter::RoutesLearned);
+                },
+                None => {
+                    // 路由表已满，可以实现更复杂的替换策略
+                    // 这里简单地替换第一个条目
+                    self.routes[0] = Some(direct);
+                },
+            }
+            self.dirty = true;
         }
+        
+        metrics::set(Gauge::Routes, self.route_count as u32);
     }
     
     /// 寻找空闲的路由表项
@@ -80,60 +503,36 @@ impl ForwardingEngine {
     }
 }
 
-impl RoutingTable for ForwardingEngine {
+impl<S: RoutingStrategy> RoutingTable for ForwardingEngine<S> {
     fn update_route(&mut self, destination: NodeId, metric: i8) {
-        // 不要为自己添加路由
-        if destination == self.node_id {
-            return;
-        }
-        
-        let current_time = self.cleanup_timer;
-        
-        // 查找是否已存在该目的地的路由
-        if let Some(index) = self.find_route(destination) {
-            // 更新现有路由
-            if let Some(route) = &mut self.routes[index] {
-                route.metric = metric;
-                route.timestamp = current_time;
-            }
-        } else {
-            // 添加新路由
-            if let Some(index) = self.find_free_slot() {
-                self.routes[index] = Some(RouteEntry {
-                    destination,
-                    next_hop: destination, // 直接路由
-                    metric,
-                    timestamp: current_time,
-                });
-                self.route_count += 1;
-            } else {
-                // 路由表已满，可以实现更复杂的替换策略
-                // 这里简单地替换第一个条目
-                self.routes[0] = Some(RouteEntry {
-                    destination,
-                    next_hop: destination,
-                    metric,
-                    timestamp: current_time,
-                });
-            }
-        }
+        // 没有投递率统计时按无丢包的一跳计
+        self.update_link(destination, LinkQuality {
+            rssi: metric,
+            delivery: 100,
+            etx: ETX_SCALE,
+            battery: 100,
+            relay_unwilling: false,
+        });
     }
     
     fn get_next_hop(&self, destination: NodeId) -> Option<NodeId> {
-        // 查找目的地路由
-        if let Some(index) = self.find_route(destination) {
+        // 查找目的地路由，失效的路由不再使用
+        let route = self.find_route(destination).and_then(|index| self.routes[index]);
+        if let Some(route) = route.filter(|route| route.is_active()) {
             // 返回下一跳
-            self.routes[index].map(|route| route.next_hop)
+            Some(route.next_hop)
         } else {
             // 没有找到路由
+            metrics::increment(Counter::RouteMisses);
             None
         }
     }
     
     fn remove_route(&mut self, destination: NodeId) {
+        // 先毒化通告给邻居，保持期过后由清理删除
         if let Some(index) = self.find_route(destination) {
-            self.routes[index] = None;
-            self.route_count -= 1;
+            self.poison(index, self.cleanup_timer);
+            metrics::set(Gauge::Routes, self.route_count as u32);
         }
     }
     
@@ -142,6 +541,8 @@ impl RoutingTable for ForwardingEngine {
             *entry = None;
         }
         self.route_count = 0;
+        self.dirty = true;
+        metrics::set(Gauge::Routes, 0);
     }
     
     fn len(&self) -> usize {
@@ -151,4 +552,13 @@ impl RoutingTable for ForwardingEngine {
     fn is_empty(&self) -> bool {
         self.route_count == 0
     }
-} 
\ No newline at end of file
+} 
+
+impl<S: RoutingStrategy> ConfigObserver for ForwardingEngine<S> {
+    fn config_changed(&mut self, config: &NodeConfig, changes: ConfigChanges) {
+        if changes.routing {
+            self.route_expiry_ms = config.route_expiry_ms as u64;
+            self.battery_weight = config.battery_weight;
+        }
+    }
+}
\ No newline at end of file
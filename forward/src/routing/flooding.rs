@@ -0,0 +1,186 @@
+use common::hal::Hardware;
+use common::metrics::{self, Counter};
+use common::protocol::{DataPacket, NodeId, PacketType};
+use common::protocol::flood::{send_flood, Flood};
+use common::protocol::mgmt::{verify_mgmt, MgmtMessage};
+use common::protocol::reliable::ReliableError;
+use common::security::MAX_SECURE_PAYLOAD;
+use common::utils::jitter;
+use common::warn;
+
+/// 去重缓存记录的泛洪数
+const SEEN_CACHE_SIZE: usize = 32;
+
+/// 去重窗口（毫秒），同一发起节点同一序号的泛洪在窗口内只处理一次
+pub const FLOOD_DUPLICATE_WINDOW_MS: u64 = 10_000;
+
+/// 重新广播前随机等待的上限（毫秒），同时收到泛洪的邻居错开发送，避免互相碰撞
+pub const FLOOD_JITTER_MS: u64 = 100;
+
+/// 同时等待重新广播的泛洪数，超出时立即发送
+const MAX_PENDING_REBROADCASTS: usize = 4;
+
+/// 最近处理过的泛洪
+#[derive(Debug, Clone, Copy)]
+struct SeenFlood {
+    origin: NodeId,
+    sequence: u16,
+    seen_at: u64,
+}
+
+/// 等待随机延时后重新广播的泛洪
+#[derive(Debug, Clone, Copy)]
+struct PendingRebroadcast {
+    data: [u8; MAX_SECURE_PAYLOAD],
+    len: usize,
+    ttl: u8,
+    send_at: u64,
+}
+
+/// 受控泛洪：发往广播地址的包逐跳重新广播，每跳递减跳数，按发起节点和序号去重，
+/// 使全网命令和选举消息能到达单跳广播覆盖不到的节点
+pub struct FloodRelay {
+    node_id: NodeId,
+    next_sequence: u16,
+    seen: [Option<SeenFlood>; SEEN_CACHE_SIZE],
+    pending: [Option<PendingRebroadcast>; MAX_PENDING_REBROADCASTS],
+}
+
+impl FloodRelay {
+    /// 创建泛洪转发器
+    pub fn new(node_id: NodeId) -> Self {
+        Self {
+            node_id,
+            next_sequence: 0,
+            seen: [None; SEEN_CACHE_SIZE],
+            pending: [None; MAX_PENDING_REBROADCASTS],
+        }
+    }
+    
+    /// 由本节点发起泛洪，返回分配的序号
+    pub fn originate<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        packet_type: PacketType,
+        payload: &[u8],
+        now: u64
+    ) -> Result<u16, ReliableError> {
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        
+        // 记下自己的泛洪，邻居转发回来的副本直接丢弃
+        self.remember(self.node_id, sequence, now);
+        let flood = Flood { origin: self.node_id, sequence, packet_type: packet_type as u8, payload };
+        send_flood(hardware, &flood, hardware.default_ttl())?;
+        Ok(sequence)
+    }
+    
+    /// 处理收到的泛洪包
+    ///
+    /// 第一次到达时在跳数耗尽前安排随机延时后重新广播（由[`FloodRelay::poll`]发出），并返回原始消息交给本地处理；
+    /// 重复到达的返回None。
+    ///
+    /// 泛洪包头中的发起节点没有认证，泛洪的管理消息必须通过认证且消息中的发起方与之一致，否则既不处理也不转发。
+    pub fn flood_packet<'a, H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        packet: &DataPacket<'a>,
+        now: u64
+    ) -> Option<Flood<'a>> {
+        let flood = Flood::deserialize(packet.data)?;
+        if flood.packet_type == PacketType::Mgmt as u8 && !authentic_mgmt(hardware, &flood) {
+            metrics::increment(Counter::SecurityRejected);
+            return None;
+        }
+        if !self.accept(flood.origin, flood.sequence, now) {
+            metrics::increment(Counter::FloodDuplicates);
+            return None;
+        }
+        
+        let ttl = packet.header.ttl.saturating_sub(1);
+        if ttl == 0 {
+            metrics::increment(Counter::TtlExpired);
+        } else {
+            let send_at = now + rebroadcast_delay(self.node_id, &flood, now);
+            self.schedule(hardware, &flood, ttl, send_at);
+        }
+        
+        Some(flood)
+    }
+    
+    /// 发出延时已到的重新广播
+    pub fn poll<H: Hardware>(&mut self, hardware: &mut H, now: u64) {
+        for slot in self.pending.iter_mut() {
+            if let Some(pending) = slot.filter(|pending| pending.send_at <= now) {
+                *slot = None;
+                if let Some(flood) = Flood::deserialize(&pending.data[..pending.len]) {
+                    rebroadcast(hardware, &flood, pending.ttl);
+                }
+            }
+        }
+    }
+    
+    /// 距下一个重新广播的时间，没有等待中的重新广播时返回None
+    pub fn wait_ms(&self, now: u64) -> Option<u64> {
+        self.pending.iter().flatten().map(|pending| pending.send_at.saturating_sub(now)).min()
+    }
+    
+    /// 保存泛洪等待重新广播，等待的泛洪过多时立即发送
+    fn schedule<H: Hardware>(&mut self, hardware: &mut H, flood: &Flood, ttl: u8, send_at: u64) {
+        let mut data = [0u8; MAX_SECURE_PAYLOAD];
+        let len = flood.serialize(&mut data);
+        match self.pending.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) if len > 0 => *slot = Some(PendingRebroadcast { data, len, ttl, send_at }),
+            _ => rebroadcast(hardware, flood, ttl),
+        }
+    }
+    
+    /// 记录收到的泛洪，返回是否为窗口内第一次到达
+    pub fn accept(&mut self, origin: NodeId, sequence: u16, now: u64) -> bool {
+        let duplicate = self.seen.iter().flatten().any(|seen| {
+            seen.origin == origin && seen.sequence == sequence
+                && now.saturating_sub(seen.seen_at) < FLOOD_DUPLICATE_WINDOW_MS
+        });
+        if duplicate {
+            return false;
+        }
+        
+        self.remember(origin, sequence, now);
+        true
+    }
+    
+    /// 记录一个泛洪，缓存满时替换最旧的
+    fn remember(&mut self, origin: NodeId, sequence: u16, now: u64) {
+        let slot = self.seen.iter().position(|entry| entry.is_none())
+            .or_else(|| (0..SEEN_CACHE_SIZE).min_by_key(|&i| self.seen[i].map_or(0, |seen| seen.seen_at)));
+        if let Some(slot) = slot {
+            self.seen[slot] = Some(SeenFlood { origin, sequence, seen_at: now });
+        }
+    }
+}
+
+/// 以剩余跳数重新广播一个泛洪
+fn rebroadcast<H: Hardware>(hardware: &mut H, flood: &Flood, ttl: u8) {
+    if let Err(e) = send_flood(hardware, flood, ttl) {
+        warn!("转发 {} 的泛洪包失败: {:?}", flood.origin, e);
+    } else {
+        metrics::increment(Counter::FloodsRelayed);
+    }
+}
+
+/// 泛洪的管理消息通过认证，且消息中的发起方就是泛洪的发起节点
+fn authentic_mgmt<H: Hardware>(hardware: &mut H, flood: &Flood) -> bool {
+    hardware.get_security().is_enabled()
+        && verify_mgmt(hardware, flood.payload)
+        && matches!(MgmtMessage::deserialize(flood.payload), Some((message, _)) if message.origin == flood.origin)
+}
+
+/// 生成[0, FLOOD_JITTER_MS]范围内的重新广播延时，以节点ID、泛洪和当前时间为种子，各邻居的延时互不相同
+fn rebroadcast_delay(node_id: NodeId, flood: &Flood, now: u64) -> u64 {
+    let origin = flood.origin.0;
+    let seed = now as u32
+        ^ u32::from_be_bytes([origin[2], origin[3], origin[4], origin[5]]).rotate_left(16)
+        ^ flood.sequence as u32;
+    
+    jitter(node_id, seed, FLOOD_JITTER_MS as u32 + 1) as u64
+}
\ No newline at end of file
@@ -0,0 +1,109 @@
+use common::protocol::NodeId;
+
+/// 按需路由的路由多久未被使用视为失效（毫秒）
+pub const ACTIVE_ROUTE_TIMEOUT_MS: u64 = 30_000;
+
+/// 到某个目的地的一条路由，供路由策略比较
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteCandidate {
+    pub next_hop: NodeId,
+    /// 跳数，直接邻居为1
+    pub hops: u8,
+    /// 路径ETX
+    pub etx: u16,
+    /// 路径上最弱一跳的信号强度
+    pub rssi: i8,
+}
+
+/// 路由策略：路由发现、路由维护和下一跳选择
+///
+/// 路由表的存储、毒化和检查点由转发引擎负责，策略决定从哪里学习路由、没有路由时是否按需查找、
+/// 路由靠什么维持和多久失效、到同一目的地的多条路由中选哪一条。
+pub trait RoutingStrategy {
+    /// 路由代价，越小越好
+    fn cost(&self, hops: u8, etx: u16, rssi: i8) -> u32;
+    
+    /// 下一跳选择：是否用新得到的路由取代当前路由，默认代价更小时取代
+    fn prefers(&self, offered: &RouteCandidate, current: &RouteCandidate) -> bool {
+        self.cost(offered.hops, offered.etx, offered.rssi) < self.cost(current.hops, current.etx, current.rssi)
+    }
+    
+    /// 路由发现：是否从邻居的路由通告中学习多跳路由，不学习时只使用直接邻居、检查点和按需发现的路由
+    fn learns_from_adverts(&self) -> bool {
+        true
+    }
+    
+    /// 路由发现：没有路由时是否泛洪路由请求按需查找，不查找时直接丢弃
+    fn discovers_on_demand(&self) -> bool {
+        true
+    }
+    
+    /// 路由维护：已确认的路由多久未刷新视为失效（毫秒），configured为运行配置中的值
+    fn route_expiry_ms(&self, configured: u64) -> u64 {
+        configured
+    }
+    
+    /// 路由维护：转发经过路由是否视为刷新，不刷新时路由只由信标和通告维持
+    fn refreshes_on_use(&self) -> bool {
+        false
+    }
+}
+
+/// 按路径ETX选择路由：每一跳至少计一次发送，丢包多的链路按期望的重传次数加重
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EtxStrategy;
+
+impl RoutingStrategy for EtxStrategy {
+    fn cost(&self, _hops: u8, etx: u16, _rssi: i8) -> u32 {
+        etx as u32
+    }
+}
+
+/// 梯度路由：沿跳数递减的方向转发，跳数相同时选路径ETX小的
+///
+/// 代价主要由跳数决定，链路质量的波动只会在跳数相同的邻居之间切换下一跳。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GradientStrategy;
+
+impl RoutingStrategy for GradientStrategy {
+    fn cost(&self, hops: u8, etx: u16, _rssi: i8) -> u32 {
+        ((hops as u32) << 16) | etx as u32
+    }
+}
+
+/// 类AODV的按需路由：不学习路由通告，只在需要时泛洪路由请求，路由靠转发流量维持
+///
+/// 同一目的地只在应答的跳数更少时换下一跳，正在使用的路由不随链路质量波动切换；
+/// 一段时间没有流量经过的路由失效，之后再有包时重新发现。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReactiveStrategy;
+
+impl RoutingStrategy for ReactiveStrategy {
+    fn cost(&self, hops: u8, _etx: u16, _rssi: i8) -> u32 {
+        hops as u32
+    }
+    
+    fn learns_from_adverts(&self) -> bool {
+        false
+    }
+    
+    fn route_expiry_ms(&self, configured: u64) -> u64 {
+        configured.min(ACTIVE_ROUTE_TIMEOUT_MS)
+    }
+    
+    fn refreshes_on_use(&self) -> bool {
+        true
+    }
+}
+
+/// 编译时选择的路由策略，默认按ETX，开启`gradient-routing`特性时使用梯度路由，开启`reactive-routing`特性时使用按需路由
+#[cfg(not(any(feature = "gradient-routing", feature = "reactive-routing")))]
+pub type DefaultStrategy = EtxStrategy;
+
+/// 编译时选择的路由策略，默认按ETX，开启`gradient-routing`特性时使用梯度路由，开启`reactive-routing`特性时使用按需路由
+#[cfg(feature = "gradient-routing")]
+pub type DefaultStrategy = GradientStrategy;
+
+/// 编译时选择的路由策略，默认按ETX，开启`gradient-routing`特性时使用梯度路由，开启`reactive-routing`特性时使用按需路由
+#[cfg(all(feature = "reactive-routing", not(feature = "gradient-routing")))]
+pub type DefaultStrategy = ReactiveStrategy;
\ No newline at end of file
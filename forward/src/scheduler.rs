@@ -0,0 +1,313 @@
+use common::hal::Hardware;
+use common::metrics::{self, Gauge};
+use common::protocol::{DataPacket, NodeId, PacketType};
+use common::protocol::data::{NO_FLOW, UNSET_TTL};
+use common::protocol::batch::BATCH_PAYLOAD_TYPE;
+use common::protocol::frame::FRAME_PAYLOAD_TYPE;
+use common::pool::{NodePool, PacketBuf, FORWARD_QUEUED_BUFFERS};
+use common::security::{send_secure_to, MAX_SECURE_PAYLOAD};
+use common::warn;
+use crate::routing::discovery::MAX_PENDING_PACKETS;
+
+/// 控制队列的容量
+const CONTROL_QUEUE_LEN: usize = 8;
+
+/// 同时调度的最大数据流数
+pub const MAX_FLOWS: usize = 4;
+
+/// 每个数据流的队列容量
+const FLOW_QUEUE_LEN: usize = 4;
+
+/// 主循环每次最多发送的包数，避免发送占满接收的时间
+pub const TX_BURST: usize = 4;
+
+/// 权重为1的流每轮获得的发送份额（字节），不小于单帧负载，保证每轮至少能发一个包
+const QUANTUM_BYTES: usize = MAX_SECURE_PAYLOAD;
+
+// 发送队列和等待路由的包都占满时缓冲池仍有余量留给接收和链路层重传
+const _: () = assert!(CONTROL_QUEUE_LEN + MAX_FLOWS * FLOW_QUEUE_LEN + MAX_PENDING_PACKETS <= FORWARD_QUEUED_BUFFERS);
+
+/// 流量类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrafficClass {
+    /// 控制流量，严格优先
+    Control,
+    /// 会话数据，按流ID在各会话之间差额轮转，权重为每轮的份额倍数
+    Data { flow_id: u16, weight: u8 },
+}
+
+impl TrafficClass {
+    /// 按头部的流ID分类，不属于任何会话的数据按控制流量处理；权重按负载类型
+    pub fn of_data(packet: &DataPacket) -> Self {
+        let flow_id = packet.header.flow_id;
+        if flow_id == NO_FLOW {
+            return TrafficClass::Control;
+        }
+        
+        let weight = match packet.data.first().copied().unwrap_or(0) {
+            // 视频帧数据量大，按分片发送，每轮多发几个才能跟上帧率
+            _ if packet.is_fragment() => 3,
+            FRAME_PAYLOAD_TYPE => 3,
+            BATCH_PAYLOAD_TYPE => 2,
+            _ => 1,
+        };
+        TrafficClass::Data { flow_id, weight }
+    }
+}
+
+/// 等待发送的包，负载保存在缓冲池中，发出或丢弃时归还
+pub struct QueuedPacket {
+    /// 下一跳
+    pub next_hop: NodeId,
+    /// 端到端的目标，发送时写入头部；None表示发给下一跳本身
+    pub destination: Option<NodeId>,
+    pub packet_type: PacketType,
+    pub packet_id: u16,
+    /// 所属会话的流ID，发送时原样写入头部
+    pub flow_id: u16,
+    /// 分片总数和分片索引，转发大负载的分片时原样写入头部
+    pub fragment: (u8, u8),
+    /// 剩余跳数，转发时为递减后的跳数，本节点发出的包为默认跳数
+    pub ttl: u8,
+    data: PacketBuf<'static>,
+}
+
+impl QueuedPacket {
+    /// 创建待发送的包，负载复制到本节点的缓冲池中；负载超过单帧长度或缓冲池耗尽时返回None
+    pub fn new(pool: &'static NodePool, next_hop: NodeId, packet_type: PacketType, packet_id: u16, data: &[u8]) -> Option<Self> {
+        if data.len() > MAX_SECURE_PAYLOAD {
+            return None;
+        }
+        
+        Some(Self {
+            next_hop,
+            destination: None,
+            packet_type,
+            packet_id,
+            flow_id: NO_FLOW,
+            fragment: (1, 0),
+            ttl: UNSET_TTL,
+            data: pool.store(data)?,
+        })
+    }
+    
+    /// 标记所属会话的流ID
+    pub fn with_flow(mut self, flow_id: u16) -> Self {
+        self.flow_id = flow_id;
+        self
+    }
+    
+    /// 设置端到端的目标，转发时头部保留原来的目标，帧发给下一跳
+    pub fn with_destination(mut self, destination: NodeId) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+    
+    /// 标记分片总数和分片索引
+    pub fn with_fragment(mut self, total_fragments: u8, fragment_index: u8) -> Self {
+        self.fragment = (total_fragments, fragment_index);
+        self
+    }
+    
+    /// 设置剩余跳数
+    pub fn with_ttl(mut self, ttl: u8) -> Self {
+        self.ttl = ttl;
+        self
+    }
+    
+    /// 负载
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// 固定容量的先进先出队列
+struct Fifo<const N: usize> {
+    entries: [Option<QueuedPacket>; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> Fifo<N> {
+    fn new() -> Self {
+        Self {
+            entries: core::array::from_fn(|_| None),
+            head: 0,
+            len: 0,
+        }
+    }
+    
+    fn push(&mut self, packet: QueuedPacket) -> Result<(), QueuedPacket> {
+        if self.len == N {
+            return Err(packet);
+        }
+        self.entries[(self.head + self.len) % N] = Some(packet);
+        self.len += 1;
+        Ok(())
+    }
+    
+    fn front(&self) -> Option<&QueuedPacket> {
+        if self.len == 0 {
+            return None;
+        }
+        self.entries[self.head].as_ref()
+    }
+    
+    fn pop(&mut self) -> Option<QueuedPacket> {
+        if self.len == 0 {
+            return None;
+        }
+        let packet = self.entries[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        packet
+    }
+}
+
+/// 一个会话的数据流
+struct Flow {
+    flow_id: u16,
+    weight: u8,
+    /// 差额计数：累计还可发送的字节数
+    deficit: usize,
+    queue: Fifo<FLOW_QUEUE_LEN>,
+}
+
+impl Flow {
+    fn quantum(&self) -> usize {
+        self.weight as usize * QUANTUM_BYTES
+    }
+}
+
+/// 转发节点的发送调度器
+///
+/// 控制流量严格优先；会话数据按头部的流ID做差额轮转（DRR）：轮到一个流时加上它的份额，
+/// 只要队首包不超过累计的差额就继续发送，按字节而不是按包数计，发得快或包大的会话
+/// 不能挤占其他会话的空口时间，同权重的会话各得相近的发送字节数。本节点直接应答或中继的
+/// 管理、回显等消息不经过调度器，在处理时立即发出，相当于最高优先级。
+pub struct TxScheduler {
+    control: Fifo<CONTROL_QUEUE_LEN>,
+    flows: [Option<Flow>; MAX_FLOWS],
+    /// 轮转位置
+    cursor: usize,
+}
+
+impl TxScheduler {
+    /// 创建空的调度器
+    pub fn new() -> Self {
+        Self {
+            control: Fifo::new(),
+            flows: [None, None, None, None],
+            cursor: 0,
+        }
+    }
+    
+    /// 放入待发送的包，队列已满时原样返回，由调用方通知发送方拥塞
+    pub fn enqueue(&mut self, class: TrafficClass, packet: QueuedPacket) -> Result<(), QueuedPacket> {
+        let result = match class {
+            TrafficClass::Control => self.control.push(packet),
+            TrafficClass::Data { flow_id, weight } => {
+                let index = self.flows.iter().position(|flow| matches!(flow, Some(f) if f.flow_id == flow_id))
+                    .or_else(|| self.flows.iter().position(|flow| matches!(flow, Some(f) if f.queue.len == 0)))
+                    .or_else(|| self.flows.iter().position(|flow| flow.is_none()));
+                
+                match index {
+                    Some(index) => {
+                        let flow = self.flows[index].get_or_insert_with(|| Flow {
+                            flow_id,
+                            weight,
+                            deficit: 0,
+                            queue: Fifo::new(),
+                        });
+                        // 空闲的流可以让给新会话
+                        if flow.flow_id != flow_id {
+                            flow.flow_id = flow_id;
+                            flow.deficit = 0;
+                        }
+                        flow.weight = weight.max(1);
+                        flow.queue.push(packet)
+                    },
+                    None => Err(packet),
+                }
+            },
+        };
+        
+        metrics::set(Gauge::TxQueue, self.pending() as u32);
+        result
+    }
+    
+    /// 取出下一个应发送的包，`bulk`为false时会话数据留在队列中等待本节点的时隙
+    pub fn dequeue(&mut self, bulk: bool) -> Option<QueuedPacket> {
+        if let Some(packet) = self.control.pop() {
+            return Some(packet);
+        }
+        if !bulk {
+            return None;
+        }
+        
+        // 份额不小于单帧负载，最多转一圈就能找到可发送的包
+        for _ in 0..=MAX_FLOWS {
+            if let Some(flow) = self.flows[self.cursor].as_mut() {
+                match flow.queue.front().map(|packet| packet.data.len()) {
+                    Some(len) if len <= flow.deficit => {
+                        flow.deficit -= len;
+                        let packet = flow.queue.pop();
+                        // 队列清空的流不保留差额，避免空闲后积攒份额突发
+                        if flow.queue.len == 0 {
+                            flow.deficit = 0;
+                        }
+                        return packet;
+                    },
+                    Some(_) => {},
+                    None => flow.deficit = 0,
+                }
+            }
+            
+            // 轮到下一个有数据的流时加上它的份额
+            self.cursor = (self.cursor + 1) % MAX_FLOWS;
+            if let Some(flow) = self.flows[self.cursor].as_mut() {
+                if flow.queue.len > 0 {
+                    flow.deficit += flow.quantum();
+                }
+            }
+        }
+        
+        None
+    }
+    
+    /// 等待发送的包数
+    pub fn pending(&self) -> usize {
+        self.control.len + self.pending_data()
+    }
+    
+    /// 等待发送的会话数据包数
+    pub fn pending_data(&self) -> usize {
+        self.flows.iter().flatten().map(|flow| flow.queue.len).sum()
+    }
+    
+    /// 按调度顺序发送最多`budget`个包，返回实际发送的包数
+    pub fn poll<H: Hardware>(&mut self, hardware: &mut H, budget: usize, bulk: bool) -> usize {
+        let node_id = hardware.get_node_id();
+        let mut sent = 0;
+        
+        while sent < budget {
+            let queued = match self.dequeue(bulk) {
+                Some(queued) => queued,
+                None => break,
+            };
+            
+            let destination = queued.destination.unwrap_or(queued.next_hop);
+            let mut packet = DataPacket::with_type(node_id, destination, queued.packet_type, queued.packet_id, queued.data());
+            (packet.header.total_fragments, packet.header.fragment_index) = queued.fragment;
+            packet.header.ttl = queued.ttl;
+            let packet = packet.with_flow(queued.flow_id);
+            if let Err(e) = send_secure_to(hardware, queued.next_hop, &packet) {
+                warn!("发送到 {} 失败: {:?}", queued.next_hop, e);
+            }
+            sent += 1;
+        }
+        
+        metrics::set(Gauge::TxQueue, self.pending() as u32);
+        sent
+    }
+}
\ No newline at end of file
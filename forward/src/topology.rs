@@ -0,0 +1,124 @@
+use common::hal::Hardware;
+use common::protocol::{DataPacket, NodeId};
+use common::protocol::topology::{send_topology, TopologyMessage, TopologyReport};
+use common::topology::TopologyMap;
+use common::{info, warn};
+use crate::routing::RoutingTable;
+use crate::routing::dynamic_forwarding::ForwardingEngine;
+
+/// 主节点发起拓扑收集的间隔（毫秒）
+pub const TOPOLOGY_INTERVAL_MS: u64 = 60000;
+
+/// 拓扑收集：主节点定期广播请求并汇总各转发节点的路由表，其他转发节点转发请求并上报自己的路由表
+pub struct TopologyAgent {
+    /// 主节点汇总的拓扑
+    map: TopologyMap,
+    /// 本节点作为主节点时的请求ID
+    request_id: u16,
+    /// 最近处理过的请求，每个请求只转发和上报一次
+    last_request: Option<(NodeId, u16)>,
+    /// 上次发起收集的时间
+    timer: u64,
+}
+
+impl TopologyAgent {
+    /// 创建拓扑收集代理
+    pub fn new() -> Self {
+        Self {
+            map: TopologyMap::new(),
+            request_id: 0,
+            last_request: None,
+            timer: 0,
+        }
+    }
+    
+    /// 主节点汇总的拓扑，上一轮收集的结果
+    pub fn map(&self) -> &TopologyMap {
+        &self.map
+    }
+    
+    /// 本节点是主节点时按间隔发起新一轮收集，返回是否发起
+    pub fn poll<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        forwarding_engine: &ForwardingEngine,
+        is_master: bool,
+        now: u64
+    ) -> bool {
+        if !is_master || now - self.timer <= TOPOLOGY_INTERVAL_MS {
+            return false;
+        }
+        self.timer = now;
+        self.map.expire(now);
+        
+        let node_id = hardware.get_node_id();
+        self.request_id = self.request_id.wrapping_add(1);
+        self.last_request = Some((node_id, self.request_id));
+        
+        // 主节点自己的路由表直接记录
+        let report = TopologyReport::from_routes(node_id, self.request_id, node_id, forwarding_engine.routes());
+        self.map.update(&report, now);
+        
+        let request = TopologyMessage::Request { master: node_id, request_id: self.request_id };
+        if let Err(e) = send_topology(hardware, NodeId::BROADCAST, &request) {
+            warn!("发送拓扑请求失败: {:?}", e);
+        }
+        true
+    }
+    
+    /// 处理拓扑消息
+    pub fn handle<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        forwarding_engine: &mut ForwardingEngine,
+        packet: &DataPacket,
+        now: u64
+    ) {
+        let node_id = hardware.get_node_id();
+        
+        match TopologyMessage::deserialize(packet.data) {
+            Some(TopologyMessage::Request { master, request_id }) => {
+                if master == node_id || self.last_request == Some((master, request_id)) {
+                    return;
+                }
+                self.last_request = Some((master, request_id));
+                
+                // 继续广播请求，让主节点听不到的转发节点也能收到
+                let request = TopologyMessage::Request { master, request_id };
+                if let Err(e) = send_topology(hardware, NodeId::BROADCAST, &request) {
+                    warn!("转发拓扑请求失败: {:?}", e);
+                }
+                
+                let report = TopologyReport::from_routes(master, request_id, node_id, forwarding_engine.routes());
+                send_toward_master(hardware, forwarding_engine, &TopologyMessage::Report(report));
+            },
+            Some(TopologyMessage::Report(report)) => {
+                if report.master == node_id {
+                    info!("收到 {} 的拓扑报告，{} 条路由", report.reporter, report.entries().len());
+                    self.map.update(&report, now);
+                } else if NodeId(packet.header.destination) == node_id {
+                    // 本节点是去往主节点的中间一跳
+                    send_toward_master(hardware, forwarding_engine, &TopologyMessage::Report(report));
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+/// 将报告发往主节点，没有路由时直接发给主节点
+fn send_toward_master<H: Hardware>(
+    hardware: &mut H,
+    forwarding_engine: &mut ForwardingEngine,
+    message: &TopologyMessage
+) {
+    let master = match message {
+        TopologyMessage::Report(report) => report.master,
+        TopologyMessage::Request { master, .. } => *master,
+    };
+    let next_hop = forwarding_engine.get_next_hop(master).unwrap_or(master);
+    
+    if let Err(e) = send_topology(hardware, next_hop, message) {
+        warn!("发送拓扑报告失败: {:?}", e);
+    }
+}
\ No newline at end of file
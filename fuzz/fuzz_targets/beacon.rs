@@ -0,0 +1,11 @@
+#![no_main]
+
+use common::protocol::{Beacon, NodeRole};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(beacon) = Beacon::from_bytes(data) {
+        let _ = beacon.is_valid();
+        let _ = NodeRole::from_u8(beacon.reserved[0]);
+    }
+});
\ No newline at end of file
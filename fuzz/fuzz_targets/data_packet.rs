@@ -0,0 +1,16 @@
+#![no_main]
+
+use common::protocol::{DataPacket, PacketType, MAX_PACKET_SIZE};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(packet) = DataPacket::parse(data) {
+        // 解析出的负载必须落在缓冲区和最大包长之内
+        let data_length = packet.header.data_length as usize;
+        assert_eq!(packet.data.len(), data_length);
+        assert!(data_length <= MAX_PACKET_SIZE);
+        
+        let _ = packet.is_valid();
+        let _ = PacketType::from_u8(packet.header.packet_type);
+    }
+});
\ No newline at end of file
@@ -0,0 +1,14 @@
+#![no_main]
+
+use common::protocol::election::ElectionMessage;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(message) = ElectionMessage::deserialize(data) {
+        // 重新序列化的结果必须与输入前缀一致
+        let mut buffer = [0u8; 16];
+        let len = message.serialize(&mut buffer);
+        assert_ne!(len, 0);
+        assert_eq!(&buffer[..len], &data[..len]);
+    }
+});
\ No newline at end of file
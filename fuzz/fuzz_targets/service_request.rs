@@ -0,0 +1,19 @@
+#![no_main]
+
+use common::protocol::{
+    deserialize_service_handover, deserialize_service_renewal, deserialize_service_request,
+    serialize_service_request,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(request) = deserialize_service_request(data) {
+        // 解析成功的请求必须能重新序列化
+        let mut buffer = [0u8; 16];
+        assert_ne!(serialize_service_request(&request, &mut buffer), 0);
+    }
+    
+    // 续期和切换请求与服务请求共用同一类负载
+    let _ = deserialize_service_renewal(data);
+    let _ = deserialize_service_handover(data);
+});
\ No newline at end of file
@@ -0,0 +1,13 @@
+#![no_main]
+
+use common::protocol::{deserialize_service_response, serialize_service_response};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(response) = deserialize_service_response(data) {
+        let mut buffer = [0u8; 16];
+        let len = serialize_service_response(&response, &mut buffer);
+        // v1响应重新序列化为当前版本，按字段比较
+        assert_eq!(deserialize_service_response(&buffer[..len]), Some(response));
+    }
+});
\ No newline at end of file
@@ -0,0 +1,187 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::hal::{Hardware, NvStorage};
+use common::hal::simulator::{SimFirmware, SimFlash, SimI2c};
+use common::info;
+use common::pool::{NodePool, PACKET_POOL};
+use common::protocol::NodeId;
+use common::protocol::data::{DEFAULT_TTL, MAX_TTL};
+use common::security::SecurityContext;
+
+use crate::link::{FrameLink, LinkError, LinkRadio};
+
+/// 以目录保存的非易失存储，每个键一个文件
+///
+/// 网络密钥和发送计数器必须跨进程重启保存，否则重启后会重复使用随机数。
+pub struct FileNvs {
+    dir: PathBuf,
+}
+
+impl FileNvs {
+    /// 使用指定目录，不存在时创建
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+    
+    fn path(&self, key: u16) -> PathBuf {
+        self.dir.join(format!("{:04x}", key))
+    }
+}
+
+impl NvStorage for FileNvs {
+    type Error = io::Error;
+    
+    fn nvs_read(&mut self, key: u16, buffer: &mut [u8]) -> Result<Option<usize>, Self::Error> {
+        match fs::read(self.path(key)) {
+            Ok(value) => {
+                let len = value.len().min(buffer.len());
+                buffer[..len].copy_from_slice(&value[..len]);
+                Ok(Some(len))
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    fn nvs_write(&mut self, key: u16, data: &[u8]) -> Result<(), Self::Error> {
+        // 先写临时文件再改名，掉电时不会留下半个值
+        let path = self.path(key);
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, data)?;
+        fs::rename(temp, path)
+    }
+    
+    fn nvs_erase(&mut self, key: u16) -> Result<(), Self::Error> {
+        match fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 网关硬件：无线电经UDP或串口链路连接，其余部分在主机上模拟
+///
+/// 网关由市电供电，没有传感器，也不进入低功耗模式。
+pub struct GatewayHardware<L: FrameLink> {
+    node_id: NodeId,
+    radio: LinkRadio<L>,
+    start_time: Instant,
+    nvs: FileNvs,
+    firmware: SimFirmware,
+    flash: SimFlash,
+    i2c: SimI2c,
+    security: SecurityContext,
+    ttl: u8,
+}
+
+impl<L: FrameLink> GatewayHardware<L> {
+    pub fn new(node_id: NodeId, link: L, nvs: FileNvs) -> Self {
+        Self {
+            node_id,
+            radio: LinkRadio::new(link),
+            start_time: Instant::now(),
+            nvs,
+            firmware: SimFirmware::new(),
+            flash: SimFlash::new(),
+            i2c: SimI2c::new(),
+            security: SecurityContext::new(),
+            ttl: DEFAULT_TTL,
+        }
+    }
+}
+
+impl<L: FrameLink> Hardware for GatewayHardware<L> {
+    type Error = LinkError;
+    type Radio = LinkRadio<L>;
+    type Nvs = FileNvs;
+    type Firmware = SimFirmware;
+    type Flash = SimFlash;
+    type I2c = SimI2c;
+    
+    fn get_node_id(&self) -> NodeId {
+        self.node_id
+    }
+    
+    fn set_node_id(&mut self, node_id: NodeId) {
+        self.node_id = node_id;
+    }
+    
+    fn get_radio(&mut self) -> &mut Self::Radio {
+        &mut self.radio
+    }
+    
+    fn get_nvs(&mut self) -> &mut Self::Nvs {
+        &mut self.nvs
+    }
+    
+    fn get_firmware(&mut self) -> &mut Self::Firmware {
+        &mut self.firmware
+    }
+    
+    fn get_flash(&mut self) -> &mut Self::Flash {
+        &mut self.flash
+    }
+    
+    fn get_i2c(&mut self) -> &mut Self::I2c {
+        &mut self.i2c
+    }
+    
+    fn get_security(&mut self) -> &mut SecurityContext {
+        &mut self.security
+    }
+    
+    fn packet_pool(&self) -> &'static NodePool {
+        &PACKET_POOL
+    }
+    
+    fn default_ttl(&self) -> u8 {
+        self.ttl
+    }
+    
+    fn set_default_ttl(&mut self, ttl: u8) {
+        self.ttl = ttl.clamp(1, MAX_TTL);
+    }
+    
+    fn get_battery_level(&self) -> Result<u8, Self::Error> {
+        Ok(100)
+    }
+    
+    fn get_timestamp_ms(&self) -> Result<u64, Self::Error> {
+        Ok(self.start_time.elapsed().as_millis() as u64)
+    }
+    
+    fn delay_ms(&mut self, ms: u32) -> Result<(), Self::Error> {
+        thread::sleep(Duration::from_millis(ms as u64));
+        Ok(())
+    }
+    
+    fn enter_low_power_mode(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    
+    fn exit_low_power_mode(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    
+    fn system_reset(&mut self) -> Result<(), Self::Error> {
+        // 网关作为主机进程运行，由进程管理器负责重启
+        info!("网关收到复位请求，已忽略");
+        Ok(())
+    }
+    
+    fn console_read(&mut self, _buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(0)
+    }
+    
+    fn console_write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        use std::io::Write;
+        
+        std::io::stdout().write_all(data).map_err(LinkError::Io)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use common::hal::{RadioDiagnostics, RadioInterface};
+use common::metrics::{self, Counter};
+use common::protocol::{Beacon, DataPacket, MAX_PACKET_SIZE};
+use common::protocol::slip::{self, SlipDecoder};
+
+/// 等待取走的帧数上限，超出时丢弃最早的帧
+const MAX_QUEUED_FRAMES: usize = 32;
+
+/// 承载原始无线帧的链路
+pub trait FrameLink {
+    /// 发送一帧
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()>;
+    
+    /// 非阻塞地读取一帧，没有完整的帧时返回None
+    fn receive_frame(&mut self, buffer: &mut [u8]) -> io::Result<Option<usize>>;
+}
+
+/// UDP链路，每个数据报为一帧，用于连接网络上的无线模块或模拟器桥
+pub struct UdpLink {
+    socket: UdpSocket,
+    peer: SocketAddr,
+}
+
+impl UdpLink {
+    /// 绑定本地地址，只与指定的对端交换帧
+    pub fn bind(local: SocketAddr, peer: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, peer })
+    }
+}
+
+impl FrameLink for UdpLink {
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.socket.send_to(frame, self.peer).map(|_| ())
+    }
+    
+    fn receive_frame(&mut self, buffer: &mut [u8]) -> io::Result<Option<usize>> {
+        loop {
+            match self.socket.recv_from(buffer) {
+                Ok((len, from)) if from == self.peer => return Ok(Some(len)),
+                // 忽略其他来源的数据报
+                Ok(_) => continue,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// 串口链路，无线模块以SLIP分帧收发原始无线帧
+pub struct SerialLink {
+    port: Box<dyn serialport::SerialPort>,
+    decoder: SlipDecoder<MAX_PACKET_SIZE>,
+    /// 已读取但尚未解码的字节
+    pending: VecDeque<u8>,
+}
+
+impl SerialLink {
+    /// 打开串口
+    pub fn open(path: &str, baud_rate: u32) -> io::Result<Self> {
+        let port = serialport::new(path, baud_rate)
+            .timeout(Duration::from_millis(1))
+            .open()?;
+        Ok(Self {
+            port,
+            decoder: SlipDecoder::new(),
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+impl FrameLink for SerialLink {
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        // 最坏情况下每个字节都需要转义
+        let mut encoded = [0u8; MAX_PACKET_SIZE * 2 + 2];
+        let len = slip::encode(frame, &mut encoded);
+        io::Write::write_all(&mut self.port, &encoded[..len])
+    }
+    
+    fn receive_frame(&mut self, buffer: &mut [u8]) -> io::Result<Option<usize>> {
+        loop {
+            while let Some(byte) = self.pending.pop_front() {
+                if let Some(frame) = self.decoder.push(byte) {
+                    if frame.len() > buffer.len() {
+                        continue;
+                    }
+                    buffer[..frame.len()].copy_from_slice(frame);
+                    return Ok(Some(frame.len()));
+                }
+            }
+            
+            let mut chunk = [0u8; 64];
+            match self.port.read(&mut chunk) {
+                Ok(0) => return Ok(None),
+                Ok(len) => self.pending.extend(&chunk[..len]),
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// 链路错误
+#[derive(Debug)]
+pub enum LinkError {
+    /// 链路读写失败
+    Io(io::Error),
+    /// 无线参数超出范围
+    InvalidConfig,
+    /// 帧超过最大长度
+    FrameTooLarge,
+}
+
+/// 基于帧链路的无线电接口
+///
+/// 信标和数据包在链路上都是原始字节，按长度和类型字节区分后分别排队。
+pub struct LinkRadio<L: FrameLink> {
+    link: L,
+    beacons: VecDeque<Beacon>,
+    packets: VecDeque<Vec<u8>>,
+    /// 重传和信道检测由链路另一端的无线模块完成，这里看不到，总是0
+    diagnostics: RadioDiagnostics,
+}
+
+impl<L: FrameLink> LinkRadio<L> {
+    pub fn new(link: L) -> Self {
+        Self {
+            link,
+            beacons: VecDeque::new(),
+            packets: VecDeque::new(),
+            diagnostics: RadioDiagnostics::default(),
+        }
+    }
+    
+    /// 读取链路上已到达的所有帧
+    fn poll(&mut self) -> Result<(), LinkError> {
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        while let Some(len) = self.link.receive_frame(&mut buffer).map_err(LinkError::Io)? {
+            let frame = &buffer[..len];
+            if len == Beacon::SIZE {
+                if let Some(beacon) = Beacon::from_bytes(frame) {
+                    if self.beacons.len() >= MAX_QUEUED_FRAMES {
+                        self.beacons.pop_front();
+                        self.count_overrun();
+                    }
+                    self.beacons.push_back(beacon);
+                    continue;
+                }
+            }
+            
+            if self.packets.len() >= MAX_QUEUED_FRAMES {
+                self.packets.pop_front();
+                self.count_overrun();
+            }
+            self.packets.push_back(frame.to_vec());
+        }
+        Ok(())
+    }
+    
+    fn count_overrun(&mut self) {
+        self.diagnostics.rx_overruns += 1;
+        metrics::increment(Counter::RxOverruns);
+    }
+    
+    /// 发送一帧并计数
+    fn send_frame(&mut self, frame: &[u8]) -> Result<(), LinkError> {
+        self.link.send_frame(frame).map_err(LinkError::Io)?;
+        self.diagnostics.frames_sent += 1;
+        metrics::increment(Counter::RadioTx);
+        Ok(())
+    }
+}
+
+impl<L: FrameLink> RadioInterface for LinkRadio<L> {
+    type Error = LinkError;
+    
+    fn send_beacon(&mut self, beacon: &Beacon) -> Result<(), Self::Error> {
+        self.send_frame(&beacon.to_bytes())
+    }
+    
+    fn send_data<'a>(&mut self, packet: &DataPacket<'a>) -> Result<(), Self::Error> {
+        let mut frame = [0u8; MAX_PACKET_SIZE];
+        let len = packet.encode(&mut frame).ok_or(LinkError::FrameTooLarge)?;
+        self.send_frame(&frame[..len])
+    }
+    
+    fn receive_beacon(&mut self) -> Result<Option<Beacon>, Self::Error> {
+        self.poll()?;
+        Ok(self.beacons.pop_front())
+    }
+    
+    fn receive_data<'a>(&mut self, buffer: &'a mut [u8]) -> Result<Option<DataPacket<'a>>, Self::Error> {
+        self.poll()?;
+        
+        while let Some(frame) = self.packets.pop_front() {
+            if frame.len() > buffer.len() {
+                self.count_overrun();
+                continue;
+            }
+            buffer[..frame.len()].copy_from_slice(&frame);
+            let buffer: &'a [u8] = buffer;
+            // 截断或长度字段越界的帧直接丢弃，按CRC错误计数
+            let packet = DataPacket::parse(&buffer[..frame.len()]);
+            if packet.is_none() {
+                self.diagnostics.crc_errors += 1;
+                metrics::increment(Counter::CrcErrors);
+            }
+            return Ok(packet);
+        }
+        
+        Ok(None)
+    }
+    
+    fn configure(&mut self, channel: u8, _power: u8) -> Result<(), Self::Error> {
+        // 信道和功率由链路另一端的无线模块设置，这里只检查范围
+        if !(11..=26).contains(&channel) {
+            return Err(LinkError::InvalidConfig);
+        }
+        Ok(())
+    }
+    
+    fn get_rssi(&self) -> Result<i8, Self::Error> {
+        // 链路不提供信号强度
+        Ok(0)
+    }
+    
+    fn diagnostics(&self) -> RadioDiagnostics {
+        self.diagnostics
+    }
+}
\ No newline at end of file
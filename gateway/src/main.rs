@@ -0,0 +1,308 @@
+mod hal;
+mod link;
+mod topics;
+
+use std::net::SocketAddr;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use common::clock::NetworkClock;
+use common::hal::{Hardware, RadioInterface};
+use common::protocol::{DataPacket, NodeId, PacketType};
+use common::protocol::command::COMMAND_TAG_LEN;
+use common::protocol::data::Reassembler;
+use common::protocol::payload::{self, Command};
+use common::protocol::echo::answer_echo;
+use common::protocol::time_sync::TimeBeacon;
+use common::security::{self, command_tag, receive_secure, send_secure, NETWORK_KEY_LEN};
+use common::utils::AlignedBuffer;
+use common::{info, warn};
+use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS};
+
+use hal::{FileNvs, GatewayHardware};
+use link::{FrameLink, SerialLink, UdpLink};
+use topics::{parse_hex, parse_node, Publication, Topics};
+
+/// 默认的网关节点ID
+const DEFAULT_NODE_ID: [u8; 6] = [0x47, 0x57, 0x00, 0x00, 0x00, 0x01];
+
+/// MQTT请求队列长度
+const MQTT_QUEUE_CAPACITY: usize = 64;
+
+/// 网关配置，从环境变量读取
+///
+/// - `AETHER_GATEWAY_LINK`：`udp:本地地址@对端地址`或`serial:设备路径:波特率`
+/// - `AETHER_MQTT_BROKER`：代理地址，默认`localhost:1883`
+/// - `AETHER_MQTT_PREFIX`：主题前缀，默认`linknebula`
+/// - `AETHER_NODE_ID`：网关节点ID（十六进制）
+/// - `AETHER_NETWORK_KEY`：网络密钥（32位十六进制），网络启用保护时配置一次即可
+/// - `AETHER_GATEWAY_STATE`：保存密钥和计数器的目录，默认`gateway-state`
+/// - `AETHER_HTTP_ADDR`：启用`http`特性时HTTP状态接口的监听地址
+struct Config {
+    link: LinkConfig,
+    state_dir: String,
+    broker_host: String,
+    broker_port: u16,
+    prefix: String,
+    node_id: NodeId,
+    network_key: Option<[u8; NETWORK_KEY_LEN]>,
+}
+
+/// 连接无线模块的链路
+enum LinkConfig {
+    Udp { local: SocketAddr, peer: SocketAddr },
+    Serial { path: String, baud_rate: u32 },
+}
+
+impl Config {
+    fn from_env() -> Result<Self, String> {
+        let env = |name: &str| std::env::var(name).ok();
+        
+        let link = env("AETHER_GATEWAY_LINK").ok_or("未设置 AETHER_GATEWAY_LINK")?;
+        let link = if let Some(udp) = link.strip_prefix("udp:") {
+            let (local, peer) = udp.split_once('@').ok_or("UDP链路格式应为 udp:本地地址@对端地址")?;
+            LinkConfig::Udp {
+                local: local.parse().map_err(|_| format!("无效的本地地址: {}", local))?,
+                peer: peer.parse().map_err(|_| format!("无效的对端地址: {}", peer))?,
+            }
+        } else if let Some(serial) = link.strip_prefix("serial:") {
+            let (path, baud_rate) = serial.rsplit_once(':').ok_or("串口链路格式应为 serial:设备路径:波特率")?;
+            LinkConfig::Serial {
+                path: path.to_string(),
+                baud_rate: baud_rate.parse().map_err(|_| format!("无效的波特率: {}", baud_rate))?,
+            }
+        } else {
+            return Err(format!("未知的链路类型: {}", link));
+        };
+        
+        let broker = env("AETHER_MQTT_BROKER").unwrap_or_else(|| "localhost:1883".to_string());
+        let (broker_host, broker_port) = match broker.rsplit_once(':') {
+            Some((host, port)) => (host.to_string(), port.parse().map_err(|_| format!("无效的代理端口: {}", port))?),
+            None => (broker, 1883),
+        };
+        
+        let node_id = match env("AETHER_NODE_ID") {
+            Some(id) => parse_node(&id).ok_or_else(|| format!("无效的节点ID: {}", id))?,
+            None => NodeId::new(DEFAULT_NODE_ID),
+        };
+        
+        let network_key = match env("AETHER_NETWORK_KEY") {
+            Some(key) => {
+                let key = parse_hex(&key).and_then(|bytes| bytes.try_into().ok());
+                Some(key.ok_or("网络密钥应为16字节十六进制")?)
+            },
+            None => None,
+        };
+        
+        Ok(Self {
+            link,
+            state_dir: env("AETHER_GATEWAY_STATE").unwrap_or_else(|| "gateway-state".to_string()),
+            broker_host,
+            broker_port,
+            prefix: env("AETHER_MQTT_PREFIX").unwrap_or_else(|| "linknebula".to_string()),
+            node_id,
+            network_key,
+        })
+    }
+}
+
+fn main() {
+    info!("启动AetherLink MQTT网关");
+    
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("配置错误: {}", e);
+            std::process::exit(1);
+        },
+    };
+    
+    // 可选的HTTP状态接口，供看板和集成测试查询
+    #[cfg(feature = "http")]
+    if let Ok(addr) = std::env::var("AETHER_HTTP_ADDR") {
+        match common::http::start(&addr) {
+            Ok(()) => info!("HTTP状态接口监听于 {}", addr),
+            Err(e) => warn!("HTTP状态接口启动失败: {}", e),
+        }
+    }
+    
+    let nvs = match FileNvs::open(&config.state_dir) {
+        Ok(nvs) => nvs,
+        Err(e) => {
+            warn!("打开状态目录 {} 失败: {}", config.state_dir, e);
+            std::process::exit(1);
+        },
+    };
+    
+    let result = match &config.link {
+        LinkConfig::Udp { local, peer } => UdpLink::bind(*local, *peer)
+            .map(|link| run(GatewayHardware::new(config.node_id, link, nvs), &config)),
+        LinkConfig::Serial { path, baud_rate } => SerialLink::open(path, *baud_rate)
+            .map(|link| run(GatewayHardware::new(config.node_id, link, nvs), &config)),
+    };
+    
+    if let Err(e) = result {
+        warn!("打开无线链路失败: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// 连接MQTT代理，返回客户端和收到的命令消息
+fn connect_mqtt(config: &Config, topics: &Topics) -> (Client, Receiver<(String, Vec<u8>)>) {
+    let mut options = MqttOptions::new(
+        format!("linknebula-gateway-{}", topics::node_segment(config.node_id)),
+        config.broker_host.clone(),
+        config.broker_port,
+    );
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(topics.status(), "offline", QoS::AtLeastOnce, true));
+    
+    let (client, mut connection) = Client::new(options, MQTT_QUEUE_CAPACITY);
+    let (sender, receiver) = mpsc::channel();
+    
+    // 连接事件循环在单独的线程中运行，断线后rumqttc会自动重连
+    let subscriber = client.clone();
+    let filter = topics.command_filter();
+    let status = topics.status();
+    thread::spawn(move || {
+        for event in connection.iter() {
+            match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    info!("已连接MQTT代理");
+                    // 重连后需要重新订阅和声明在线
+                    let _ = subscriber.try_subscribe(filter.as_str(), QoS::AtLeastOnce);
+                    let _ = subscriber.try_publish(status.as_str(), QoS::AtLeastOnce, true, "online");
+                },
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    if sender.send((publish.topic, publish.payload.to_vec())).is_err() {
+                        break;
+                    }
+                },
+                Ok(_) => {},
+                Err(e) => {
+                    warn!("MQTT连接错误: {}", e);
+                    thread::sleep(Duration::from_secs(5));
+                },
+            }
+        }
+    });
+    
+    (client, receiver)
+}
+
+fn run<L: FrameLink>(mut hardware: GatewayHardware<L>, config: &Config) {
+    if let Err(e) = hardware.get_radio().configure(11, 20) {
+        warn!("无线电配置失败: {:?}", e);
+    }
+    security::restore(&mut hardware);
+    if let Some(key) = config.network_key {
+        if !security::provision(&mut hardware, key) {
+            warn!("保存网络密钥失败");
+        }
+    }
+    
+    let mut topics = Topics::new(&config.prefix);
+    let (mut client, commands) = connect_mqtt(config, &topics);
+    let mut clock = NetworkClock::new();
+    let mut buffer = AlignedBuffer::<256>::new();
+    // 超过一帧的响应（例如客户端的日志）分片到达，收齐后再转换
+    let mut reassembler = Reassembler::new();
+    // 各节点最近一次的遥测，通过HTTP接口的/nodes查询
+    #[cfg(feature = "http")]
+    let mut nodes = std::collections::BTreeMap::new();
+    
+    info!("网关 {} 已启动，主题前缀: {}", config.node_id, config.prefix);
+    
+    loop {
+        let mut idle = true;
+        
+        // 网格到MQTT：信标作为遥测
+        while let Ok(Some(beacon)) = hardware.get_radio().receive_beacon() {
+            idle = false;
+            if !security::verify_beacon(&mut hardware, &beacon) {
+                continue;
+            }
+            let telemetry = topics.beacon(&beacon);
+            #[cfg(feature = "http")]
+            {
+                let source = NodeId(beacon.source);
+                common::http::set_node_gauge("battery_level", source, beacon.battery_level as i64);
+                common::http::set_node_gauge("rssi", source, beacon.rssi as i64);
+                common::http::set_node_gauge("hops", source, beacon.hop_count as i64);
+                nodes.insert(topics::node_segment(source), telemetry.payload.clone());
+                let entries: Vec<String> = nodes.iter()
+                    .map(|(node, payload)| format!("\"{}\":{}", node, payload))
+                    .collect();
+                common::http::publish("/nodes", format!("{{{}}}", entries.join(",")));
+            }
+            publish(&mut client, telemetry);
+        }
+        
+        let local_now = hardware.get_timestamp_ms().unwrap_or(0);
+        let received = match receive_secure(&mut hardware, buffer.as_mut_slice()) {
+            Some(fragment) if fragment.is_fragment() => {
+                idle = false;
+                reassembler.push(&fragment, local_now)
+            },
+            received => received,
+        };
+        if let Some(packet) = received {
+            idle = false;
+            
+            // 跟随网络时间，样本时间戳与服务器存储的一致
+            if packet.header.packet_type == PacketType::TimeSync as u8 {
+                if let Some(beacon) = TimeBeacon::deserialize(packet.data) {
+                    clock.apply(&beacon, local_now);
+                }
+            }
+            
+            // 应答回显请求，网关也能被测量时延和追踪路径
+            answer_echo(&mut hardware, &packet);
+            
+            for publication in topics.packet(&packet, clock.now(local_now)) {
+                publish(&mut client, publication);
+            }
+        }
+        
+        // MQTT到网格：命令
+        while let Ok((topic, payload)) = commands.try_recv() {
+            idle = false;
+            match topics.command(&topic, &payload) {
+                Ok((target, mut data)) => {
+                    // 重启和配置命令附带认证码，节点据此确认命令来自持有网络密钥的网关
+                    if payload::decode::<Command>(&data).is_some_and(|command| command.command.is_privileged()) {
+                        let tag = command_tag(hardware.get_security(), target, &data).unwrap_or([0; COMMAND_TAG_LEN]);
+                        data.extend_from_slice(&tag);
+                    }
+                    let node_id = hardware.get_node_id();
+                    let packet = DataPacket::new(node_id, target, 0, &data);
+                    match send_secure(&mut hardware, &packet) {
+                        Ok(()) => info!("已向 {} 下发命令 {}", target, topic),
+                        Err(e) => warn!("下发命令失败: {:?}", e),
+                    }
+                },
+                Err(e) => warn!("无法转换命令 {}: {:?}", topic, e),
+            }
+        }
+        
+        // 分页查询的续传：服务器暂停、分片丢失或超时后带偏移重新请求
+        for (target, data) in topics.query_resumes(local_now) {
+            let packet = DataPacket::new(hardware.get_node_id(), target, 0, &data);
+            if let Err(e) = send_secure(&mut hardware, &packet) {
+                warn!("续传查询失败: {:?}", e);
+            }
+        }
+        
+        if idle {
+            let _ = hardware.delay_ms(10);
+        }
+    }
+}
+
+/// 发布一条消息，请求队列已满时丢弃
+fn publish(client: &mut Client, publication: Publication) {
+    if let Err(e) = client.try_publish(publication.topic, QoS::AtMostOnce, publication.retain, publication.payload) {
+        warn!("发布MQTT消息失败: {}", e);
+    }
+}
\ No newline at end of file
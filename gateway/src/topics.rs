@@ -0,0 +1,653 @@
+use common::protocol::{
+    Beacon, DataPacket, NodeId, NodeRole, PacketType, ServiceType,
+    deserialize_service_handover, deserialize_service_renewal,
+    deserialize_service_request, deserialize_service_response,
+};
+use common::protocol::batch::{deserialize_batch, BATCH_PAYLOAD_TYPE};
+use common::protocol::command::{
+    deserialize_aggregate_response, deserialize_latest_response, deserialize_log_response, deserialize_records,
+    AggregatePage, AggregateParams, AggregateWindow, CommandStatus, CommandType, ConfigParam, Downsample, Measurement, QueryChunk,
+    QueryPage, QueryParams, QueryProgress, QueryReassembler, MAX_LOG_RESPONSE_SAMPLES,
+};
+use common::protocol::payload::{self, Command};
+use common::protocol::error_report::ErrorReport;
+use common::warn;
+
+/// 同时等待结果的分页查询数，超出时放弃最早的查询
+const MAX_PENDING_QUERIES: usize = 8;
+
+/// 单个查询结果的上限（字节）
+const MAX_QUERY_RESULT: usize = 64 * 1024;
+
+/// 没有收到新分片多久后续传（毫秒）
+const QUERY_RESUME_TIMEOUT_MS: u64 = 2000;
+
+/// 没有进展时最多续传的次数，之后放弃查询
+const MAX_QUERY_RESUMES: u8 = 3;
+
+/// 主题方案
+///
+/// 前缀默认为`linknebula`，节点ID写作12位小写十六进制，例如`0a1b2c3d4e5f`。
+///
+/// 网格到MQTT：
+/// - `{前缀}/{节点}/sensor`：传感器样本，每个样本一条消息，
+///   `{"service_id":1,"timestamp":123456,"temperature":21.50,"humidity":40.00,"pressure":101320}`，
+///   时间戳为网络时间（毫秒）
+/// - `{前缀}/{节点}/telemetry`：信标中的角色、电量、信号强度和跳数，保留消息
+/// - `{前缀}/{节点}/response`：下行命令的执行结果，`{"command":"reboot","status":"ok"}`
+/// - `{前缀}/{节点}/log`：读取日志命令取回的样本，每个样本一条消息，
+///   `{"seq":42,"timestamp":123456,"temperature":21.50,"humidity":40.00,"pressure":101320}`，
+///   时间戳为节点本地时间（毫秒）
+/// - `{前缀}/{节点}/latest`：最新值查询返回的该节点最新样本，格式同`log`但没有序号，保留消息，
+///   节点为样本的来源节点而不是应答的服务器
+/// - `{前缀}/{节点}/aggregate`：聚合查询返回的各时间窗口，每个窗口一条消息，
+///   `{"measurement":"temperature","start":3600000,"count":60,"min":20.10,"max":22.40,"avg":21.25}`，
+///   节点为记录的来源节点；窗口超出一个数据包时网关带偏移取回后续窗口
+/// - `{前缀}/{节点}/query`：查询命令收齐的记录，每条记录一条消息，格式同`latest`，节点为记录的来源节点；
+///   结果分片到达，网关在服务器暂停或分片丢失时带偏移续传，收齐后才发布
+/// - `{前缀}/{节点}/error`：节点的错误报告，
+///   `{"code":"route_lost","category":"routing","occurrences":3,"timestamp":123456,"detail":7}`，
+///   节点为发生错误的节点，中继转发的报告可能收到多份
+/// - `{前缀}/directory/{request|response|renew|handover}`：服务目录流量
+/// - `{前缀}/gateway/status`：网关在线状态`online`/`offline`，保留消息，离线由遗嘱发布
+///
+/// MQTT到网格：
+/// - `{前缀}/{节点}/command/{query|configure|clear|reboot|stats|read_log|latest|aggregate}`：转换为发往该节点的命令包。
+///   configure的负载为空格或逗号分隔的`参数=值`：`sample_interval`（毫秒）、`channel`、
+///   `server`和`node_id`（节点ID）、`qos`和`network_key`（十六进制原始值）；
+///   read_log的负载为`起始序号 [样本数]`，为空时从最早的样本开始；
+///   query的负载为`起始时间 结束时间 [every=分钟|max=条数]`，按网络时间（毫秒）查询并在服务器上降采样，
+///   为空时返回全部记录；latest的负载为可选的节点ID，为空时返回所有节点的最新样本；
+///   aggregate的负载为`来源节点 temperature|humidity|pressure 起始时间 结束时间 every=分钟|points=窗口数`，
+///   例如每小时的平均温度用`every=60`；其余命令忽略负载
+pub struct Topics {
+    prefix: String,
+    queries: QueryTracker,
+    /// 等待后续窗口的聚合查询：目标节点和下一页的查询参数
+    aggregates: Vec<PendingAggregate>,
+}
+
+/// 网关等待后续窗口的聚合查询
+struct PendingAggregate {
+    node: NodeId,
+    /// 偏移为下一页的第一个窗口
+    params: AggregateParams,
+    /// 上一页已收到，需要发送下一页的请求
+    requested: bool,
+}
+
+/// 网关等待结果的查询
+struct PendingQuery {
+    node: NodeId,
+    /// 查询命令中分页参数之前的参数，续传时附上新的分页参数重发
+    request: Vec<u8>,
+    reassembler: QueryReassembler<Vec<u8>>,
+    /// 最近一次收到分片或发送请求的时间，None表示还没有开始计时
+    last_activity: Option<u64>,
+    /// 服务器暂停或出现缺口，需要立即续传
+    resume_requested: bool,
+    /// 上次收到新数据以来的续传次数
+    resumes: u8,
+}
+
+/// 跟踪网关下发的分页查询，重组结果并在需要时续传
+struct QueryTracker {
+    next_token: u16,
+    pending: Vec<PendingQuery>,
+}
+
+impl QueryTracker {
+    fn new() -> Self {
+        Self { next_token: 1, pending: Vec::new() }
+    }
+    
+    /// 登记发往节点的查询，返回分页参数；令牌跳过0，0留给不分页的旧请求方
+    fn start(&mut self, node: NodeId, request: &[u8]) -> QueryPage {
+        let token = self.next_token;
+        self.next_token = self.next_token.checked_add(1).unwrap_or(1);
+        
+        if self.pending.len() >= MAX_PENDING_QUERIES {
+            let dropped = self.pending.remove(0);
+            warn!("等待的查询过多，放弃发往 {} 的查询 {}", dropped.node, dropped.reassembler.token());
+        }
+        self.pending.push(PendingQuery {
+            node,
+            request: request.to_vec(),
+            reassembler: QueryReassembler::new(token, vec![0u8; MAX_QUERY_RESULT]),
+            last_activity: None,
+            resume_requested: false,
+            resumes: 0,
+        });
+        QueryPage { token, offset: 0 }
+    }
+    
+    /// 处理一个查询分片，收齐时返回完整结果
+    fn accept(&mut self, source: NodeId, chunk: &QueryChunk, data: &[u8]) -> Option<Vec<u8>> {
+        let index = self.pending.iter()
+            .position(|query| query.node == source && query.reassembler.token() == chunk.token)?;
+        let query = &mut self.pending[index];
+        let received = query.reassembler.next_page().offset;
+        
+        let progress = query.reassembler.accept(data);
+        if query.reassembler.next_page().offset > received {
+            query.last_activity = None;
+            query.resumes = 0;
+        }
+        match progress {
+            QueryProgress::Resume => query.resume_requested = true,
+            QueryProgress::Complete => {
+                let query = self.pending.remove(index);
+                return query.reassembler.data().map(|data| data.to_vec());
+            },
+            QueryProgress::Overflow => {
+                warn!("{} 的查询结果超过 {} 字节，已放弃", source, MAX_QUERY_RESULT);
+                self.pending.remove(index);
+            },
+            QueryProgress::Receiving | QueryProgress::Rejected(_) | QueryProgress::Ignored => {},
+        }
+        None
+    }
+    
+    /// 节点拒绝了查询，放弃该节点最早的查询
+    fn reject(&mut self, source: NodeId) {
+        if let Some(index) = self.pending.iter().position(|query| query.node == source) {
+            self.pending.remove(index);
+        }
+    }
+    
+    /// 需要续传的查询：服务器暂停、出现缺口或超时没有新分片；多次续传仍无进展时放弃
+    fn resumes(&mut self, now: u64) -> Vec<(NodeId, Vec<u8>)> {
+        let mut packets = Vec::new();
+        self.pending.retain_mut(|query| {
+            let last_activity = *query.last_activity.get_or_insert(now);
+            if !query.resume_requested && now.saturating_sub(last_activity) < QUERY_RESUME_TIMEOUT_MS {
+                return true;
+            }
+            if query.resumes >= MAX_QUERY_RESUMES {
+                warn!("发往 {} 的查询 {} 多次续传没有进展，已放弃", query.node, query.reassembler.token());
+                return false;
+            }
+            
+            let mut parameters = query.request.clone();
+            parameters.extend_from_slice(&query.reassembler.next_page().to_bytes());
+            packets.push((query.node, encode_command(CommandType::Query, &parameters)));
+            query.last_activity = Some(now);
+            query.resume_requested = false;
+            query.resumes += 1;
+            true
+        });
+        packets
+    }
+}
+
+/// 一条待发布的消息
+#[derive(Debug, Clone, PartialEq)]
+pub struct Publication {
+    pub topic: String,
+    pub payload: String,
+    pub retain: bool,
+}
+
+/// 命令主题或负载无法转换为命令包
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    /// 主题不符合命令主题格式
+    InvalidTopic,
+    /// 未知命令
+    UnknownCommand,
+    /// 配置参数名未知或值格式错误
+    InvalidParameter(String),
+}
+
+/// 节点ID的主题段
+pub fn node_segment(node: NodeId) -> String {
+    node.0.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 解析十六进制字节串，允许冒号分隔
+pub fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let digits: String = text.chars().filter(|c| *c != ':').collect();
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// 解析节点ID，主题段和配置中冒号分隔与连续的写法都接受
+pub fn parse_node(text: &str) -> Option<NodeId> {
+    text.parse().ok()
+}
+
+fn service_name(service_type: ServiceType) -> &'static str {
+    match service_type {
+        ServiceType::Storage => "storage",
+        ServiceType::Processing => "processing",
+        ServiceType::Gateway => "gateway",
+        ServiceType::VideoRelay => "video_relay",
+        ServiceType::AudioRelay => "audio_relay",
+        ServiceType::DataRelay => "data_relay",
+        ServiceType::SensorCollection => "sensor_collection",
+    }
+}
+
+fn role_name(role: NodeRole) -> &'static str {
+    match role {
+        NodeRole::Unknown => "unknown",
+        NodeRole::Client => "client",
+        NodeRole::Forward => "forward",
+        NodeRole::Server => "server",
+    }
+}
+
+fn command_name(command: CommandType) -> &'static str {
+    match command {
+        CommandType::Query => "query",
+        CommandType::Configure => "configure",
+        CommandType::Clear => "clear",
+        CommandType::Reboot => "reboot",
+        CommandType::Stats => "stats",
+        CommandType::ReadLog => "read_log",
+        CommandType::Latest => "latest",
+        CommandType::Aggregate => "aggregate",
+    }
+}
+
+fn measurement_name(measurement: Measurement) -> &'static str {
+    match measurement {
+        Measurement::Temperature => "temperature",
+        Measurement::Humidity => "humidity",
+        Measurement::Pressure => "pressure",
+    }
+}
+
+fn status_name(status: CommandStatus) -> &'static str {
+    match status {
+        CommandStatus::Ok => "ok",
+        CommandStatus::InvalidParameter => "invalid_parameter",
+        CommandStatus::Unsupported => "unsupported",
+        CommandStatus::PersistFailed => "persist_failed",
+        CommandStatus::Unauthorized => "unauthorized",
+    }
+}
+
+impl Topics {
+    pub fn new(prefix: &str) -> Self {
+        Self { prefix: prefix.trim_end_matches('/').to_string(), queries: QueryTracker::new(), aggregates: Vec::new() }
+    }
+    
+    /// 网关在线状态主题
+    pub fn status(&self) -> String {
+        format!("{}/gateway/status", self.prefix)
+    }
+    
+    /// 订阅所有节点命令的主题过滤器
+    pub fn command_filter(&self) -> String {
+        format!("{}/+/command/+", self.prefix)
+    }
+    
+    fn node_topic(&self, node: NodeId, leaf: &str) -> String {
+        format!("{}/{}/{}", self.prefix, node_segment(node), leaf)
+    }
+    
+    fn directory_topic(&self, leaf: &str) -> String {
+        format!("{}/directory/{}", self.prefix, leaf)
+    }
+    
+    /// 信标转换为节点遥测
+    pub fn beacon(&self, beacon: &Beacon) -> Publication {
+        Publication {
+            topic: self.node_topic(NodeId(beacon.source), "telemetry"),
+            payload: format!(
+                "{{\"role\":\"{}\",\"battery\":{},\"rssi\":{},\"hops\":{}}}",
+                role_name(beacon.role()), beacon.battery_level, beacon.rssi, beacon.hop_count
+            ),
+            retain: true,
+        }
+    }
+    
+    /// 数据包转换为待发布的消息，不需要桥接的包返回空
+    pub fn packet(&mut self, packet: &DataPacket, network_now: u64) -> Vec<Publication> {
+        let source = NodeId(packet.header.source);
+        let data = packet.data;
+        let publish = |topic: String, payload: String| Publication { topic, payload, retain: false };
+        
+        match PacketType::from_u8(packet.header.packet_type) {
+            Some(PacketType::Data) => self.data(source, data, network_now),
+            Some(PacketType::ServiceRequest) => deserialize_service_request(data)
+                .map(|request| publish(self.directory_topic("request"), format!(
+                    "{{\"client\":\"{}\",\"request_id\":{},\"service\":\"{}\",\"min_bandwidth\":{},\"max_latency\":{},\"reliability\":{},\"expiry\":{}}}",
+                    node_segment(source), request.request_id, service_name(request.service_type), request.qos.min_bandwidth,
+                    request.qos.max_latency, request.qos.reliability, request.expiry_time
+                )))
+                .into_iter().collect(),
+            Some(PacketType::ServiceResponse) => deserialize_service_response(data)
+                .map(|response| publish(self.directory_topic("response"), format!(
+                    "{{\"request_id\":{},\"service_id\":{},\"server\":\"{}\",\"status\":{}}}",
+                    response.request_id, response.service_id, node_segment(response.server_node_id), response.status
+                )))
+                .into_iter().collect(),
+            Some(PacketType::ServiceRenew) => deserialize_service_renewal(data)
+                .map(|renewal| publish(self.directory_topic("renew"), format!(
+                    "{{\"client\":\"{}\",\"service_id\":{},\"expiry\":{}}}",
+                    node_segment(source), renewal.service_id, renewal.expiry_time
+                )))
+                .into_iter().collect(),
+            Some(PacketType::ServiceHandover) => deserialize_service_handover(data)
+                .map(|handover| publish(self.directory_topic("handover"), format!(
+                    "{{\"client\":\"{}\",\"service_id\":{},\"server\":\"{}\",\"service\":\"{}\"}}",
+                    node_segment(source), handover.service_id,
+                    node_segment(handover.server_node_id), service_name(handover.request.service_type)
+                )))
+                .into_iter().collect(),
+            Some(PacketType::ErrorReport) => ErrorReport::deserialize(data)
+                .map(|report| publish(self.node_topic(report.origin, "error"), format!(
+                    "{{\"code\":\"{}\",\"category\":\"{}\",\"occurrences\":{},\"timestamp\":{},\"detail\":{}}}",
+                    report.code.key(), format!("{:?}", report.code.category()).to_lowercase(),
+                    report.occurrences, report.timestamp, report.detail
+                )))
+                .into_iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+    
+    /// 应用数据：批量传感器样本和命令响应
+    fn data(&mut self, source: NodeId, data: &[u8], network_now: u64) -> Vec<Publication> {
+        // 查询结果的分片：命令类型(1) 状态(1) 令牌(2) 偏移(4) 标志(1) 记录*，收齐后发布到各记录来源节点的主题
+        if let Some(chunk) = QueryChunk::deserialize(data) {
+            let result = match self.queries.accept(source, &chunk, data) {
+                Some(result) => result,
+                None => return Vec::new(),
+            };
+            let records = match deserialize_records(&result) {
+                Some(records) => records,
+                None => {
+                    warn!("{} 的查询结果长度 {} 不是整条记录", source, result.len());
+                    return Vec::new();
+                },
+            };
+            return records.map(|(node, sample)| Publication {
+                topic: self.node_topic(node, "query"),
+                payload: format!(
+                    "{{\"timestamp\":{},\"temperature\":{:.2},\"humidity\":{:.2},\"pressure\":{:.0}}}",
+                    sample.timestamp, sample.temperature, sample.humidity, sample.pressure
+                ),
+                retain: false,
+            }).collect();
+        }
+        
+        // 客户端的命令响应固定为 命令类型(1) 状态(1)
+        if data.len() == 2 {
+            if let (Some(command), Some(status)) = (CommandType::from_u8(data[0]), CommandStatus::from_u8(data[1])) {
+                if command == CommandType::Query && status != CommandStatus::Ok {
+                    self.queries.reject(source);
+                }
+                return vec![Publication {
+                    topic: self.node_topic(source, "response"),
+                    payload: format!("{{\"command\":\"{}\",\"status\":\"{}\"}}", command_name(command), status_name(status)),
+                    retain: false,
+                }];
+            }
+        }
+        
+        // 读取日志的响应：命令类型(1) 状态(1) 首个序号(4) 样本数(1) 样本*
+        if let Some((first_seq, samples)) = deserialize_log_response(data) {
+            return samples.enumerate().map(|(i, sample)| Publication {
+                topic: self.node_topic(source, "log"),
+                payload: format!(
+                    "{{\"seq\":{},\"timestamp\":{},\"temperature\":{:.2},\"humidity\":{:.2},\"pressure\":{:.0}}}",
+                    first_seq + i as u32, sample.timestamp,
+                    sample.temperature, sample.humidity, sample.pressure
+                ),
+                retain: false,
+            }).collect();
+        }
+        
+        // 最新值查询的响应：命令类型(1) 状态(1) 记录*，发布到各记录来源节点的主题
+        if let Some(records) = deserialize_latest_response(data) {
+            return records.map(|(node, sample)| Publication {
+                topic: self.node_topic(node, "latest"),
+                payload: format!(
+                    "{{\"timestamp\":{},\"temperature\":{:.2},\"humidity\":{:.2},\"pressure\":{:.0}}}",
+                    sample.timestamp, sample.temperature, sample.humidity, sample.pressure
+                ),
+                retain: true,
+            }).collect();
+        }
+        
+        // 聚合查询的响应：命令类型(1) 状态(1) 节点ID(6) 测量值(1) 窗口偏移(2) 后续(1) 窗口*，发布到记录来源节点的主题
+        if let Some((page, buckets)) = deserialize_aggregate_response(data) {
+            let publications: Vec<Publication> = buckets.map(|bucket| Publication {
+                topic: self.node_topic(page.node_id, "aggregate"),
+                payload: format!(
+                    "{{\"measurement\":\"{}\",\"start\":{},\"count\":{},\"min\":{:.2},\"max\":{:.2},\"avg\":{:.2}}}",
+                    measurement_name(page.measurement), bucket.start_time, bucket.count, bucket.min, bucket.max, bucket.avg
+                ),
+                retain: false,
+            }).collect();
+            self.aggregate_page(source, &page, publications.len());
+            return publications;
+        }
+        
+        if data.first() != Some(&BATCH_PAYLOAD_TYPE) {
+            return Vec::new();
+        }
+        let (service_id, samples) = match deserialize_batch(data) {
+            Some(batch) => batch,
+            None => return Vec::new(),
+        };
+        
+        samples.map(|sample| Publication {
+            topic: self.node_topic(source, "sensor"),
+            payload: format!(
+                "{{\"service_id\":{},\"timestamp\":{},\"temperature\":{:.2},\"humidity\":{:.2},\"pressure\":{:.0}}}",
+                service_id,
+                network_now.saturating_sub(sample.age_secs as u64 * 1000),
+                sample.temperature, sample.humidity, sample.pressure
+            ),
+            retain: false,
+        }).collect()
+    }
+    
+    /// 收到一页聚合窗口，还有后续窗口时登记下一页的请求，否则结束跟踪
+    fn aggregate_page(&mut self, source: NodeId, page: &AggregatePage, received: usize) {
+        let index = match self.aggregates.iter().position(|pending| {
+            pending.node == source
+                && pending.params.node_id == page.node_id
+                && pending.params.measurement == page.measurement
+                && pending.params.offset == page.offset
+        }) {
+            Some(index) => index,
+            None => return,
+        };
+        
+        match page.offset.checked_add(received as u16) {
+            Some(offset) if page.more && received > 0 => {
+                let pending = &mut self.aggregates[index];
+                pending.params.offset = offset;
+                pending.requested = true;
+            },
+            _ => {
+                self.aggregates.remove(index);
+            },
+        }
+    }
+    
+    /// 分页查询和聚合查询需要（重）发的命令包，每次循环调用
+    pub fn query_resumes(&mut self, now: u64) -> Vec<(NodeId, Vec<u8>)> {
+        let mut packets = self.queries.resumes(now);
+        for pending in self.aggregates.iter_mut().filter(|pending| pending.requested) {
+            packets.push((pending.node, encode_command(CommandType::Aggregate, &pending.params.to_bytes())));
+            pending.requested = false;
+        }
+        packets
+    }
+    
+    /// 将命令主题和负载转换为目标节点和命令包负载，查询命令附上分页参数并开始跟踪结果
+    pub fn command(&mut self, topic: &str, payload: &[u8]) -> Result<(NodeId, Vec<u8>), CommandError> {
+        let rest = topic.strip_prefix(&self.prefix).and_then(|rest| rest.strip_prefix('/'))
+            .ok_or(CommandError::InvalidTopic)?;
+        let mut parts = rest.split('/');
+        let (node, name) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(node), Some("command"), Some(name), None) => (node, name),
+            _ => return Err(CommandError::InvalidTopic),
+        };
+        let node = parse_node(node).ok_or(CommandError::InvalidTopic)?;
+        
+        let command = match name {
+            "query" => CommandType::Query,
+            "configure" => CommandType::Configure,
+            "clear" => CommandType::Clear,
+            "reboot" => CommandType::Reboot,
+            "stats" => CommandType::Stats,
+            "read_log" => CommandType::ReadLog,
+            "latest" => CommandType::Latest,
+            "aggregate" => CommandType::Aggregate,
+            _ => return Err(CommandError::UnknownCommand),
+        };
+        
+        let mut parameters = Vec::new();
+        if command == CommandType::Configure {
+            let text = String::from_utf8_lossy(payload);
+            for setting in text.split(|c: char| c.is_whitespace() || c == ',').filter(|s| !s.is_empty()) {
+                encode_setting(setting, &mut parameters)?;
+            }
+        } else if command == CommandType::ReadLog {
+            encode_log_range(&String::from_utf8_lossy(payload), &mut parameters)?;
+        } else if command == CommandType::Query {
+            encode_query(&String::from_utf8_lossy(payload), &mut parameters)?;
+            let page = self.queries.start(node, &parameters);
+            parameters.extend_from_slice(&page.to_bytes());
+        } else if command == CommandType::Latest {
+            encode_latest(&String::from_utf8_lossy(payload), &mut parameters)?;
+        } else if command == CommandType::Aggregate {
+            let params = encode_aggregate(&String::from_utf8_lossy(payload), &mut parameters)?;
+            if self.aggregates.len() >= MAX_PENDING_QUERIES {
+                self.aggregates.remove(0);
+            }
+            self.aggregates.push(PendingAggregate { node, params, requested: false });
+        }
+        
+        Ok((node, encode_command(command, &parameters)))
+    }
+}
+
+/// 按命令负载格式编码命令类型和参数
+fn encode_command(command: CommandType, parameters: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0u8; 2 + parameters.len()];
+    let len = payload::encode(&Command { command, parameters }, &mut packet);
+    packet.truncate(len);
+    packet
+}
+
+/// 将`起始时间 结束时间 [every=分钟|max=条数]`编码为查询参数，为空时查询全部记录
+fn encode_query(text: &str, out: &mut Vec<u8>) -> Result<(), CommandError> {
+    let invalid = || CommandError::InvalidParameter(text.to_string());
+    let mut parts = text.split_whitespace();
+    
+    let (start_time, end_time) = match (parts.next(), parts.next()) {
+        (None, _) => return Ok(()),
+        (Some(start), Some(end)) => (
+            start.parse::<u64>().map_err(|_| invalid())?,
+            end.parse::<u64>().map_err(|_| invalid())?,
+        ),
+        _ => return Err(invalid()),
+    };
+    let downsample = match parts.next().map(|setting| setting.split_once('=')) {
+        None => Downsample::None,
+        Some(Some(("every", minutes))) => Downsample::PerMinutes(minutes.parse().map_err(|_| invalid())?),
+        Some(Some(("max", max))) => Downsample::MaxRecords(max.parse().map_err(|_| invalid())?),
+        _ => return Err(invalid()),
+    };
+    if parts.next().is_some() || matches!(downsample, Downsample::PerMinutes(0) | Downsample::MaxRecords(0)) {
+        return Err(invalid());
+    }
+    
+    out.extend_from_slice(&QueryParams { start_time, end_time, downsample }.to_bytes());
+    Ok(())
+}
+
+/// 将可选的节点ID编码为最新值查询参数，为空时查询所有节点
+fn encode_latest(text: &str, out: &mut Vec<u8>) -> Result<(), CommandError> {
+    let text = text.trim();
+    if !text.is_empty() {
+        let node = parse_node(text).ok_or_else(|| CommandError::InvalidParameter(text.to_string()))?;
+        out.extend_from_slice(&node.0);
+    }
+    Ok(())
+}
+
+/// 将`来源节点 测量值 起始时间 结束时间 every=分钟|points=窗口数`编码为聚合查询参数，返回编码的参数
+fn encode_aggregate(text: &str, out: &mut Vec<u8>) -> Result<AggregateParams, CommandError> {
+    let invalid = || CommandError::InvalidParameter(text.to_string());
+    let mut parts = text.split_whitespace();
+    
+    let node_id = parts.next().and_then(parse_node).ok_or_else(invalid)?;
+    let measurement = match parts.next() {
+        Some("temperature") => Measurement::Temperature,
+        Some("humidity") => Measurement::Humidity,
+        Some("pressure") => Measurement::Pressure,
+        _ => return Err(invalid()),
+    };
+    let start_time = parts.next().and_then(|value| value.parse::<u64>().ok()).ok_or_else(invalid)?;
+    let end_time = parts.next().and_then(|value| value.parse::<u64>().ok()).ok_or_else(invalid)?;
+    let window = match parts.next().and_then(|setting| setting.split_once('=')) {
+        Some(("every", minutes)) => AggregateWindow::Minutes(minutes.parse().map_err(|_| invalid())?),
+        Some(("points", points)) => AggregateWindow::Points(points.parse().map_err(|_| invalid())?),
+        _ => return Err(invalid()),
+    };
+    if parts.next().is_some() || matches!(window, AggregateWindow::Minutes(0) | AggregateWindow::Points(0)) {
+        return Err(invalid());
+    }
+    
+    let params = AggregateParams { node_id, measurement, start_time, end_time, window, offset: 0 };
+    out.extend_from_slice(&params.to_bytes());
+    Ok(params)
+}
+
+/// 将`起始序号 [样本数]`编码为 起始序号(4) 样本数(1)
+fn encode_log_range(text: &str, out: &mut Vec<u8>) -> Result<(), CommandError> {
+    let invalid = || CommandError::InvalidParameter(text.to_string());
+    let mut parts = text.split_whitespace();
+    
+    let start_seq = match parts.next() {
+        Some(value) => value.parse::<u32>().map_err(|_| invalid())?,
+        None => 0,
+    };
+    let count = match parts.next() {
+        Some(value) => value.parse::<u8>().map_err(|_| invalid())?,
+        None => MAX_LOG_RESPONSE_SAMPLES as u8,
+    };
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+    
+    out.extend_from_slice(&start_seq.to_be_bytes());
+    out.push(count);
+    Ok(())
+}
+
+/// 将一个`参数=值`编码为 参数ID(1) 值
+fn encode_setting(setting: &str, out: &mut Vec<u8>) -> Result<(), CommandError> {
+    let invalid = || CommandError::InvalidParameter(setting.to_string());
+    let (name, value) = setting.split_once('=').ok_or_else(invalid)?;
+    
+    let (param, bytes) = match name {
+        "sample_interval" => (ConfigParam::SampleInterval, value.parse::<u32>().map_err(|_| invalid())?.to_be_bytes().to_vec()),
+        "channel" => (ConfigParam::Channel, vec![value.parse::<u8>().map_err(|_| invalid())?]),
+        "server" => (ConfigParam::Server, parse_node(value).ok_or_else(invalid)?.0.to_vec()),
+        "node_id" => (ConfigParam::NodeIdentity, parse_node(value).ok_or_else(invalid)?.0.to_vec()),
+        "qos" => (ConfigParam::Qos, parse_hex(value).ok_or_else(invalid)?),
+        "network_key" => (ConfigParam::NetworkKey, parse_hex(value).ok_or_else(invalid)?),
+        _ => return Err(invalid()),
+    };
+    if bytes.len() != param.value_len() {
+        return Err(invalid());
+    }
+    
+    out.push(param as u8);
+    out.extend_from_slice(&bytes);
+    Ok(())
+}
\ No newline at end of file
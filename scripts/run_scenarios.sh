@@ -0,0 +1,12 @@
+#!/bin/bash
+set -e
+
+# 逐个运行模拟场景，任一场景的期望检查失败时退出；cargo test -p simulator也会按虚拟时间运行这些场景
+cargo build --release --package simulator
+
+for scenario in simulator/scenarios/*.txt; do
+    echo "运行场景 $scenario..."
+    ./target/release/simulator "$scenario"
+done
+
+echo "所有场景通过！"
@@ -1,7 +1,16 @@
 use common::protocol::{DataPacket, NodeId};
+use common::protocol::command::{
+    parse_paged_query, AggregateParams, CommandStatus, QueryPage, QueryParams, LATEST_RECORD_LEN, MAX_LATEST_RECORDS,
+};
+use common::protocol::payload;
 use common::hal::Hardware;
+use common::hal::nvs::{RebootBreadcrumb, RebootReason};
+use common::security::send_secure;
+use common::{info, warn};
 use crate::api::{Command, CommandHandler, CommandType};
-use crate::storage::Storage;
+use crate::api::paging::{answer_query, QuerySnapshots};
+use crate::api::stats::ServerStats;
+use crate::storage::{aggregate, Storage};
 
 /// 命令处理器
 pub struct CommandProcessor {
@@ -13,6 +22,10 @@ pub struct CommandProcessor {
     write_position: usize,
     /// 读取位置
     read_position: usize,
+    /// 是否已请求重启
+    reboot_requested: bool,
+    /// 分页查询的结果快照，续传时按第一次查询的结果切片
+    snapshots: QuerySnapshots,
 }
 
 impl CommandProcessor {
@@ -23,9 +36,16 @@ impl CommandProcessor {
             commands: [None; 16],
             write_position: 0,
             read_position: 0,
+            reboot_requested: false,
+            snapshots: QuerySnapshots::new(),
         }
     }
     
+    /// 是否已执行重启命令（模拟器中主循环据此退出）
+    pub fn reboot_requested(&self) -> bool {
+        self.reboot_requested
+    }
+    
     /// 检查队列是否为空
     fn is_empty(&self) -> bool {
         self.write_position == self.read_position
@@ -36,49 +56,102 @@ impl CommandProcessor {
         (self.write_position + 1) % self.commands.len() == self.read_position
     }
     
-    /// 将命令数据解析为命令结构
-    fn parse_command(&self, source: NodeId, data: &[u8]) -> Option<Command> {
-        if data.is_empty() {
-            return None;
-        }
+    /// 查询请求方的记录并分页应答，续传的请求使用第一次查询时的结果
+    pub fn answer_query<H: Hardware, S: Storage>(
+        &mut self,
+        hardware: &mut H,
+        storage: &S,
+        requester: NodeId,
+        params: &QueryParams,
+        page: QueryPage
+    ) {
+        answer_query(hardware, &mut self.snapshots, requester, page, || storage.query(requester, params));
+    }
+    
+    /// 执行查询命令
+    fn execute_query<H: Hardware, S: Storage>(
+        &mut self,
+        hardware: &mut H,
+        storage: &mut S,
+        command: &Command
+    ) {
+        info!("执行查询命令");
         
-        // 获取命令类型
-        let command_type = match data[0] {
-            0x01 => CommandType::Query,
-            0x02 => CommandType::Configure,
-            0x03 => CommandType::Clear,
-            0x04 => CommandType::Reboot,
-            _ => return None, // 未知命令
+        // 参数为空时返回全部记录，否则按时间范围查询并降采样
+        let (params, page) = match parse_paged_query(&command.parameters) {
+            Some(query) => query,
+            None => {
+                let response = [CommandStatus::InvalidParameter as u8];
+                self.send_response(hardware, command.source, CommandType::Query, &response);
+                return;
+            }
         };
+        // 结果可能超出一个数据包，从请求的偏移起分片发送
+        self.answer_query(hardware, storage, command.source, &params, page);
+    }
+    
+    /// 执行最新值查询命令，只返回每个节点最新的一条记录
+    fn execute_latest<H: Hardware, S: Storage>(
+        &self,
+        hardware: &mut H,
+        storage: &mut S,
+        command: &Command
+    ) {
+        info!("执行最新值查询命令");
         
-        // 获取命令参数
-        let parameters = if data.len() > 1 {
-            data[1..].to_vec()
-        } else {
-            Vec::new()
+        let node_id = match command.parameters.len() {
+            0 => None,
+            6 => {
+                let mut id = [0u8; 6];
+                id.copy_from_slice(&command.parameters);
+                Some(NodeId::new(id))
+            },
+            _ => {
+                let response = [CommandStatus::InvalidParameter as u8];
+                self.send_response(hardware, command.source, CommandType::Latest, &response);
+                return;
+            }
         };
         
-        Some(Command {
-            source,
-            command_type,
-            parameters,
-        })
+        // 节点太多时只返回一个数据包能容纳的记录
+        let records = storage.latest(node_id);
+        let len = records.len().min(MAX_LATEST_RECORDS * LATEST_RECORD_LEN);
+        let mut response = Vec::with_capacity(len + 1);
+        response.push(CommandStatus::Ok as u8);
+        response.extend_from_slice(&records[..len]);
+        
+        self.send_response(hardware, command.source, CommandType::Latest, &response);
     }
     
-    /// 执行查询命令
-    fn execute_query<H: Hardware, S: Storage>(
+    /// 执行聚合查询命令，按时间窗口返回节点测量值的最小、最大和平均值
+    fn execute_aggregate<H: Hardware, S: Storage>(
         &self,
         hardware: &mut H,
         storage: &mut S,
         command: &Command
     ) {
-        println!("执行查询命令");
+        info!("执行聚合查询命令");
         
-        // 获取节点数据
-        let data = storage.get_data_for_node(command.source);
+        let params = match AggregateParams::from_bytes(&command.parameters) {
+            Some(params) => params,
+            None => {
+                let response = [CommandStatus::InvalidParameter as u8];
+                self.send_response(hardware, command.source, CommandType::Aggregate, &response);
+                return;
+            }
+        };
         
-        // 发送响应
-        self.send_response(hardware, command.source, CommandType::Query, &data);
+        // 从请求的偏移起返回一个数据包能容纳的窗口，还有剩余时置后续标志，请求方带新的偏移再次查询
+        let buckets = storage.aggregate(&params);
+        let (header, page) = aggregate::page(&params, &buckets);
+        let mut response = Vec::new();
+        response.push(CommandStatus::Ok as u8);
+        response.extend_from_slice(&header.to_bytes());
+        for bucket in page {
+            response.extend_from_slice(&bucket.to_bytes());
+        }
+        
+        self.send_response(hardware, command.source, CommandType::Aggregate, &response);
     }
     
     /// 执行配置命令
@@ -88,7 +161,7 @@ impl CommandProcessor {
         storage: &mut S,
         command: &Command
     ) {
-        println!("执行配置命令");
+        info!("执行配置命令");
         
         // 实际中应该根据参数配置采集间隔等参数
         // 这里简单地发送确认响应
@@ -105,7 +178,7 @@ impl CommandProcessor {
         storage: &mut S,
         command: &Command
     ) {
-        println!("执行清空数据命令");
+        info!("执行清空数据命令");
         
         // 清空指定节点的数据
         storage.clear_data_for_node(command.source);
@@ -119,19 +192,52 @@ impl CommandProcessor {
     
     /// 执行重启命令
     fn execute_reboot<H: Hardware, S: Storage>(
-        &self,
+        &mut self,
         hardware: &mut H,
         storage: &mut S,
         command: &Command
     ) {
-        println!("执行重启命令（模拟）");
+        info!("执行重启命令，来自 {}", command.source);
         
-        // 此处实际实现中应该真正重启设备
-        // 在模拟中，只是发送确认响应
+        // 先发送确认响应，确保请求方知道命令已被接受
         let response = [0x01]; // 简单的确认码
+        self.send_response(hardware, command.source, CommandType::Reboot, &response);
+        
+        // 记录重启原因，重启后可以确认是谁触发的
+        let breadcrumb = RebootBreadcrumb {
+            reason: RebootReason::Command,
+            requested_by: command.source,
+            timestamp: hardware.get_timestamp_ms().unwrap_or(0),
+        };
+        if breadcrumb.store(hardware.get_nvs()).is_err() {
+            warn!("写入重启记录失败");
+        }
+        
+        // 复位设备
+        self.reboot_requested = true;
+        if hardware.system_reset().is_err() {
+            warn!("系统复位失败");
+            self.reboot_requested = false;
+        }
+    }
+    
+    /// 执行统计查询命令
+    fn execute_stats<H: Hardware, S: Storage>(
+        &self,
+        hardware: &mut H,
+        storage: &mut S,
+        stats: &ServerStats,
+        command: &Command
+    ) {
+        info!("执行统计查询命令");
+        
+        // 生成并序列化统计快照
+        let snapshot = stats.snapshot(hardware, storage);
+        let mut response = [0u8; 128];
+        let len = snapshot.serialize(&mut response);
         
         // 发送响应
-        self.send_response(hardware, command.source, CommandType::Reboot, &response);
+        self.send_response(hardware, command.source, CommandType::Stats, &response[..len]);
     }
     
     /// 发送响应
@@ -156,47 +262,56 @@ impl CommandProcessor {
         );
         
         // 发送数据包
-        let radio = hardware.get_radio();
-        if let Err(e) = radio.send_data(&packet) {
-            println!("发送响应失败: {:?}", e);
+        if let Err(e) = send_secure(hardware, &packet) {
+            warn!("发送响应失败: {:?}", e);
         } else {
-            println!("响应已发送给 {:?}", destination);
+            info!("响应已发送给 {}", destination);
         }
     }
 }
 
 impl CommandHandler for CommandProcessor {
-    fn add_command(&mut self, source: NodeId, data: &[u8]) {
+    fn add_command(&mut self, source: NodeId, command: &payload::Command<'_>) {
         if self.is_full() {
-            println!("命令队列已满，忽略新命令");
+            warn!("命令队列已满，忽略新命令");
             return;
         }
         
-        if let Some(command) = self.parse_command(source, data) {
-            self.commands[self.write_position] = Some(command);
-            self.write_position = (self.write_position + 1) % self.commands.len();
-            println!("添加新命令到队列，类型: {:?}", command.command_type);
-        }
+        let command = Command {
+            source,
+            command_type: command.command,
+            parameters: command.parameters.to_vec(),
+        };
+        info!("添加新命令到队列，类型: {:?}", command.command_type);
+        self.commands[self.write_position] = Some(command);
+        self.write_position = (self.write_position + 1) % self.commands.len();
     }
     
-    fn process_commands<H, S>(&mut self, hardware: &mut H, storage: &mut S)
+    fn process_commands<H, S>(&mut self, hardware: &mut H, storage: &mut S, stats: &ServerStats)
     where
         H: Hardware,
         S: Storage,
     {
-        while !self.is_empty() {
-            if let Some(command) = &self.commands[self.read_position] {
+        while !self.is_empty() && !self.reboot_requested {
+            if let Some(command) = self.commands[self.read_position].take() {
                 match command.command_type {
-                    CommandType::Query => self.execute_query(hardware, storage, command),
-                    CommandType::Configure => self.execute_configure(hardware, storage, command),
-                    CommandType::Clear => self.execute_clear(hardware, storage, command),
-                    CommandType::Reboot => self.execute_reboot(hardware, storage, command),
+                    CommandType::Query => self.execute_query(hardware, storage, &command),
+                    CommandType::Configure => self.execute_configure(hardware, storage, &command),
+                    CommandType::Clear => self.execute_clear(hardware, storage, &command),
+                    CommandType::Reboot => self.execute_reboot(hardware, storage, &command),
+                    CommandType::Stats => self.execute_stats(hardware, storage, stats, &command),
+                    CommandType::Latest => self.execute_latest(hardware, storage, &command),
+                    CommandType::Aggregate => self.execute_aggregate(hardware, storage, &command),
+                    // 本地样本日志只在客户端上
+                    CommandType::ReadLog => {
+                        let response = [CommandStatus::Unsupported as u8];
+                        self.send_response(hardware, command.source, CommandType::ReadLog, &response);
+                    },
                 }
             }
             
             // 移除已处理的命令
-            self.commands[self.read_position] = None;
             self.read_position = (self.read_position + 1) % self.commands.len();
         }
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
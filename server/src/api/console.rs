@@ -0,0 +1,571 @@
+use core::fmt::{self, Write};
+use common::config::{NodeConfig, ScoreWeights, MAX_ADMINS};
+use common::hal::Hardware;
+use common::log;
+use common::mgmt::MgmtRequester;
+use common::monitor::TrafficMonitor;
+use common::protocol::{NodeId, PacketType};
+use common::protocol::echo::{send_echo, Echo, Trace};
+use common::protocol::stats::{send_stats, StatsMessage};
+use common::protocol::route_table::{send_route_table, RouteTableMessage, MAX_ROUTE_TABLE_ENTRIES};
+use common::protocol::mgmt::{MgmtAttribute, MgmtOp, MgmtStatus};
+use crate::api::stats::ServerStats;
+use crate::ota::distributor::OtaDistributor;
+use crate::storage::Storage;
+
+/// 控制台单行最大长度
+const MAX_LINE_LEN: usize = 64;
+
+/// 串口控制台输出适配器
+struct ConsoleWriter<'a, H: Hardware> {
+    hardware: &'a mut H,
+}
+
+impl<'a, H: Hardware> Write for ConsoleWriter<'a, H> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.hardware.console_write(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+/// 串口命令行，按行读取并执行本地命令
+pub struct SerialConsole {
+    /// 行缓冲区
+    line: [u8; MAX_LINE_LEN],
+    /// 当前行长度
+    len: usize,
+}
+
+impl SerialConsole {
+    /// 创建新的串口控制台
+    pub fn new() -> Self {
+        Self {
+            line: [0; MAX_LINE_LEN],
+            len: 0,
+        }
+    }
+    
+    /// 读取串口输入，遇到换行时执行命令
+    pub fn poll<H: Hardware, S: Storage>(
+        &mut self,
+        hardware: &mut H,
+        storage: &mut S,
+        stats: &ServerStats,
+        ota: &mut OtaDistributor,
+        mgmt: &mut MgmtRequester,
+        config: &mut NodeConfig
+    ) {
+        self.read_lines(hardware, |console, hardware, line| {
+            console.execute(hardware, storage, stats, ota, mgmt, config, line);
+        });
+    }
+    
+    /// 监听模式下读取串口输入，只支持查看流量统计和修改本节点配置
+    pub fn poll_monitor<H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        monitor: &mut TrafficMonitor,
+        config: &mut NodeConfig
+    ) {
+        self.read_lines(hardware, |console, hardware, line| {
+            let mut parts = line.split_whitespace();
+            let now = hardware.get_timestamp_ms().unwrap_or(0);
+            match (parts.next(), parts.next()) {
+                (None, _) => {},
+                (Some("monitor"), None) => {
+                    let mut out = ConsoleWriter { hardware };
+                    let _ = monitor.report(&mut out, now);
+                },
+                (Some("monitor"), Some("clear")) => {
+                    monitor.clear(now);
+                    let mut out = ConsoleWriter { hardware };
+                    let _ = writeln!(out, "已清空统计");
+                },
+                (Some("config"), first) => console.execute_config(hardware, config, first.into_iter().chain(parts)),
+                _ => {
+                    let mut out = ConsoleWriter { hardware };
+                    let _ = writeln!(out, "监听模式可用命令: monitor [clear], config；退出: config set monitor 0");
+                },
+            }
+        });
+    }
+    
+    /// 读取串口输入，每读到完整的一行调用一次`execute`
+    fn read_lines<H: Hardware>(&mut self, hardware: &mut H, mut execute: impl FnMut(&mut Self, &mut H, &str)) {
+        let mut input = [0u8; 32];
+        let count = hardware.console_read(&mut input).unwrap_or(0);
+        
+        for &byte in input[..count].iter() {
+            match byte {
+                b'\r' | b'\n' => {
+                    if self.len > 0 {
+                        let mut line = [0u8; MAX_LINE_LEN];
+                        let len = self.len;
+                        line[..len].copy_from_slice(&self.line[..len]);
+                        self.len = 0;
+                        
+                        if let Ok(text) = core::str::from_utf8(&line[..len]) {
+                            execute(self, hardware, text.trim());
+                        }
+                    }
+                },
+                _ => {
+                    if self.len < MAX_LINE_LEN {
+                        self.line[self.len] = byte;
+                        self.len += 1;
+                    }
+                },
+            }
+        }
+    }
+    
+    /// 执行一行命令
+    fn execute<H: Hardware, S: Storage>(
+        &mut self,
+        hardware: &mut H,
+        storage: &mut S,
+        stats: &ServerStats,
+        ota: &mut OtaDistributor,
+        mgmt: &mut MgmtRequester,
+        config: &mut NodeConfig,
+        line: &str
+    ) {
+        let mut parts = line.split_whitespace();
+        let command = match parts.next() {
+            Some(command) => command,
+            None => return,
+        };
+        
+        match command {
+            "stats" => match parts.next() {
+                Some(target) => self.execute_remote_stats(hardware, target, parts),
+                None => {
+                    let snapshot = stats.snapshot(hardware, storage);
+                    let mut out = ConsoleWriter { hardware };
+                    let _ = write!(out, "{}", snapshot);
+                },
+            },
+            "routes" => self.execute_remote_routes(hardware, parts),
+            "ota" => self.execute_ota(hardware, ota, parts),
+            "mgmt" => self.execute_mgmt(hardware, mgmt, config, parts),
+            "config" => self.execute_config(hardware, config, parts),
+            "ping" | "trace" => self.execute_echo(hardware, command == "trace", parts),
+            "help" => {
+                let mut out = ConsoleWriter { hardware };
+                let _ = writeln!(out, "可用命令: stats, routes, ota, mgmt, config, ping, trace, help；进入监听模式: config set monitor 1");
+            },
+            _ => {
+                let mut out = ConsoleWriter { hardware };
+                let _ = writeln!(out, "未知命令: {}", command);
+            },
+        }
+    }
+    
+    /// 固件更新命令：
+    /// ota load <版本> <长度>          使用暂存区中的镜像作为分发源
+    /// ota start <节点ID> [中继ID]     开始更新节点，节点ID为冒号分隔或连续的12位十六进制
+    /// ota status                      查看各节点的更新状态
+    fn execute_ota<'a, H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        ota: &mut OtaDistributor,
+        mut args: impl Iterator<Item = &'a str>
+    ) {
+        let now = hardware.get_timestamp_ms().unwrap_or(0);
+        
+        match args.next() {
+            Some("load") => {
+                let version = args.next().and_then(|v| v.parse::<u32>().ok());
+                let size = args.next().and_then(|v| v.parse::<u32>().ok());
+                let loaded = match (version, size) {
+                    (Some(version), Some(size)) => ota.load_image(hardware, version, size),
+                    _ => false,
+                };
+                
+                let image = ota.image();
+                let mut out = ConsoleWriter { hardware };
+                match image {
+                    Some(image) if loaded => {
+                        let _ = writeln!(out, "镜像版本 {}，{} 字节，CRC {:08X}", image.version, image.size, image.crc);
+                    },
+                    _ => {
+                        let _ = writeln!(out, "用法: ota load <版本> <长度>，镜像需已写入暂存区");
+                    },
+                }
+            },
+            Some("start") => {
+                let node = args.next().and_then(|text| text.parse::<NodeId>().ok());
+                let started = match node {
+                    Some(node) => {
+                        let via = args.next().and_then(|text| text.parse::<NodeId>().ok()).unwrap_or(node);
+                        ota.start(hardware, node, via, now)
+                    },
+                    None => false,
+                };
+                
+                let mut out = ConsoleWriter { hardware };
+                match node.filter(|_| started) {
+                    Some(node) => {
+                        let _ = writeln!(out, "已通知节点 {}", node);
+                    },
+                    None => {
+                        let _ = writeln!(out, "用法: ota start <节点ID> [中继ID]，需先加载镜像");
+                    },
+                }
+            },
+            Some("status") => {
+                let mut out = ConsoleWriter { hardware };
+                for target in ota.targets() {
+                    let _ = writeln!(out, "{}: {:?}", target.node, target.state);
+                }
+            },
+            _ => {
+                let mut out = ConsoleWriter { hardware };
+                let _ = writeln!(out, "用法: ota load|start|status");
+            },
+        }
+    }
+    
+    /// 本节点配置命令：
+    /// config                  查看当前配置
+    /// config set <属性> <值>  修改并保存配置，属性同 mgmt
+    fn execute_config<'a, H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        config: &mut NodeConfig,
+        mut args: impl Iterator<Item = &'a str>
+    ) {
+        match args.next() {
+            None => {
+                let mut out = ConsoleWriter { hardware };
+                let _ = writeln!(out, "角色: {:?}", config.role);
+                let _ = writeln!(out, "信标间隔: {} ms", config.beacon_interval_ms);
+                let _ = writeln!(out, "信道: {}，发射功率: {} dBm", config.channel, config.tx_power);
+                let _ = writeln!(out, "选举间隔: {} ms", config.election_interval_ms);
+                let _ = writeln!(out, "路由过期: {} ms，服务过期: {} ms", config.route_expiry_ms, config.service_expiry_ms);
+                let _ = writeln!(out, "邻居保活: {} ms", config.keepalive_ms);
+                let _ = writeln!(out, "跳数限制: {}", config.default_ttl);
+                let _ = writeln!(out, "电量路由: 权重 {}，告急阈值 {}%", config.battery_weight, config.critical_battery);
+                match config.duty_cycle_permille {
+                    0 => {
+                        let _ = writeln!(out, "占空比上限: 不限制");
+                    },
+                    permille => {
+                        let _ = writeln!(out, "占空比上限: {}‰", permille);
+                    },
+                }
+                let weights = config.score_weights;
+                let _ = writeln!(out, "评分权重: 带宽 {} 延迟 {} 可靠性 {} 负载 {} 电量 {} 每跳 -{}",
+                                 weights.bandwidth, weights.latency, weights.reliability,
+                                 weights.load, weights.battery, weights.hop_penalty);
+                let _ = writeln!(out, "监听模式: {}", if config.monitor { "开" } else { "关" });
+                let _ = writeln!(out, "日志级别: {:?}", log::level());
+                let _ = write!(out, "管理员:");
+                for admin in config.admins.iter().flatten() {
+                    let _ = write!(out, " {}", admin);
+                }
+                let _ = writeln!(out);
+            },
+            Some("set") => {
+                let attribute = args.next().and_then(parse_attribute);
+                let mut value = [0u8; MAX_ADMINS * 6];
+                let result = match (attribute, args.next()) {
+                    (Some(attribute), Some(text)) => match encode_value(attribute, text, &mut value) {
+                        Some(len) => Some(config.set(hardware, attribute, &value[..len])),
+                        None => None,
+                    },
+                    _ => None,
+                };
+                
+                let mut out = ConsoleWriter { hardware };
+                match result {
+                    Some(Ok(())) => {
+                        let _ = writeln!(out, "已保存");
+                    },
+                    Some(Err(status)) => {
+                        let _ = writeln!(out, "设置失败: {:?}", status);
+                    },
+                    None => {
+                        let _ = writeln!(out, "用法: config set <beacon|power|channel|log|election|expiry|service|keepalive|ttl|admins|duty|weights|monitor|battery> <值>");
+                    },
+                }
+            },
+            _ => {
+                let mut out = ConsoleWriter { hardware };
+                let _ = writeln!(out, "用法: config [set <属性> <值>]");
+            },
+        }
+    }
+    
+    /// 回显命令，应答到达后输出到日志：
+    /// ping <节点ID> [中继ID]   测量到节点的往返时延和跳数
+    /// trace <节点ID> [中继ID]  同时记录沿途各节点和收到请求时的信号强度
+    fn execute_echo<'a, H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        trace: bool,
+        mut args: impl Iterator<Item = &'a str>
+    ) {
+        let target = match args.next().and_then(|text| text.parse::<NodeId>().ok()) {
+            Some(target) => target,
+            None => {
+                let mut out = ConsoleWriter { hardware };
+                let _ = writeln!(out, "用法: ping|trace <节点ID> [中继ID]");
+                return;
+            },
+        };
+        let via = args.next().and_then(|text| text.parse::<NodeId>().ok()).unwrap_or(target);
+        
+        let echo = Echo {
+            origin: hardware.get_node_id(),
+            target,
+            sent_at: hardware.get_timestamp_ms().unwrap_or(0),
+            hop_count: 0,
+            trace: trace.then(Trace::new),
+        };
+        let result = send_echo(hardware, via, PacketType::EchoRequest, 0, &echo);
+        let mut out = ConsoleWriter { hardware };
+        match result {
+            Ok(()) => {
+                let _ = writeln!(out, "已向 {} 发送回显请求", target);
+            },
+            Err(e) => {
+                let _ = writeln!(out, "发送回显请求失败: {:?}", e);
+            },
+        }
+    }
+    
+    /// 远程读取节点的计数器，应答到达后输出到日志：
+    /// stats <节点ID> [中继ID]
+    fn execute_remote_stats<'a, H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        target: &str,
+        mut args: impl Iterator<Item = &'a str>
+    ) {
+        let target = match target.parse::<NodeId>() {
+            Ok(target) => target,
+            Err(_) => {
+                let mut out = ConsoleWriter { hardware };
+                let _ = writeln!(out, "用法: stats [节点ID] [中继ID]");
+                return;
+            },
+        };
+        let via = args.next().and_then(|text| text.parse::<NodeId>().ok()).unwrap_or(target);
+        
+        let request = StatsMessage::Request { origin: hardware.get_node_id(), target, first: 0 };
+        let result = send_stats(hardware, via, 0, &request);
+        let mut out = ConsoleWriter { hardware };
+        match result {
+            Ok(()) => {
+                let _ = writeln!(out, "已向 {} 发送计数器查询", target);
+            },
+            Err(e) => {
+                let _ = writeln!(out, "发送计数器查询失败: {:?}", e);
+            },
+        }
+    }
+    
+    /// 远程读取转发节点的路由表，应答到达后输出到日志：
+    /// routes <节点ID> [中继ID] [条目数]
+    fn execute_remote_routes<'a, H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        mut args: impl Iterator<Item = &'a str>
+    ) {
+        let target = match args.next().and_then(|text| text.parse::<NodeId>().ok()) {
+            Some(target) => target,
+            None => {
+                let mut out = ConsoleWriter { hardware };
+                let _ = writeln!(out, "用法: routes <节点ID> [中继ID] [条目数]");
+                return;
+            },
+        };
+        let via = args.next().and_then(|text| text.parse::<NodeId>().ok()).unwrap_or(target);
+        let max_entries = args.next().and_then(|text| text.parse::<usize>().ok())
+            .unwrap_or(MAX_ROUTE_TABLE_ENTRIES)
+            .min(MAX_ROUTE_TABLE_ENTRIES);
+        
+        let request = RouteTableMessage::Request { origin: hardware.get_node_id(), target, max_entries: max_entries as u8 };
+        let result = send_route_table(hardware, via, 0, &request);
+        let mut out = ConsoleWriter { hardware };
+        match result {
+            Ok(()) => {
+                let _ = writeln!(out, "已向 {} 发送路由表查询", target);
+            },
+            Err(e) => {
+                let _ = writeln!(out, "发送路由表查询失败: {:?}", e);
+            },
+        }
+    }
+    
+    /// 远程管理命令，应答到达后输出到日志：
+    /// mgmt get <节点ID> <属性> [起始序号] [中继ID]
+    /// mgmt set <节点ID> <属性> <值> [中继ID]
+    /// mgmt set * <属性> <值>   全网设置定时参数（beacon、election、expiry、service、keepalive），本节点同时生效
+    /// 属性为 beacon、power、channel、routes、log、election、expiry、service、keepalive、ttl、admins、role、duty、battery、policy；
+    /// admins 的值为逗号分隔的节点ID，duty 的值为千分比，battery 的值为 权重,告急阈值，policy 的值为 服务类型,策略；
+    /// 起始序号用于分页读取 routes
+    fn execute_mgmt<'a, H: Hardware>(
+        &mut self,
+        hardware: &mut H,
+        mgmt: &mut MgmtRequester,
+        config: &mut NodeConfig,
+        args: impl Iterator<Item = &'a str>
+    ) {
+        let mut args = args.peekable();
+        let op = match args.next() {
+            Some("get") => Some(MgmtOp::Get),
+            Some("set") => Some(MgmtOp::Set),
+            _ => None,
+        };
+        let target = args.next().and_then(|text| match text {
+            "*" => Some(NodeId::BROADCAST),
+            _ => text.parse::<NodeId>().ok(),
+        });
+        let attribute = args.next().and_then(parse_attribute);
+        
+        let (op, target, attribute) = match (op, target, attribute) {
+            (Some(op), Some(target), Some(attribute)) => (op, target, attribute),
+            _ => {
+                let mut out = ConsoleWriter { hardware };
+                let _ = writeln!(out, "用法: mgmt get|set <节点ID|*> <beacon|power|channel|routes|log|election|expiry|service|keepalive|ttl|admins|role|duty|battery|policy> [值] [中继ID]");
+                return;
+            },
+        };
+        
+        // 设置时需要值；读取路由表时可带起始序号
+        let mut value = [0u8; MAX_ADMINS * 6];
+        let value_len = match op {
+            MgmtOp::Set => match args.next().and_then(|text| encode_value(attribute, text, &mut value)) {
+                Some(len) => len,
+                None => {
+                    let mut out = ConsoleWriter { hardware };
+                    let _ = writeln!(out, "缺少或无效的值");
+                    return;
+                },
+            },
+            // 节点ID至少12个字符，较短的参数是路由表的起始序号
+            _ => match args.peek().filter(|v| v.len() < 12).and_then(|v| v.parse::<u8>().ok()) {
+                Some(start) if attribute == MgmtAttribute::RoutingTable => {
+                    args.next();
+                    value[0] = start;
+                    1
+                },
+                _ => 0,
+            },
+        };
+        
+        // 全网设置先在本节点校验并生效，取值越界时不广播
+        if target.is_broadcast() {
+            let result = match (op, attribute.is_timer()) {
+                (MgmtOp::Set, true) => config.set(hardware, attribute, &value[..value_len]),
+                _ => Err(MgmtStatus::Unsupported),
+            };
+            let result = result.map(|_| mgmt.broadcast(hardware, attribute, &value[..value_len]));
+            let mut out = ConsoleWriter { hardware };
+            match result {
+                Ok(Ok(sequence)) => {
+                    let _ = writeln!(out, "已广播全网设置，序号 {}", sequence);
+                },
+                Ok(Err(e)) => {
+                    let _ = writeln!(out, "广播管理请求失败: {:?}", e);
+                },
+                Err(status) => {
+                    let _ = writeln!(out, "全网设置失败: {:?}", status);
+                },
+            }
+            return;
+        }
+        
+        let via = args.next().and_then(|text| text.parse::<NodeId>().ok()).unwrap_or(target);
+        let result = mgmt.request(hardware, target, via, op, attribute, &value[..value_len]);
+        let mut out = ConsoleWriter { hardware };
+        match result {
+            Ok(sequence) => {
+                let _ = writeln!(out, "已发送管理请求，序号 {}", sequence);
+            },
+            Err(e) => {
+                let _ = writeln!(out, "发送管理请求失败: {:?}", e);
+            },
+        }
+    }
+}
+
+/// 解析管理属性名
+fn parse_attribute(text: &str) -> Option<MgmtAttribute> {
+    match text {
+        "beacon" => Some(MgmtAttribute::BeaconInterval),
+        "power" => Some(MgmtAttribute::TxPower),
+        "channel" => Some(MgmtAttribute::Channel),
+        "routes" => Some(MgmtAttribute::RoutingTable),
+        "log" => Some(MgmtAttribute::LogLevel),
+        "election" => Some(MgmtAttribute::ElectionInterval),
+        "expiry" => Some(MgmtAttribute::RouteExpiry),
+        "service" => Some(MgmtAttribute::ServiceExpiry),
+        "keepalive" => Some(MgmtAttribute::KeepaliveInterval),
+        "ttl" => Some(MgmtAttribute::DefaultTtl),
+        "admins" => Some(MgmtAttribute::Admins),
+        "role" => Some(MgmtAttribute::Role),
+        "duty" => Some(MgmtAttribute::DutyCycle),
+        "weights" => Some(MgmtAttribute::ScoreWeights),
+        "monitor" => Some(MgmtAttribute::Monitor),
+        "proxy" => Some(MgmtAttribute::DirectoryProxy),
+        "battery" => Some(MgmtAttribute::BatteryRouting),
+        "capacity" => Some(MgmtAttribute::PathCapacity),
+        "policy" => Some(MgmtAttribute::SelectionPolicy),
+        _ => None,
+    }
+}
+
+/// 按属性的编码方式写入设置值，返回长度
+fn encode_value(attribute: MgmtAttribute, text: &str, out: &mut [u8]) -> Option<usize> {
+    match attribute {
+        MgmtAttribute::BeaconInterval
+        | MgmtAttribute::ElectionInterval
+        | MgmtAttribute::RouteExpiry
+        | MgmtAttribute::ServiceExpiry
+        | MgmtAttribute::KeepaliveInterval => {
+            let number = text.parse::<u32>().ok()?;
+            out[..4].copy_from_slice(&number.to_be_bytes());
+            Some(4)
+        },
+        MgmtAttribute::DutyCycle | MgmtAttribute::PathCapacity => {
+            let number = text.parse::<u16>().ok()?;
+            out[..2].copy_from_slice(&number.to_be_bytes());
+            Some(2)
+        },
+        // 逗号分隔的六个权重：带宽,延迟,可靠性,负载,电量,每跳扣分
+        MgmtAttribute::ScoreWeights => {
+            let mut len = 0;
+            for weight in text.split(',') {
+                *out.get_mut(len)? = weight.parse::<u8>().ok()?;
+                len += 1;
+            }
+            (len == ScoreWeights::SIZE).then_some(len)
+        },
+        // 权重,告急阈值；服务类型,策略
+        MgmtAttribute::BatteryRouting | MgmtAttribute::SelectionPolicy => {
+            let (first, second) = text.split_once(',')?;
+            out[0] = first.parse::<u8>().ok()?;
+            out[1] = second.parse::<u8>().ok()?;
+            Some(2)
+        },
+        // 逗号分隔的节点ID，"-" 表示清空
+        MgmtAttribute::Admins => {
+            if text == "-" {
+                return Some(0);
+            }
+            let mut len = 0;
+            for id in text.split(',') {
+                let node = id.parse::<NodeId>().ok()?;
+                out.get_mut(len..len + 6)?.copy_from_slice(&node.0);
+                len += 6;
+            }
+            Some(len)
+        },
+        _ => {
+            out[0] = text.parse::<u8>().ok()?;
+            Some(1)
+        },
+    }
+}
\ No newline at end of file
@@ -1,20 +1,13 @@
 pub mod cli;
+pub mod console;
+pub mod paging;
+pub mod stats;
 
 use common::protocol::NodeId;
+use common::protocol::payload;
+pub use common::protocol::command::CommandType;
 use crate::storage::Storage;
-
-/// 命令类型
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum CommandType {
-    /// 查询传感器数据
-    Query = 0x01,
-    /// 配置采集间隔
-    Configure = 0x02,
-    /// 清空数据
-    Clear = 0x03,
-    /// 重启设备
-    Reboot = 0x04,
-}
+use stats::ServerStats;
 
 /// 命令结构
 #[derive(Debug)]
@@ -30,10 +23,10 @@ pub struct Command {
 /// 命令处理接口
 pub trait CommandHandler {
     /// 添加命令到队列
-    fn add_command(&mut self, source: NodeId, data: &[u8]);
+    fn add_command(&mut self, source: NodeId, command: &payload::Command<'_>);
     
     /// 处理所有待处理的命令
-    fn process_commands<H, S>(&mut self, hardware: &mut H, storage: &mut S)
+    fn process_commands<H, S>(&mut self, hardware: &mut H, storage: &mut S, stats: &ServerStats)
     where
         H: common::hal::Hardware,
         S: Storage;
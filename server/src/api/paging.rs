@@ -0,0 +1,132 @@
+use common::hal::Hardware;
+use common::protocol::{DataPacket, NodeId};
+use common::protocol::command::{CommandStatus, CommandType, QueryChunk, QueryPage, MAX_QUERY_CHUNK_DATA};
+use common::security::{send_secure, MAX_SECURE_PAYLOAD};
+use common::{info, warn};
+
+/// 单次应答最多连续发送的分片数，剩余数据由请求方带偏移续传，大查询不会长时间占满信道
+pub const MAX_CHUNKS_PER_RESPONSE: usize = 8;
+
+/// 同时保存的查询结果快照数
+const MAX_QUERY_SNAPSHOTS: usize = 4;
+
+/// 快照的有效期（毫秒），请求方超过这么久没有续传需要从头查询
+pub const QUERY_SNAPSHOT_TTL_MS: u64 = 60_000;
+
+/// 一个请求方的查询结果
+struct Snapshot {
+    requester: NodeId,
+    token: u16,
+    data: Vec<u8>,
+    taken_at: u64,
+}
+
+/// 分页查询的结果快照
+///
+/// 续传时重新查询的话，两次请求之间写入或淘汰的记录会让后面的字节偏移整体移动，请求方拼出的结果重复或缺漏；
+/// 因此在偏移为0的请求时保存整个结果，同一请求方同一令牌的续传都从这份快照切片。
+pub struct QuerySnapshots {
+    snapshots: [Option<Snapshot>; MAX_QUERY_SNAPSHOTS],
+}
+
+impl QuerySnapshots {
+    pub fn new() -> Self {
+        Self { snapshots: core::array::from_fn(|_| None) }
+    }
+    
+    /// 本次请求要切片的结果：偏移为0时执行查询并保存快照，续传时使用保存的快照；快照已过期或被替换时返回None
+    pub fn result(&mut self, requester: NodeId, page: QueryPage, now: u64, query: impl FnOnce() -> Vec<u8>) -> Option<&[u8]> {
+        let existing = self.snapshots.iter().position(|snapshot| {
+            matches!(snapshot, Some(s) if s.requester == requester && s.token == page.token)
+        });
+        
+        if page.offset > 0 {
+            let snapshot = self.snapshots[existing?].as_ref()?;
+            if now.saturating_sub(snapshot.taken_at) >= QUERY_SNAPSHOT_TTL_MS {
+                return None;
+            }
+            return Some(&snapshot.data);
+        }
+        
+        // 同一令牌从头查询时覆盖旧快照，否则占用空位或替换最旧的
+        let slot = existing
+            .or_else(|| self.snapshots.iter().position(|snapshot| snapshot.is_none()))
+            .or_else(|| (0..MAX_QUERY_SNAPSHOTS).min_by_key(|&i| self.snapshots[i].as_ref().map_or(0, |s| s.taken_at)))?;
+        self.snapshots[slot] = Some(Snapshot { requester, token: page.token, data: query(), taken_at: now });
+        self.snapshots[slot].as_ref().map(|snapshot| &snapshot.data[..])
+    }
+}
+
+/// 从请求的偏移起把查询结果切成分片，最多`MAX_CHUNKS_PER_RESPONSE`个；偏移超出结果长度时返回None
+///
+/// 结果为空或偏移正好在末尾时返回一个没有数据的最后分片，请求方据此结束查询。
+pub fn query_chunks(page: QueryPage, data: &[u8]) -> Option<impl Iterator<Item = QueryChunk<'_>>> {
+    let start = page.offset as usize;
+    let remaining = data.get(start..)?;
+    let count = remaining.len().div_ceil(MAX_QUERY_CHUNK_DATA).clamp(1, MAX_CHUNKS_PER_RESPONSE);
+    
+    Some((0..count).map(move |index| {
+        let begin = index * MAX_QUERY_CHUNK_DATA;
+        let end = (begin + MAX_QUERY_CHUNK_DATA).min(remaining.len());
+        let more = end < remaining.len();
+        QueryChunk {
+            token: page.token,
+            offset: (start + begin) as u32,
+            more,
+            paused: more && index + 1 == count,
+            data: &remaining[begin..end],
+        }
+    }))
+}
+
+/// 从结果快照分片应答查询，快照已过期时回复参数错误，请求方需要从头查询
+pub fn answer_query<H: Hardware>(
+    hardware: &mut H,
+    snapshots: &mut QuerySnapshots,
+    requester: NodeId,
+    page: QueryPage,
+    query: impl FnOnce() -> Vec<u8>
+) {
+    let now = hardware.get_timestamp_ms().unwrap_or(0);
+    match snapshots.result(requester, page, now, query) {
+        Some(data) => send_query_response(hardware, requester, page, data),
+        None => {
+            warn!("{} 续传的查询 {} 已没有结果快照", requester, page.token);
+            send_chunk(hardware, requester, &[CommandType::Query as u8, CommandStatus::InvalidParameter as u8]);
+        },
+    }
+}
+
+/// 分片发送查询结果，偏移无效时回复参数错误
+pub fn send_query_response<H: Hardware>(hardware: &mut H, destination: NodeId, page: QueryPage, data: &[u8]) {
+    let chunks = match query_chunks(page, data) {
+        Some(chunks) => chunks,
+        None => {
+            warn!("查询偏移 {} 超出结果长度 {}", page.offset, data.len());
+            send_chunk(hardware, destination, &[CommandType::Query as u8, CommandStatus::InvalidParameter as u8]);
+            return;
+        }
+    };
+    
+    let mut buffer = [0u8; MAX_SECURE_PAYLOAD];
+    let mut sent = 0;
+    for chunk in chunks {
+        let len = chunk.serialize(&mut buffer);
+        if !send_chunk(hardware, destination, &buffer[..len]) {
+            break;
+        }
+        sent += 1;
+    }
+    info!("查询结果已分 {} 片发送给 {}，令牌: {}", sent, destination, page.token);
+}
+
+fn send_chunk<H: Hardware>(hardware: &mut H, destination: NodeId, data: &[u8]) -> bool {
+    let packet = DataPacket::new(hardware.get_node_id(), destination, 0, data);
+    match send_secure(hardware, &packet) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("发送查询应答失败: {:?}", e);
+            false
+        },
+    }
+}
\ No newline at end of file
@@ -0,0 +1,154 @@
+use core::fmt;
+use common::protocol::NodeId;
+use common::protocol::wire::{put_u16be, put_u32be};
+use common::hal::Hardware;
+use common::metrics::{self, Counter, MetricsSnapshot};
+use crate::storage::Storage;
+
+/// 统计快照中最多包含的节点数
+pub const MAX_STATS_NODES: usize = 16;
+
+/// 服务器运行统计计数器
+pub struct ServerStats {
+    /// 启动时间戳
+    boot_time: u64,
+    /// 接收到的数据包总数
+    packets_received: u32,
+    /// 被丢弃的数据包数
+    packets_dropped: u32,
+    /// 识别为重传重复而未存储的上传数
+    duplicates_suppressed: u32,
+}
+
+/// 统计快照，用于通过无线或串口返回
+pub struct StatsSnapshot {
+    /// 运行时间（秒）
+    pub uptime_secs: u32,
+    /// 电池电量
+    pub battery_level: u8,
+    /// 当前记录数
+    pub record_count: u16,
+    /// 存储容量
+    pub capacity: u16,
+    /// 接收到的数据包总数
+    pub packets_received: u32,
+    /// 被丢弃的数据包数
+    pub packets_dropped: u32,
+    /// 识别为重传重复而未存储的上传数
+    pub duplicates_suppressed: u32,
+    /// 每个节点的记录数
+    pub node_records: [(NodeId, u16); MAX_STATS_NODES],
+    /// 有效的节点数
+    pub node_count: usize,
+    /// 各层的运行指标
+    pub metrics: MetricsSnapshot,
+}
+
+impl ServerStats {
+    /// 创建新的统计计数器
+    pub fn new(boot_time: u64) -> Self {
+        Self {
+            boot_time,
+            packets_received: 0,
+            packets_dropped: 0,
+            duplicates_suppressed: 0,
+        }
+    }
+    
+    /// 记录收到一个数据包
+    pub fn record_received(&mut self) {
+        self.packets_received = self.packets_received.wrapping_add(1);
+    }
+    
+    /// 记录丢弃一个数据包
+    pub fn record_dropped(&mut self) {
+        self.packets_dropped = self.packets_dropped.wrapping_add(1);
+    }
+    
+    /// 记录一次被抑制的重复上传
+    pub fn record_duplicate(&mut self) {
+        self.duplicates_suppressed = self.duplicates_suppressed.wrapping_add(1);
+        metrics::increment(Counter::DuplicatesSuppressed);
+    }
+    
+    /// 收到的数据包中未被丢弃的比例（0-100%），尚未收到数据包时为100
+    pub fn reliability(&self) -> u8 {
+        if self.packets_received == 0 {
+            return 100;
+        }
+        let delivered = self.packets_received.saturating_sub(self.packets_dropped) as u64;
+        (delivered * 100 / self.packets_received as u64) as u8
+    }
+    
+    /// 生成统计快照
+    pub fn snapshot<H: Hardware, S: Storage>(&self, hardware: &H, storage: &S) -> StatsSnapshot {
+        let now = hardware.get_timestamp_ms().unwrap_or(self.boot_time);
+        
+        let mut snapshot = StatsSnapshot {
+            uptime_secs: (now.saturating_sub(self.boot_time) / 1000) as u32,
+            battery_level: hardware.get_battery_level().unwrap_or(0),
+            record_count: storage.record_count() as u16,
+            capacity: storage.capacity() as u16,
+            packets_received: self.packets_received,
+            packets_dropped: self.packets_dropped,
+            duplicates_suppressed: self.duplicates_suppressed,
+            node_records: [(NodeId::BROADCAST, 0); MAX_STATS_NODES],
+            node_count: 0,
+            metrics: metrics::snapshot(),
+        };
+        snapshot.node_count = storage.count_records_by_node(&mut snapshot.node_records);
+        
+        snapshot
+    }
+}
+
+impl StatsSnapshot {
+    /// 序列化统计快照，返回写入长度
+    ///
+    /// 格式：运行时间(4) 电量(1) 记录数(2) 容量(2) 接收数(4) 丢弃数(4) 节点数(1) [节点ID(6) 记录数(2)]* [指标]
+    ///
+    /// 缓冲区放得下时在末尾附加运行指标快照。
+    pub fn serialize(&self, buffer: &mut [u8]) -> usize {
+        const FIXED_LEN: usize = 18;
+        const NODE_LEN: usize = 8;
+        
+        if buffer.len() < FIXED_LEN {
+            return 0;
+        }
+        
+        put_u32be(&mut buffer[0..4], self.uptime_secs);
+        buffer[4] = self.battery_level;
+        put_u16be(&mut buffer[5..7], self.record_count);
+        put_u16be(&mut buffer[7..9], self.capacity);
+        put_u32be(&mut buffer[9..13], self.packets_received);
+        put_u32be(&mut buffer[13..17], self.packets_dropped);
+        
+        // 只写入缓冲区放得下的节点
+        let nodes = self.node_count.min((buffer.len() - FIXED_LEN) / NODE_LEN);
+        buffer[17] = nodes as u8;
+        
+        let mut offset = FIXED_LEN;
+        for (node_id, count) in self.node_records[..nodes].iter() {
+            buffer[offset..offset + 6].copy_from_slice(&node_id.0);
+            put_u16be(&mut buffer[offset + 6..offset + 8], *count);
+            offset += NODE_LEN;
+        }
+        
+        offset + self.metrics.serialize(&mut buffer[offset..])
+    }
+}
+
+impl fmt::Display for StatsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "运行时间: {}s", self.uptime_secs)?;
+        writeln!(f, "电池电量: {}%", self.battery_level)?;
+        writeln!(f, "存储占用: {}/{}", self.record_count, self.capacity)?;
+        writeln!(f, "接收数据包: {}, 丢弃: {}, 重复: {}",
+                 self.packets_received, self.packets_dropped, self.duplicates_suppressed)?;
+        for (node_id, count) in self.node_records[..self.node_count].iter() {
+            writeln!(f, "  节点 {}: {} 条记录", node_id, count)?;
+        }
+        writeln!(f, "运行指标:")?;
+        write!(f, "{}", self.metrics)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,702 @@
+#![cfg_attr(not(feature = "simulator"), no_std)]
+
+pub mod storage;
+pub mod api;
+pub mod video;
+pub mod ota;
+
+use core::marker::PhantomData;
+
+use common::protocol::{Beacon, DataPacket, NodeId, NodeRole, PacketType, ServiceType, deserialize_service_close};
+use common::hal::{Hardware, ResetCause, RADIO_DIAGNOSTICS_INTERVAL_MS};
+use common::hal::nvs::RebootBreadcrumb;
+use common::protocol::batch::deserialize_batch;
+use common::protocol::data::{flow_id_of, Reassembler, MAX_FRAGMENT_PAYLOAD, NO_FLOW};
+use common::beacon_interval::AdaptiveBeacon;
+use common::channel_plan::ChannelFollower;
+use common::clock::NetworkClock;
+use common::config::{ConfigObserver, NodeConfig};
+use common::mgmt::{MgmtAgent, MgmtRequester};
+use common::protocol::echo::{answer_echo, Echo};
+use common::protocol::route_discovery::answer_route_request;
+use common::protocol::stats::{answer_stats, send_stats, StatsCollector, StatsMessage, StatsProgress};
+use common::protocol::route_table::RouteTableMessage;
+use common::protocol::hello::answer_hello;
+use common::protocol::path::answer_path_establish;
+use common::protocol::error_report::{ErrorCode, ErrorReporter};
+use common::protocol::mgmt::{MgmtAttribute, MgmtMessage, MgmtOp};
+use common::protocol::frame::VideoTierNotice;
+use common::protocol::payload::{self, Command, PayloadType, Query, SensorReading, VideoFrame};
+use common::protocol::reliable::{send_ack, send_fragment_ack};
+use common::protocol::service_beacon::{send_service_beacon, ServiceBeacon};
+use common::protocol::time_sync::TimeBeacon;
+use common::security::{self, receive_secure};
+use common::utils::AlignedBuffer;
+use common::metrics::{self, Gauge};
+use common::monitor::TrafficMonitor;
+use common::{info, warn};
+use storage::StorageEvent;
+#[cfg(not(feature = "flash-log"))]
+use storage::circular_buffer::CircularBuffer;
+#[cfg(feature = "flash-log")]
+use storage::flash_log::FlashLog;
+use storage::dedup::UploadDedup;
+use storage::retention::{RetentionAction, RetentionPolicy};
+use api::cli::CommandProcessor;
+use api::console::SerialConsole;
+use api::stats::ServerStats;
+use video::reassembler::{FrameReassembler, StreamTiers, DEFAULT_FRAME_TIMEOUT_MS};
+use ota::distributor::OtaDistributor;
+
+/// 内存中记录的最大保留时间（24小时）
+const RECORD_MAX_AGE_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// 监听模式下在日志中汇总流量的间隔（毫秒）
+const MONITOR_SUMMARY_INTERVAL_MS: u64 = 60000;
+
+/// 服务信标中声明的最大带宽（kbps）
+const SERVICE_MAX_BANDWIDTH_KBPS: u16 = 1000;
+
+/// 服务信标中声明的最小处理延迟（毫秒）
+const SERVICE_MIN_LATENCY_MS: u16 = 50;
+
+/// HTTP接口上的状态更新间隔（毫秒）
+#[cfg(feature = "http")]
+const HTTP_PUBLISH_INTERVAL_MS: u64 = 5000;
+
+/// 服务端主循环的状态，每次调用[`ServerLoop::step`]执行一轮，模拟器和测试可以单步运行真实的主循环
+pub struct ServerLoop<H: Hardware> {
+    config: NodeConfig,
+    #[cfg(not(feature = "flash-log"))]
+    data_storage: CircularBuffer,
+    #[cfg(feature = "flash-log")]
+    data_storage: FlashLog<H::Flash>,
+    retention: RetentionPolicy,
+    command_processor: CommandProcessor,
+    stats: ServerStats,
+    console: SerialConsole,
+    ota: OtaDistributor,
+    mgmt: MgmtAgent,
+    mgmt_requester: MgmtRequester,
+    clock: NetworkClock,
+    channel_follower: ChannelFollower,
+    errors: ErrorReporter,
+    uplink: UplinkReceiver,
+    rx_buffer: AlignedBuffer<1024>,
+    reassembler: Reassembler,
+    /// 视频帧重组
+    frames: FrameReassembler,
+    /// 控制台远程查询的计数器
+    remote_stats: StatsCollector,
+    beacon_schedule: AdaptiveBeacon,
+    beacon_timer: u64,
+    beacon_sequence: u8,
+    diagnostics_timer: u64,
+    #[cfg(feature = "http")]
+    http_timer: u64,
+    /// 启用flash-log时记录存储的类型取决于硬件
+    marker: PhantomData<fn(&mut H)>,
+}
+
+impl<H: Hardware> ServerLoop<H> {
+    /// 加载配置并初始化存储和各子系统
+    pub fn start(hardware: &mut H) -> Self {
+        // 运行配置，从非易失存储加载，可通过远程管理和控制台调整
+        let config = NodeConfig::load(hardware.get_nvs(), NodeRole::Server);
+        hardware.set_default_ttl(config.default_ttl);
+        
+        // 配置无线电
+        let radio = hardware.get_radio();
+        let _ = radio.configure(config.channel, config.tx_power);
+        let _ = radio.set_airtime_limit(config.airtime_config());
+        
+        // 恢复网络密钥和发送计数器
+        security::restore(hardware);
+        
+        // 检查上次重启原因
+        if let Some(breadcrumb) = RebootBreadcrumb::take(hardware.get_nvs()) {
+            info!("上次重启由 {} 的命令触发，时间戳: {}",
+                     breadcrumb.requested_by, breadcrumb.timestamp);
+        }
+        
+        // 初始化存储；启用flash-log时记录追加到闪存数据分区，断电重启后历史数据仍在
+        #[cfg(not(feature = "flash-log"))]
+        let data_storage = CircularBuffer::new();
+        #[cfg(feature = "flash-log")]
+        let data_storage = FlashLog::open(hardware.get_flash().clone());
+        
+        // 初始化保留策略，定期移除过期记录
+        let retention = RetentionPolicy::new(RECORD_MAX_AGE_MS, RetentionAction::Drop);
+        
+        // 初始化命令处理器
+        let command_processor = CommandProcessor::new(hardware.get_node_id());
+        
+        // 初始化运行统计和串口控制台
+        let stats = ServerStats::new(hardware.get_timestamp_ms().unwrap_or(0));
+        let console = SerialConsole::new();
+        
+        // 初始化固件分发端，镜像通过控制台加载
+        let ota = OtaDistributor::new();
+        
+        // 远程管理：响应对本节点的请求，并通过控制台管理其他节点
+        let mgmt = MgmtAgent::new(hardware.get_nvs());
+        let mgmt_requester = MgmtRequester::new(hardware.get_nvs());
+        
+        // 网络时钟，记录时间戳使用主节点的网络时间
+        let clock = NetworkClock::new();
+        
+        // 跟随主节点组织的信道切换
+        let channel_follower = ChannelFollower::new();
+        
+        // 错误上报，存储已满等错误发往主节点
+        let mut errors = ErrorReporter::new();
+        if hardware.reset_cause() == ResetCause::Watchdog {
+            errors.record(ErrorCode::WatchdogReset, 0);
+        }
+        
+        // 初始化视频会话的质量档位和重传上传的去重缓存
+        let uplink = UplinkReceiver {
+            tiers: StreamTiers::new(DEFAULT_FRAME_TIMEOUT_MS),
+            uploads: UploadDedup::new(),
+        };
+        
+        // 创建缓冲区
+        let rx_buffer = AlignedBuffer::<1024>::new();
+        // 超过单帧长度的负载分片发送，在这里重组；视频帧较大，单独重组
+        let reassembler = Reassembler::new();
+        let frames = FrameReassembler::empty();
+        // 信标间隔随听到的邻居数伸缩
+        let beacon_schedule = AdaptiveBeacon::new(config.beacon_interval_ms);
+        
+        info!("服务端节点启动完成，开始执行主循环");
+        
+        Self {
+            config,
+            data_storage,
+            retention,
+            command_processor,
+            stats,
+            console,
+            ota,
+            mgmt,
+            mgmt_requester,
+            clock,
+            channel_follower,
+            errors,
+            uplink,
+            rx_buffer,
+            reassembler,
+            frames,
+            remote_stats: StatsCollector::new(),
+            beacon_schedule,
+            beacon_timer: 0,
+            beacon_sequence: 0,
+            diagnostics_timer: 0,
+            #[cfg(feature = "http")]
+            http_timer: 0,
+            marker: PhantomData,
+        }
+    }
+    
+    /// 执行一轮主循环，返回到下一轮之前可以休眠的毫秒数；执行了重启命令时返回None
+    pub fn step(&mut self, hardware: &mut H) -> Option<u32> {
+        // 监听模式下不运行服务，直到通过控制台或远程管理关闭
+        if self.config.monitor {
+            run_monitor(hardware, &mut self.console, &mut self.config);
+        }
+        
+        // 获取当前时间
+        let now = hardware.get_timestamp_ms().unwrap_or(0);
+        let network_now = self.clock.now(now);
+        
+        // 统计直接听到的邻居，用于调整信标间隔
+        while let Ok(Some(beacon)) = hardware.get_radio().receive_beacon() {
+            if beacon.is_valid() && security::verify_beacon(hardware, &beacon) {
+                self.beacon_schedule.observe(&beacon, now);
+            }
+        }
+        
+        // 按信标间隔广播信标，让客户端能够发现服务器；邻居越多间隔越长
+        if now - self.beacon_timer > self.beacon_schedule.interval_ms(now) {
+            send_beacon(hardware, self.beacon_sequence);
+            advertise_services(hardware, &self.data_storage, &self.stats);
+            self.beacon_sequence = self.beacon_sequence.wrapping_add(1);
+            self.beacon_timer = now;
+        }
+        
+        // 接收数据包
+        let buffer = self.rx_buffer.as_mut_slice();
+        
+        // 大负载的分片先重组，收齐后按一个完整的包处理
+        let received = match receive_secure(hardware, buffer) {
+            // 会话数据（视频帧）的分片逐个确认，按会话的质量档位确定重组超时
+            Some(fragment) if fragment.is_fragment() && fragment.header.flow_id != NO_FLOW => {
+                if fragment.header.packet_id != 0 {
+                    if let Err(e) = send_fragment_ack(hardware, &fragment) {
+                        warn!("发送分片确认失败: {:?}", e);
+                    }
+                }
+                let timeout_ms = self.uplink.tiers.timeout_ms(NodeId(fragment.header.source), fragment.header.flow_id);
+                self.frames.push_with_timeout(&fragment, now, timeout_ms)
+            },
+            Some(fragment) if fragment.is_fragment() => self.reassembler.push(&fragment, now),
+            received => received,
+        };
+        if let Some(packet) = received {
+            self.data_storage.update_timestamp(network_now);
+            self.stats.record_received();
+            
+            if packet.header.packet_type == PacketType::EchoRequest as u8 {
+                // 回显请求，用于客户端测量往返时延
+                answer_echo(hardware, &packet);
+            } else if packet.header.packet_type == PacketType::EchoReply as u8 {
+                // 控制台发出的回显请求的应答输出到日志
+                if let Some(reply) = Echo::deserialize(packet.data) {
+                    if reply.origin == hardware.get_node_id() {
+                        log_echo_reply(&reply, now);
+                    }
+                }
+            } else if packet.header.packet_type == PacketType::StatsRequest as u8 {
+                answer_stats(hardware, &packet);
+            } else if packet.header.packet_type == PacketType::StatsResponse as u8 {
+                // 控制台发出的计数器查询的应答逐页到达，收齐后输出到日志，没有收齐时经上一跳查询下一页
+                match StatsMessage::deserialize(packet.header.packet_type, packet.data) {
+                    Some(StatsMessage::Response { origin, target, page }) if origin == hardware.get_node_id() => {
+                        match self.remote_stats.accept(target, &page) {
+                            StatsProgress::Complete(snapshot) => info!("{} 的计数器:\n{}", target, snapshot),
+                            StatsProgress::Next(first) => {
+                                let request = StatsMessage::Request { origin, target, first };
+                                if let Err(e) = send_stats(hardware, NodeId(packet.header.source), 0, &request) {
+                                    warn!("查询 {} 的下一页计数器失败: {:?}", target, e);
+                                }
+                            },
+                            StatsProgress::Ignored => {},
+                        }
+                    },
+                    _ => {},
+                }
+            } else if packet.header.packet_type == PacketType::RouteTableResponse as u8 {
+                // 控制台发出的路由表查询的应答输出到日志
+                match RouteTableMessage::deserialize(packet.header.packet_type, packet.data) {
+                    Some(RouteTableMessage::Response { origin, target, dump }) if origin == hardware.get_node_id() => {
+                        info!("{} 的路由表:\n{}", target, dump);
+                    },
+                    _ => {},
+                }
+            } else if packet.header.packet_type == PacketType::Hello as u8 {
+                // 听到本节点信标的转发节点验证反向链路
+                answer_hello(hardware, &packet);
+            } else if packet.header.packet_type == PacketType::PathEstablish as u8 {
+                // 中继为客户端建立到本节点的路径，收到确认后停止重传
+                if !answer_path_establish(hardware, &packet) {
+                    warn!("应答来自 {} 的路径建立失败", NodeId(packet.header.source));
+                }
+            } else if packet.header.packet_type == PacketType::Flood as u8 {
+                // 转发节点查找本节点的路由请求，服务器只应答不转发
+                answer_route_request(hardware, &packet);
+            } else if packet.header.packet_type == PacketType::TimeSync as u8 {
+                // 时间信标，服务器只校准本地的网络时钟，不转发
+                if let Some(beacon) = TimeBeacon::deserialize(packet.data) {
+                    if let Some((_, step)) = self.clock.apply(&beacon, now) {
+                        if step != 0 {
+                            info!("网络时钟跳变 {}ms，主节点: {}", step, beacon.master);
+                        }
+                    }
+                }
+            } else if packet.header.packet_type == PacketType::RouteAdvert as u8
+                || packet.header.packet_type == PacketType::ServiceAdvertisement as u8
+                || packet.header.packet_type == PacketType::ServiceBeacon as u8 {
+                // 转发节点之间的路由通告、目录同步和其他服务器的服务信标，服务器不转发
+            } else if packet.header.packet_type == PacketType::ServiceClose as u8 {
+                // 客户端关闭服务，释放该会话的重组状态
+                if let Some(close) = deserialize_service_close(packet.data) {
+                    info!("服务 {} 已关闭，原因: {}", close.service_id, close.reason);
+                    self.uplink.tiers.close(close.service_id);
+                    self.frames.discard_flow(flow_id_of(close.service_id));
+                }
+            } else if packet.header.packet_type == PacketType::ChannelPlan as u8 {
+                // 信道切换通告，服务器不转发
+                self.channel_follower.handle(&packet, self.config.channel);
+            } else if self.ota.handle(hardware, &packet, now) {
+                // 固件更新的分块请求和结果报告
+            } else if packet.header.packet_type == PacketType::Mgmt as u8 {
+                // 控制台发出的请求的应答输出到日志，发给本节点的请求由代理处理
+                match MgmtMessage::deserialize(packet.data) {
+                    Some((response, _)) if response.op == MgmtOp::Response => {
+                        info!("节点 {} 属性 {} 的管理应答: {:?} {:?}",
+                              response.origin, response.attribute, response.status, response.value);
+                    },
+                    _ => {
+                        self.mgmt.handle(hardware, &packet, &mut self.config);
+                    },
+                }
+            } else {
+                handle_data_packet(hardware, &mut self.data_storage, &mut self.command_processor, &mut self.stats, &mut self.uplink,
+                                   &packet, network_now);
+            }
+        }
+        
+        // 执行保留策略，保证内存中始终是最近一段时间的数据
+        self.retention.run(&mut self.data_storage, None, network_now);
+        
+        // 存储接近占满时尽快上传，未读记录将被覆盖时向主节点报警
+        #[cfg(feature = "http")]
+        let mut upload_now = false;
+        self.data_storage.take_events(|event| match event {
+            StorageEvent::HighWatermark { records, capacity } => {
+                warn!("存储占用达到高水位: {}/{}", records, capacity);
+                #[cfg(feature = "http")]
+                {
+                    upload_now = true;
+                }
+            },
+            StorageEvent::LowWatermark { records, capacity } => {
+                info!("存储占用回落: {}/{}", records, capacity);
+            },
+            StorageEvent::OverwriteImminent { unread, capacity } => {
+                warn!("{} 条未读记录即将被覆盖，容量 {}", unread, capacity);
+                self.errors.record(ErrorCode::StorageFull, unread as u32);
+                #[cfg(feature = "http")]
+                {
+                    upload_now = true;
+                }
+            },
+            StorageEvent::UnreadOverwritten { count } => {
+                warn!("{} 条未读记录已被覆盖", count);
+            },
+        });
+        
+        // 处理命令
+        self.command_processor.process_commands(hardware, &mut self.data_storage, &self.stats);
+        
+        // 已执行重启命令（仅模拟器会运行到这里）
+        if self.command_processor.reboot_requested() {
+            return None;
+        }
+        
+        // 处理串口控制台命令
+        self.console.poll(hardware, &mut self.data_storage, &self.stats, &mut self.ota, &mut self.mgmt_requester, &mut self.config);
+        
+        // 服务器的无线电在每次使用时读取配置，这里只记录变化并更新信标间隔
+        let changes = self.config.take_changes();
+        if !changes.is_empty() {
+            info!("运行配置已更新: {:?}", changes);
+            self.beacon_schedule.config_changed(&self.config, changes);
+        }
+        
+        // 重新通知无响应的更新节点
+        self.ota.poll(hardware, now);
+        
+        // 到了切换时间换到新信道并保存
+        if let Some(channel) = self.channel_follower.poll(network_now) {
+            match self.config.set(hardware, MgmtAttribute::Channel, &[channel]) {
+                Ok(()) => info!("跟随主节点切换到信道 {}", channel),
+                Err(e) => warn!("切换到信道 {} 失败: {:?}", channel, e),
+            }
+        }
+        
+        // 定期读取无线电收发统计，只能读取累计值的后端借此更新指标
+        if now - self.diagnostics_timer > RADIO_DIAGNOSTICS_INTERVAL_MS {
+            hardware.get_radio().diagnostics();
+            self.diagnostics_timer = now;
+        }
+        
+        // 限速上报本节点的错误
+        self.errors.poll(hardware, self.clock.master(), network_now);
+        
+        // 更新HTTP接口上的传感器数据，发布后的记录视为已读
+        #[cfg(feature = "http")]
+        if upload_now || now - self.http_timer > HTTP_PUBLISH_INTERVAL_MS {
+            publish_sensors(&self.data_storage);
+            self.data_storage.mark_all_read();
+            self.http_timer = now;
+        }
+        
+        // 每500毫秒执行一轮，可以根据实际硬件调整
+        Some(500)
+    }
+}
+
+/// 服务端主循环，收到重启命令后返回，由入口重新启动
+pub fn server_main<H: Hardware>(hardware: &mut H) {
+    let mut node = ServerLoop::start(hardware);
+    while let Some(delay_ms) = node.step(hardware) {
+        let _ = hardware.delay_ms(delay_ms);
+    }
+}
+
+/// 监听模式：无线电只收不发，按节点和包类型统计听到的流量，通过控制台查看
+fn run_monitor<H: Hardware>(hardware: &mut H, console: &mut SerialConsole, config: &mut NodeConfig) {
+    if !hardware.get_radio().set_listen_only(true) {
+        warn!("无线电不支持只收不发，监听模式下仍可能回复链路确认");
+    }
+    if !hardware.get_radio().set_promiscuous(true) {
+        warn!("无线电不支持混杂模式，监听模式下只能听到发给本节点和广播的帧");
+    }
+    info!("进入监听模式，控制台输入 monitor 查看统计");
+    
+    let mut now = hardware.get_timestamp_ms().unwrap_or(0);
+    let mut monitor = TrafficMonitor::new(now);
+    let mut rx_buffer = AlignedBuffer::<1024>::new();
+    let mut summary_timer = now;
+    
+    while config.monitor {
+        now = hardware.get_timestamp_ms().unwrap_or(0);
+        
+        // 不解密也不校验，受保护的包只统计头部
+        let radio = hardware.get_radio();
+        while let Ok(Some(beacon)) = radio.receive_beacon() {
+            monitor.observe_beacon(&beacon, radio.get_rssi().unwrap_or(0), now);
+        }
+        while let Ok(Some(packet)) = radio.receive_data(rx_buffer.as_mut_slice()) {
+            monitor.observe_data(&packet, radio.get_rssi().unwrap_or(0), now);
+        }
+        
+        console.poll_monitor(hardware, &mut monitor, config);
+        
+        let changes = config.take_changes();
+        if !changes.is_empty() {
+            info!("运行配置已更新: {:?}", changes);
+        }
+        
+        if now - summary_timer > MONITOR_SUMMARY_INTERVAL_MS {
+            let packets: u32 = monitor.nodes().map(|traffic| traffic.total()).sum();
+            info!("监听中，听到 {} 个节点，共 {} 个包", monitor.nodes().count(), packets);
+            summary_timer = now;
+        }
+        
+        let _ = hardware.delay_ms(50);
+    }
+    
+    hardware.get_radio().set_listen_only(false);
+    hardware.get_radio().set_promiscuous(false);
+    info!("退出监听模式");
+}
+
+/// 将存储的传感器数据以JSON发布到HTTP接口
+#[cfg(feature = "http")]
+fn publish_sensors<S: storage::Storage>(storage: &S) {
+    use common::http::{node_json, publish};
+    
+    let mut records = Vec::with_capacity(storage.record_count());
+    storage.for_each_record(|record| {
+        records.push(format!(
+            "{{\"node\":{},\"timestamp\":{},\"temperature\":{},\"humidity\":{},\"pressure\":{}}}",
+            node_json(record.node_id), record.timestamp, record.temperature, record.humidity, record.pressure
+        ));
+    });
+    publish("/sensors", format!("[{}]", records.join(",")));
+}
+
+/// 发送服务器信标，序号供转发节点去重
+fn send_beacon<H: Hardware>(hardware: &mut H, sequence: u8) {
+    let node_id = hardware.get_node_id();
+    let battery_level = hardware.get_battery_level().unwrap_or(100);
+    metrics::set(Gauge::BatteryLevel, battery_level as u32);
+    let rssi = hardware.get_radio().get_rssi().unwrap_or(-80);
+    
+    // 创建信标
+    let mut beacon = Beacon::with_role(node_id, battery_level, rssi, NodeRole::Server);
+    beacon.set_sequence(sequence);
+    security::sign_beacon(hardware, &mut beacon);
+    
+    // 发送信标
+    let radio = hardware.get_radio();
+    if let Err(e) = radio.send_beacon(&beacon) {
+        warn!("发送信标失败: {:?}", e);
+    } else {
+        info!("发送服务器信标，电池电量: {}%", battery_level);
+    }
+}
+
+/// 随信标广播本服务器的服务能力，负载按存储占用计算
+fn advertise_services<H: Hardware, S: storage::Storage>(hardware: &mut H, storage: &S, stats: &ServerStats) {
+    let battery_level = hardware.get_battery_level().unwrap_or(100);
+    let load = (storage.record_count() * 100 / storage.capacity().max(1)) as u8;
+    
+    let beacon = ServiceBeacon::new(
+        load,
+        battery_level,
+        SERVICE_MAX_BANDWIDTH_KBPS,
+        SERVICE_MIN_LATENCY_MS,
+        stats.reliability()
+    )
+        .with_service(ServiceType::VideoRelay)
+        .with_service(ServiceType::Storage)
+        .with_service(ServiceType::SensorCollection);
+    
+    if let Err(e) = send_service_beacon(hardware, &beacon) {
+        warn!("发送服务信标失败: {:?}", e);
+    }
+}
+
+/// 输出控制台回显请求的结果，路径追踪时逐跳列出节点和信号强度
+fn log_echo_reply(reply: &Echo, now: u64) {
+    info!("{} 的回显应答: 往返 {}ms，跳数 {}", reply.target, now.saturating_sub(reply.sent_at), reply.hop_count);
+    for (index, hop) in reply.trace.iter().flat_map(|trace| trace.hops()).enumerate() {
+        info!("  {}. {}  RSSI {} dBm", index + 1, hop.node_id, hop.rssi);
+    }
+}
+
+/// 客户端上行数据的接收状态
+struct UplinkReceiver {
+    /// 视频会话的质量档位
+    tiers: StreamTiers,
+    /// 确认丢失后重传的传感器上传去重
+    uploads: UploadDedup,
+}
+
+/// 处理接收到的数据包
+fn handle_data_packet<H: Hardware, S: storage::Storage>(
+    hardware: &mut H,
+    storage: &mut S,
+    command_processor: &mut CommandProcessor,
+    stats: &mut ServerStats,
+    uplink: &mut UplinkReceiver,
+    packet: &DataPacket,
+    network_now: u64
+) {
+    let source = NodeId(packet.header.source);
+    
+    info!("接收到来自 {} 的数据包，大小: {} 字节",
+        source, packet.data.len());
+    
+    // 按负载类型分发
+    match PayloadType::of(packet.data) {
+        // 传感器数据
+        Some(PayloadType::SensorReading) => {
+            info!("接收到传感器数据");
+            
+            let reading = match payload::decode::<SensorReading>(packet.data) {
+                Some(reading) => reading,
+                None => {
+                    warn!("传感器数据格式错误");
+                    stats.record_dropped();
+                    return;
+                },
+            };
+            
+            // 包ID非0表示发送方需要确认
+            if packet.header.packet_id != 0 {
+                if let Err(e) = send_ack(hardware, source, packet.header.packet_id, reading.service_id) {
+                    warn!("发送确认失败: {:?}", e);
+                }
+            }
+            
+            // 存储传感器数据，确认丢失后重传的包已经存过
+            if uplink.uploads.is_duplicate(source, packet.header.packet_id, network_now, network_now) {
+                info!("丢弃来自 {} 的重复上传，包ID: {}", source, packet.header.packet_id);
+                stats.record_duplicate();
+            } else {
+                storage.add_data(source, reading.temperature, reading.humidity, reading.pressure);
+                
+                info!("存储传感器数据: 温度={}°C, 湿度={}%, 气压={}hPa",
+                         reading.temperature, reading.humidity, reading.pressure / 100.0);
+            }
+        },
+        // 批量传感器数据
+        Some(PayloadType::Batch) => {
+            match deserialize_batch(packet.data) {
+                Some((session_id, samples)) => {
+                    if packet.header.packet_id != 0 {
+                        if let Err(e) = send_ack(hardware, source, packet.header.packet_id, session_id) {
+                            warn!("发送确认失败: {:?}", e);
+                        }
+                    }
+                    
+                    // 按样本距发送时刻的秒数还原采样的网络时间
+                    let mut samples = samples
+                        .map(|sample| (network_now.saturating_sub(sample.age_secs as u64 * 1000), sample))
+                        .peekable();
+                    let first = samples.peek().map_or(network_now, |(timestamp, _)| *timestamp);
+                    
+                    // 确认丢失后重传的批量仍需确认，但不再重复存储
+                    if uplink.uploads.is_duplicate(source, packet.header.packet_id, first, network_now) {
+                        info!("丢弃来自 {} 的重复批量，包ID: {}", source, packet.header.packet_id);
+                        stats.record_duplicate();
+                    } else {
+                        let mut count = 0;
+                        for (timestamp, sample) in samples {
+                            storage.add_data_at(source, timestamp, sample.temperature, sample.humidity, sample.pressure);
+                            count += 1;
+                        }
+                        
+                        info!("存储批量传感器数据: {} 个样本", count);
+                    }
+                },
+                None => {
+                    warn!("批量传感器数据格式错误");
+                    stats.record_dropped();
+                },
+            }
+        },
+        // 视频帧，分片已在接收时重组
+        Some(PayloadType::VideoFrame) => {
+            match payload::decode::<VideoFrame>(packet.data) {
+                Some(frame) => {
+                    // 单个数据包装得下的帧在这里确认，分片在接收时已逐个确认
+                    if packet.header.packet_id != 0 && packet.data.len() <= MAX_FRAGMENT_PAYLOAD {
+                        if let Err(e) = send_ack(hardware, source, packet.header.packet_id, frame.service_id) {
+                            warn!("发送确认失败: {:?}", e);
+                        }
+                    }
+                    
+                    info!("收到完整视频帧 #{}，来自 {}，服务ID: {}，{} 字节",
+                             frame.frame_number, source, frame.service_id, frame.data.len());
+                },
+                None => {
+                    warn!("视频帧格式错误");
+                    stats.record_dropped();
+                },
+            }
+        },
+        // 视频质量档位变化
+        Some(PayloadType::VideoTier) => {
+            match VideoTierNotice::deserialize(packet.data) {
+                Some(notice) => {
+                    if packet.header.packet_id != 0 {
+                        if let Err(e) = send_ack(hardware, source, packet.header.packet_id, notice.service_id) {
+                            warn!("发送确认失败: {:?}", e);
+                        }
+                    }
+                    
+                    let tier = notice.video_tier();
+                    info!("{} 的视频会话 {} 切换到档位 {}：每 {}ms 一帧，{} 字节",
+                             source, notice.service_id, notice.tier, tier.frame_interval_ms, tier.frame_size);
+                    uplink.tiers.set_tier(source, notice.service_id, tier);
+                },
+                None => {
+                    warn!("视频档位通知格式错误");
+                    stats.record_dropped();
+                },
+            }
+        },
+        // 命令
+        Some(PayloadType::Command) => {
+            info!("接收到命令");
+            match payload::decode::<Command>(packet.data) {
+                Some(command) => command_processor.add_command(source, &command),
+                None => {
+                    warn!("未知命令或命令格式错误");
+                    stats.record_dropped();
+                },
+            }
+        },
+        // 查询
+        Some(PayloadType::Query) => {
+            info!("接收到查询");
+            // 处理查询，分片返回存储的数据；带参数时按时间范围查询并降采样
+            match payload::decode::<Query>(packet.data) {
+                Some(query) => command_processor.answer_query(hardware, storage, source, &query.params, query.page),
+                None => {
+                    warn!("查询参数格式错误");
+                    stats.record_dropped();
+                },
+            }
+        },
+        None => {
+            info!("接收到未知类型的数据包: {:?}", packet.data.first());
+            stats.record_dropped();
+        },
+    }
+}
\ No newline at end of file
@@ -0,0 +1,236 @@
+use common::hal::{FirmwareStorage, Hardware};
+use common::ota::image_digest;
+use common::protocol::{DataPacket, NodeId, PacketType};
+use common::protocol::ota::{send_ota, OtaBody, OtaMessage, OtaStatus, OTA_CHUNK_SIZE, OTA_DIGEST_LEN, OTA_TAG_LEN};
+use common::security::{sign_ota, verify_ota};
+use common::{info, warn};
+
+/// 同时更新的最大节点数
+pub const MAX_OTA_TARGETS: usize = 8;
+
+/// 节点无响应多久后重新通知（毫秒），节点收到后从已保存的偏移继续
+const OFFER_RETRY_MS: u64 = 30000;
+
+/// 最大通知次数
+const MAX_OFFERS: u8 = 10;
+
+/// 待分发的镜像，数据位于本节点的固件暂存区
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OtaImage {
+    /// 镜像版本
+    pub version: u32,
+    /// 镜像长度
+    pub size: u32,
+    /// 镜像CRC-32
+    pub crc: u32,
+    /// 镜像SHA-256摘要，随通知一起认证，节点收齐后据此确认镜像未被篡改
+    pub digest: [u8; OTA_DIGEST_LEN],
+}
+
+/// 单个节点的更新状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetState {
+    /// 已通知，等待节点请求分块
+    Offered,
+    /// 正在传输，记录节点最近请求的偏移
+    Transferring { offset: u32 },
+    /// 节点已报告结果
+    Finished(OtaStatus),
+    /// 多次通知无响应
+    Unreachable,
+}
+
+/// 正在更新的节点
+#[derive(Debug, Clone, Copy)]
+pub struct OtaTarget {
+    /// 目标节点
+    pub node: NodeId,
+    /// 到达目标的第一跳中继
+    pub via: NodeId,
+    /// 更新状态
+    pub state: TargetState,
+    last_activity: u64,
+    offers: u8,
+}
+
+/// 服务器侧的镜像分发端：通知节点有新镜像，响应节点的分块请求并跟踪更新结果
+pub struct OtaDistributor {
+    image: Option<OtaImage>,
+    targets: [Option<OtaTarget>; MAX_OTA_TARGETS],
+}
+
+impl OtaDistributor {
+    pub fn new() -> Self {
+        Self {
+            image: None,
+            targets: [None; MAX_OTA_TARGETS],
+        }
+    }
+    
+    /// 当前分发的镜像
+    pub fn image(&self) -> Option<OtaImage> {
+        self.image
+    }
+    
+    /// 使用暂存区中已有的镜像作为分发源，计算其CRC和摘要
+    ///
+    /// 镜像更换后之前的更新目标全部清除。暂存区读取失败时返回false。
+    pub fn load_image<H: Hardware>(&mut self, hardware: &mut H, version: u32, size: u32) -> bool {
+        if size == 0 {
+            return false;
+        }
+        
+        let (crc, digest) = match image_digest(hardware, size) {
+            Some(result) => result,
+            None => return false,
+        };
+        
+        self.image = Some(OtaImage { version, size, crc, digest });
+        self.targets = [None; MAX_OTA_TARGETS];
+        true
+    }
+    
+    /// 开始更新指定节点，经由via转发；via为节点本身时直接发送
+    pub fn start<H: Hardware>(&mut self, hardware: &mut H, node: NodeId, via: NodeId, current_time: u64) -> bool {
+        let image = match self.image {
+            Some(image) => image,
+            None => return false,
+        };
+        
+        let index = self.targets.iter()
+            .position(|entry| matches!(entry, Some(t) if t.node == node))
+            .or_else(|| self.targets.iter().position(|entry| entry.is_none()))
+            .or_else(|| self.targets.iter().position(|entry| {
+                matches!(entry, Some(t) if matches!(t.state, TargetState::Finished(_) | TargetState::Unreachable))
+            }));
+        let index = match index {
+            Some(index) => index,
+            None => return false,
+        };
+        
+        let mut target = OtaTarget {
+            node,
+            via,
+            state: TargetState::Offered,
+            last_activity: current_time,
+            offers: 0,
+        };
+        send_offer(hardware, &image, &mut target);
+        self.targets[index] = Some(target);
+        true
+    }
+    
+    /// 遍历所有更新目标
+    pub fn targets(&self) -> impl Iterator<Item = &OtaTarget> {
+        self.targets.iter().flatten()
+    }
+    
+    /// 处理节点发来的分块请求和结果报告，不是固件更新包时返回false
+    ///
+    /// 未通过认证的请求和报告直接丢弃。
+    pub fn handle<H: Hardware>(&mut self, hardware: &mut H, packet: &DataPacket, current_time: u64) -> bool {
+        if packet.header.packet_type != PacketType::Ota as u8 {
+            return false;
+        }
+        let (image, message) = match (self.image, OtaMessage::deserialize(packet.data)) {
+            (Some(image), Some(message)) if message.version == image.version => (image, message),
+            _ => return true,
+        };
+        if !verify_ota(hardware, &message) {
+            warn!("固件更新消息未通过认证，来源: {}", message.origin);
+            return true;
+        }
+        let target = match self.targets.iter_mut().flatten().find(|t| t.node == message.origin) {
+            Some(target) => target,
+            None => return true,
+        };
+        
+        // 应答沿请求到达的上一跳返回
+        target.via = NodeId(packet.header.source);
+        target.last_activity = current_time;
+        
+        match message.body {
+            OtaBody::Request { offset } => {
+                target.state = TargetState::Transferring { offset };
+                send_chunk(hardware, &image, target, offset, packet.header.packet_id);
+            },
+            OtaBody::Result { status } => {
+                target.state = TargetState::Finished(status);
+                info!("节点 {} 固件更新结果: {:?}", target.node, status);
+            },
+            _ => {},
+        }
+        
+        true
+    }
+    
+    /// 重新通知长时间无响应的节点
+    pub fn poll<H: Hardware>(&mut self, hardware: &mut H, current_time: u64) {
+        let image = match self.image {
+            Some(image) => image,
+            None => return,
+        };
+        
+        for target in self.targets.iter_mut().flatten() {
+            if matches!(target.state, TargetState::Finished(_) | TargetState::Unreachable)
+                || current_time.saturating_sub(target.last_activity) < OFFER_RETRY_MS {
+                continue;
+            }
+            
+            if target.offers >= MAX_OFFERS {
+                warn!("节点 {} 多次通知无响应，放弃固件更新", target.node);
+                target.state = TargetState::Unreachable;
+                continue;
+            }
+            
+            target.last_activity = current_time;
+            send_offer(hardware, &image, target);
+        }
+    }
+}
+
+/// 通知节点有新镜像
+fn send_offer<H: Hardware>(hardware: &mut H, image: &OtaImage, target: &mut OtaTarget) {
+    target.offers += 1;
+    let mut offer = OtaMessage {
+        origin: hardware.get_node_id(),
+        target: target.node,
+        version: image.version,
+        body: OtaBody::Offer { size: image.size, crc: image.crc, digest: image.digest },
+        tag: [0; OTA_TAG_LEN],
+    };
+    sign_ota(hardware, &mut offer);
+    
+    if let Err(e) = send_ota(hardware, target.via, 0, &offer) {
+        warn!("发送固件更新通知失败: {:?}", e);
+    }
+}
+
+/// 从暂存区读取并发送请求的分块
+fn send_chunk<H: Hardware>(hardware: &mut H, image: &OtaImage, target: &OtaTarget, offset: u32, packet_id: u16) {
+    if offset >= image.size {
+        return;
+    }
+    
+    let mut data = [0u8; OTA_CHUNK_SIZE];
+    let len = ((image.size - offset) as usize).min(data.len());
+    match hardware.get_firmware().read_staging(offset, &mut data[..len]) {
+        Ok(read) if read == len => {},
+        _ => {
+            warn!("读取镜像分块失败，偏移: {}", offset);
+            return;
+        },
+    }
+    
+    let mut chunk = OtaMessage {
+        origin: hardware.get_node_id(),
+        target: target.node,
+        version: image.version,
+        body: OtaBody::Chunk { offset, data: &data[..len] },
+        tag: [0; OTA_TAG_LEN],
+    };
+    sign_ota(hardware, &mut chunk);
+    if let Err(e) = send_ota(hardware, target.via, packet_id, &chunk) {
+        warn!("发送镜像分块失败: {:?}", e);
+    }
+}
\ No newline at end of file
@@ -0,0 +1 @@
+pub mod distributor;
\ No newline at end of file
@@ -0,0 +1,234 @@
+use common::protocol::NodeId;
+use common::protocol::command::{AggregateBucket, AggregatePage, AggregateParams, AggregateWindow, MAX_AGGREGATE_BUCKETS};
+use crate::storage::{SensorRecord, Storage};
+
+/// 汇总的粒度（毫秒），聚合窗口都是它的整数倍
+const MINUTE_MS: u64 = 60_000;
+
+/// 最多保留的节点分钟汇总数，写满后丢弃最早一分钟的汇总
+pub const ROLLUP_CAPACITY: usize = 256;
+
+/// 一个时间窗口的累加器，逐条记录更新，不保留记录本身
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    start_time: u64,
+    count: u32,
+    min: f32,
+    max: f32,
+    sum: f64,
+}
+
+impl Accumulator {
+    fn new(start_time: u64, value: f32) -> Self {
+        Self { start_time, count: 1, min: value, max: value, sum: value as f64 }
+    }
+    
+    fn add(&mut self, value: f32) {
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as f64;
+    }
+    
+    /// 并入另一个累加器的统计
+    fn merge(&mut self, other: &Accumulator) {
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+    }
+    
+    /// 移除一个值，返回最小和最大值是否仍然准确
+    fn remove(&mut self, value: f32) -> bool {
+        self.count -= 1;
+        self.sum -= value as f64;
+        value > self.min && value < self.max
+    }
+    
+    fn bucket(&self) -> AggregateBucket {
+        AggregateBucket {
+            start_time: self.start_time,
+            count: self.count.min(u16::MAX as u32) as u16,
+            min: self.min,
+            max: self.max,
+            avg: (self.sum / self.count as f64) as f32,
+        }
+    }
+}
+
+/// 一个节点一分钟内的记录汇总，温度、湿度和气压各一个累加器
+#[derive(Debug, Clone, Copy)]
+struct MinuteRollup {
+    node_id: NodeId,
+    minute: u64,
+    values: [Accumulator; 3],
+    /// 移除过可能是最小或最大值的记录，查询时重新扫描这一分钟
+    stale: bool,
+}
+
+/// 按节点和分钟增量维护的记录汇总，聚合查询合并汇总而不必扫描整个存储
+///
+/// 存储写入和移除记录时同步更新。写满后丢弃最早一分钟的汇总，查询更早的时间范围时扫描存储。
+#[derive(Debug, Clone, Default)]
+pub struct Rollups {
+    /// 按分钟和节点排序
+    entries: Vec<MinuteRollup>,
+    /// 早于这一分钟的汇总已丢弃
+    floor: u64,
+}
+
+impl Rollups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    fn find(&self, node_id: NodeId, minute: u64) -> Result<usize, usize> {
+        self.entries.binary_search_by_key(&(minute, node_id.0), |entry| (entry.minute, entry.node_id.0))
+    }
+    
+    /// 记录写入存储后调用
+    pub fn add(&mut self, record: &SensorRecord) {
+        let minute = record.timestamp / MINUTE_MS;
+        let values = [record.temperature, record.humidity, record.pressure];
+        if minute < self.floor {
+            return;
+        }
+        if let Ok(index) = self.find(record.node_id, minute) {
+            for (accumulator, value) in self.entries[index].values.iter_mut().zip(values) {
+                accumulator.add(value);
+            }
+            return;
+        }
+        
+        // 写满时丢弃最早一分钟的所有汇总，新记录本身最早时不再汇总
+        if self.entries.len() >= ROLLUP_CAPACITY {
+            self.floor = self.entries[0].minute.min(minute) + 1;
+            let floor = self.floor;
+            self.entries.retain(|entry| entry.minute >= floor);
+            if minute < floor {
+                return;
+            }
+        }
+        let start_time = minute * MINUTE_MS;
+        let index = self.find(record.node_id, minute).unwrap_or_else(|index| index);
+        self.entries.insert(index, MinuteRollup {
+            node_id: record.node_id,
+            minute,
+            values: values.map(|value| Accumulator::new(start_time, value)),
+            stale: false,
+        });
+    }
+    
+    /// 记录从存储移除后调用
+    pub fn remove(&mut self, record: &SensorRecord) {
+        let minute = record.timestamp / MINUTE_MS;
+        let values = [record.temperature, record.humidity, record.pressure];
+        if minute < self.floor {
+            return;
+        }
+        if let Ok(index) = self.find(record.node_id, minute) {
+            let entry = &mut self.entries[index];
+            for (accumulator, value) in entry.values.iter_mut().zip(values) {
+                if !accumulator.remove(value) {
+                    entry.stale = true;
+                }
+            }
+            if entry.values[0].count == 0 {
+                self.entries.remove(index);
+            }
+        }
+    }
+    
+    /// 存储清空后调用
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.floor = 0;
+    }
+}
+
+/// 按时间窗口聚合存储中一个节点的测量值，返回按窗口起始时间排序的统计结果，没有记录的窗口不返回
+///
+/// 窗口以整分钟为单位：按分钟聚合时与整分钟对齐；按窗口数聚合时从第一条记录所在的分钟起等分到最后一条记录所在的分钟。
+/// 各分钟的统计取自增量汇总，只有查询范围首尾不完整的分钟、过期或已丢弃的汇总才需要扫描一次存储。
+pub fn aggregate<S: Storage + ?Sized>(storage: &S, params: &AggregateParams) -> Vec<AggregateBucket> {
+    if params.start_time > params.end_time {
+        return Vec::new();
+    }
+    let rollups = storage.rollups();
+    let slot = params.measurement as usize - 1;
+    let (first_minute, last_minute) = (params.start_time / MINUTE_MS, params.end_time / MINUTE_MS);
+    let covered = |minute: u64| {
+        minute >= rollups.floor
+            && minute * MINUTE_MS >= params.start_time
+            && minute * MINUTE_MS + MINUTE_MS - 1 <= params.end_time
+    };
+    
+    // 每分钟一个累加器，按分钟排序
+    let mut minutes: Vec<Accumulator> = Vec::new();
+    let mut scan = !covered(first_minute) || !covered(last_minute);
+    let start = rollups.entries.partition_point(|entry| entry.minute < first_minute);
+    for entry in rollups.entries[start..].iter().take_while(|entry| entry.minute <= last_minute) {
+        if entry.node_id != params.node_id {
+            continue;
+        }
+        if entry.stale || !covered(entry.minute) {
+            scan = true;
+        } else {
+            minutes.push(entry.values[slot]);
+        }
+    }
+    
+    // 汇总不能覆盖的分钟逐条扫描，记录不一定按时间排列
+    if scan {
+        let mut scanned: Vec<Accumulator> = Vec::new();
+        storage.for_each_record(|record| {
+            if record.node_id != params.node_id || record.timestamp < params.start_time || record.timestamp > params.end_time {
+                return;
+            }
+            let start_time = record.timestamp / MINUTE_MS * MINUTE_MS;
+            if minutes.binary_search_by_key(&start_time, |minute| minute.start_time).is_ok() {
+                return;
+            }
+            let value = params.measurement.select(record.temperature, record.humidity, record.pressure);
+            match scanned.binary_search_by_key(&start_time, |minute| minute.start_time) {
+                Ok(index) => scanned[index].add(value),
+                Err(index) => scanned.insert(index, Accumulator::new(start_time, value)),
+            }
+        });
+        minutes.extend(scanned);
+        minutes.sort_by_key(|minute| minute.start_time);
+    }
+    
+    let (origin, period) = match (params.window, minutes.first(), minutes.last()) {
+        (AggregateWindow::Minutes(window), _, _) => (0, (window as u64).max(1) * MINUTE_MS),
+        (AggregateWindow::Points(points), Some(first), Some(last)) => {
+            let span = (last.start_time - first.start_time) / MINUTE_MS + 1;
+            (first.start_time, span.div_ceil((points as u64).max(1)) * MINUTE_MS)
+        },
+        (AggregateWindow::Points(_), _, _) => return Vec::new(),
+    };
+    
+    let mut windows: Vec<Accumulator> = Vec::new();
+    for minute in minutes {
+        let start_time = origin + (minute.start_time - origin) / period * period;
+        match windows.last_mut() {
+            Some(window) if window.start_time == start_time => window.merge(&minute),
+            _ => windows.push(Accumulator { start_time, ..minute }),
+        }
+    }
+    
+    windows.iter().map(Accumulator::bucket).collect()
+}
+
+/// 从参数中的窗口偏移起取出一个数据包能容纳的窗口，返回响应头部和这一页的窗口
+pub fn page<'a>(params: &AggregateParams, buckets: &'a [AggregateBucket]) -> (AggregatePage, &'a [AggregateBucket]) {
+    let start = (params.offset as usize).min(buckets.len());
+    let end = (start + MAX_AGGREGATE_BUCKETS).min(buckets.len());
+    let header = AggregatePage {
+        node_id: params.node_id,
+        measurement: params.measurement,
+        offset: params.offset,
+        more: end < buckets.len(),
+    };
+    (header, &buckets[start..end])
+}
\ No newline at end of file
@@ -1,16 +1,45 @@
+use common::metrics::{self, Counter};
 use common::protocol::NodeId;
-use crate::storage::{SensorRecord, Storage};
+use common::protocol::command::QueryParams;
+use crate::storage::{downsample, serialize_records, SensorRecord, Storage, StorageEvent, Watermarks};
+use crate::storage::aggregate::Rollups;
+use crate::storage::latest::LatestRecords;
 
 /// 环形缓冲区，用于存储传感器数据
 pub struct CircularBuffer {
     /// 存储区
     records: [Option<SensorRecord>; 1024],
+    /// 各位置的记录是否还没有被读取（上传）
+    unread: [bool; 1024],
     /// 当前写入位置
     write_position: usize,
     /// 当前存储的记录数
     record_count: usize,
+    /// 未读记录数
+    unread_count: usize,
     /// 全局时间戳，用于给记录分配时间戳
     timestamp: u64,
+    /// 水位线
+    watermarks: Watermarks,
+    /// 是否处于高水位
+    above_high: bool,
+    /// 是否已报告未读记录即将被覆盖
+    overwrite_warned: bool,
+    /// 尚未取出的事件
+    events: PendingEvents,
+    /// 聚合查询使用的分钟汇总
+    rollups: Rollups,
+    /// 各节点的最新记录
+    latest: LatestRecords,
+}
+
+/// 尚未取出的存储事件，同类事件只保留最新的一次
+#[derive(Debug, Clone, Copy, Default)]
+struct PendingEvents {
+    high: bool,
+    low: bool,
+    overwrite_imminent: bool,
+    overwritten: usize,
 }
 
 impl CircularBuffer {
@@ -18,9 +47,89 @@ impl CircularBuffer {
     pub fn new() -> Self {
         Self {
             records: [None; 1024],
+            unread: [false; 1024],
             write_position: 0,
             record_count: 0,
+            unread_count: 0,
             timestamp: 0,
+            watermarks: Watermarks::default(),
+            above_high: false,
+            overwrite_warned: false,
+            events: PendingEvents::default(),
+            rollups: Rollups::new(),
+            latest: LatestRecords::new(),
+        }
+    }
+    
+    /// 设置水位线
+    pub fn with_watermarks(mut self, watermarks: Watermarks) -> Self {
+        self.watermarks = watermarks;
+        self
+    }
+    
+    /// 未读记录数
+    pub fn unread_count(&self) -> usize {
+        self.unread_count
+    }
+    
+    /// 所有记录已上传，标记为已读
+    pub fn mark_all_read(&mut self) {
+        self.unread = [false; 1024];
+        self.unread_count = 0;
+        self.update_watermarks();
+    }
+    
+    /// 取出自上次调用以来的存储事件
+    pub fn take_events<F: FnMut(StorageEvent)>(&mut self, mut on_event: F) {
+        let events = core::mem::take(&mut self.events);
+        let (records, capacity) = (self.record_count, self.records.len());
+        
+        if events.high {
+            on_event(StorageEvent::HighWatermark { records, capacity });
+        }
+        if events.low {
+            on_event(StorageEvent::LowWatermark { records, capacity });
+        }
+        if events.overwrite_imminent {
+            on_event(StorageEvent::OverwriteImminent { unread: self.unread_count, capacity });
+        }
+        if events.overwritten > 0 {
+            on_event(StorageEvent::UnreadOverwritten { count: events.overwritten });
+        }
+    }
+    
+    /// 记录数或未读数变化后检查是否越过水位线
+    fn update_watermarks(&mut self) {
+        let capacity = self.records.len();
+        let percent = self.record_count * 100 / capacity;
+        
+        if !self.above_high && percent >= self.watermarks.high_percent as usize {
+            self.above_high = true;
+            self.events.high = true;
+            self.events.low = false;
+        } else if self.above_high && percent < self.watermarks.low_percent as usize {
+            self.above_high = false;
+            self.events.low = true;
+            self.events.high = false;
+        }
+        
+        // 写入位置循环前进，未读记录几乎占满时再写几条就会覆盖未读记录
+        let imminent = self.unread_count + self.watermarks.overwrite_margin >= capacity;
+        if imminent && !self.overwrite_warned {
+            self.events.overwrite_imminent = true;
+        }
+        self.overwrite_warned = imminent;
+    }
+    
+    /// 移除一个位置上的记录
+    fn remove_at(&mut self, index: usize) {
+        if let Some(record) = self.records[index].take() {
+            self.rollups.remove(&record);
+            self.latest.remove(&record);
+            self.record_count -= 1;
+        }
+        if core::mem::take(&mut self.unread[index]) {
+            self.unread_count -= 1;
         }
     }
     
@@ -32,15 +141,31 @@ impl CircularBuffer {
     /// 添加传感器记录
     fn add_record(&mut self, record: SensorRecord) {
         // 更新记录数
-        if self.records[self.write_position].is_none() {
+        let position = self.write_position;
+        if let Some(overwritten) = self.records[position] {
+            self.rollups.remove(&overwritten);
+            self.latest.remove(&overwritten);
+            metrics::increment(Counter::RecordsOverwritten);
+            if self.unread[position] {
+                self.events.overwritten += 1;
+            }
+        } else {
             self.record_count += 1;
         }
+        if !self.unread[position] {
+            self.unread_count += 1;
+        }
+        metrics::increment(Counter::RecordsStored);
         
         // 写入记录
-        self.records[self.write_position] = Some(record);
+        self.records[position] = Some(record);
+        self.rollups.add(&record);
+        self.latest.add(&record);
+        self.unread[position] = true;
         
         // 更新写入位置
-        self.write_position = (self.write_position + 1) % self.records.len();
+        self.write_position = (position + 1) % self.records.len();
+        self.update_watermarks();
     }
     
     /// 查找指定节点的所有记录
@@ -72,40 +197,6 @@ impl CircularBuffer {
         
         result
     }
-    
-    /// 序列化传感器记录
-    fn serialize_records(&self, records: &[SensorRecord]) -> Vec<u8> {
-        let mut result = Vec::with_capacity(records.len() * 20);
-        
-        for record in records {
-            // 记录格式：
-            // 节点ID (6字节)
-            // 时间戳 (8字节)
-            // 温度 (2字节，定点数，乘以100)
-            // 湿度 (2字节，定点数，乘以100)
-            // 气压 (2字节，百帕单位)
-            
-            // 添加节点ID
-            result.extend_from_slice(&record.node_id.0);
-            
-            // 添加时间戳
-            result.extend_from_slice(&record.timestamp.to_be_bytes());
-            
-            // 添加温度
-            let temp = (record.temperature * 100.0) as u16;
-            result.extend_from_slice(&temp.to_be_bytes());
-            
-            // 添加湿度
-            let humidity = (record.humidity * 100.0) as u16;
-            result.extend_from_slice(&humidity.to_be_bytes());
-            
-            // 添加气压
-            let pressure = (record.pressure / 100.0) as u16; // 转换为百帕
-            result.extend_from_slice(&pressure.to_be_bytes());
-        }
-        
-        result
-    }
 }
 
 impl Storage for CircularBuffer {
@@ -126,12 +217,22 @@ impl Storage for CircularBuffer {
         self.timestamp += 1000;
     }
     
+    fn add_data_at(&mut self, node_id: NodeId, timestamp: u64, temperature: f32, humidity: f32, pressure: f32) {
+        self.add_record(SensorRecord {
+            node_id,
+            timestamp,
+            temperature,
+            humidity,
+            pressure,
+        });
+    }
+    
     fn get_data_for_node(&self, node_id: NodeId) -> Vec<u8> {
         // 查找记录
         let records = self.find_records_for_node(node_id);
         
         // 序列化记录
-        self.serialize_records(&records)
+        serialize_records(&records)
     }
     
     fn get_data_in_timerange(&self, start_time: u64, end_time: u64) -> Vec<u8> {
@@ -139,25 +240,92 @@ impl Storage for CircularBuffer {
         let records = self.find_records_in_timerange(start_time, end_time);
         
         // 序列化记录
-        self.serialize_records(&records)
+        serialize_records(&records)
+    }
+    
+    fn query(&self, node_id: NodeId, params: &QueryParams) -> Vec<u8> {
+        // 按时间戳排序的记录即时间索引，覆盖写入的环形缓冲区中记录不一定按时间排列
+        let mut records: Vec<SensorRecord> = self.find_records_for_node(node_id).into_iter()
+            .filter(|r| r.timestamp >= params.start_time && r.timestamp <= params.end_time)
+            .collect();
+        records.sort_by_key(|r| r.timestamp);
+        
+        let records = downsample(&records, params.downsample);
+        serialize_records(&records)
     }
     
     fn clear_data_for_node(&mut self, node_id: NodeId) {
-        for record in self.records.iter_mut() {
-            if let Some(r) = record {
-                if r.node_id == node_id {
-                    *record = None;
-                    self.record_count -= 1;
-                }
+        for index in 0..self.records.len() {
+            if matches!(self.records[index], Some(r) if r.node_id == node_id) {
+                self.remove_at(index);
             }
         }
+        self.update_watermarks();
     }
     
     fn clear_all_data(&mut self) {
         for record in self.records.iter_mut() {
             *record = None;
         }
+        self.unread = [false; 1024];
         self.record_count = 0;
+        self.unread_count = 0;
         self.write_position = 0;
+        self.rollups.clear();
+        self.latest.clear();
+        self.update_watermarks();
+    }
+    
+    fn record_count(&self) -> usize {
+        self.record_count
+    }
+    
+    fn capacity(&self) -> usize {
+        self.records.len()
+    }
+    
+    fn count_records_by_node(&self, out: &mut [(NodeId, u16)]) -> usize {
+        let mut node_count = 0;
+        
+        for record in self.records.iter().flatten() {
+            if let Some(entry) = out[..node_count].iter_mut().find(|(id, _)| *id == record.node_id) {
+                entry.1 = entry.1.saturating_add(1);
+            } else if node_count < out.len() {
+                out[node_count] = (record.node_id, 1);
+                node_count += 1;
+            }
+        }
+        
+        node_count
+    }
+    
+    fn evict_older_than<F: FnMut(&SensorRecord)>(&mut self, cutoff: u64, mut on_evict: F) -> usize {
+        let mut evicted = 0;
+        
+        for index in 0..self.records.len() {
+            if let Some(r) = self.records[index] {
+                if r.timestamp < cutoff {
+                    on_evict(&r);
+                    self.remove_at(index);
+                    evicted += 1;
+                }
+            }
+        }
+        
+        metrics::add(Counter::RecordsEvicted, evicted as u32);
+        self.update_watermarks();
+        evicted
+    }
+    
+    fn for_each_record<F: FnMut(&SensorRecord)>(&self, f: F) {
+        self.records.iter().flatten().for_each(f);
+    }
+    
+    fn rollups(&self) -> &Rollups {
+        &self.rollups
+    }
+    
+    fn latest_records(&self) -> &LatestRecords {
+        &self.latest
     }
 } 
\ No newline at end of file
@@ -0,0 +1,76 @@
+use common::protocol::NodeId;
+
+/// 最近上传缓存的条目数
+pub const DEDUP_CACHE_SIZE: usize = 32;
+
+/// 缓存条目的保留时间，覆盖客户端全部重传的时间跨度（毫秒）
+pub const DEDUP_WINDOW_MS: u64 = 60_000;
+
+/// 时间戳容差：批量样本的时间戳按到达时刻推算，晚到的重传推算出的时间戳也晚（毫秒）
+pub const DEDUP_TIMESTAMP_TOLERANCE_MS: u64 = 20_000;
+
+/// 一次上传的标识
+#[derive(Debug, Clone, Copy)]
+struct UploadKey {
+    source: NodeId,
+    packet_id: u16,
+    /// 上传中第一个样本的时间戳
+    timestamp: u64,
+    /// 收到的时间，用于过期
+    received_at: u64,
+}
+
+/// 上传去重，丢弃确认丢失后重传的重复上传，避免同一批样本被存储两次
+///
+/// 包ID在客户端重启后从头分配，只凭包ID会误判，所以同时比较样本时间戳。
+pub struct UploadDedup {
+    recent: [Option<UploadKey>; DEDUP_CACHE_SIZE],
+    /// 缓存已满时下一个被覆盖的位置
+    next: usize,
+}
+
+impl UploadDedup {
+    /// 创建空的去重缓存
+    pub fn new() -> Self {
+        Self {
+            recent: [None; DEDUP_CACHE_SIZE],
+            next: 0,
+        }
+    }
+    
+    /// 检查上传是否是最近收到过的重传，不是时记入缓存
+    ///
+    /// 包ID为0的上传不需要确认也不会重传，总是视为新上传。
+    pub fn is_duplicate(&mut self, source: NodeId, packet_id: u16, timestamp: u64, now: u64) -> bool {
+        if packet_id == 0 {
+            return false;
+        }
+        
+        for entry in self.recent.iter_mut() {
+            if matches!(entry, Some(key) if now.saturating_sub(key.received_at) > DEDUP_WINDOW_MS) {
+                *entry = None;
+            }
+        }
+        
+        let duplicate = self.recent.iter().flatten().any(|key| {
+            key.source == source
+                && key.packet_id == packet_id
+                && key.timestamp.abs_diff(timestamp) <= DEDUP_TIMESTAMP_TOLERANCE_MS
+        });
+        if duplicate {
+            return true;
+        }
+        
+        let index = match self.recent.iter().position(|entry| entry.is_none()) {
+            Some(index) => index,
+            None => {
+                let index = self.next;
+                self.next = (self.next + 1) % DEDUP_CACHE_SIZE;
+                index
+            },
+        };
+        self.recent[index] = Some(UploadKey { source, packet_id, timestamp, received_at: now });
+        
+        false
+    }
+}
\ No newline at end of file
@@ -0,0 +1,498 @@
+use common::hal::FlashStorage;
+use common::metrics::{self, Counter};
+use common::protocol::NodeId;
+use common::protocol::command::QueryParams;
+use common::warn;
+use crate::storage::{downsample, serialize_records, SensorRecord, Storage, StorageEvent};
+use crate::storage::aggregate::Rollups;
+use crate::storage::latest::LatestRecords;
+use crate::storage::retention::RecordArchive;
+
+/// 扇区头中的标记，擦除后的扇区头为全0xFF
+const SECTOR_MAGIC: u32 = 0x4E4C_4F47;
+
+/// 扇区头长度：标记(4) 扇区序号(4)
+const SECTOR_HEADER_LEN: usize = 8;
+
+/// 每条记录占用的长度：状态(1) 节点ID(6) 时间戳(8) 温度(4) 湿度(4) 气压(4)，补齐到32字节
+const RECORD_LEN: usize = 32;
+
+/// 记录状态，只能由擦除值逐步清零：空闲、有效、已删除
+const RECORD_FREE: u8 = 0xFF;
+const RECORD_VALID: u8 = 0x7F;
+const RECORD_DELETED: u8 = 0x00;
+
+/// 打开日志时读取扇区数的尝试次数，SDK忙时重试
+const OPEN_ATTEMPTS: usize = 8;
+
+/// 记录在日志中的位置
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    sector: usize,
+    index: usize,
+    /// 全局位置：扇区序号乘以每扇区记录数加扇区内位置，越大写入越晚
+    position: u64,
+}
+
+/// 追加写入闪存数据分区的传感器日志，断电重启后历史数据仍在
+///
+/// 记录按顺序写满一个扇区后转到下一个扇区，扇区循环使用；写满整个分区后擦除最旧的扇区，
+/// 各扇区的擦除次数保持一致。扇区头中的序号逐个递增，打开时从序号最大的扇区继续写入，
+/// 不会每次从第一个扇区开始而集中磨损它。删除记录只把状态清零，扇区轮换时才真正擦除。
+pub struct FlashLog<F: FlashStorage> {
+    flash: F,
+    sector_size: usize,
+    sectors: usize,
+    /// 每个扇区的记录数
+    records_per_sector: usize,
+    /// 正在写入的扇区
+    current_sector: usize,
+    /// 正在写入的扇区的序号
+    current_sequence: u32,
+    /// 正在写入的扇区中下一条记录的位置
+    write_index: usize,
+    /// 有效记录数
+    record_count: usize,
+    /// 未读记录数
+    unread_count: usize,
+    /// 全局位置不小于此值的记录尚未读取（上传）
+    read_mark: u64,
+    /// 全局时间戳，用于给记录分配时间戳
+    timestamp: u64,
+    /// 是否已报告未读记录即将被擦除
+    overwrite_warned: bool,
+    /// 尚未取出的未读记录即将被擦除事件
+    overwrite_imminent: bool,
+    /// 自上次取出事件以来被擦除的未读记录数
+    overwritten: usize,
+    /// 聚合查询使用的分钟汇总，打开时由已有记录重建
+    rollups: Rollups,
+    /// 各节点的最新记录，打开时由已有记录重建
+    latest: LatestRecords,
+}
+
+impl<F: FlashStorage> FlashLog<F> {
+    /// 打开数据分区上的日志并恢复已有的记录，分区中没有日志时从第一个扇区开始
+    ///
+    /// 恢复的记录都视为未读。读取扇区数失败时重试，仍然失败或分区为空时日志容量为0，丢弃所有记录。
+    pub fn open(flash: F) -> Self {
+        let sector_size = flash.sector_size();
+        let sectors = (0..OPEN_ATTEMPTS)
+            .find_map(|_| flash.sector_count().ok())
+            .unwrap_or(0);
+        let mut log = Self {
+            flash,
+            sector_size,
+            sectors,
+            records_per_sector: sector_size.saturating_sub(SECTOR_HEADER_LEN) / RECORD_LEN,
+            current_sector: 0,
+            current_sequence: 0,
+            write_index: 0,
+            record_count: 0,
+            unread_count: 0,
+            read_mark: 0,
+            timestamp: 0,
+            overwrite_warned: false,
+            overwrite_imminent: false,
+            overwritten: 0,
+            rollups: Rollups::new(),
+            latest: LatestRecords::new(),
+        };
+        if log.capacity() == 0 {
+            warn!("闪存数据分区容量为0，传感器记录将被丢弃");
+            return log;
+        }
+        
+        // 序号最大的扇区是最后写入的
+        let newest = (0..sectors)
+            .filter_map(|sector| log.sector_sequence(sector).map(|sequence| (sector, sequence)))
+            .max_by_key(|&(_, sequence)| sequence);
+        match newest {
+            Some((sector, sequence)) => {
+                log.current_sector = sector;
+                log.current_sequence = sequence;
+                log.write_index = log.find_write_index();
+            },
+            None => {
+                if log.start_sector(0, 0).is_err() {
+                    warn!("初始化闪存日志失败");
+                }
+            },
+        }
+        
+        let mut count = 0;
+        let mut rollups = Rollups::new();
+        let mut latest = LatestRecords::new();
+        log.for_each_valid(|_, record| {
+            count += 1;
+            rollups.add(&record);
+            latest.add(&record);
+        });
+        log.record_count = count;
+        log.rollups = rollups;
+        log.latest = latest;
+        log.unread_count = count;
+        log.update_overwrite_warning();
+        log
+    }
+    
+    /// 未读记录数
+    pub fn unread_count(&self) -> usize {
+        self.unread_count
+    }
+    
+    /// 所有记录已上传，标记为已读
+    pub fn mark_all_read(&mut self) {
+        self.read_mark = self.position(self.current_sequence, self.write_index);
+        self.unread_count = 0;
+        self.update_overwrite_warning();
+    }
+    
+    /// 更新内部时间戳
+    pub fn update_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = timestamp;
+    }
+    
+    /// 取出自上次调用以来的存储事件
+    ///
+    /// 日志写满后占用始终接近容量，只报告未读记录即将被擦除和已被擦除，不报告高低水位。
+    pub fn take_events<G: FnMut(StorageEvent)>(&mut self, mut on_event: G) {
+        if core::mem::take(&mut self.overwrite_imminent) {
+            on_event(StorageEvent::OverwriteImminent { unread: self.unread_count, capacity: self.capacity() });
+        }
+        let overwritten = core::mem::take(&mut self.overwritten);
+        if overwritten > 0 {
+            on_event(StorageEvent::UnreadOverwritten { count: overwritten });
+        }
+    }
+    
+    /// 追加一条记录，当前扇区写满时轮换到下一个扇区，返回是否写入
+    fn add_record(&mut self, record: SensorRecord) -> bool {
+        if self.capacity() == 0 {
+            return false;
+        }
+        if self.write_index >= self.records_per_sector && !self.advance() {
+            return false;
+        }
+        
+        let mut bytes = [0xFF; RECORD_LEN];
+        bytes[1..7].copy_from_slice(&record.node_id.0);
+        bytes[7..15].copy_from_slice(&record.timestamp.to_be_bytes());
+        bytes[15..19].copy_from_slice(&record.temperature.to_be_bytes());
+        bytes[19..23].copy_from_slice(&record.humidity.to_be_bytes());
+        bytes[23..27].copy_from_slice(&record.pressure.to_be_bytes());
+        
+        // 先写内容再写状态，写入中途断电的记录状态仍为空闲，打开时跳过
+        let offset = self.record_offset(self.current_sector, self.write_index);
+        self.write_index += 1;
+        if self.flash.flash_write(offset, &bytes).and_then(|_| self.flash.flash_write(offset, &[RECORD_VALID])).is_err() {
+            warn!("写入闪存日志失败");
+            return false;
+        }
+        
+        self.record_count += 1;
+        self.unread_count += 1;
+        self.rollups.add(&record);
+        self.latest.add(&record);
+        metrics::increment(Counter::RecordsStored);
+        self.update_overwrite_warning();
+        true
+    }
+    
+    /// 擦除最旧的扇区作为新的写入扇区，其中的记录随之丢弃
+    fn advance(&mut self) -> bool {
+        let next = (self.current_sector + 1) % self.sectors;
+        let mut dropped = 0;
+        let mut records = Vec::new();
+        let mut unread = 0;
+        self.scan_sector(next, |slot, state| {
+            if state == RECORD_VALID {
+                dropped += 1;
+                records.extend(self.read_record(slot));
+                if slot.position >= self.read_mark {
+                    unread += 1;
+                }
+            }
+        });
+        
+        if self.start_sector(next, self.current_sequence.wrapping_add(1)).is_err() {
+            warn!("擦除闪存日志扇区 {} 失败", next);
+            return false;
+        }
+        
+        for record in records.iter() {
+            self.rollups.remove(record);
+            self.latest.remove(record);
+        }
+        self.record_count -= dropped;
+        self.unread_count -= unread;
+        self.overwritten += unread;
+        metrics::add(Counter::RecordsOverwritten, dropped as u32);
+        true
+    }
+    
+    /// 擦除扇区并写入扇区头，之后从该扇区开头写入
+    fn start_sector(&mut self, sector: usize, sequence: u32) -> Result<(), F::Error> {
+        self.flash.erase_sector(sector)?;
+        let mut header = [0u8; SECTOR_HEADER_LEN];
+        header[0..4].copy_from_slice(&SECTOR_MAGIC.to_be_bytes());
+        header[4..8].copy_from_slice(&sequence.to_be_bytes());
+        self.flash.flash_write((sector * self.sector_size) as u32, &header)?;
+        
+        self.current_sector = sector;
+        self.current_sequence = sequence;
+        self.write_index = 0;
+        Ok(())
+    }
+    
+    /// 扇区的序号，扇区头无效时返回None
+    fn sector_sequence(&self, sector: usize) -> Option<u32> {
+        let mut header = [0u8; SECTOR_HEADER_LEN];
+        self.flash.flash_read((sector * self.sector_size) as u32, &mut header).ok()?;
+        if u32::from_be_bytes([header[0], header[1], header[2], header[3]]) != SECTOR_MAGIC {
+            return None;
+        }
+        Some(u32::from_be_bytes([header[4], header[5], header[6], header[7]]))
+    }
+    
+    /// 打开时在当前扇区中找到第一个完全空闲的位置，写入中途断电留下的记录标记为已删除
+    fn find_write_index(&mut self) -> usize {
+        for index in 0..self.records_per_sector {
+            let offset = self.record_offset(self.current_sector, index);
+            let mut bytes = [0u8; RECORD_LEN];
+            if self.flash.flash_read(offset, &mut bytes).is_err() || bytes[0] != RECORD_FREE {
+                continue;
+            }
+            if bytes.iter().all(|&byte| byte == 0xFF) {
+                return index;
+            }
+            let _ = self.flash.flash_write(offset, &[RECORD_DELETED]);
+        }
+        self.records_per_sector
+    }
+    
+    /// 记录在分区中的偏移
+    fn record_offset(&self, sector: usize, index: usize) -> u32 {
+        (sector * self.sector_size + SECTOR_HEADER_LEN + index * RECORD_LEN) as u32
+    }
+    
+    /// 记录的全局位置
+    fn position(&self, sequence: u32, index: usize) -> u64 {
+        sequence as u64 * self.records_per_sector as u64 + index as u64
+    }
+    
+    /// 依次访问扇区中已写入的记录及其状态，扇区头无效时不访问
+    fn scan_sector<G: FnMut(Slot, u8)>(&self, sector: usize, mut f: G) {
+        let sequence = match self.sector_sequence(sector) {
+            Some(sequence) => sequence,
+            None => return,
+        };
+        let written = if sector == self.current_sector { self.write_index } else { self.records_per_sector };
+        for index in 0..written {
+            let mut state = [0u8; 1];
+            if self.flash.flash_read(self.record_offset(sector, index), &mut state).is_ok() {
+                f(Slot { sector, index, position: self.position(sequence, index) }, state[0]);
+            }
+        }
+    }
+    
+    /// 读取一个位置上的记录内容，不检查状态
+    fn read_record(&self, slot: Slot) -> Option<SensorRecord> {
+        let mut bytes = [0u8; RECORD_LEN];
+        self.flash.flash_read(self.record_offset(slot.sector, slot.index), &mut bytes).ok()?;
+        let field = |offset: usize| [bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]];
+        let mut node_id = [0u8; 6];
+        node_id.copy_from_slice(&bytes[1..7]);
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&bytes[7..15]);
+        Some(SensorRecord {
+            node_id: NodeId(node_id),
+            timestamp: u64::from_be_bytes(timestamp),
+            temperature: f32::from_be_bytes(field(15)),
+            humidity: f32::from_be_bytes(field(19)),
+            pressure: f32::from_be_bytes(field(23)),
+        })
+    }
+    
+    /// 从最旧到最新依次访问有效记录
+    fn for_each_valid<G: FnMut(Slot, SensorRecord)>(&self, mut f: G) {
+        if self.capacity() == 0 {
+            return;
+        }
+        
+        for step in 1..=self.sectors {
+            let sector = (self.current_sector + step) % self.sectors;
+            self.scan_sector(sector, |slot, state| {
+                if state != RECORD_VALID {
+                    return;
+                }
+                if let Some(record) = self.read_record(slot) {
+                    f(slot, record);
+                }
+            });
+        }
+    }
+    
+    /// 收集满足条件的记录
+    fn find_records<P: FnMut(&SensorRecord) -> bool>(&self, mut predicate: P) -> Vec<SensorRecord> {
+        let mut result = Vec::new();
+        self.for_each_valid(|_, record| {
+            if predicate(&record) {
+                result.push(record);
+            }
+        });
+        result
+    }
+    
+    /// 删除满足条件的记录，每条被删除的记录先交给on_remove，返回删除数量
+    fn remove_where<P: FnMut(&SensorRecord) -> bool, G: FnMut(&SensorRecord)>(&mut self, mut predicate: P, mut on_remove: G) -> usize {
+        let mut slots = Vec::new();
+        self.for_each_valid(|slot, record| {
+            if predicate(&record) {
+                on_remove(&record);
+                slots.push((slot, record));
+            }
+        });
+        
+        for (slot, record) in slots.iter() {
+            if self.flash.flash_write(self.record_offset(slot.sector, slot.index), &[RECORD_DELETED]).is_err() {
+                warn!("删除闪存日志记录失败");
+            }
+            self.rollups.remove(record);
+            self.latest.remove(record);
+            self.record_count -= 1;
+            if slot.position >= self.read_mark {
+                self.unread_count -= 1;
+            }
+        }
+        
+        self.update_overwrite_warning();
+        slots.len()
+    }
+    
+    /// 下一次轮换要擦除的扇区中还有未读记录时提前报告
+    fn update_overwrite_warning(&mut self) {
+        let imminent = self.unread_count > 0 && self.unread_count + self.records_per_sector >= self.capacity();
+        if imminent && !self.overwrite_warned {
+            self.overwrite_imminent = true;
+        }
+        self.overwrite_warned = imminent;
+    }
+}
+
+impl<F: FlashStorage> Storage for FlashLog<F> {
+    fn add_data(&mut self, node_id: NodeId, temperature: f32, humidity: f32, pressure: f32) {
+        self.add_record(SensorRecord {
+            node_id,
+            timestamp: self.timestamp,
+            temperature,
+            humidity,
+            pressure,
+        });
+        
+        // 与环形缓冲区一致，每条记录时间戳加1秒
+        self.timestamp += 1000;
+    }
+    
+    fn add_data_at(&mut self, node_id: NodeId, timestamp: u64, temperature: f32, humidity: f32, pressure: f32) {
+        self.add_record(SensorRecord {
+            node_id,
+            timestamp,
+            temperature,
+            humidity,
+            pressure,
+        });
+    }
+    
+    fn get_data_for_node(&self, node_id: NodeId) -> Vec<u8> {
+        serialize_records(&self.find_records(|record| record.node_id == node_id))
+    }
+    
+    fn get_data_in_timerange(&self, start_time: u64, end_time: u64) -> Vec<u8> {
+        serialize_records(&self.find_records(|record| record.timestamp >= start_time && record.timestamp <= end_time))
+    }
+    
+    fn query(&self, node_id: NodeId, params: &QueryParams) -> Vec<u8> {
+        // 日志按写入顺序排列，补传的历史样本不一定按时间排列
+        let mut records = self.find_records(|r| {
+            r.node_id == node_id && r.timestamp >= params.start_time && r.timestamp <= params.end_time
+        });
+        records.sort_by_key(|r| r.timestamp);
+        
+        serialize_records(&downsample(&records, params.downsample))
+    }
+    
+    fn clear_data_for_node(&mut self, node_id: NodeId) {
+        self.remove_where(|record| record.node_id == node_id, |_| {});
+    }
+    
+    fn clear_all_data(&mut self) {
+        // 从下一个扇区重新开始，保持扇区轮换的顺序
+        if self.capacity() == 0 {
+            return;
+        }
+        let next = (self.current_sector + 1) % self.sectors;
+        for sector in 0..self.sectors {
+            if sector != next && self.sector_sequence(sector).is_some() && self.flash.erase_sector(sector).is_err() {
+                warn!("擦除闪存日志扇区 {} 失败", sector);
+            }
+        }
+        if self.start_sector(next, self.current_sequence.wrapping_add(1)).is_err() {
+            warn!("擦除闪存日志扇区 {} 失败", next);
+        }
+        
+        self.record_count = 0;
+        self.unread_count = 0;
+        self.rollups.clear();
+        self.latest.clear();
+        self.read_mark = self.position(self.current_sequence, self.write_index);
+        self.update_overwrite_warning();
+    }
+    
+    fn record_count(&self) -> usize {
+        self.record_count
+    }
+    
+    fn capacity(&self) -> usize {
+        self.sectors * self.records_per_sector
+    }
+    
+    fn count_records_by_node(&self, out: &mut [(NodeId, u16)]) -> usize {
+        let mut node_count = 0;
+        
+        self.for_each_valid(|_, record| {
+            if let Some(entry) = out[..node_count].iter_mut().find(|(id, _)| *id == record.node_id) {
+                entry.1 = entry.1.saturating_add(1);
+            } else if node_count < out.len() {
+                out[node_count] = (record.node_id, 1);
+                node_count += 1;
+            }
+        });
+        
+        node_count
+    }
+    
+    fn evict_older_than<G: FnMut(&SensorRecord)>(&mut self, cutoff: u64, on_evict: G) -> usize {
+        let evicted = self.remove_where(|record| record.timestamp < cutoff, on_evict);
+        metrics::add(Counter::RecordsEvicted, evicted as u32);
+        evicted
+    }
+    
+    fn for_each_record<G: FnMut(&SensorRecord)>(&self, mut f: G) {
+        self.for_each_valid(|_, record| f(&record));
+    }
+    
+    fn rollups(&self) -> &Rollups {
+        &self.rollups
+    }
+    
+    fn latest_records(&self) -> &LatestRecords {
+        &self.latest
+    }
+}
+
+impl<F: FlashStorage> RecordArchive for FlashLog<F> {
+    fn archive_record(&mut self, record: &SensorRecord) -> bool {
+        self.add_record(*record)
+    }
+}
\ No newline at end of file
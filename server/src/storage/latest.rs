@@ -0,0 +1,91 @@
+use common::protocol::NodeId;
+use crate::storage::{SensorRecord, Storage};
+
+/// 一个节点的最新记录和该节点在存储中的记录数
+#[derive(Debug, Clone, Copy)]
+struct LatestEntry {
+    record: SensorRecord,
+    count: u32,
+    /// 最新记录已被移除，查询时重新扫描这个节点
+    stale: bool,
+}
+
+/// 按节点增量维护的最新记录索引，查询最新记录时不必扫描整个存储
+///
+/// 与分钟汇总一样在存储写入和移除记录时同步更新。移除的正是某个节点的最新记录时只标记该节点，查询时扫描存储找出新的最新记录。
+#[derive(Debug, Clone, Default)]
+pub struct LatestRecords {
+    /// 按节点ID排序
+    entries: Vec<LatestEntry>,
+}
+
+impl LatestRecords {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    fn find(&self, node_id: NodeId) -> Result<usize, usize> {
+        self.entries.binary_search_by_key(&node_id.0, |entry| entry.record.node_id.0)
+    }
+    
+    /// 记录写入存储后调用
+    pub fn add(&mut self, record: &SensorRecord) {
+        match self.find(record.node_id) {
+            Ok(index) => {
+                let entry = &mut self.entries[index];
+                entry.count += 1;
+                // 比已移除的最新记录还新的记录一定是最新的
+                if record.timestamp >= entry.record.timestamp {
+                    entry.record = *record;
+                    entry.stale = false;
+                }
+            },
+            Err(index) => self.entries.insert(index, LatestEntry { record: *record, count: 1, stale: false }),
+        }
+    }
+    
+    /// 记录从存储移除后调用
+    pub fn remove(&mut self, record: &SensorRecord) {
+        if let Ok(index) = self.find(record.node_id) {
+            let entry = &mut self.entries[index];
+            entry.count -= 1;
+            if entry.count == 0 {
+                self.entries.remove(index);
+            } else if record.timestamp >= entry.record.timestamp {
+                entry.stale = true;
+            }
+        }
+    }
+    
+    /// 存储清空后调用
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// 每个节点最新的一条记录，按节点ID排序，node_id为None时包含所有节点
+///
+/// 只有最新记录被移除过的节点才扫描存储。
+pub fn latest<S: Storage + ?Sized>(storage: &S, node_id: Option<NodeId>) -> Vec<SensorRecord> {
+    let index = storage.latest_records();
+    let mut result: Vec<LatestEntry> = index.entries.iter()
+        .filter(|entry| !matches!(node_id, Some(id) if id != entry.record.node_id))
+        .copied()
+        .collect();
+    
+    if result.iter().any(|entry| entry.stale) {
+        for entry in result.iter_mut().filter(|entry| entry.stale) {
+            entry.record.timestamp = 0;
+        }
+        storage.for_each_record(|record| {
+            if let Ok(position) = result.binary_search_by_key(&record.node_id.0, |entry| entry.record.node_id.0) {
+                let entry = &mut result[position];
+                if entry.stale && record.timestamp >= entry.record.timestamp {
+                    entry.record = *record;
+                }
+            }
+        });
+    }
+    
+    result.iter().map(|entry| entry.record).collect()
+}
\ No newline at end of file
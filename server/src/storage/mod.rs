@@ -1,13 +1,28 @@
+pub mod aggregate;
+pub mod circular_buffer;
+pub mod dedup;
+pub mod flash_log;
+pub mod latest;
+pub mod retention;
+
+use common::protocol::{NetworkPacket, NodeId};
+use common::protocol::command::{AggregateBucket, AggregateParams, Downsample, QueryParams};
+use crate::storage::aggregate::Rollups;
+use crate::storage::latest::LatestRecords;
+
 pub struct StorageEngine {
     dma_channel: DmaChannel,
     buffer: AlignedBuffer<[u8; 4096]>,
+    /// 待写入的包的线上格式，DMA传输期间保持有效
+    staging: [u8; NetworkPacket::SIZE],
 }
 
 impl StorageEngine {
-    /// DMA零拷贝写入
+    /// 按线上格式序列化后经DMA写入，存储内容与主机字节序无关
     pub fn store_packet(&mut self, packet: &NetworkPacket) {
         // 配置DMA源地址
-        let src_ptr = packet.as_bytes().as_ptr() as u32;
+        self.staging = packet.to_bytes();
+        let src_ptr = self.staging.as_ptr() as u32;
         
         // 获取当前写入位置
         let offset = self.next_offset();
@@ -17,7 +32,7 @@ impl StorageEngine {
             self.dma_channel.configure(
                 src_ptr,
                 self.buffer.as_ptr() as u32 + offset,
-                packet.as_bytes().len() as u32,
+                NetworkPacket::SIZE as u32,
                 || {
                     // 传输完成回调
                     self.update_index();
@@ -26,4 +41,174 @@ impl StorageEngine {
             self.dma_channel.enable();
         }
     }
+}
+
+/// 传感器记录
+#[derive(Debug, Clone, Copy)]
+pub struct SensorRecord {
+    /// 来源节点ID
+    pub node_id: NodeId,
+    /// 记录时间戳（毫秒）
+    pub timestamp: u64,
+    /// 温度 (°C)
+    pub temperature: f32,
+    /// 湿度 (%)
+    pub humidity: f32,
+    /// 气压 (Pa)
+    pub pressure: f32,
+}
+
+/// 存储占用的水位线
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watermarks {
+    /// 占用升至此百分比时报告高水位
+    pub high_percent: u8,
+    /// 回落到此百分比以下才解除高水位，避免在阈值附近反复报告
+    pub low_percent: u8,
+    /// 未读记录只剩这么多空位就会被覆盖时提前报告
+    pub overwrite_margin: usize,
+}
+
+impl Default for Watermarks {
+    fn default() -> Self {
+        Self {
+            high_percent: 80,
+            low_percent: 60,
+            overwrite_margin: 64,
+        }
+    }
+}
+
+/// 存储占用事件，由主循环取出后决定上传数据或报警
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageEvent {
+    /// 占用升至高水位
+    HighWatermark { records: usize, capacity: usize },
+    /// 占用回落到低水位以下
+    LowWatermark { records: usize, capacity: usize },
+    /// 未读记录即将开始被覆盖
+    OverwriteImminent { unread: usize, capacity: usize },
+    /// 自上次取出事件以来被覆盖的未读记录数
+    UnreadOverwritten { count: usize },
+}
+
+/// 传感器数据存储接口
+pub trait Storage {
+    /// 添加一条传感器数据
+    fn add_data(&mut self, node_id: NodeId, temperature: f32, humidity: f32, pressure: f32);
+    
+    /// 添加一条带指定时间戳的传感器数据，用于批量上传的历史样本
+    fn add_data_at(&mut self, node_id: NodeId, timestamp: u64, temperature: f32, humidity: f32, pressure: f32);
+    
+    /// 获取指定节点的序列化数据
+    fn get_data_for_node(&self, node_id: NodeId) -> Vec<u8>;
+    
+    /// 获取时间范围内的序列化数据
+    fn get_data_in_timerange(&self, start_time: u64, end_time: u64) -> Vec<u8>;
+    
+    /// 按查询参数获取指定节点的序列化数据，记录按时间排序并降采样
+    fn query(&self, node_id: NodeId, params: &QueryParams) -> Vec<u8>;
+    
+    /// 获取每个节点最新一条记录的序列化数据，node_id为None时包含所有节点
+    fn latest(&self, node_id: Option<NodeId>) -> Vec<u8> {
+        serialize_records(&latest::latest(self, node_id))
+    }
+    
+    /// 清空指定节点的数据
+    fn clear_data_for_node(&mut self, node_id: NodeId);
+    
+    /// 清空所有数据
+    fn clear_all_data(&mut self);
+    
+    /// 当前存储的记录数
+    fn record_count(&self) -> usize;
+    
+    /// 最大可存储的记录数
+    fn capacity(&self) -> usize;
+    
+    /// 按节点统计记录数，结果写入out，返回节点数量
+    fn count_records_by_node(&self, out: &mut [(NodeId, u16)]) -> usize;
+    
+    /// 移除时间戳早于cutoff的记录，每条被移除的记录都会先交给on_evict，返回移除数量
+    fn evict_older_than<F: FnMut(&SensorRecord)>(&mut self, cutoff: u64, on_evict: F) -> usize;
+    
+    /// 依次访问所有记录
+    fn for_each_record<F: FnMut(&SensorRecord)>(&self, f: F);
+    
+    /// 随记录写入和移除增量更新的分钟汇总
+    fn rollups(&self) -> &Rollups;
+    
+    /// 随记录写入和移除增量更新的各节点最新记录
+    fn latest_records(&self) -> &LatestRecords;
+    
+    /// 按时间窗口聚合一个节点的测量值，返回各窗口的最小、最大和平均值，没有记录的窗口不返回
+    fn aggregate(&self, params: &AggregateParams) -> Vec<AggregateBucket> {
+        aggregate::aggregate(self, params)
+    }
+}
+
+/// 序列化传感器记录
+pub(crate) fn serialize_records(records: &[SensorRecord]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(records.len() * 20);
+    
+    for record in records {
+        // 记录格式：
+        // 节点ID (6字节)
+        // 时间戳 (8字节)
+        // 温度 (2字节，定点数，乘以100)
+        // 湿度 (2字节，定点数，乘以100)
+        // 气压 (2字节，百帕单位)
+        
+        // 添加节点ID
+        result.extend_from_slice(&record.node_id.0);
+        
+        // 添加时间戳
+        result.extend_from_slice(&record.timestamp.to_be_bytes());
+        
+        // 添加温度
+        let temp = (record.temperature * 100.0) as u16;
+        result.extend_from_slice(&temp.to_be_bytes());
+        
+        // 添加湿度
+        let humidity = (record.humidity * 100.0) as u16;
+        result.extend_from_slice(&humidity.to_be_bytes());
+        
+        // 添加气压
+        let pressure = (record.pressure / 100.0) as u16; // 转换为百帕
+        result.extend_from_slice(&pressure.to_be_bytes());
+    }
+    
+    result
+}
+
+/// 对按时间排序的记录降采样，每个时间段只保留最早的一条
+pub(crate) fn downsample(records: &[SensorRecord], downsample: Downsample) -> Vec<SensorRecord> {
+    let (first, last) = match (records.first(), records.last()) {
+        (Some(first), Some(last)) => (first.timestamp, last.timestamp),
+        _ => return Vec::new(),
+    };
+    
+    // 按分钟降采样时时间段与整分钟对齐，按条数降采样时从第一条记录起等分
+    let (origin, period) = match downsample {
+        Downsample::None => return records.to_vec(),
+        Downsample::PerMinutes(minutes) => (0, (minutes as u64 * 60_000).max(1)),
+        Downsample::MaxRecords(max) => {
+            if records.len() <= max as usize {
+                return records.to_vec();
+            }
+            (first, (last - first + 1).div_ceil((max as u64).max(1)))
+        },
+    };
+    
+    let mut result = Vec::new();
+    let mut last_slot = None;
+    for record in records {
+        let slot = (record.timestamp - origin) / period;
+        if last_slot != Some(slot) {
+            result.push(*record);
+            last_slot = Some(slot);
+        }
+    }
+    
+    result
 }
\ No newline at end of file
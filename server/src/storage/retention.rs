@@ -0,0 +1,131 @@
+use common::{info, warn};
+use crate::storage::{SensorRecord, Storage};
+
+/// 过期记录的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionAction {
+    /// 直接丢弃
+    Drop,
+    /// 归档到Flash后再从内存中移除
+    Archive,
+}
+
+/// 归档目标，例如Flash日志
+pub trait RecordArchive {
+    /// 归档一条记录，成功返回true
+    fn archive_record(&mut self, record: &SensorRecord) -> bool;
+}
+
+/// 按记录年龄执行的保留策略
+pub struct RetentionPolicy {
+    /// 记录最大保留时间（毫秒）
+    max_age_ms: u64,
+    /// 过期记录的处理方式
+    action: RetentionAction,
+    /// 两次检查之间的间隔（毫秒）
+    check_interval_ms: u64,
+    /// 上次检查时间
+    last_run: u64,
+    /// 累计移除的记录数
+    evicted_total: u32,
+    /// 累计归档失败的记录数
+    archive_failures: u32,
+}
+
+impl RetentionPolicy {
+    /// 默认每分钟检查一次
+    pub const DEFAULT_CHECK_INTERVAL_MS: u64 = 60_000;
+    
+    /// 创建新的保留策略
+    pub fn new(max_age_ms: u64, action: RetentionAction) -> Self {
+        Self {
+            max_age_ms,
+            action,
+            check_interval_ms: Self::DEFAULT_CHECK_INTERVAL_MS,
+            last_run: 0,
+            evicted_total: 0,
+            archive_failures: 0,
+        }
+    }
+    
+    /// 设置检查间隔
+    pub fn with_check_interval(mut self, interval_ms: u64) -> Self {
+        self.check_interval_ms = interval_ms;
+        self
+    }
+    
+    /// 修改最大保留时间
+    pub fn set_max_age(&mut self, max_age_ms: u64) {
+        self.max_age_ms = max_age_ms;
+    }
+    
+    /// 获取最大保留时间
+    pub fn max_age(&self) -> u64 {
+        self.max_age_ms
+    }
+    
+    /// 获取累计移除的记录数
+    pub fn evicted_total(&self) -> u32 {
+        self.evicted_total
+    }
+    
+    /// 获取累计归档失败的记录数
+    pub fn archive_failures(&self) -> u32 {
+        self.archive_failures
+    }
+    
+    /// 由主循环定时调用，到达检查间隔时移除过期记录，返回本次移除数量
+    pub fn run<S: Storage>(
+        &mut self,
+        storage: &mut S,
+        archive: Option<&mut dyn RecordArchive>,
+        current_time: u64
+    ) -> usize {
+        if current_time.saturating_sub(self.last_run) < self.check_interval_ms {
+            return 0;
+        }
+        self.last_run = current_time;
+        
+        self.apply(storage, archive, current_time)
+    }
+    
+    /// 立即执行一次保留策略，返回移除数量
+    pub fn apply<S: Storage>(
+        &mut self,
+        storage: &mut S,
+        archive: Option<&mut dyn RecordArchive>,
+        current_time: u64
+    ) -> usize {
+        let cutoff = current_time.saturating_sub(self.max_age_ms);
+        if cutoff == 0 {
+            return 0;
+        }
+        
+        let mut failures: u32 = 0;
+        let evicted = match (self.action, archive) {
+            (RetentionAction::Archive, Some(archive)) => {
+                storage.evict_older_than(cutoff, |record| {
+                    if !archive.archive_record(record) {
+                        failures += 1;
+                    }
+                })
+            },
+            (RetentionAction::Archive, None) => {
+                warn!("未配置归档目标，过期记录将被直接丢弃");
+                storage.evict_older_than(cutoff, |_| {})
+            },
+            (RetentionAction::Drop, _) => storage.evict_older_than(cutoff, |_| {}),
+        };
+        
+        if evicted > 0 {
+            info!("保留策略移除了 {} 条早于 {}ms 的记录", evicted, cutoff);
+        }
+        if failures > 0 {
+            warn!("有 {} 条记录归档失败", failures);
+        }
+        
+        self.evicted_total = self.evicted_total.wrapping_add(evicted as u32);
+        self.archive_failures = self.archive_failures.wrapping_add(failures);
+        evicted
+    }
+}
\ No newline at end of file
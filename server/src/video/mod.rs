@@ -0,0 +1 @@
+pub mod reassembler;
\ No newline at end of file
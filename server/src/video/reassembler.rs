@@ -0,0 +1,87 @@
+use common::protocol::NodeId;
+use common::protocol::data::{flow_id_of, Reassembler};
+use common::protocol::frame::{VideoTier, MAX_VIDEO_PAYLOAD};
+
+/// 同时重组的最大帧数
+pub const MAX_ACTIVE_FRAMES: usize = 4;
+
+/// 未收齐分片的帧的默认超时（毫秒）
+pub const DEFAULT_FRAME_TIMEOUT_MS: u64 = 5000;
+
+/// 已知质量档位时，未收齐的帧最多等待的帧间隔数
+pub const FRAME_TIMEOUT_INTERVALS: u64 = 4;
+
+/// 视频帧重组器，与客户端的FrameSender对应
+///
+/// 视频帧与其他大负载一样由数据包头部的分片字段拆分，缓冲区按最大的视频帧确定。
+pub type FrameReassembler = Reassembler<MAX_VIDEO_PAYLOAD, MAX_ACTIVE_FRAMES>;
+
+/// 客户端通知的会话质量档位
+#[derive(Clone, Copy)]
+struct StreamTier {
+    source: NodeId,
+    service_id: u32,
+    tier: VideoTier,
+}
+
+/// 各视频会话的质量档位，决定该会话未收齐的帧等待多久
+pub struct StreamTiers {
+    /// 收满后覆盖最早记录的会话
+    streams: [Option<StreamTier>; MAX_ACTIVE_FRAMES],
+    next_stream: usize,
+    /// 尚未通知档位的会话使用的超时
+    timeout_ms: u64,
+}
+
+impl StreamTiers {
+    pub fn new(timeout_ms: u64) -> Self {
+        Self {
+            streams: [None; MAX_ACTIVE_FRAMES],
+            next_stream: 0,
+            timeout_ms,
+        }
+    }
+    
+    /// 记录会话切换到的质量档位
+    ///
+    /// 此后该会话新帧的重组超时为若干个帧间隔：高档位尽快放弃残缺帧腾出槽位，低档位给慢速发送留足时间。
+    pub fn set_tier(&mut self, source: NodeId, service_id: u32, tier: VideoTier) {
+        let stream = StreamTier { source, service_id, tier };
+        let existing = self.streams.iter()
+            .position(|entry| matches!(entry, Some(s) if s.source == source && s.service_id == service_id));
+        let index = match existing.or_else(|| self.streams.iter().position(|entry| entry.is_none())) {
+            Some(index) => index,
+            None => {
+                let index = self.next_stream;
+                self.next_stream = (self.next_stream + 1) % MAX_ACTIVE_FRAMES;
+                index
+            },
+        };
+        self.streams[index] = Some(stream);
+    }
+    
+    /// 会话当前的质量档位，客户端尚未通知时返回None
+    pub fn tier(&self, source: NodeId, service_id: u32) -> Option<VideoTier> {
+        self.streams.iter()
+            .flatten()
+            .find(|s| s.source == source && s.service_id == service_id)
+            .map(|s| s.tier)
+    }
+    
+    /// 会话数据的新帧最多等待多久，分片只带流ID，按流ID找到会话
+    pub fn timeout_ms(&self, source: NodeId, flow_id: u16) -> u64 {
+        self.streams.iter()
+            .flatten()
+            .find(|s| s.source == source && flow_id_of(s.service_id) == flow_id)
+            .map_or(self.timeout_ms, |s| s.tier.frame_interval_ms as u64 * FRAME_TIMEOUT_INTERVALS)
+    }
+    
+    /// 会话关闭，丢弃其质量档位
+    pub fn close(&mut self, service_id: u32) {
+        for entry in self.streams.iter_mut() {
+            if matches!(entry, Some(s) if s.service_id == service_id) {
+                *entry = None;
+            }
+        }
+    }
+}
\ No newline at end of file
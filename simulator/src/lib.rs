@@ -0,0 +1,165 @@
+pub mod scenario;
+
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+
+use common::hal::simulator::{LinkImpairment, SimChannel, SimHardware, SimScheduler, SimShutdown};
+use common::info;
+use common::metrics::NodeMetrics;
+use common::protocol::NodeId;
+use scenario::{Action, Expectation, Metric, NodeSpec, Role, Scenario};
+
+/// 节点线程的栈大小，与独立运行时主线程的栈相同，主循环在栈上分配收发缓冲区
+const NODE_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// 在独立线程中运行一个节点的固件主循环，所有节点共享同一个模拟信道和调度器
+///
+/// 节点按调度器的虚拟时间运行，更新的指标计入`metrics`；收到重启命令后主循环返回，与独立运行时一样重新启动节点。
+/// 调度器关闭后节点在下一次延时中退出，线程正常结束。
+pub fn spawn_node(
+    spec: &NodeSpec,
+    channel: SimChannel,
+    scheduler: &SimScheduler,
+    metrics: Arc<NodeMetrics>,
+) -> io::Result<thread::JoinHandle<()>> {
+    let NodeSpec { role, id, position } = *spec;
+    if let Some((x, y)) = position {
+        channel.set_position(id, x, y);
+    }
+    
+    // 线程启动之前登记，虚拟时间不会在节点开始运行之前推进
+    let participant = scheduler.join();
+    let scheduler = scheduler.clone();
+    thread::Builder::new()
+        .name(format!("{:?} {}", role, id))
+        .stack_size(NODE_STACK_SIZE)
+        .spawn(move || {
+            let _participant = participant;
+            metrics.bind();
+            let mut hardware = SimHardware::new(id, channel).with_scheduler(scheduler);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| loop {
+                match role {
+                    Role::Client => client::client_main(&mut hardware),
+                    Role::Forward => forward::forward_main(&mut hardware),
+                    Role::Server => server::server_main(&mut hardware),
+                }
+                
+                if !hardware.take_reset_request() {
+                    break;
+                }
+                info!("节点 {} 重新启动", id);
+            }));
+            
+            // 调度器关闭引起的展开是正常退出，其他panic照常传给等待线程的一方
+            if let Err(payload) = result {
+                if !payload.is::<SimShutdown>() {
+                    panic::resume_unwind(payload);
+                }
+            }
+        })
+}
+
+/// 一项期望的检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Check {
+    pub expectation: Expectation,
+    /// 运行结束时的实际值
+    pub value: u32,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        self.value >= self.expectation.min
+    }
+}
+
+/// 一次场景运行的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub checks: Vec<Check>,
+    /// 信道上各帧的文本导出，场景没有要求抓包时为None
+    pub capture: Option<String>,
+}
+
+impl Outcome {
+    /// 所有期望都满足
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(Check::passed)
+    }
+}
+
+/// 按虚拟时间运行场景并检查期望
+///
+/// 每个节点一个线程，指标按节点分开统计，不受同一进程中其他场景或测试的影响。
+/// 检查完期望后关闭调度器并等待所有节点线程退出，节点的指标随之释放。
+pub fn run(scenario: &Scenario) -> io::Result<Outcome> {
+    let scheduler = SimScheduler::new();
+    let channel = SimChannel::new();
+    channel.set_clock(scheduler.clock());
+    let mut sniffer = scenario.capture.as_ref().map(|_| channel.attach_sniffer());
+    
+    let apply = |action: Action| match action {
+        Action::Move { node, x, y } => channel.set_position(node, x, y),
+        Action::Link { source, receiver, loss } => {
+            channel.set_link(source, receiver, LinkImpairment { loss, ..LinkImpairment::default() });
+        },
+        Action::Noise { channel: radio_channel, energy_dbm } => channel.set_noise(radio_channel, energy_dbm),
+        Action::Connect { a, b } => channel.connect(a, b),
+    };
+    
+    // 启动时的操作在节点运行之前执行，运行场景的线程也是参与者，按之后操作的时间延时
+    let started = scenario.events.partition_point(|event| event.at_ms == 0);
+    for event in &scenario.events[..started] {
+        apply(event.action);
+    }
+    let runner = scheduler.join();
+    let mut nodes: Vec<(NodeId, Arc<NodeMetrics>)> = Vec::new();
+    let mut threads = Vec::new();
+    for spec in &scenario.nodes {
+        let metrics = NodeMetrics::new();
+        match spawn_node(spec, channel.clone(), &scheduler, metrics.clone()) {
+            Ok(thread) => threads.push(thread),
+            Err(e) => {
+                shutdown(&scheduler, threads);
+                return Err(e);
+            },
+        }
+        nodes.push((spec.id, metrics));
+    }
+    
+    for event in scenario.events[started..].iter().take_while(|event| event.at_ms <= scenario.duration_ms) {
+        scheduler.sleep_until(event.at_ms);
+        apply(event.action);
+    }
+    scheduler.sleep_until(scenario.duration_ms);
+    scheduler.stop();
+    drop(runner);
+    
+    let checks = scenario.expectations.iter().map(|&expectation| {
+        let value = match expectation.metric {
+            Metric::Counter { node: Some(node), counter } => nodes.iter()
+                .find(|(id, _)| *id == node)
+                .map_or(0, |(_, metrics)| metrics.get(counter)),
+            Metric::Counter { node: None, counter } => nodes.iter()
+                .fold(0u32, |sum, (_, metrics)| sum.saturating_add(metrics.get(counter))),
+            Metric::Delivery { source, destination } => channel.delivered(source, destination),
+        };
+        Check { expectation, value }
+    }).collect();
+    
+    let capture = sniffer.as_mut().map(|sniffer| sniffer.dump());
+    shutdown(&scheduler, threads);
+    Ok(Outcome { checks, capture })
+}
+
+/// 关闭调度器并等待节点线程退出，节点线程中的panic在这里重新抛出
+fn shutdown(scheduler: &SimScheduler, threads: Vec<thread::JoinHandle<()>>) {
+    scheduler.shutdown();
+    for thread in threads {
+        if let Err(payload) = thread.join() {
+            panic::resume_unwind(payload);
+        }
+    }
+}
\ No newline at end of file
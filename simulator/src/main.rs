@@ -0,0 +1,64 @@
+use std::fs;
+use std::process::ExitCode;
+
+use simulator::scenario::{self, Metric};
+
+const USAGE: &str = "用法: simulator <场景文件>
+  
+  在同一进程中按场景启动多个节点，共享一个模拟信道，按虚拟时间运行指定时长后检查计数器和端到端送达；
+  任一检查不满足时以非零状态退出，可直接用于持续集成";
+
+fn main() -> ExitCode {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        },
+    };
+    
+    let scenario = match fs::read_to_string(&path) {
+        Ok(text) => match scenario::parse(&text) {
+            Ok(scenario) => scenario,
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            },
+        },
+        Err(e) => {
+            eprintln!("读取 {} 失败: {}", path, e);
+            return ExitCode::FAILURE;
+        },
+    };
+    
+    let outcome = match simulator::run(&scenario) {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("启动节点失败: {}", e);
+            return ExitCode::FAILURE;
+        },
+    };
+    println!("{} 个节点运行了 {} 毫秒虚拟时间", scenario.nodes.len(), scenario.duration_ms);
+    
+    if let (Some(path), Some(capture)) = (&scenario.capture, &outcome.capture) {
+        if let Err(e) = fs::write(path, capture) {
+            eprintln!("写入抓包文件 {} 失败: {}", path, e);
+        }
+    }
+    
+    for check in &outcome.checks {
+        let what = match check.expectation.metric {
+            Metric::Counter { node: Some(node), counter } => format!("{} {}", node, counter.key()),
+            Metric::Counter { node: None, counter } => counter.key().to_string(),
+            Metric::Delivery { source, destination } => format!("delivery {} -> {}", source, destination),
+        };
+        println!("{} {} = {}（期望 >= {}）", if check.passed() { "通过" } else { "失败" },
+            what, check.value, check.expectation.min);
+    }
+    
+    if outcome.passed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
\ No newline at end of file
@@ -0,0 +1,237 @@
+use std::fmt;
+
+use common::metrics::Counter;
+use common::protocol::NodeId;
+
+/// 节点角色，决定运行哪个固件的主循环
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Forward,
+    Server,
+}
+
+impl Role {
+    fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "client" => Some(Role::Client),
+            "forward" => Some(Role::Forward),
+            "server" => Some(Role::Server),
+            _ => None,
+        }
+    }
+}
+
+/// 场景中的一个节点
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NodeSpec {
+    pub role: Role,
+    pub id: NodeId,
+    /// 位置（米），没有位置的节点之间不按距离计算信号强度
+    pub position: Option<(f32, f32)>,
+}
+
+/// 运行过程中对信道的操作
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// 移动节点
+    Move { node: NodeId, x: f32, y: f32 },
+    /// 设置从源节点到接收节点的丢包概率，该链路其他损伤参数恢复默认
+    Link { source: NodeId, receiver: NodeId, loss: f32 },
+    /// 设置无线信道上的干扰能量
+    Noise { channel: u8, energy_dbm: i8 },
+    /// 加入一条双向链路，第一次使用后只有加入的链路可达
+    Connect { a: NodeId, b: NodeId },
+}
+
+/// 在指定时间执行的操作
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Event {
+    /// 距启动的时间（毫秒）
+    pub at_ms: u64,
+    pub action: Action,
+}
+
+/// 运行结束时检查的数值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// 一个节点的计数器，None为所有节点的合计
+    Counter { node: Option<NodeId>, counter: Counter },
+    /// 目标节点收到的由源节点发出、以目标节点为最终目标的数据帧数
+    Delivery { source: NodeId, destination: NodeId },
+}
+
+/// 运行结束时检查的数值下限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Expectation {
+    pub metric: Metric,
+    pub min: u32,
+}
+
+/// 一次模拟运行的完整描述
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    pub nodes: Vec<NodeSpec>,
+    /// 按时间排序的操作
+    pub events: Vec<Event>,
+    /// 运行时长（毫秒）
+    pub duration_ms: u64,
+    pub expectations: Vec<Expectation>,
+    /// 运行结束后把信道上的帧以文本形式导出到该文件
+    pub capture: Option<String>,
+}
+
+/// 场景文件格式错误，附带行号，整个文件的问题没有行号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: Option<usize>,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "场景文件第 {} 行: {}", line, self.reason),
+            None => write!(f, "场景文件: {}", self.reason),
+        }
+    }
+}
+
+/// 解析场景文件，每行一条指令，`#`之后为注释
+///
+/// ```text
+/// node <client|forward|server> <节点ID> [x y]
+/// [at <毫秒>] move <节点ID> <x> <y>
+/// [at <毫秒>] link <源节点> <接收节点> <丢包概率>
+/// [at <毫秒>] noise <信道> <dBm>
+/// [at <毫秒>] connect <节点ID> <节点ID>
+/// run <毫秒>
+/// expect [节点ID] <计数器> >= <次数>
+/// expect delivery <源节点> <目标节点> >= <次数>
+/// capture <文件>
+/// ```
+///
+/// 时间都是虚拟时间；期望不带节点ID时检查所有节点的合计。
+pub fn parse(text: &str) -> Result<Scenario, ParseError> {
+    let mut scenario = Scenario {
+        nodes: Vec::new(),
+        events: Vec::new(),
+        duration_ms: 0,
+        expectations: Vec::new(),
+        capture: None,
+    };
+    
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |reason| ParseError { line: Some(line_number), reason };
+        let mut parts = line.split('#').next().unwrap_or("").split_whitespace();
+        
+        let mut at_ms = 0;
+        let mut command = match parts.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        if command == "at" {
+            at_ms = parse_arg(parts.next()).ok_or(error("时间无效"))?;
+            command = parts.next().ok_or(error("缺少操作"))?;
+        }
+        
+        match command {
+            "node" if at_ms == 0 => {
+                let role = parts.next().and_then(Role::from_arg).ok_or(error("角色应为client、forward或server"))?;
+                let id = parse_arg(parts.next()).ok_or(error("节点ID无效"))?;
+                if scenario.nodes.iter().any(|node| node.id == id) {
+                    return Err(error("节点ID重复"));
+                }
+                let position = match parts.next() {
+                    Some(x) => Some((
+                        x.parse().map_err(|_| error("坐标无效"))?,
+                        parse_arg(parts.next()).ok_or(error("坐标无效"))?,
+                    )),
+                    None => None,
+                };
+                scenario.nodes.push(NodeSpec { role, id, position });
+            },
+            "move" => {
+                let node = parse_arg(parts.next()).ok_or(error("节点ID无效"))?;
+                let x = parse_arg(parts.next()).ok_or(error("坐标无效"))?;
+                let y = parse_arg(parts.next()).ok_or(error("坐标无效"))?;
+                scenario.events.push(Event { at_ms, action: Action::Move { node, x, y } });
+            },
+            "link" => {
+                let source = parse_arg(parts.next()).ok_or(error("节点ID无效"))?;
+                let receiver = parse_arg(parts.next()).ok_or(error("节点ID无效"))?;
+                let loss = parse_arg(parts.next()).filter(|loss| (0.0..=1.0).contains(loss))
+                    .ok_or(error("丢包概率应在0到1之间"))?;
+                scenario.events.push(Event { at_ms, action: Action::Link { source, receiver, loss } });
+            },
+            "noise" => {
+                let channel = parse_arg(parts.next()).ok_or(error("信道无效"))?;
+                let energy_dbm = parse_arg(parts.next()).ok_or(error("干扰能量无效"))?;
+                scenario.events.push(Event { at_ms, action: Action::Noise { channel, energy_dbm } });
+            },
+            "connect" => {
+                let a = parse_arg(parts.next()).ok_or(error("节点ID无效"))?;
+                let b = parse_arg(parts.next()).ok_or(error("节点ID无效"))?;
+                scenario.events.push(Event { at_ms, action: Action::Connect { a, b } });
+            },
+            "run" if at_ms == 0 => {
+                scenario.duration_ms = parse_arg(parts.next()).ok_or(error("运行时长无效"))?;
+            },
+            "expect" if at_ms == 0 => {
+                let metric = match parts.next().ok_or(error("缺少计数器"))? {
+                    "delivery" => Metric::Delivery {
+                        source: parse_arg(parts.next()).ok_or(error("节点ID无效"))?,
+                        destination: parse_arg(parts.next()).ok_or(error("节点ID无效"))?,
+                    },
+                    key => {
+                        let node = key.parse::<NodeId>().ok();
+                        let key = match node {
+                            Some(_) => parts.next().ok_or(error("缺少计数器"))?,
+                            None => key,
+                        };
+                        let counter = Counter::ALL.iter().copied().find(|counter| counter.key() == key)
+                            .ok_or(error("未知的计数器"))?;
+                        Metric::Counter { node, counter }
+                    },
+                };
+                if parts.next() != Some(">=") {
+                    return Err(error("只支持 >= 比较"));
+                }
+                let min = parse_arg(parts.next()).ok_or(error("次数无效"))?;
+                scenario.expectations.push(Expectation { metric, min });
+            },
+            "capture" if at_ms == 0 => {
+                scenario.capture = Some(parts.next().ok_or(error("缺少文件名"))?.to_string());
+            },
+            _ => return Err(error("未知指令")),
+        }
+        
+        if parts.next().is_some() {
+            return Err(error("多余的参数"));
+        }
+    }
+    
+    if scenario.nodes.is_empty() {
+        return Err(ParseError { line: None, reason: "场景中没有节点" });
+    }
+    if scenario.duration_ms == 0 {
+        return Err(ParseError { line: None, reason: "缺少 run 指令" });
+    }
+    let known = |id: NodeId| scenario.nodes.iter().any(|node| node.id == id);
+    let unknown_node = scenario.expectations.iter().any(|expectation| match expectation.metric {
+        Metric::Counter { node, .. } => node.map_or(false, |id| !known(id)),
+        Metric::Delivery { source, destination } => !known(source) || !known(destination),
+    });
+    if unknown_node {
+        return Err(ParseError { line: None, reason: "期望中的节点不在场景中" });
+    }
+    
+    // 同一时间的操作保持文件中的顺序
+    scenario.events.sort_by_key(|event| event.at_ms);
+    Ok(scenario)
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: Option<&str>) -> Option<T> {
+    arg?.parse().ok()
+}
\ No newline at end of file
@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod scenarios_tests {
+    use std::fs;
+    use std::path::Path;
+    use simulator::scenario;
+    
+    /// 按虚拟时间运行scenarios下的每个场景，任一期望不满足时失败
+    #[test]
+    fn test_scenarios_pass() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        let mut paths: Vec<_> = fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "txt"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+        
+        for path in paths {
+            let text = fs::read_to_string(&path).unwrap();
+            let scenario = scenario::parse(&text).unwrap();
+            let outcome = simulator::run(&scenario).unwrap();
+            for check in &outcome.checks {
+                assert!(check.passed(), "{}: {:?} = {}", path.display(), check.expectation, check.value);
+            }
+        }
+    }
+}
\ No newline at end of file
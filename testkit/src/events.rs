@@ -0,0 +1,71 @@
+use std::fmt::Write as _;
+
+use common::protocol::NodeId;
+use common::protocol::beacon::NodeRole;
+
+/// 虚拟网络运行中发生的事件，时间为虚拟毫秒
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimEvent {
+    /// 节点状态变化：睡眠、电量或信标中声明的角色
+    Node {
+        time: u64,
+        node: NodeId,
+        asleep: bool,
+        battery: u8,
+        role: NodeRole,
+    },
+    /// 信道上发出一帧，广播帧没有目标
+    Tx {
+        time: u64,
+        source: NodeId,
+        destination: Option<NodeId>,
+        beacon: bool,
+        len: usize,
+    },
+    /// 节点信标中的跳数变化
+    Route {
+        time: u64,
+        node: NodeId,
+        hops: u8,
+    },
+}
+
+impl SimEvent {
+    /// 单行JSON，`type`字段区分事件种类
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        let _ = match self {
+            SimEvent::Node { time, node, asleep, battery, role } => write!(
+                out,
+                "{{\"type\":\"node\",\"time\":{},\"node\":\"{}\",\"asleep\":{},\"battery\":{},\"role\":\"{}\"}}",
+                time, node, asleep, battery, role_name(*role)
+            ),
+            SimEvent::Tx { time, source, destination, beacon, len } => {
+                let destination = match destination {
+                    Some(destination) => format!("\"{}\"", destination),
+                    None => "null".into(),
+                };
+                write!(
+                    out,
+                    "{{\"type\":\"tx\",\"time\":{},\"source\":\"{}\",\"destination\":{},\"kind\":\"{}\",\"len\":{}}}",
+                    time, source, destination, if *beacon { "beacon" } else { "data" }, len
+                )
+            },
+            SimEvent::Route { time, node, hops } => write!(
+                out,
+                "{{\"type\":\"route\",\"time\":{},\"node\":\"{}\",\"hops\":{}}}",
+                time, node, hops
+            ),
+        };
+        out
+    }
+}
+
+fn role_name(role: NodeRole) -> &'static str {
+    match role {
+        NodeRole::Unknown => "unknown",
+        NodeRole::Client => "client",
+        NodeRole::Forward => "forward",
+        NodeRole::Server => "server",
+    }
+}
\ No newline at end of file
@@ -0,0 +1,323 @@
+pub mod events;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+use std::collections::VecDeque;
+use std::sync::mpsc;
+
+use common::hal::simulator::{SimChannel, SimClock, SimFrame, SimHardware};
+use common::hal::{Hardware, RadioInterface};
+use common::protocol::{Beacon, NodeId};
+use common::protocol::beacon::NodeRole;
+use common::protocol::data::DataHeader;
+use forward::ForwardLoop;
+use server::ServerLoop;
+
+pub use events::SimEvent;
+
+/// 每步推进的虚拟时间（毫秒）
+pub const TICK_MS: u64 = 10;
+
+/// 节点每步执行的逻辑，参数为节点硬件和当前虚拟时间
+///
+/// 转发节点和服务器用[`VirtualNet::add_forwarder`]和[`VirtualNet::add_server`]运行真实的主循环；
+/// 客户端启动时阻塞等待网络，仍由测试提供与主循环等价的单步处理。
+pub type StepFn = Box<dyn FnMut(&mut SimHardware, u64)>;
+
+/// 事件观察者
+pub type EventFn = Box<dyn FnMut(&SimEvent)>;
+
+/// 断言收到的数据包，负载拷贝出来以便跨步保存
+#[derive(Debug, Clone)]
+pub struct ReceivedPacket {
+    pub header: DataHeader,
+    pub data: Vec<u8>,
+}
+
+impl ReceivedPacket {
+    /// 源节点
+    pub fn source(&self) -> NodeId {
+        NodeId(self.header.source)
+    }
+    
+    /// 目标节点
+    pub fn destination(&self) -> NodeId {
+        NodeId(self.header.destination)
+    }
+}
+
+struct VirtualNode {
+    id: NodeId,
+    hardware: SimHardware,
+    step: Option<StepFn>,
+    /// 已从无线电取出但还没有被断言取走的数据包
+    pending: VecDeque<ReceivedPacket>,
+    /// 上次报告的状态：（睡眠，电量，角色），用于只在变化时产生事件
+    reported: Option<(bool, u8, NodeRole)>,
+    /// 信标中声明的角色和跳数
+    role: NodeRole,
+    hops: Option<u8>,
+}
+
+/// 共享信道和虚拟时钟的多节点网络，节点按加入顺序逐步执行，结果可重复
+pub struct VirtualNet {
+    channel: SimChannel,
+    clock: SimClock,
+    nodes: Vec<VirtualNode>,
+    /// 注册了观察者后才监听信道
+    tap: Option<mpsc::Receiver<SimFrame>>,
+    observers: Vec<EventFn>,
+}
+
+impl VirtualNet {
+    /// 创建空网络，虚拟时间从0开始
+    pub fn new() -> Self {
+        Self::with_channel(SimChannel::new())
+    }
+    
+    /// 使用指定信道创建网络，例如设置了睡眠收帧方式的信道
+    pub fn with_channel(channel: SimChannel) -> Self {
+        let clock = SimClock::new();
+        channel.set_clock(clock.clone());
+        Self {
+            channel,
+            clock,
+            nodes: Vec::new(),
+            tap: None,
+            observers: Vec::new(),
+        }
+    }
+    
+    /// 加入一个只由测试直接操作的节点
+    pub fn add_node(&mut self, id: NodeId) -> &mut SimHardware {
+        self.push_node(id, None)
+    }
+    
+    /// 加入一个每步执行指定逻辑的节点
+    pub fn add_node_with<F>(&mut self, id: NodeId, step: F) -> &mut SimHardware
+    where
+        F: FnMut(&mut SimHardware, u64) + 'static,
+    {
+        self.push_node(id, Some(Box::new(step)))
+    }
+    
+    /// 加入运行真实转发节点主循环的节点
+    pub fn add_forwarder(&mut self, id: NodeId) -> &mut SimHardware {
+        self.add_node_loop(id, ForwardLoop::start, ForwardLoop::step)
+    }
+    
+    /// 加入运行真实服务端主循环的节点
+    pub fn add_server(&mut self, id: NodeId) -> &mut SimHardware {
+        self.add_node_loop(id, |hardware| Some(ServerLoop::start(hardware)), ServerLoop::step)
+    }
+    
+    /// 加入单步运行主循环的节点，按主循环返回的休眠间隔唤醒，主循环退出后请求了重启时重新启动
+    fn add_node_loop<S, Start, Step>(&mut self, id: NodeId, mut start: Start, mut step: Step) -> &mut SimHardware
+    where
+        S: 'static,
+        Start: FnMut(&mut SimHardware) -> Option<S> + 'static,
+        Step: FnMut(&mut S, &mut SimHardware) -> Option<u32> + 'static,
+    {
+        let mut state = None;
+        let mut stopped = false;
+        let mut wake_at = 0;
+        self.add_node_with(id, move |hardware, now| {
+            if stopped || now < wake_at {
+                return;
+            }
+            if state.is_none() {
+                state = start(hardware);
+                stopped = state.is_none();
+            }
+            if let Some(node) = state.as_mut() {
+                match step(node, hardware) {
+                    Some(delay_ms) => wake_at = now + delay_ms as u64,
+                    None => {
+                        state = None;
+                        stopped = !hardware.take_reset_request();
+                    },
+                }
+            }
+        })
+    }
+    
+    fn push_node(&mut self, id: NodeId, step: Option<StepFn>) -> &mut SimHardware {
+        assert!(self.nodes.iter().all(|node| node.id != id), "节点 {} 已存在", id);
+        let hardware = SimHardware::new(id, self.channel.clone()).with_clock(self.clock.clone());
+        self.nodes.push(VirtualNode {
+            id,
+            hardware,
+            step,
+            pending: VecDeque::new(),
+            reported: None,
+            role: NodeRole::Unknown,
+            hops: None,
+        });
+        &mut self.nodes.last_mut().unwrap().hardware
+    }
+    
+    /// 获取节点硬件，节点不存在时panic
+    pub fn node(&mut self, id: NodeId) -> &mut SimHardware {
+        self.nodes.iter_mut()
+            .find(|node| node.id == id)
+            .map(|node| &mut node.hardware)
+            .unwrap_or_else(|| panic!("未知节点 {}", id))
+    }
+    
+    /// 共享信道
+    pub fn channel(&self) -> &SimChannel {
+        &self.channel
+    }
+    
+    /// 当前虚拟时间（毫秒）
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+    
+    /// 注册事件观察者，每步结束时按发生顺序收到节点状态变化、发出的帧和跳数变化
+    pub fn on_event<F>(&mut self, observer: F)
+    where
+        F: FnMut(&SimEvent) + 'static,
+    {
+        if self.tap.is_none() {
+            self.tap = Some(self.channel.tap());
+        }
+        self.observers.push(Box::new(observer));
+    }
+    
+    /// 在指定地址启动WebSocket服务器，把事件以JSON文本帧推送给浏览器等外部可视化工具
+    #[cfg(feature = "websocket")]
+    pub fn serve_events(&mut self, addr: &str) -> std::io::Result<std::net::SocketAddr> {
+        let server = websocket::EventServer::bind(addr)?;
+        let local_addr = server.local_addr();
+        self.on_event(move |event| server.send_text(&event.to_json()));
+        Ok(local_addr)
+    }
+    
+    /// 推进一步虚拟时间，然后按加入顺序执行每个节点的逻辑
+    pub fn step(&mut self) {
+        self.clock.advance(TICK_MS);
+        let now = self.clock.now();
+        for node in self.nodes.iter_mut() {
+            if let Some(step) = node.step.as_mut() {
+                step(&mut node.hardware, now);
+            }
+        }
+        
+        if !self.observers.is_empty() {
+            let events = self.collect_events(now);
+            for event in events.iter() {
+                for observer in self.observers.iter_mut() {
+                    observer(event);
+                }
+            }
+        }
+    }
+    
+    /// 本步发出的帧、信标带来的跳数变化和节点状态变化
+    fn collect_events(&mut self, time: u64) -> Vec<SimEvent> {
+        let mut events = Vec::new();
+        let frames: Vec<SimFrame> = self.tap.as_ref().map_or(Vec::new(), |tap| tap.try_iter().collect());
+        for frame in frames {
+            match frame {
+                SimFrame::Beacon(source, beacon) => {
+                    events.push(SimEvent::Tx { time, source, destination: None, beacon: true, len: Beacon::SIZE });
+                    if let Some(node) = self.nodes.iter_mut().find(|node| node.id == source) {
+                        node.role = beacon.role();
+                        if node.hops != Some(beacon.hop_count) {
+                            node.hops = Some(beacon.hop_count);
+                            events.push(SimEvent::Route { time, node: source, hops: beacon.hop_count });
+                        }
+                    }
+                },
+                SimFrame::Data(source, frame) => {
+                    let destination = DataHeader::from_bytes(&frame)
+                        .map(|header| NodeId(header.destination))
+                        .filter(|destination| !destination.is_broadcast());
+                    events.push(SimEvent::Tx { time, source, destination, beacon: false, len: frame.len() });
+                },
+            }
+        }
+        
+        for node in self.nodes.iter_mut() {
+            let state = (
+                node.hardware.is_asleep(),
+                node.hardware.get_battery_level().unwrap_or(0),
+                node.role,
+            );
+            if node.reported != Some(state) {
+                node.reported = Some(state);
+                events.push(SimEvent::Node { time, node: node.id, asleep: state.0, battery: state.1, role: state.2 });
+            }
+        }
+        
+        events
+    }
+    
+    /// 连续执行指定的虚拟时长
+    pub fn run_for(&mut self, ms: u64) {
+        let end = self.now() + ms;
+        while self.now() < end {
+            self.step();
+        }
+    }
+    
+    /// 在指定的虚拟秒数内逐步执行，条件满足时立即返回true
+    pub fn within_virtual_secs<F>(&mut self, secs: u64, mut condition: F) -> bool
+    where
+        F: FnMut(&mut Self) -> bool,
+    {
+        let deadline = self.now() + secs * 1000;
+        loop {
+            if condition(self) {
+                return true;
+            }
+            if self.now() >= deadline {
+                return false;
+            }
+            self.step();
+        }
+    }
+    
+    /// 断言某个状态在指定的虚拟秒数内出现
+    pub fn expect_state<F>(&mut self, secs: u64, what: &str, condition: F)
+    where
+        F: FnMut(&mut Self) -> bool,
+    {
+        if !self.within_virtual_secs(secs, condition) {
+            panic!("{} 秒虚拟时间内未达到状态：{}", secs, what);
+        }
+    }
+    
+    /// 断言节点在指定的虚拟秒数内收到满足条件的数据包
+    ///
+    /// 每次查看之前先执行一步，让其他节点处理已经发出的帧；不满足条件的包留在节点的待取队列中，
+    /// 之后的断言仍能取到。节点运行主循环时收到的包由主循环处理，不应对其断言。
+    pub fn expect_packet<F>(&mut self, id: NodeId, secs: u64, mut predicate: F) -> ReceivedPacket
+    where
+        F: FnMut(&ReceivedPacket) -> bool,
+    {
+        let deadline = self.now() + secs * 1000;
+        loop {
+            self.step();
+            
+            let node = self.nodes.iter_mut()
+                .find(|node| node.id == id)
+                .unwrap_or_else(|| panic!("未知节点 {}", id));
+            let mut buffer = [0u8; 256];
+            while let Ok(Some(packet)) = node.hardware.get_radio().receive_data(&mut buffer) {
+                node.pending.push_back(ReceivedPacket {
+                    header: packet.header,
+                    data: packet.data.to_vec(),
+                });
+            }
+            if let Some(index) = node.pending.iter().position(|packet| predicate(packet)) {
+                return node.pending.remove(index).unwrap();
+            }
+            
+            if self.now() >= deadline {
+                panic!("节点 {} 在 {} 秒虚拟时间内未收到期望的数据包", id, secs);
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,139 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use sha1::{Digest, Sha1};
+
+/// 握手请求头的最大长度
+const MAX_REQUEST_LEN: usize = 4096;
+
+/// RFC 6455中计算握手应答的固定GUID
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// 只推送不接收的WebSocket服务器，每条事件是一个文本帧
+///
+/// 客户端发来的帧一律忽略，写入失败的连接在下次推送时移除。
+pub struct EventServer {
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+    addr: SocketAddr,
+}
+
+impl EventServer {
+    /// 绑定地址并在后台线程接受连接，端口为0时由系统分配
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        
+        let accepted = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // 握手失败的连接直接关闭
+                if let Ok(stream) = handshake(stream) {
+                    if let Ok(mut clients) = accepted.lock() {
+                        clients.push(stream);
+                    }
+                }
+            }
+        });
+        
+        Ok(Self { clients, addr })
+    }
+    
+    /// 实际监听的地址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+    
+    /// 已连接的客户端数
+    pub fn clients(&self) -> usize {
+        self.clients.lock().map_or(0, |clients| clients.len())
+    }
+    
+    /// 向所有客户端推送一条文本消息
+    pub fn send_text(&self, text: &str) {
+        let frame = text_frame(text);
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.retain_mut(|stream| stream.write_all(&frame).is_ok());
+        }
+    }
+}
+
+/// 读取HTTP升级请求并回复101
+fn handshake(mut stream: TcpStream) -> io::Result<TcpStream> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    
+    let mut request = Vec::new();
+    let mut chunk = [0u8; 512];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let len = stream.read(&mut chunk)?;
+        if len == 0 || request.len() + len > MAX_REQUEST_LEN {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        request.extend_from_slice(&chunk[..len]);
+    }
+    
+    let request = String::from_utf8_lossy(&request);
+    let key = request.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("sec-websocket-key"))
+        .map(|(_, value)| value.trim().to_string())
+        .ok_or(io::ErrorKind::InvalidData)?;
+    
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    );
+    stream.write_all(response.as_bytes())?;
+    // 推送时不等待慢速客户端太久
+    stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+    Ok(stream)
+}
+
+/// 握手应答：SHA-1(key + GUID)的Base64
+pub fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(HANDSHAKE_GUID.as_bytes());
+    base64(&hasher.finalize())
+}
+
+/// 服务器发出的帧不加掩码：FIN+文本(1) 长度(1/3/9) 负载
+fn text_frame(text: &str) -> Vec<u8> {
+    let payload = text.as_bytes();
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x81);
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        },
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        },
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// 标准Base64编码，带填充
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let value = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((value >> (18 - 6 * i)) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
\ No newline at end of file
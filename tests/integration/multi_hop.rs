@@ -1,58 +1,89 @@
 #[cfg(test)]
 mod multi_hop_tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
     use common::protocol::{NodeId, DataPacket};
-    use common::hal::simulator::{SimChannel, SimHardware};
+    use common::hal::{Hardware, RadioInterface};
+    use common::hal::simulator::SimFrame;
+    use testkit::{SimEvent, VirtualNet};
+    use testkit::websocket::accept_key;
     
     #[test]
     fn test_multi_hop_communication() {
-        let channel = SimChannel::new();
+        let mut net = VirtualNet::new();
         
         // 创建三个节点：客户端、转发节点和服务器
         let client_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
         let forwarder_id = NodeId::new([0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6]);
-        let server_id = NodeId::new([0xS1, 0xS2, 0xS3, 0xS4, 0xS5, 0xS6]);
+        let server_id = NodeId::new([0x51, 0x52, 0x53, 0x54, 0x55, 0x56]);
         
-        let mut client = SimHardware::new(client_id, channel.clone());
-        let mut forwarder = SimHardware::new(forwarder_id, channel.clone());
-        let mut server = SimHardware::new(server_id, channel.clone());
+        // 转发节点和服务器运行真实的主循环，客户端由测试直接操作
+        net.add_node(client_id);
+        net.add_forwarder(forwarder_id);
+        net.add_server(server_id);
+        
+        // 客户端和服务器互相听不到，只能经过转发节点
+        net.channel().connect(client_id, forwarder_id);
+        net.channel().connect(forwarder_id, server_id);
+        let frames = net.channel().tap();
         
         // 测试从客户端到服务器的数据包是否能通过转发节点
         let test_data = [0x01, 0x02, 0x03, 0x04];
         let packet = DataPacket::new(client_id, server_id, 1, &test_data);
         
-        // 客户端发送数据包
-        client.get_radio().send_data(&packet).unwrap();
-        
-        // 缓冲区用于接收数据
-        let mut buffer = [0u8; 256];
-        
-        // 转发节点接收数据包
-        if let Ok(Some(received_packet)) = forwarder.get_radio().receive_data(&mut buffer) {
-            assert_eq!(received_packet.header.source, client_id.0);
-            assert_eq!(received_packet.header.destination, server_id.0);
-            assert_eq!(received_packet.data, test_data);
-            
-            // 转发节点创建新的数据包转发
-            let forwarded_packet = DataPacket::new(
-                forwarder_id,
-                server_id, 
-                received_packet.header.packet_id,
-                received_packet.data
-            );
-            
-            // 转发节点发送转发的数据包
-            forwarder.get_radio().send_data(&forwarded_packet).unwrap();
-        } else {
-            panic!("转发节点未能接收到客户端的数据包");
-        }
-        
-        // 服务器接收转发的数据包
-        if let Ok(Some(received_packet)) = server.get_radio().receive_data(&mut buffer) {
-            assert_eq!(received_packet.header.source, forwarder_id.0);
-            assert_eq!(received_packet.header.destination, server_id.0);
-            assert_eq!(received_packet.data, test_data);
-        } else {
-            panic!("服务器未能接收到转发节点的数据包");
-        }
+        // 客户端把数据包发给下一跳的转发节点，头部的目标仍是服务器
+        net.node(client_id).get_radio().send_data_to(forwarder_id, &packet).unwrap();
+        
+        // 转发节点先与服务器建立路由，再把数据包发给服务器，头部的目标和负载不变
+        let relayed = net.within_virtual_secs(30, |_| frames.try_iter().any(|sent| match sent {
+            SimFrame::Data(source, frame) if source == forwarder_id => DataPacket::parse(&frame)
+                .map_or(false, |packet| packet.header.destination == server_id.0 && packet.data == test_data),
+            _ => false,
+        }));
+        assert!(relayed, "转发节点没有把客户端的数据包转发给服务器");
+    }
+    
+    #[test]
+    fn test_virtual_time_is_shared() {
+        let mut net = VirtualNet::new();
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        net.add_node(node_id);
+        
+        // 节点的延时直接推进共享的虚拟时钟
+        net.node(node_id).delay_ms(500).unwrap();
+        assert_eq!(net.now(), 500);
+        assert_eq!(net.node(node_id).get_timestamp_ms().unwrap(), 500);
+        
+        // 条件一直不满足时在截止时间返回
+        assert!(!net.within_virtual_secs(2, |_| false));
+        assert_eq!(net.now(), 2500);
+    }
+    
+    #[test]
+    fn test_event_feed() {
+        let mut net = VirtualNet::new();
+        let client_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let server_id = NodeId::new([0x51, 0x52, 0x53, 0x54, 0x55, 0x56]);
+        net.add_node(client_id);
+        net.add_node(server_id);
+        
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        net.on_event(move |event| sink.borrow_mut().push(event.clone()));
+        
+        net.node(client_id).get_radio().send_data(&DataPacket::new(client_id, server_id, 1, &[1, 2])).unwrap();
+        net.step();
+        net.step();
+        
+        // 第一步报告发出的帧和所有节点的初始状态，状态不变时不再重复
+        let events = events.borrow();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], SimEvent::Tx { source, destination: Some(destination), beacon: false, .. }
+            if source == client_id && destination == server_id));
+        assert!(events[1..].iter().all(|event| matches!(event, SimEvent::Node { time: 10, .. })));
+        assert!(events[0].to_json().starts_with("{\"type\":\"tx\",\"time\":10,\"source\":\"01:02:03:04:05:06\""));
+        
+        // RFC 6455中的握手示例
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
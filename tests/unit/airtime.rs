@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod airtime_tests {
+    use common::hal::AirtimeConfig;
+    use common::hal::airtime::{AirtimeLimiter, AIRTIME_WINDOW_MS};
+    
+    /// 每帧恰好1秒发射时间，1%占空比每小时36秒
+    fn config() -> AirtimeConfig {
+        AirtimeConfig {
+            bitrate_bps: 8,
+            overhead_us: 0,
+            duty_cycle_permille: 10,
+        }
+    }
+    
+    #[test]
+    fn test_airtime_limit_enforced() {
+        let mut limiter = AirtimeLimiter::new(config());
+        assert_eq!(config().airtime_us(1), 1_000_000);
+        
+        for i in 0..36 {
+            assert!(limiter.try_consume(1, i * 1000));
+        }
+        assert!(!limiter.try_consume(1, 36_000));
+        
+        let status = limiter.status(36_000);
+        assert_eq!(status.used_ms, 36_000);
+        assert_eq!(status.budget_ms, 36_000);
+        assert_eq!(status.remaining_ms(), 0);
+        assert!(status.is_nearly_exhausted());
+    }
+    
+    #[test]
+    fn test_airtime_window_slides() {
+        let mut limiter = AirtimeLimiter::new(config());
+        for _ in 0..36 {
+            limiter.try_consume(1, 0);
+        }
+        assert!(!limiter.try_consume(1, AIRTIME_WINDOW_MS / 2));
+        
+        // 一小时后早先的发射时间移出窗口
+        assert!(limiter.try_consume(1, AIRTIME_WINDOW_MS));
+        assert_eq!(limiter.status(AIRTIME_WINDOW_MS).used_ms, 1000);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod beacon_interval_tests {
+    use common::beacon_interval::{AdaptiveBeacon, BeaconBounds, TARGET_NEIGHBORS};
+    use common::config::{ConfigChanges, ConfigObserver, NodeConfig};
+    use common::protocol::{NodeId, NodeRole};
+    
+    #[test]
+    fn test_interval_scales_with_neighbor_density() {
+        let mut schedule = AdaptiveBeacon::new(60_000);
+        let bounds = schedule.bounds();
+        assert_eq!(bounds, BeaconBounds { base_ms: 60_000, min_ms: 15_000, max_ms: 240_000 });
+        
+        // 孤立节点用最短间隔，尽快被发现
+        assert_eq!(schedule.interval_ms(0), 15_000);
+        
+        // 达到目标密度时使用配置的间隔
+        for i in 0..TARGET_NEIGHBORS as u8 {
+            schedule.observe_node(NodeId::new([0, 0, 0, 0, 0, i]), 1_000);
+        }
+        assert_eq!(schedule.interval_ms(1_000), 60_000);
+        
+        // 密集网络中拉长，但不超过上限
+        for i in 0..30 {
+            schedule.observe_node(NodeId::new([0, 0, 0, 0, 1, i]), 2_000);
+        }
+        assert_eq!(schedule.interval_ms(2_000), 240_000);
+        
+        // 长时间听不到的邻居不再计数
+        assert_eq!(schedule.neighbor_count(2_000 + 3 * 240_000 + 1), 0);
+        
+        // 修改配置的信标间隔后按新基准伸缩
+        let mut config = NodeConfig::defaults(NodeRole::Forward);
+        config.beacon_interval_ms = 8_000;
+        schedule.config_changed(&config, ConfigChanges { beacon: true, ..ConfigChanges::default() });
+        assert_eq!(schedule.bounds(), BeaconBounds { base_ms: 8_000, min_ms: 2_000, max_ms: 32_000 });
+    }
+}
\ No newline at end of file
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod channel_plan_tests {
+    use common::channel_plan::{ChannelMigration, ChannelSurvey, BUSY_ENERGY_DBM};
+    use common::protocol::NodeId;
+    use common::protocol::channel::{ChannelMessage, ChannelReport, CHANNEL_COUNT, FIRST_CHANNEL};
+    
+    fn report(reporter: u8, crc_permille: u16, noisy: &[(u8, i8)]) -> ChannelReport {
+        let mut energy = [-95; CHANNEL_COUNT];
+        for (channel, dbm) in noisy {
+            energy[(channel - FIRST_CHANNEL) as usize] = *dbm;
+        }
+        ChannelReport {
+            master: NodeId([0xF1; 6]),
+            reporter: NodeId([0, 0, 0, 0, 0, reporter]),
+            channel: 15,
+            crc_permille,
+            energy,
+        }
+    }
+    
+    #[test]
+    fn test_survey_picks_cleanest_channel_for_whole_network() {
+        let mut survey = ChannelSurvey::new();
+        
+        // 干扰不明显时不换信道
+        survey.record(&report(1, 10, &[]), 0);
+        assert_eq!(survey.choose(15), None);
+        
+        // 一个节点附近信道15和11都很吵；信道11在另一个节点处安静，但按最差位置衡量仍然不可用
+        survey.record(&report(1, 200, &[(15, -60), (11, -62)]), 1000);
+        survey.record(&report(2, 30, &[(15, -70), (12, -93)]), 1000);
+        assert_eq!(survey.crc_permille(15), Some(115));
+        assert!(survey.worst_energy(11).unwrap() > BUSY_ENERGY_DBM);
+        assert_eq!(survey.choose(15), Some(13));
+        
+        // 报告经过线上格式后不变
+        let message = ChannelMessage::Report(report(1, 200, &[(15, -60)]));
+        let mut buffer = [0u8; 64];
+        let len = message.serialize(&mut buffer);
+        assert_eq!(ChannelMessage::deserialize(&buffer[..len]), Some(message));
+    }
+    
+    #[test]
+    fn test_migration_alternates_during_grace() {
+        let mut migration = ChannelMigration::new(15, 20, 1000, 500);
+        
+        assert_eq!(migration.tune(999), 15);
+        // 宽限期内在新旧信道之间交替
+        let tuned: Vec<u8> = (0..4).map(|_| migration.tune(1200)).collect();
+        assert_eq!(tuned, vec![15, 20, 15, 20]);
+        assert!(!migration.is_complete(1499));
+        
+        assert_eq!(migration.tune(1500), 20);
+        assert!(migration.is_complete(1500));
+    }
+}
\ No newline at end of file
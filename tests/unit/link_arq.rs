@@ -0,0 +1,334 @@
+#[cfg(test)]
+mod link_arq_tests {
+    use common::hal::{Hardware, LinkAddress, LinkArqConfig, RadioInterface};
+    use common::hal::arq::LinkArq;
+    use common::pool;
+    use common::hal::simulator::{LinkImpairment, SimChannel, SimChannelConfig, SimClock, SimHardware};
+    use common::protocol::{Beacon, DataPacket, NodeId, PacketType};
+    use common::protocol::data::{flow_id_of, Fragmenter, Reassembler, MAX_FRAGMENT_PAYLOAD};
+    use common::protocol::reliable::{send_fragment_ack, DeliveryEvent, Reception, ReliableReceiver, ReliableSender, RetryConfig};
+    
+    #[test]
+    fn test_link_arq_retransmits_until_acknowledged() {
+        let neighbor = NodeId([0, 0, 0, 0, 0, 2]);
+        let mut arq = LinkArq::new(LinkArqConfig { ack_timeout_ms: 50, max_retries: 2 }, pool::node_pool());
+        
+        let sequence = arq.track(neighbor, &[1, 2, 3], 0);
+        
+        // 超时前不重传
+        let mut resent = Vec::new();
+        arq.poll(49, |dest, seq, frame| resent.push((dest, seq, frame.to_vec())));
+        assert!(resent.is_empty());
+        
+        // 超时后原样重传
+        arq.poll(50, |dest, seq, frame| resent.push((dest, seq, frame.to_vec())));
+        assert_eq!(resent, vec![(neighbor, sequence, vec![1, 2, 3])]);
+        
+        // 确认后不再重传
+        assert!(arq.acknowledge(neighbor, sequence));
+        assert_eq!(arq.pending(), 0);
+        arq.poll(200, |_, _, _| panic!("已确认的帧不应重传"));
+    }
+    
+    #[test]
+    fn test_link_arq_gives_up_after_max_retries() {
+        let neighbor = NodeId([0, 0, 0, 0, 0, 2]);
+        let mut arq = LinkArq::new(LinkArqConfig { ack_timeout_ms: 50, max_retries: 2 }, pool::node_pool());
+        arq.track(neighbor, &[1], 0);
+        
+        let mut count = 0;
+        for now in [50, 100, 150] {
+            arq.poll(now, |_, _, _| count += 1);
+        }
+        assert_eq!(count, 2);
+        assert_eq!(arq.pending(), 0);
+    }
+    
+    #[test]
+    fn test_link_arq_filters_duplicates() {
+        let neighbor = NodeId([0, 0, 0, 0, 0, 2]);
+        let mut arq = LinkArq::new(LinkArqConfig::default(), pool::node_pool());
+        
+        assert!(!arq.is_duplicate(neighbor, 7, 0));
+        assert!(arq.is_duplicate(neighbor, 7, 100));
+        assert!(!arq.is_duplicate(neighbor, 8, 100));
+        
+        // 判定窗口过后序号回绕到同一值视为新帧
+        assert!(!arq.is_duplicate(neighbor, 7, 5000));
+    }
+    
+    #[test]
+    fn test_radio_diagnostics_count_retries_and_bad_frames() {
+        let channel = SimChannel::new();
+        let clock = SimClock::new();
+        let sender_id = NodeId([0, 0, 0, 0, 0, 1]);
+        let receiver_id = NodeId([0, 0, 0, 0, 0, 2]);
+        let mut sender = SimHardware::new(sender_id, channel.clone()).with_clock(clock.clone());
+        let radio = sender.get_radio();
+        radio.set_link_arq(Some(LinkArqConfig { ack_timeout_ms: 50, max_retries: 2 }));
+        
+        // 没有确认时逐跳重传，计为硬件重传
+        radio.send_data(&DataPacket::new(sender_id, receiver_id, 1, &[1, 2, 3])).unwrap();
+        clock.advance(60);
+        let mut buffer = [0u8; 256];
+        radio.receive_data(&mut buffer).unwrap();
+        let diagnostics = radio.diagnostics();
+        assert_eq!(diagnostics.frames_sent, 2);
+        assert_eq!(diagnostics.hw_retries, 1);
+        
+        // 接收方丢弃解析不了的帧并计为CRC错误
+        let noisy = SimChannel::new();
+        let mut receiver = SimHardware::new(receiver_id, noisy.clone());
+        noisy.push_packet(sender_id, &[0xFF; 4], 4);
+        assert!(receiver.get_radio().receive_data(&mut buffer).unwrap().is_none());
+        assert_eq!(receiver.get_radio().diagnostics().crc_errors, 1);
+    }
+    
+    #[test]
+    fn test_channel_delay_and_loss() {
+        let channel = SimChannel::new();
+        let clock = SimClock::new();
+        channel.set_clock(clock.clone());
+        channel.set_config(SimChannelConfig {
+            default: LinkImpairment { delay_ms: 100, ..LinkImpairment::default() },
+            ..SimChannelConfig::default()
+        });
+        let sender_id = NodeId([0, 0, 0, 0, 0, 1]);
+        let receiver_id = NodeId([0, 0, 0, 0, 0, 2]);
+        let mut sender = SimHardware::new(sender_id, channel.clone()).with_clock(clock.clone());
+        let mut receiver = SimHardware::new(receiver_id, channel.clone()).with_clock(clock.clone());
+        let mut buffer = [0u8; 256];
+        
+        // 延迟未到时帧还在路上
+        sender.get_radio().send_data(&DataPacket::new(sender_id, receiver_id, 1, &[1, 2, 3])).unwrap();
+        clock.advance(50);
+        assert!(receiver.get_radio().receive_data(&mut buffer).unwrap().is_none());
+        clock.advance(50);
+        let packet = receiver.get_radio().receive_data(&mut buffer).unwrap().unwrap();
+        assert_eq!(packet.data, [1, 2, 3]);
+        
+        // 单独设置的链路全部丢包
+        channel.set_link(sender_id, receiver_id, LinkImpairment { loss: 1.0, ..LinkImpairment::default() });
+        sender.get_radio().send_data(&DataPacket::new(sender_id, receiver_id, 2, &[4])).unwrap();
+        clock.advance(200);
+        assert!(receiver.get_radio().receive_data(&mut buffer).unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_channel_impairs_beacons_per_receiver() {
+        let channel = SimChannel::new();
+        let clock = SimClock::new();
+        channel.set_clock(clock.clone());
+        channel.set_config(SimChannelConfig {
+            default: LinkImpairment { delay_ms: 100, ..LinkImpairment::default() },
+            ..SimChannelConfig::default()
+        });
+        let a = NodeId([0, 0, 0, 0, 0, 1]);
+        let b = NodeId([0, 0, 0, 0, 0, 2]);
+        let c = NodeId([0, 0, 0, 0, 0, 3]);
+        let mut node_a = SimHardware::new(a, channel.clone()).with_clock(clock.clone());
+        let mut node_b = SimHardware::new(b, channel.clone()).with_clock(clock.clone());
+        let mut node_c = SimHardware::new(c, channel.clone()).with_clock(clock.clone());
+        
+        // 信标同样要等延迟到了才收到
+        node_a.get_radio().send_beacon(&Beacon::new(a, 90, 0)).unwrap();
+        clock.advance(50);
+        assert!(node_b.get_radio().receive_beacon().unwrap().is_none());
+        clock.advance(50);
+        assert!(node_b.get_radio().receive_beacon().unwrap().is_some());
+        assert!(node_c.get_radio().receive_beacon().unwrap().is_some());
+        
+        // 只有到b的链路丢包，先来检查的b丢失不影响c收到
+        channel.set_link(a, b, LinkImpairment { loss: 1.0, ..LinkImpairment::default() });
+        node_a.get_radio().send_beacon(&Beacon::new(a, 90, 0)).unwrap();
+        clock.advance(200);
+        assert!(node_b.get_radio().receive_beacon().unwrap().is_none());
+        assert!(node_c.get_radio().receive_beacon().unwrap().is_some());
+        assert!(node_b.get_radio().receive_beacon().unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_channel_delivers_by_address_and_connectivity() {
+        let channel = SimChannel::new();
+        let a = NodeId([0, 0, 0, 0, 0, 1]);
+        let b = NodeId([0, 0, 0, 0, 0, 2]);
+        let c = NodeId([0, 0, 0, 0, 0, 3]);
+        let mut node_a = SimHardware::new(a, channel.clone());
+        let mut node_b = SimHardware::new(b, channel.clone());
+        let mut node_c = SimHardware::new(c, channel.clone());
+        let mut buffer = [0u8; 256];
+        
+        // 单播帧只交给下一跳，广播帧每个节点各收到一次
+        node_a.get_radio().send_data(&DataPacket::new(a, b, 1, &[1])).unwrap();
+        node_a.get_radio().send_data(&DataPacket::new(a, NodeId::BROADCAST, 2, &[2])).unwrap();
+        assert_eq!(node_c.get_radio().receive_data(&mut buffer).unwrap().unwrap().header.packet_id, 2);
+        assert!(node_c.get_radio().receive_data(&mut buffer).unwrap().is_none());
+        assert_eq!(node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap().header.packet_id, 1);
+        assert_eq!(node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap().header.packet_id, 2);
+        
+        // 混杂模式下旁听发给其他节点的帧，下一跳照样收到
+        node_c.get_radio().set_promiscuous(true);
+        node_a.get_radio().send_data(&DataPacket::new(a, b, 3, &[3])).unwrap();
+        assert_eq!(node_c.get_radio().receive_data(&mut buffer).unwrap().unwrap().header.packet_id, 3);
+        assert_eq!(node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap().header.packet_id, 3);
+        
+        // 设置连通关系后听不到的节点收不到
+        channel.connect(a, b);
+        channel.connect(b, c);
+        assert!(!channel.can_hear(a, c));
+        node_a.get_radio().send_data(&DataPacket::new(a, NodeId::BROADCAST, 4, &[4])).unwrap();
+        assert!(node_c.get_radio().receive_data(&mut buffer).unwrap().is_none());
+        assert_eq!(node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap().header.packet_id, 4);
+    }    
+    #[test]
+    fn test_frames_addressed_to_next_hop() {
+        let channel = SimChannel::new();
+        let a = NodeId([0, 0, 0, 0, 0, 1]);
+        let b = NodeId([0, 0, 0, 0, 0, 2]);
+        let c = NodeId([0, 0, 0, 0, 0, 3]);
+        let mut node_a = SimHardware::new(a, channel.clone());
+        let mut node_b = SimHardware::new(b, channel.clone());
+        let mut node_c = SimHardware::new(c, channel.clone());
+        channel.connect(a, b);
+        channel.connect(b, c);
+        let mut buffer = [0u8; 256];
+        
+        // 目标听不到源节点的单播帧只交给混杂模式下听得到的节点
+        node_a.get_radio().send_data(&DataPacket::new(a, c, 1, &[1])).unwrap();
+        assert!(node_b.get_radio().receive_data(&mut buffer).unwrap().is_none());
+        node_b.get_radio().set_promiscuous(true);
+        node_a.get_radio().send_data(&DataPacket::new(a, c, 2, &[2])).unwrap();
+        assert_eq!(node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap().header.packet_id, 2);
+        assert_eq!(node_b.get_radio().last_link(), Some(LinkAddress { source: a, destination: c }));
+        assert!(node_b.get_radio().receive_data(&mut buffer).unwrap().is_none());
+        assert!(node_c.get_radio().receive_data(&mut buffer).unwrap().is_none());
+        
+        // 发给下一跳的帧头部保留端到端的目标
+        node_b.get_radio().set_promiscuous(false);
+        node_a.get_radio().send_data_to(b, &DataPacket::new(a, c, 3, &[3])).unwrap();
+        let packet = node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap();
+        assert_eq!(NodeId(packet.header.destination), c);
+        assert_eq!(node_b.get_radio().last_link(), Some(LinkAddress { source: a, destination: b }));
+    }
+    
+    #[test]
+    fn test_reliable_sender_retransmits_control_until_answered() {
+        let channel = SimChannel::new();
+        let a = NodeId([0, 0, 0, 0, 0, 1]);
+        let b = NodeId([0, 0, 0, 0, 0, 2]);
+        let mut node_a = SimHardware::new(a, channel.clone());
+        let mut node_b = SimHardware::new(b, channel.clone());
+        let mut sender = ReliableSender::new(RetryConfig { initial_timeout_ms: 100, max_timeout_ms: 100, backoff_factor: 1, max_retries: 1 });
+        let mut buffer = [0u8; 256];
+        
+        // 控制包按指定类型发出
+        let packet_id = sender.send_typed(&mut node_a, 0, b, PacketType::ServiceRequest, &[1, 2], 0).unwrap();
+        let packet = node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap();
+        assert_eq!(packet.header.packet_type, PacketType::ServiceRequest as u8);
+        assert_eq!(packet.header.packet_id, packet_id);
+        sender.send(&mut node_a, 7, b, &[3], 0).unwrap();
+        assert!(node_b.get_radio().receive_data(&mut buffer).unwrap().is_some());
+        
+        // 只重传指定会话中超时的帧
+        sender.poll_session(&mut node_a, 0, 100, |_| panic!("还有重传次数"));
+        let packet = node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap();
+        assert_eq!((packet.header.packet_type, packet.header.packet_id), (PacketType::ServiceRequest as u8, packet_id));
+        assert!(node_b.get_radio().receive_data(&mut buffer).unwrap().is_none());
+        
+        // 沿用包ID的应答结束重传，其他会话的帧重传次数用完后报告失败
+        assert_eq!(sender.complete(0, packet_id), Some(DeliveryEvent::Delivered { session_id: 0, packet_id }));
+        assert_eq!(sender.pending(), 1);
+        let mut failed = Vec::new();
+        for now in [200, 300] {
+            sender.poll(&mut node_a, now, |event| failed.push(event));
+        }
+        assert!(matches!(failed[..], [DeliveryEvent::Failed { session_id: 7, destination, .. }] if destination == b));
+        assert_eq!(sender.pending(), 0);
+    }
+    
+    #[test]
+    fn test_reliable_receiver_acknowledges_duplicates() {
+        let channel = SimChannel::new();
+        let a = NodeId([0, 0, 0, 0, 0, 1]);
+        let b = NodeId([0, 0, 0, 0, 0, 2]);
+        let mut node_a = SimHardware::new(a, channel.clone());
+        let mut node_b = SimHardware::new(b, channel.clone());
+        let mut sender = ReliableSender::new(RetryConfig { initial_timeout_ms: 100, max_timeout_ms: 100, backoff_factor: 1, max_retries: 2 });
+        let mut receiver = ReliableReceiver::new();
+        let mut buffer = [0u8; 256];
+        let mut ack_buffer = [0u8; 256];
+        
+        // 第一次收到的帧交给上层并确认
+        let packet_id = sender.send_typed(&mut node_a, 5, b, PacketType::PathConfirm, &[1], 0).unwrap();
+        let packet = node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap();
+        assert_eq!(receiver.receive(&mut node_b, &packet, 5, 0), Reception::New);
+        
+        // 确认丢失后发送端重传，接收端重新确认但不再交给上层
+        assert!(node_a.get_radio().receive_data(&mut ack_buffer).unwrap().is_some());
+        sender.poll(&mut node_a, 100, |_| panic!("还有重传次数"));
+        let packet = node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap();
+        assert_eq!(packet.header.packet_id, packet_id);
+        assert_eq!(receiver.receive(&mut node_b, &packet, 5, 50), Reception::Duplicate);
+        let ack = node_a.get_radio().receive_data(&mut ack_buffer).unwrap().unwrap();
+        assert_eq!(sender.handle_ack(&ack), Some(DeliveryEvent::Delivered { session_id: 5, packet_id }));
+        
+        // 其他会话相同包ID的帧和过期之后的重复帧都视为新帧，包ID为0的帧不确认
+        assert_eq!(receiver.receive(&mut node_b, &packet, 6, 100), Reception::New);
+        assert_eq!(receiver.receive(&mut node_b, &packet, 5, 200_000), Reception::New);
+        while node_a.get_radio().receive_data(&mut ack_buffer).unwrap().is_some() {}
+        let unacknowledged = DataPacket::new(a, b, 0, &[2]);
+        assert_eq!(receiver.receive(&mut node_b, &unacknowledged, 5, 200_000), Reception::New);
+        assert!(node_a.get_radio().receive_data(&mut ack_buffer).unwrap().is_none());
+    }    
+    #[test]
+    fn test_reliable_sender_acknowledges_fragments_individually() {
+        let channel = SimChannel::new();
+        let a = NodeId([0, 0, 0, 0, 0, 1]);
+        let b = NodeId([0, 0, 0, 0, 0, 2]);
+        let mut node_a = SimHardware::new(a, channel.clone());
+        let mut node_b = SimHardware::new(b, channel.clone());
+        let mut sender = ReliableSender::new(RetryConfig { initial_timeout_ms: 100, max_timeout_ms: 100, backoff_factor: 1, max_retries: 1 });
+        let mut buffer = [0u8; 256];
+        let mut ack_buffer = [0u8; 256];
+        
+        // 会话数据的各分片共用一个包ID，逐个发出
+        let payload: Vec<u8> = (0..MAX_FRAGMENT_PAYLOAD * 2 + 5).map(|i| i as u8).collect();
+        let packet_id = sender.allocate_packet_id();
+        let fragments: Vec<DataPacket> = Fragmenter::new(a, b, PacketType::Data, packet_id, &payload)
+            .unwrap()
+            .with_flow(flow_id_of(9))
+            .collect();
+        for fragment in &fragments {
+            sender.send_fragment(&mut node_a, 9, fragment, 0).unwrap();
+        }
+        assert_eq!(sender.pending(), 3);
+        
+        // 确认只结束对应分片的重传
+        for index in 0..3u8 {
+            let fragment = node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap();
+            assert_eq!((fragment.header.packet_id, fragment.header.fragment_index, fragment.header.total_fragments), (packet_id, index, 3));
+            if index == 1 {
+                send_fragment_ack(&mut node_b, &fragment).unwrap();
+            }
+        }
+        let ack = node_a.get_radio().receive_data(&mut ack_buffer).unwrap().unwrap();
+        assert_eq!(sender.handle_ack(&ack), Some(DeliveryEvent::Delivered { session_id: 9, packet_id }));
+        assert_eq!(sender.handle_ack(&ack), None);
+        assert_eq!(sender.pending(), 2);
+        
+        // 未确认的分片带原来的分片字段重传，接收端照常重组
+        sender.poll(&mut node_a, 100, |_| panic!("还有重传次数"));
+        let mut reassembler = Reassembler::new();
+        let mut complete = None;
+        let first = node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap();
+        assert_eq!(first.header.fragment_index, 0);
+        assert!(reassembler.push(&first, 100).is_none());
+        assert!(reassembler.push(&fragments[1], 100).is_none());
+        let last = node_b.get_radio().receive_data(&mut buffer).unwrap().unwrap();
+        assert_eq!(last.header.fragment_index, 2);
+        if let Some(packet) = reassembler.push(&last, 100) {
+            complete = Some(packet.data.to_vec());
+        }
+        assert_eq!(complete, Some(payload));
+    }
+}
\ No newline at end of file
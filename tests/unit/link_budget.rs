@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod link_budget_tests {
+    use common::hal::{Hardware, RadioInterface};
+    use common::hal::simulator::{SimChannel, SimHardware};
+    use common::link_budget::{average_rssi, LinkBudget};
+    use common::protocol::{Beacon, NodeId};
+    
+    #[test]
+    fn test_link_budget_estimate() {
+        let budget = LinkBudget {
+            tx_power_dbm: 20,
+            sensitivity_dbm: -95,
+            reference_loss_db: 40.0,
+            path_loss_exponent: 2.0,
+        };
+        
+        // 损耗比1米处多20dB，自由空间下距离为10米
+        let estimate = budget.estimate(-40);
+        assert_eq!(estimate.path_loss_db, 60);
+        assert_eq!(estimate.margin_db, 55);
+        assert!((estimate.distance_m - 10.0).abs() < 0.01);
+        
+        // 余量10dB时信号为-85dBm，损耗105dB
+        assert_eq!(budget.rssi_for_margin(10), -85);
+        let max = budget.max_distance(10);
+        assert!((max - 1778.3).abs() < 1.0);
+    }
+    
+    #[test]
+    fn test_average_rssi() {
+        assert_eq!(average_rssi(&[]), None);
+        assert_eq!(average_rssi(&[-70, -71]), Some(-71));
+        assert_eq!(average_rssi(&[-60, -70, -80]), Some(-70));
+    }
+    
+    #[test]
+    fn test_simulated_rssi_follows_distance() {
+        let channel = SimChannel::new();
+        let gateway = NodeId([0, 0, 0, 0, 0, 1]);
+        let near = NodeId([0, 0, 0, 0, 0, 2]);
+        let far = NodeId([0, 0, 0, 0, 0, 3]);
+        let mut receiver = SimHardware::new(gateway, channel.clone());
+        let mut near_node = SimHardware::new(near, channel.clone());
+        let mut far_node = SimHardware::new(far, channel.clone());
+        receiver.set_position(0.0, 0.0);
+        near_node.set_position(6.0, 8.0);
+        far_node.set_position(100.0, 0.0);
+        
+        // 默认模型：20dBm发射，1米处损耗40dB，指数2.7
+        near_node.get_radio().send_beacon(&Beacon::new(near, 100, 0)).unwrap();
+        assert!(receiver.get_radio().receive_beacon().unwrap().is_some());
+        assert_eq!(receiver.get_radio().get_rssi().unwrap(), -47);
+        
+        far_node.get_radio().send_beacon(&Beacon::new(far, 100, 0)).unwrap();
+        assert!(receiver.get_radio().receive_beacon().unwrap().is_some());
+        assert_eq!(receiver.get_radio().get_rssi().unwrap(), -74);
+        assert_eq!(channel.rssi(near, far), channel.rssi(far, near));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod low_power_tests {
+    use common::hal::{Hardware, RadioInterface};
+    use common::hal::simulator::{SimChannel, SimHardware, SimulatorError, SleepRx};
+    use common::protocol::{DataPacket, NodeId};
+    
+    const SENSOR: NodeId = NodeId([0x01, 0x01, 0x01, 0x01, 0x01, 0x01]);
+    const RELAY: NodeId = NodeId([0x02, 0x02, 0x02, 0x02, 0x02, 0x02]);
+    
+    fn send_to_sensor(relay: &mut SimHardware) {
+        let packet = DataPacket::new(RELAY, SENSOR, 1, &[0x01, 0x02, 0x03]);
+        relay.get_radio().send_data(&packet).unwrap();
+    }
+    
+    #[test]
+    fn test_sleeping_node_misses_frames() {
+        let channel = SimChannel::new();
+        let mut sensor = SimHardware::new(SENSOR, channel.clone());
+        let mut relay = SimHardware::new(RELAY, channel);
+        let mut buffer = [0u8; 256];
+        
+        sensor.enter_low_power_mode().unwrap();
+        send_to_sensor(&mut relay);
+        
+        // 睡眠时不收不发
+        assert!(sensor.get_radio().receive_data(&mut buffer).unwrap().is_none());
+        let packet = DataPacket::new(SENSOR, RELAY, 2, &[0x04]);
+        assert!(matches!(sensor.get_radio().send_data(&packet), Err(SimulatorError::Asleep)));
+        
+        // 醒来后睡眠期间的帧已丢失
+        sensor.exit_low_power_mode().unwrap();
+        assert!(sensor.get_radio().receive_data(&mut buffer).unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_buffered_frames_arrive_after_timer_wake() {
+        let channel = SimChannel::new().with_sleep_rx(SleepRx::Buffer);
+        let mut sensor = SimHardware::new(SENSOR, channel.clone());
+        let mut relay = SimHardware::new(RELAY, channel);
+        let mut buffer = [0u8; 256];
+        
+        sensor.enter_low_power_mode().unwrap();
+        sensor.schedule_wake(20);
+        send_to_sensor(&mut relay);
+        
+        // 定时器到期前仍在睡眠，睡眠电流远小于工作电流
+        sensor.delay_ms(5).unwrap();
+        assert!(sensor.is_asleep());
+        let asleep_charge = sensor.consumed_charge();
+        
+        sensor.delay_ms(30).unwrap();
+        assert!(!sensor.is_asleep());
+        assert!(sensor.consumed_charge() - asleep_charge > asleep_charge * 10);
+        
+        let packet = sensor.get_radio().receive_data(&mut buffer).unwrap().unwrap();
+        assert_eq!(packet.data, [0x01, 0x02, 0x03]);
+    }
+}
\ No newline at end of file
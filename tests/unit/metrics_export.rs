@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod metrics_export_tests {
+    use common::metrics::{Counter, Gauge, MetricsSnapshot, COUNTER_COUNT, GAUGE_COUNT};
+    use common::protocol::{NodeId, PacketType};
+    use common::protocol::reliable::MAX_FRAME_PAYLOAD;
+    use common::protocol::stats::{StatsCollector, StatsMessage, StatsPage, StatsProgress, STATS_RESPONSE_LEN};
+    
+    #[test]
+    fn test_metric_keys_are_unique() {
+        let mut keys: Vec<&str> = Counter::ALL.iter().map(|counter| counter.key())
+            .chain(Gauge::ALL.iter().map(|gauge| gauge.key()))
+            .collect();
+        keys.sort();
+        keys.dedup();
+        
+        assert_eq!(keys.len(), COUNTER_COUNT + GAUGE_COUNT);
+    }
+    
+    #[test]
+    fn test_prometheus_text_format() {
+        let mut snapshot = MetricsSnapshot {
+            counters: [0; COUNTER_COUNT],
+            gauges: [0; GAUGE_COUNT],
+        };
+        snapshot.counters[Counter::RadioTx as usize] = 42;
+        snapshot.gauges[Gauge::BatteryLevel as usize] = 87;
+        
+        let mut text = String::new();
+        snapshot.write_prometheus(&mut text).unwrap();
+        
+        assert!(text.contains("# TYPE linknebula_radio_tx_total counter\n"));
+        assert!(text.contains("\nlinknebula_radio_tx_total 42\n"));
+        assert!(text.contains("# TYPE linknebula_battery_level gauge\n"));
+        assert!(text.contains("\nlinknebula_battery_level 87\n"));
+        
+        // 每个指标都有HELP、TYPE和样本三行
+        assert_eq!(text.lines().count(), (COUNTER_COUNT + GAUGE_COUNT) * 3);
+    }
+    
+    #[test]
+    fn test_stats_response_roundtrip() {
+        let mut snapshot = MetricsSnapshot {
+            counters: [0; COUNTER_COUNT],
+            gauges: [0; GAUGE_COUNT],
+        };
+        snapshot.counters[Counter::PacketsForwarded as usize] = 7;
+        snapshot.counters[Counter::PacketsDropped as usize] = 3;
+        snapshot.gauges[Gauge::BatteryLevel as usize] = 87;
+        let origin = NodeId([1, 2, 3, 4, 5, 6]);
+        let target = NodeId([6, 5, 4, 3, 2, 1]);
+        
+        // 应答逐页发送，每页都放得进一帧，发起方按下一页的起始编号继续查询直到收齐
+        let mut collector = StatsCollector::new();
+        let mut first = 0;
+        let mut pages = 0;
+        let collected = loop {
+            let request = StatsMessage::Request { origin, target, first };
+            let mut buffer = [0u8; STATS_RESPONSE_LEN];
+            let len = request.serialize(&mut buffer);
+            assert_eq!(StatsMessage::deserialize(PacketType::StatsRequest as u8, &buffer[..len]), Some(request));
+            
+            let message = StatsMessage::Response { origin, target, page: StatsPage::of(&snapshot, first) };
+            let len = message.serialize(&mut buffer);
+            assert!(len > 0 && len <= MAX_FRAME_PAYLOAD);
+            let page = match StatsMessage::deserialize(PacketType::StatsResponse as u8, &buffer[..len]) {
+                Some(StatsMessage::Response { page, .. }) => page,
+                other => panic!("unexpected {:?}", other),
+            };
+            pages += 1;
+            match collector.accept(target, &page) {
+                StatsProgress::Complete(collected) => break collected,
+                StatsProgress::Next(next) => first = next,
+                StatsProgress::Ignored => panic!("page ignored"),
+            }
+        };
+        assert_eq!(collected, snapshot);
+        assert_eq!(pages, (COUNTER_COUNT + GAUGE_COUNT).div_ceil(StatsPage::of(&snapshot, 0).values().len()));
+        
+        // 旧版本不带起始编号的查询从头开始
+        let legacy = [1, 2, 3, 4, 5, 6, 6, 5, 4, 3, 2, 1];
+        assert_eq!(StatsMessage::deserialize(PacketType::StatsRequest as u8, &legacy),
+                   Some(StatsMessage::Request { origin, target, first: 0 }));
+    }
+}
\ No newline at end of file
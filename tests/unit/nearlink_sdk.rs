@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod nearlink_sdk_tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use common::hal::nearlink::{Nearlink, NearlinkConfig, NearlinkRadioStats, NearlinkSdk, NlError};
+    
+    /// 模拟SDK，可以让它报告错误的长度
+    #[derive(Default)]
+    struct MockSdk {
+        guard: AtomicBool,
+        rx_frame: Option<Vec<u8>>,
+        /// 接收时报告的长度比实际写入的多出多少
+        overreport: usize,
+        nvs: HashMap<u16, Vec<u8>>,
+        ota: Vec<u8>,
+    }
+    
+    impl NearlinkSdk for MockSdk {
+        fn guard(&self) -> &AtomicBool {
+            &self.guard
+        }
+        
+        fn init(&mut self, _config: &NearlinkConfig) -> i32 {
+            0
+        }
+        
+        fn send(&mut self, _dest: &[u8; 6], _data: &[u8]) -> i32 {
+            0
+        }
+        
+        fn recv(&mut self, buf: &mut [u8], actual_len: &mut usize) -> i32 {
+            match self.rx_frame.take() {
+                Some(frame) => {
+                    let len = frame.len().min(buf.len());
+                    buf[..len].copy_from_slice(&frame[..len]);
+                    *actual_len = len + self.overreport;
+                    0
+                },
+                None => -1,
+            }
+        }
+        
+        fn configure(&mut self, _channel: u8, tx_power: i8) -> i32 {
+            if tx_power > 20 { -5 } else { 0 }
+        }
+        
+        fn radio_stats(&mut self, stats: &mut NearlinkRadioStats) -> i32 {
+            stats.tx_frames = 10;
+            stats.crc_errors = 2;
+            0
+        }
+        
+        fn uart_read(&mut self, buf: &mut [u8]) -> i32 {
+            buf.len() as i32 + self.overreport as i32
+        }
+        
+        fn uart_write(&mut self, _data: &[u8]) -> i32 {
+            0
+        }
+        
+        fn nvs_read(&mut self, key: u16, buf: &mut [u8], actual_len: &mut usize) -> i32 {
+            match self.nvs.get(&key) {
+                Some(value) if value.len() <= buf.len() => {
+                    buf[..value.len()].copy_from_slice(value);
+                    *actual_len = value.len();
+                    0
+                },
+                Some(_) => -2,
+                None => -1,
+            }
+        }
+        
+        fn nvs_write(&mut self, key: u16, data: &[u8]) -> i32 {
+            self.nvs.insert(key, data.to_vec());
+            0
+        }
+        
+        fn nvs_erase(&mut self, key: u16) -> i32 {
+            self.nvs.remove(&key);
+            0
+        }
+        
+        fn ota_capacity(&mut self) -> usize {
+            self.ota.len()
+        }
+        
+        fn ota_erase(&mut self) -> i32 {
+            self.ota.fill(0xFF);
+            0
+        }
+        
+        fn ota_write(&mut self, offset: u32, data: &[u8]) -> i32 {
+            let offset = offset as usize;
+            self.ota[offset..offset + data.len()].copy_from_slice(data);
+            0
+        }
+        
+        fn ota_read(&mut self, offset: u32, buf: &mut [u8]) -> i32 {
+            let offset = offset as usize;
+            let len = buf.len().min(self.ota.len() - offset);
+            buf[..len].copy_from_slice(&self.ota[offset..offset + len]);
+            len as i32
+        }
+        
+        fn ota_mark_boot(&mut self, _size: u32, _crc: u32) -> i32 {
+            0
+        }
+        
+        fn data_capacity(&mut self) -> usize {
+            0
+        }
+        
+        fn data_erase_sector(&mut self, _sector: u32) -> i32 {
+            0
+        }
+        
+        fn data_write(&mut self, _offset: u32, _data: &[u8]) -> i32 {
+            0
+        }
+        
+        fn data_read(&mut self, _offset: u32, _buf: &mut [u8]) -> i32 {
+            0
+        }
+        
+        fn i2c_write(&mut self, _addr: u8, _data: &[u8]) -> i32 {
+            0
+        }
+        
+        fn i2c_read(&mut self, _addr: u8, _buf: &mut [u8]) -> i32 {
+            0
+        }
+        
+        fn reset_cause(&mut self) -> u8 {
+            2
+        }
+        
+        fn timestamp_ms(&mut self) -> u64 {
+            1000
+        }
+        
+        fn delay_ms(&mut self, _ms: u32) {}
+    }
+    
+    #[test]
+    fn test_return_codes_are_typed() {
+        let mut sdk = Nearlink::new(MockSdk::default());
+        let mut buf = [0u8; 32];
+        
+        assert_eq!(sdk.recv(&mut buf), Err(NlError::NoData));
+        assert_eq!(sdk.configure(15, 30), Err(NlError::Sdk(-5)));
+        assert_eq!(sdk.configure(15, 10), Ok(()));
+        
+        // 键不存在时返回None，其他错误原样返回
+        assert_eq!(sdk.nvs_read(0x0001, &mut buf), Ok(None));
+        sdk.nvs_write(0x0001, &[1, 2, 3]).unwrap();
+        assert_eq!(sdk.nvs_read(0x0001, &mut buf), Ok(Some(3)));
+        sdk.nvs_write(0x0002, &[0; 64]).unwrap();
+        assert_eq!(sdk.nvs_read(0x0002, &mut buf), Err(NlError::Sdk(-2)));
+    }
+    
+    #[test]
+    fn test_lengths_are_checked() {
+        let mut sdk = Nearlink::new(MockSdk { ota: vec![0; 1024], ..MockSdk::default() });
+        let mut buf = [0u8; 8];
+        
+        // 超长或空的帧、越界的OTA写入和非7位I2C地址不会交给SDK
+        assert_eq!(sdk.send(&[0xFF; 6], &[0u8; 300]), Err(NlError::InvalidArgument));
+        assert_eq!(sdk.send(&[0xFF; 6], &[]), Err(NlError::InvalidArgument));
+        assert_eq!(sdk.ota_write(1020, &[0u8; 8]), Err(NlError::InvalidArgument));
+        assert_eq!(sdk.ota_write(u32::MAX, &[0u8; 8]), Err(NlError::InvalidArgument));
+        assert_eq!(sdk.ota_mark_boot(2048, 0), Err(NlError::InvalidArgument));
+        assert_eq!(sdk.i2c_write(0x80, &[0]), Err(NlError::InvalidArgument));
+        
+        sdk.ota_write(1016, &[7u8; 8]).unwrap();
+        assert_eq!(sdk.ota_read(1016, &mut buf), Ok(8));
+        assert_eq!(buf, [7u8; 8]);
+        
+        // SDK报告的长度超出缓冲区时不相信它
+        let mut sdk = Nearlink::new(MockSdk { rx_frame: Some(vec![1; 8]), overreport: 4, ..MockSdk::default() });
+        assert_eq!(sdk.recv(&mut buf), Err(NlError::BadLength));
+        assert_eq!(sdk.uart_read(&mut buf), Err(NlError::BadLength));
+    }
+    
+    #[test]
+    fn test_reentrant_call_is_rejected() {
+        let mut sdk = Nearlink::new(MockSdk::default());
+        sdk.init(&NearlinkConfig { channel: 15, tx_power: 20, pan_id: 0x1234 }).unwrap();
+        
+        // 模拟另一处调用尚未返回：标志已被占用时不进入SDK
+        let mut mock = MockSdk::default();
+        mock.guard.store(true, Ordering::SeqCst);
+        let mut busy = Nearlink::new(mock);
+        assert_eq!(busy.send(&[0xFF; 6], &[1, 2, 3]), Err(NlError::Busy));
+        // 被拒绝的调用不能释放别人持有的标志
+        assert_eq!(busy.timestamp_ms(), Err(NlError::Busy));
+        // 忙时读取分区容量失败，不当作容量为0或越界
+        assert_eq!(busy.data_capacity(), Err(NlError::Busy));
+        assert_eq!(busy.data_write(0, &[1, 2, 3]), Err(NlError::Busy));
+        assert_eq!(busy.ota_write(0, &[1, 2, 3]), Err(NlError::Busy));
+        
+        // 调用返回后释放标志
+        assert_eq!(sdk.send(&[0xFF; 6], &[1, 2, 3]), Ok(()));
+        assert_eq!(sdk.send(&[0xFF; 6], &[1, 2, 3]), Ok(()));
+        assert_eq!(sdk.reset_cause(), Ok(2));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod node_config_tests {
+    use common::config::{NodeConfig, SelectionPolicy};
+    use common::hal::Hardware;
+    use common::hal::simulator::{SimChannel, SimHardware};
+    use common::mgmt::Managed;
+    use common::protocol::{NodeId, NodeRole, ServiceType};
+    use common::protocol::mgmt::{MgmtAttribute, MgmtStatus};
+    
+    #[test]
+    fn test_node_config_roundtrip() {
+        let mut config = NodeConfig::defaults(NodeRole::Forward);
+        config.channel = 20;
+        config.route_expiry_ms = 120000;
+        config.monitor = true;
+        config.directory_proxy = true;
+        config.admins[0] = Some(NodeId([1, 2, 3, 4, 5, 6]));
+        
+        let bytes = config.to_bytes();
+        assert_eq!(NodeConfig::from_bytes(&bytes, NodeRole::Forward), Some(config));
+        
+        // 角色与固件不一致时回退到默认配置
+        assert_eq!(NodeConfig::from_bytes(&bytes, NodeRole::Server), None);
+    }
+    
+    #[test]
+    fn test_node_config_migrates_older_versions() {
+        // 版本5的格式：没有服务过期、保活间隔、跳数限制、电量设置和路径带宽
+        let admin = NodeId([1, 2, 3, 4, 5, 6]);
+        let mut bytes = vec![5, NodeRole::Forward as u8];
+        bytes.extend_from_slice(&45000u32.to_be_bytes());
+        bytes.extend_from_slice(&[21, 14]);
+        bytes.extend_from_slice(&600000u32.to_be_bytes());
+        bytes.extend_from_slice(&90000u32.to_be_bytes());
+        bytes.extend_from_slice(&10u16.to_be_bytes());
+        bytes.extend_from_slice(&[40, 30, 20, 10, 5, 10]);
+        bytes.extend_from_slice(&[0, 1, 1]);
+        bytes.extend_from_slice(&admin.0);
+        
+        let config = NodeConfig::from_bytes(&bytes, NodeRole::Forward).unwrap();
+        let defaults = NodeConfig::defaults(NodeRole::Forward);
+        assert_eq!((config.beacon_interval_ms, config.channel, config.tx_power), (45000, 21, 14));
+        assert_eq!((config.route_expiry_ms, config.duty_cycle_permille), (90000, 10));
+        assert!(config.directory_proxy && !config.monitor);
+        assert_eq!(config.admins[0], Some(admin));
+        assert_eq!(config.keepalive_ms, defaults.keepalive_ms);
+        assert_eq!(config.default_ttl, defaults.default_ttl);
+        assert_eq!(config.critical_battery, defaults.critical_battery);
+        assert_eq!(config.path_capacity_kbps, defaults.path_capacity_kbps);
+        assert_eq!(config.selection_policies, defaults.selection_policies);
+        
+        // 截断的记录和未来的版本仍然回退到默认配置
+        assert_eq!(NodeConfig::from_bytes(&bytes[..bytes.len() - 1], NodeRole::Forward), None);
+        bytes[0] = 99;
+        assert_eq!(NodeConfig::from_bytes(&bytes, NodeRole::Forward), None);
+    }
+    
+    #[test]
+    fn test_node_config_selection_policy() {
+        let mut hardware = SimHardware::new(NodeId([1, 2, 3, 4, 5, 6]), SimChannel::new());
+        let mut config = NodeConfig::defaults(NodeRole::Forward);
+        
+        // 按服务类型设置，未知的类型或策略被拒绝
+        let storage = ServiceType::Storage as u8;
+        config.set(&mut hardware, MgmtAttribute::SelectionPolicy, &[storage, SelectionPolicy::WeightedRandom as u8]).unwrap();
+        assert_eq!(config.selection_policies[0], SelectionPolicy::WeightedRandom);
+        assert!(config.take_changes().directory);
+        assert_eq!(config.set(&mut hardware, MgmtAttribute::SelectionPolicy, &[9, 0]), Err(MgmtStatus::InvalidValue));
+        assert_eq!(config.set(&mut hardware, MgmtAttribute::SelectionPolicy, &[storage, 3]), Err(MgmtStatus::InvalidValue));
+        
+        let mut out = [0u8; 16];
+        let len = config.get_attribute(&mut hardware, MgmtAttribute::SelectionPolicy, &[], &mut out).unwrap();
+        assert_eq!(&out[..len], &[2, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(NodeConfig::from_bytes(&config.to_bytes(), NodeRole::Forward), Some(config));
+        
+        // 只有转发节点挑选服务器
+        let mut server = NodeConfig::defaults(NodeRole::Server);
+        let result = server.set(&mut hardware, MgmtAttribute::SelectionPolicy, &[storage, 1]);
+        assert_eq!(result, Err(MgmtStatus::Unsupported));
+    }
+    
+    #[test]
+    fn test_node_config_admins() {
+        let mut config = NodeConfig::defaults(NodeRole::Server);
+        let admin = NodeId([1, 2, 3, 4, 5, 6]);
+        
+        // 管理员列表为空时不限制请求方
+        assert!(config.is_admin(NodeId([9; 6])));
+        
+        config.admins[0] = Some(admin);
+        assert!(config.is_admin(admin));
+        assert!(!config.is_admin(NodeId([9; 6])));
+    }
+    
+    #[test]
+    fn test_node_config_timers() {
+        let mut hardware = SimHardware::new(NodeId([1, 2, 3, 4, 5, 6]), SimChannel::new());
+        let mut config = NodeConfig::defaults(NodeRole::Forward);
+        
+        // 低于下限的取值被拒绝，配置不变
+        let result = config.set(&mut hardware, MgmtAttribute::KeepaliveInterval, &1000u32.to_be_bytes());
+        assert_eq!(result, Err(MgmtStatus::InvalidValue));
+        assert!(config.take_changes().is_empty());
+        
+        config.set(&mut hardware, MgmtAttribute::KeepaliveInterval, &30000u32.to_be_bytes()).unwrap();
+        config.set(&mut hardware, MgmtAttribute::ServiceExpiry, &600000u32.to_be_bytes()).unwrap();
+        let changes = config.take_changes();
+        assert!(changes.keepalive && changes.directory);
+        
+        // 设置后立即保存，重启后保持
+        let restored = NodeConfig::load(hardware.get_nvs(), NodeRole::Forward);
+        assert_eq!(restored.keepalive_ms, 30000);
+        assert_eq!(restored.service_expiry_ms, 600000);
+        assert!(MgmtAttribute::KeepaliveInterval.is_timer());
+        assert!(!MgmtAttribute::Channel.is_timer());
+    }
+}
\ No newline at end of file
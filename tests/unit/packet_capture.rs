@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod packet_capture_tests {
+    use common::hal::{Hardware, RadioInterface};
+    use common::hal::simulator::{SimChannel, SimClock, SimFrame, SimHardware};
+    use common::protocol::{Beacon, DataPacket, NodeId, PacketType};
+    
+    const SENSOR: NodeId = NodeId([0x01, 0x01, 0x01, 0x01, 0x01, 0x01]);
+    const RELAY: NodeId = NodeId([0x02, 0x02, 0x02, 0x02, 0x02, 0x02]);
+    
+    #[test]
+    fn test_sniffer_captures_traffic() {
+        let channel = SimChannel::new();
+        let clock = SimClock::new();
+        channel.set_clock(clock.clone());
+        let mut sniffer = channel.attach_sniffer();
+        let mut sensor = SimHardware::new(SENSOR, channel.clone()).with_clock(clock.clone());
+        let mut relay = SimHardware::new(RELAY, channel).with_clock(clock.clone());
+        
+        clock.advance(1500);
+        relay.get_radio().send_beacon(&Beacon::new(RELAY, 90, -40)).unwrap();
+        clock.advance(250);
+        let packet = DataPacket::new(SENSOR, RELAY, 7, &[0xAA, 0xBB]);
+        sensor.get_radio().send_data(&packet).unwrap();
+        
+        let frames = sniffer.frames();
+        assert_eq!(frames.len(), 2);
+        assert!(matches!(frames[0].frame, SimFrame::Beacon(source, _) if source == RELAY));
+        assert_eq!(frames[0].timestamp, 1500);
+        assert_eq!(frames[1].source(), SENSOR);
+        assert_eq!(frames[1].packet_type(), Some(PacketType::Data));
+        assert_eq!(frames[1].timestamp, 1750);
+        
+        // 摘要行带时间和方向，之后是十六进制字节
+        let dump = sniffer.dump();
+        let lines: Vec<&str> = dump.lines().collect();
+        assert!(lines[0].starts_with(&format!("1.500 {} > * Beacon", RELAY)));
+        assert!(dump.contains(&format!("1.750 {} > {} Data", SENSOR, RELAY)));
+        assert!(lines.iter().any(|line| line.starts_with("0000  ")));
+        
+        sniffer.clear();
+        assert!(sniffer.frames().is_empty());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod packet_pool_tests {
+    use common::hal::Hardware;
+    use common::hal::simulator::{SimChannel, SimHardware};
+    use common::pool::{PacketPool, PACKET_BUFFER_LEN, POOL_SIZE};
+    use common::protocol::NodeId;
+    
+    #[test]
+    fn test_pool_acquire_and_release() {
+        static POOL: PacketPool<2> = PacketPool::new();
+        
+        let first = POOL.store(b"hello").unwrap();
+        let mut second = POOL.acquire().unwrap();
+        assert_eq!(&first[..], b"hello");
+        assert!(second.is_empty());
+        assert_eq!(second.as_mut_slice().len(), PACKET_BUFFER_LEN);
+        
+        // 池耗尽时取不到缓冲区
+        assert!(POOL.acquire().is_none());
+        assert_eq!(POOL.available(), 0);
+        
+        // 显式释放或丢弃句柄都会归还缓冲区
+        first.release();
+        assert_eq!(POOL.available(), 1);
+        drop(second);
+        assert_eq!(POOL.available(), 2);
+        
+        // 超过缓冲区长度的数据不占用缓冲区
+        assert!(POOL.store(&[0u8; PACKET_BUFFER_LEN + 1]).is_none());
+        assert_eq!(POOL.available(), 2);
+    }    
+    #[test]
+    fn test_node_pools_are_independent() {
+        let channel = SimChannel::new();
+        let first = SimHardware::new(NodeId::new([0x01; 6]), channel.clone());
+        let second = SimHardware::new(NodeId::new([0x02; 6]), channel);
+        
+        // 一个节点占满自己的缓冲池不影响同一进程中的其他节点
+        let held: Vec<_> = (0..POOL_SIZE).filter_map(|_| first.packet_pool().acquire()).collect();
+        assert_eq!(held.len(), POOL_SIZE);
+        assert!(first.packet_pool().acquire().is_none());
+        assert!(second.packet_pool().acquire().is_some());
+        
+        // 归还后可以再取
+        drop(held);
+        assert_eq!(first.packet_pool().available(), POOL_SIZE);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod path_admission_tests {
+    use common::config::{ConfigChanges, ConfigObserver, NodeConfig};
+    use common::protocol::{NodeId, NodeRole, PathStatus};
+    use common::protocol::data::flow_id_of;
+    use forward::path_table::{PathTable, PATH_IDLE_TIMEOUT_MS};
+    
+    #[test]
+    fn test_paths_admitted_within_capacity() {
+        let client = NodeId::new([0xC1; 6]);
+        let server = NodeId::new([0x51; 6]);
+        let mut paths = PathTable::new(200);
+        
+        // 两条视频中继占满带宽，第三条被拒绝
+        assert_eq!(paths.reserve(1, client, server, 120, 0), PathStatus::Success);
+        assert_eq!(paths.reserve(2, client, server, 80, 0), PathStatus::Success);
+        assert_eq!(paths.reserve(3, client, server, 10, 0), PathStatus::NoResource);
+        assert_eq!(paths.available_kbps(), 0);
+        
+        // 同一服务重新建立路径时替换原预留，不重复计算
+        assert_eq!(paths.reserve(2, client, server, 60, 0), PathStatus::Success);
+        assert_eq!(paths.reserved_kbps(), 180);
+        
+        // 拆除后释放带宽
+        assert_eq!(paths.release(1).unwrap().reserved_kbps, 120);
+        assert_eq!(paths.reserve(3, client, server, 100, 0), PathStatus::Success);
+        
+        // 有数据经过的路径保持，空闲的路径超时回收
+        paths.touch(flow_id_of(3), PATH_IDLE_TIMEOUT_MS);
+        assert_eq!(paths.expire(PATH_IDLE_TIMEOUT_MS + 1), 1);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths.reserved_kbps(), 100);
+    }
+    
+    #[test]
+    fn test_default_capacity_admits_default_video_qos() {
+        let client = NodeId::new([0xC1; 6]);
+        let server = NodeId::new([0x51; 6]);
+        let mut config = NodeConfig::defaults(NodeRole::Forward);
+        let mut paths = PathTable::new(config.path_capacity_kbps as u32);
+        
+        // 客户端默认的视频QoS要求500kbps，默认容量至少能容纳一路视频加传感器上报
+        assert_eq!(paths.reserve(1, client, server, 500, 0), PathStatus::Success);
+        assert_eq!(paths.reserve(2, client, server, 10, 0), PathStatus::Success);
+        
+        // 配置变更后按新容量准入，已有的预留保留
+        config.path_capacity_kbps = 600;
+        paths.config_changed(&config, ConfigChanges { paths: true, ..ConfigChanges::default() });
+        assert_eq!(paths.reserve(3, client, server, 500, 0), PathStatus::NoResource);
+        assert_eq!(paths.reserved_kbps(), 510);
+    }
+}
\ No newline at end of file
@@ -1,7 +1,28 @@
 #[cfg(test)]
 mod protocol_parsing_tests {
-    use common::protocol::{NodeId, Beacon, DataPacket, PacketType};
+    use common::protocol::{
+        NodeId, ParseNodeIdError, Beacon, DataPacket, NetworkPacket, PacketHeader, PacketType, NodeRole, MAX_BEACON_HOPS,
+    };
+    use common::hal::Hardware;
+    use common::hal::simulator::{SimChannel, SimHardware};
+    use common::protocol::data::{
+        Fragmenter, PacketError, Reassembler, DEFAULT_TTL, MAX_FRAGMENTS, MAX_FRAGMENT_PAYLOAD, MAX_REASSEMBLED_SIZE, MAX_TTL,
+        REASSEMBLY_TIMEOUT_MS, UNSET_TTL,
+    };
+    use common::protocol::election::ElectionMessage;
+    use common::protocol::batch::{SampleBatch, deserialize_batch, MAX_BATCH_SAMPLES};
+    use common::protocol::command::{
+        deserialize_log_response, CommandStatus, CommandType, Downsample, LoggedSample, QueryPage, QueryParams,
+        LOG_RESPONSE_HEADER_LEN, MAX_LOG_RESPONSE_SAMPLES,
+    };
+    use common::protocol::frame::{VideoTierNotice, DEFAULT_VIDEO_TIER, MAX_VIDEO_FRAME_SIZE, MAX_VIDEO_PAYLOAD, VIDEO_TIERS};
+    use common::protocol::ota::{OtaBody, OtaMessage, OtaStatus, OTA_CHUNK_SIZE, OTA_DIGEST_LEN, OTA_TAG_LEN};
+    use common::protocol::payload::{self, Command, PayloadType, Query, SensorReading, VideoFrame};
+    use common::protocol::tdma::{SlotAllocator, SlotTable, SLOT_REGISTRATION_MS};
+    use common::protocol::time_sync::{TimeBeacon, TIME_BEACON_LEN};
+    use common::security::{receive_secure, send_secure};
     use common::utils::calculate_checksum;
+    use server::video::reassembler::FrameReassembler;
     
     #[test]
     fn test_beacon_creation_and_parsing() {
@@ -26,6 +47,26 @@ mod protocol_parsing_tests {
         assert_eq!(parsed_node_id, node_id);
     }
     
+    #[test]
+    fn test_beacon_relay_hop_limit() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let mut beacon = Beacon::with_role(node_id, 90, -60, NodeRole::Server);
+        beacon.set_sequence(7);
+        
+        // 每次转发跳数加一，源节点、角色和序号保持不变
+        for hops in 1..=MAX_BEACON_HOPS {
+            beacon = beacon.relayed().unwrap();
+            assert_eq!(beacon.hop_count, hops);
+            assert!(beacon.is_valid());
+        }
+        assert_eq!(NodeId(beacon.source), node_id);
+        assert_eq!(beacon.role(), NodeRole::Server);
+        assert_eq!(beacon.sequence(), 7);
+        
+        // 达到最大跳数后不再转发
+        assert!(beacon.relayed().is_none());
+    }
+    
     #[test]
     fn test_data_packet_creation_and_parsing() {
         let source_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
@@ -58,6 +99,66 @@ mod protocol_parsing_tests {
         assert_ne!(checksum, packet.header.checksum); // 应该不相等，因为计算方式不同
     }
     
+    #[test]
+    fn test_data_packet_parse_rejects_truncated() {
+        let source_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let test_data = [0x11, 0x22, 0x33, 0x44, 0x55];
+        let packet = DataPacket::new(source_id, NodeId::BROADCAST, 7, &test_data);
+        
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&packet.header.to_bytes());
+        buffer.extend_from_slice(&test_data);
+        
+        let parsed = DataPacket::parse(&buffer).unwrap();
+        assert_eq!(parsed.data, test_data);
+        assert!(parsed.is_valid());
+        
+        // 负载被截断或头部不完整时不应读越界
+        assert!(DataPacket::parse(&buffer[..buffer.len() - 1]).is_none());
+        assert!(DataPacket::parse(&buffer[..header.len() - 1]).is_none());
+        
+        // 只有头部的信标字节序列长度不符
+        assert!(Beacon::from_bytes(&buffer[..4]).is_none());
+        
+        // 截断的选举结果
+        let result = ElectionMessage::Result { election_id: 3, master: source_id };
+        let mut message = [0u8; 9];
+        let len = result.serialize(&mut message);
+        assert_eq!(ElectionMessage::deserialize(&message[..len]), Some(result));
+        assert!(ElectionMessage::deserialize(&message[..len - 1]).is_none());
+    }
+    
+    #[test]
+    fn test_packet_validation() {
+        let source_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let test_data = [0x11, 0x22, 0x33];
+        let packet = DataPacket::new(source_id, NodeId::BROADCAST, 7, &test_data);
+        assert_eq!(packet.validate(), Ok(()));
+        
+        // 负载被改动
+        let corrupted = [0x11, 0x22, 0x34];
+        let tampered = DataPacket { header: packet.header, data: &corrupted };
+        assert_eq!(tampered.validate(), Err(PacketError::Checksum));
+        
+        // 即使校验和正确，头部字段不合法也要拒绝
+        let mut wrong_version = DataPacket { header: packet.header, data: &test_data };
+        wrong_version.header.version = 9;
+        wrong_version.update_checksum();
+        assert_eq!(wrong_version.validate(), Err(PacketError::Version));
+        
+        let mut wrong_length = DataPacket { header: packet.header, data: &test_data[..2] };
+        wrong_length.update_checksum();
+        assert_eq!(wrong_length.validate(), Err(PacketError::Length));
+        
+        let mut wrong_fragment = DataPacket { header: packet.header, data: &test_data };
+        wrong_fragment.header.fragment_index = 1;
+        wrong_fragment.update_checksum();
+        assert_eq!(wrong_fragment.validate(), Err(PacketError::Fragment));
+        
+        let spoofed = DataPacket::new(NodeId::BROADCAST, source_id, 7, &test_data);
+        assert_eq!(spoofed.validate(), Err(PacketError::Source));
+    }
+    
     #[test]
     fn test_node_id_functions() {
         let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
@@ -74,4 +175,429 @@ mod protocol_parsing_tests {
         assert_eq!(node_id, same_id);
         assert_ne!(node_id, different_id);
     }
-} 
\ No newline at end of file
+    
+    #[test]
+    fn test_node_id_text_form() {
+        let node_id = NodeId::new([0xF1, 0xF2, 0xF3, 0xF4, 0xA5, 0x06]);
+        
+        // 冒号分隔的小写十六进制，简短形式只显示最后两个字节
+        assert_eq!(node_id.to_string(), "f1:f2:f3:f4:a5:06");
+        assert_eq!(node_id.short().to_string(), "…a5:06");
+        
+        // 两种写法都能解析，大小写均可
+        assert_eq!("f1:f2:f3:f4:a5:06".parse::<NodeId>(), Ok(node_id));
+        assert_eq!("F1F2F3F4A506".parse::<NodeId>(), Ok(node_id));
+        assert_eq!(node_id.to_string().parse::<NodeId>(), Ok(node_id));
+        
+        // 长度、分隔或字符不对时拒绝
+        assert_eq!("f1:f2:f3:f4:a5".parse::<NodeId>(), Err(ParseNodeIdError));
+        assert_eq!("f1:f2:f3:f4:a5:06:07".parse::<NodeId>(), Err(ParseNodeIdError));
+        assert_eq!("f1:f2:f3:f4:a5:6".parse::<NodeId>(), Err(ParseNodeIdError));
+        assert_eq!("f1f2f3f4a5g6".parse::<NodeId>(), Err(ParseNodeIdError));
+        assert_eq!("+1f2f3f4a506".parse::<NodeId>(), Err(ParseNodeIdError));
+    }
+    
+    #[test]
+    fn test_sample_batch_round_trip() {
+        let mut batch = SampleBatch::new();
+        assert!(batch.push(1_000, 21.5, 55.25, 101320.0));
+        assert!(batch.push(11_000, -3.75, 80.0, 99850.0));
+        
+        // 在第21秒发送，两个样本分别是20秒和10秒前采集的
+        let mut buffer = [0u8; 64];
+        let len = batch.serialize(0x12345678, 21_000, &mut buffer);
+        
+        let (service_id, samples) = deserialize_batch(&buffer[..len]).unwrap();
+        assert_eq!(service_id, 0x12345678);
+        
+        let samples: Vec<_> = samples.collect();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].age_secs, 20);
+        assert_eq!(samples[0].temperature, 21.5);
+        assert_eq!(samples[0].humidity, 55.25);
+        assert_eq!(samples[0].pressure, 101320.0);
+        assert_eq!(samples[1].age_secs, 10);
+        assert_eq!(samples[1].temperature, -3.75);
+        
+        // 截断的负载应该被拒绝
+        assert!(deserialize_batch(&buffer[..len - 1]).is_none());
+    }
+    
+    #[test]
+    fn test_sample_batch_capacity() {
+        let mut batch = SampleBatch::new();
+        for i in 0..MAX_BATCH_SAMPLES {
+            assert!(batch.push(i as u64, 20.0, 50.0, 100000.0));
+        }
+        
+        // 已满时拒绝新样本
+        assert!(!batch.push(0, 20.0, 50.0, 100000.0));
+        assert_eq!(batch.len(), MAX_BATCH_SAMPLES);
+        
+        batch.clear();
+        assert!(batch.is_empty());
+    }
+    
+    #[test]
+    fn test_ota_chunk_round_trip() {
+        let data = [0xA5u8; OTA_CHUNK_SIZE];
+        let message = OtaMessage {
+            origin: NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]),
+            target: NodeId::new([0x11, 0x12, 0x13, 0x14, 0x15, 0x16]),
+            version: 7,
+            body: OtaBody::Chunk { offset: 4096, data: &data },
+            tag: [0x5A; OTA_TAG_LEN],
+        };
+        
+        let mut buffer = [0u8; 200];
+        let len = message.serialize(&mut buffer);
+        assert_eq!(OtaMessage::deserialize(&buffer[..len]), Some(message));
+        
+        // 超过分块长度的数据应该被拒绝
+        let mut oversized = [0u8; 200];
+        oversized[..len].copy_from_slice(&buffer[..len]);
+        assert!(OtaMessage::deserialize(&oversized[..len + 1]).is_none());
+        
+        // 结果报告
+        let result = OtaMessage { body: OtaBody::Result { status: OtaStatus::CrcMismatch }, ..message };
+        let len = result.serialize(&mut buffer);
+        assert_eq!(OtaMessage::deserialize(&buffer[..len]), Some(result));
+        
+        // 通知带镜像摘要，截断摘要的通知应该被拒绝
+        let offer = OtaMessage { body: OtaBody::Offer { size: 65536, crc: 0x1234_5678, digest: [0xC3; OTA_DIGEST_LEN] }, ..message };
+        let len = offer.serialize(&mut buffer);
+        assert_eq!(OtaMessage::deserialize(&buffer[..len]), Some(offer));
+        assert!(OtaMessage::deserialize(&buffer[..len - 1]).is_none());
+    }
+    
+    #[test]
+    fn test_slot_table_in_time_beacon() {
+        let first = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let second = NodeId::new([0x11, 0x12, 0x13, 0x14, 0x15, 0x16]);
+        let mut allocator = SlotAllocator::new(100);
+        assert_eq!(allocator.register(first, 0), Some(0));
+        assert_eq!(allocator.register(second, 0), Some(1));
+        assert_eq!(allocator.register(first, 1000), Some(0));
+        
+        // 时隙表附在时间信标之后，旧的解析只读取前面的信标
+        let beacon = TimeBeacon { master: first, sequence: 3, network_time: 5000, hop_count: 0 };
+        let mut buffer = [0u8; 128];
+        let len = beacon.serialize(&mut buffer);
+        let len = len + allocator.table().serialize(&mut buffer[len..]);
+        assert_eq!(TimeBeacon::deserialize(&buffer[..len]), Some(beacon));
+        let slots = SlotTable::from_time_sync(&buffer[..len]);
+        assert_eq!(slots, allocator.table());
+        assert!(SlotTable::from_time_sync(&buffer[..TIME_BEACON_LEN]).is_empty());
+        
+        // 超帧200ms，第二个节点的时隙从100ms开始
+        assert!(slots.may_send_bulk(first, 1250));
+        assert_eq!(slots.wait_ms(second, 1250), 50);
+        assert_eq!(slots.wait_ms(first, 1350), 50);
+        
+        // 没有分到时隙的节点不受限制
+        assert!(slots.may_send_bulk(NodeId::BROADCAST, 1350));
+        
+        // 长时间没有续期的时隙被收回，其余节点保持原时隙
+        allocator.expire(SLOT_REGISTRATION_MS + 500);
+        let slots = allocator.table();
+        assert_eq!(slots.slot_of(first), Some(0));
+        assert_eq!(slots.slot_of(second), None);
+        assert_eq!(slots.slot_count(), 1);
+    }
+    
+    #[test]
+    fn test_header_wire_byte_order() {
+        let source_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let packet = DataPacket::new(source_id, NodeId::BROADCAST, 0x1234, &[0xAA; 3]);
+        
+        // 头部多字节字段固定为小端，与主机字节序无关
+        let header = packet.header.to_bytes();
+        assert_eq!(&header[14..16], &[0x34, 0x12]);
+        assert_eq!(&header[19..21], &[0x03, 0x00]);
+        
+        // 流ID在校验和之前，参与校验
+        let flowed = DataPacket::new(source_id, NodeId::BROADCAST, 0x1234, &[0xAA; 3]).with_flow(0x0102);
+        let header = flowed.header.to_bytes();
+        assert_eq!(&header[21..23], &[0x02, 0x01]);
+        assert_ne!(flowed.header.checksum, packet.header.checksum);
+        assert!(flowed.is_valid());
+        
+        let beacon = Beacon::new(source_id, 80, -60);
+        let bytes = beacon.to_bytes();
+        assert_eq!(u16::from_le_bytes([bytes[22], bytes[23]]), beacon.checksum);
+        assert_eq!(Beacon::from_bytes(&bytes).map(|parsed| parsed.to_bytes()), Some(bytes));
+        
+        // 负载中的字段固定为大端，浮点数按位模式传输
+        let page = QueryPage { token: 0x0102, offset: 0x0304_0506 };
+        assert_eq!(page.to_bytes(), [0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let sample = LoggedSample { timestamp: 0x0102, temperature: -3.25, humidity: 0.0, pressure: 0.0 };
+        let bytes = sample.to_bytes();
+        assert_eq!(&bytes[0..8], &[0, 0, 0, 0, 0, 0, 0x01, 0x02]);
+        assert_eq!(&bytes[8..12], &[0xC0, 0x50, 0x00, 0x00]);
+        assert_eq!(LoggedSample::from_bytes(&bytes), Some(sample));
+    }
+    
+    #[test]
+    fn test_log_response_parsing() {
+        let sample = LoggedSample { timestamp: 604_800_000, temperature: -3.25, humidity: 88.5, pressure: 99870.0 };
+        
+        let mut response = vec![CommandType::ReadLog as u8, CommandStatus::Ok as u8];
+        response.extend_from_slice(&1000u32.to_be_bytes());
+        response.push(2);
+        response.extend_from_slice(&sample.to_bytes());
+        response.extend_from_slice(&LoggedSample { timestamp: 604_805_000, ..sample }.to_bytes());
+        
+        let (first_seq, samples) = deserialize_log_response(&response).unwrap();
+        let samples: Vec<_> = samples.collect();
+        assert_eq!(first_seq, 1000);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0], sample);
+        assert_eq!(samples[1].timestamp, 604_805_000);
+        
+        // 样本数与长度不符或执行失败时不解析
+        assert!(deserialize_log_response(&response[..response.len() - 1]).is_none());
+        response[1] = CommandStatus::Unsupported as u8;
+        assert!(deserialize_log_response(&response).is_none());
+        
+        // 样本多的响应超过一帧，分片发送后重组出完整的响应
+        let mut response = vec![CommandType::ReadLog as u8, CommandStatus::Ok as u8];
+        response.extend_from_slice(&0u32.to_be_bytes());
+        response.push(MAX_LOG_RESPONSE_SAMPLES as u8);
+        for i in 0..MAX_LOG_RESPONSE_SAMPLES {
+            response.extend_from_slice(&LoggedSample { timestamp: i as u64 * 1000, ..sample }.to_bytes());
+        }
+        assert!(response.len() > MAX_FRAGMENT_PAYLOAD);
+        assert_eq!(response.len(), LOG_RESPONSE_HEADER_LEN + MAX_LOG_RESPONSE_SAMPLES * LoggedSample::SIZE);
+        
+        let source = NodeId::new([1, 2, 3, 4, 5, 6]);
+        let destination = NodeId::new([6, 5, 4, 3, 2, 1]);
+        let mut reassembler = Reassembler::new();
+        let mut complete = None;
+        for fragment in Fragmenter::new(source, destination, PacketType::Data, 9, &response).unwrap() {
+            assert!(fragment.is_fragment());
+            complete = reassembler.push(&fragment, 0).map(|packet| packet.data.to_vec());
+        }
+        let (_, samples) = deserialize_log_response(complete.as_deref().unwrap()).unwrap();
+        assert_eq!(samples.count(), MAX_LOG_RESPONSE_SAMPLES);
+    }
+    
+    #[test]
+    fn test_video_frame_fragments_reassembled() {
+        let source = NodeId::new([1, 2, 3, 4, 5, 6]);
+        let destination = NodeId::new([6, 5, 4, 3, 2, 1]);
+        
+        // 最大的视频帧经共用的分片器拆分，按视频帧大小的重组器还原
+        let data: Vec<u8> = (0..MAX_VIDEO_FRAME_SIZE).map(|i| (i * 7) as u8).collect();
+        let mut encoded = vec![0u8; MAX_VIDEO_PAYLOAD];
+        let len = payload::encode(&VideoFrame { service_id: 9, frame_number: 3, data: &data }, &mut encoded);
+        assert_eq!(len, MAX_VIDEO_PAYLOAD);
+        let mut frames = FrameReassembler::empty();
+        let mut complete = None;
+        for fragment in Fragmenter::new(source, destination, PacketType::Data, 11, &encoded).unwrap().with_flow(9) {
+            complete = frames.push(&fragment, 0).map(|packet| packet.data.to_vec());
+        }
+        let complete = complete.unwrap();
+        let frame = payload::decode::<VideoFrame>(&complete).unwrap();
+        assert_eq!((frame.service_id, frame.frame_number, frame.data), (9, 3, &data[..]));
+        
+        // 最后一个分片携带满长负载时会超出缓冲区，直接丢弃
+        let max_fragments = MAX_VIDEO_PAYLOAD.div_ceil(MAX_FRAGMENT_PAYLOAD);
+        let mut forged = DataPacket::new(source, destination, 12, &[0xAA; MAX_FRAGMENT_PAYLOAD]).with_flow(9);
+        forged.header.total_fragments = max_fragments as u8;
+        forged.header.fragment_index = max_fragments as u8 - 1;
+        forged.update_checksum();
+        assert!(frames.push(&forged, 0).is_none());
+        assert_eq!(frames.pending(), 0);
+        
+        // 会话关闭时丢弃未收齐的帧
+        let first = Fragmenter::new(source, destination, PacketType::Data, 13, &encoded).unwrap().with_flow(9).next().unwrap();
+        assert!(frames.push(&first, 0).is_none());
+        assert_eq!(frames.pending(), 1);
+        frames.discard_flow(9);
+        assert_eq!(frames.pending(), 0);
+    }
+    
+    #[test]
+    fn test_video_tier_notice() {
+        // 档位按质量从低到高排列，每档都能放进一个视频帧
+        for pair in VIDEO_TIERS.windows(2) {
+            assert!(pair[0].bitrate_kbps() < pair[1].bitrate_kbps());
+        }
+        assert!(VIDEO_TIERS.iter().all(|tier| tier.frame_size as usize <= MAX_VIDEO_FRAME_SIZE));
+        
+        let notice = VideoTierNotice { service_id: 0x01020304, tier: DEFAULT_VIDEO_TIER };
+        let bytes = notice.serialize();
+        assert_eq!(VideoTierNotice::deserialize(&bytes), Some(notice));
+        assert_eq!(notice.video_tier(), VIDEO_TIERS[DEFAULT_VIDEO_TIER as usize]);
+        
+        // 超出档位表的档位被拒绝
+        let mut invalid = bytes;
+        invalid[5] = VIDEO_TIERS.len() as u8;
+        assert_eq!(VideoTierNotice::deserialize(&invalid), None);
+    }
+    
+    #[test]
+    fn test_fragment_reassembly() {
+        let source = NodeId::new([1, 2, 3, 4, 5, 6]);
+        let destination = NodeId::new([6, 5, 4, 3, 2, 1]);
+        let payload: Vec<u8> = (0..MAX_FRAGMENT_PAYLOAD * 2 + 17).map(|i| i as u8).collect();
+        
+        let fragments: Vec<DataPacket> = Fragmenter::new(source, destination, PacketType::Data, 42, &payload)
+            .unwrap()
+            .collect();
+        assert_eq!(fragments.len(), 3);
+        assert!(fragments.iter().all(|fragment| fragment.is_fragment() && fragment.validate().is_ok()));
+        
+        // 乱序到达也能重组
+        let mut reassembler = Reassembler::new();
+        assert!(reassembler.push(&fragments[2], 0).is_none());
+        assert!(reassembler.push(&fragments[0], 10).is_none());
+        let complete = reassembler.push(&fragments[1], 20).unwrap();
+        assert!(!complete.is_fragment());
+        assert_eq!(complete.header.packet_type, PacketType::Data as u8);
+        assert_eq!(complete.data, &payload[..]);
+        
+        // 超时未收齐的分片被丢弃
+        assert!(reassembler.push(&fragments[0], 100).is_none());
+        assert_eq!(reassembler.pending(), 1);
+        assert!(reassembler.push(&fragments[1], 100 + REASSEMBLY_TIMEOUT_MS + 1).is_none());
+        assert!(reassembler.push(&fragments[2], 100 + REASSEMBLY_TIMEOUT_MS + 2).is_none());
+        
+        // 最大长度的负载照常重组
+        let payload: Vec<u8> = (0..MAX_REASSEMBLED_SIZE).map(|i| i as u8).collect();
+        let mut reassembler = Reassembler::new();
+        let mut complete = None;
+        for fragment in Fragmenter::new(source, destination, PacketType::Data, 43, &payload).unwrap() {
+            complete = reassembler.push(&fragment, 0).map(|packet| packet.data.to_vec());
+        }
+        assert_eq!(complete, Some(payload));
+        
+        // 最后一个分片索引合法但携带满长负载时会超出重组缓冲区，直接丢弃
+        let mut forged = DataPacket::new(source, destination, 44, &[0xAA; MAX_FRAGMENT_PAYLOAD]);
+        forged.header.total_fragments = MAX_FRAGMENTS as u8;
+        forged.header.fragment_index = MAX_FRAGMENTS as u8 - 1;
+        forged.update_checksum();
+        assert!(forged.validate().is_ok());
+        assert!(reassembler.push(&forged, 0).is_none());
+        assert_eq!(reassembler.pending(), 0);
+    }
+    
+    #[test]
+    fn test_ttl_in_header() {
+        let source = NodeId::new([1, 2, 3, 4, 5, 6]);
+        let destination = NodeId::new([6, 5, 4, 3, 2, 1]);
+        let packet = DataPacket::new(source, destination, 7, &[1, 2, 3]);
+        assert_eq!(packet.header.ttl, UNSET_TTL);
+        
+        // 跳数参与校验，转发节点递减后重新计算校验和
+        let relayed = DataPacket::new(source, destination, 7, &[1, 2, 3]).with_ttl(DEFAULT_TTL - 1);
+        assert_ne!(relayed.header.checksum, packet.header.checksum);
+        assert!(relayed.validate().is_ok());
+        
+        let mut buffer = [0u8; 64];
+        let len = relayed.encode(&mut buffer).unwrap();
+        assert_eq!(buffer[18], DEFAULT_TTL - 1);
+        let parsed = DataPacket::parse(&buffer[..len]).unwrap();
+        assert_eq!(parsed.header.ttl, DEFAULT_TTL - 1);
+        assert!(parsed.is_valid());
+    }
+    
+    #[test]
+    fn test_default_ttl_is_per_node() {
+        let channel = SimChannel::new();
+        let first = NodeId::new([1, 1, 1, 1, 1, 1]);
+        let second = NodeId::new([2, 2, 2, 2, 2, 2]);
+        let mut first_node = SimHardware::new(first, channel.clone());
+        let mut second_node = SimHardware::new(second, channel);
+        first_node.set_default_ttl(4);
+        second_node.set_default_ttl(MAX_TTL + 1);
+        assert_eq!(first_node.default_ttl(), 4);
+        assert_eq!(second_node.default_ttl(), MAX_TTL);
+        
+        // 新建的包发送时填入发送方自己的跳数限制，转发时设置的跳数原样发出
+        let mut buffer = [0u8; 64];
+        send_secure(&mut first_node, &DataPacket::new(first, second, 1, &[1])).unwrap();
+        let received = receive_secure(&mut second_node, &mut buffer).unwrap();
+        assert_eq!(received.header.ttl, 4);
+        
+        send_secure(&mut second_node, &DataPacket::new(second, first, 2, &[2])).unwrap();
+        let received = receive_secure(&mut first_node, &mut buffer).unwrap();
+        assert_eq!(received.header.ttl, MAX_TTL);
+        
+        send_secure(&mut second_node, &DataPacket::new(second, first, 3, &[3]).with_ttl(2)).unwrap();
+        let received = receive_secure(&mut first_node, &mut buffer).unwrap();
+        assert_eq!(received.header.ttl, 2);
+    }
+    
+    #[test]
+    fn test_network_packet_wire_format() {
+        let mut payload = [0u8; 252];
+        payload[0] = 0xAB;
+        payload[251] = 0xCD;
+        let packet = NetworkPacket {
+            header: PacketHeader {
+                magic: 0x4C4E,
+                version: 6,
+                packet_type: PacketType::Data,
+                ttl: 3,
+                src_mac: [1, 2, 3, 4, 5, 6],
+                dest_mac: [6, 5, 4, 3, 2, 1],
+                checksum: 0x1234_5678,
+            },
+            payload,
+        };
+        
+        // 多字节字段固定为小端，与主机字节序无关
+        let bytes = packet.to_bytes();
+        assert_eq!(bytes.len(), NetworkPacket::SIZE);
+        assert_eq!(&bytes[0..2], &[0x4E, 0x4C]);
+        assert_eq!(bytes[3], PacketType::Data as u8);
+        assert_eq!(&bytes[17..21], &[0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(bytes[PacketHeader::SIZE], 0xAB);
+        
+        let parsed = NetworkPacket::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.header.to_bytes(), packet.header.to_bytes());
+        assert_eq!(parsed.payload, packet.payload);
+        
+        // 截断的包和未知包类型被拒绝
+        assert!(NetworkPacket::from_bytes(&bytes[..NetworkPacket::SIZE - 1]).is_none());
+        let mut unknown = bytes;
+        unknown[3] = 0xEE;
+        assert!(NetworkPacket::from_bytes(&unknown).is_none());
+    }
+    
+    #[test]
+    fn test_typed_payload_codec() {
+        let mut buffer = [0u8; 64];
+        
+        let reading = SensorReading { service_id: 7, temperature: 21.5, humidity: 40.25, pressure: 101325.0 };
+        let len = payload::encode(&reading, &mut buffer);
+        assert_eq!(PayloadType::of(&buffer[..len]), Some(PayloadType::SensorReading));
+        assert_eq!(payload::decode::<SensorReading>(&buffer[..len]), Some(reading));
+        assert!(payload::decode::<SensorReading>(&buffer[..len - 1]).is_none());
+        // 类型字节不符时不按其他类型解析
+        assert!(payload::decode::<Query>(&buffer[..len]).is_none());
+        // 缓冲区不足时不写入部分负载
+        assert_eq!(payload::encode(&reading, &mut buffer[..8]), 0);
+        
+        let command = Command { command: CommandType::ReadLog, parameters: &[0, 0, 0, 5, 10] };
+        let len = payload::encode(&command, &mut buffer);
+        assert_eq!(payload::decode::<Command>(&buffer[..len]), Some(command));
+        buffer[1] = 0xEE;
+        assert!(payload::decode::<Command>(&buffer[..len]).is_none());
+        
+        let query = Query {
+            params: QueryParams { start_time: 1000, end_time: 5000, downsample: Downsample::MaxRecords(10) },
+            page: QueryPage { token: 3, offset: 400 },
+        };
+        let len = payload::encode(&query, &mut buffer);
+        assert_eq!(payload::decode::<Query>(&buffer[..len]), Some(query));
+        
+        // 视频帧负载为整帧，空帧被拒绝
+        let frame = VideoFrame { service_id: 9, frame_number: 3, data: &[1, 2, 3] };
+        let len = payload::encode(&frame, &mut buffer);
+        assert_eq!(payload::decode::<VideoFrame>(&buffer[..len]), Some(frame));
+        let len = payload::encode(&VideoFrame { data: &[], ..frame }, &mut buffer);
+        assert!(payload::decode::<VideoFrame>(&buffer[..len]).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,494 @@
+#[cfg(test)]
+mod protocol_properties_tests {
+    use common::protocol::{
+        Beacon, DataPacket, NodeId, PacketType, QosRequirements, ServiceClose, ServiceHandover, ServiceRenewal,
+        ServiceRequest, ServiceResponse, ServiceType, SERVICE_REQUEST_V1_FULL_EXPIRY_LEN, SERVICE_REQUEST_V1_LEN,
+        SERVICE_RESPONSE_LEN, SERVICE_RESPONSE_V1_LEN,
+        serialize_service_request, deserialize_service_request,
+        serialize_service_response, deserialize_service_response,
+        serialize_service_renewal, deserialize_service_renewal,
+        serialize_service_handover, deserialize_service_handover,
+        serialize_service_close, deserialize_service_close,
+    };
+    use common::protocol::batch::{SampleBatch, deserialize_batch, MAX_BATCH_SAMPLES};
+    use common::protocol::echo::{Echo, Trace, MAX_ECHO_PAYLOAD_LEN, MAX_TRACE_HOPS};
+    use common::protocol::election::{Candidacy, ElectionMessage};
+    use common::protocol::error_report::{ErrorCode, ErrorReport, ERROR_REPORT_LEN};
+    use common::protocol::frame::MAX_VIDEO_FRAME_SIZE;
+    use common::protocol::keepalive::{PathKeepAlive, KEEPALIVE_PAYLOAD_LEN};
+    use common::protocol::mgmt::{MgmtMessage, MgmtOp, MgmtStatus, MAX_MGMT_VALUE};
+    use common::protocol::ota::{OtaBody, OtaMessage, OtaStatus, OTA_CHUNK_SIZE, OTA_DIGEST_LEN, OTA_TAG_LEN};
+    use common::protocol::payload::{self, VideoFrame};
+    use common::protocol::time_sync::TimeBeacon;
+    use common::protocol::topology::{TopologyMessage, TopologyReport, MAX_TOPOLOGY_ENTRIES};
+    use common::security::MAX_SECURE_PAYLOAD;
+    use proptest::prelude::*;
+    
+    /// 数据包头部长度
+    const HEADER_LEN: usize = common::protocol::data::DataHeader::SIZE;
+    
+    /// 头部中数据长度字段的偏移，翻转它会改变解析出的负载范围
+    const DATA_LENGTH_OFFSET: usize = 18;
+    
+    fn node_id() -> impl Strategy<Value = NodeId> {
+        any::<[u8; 6]>().prop_map(NodeId)
+    }
+    
+    fn service_type() -> impl Strategy<Value = ServiceType> {
+        prop_oneof![
+            Just(ServiceType::Storage),
+            Just(ServiceType::Processing),
+            Just(ServiceType::Gateway),
+            Just(ServiceType::VideoRelay),
+            Just(ServiceType::AudioRelay),
+            Just(ServiceType::DataRelay),
+            Just(ServiceType::SensorCollection),
+        ]
+    }
+    
+    fn service_request() -> impl Strategy<Value = ServiceRequest> {
+        (any::<u16>(), service_type(), any::<u16>(), any::<u16>(), any::<u8>(), any::<u32>())
+            .prop_map(|(request_id, service_type, min_bandwidth, max_latency, reliability, expiry_time)| ServiceRequest {
+                request_id,
+                service_type,
+                qos: QosRequirements { min_bandwidth, max_latency, reliability },
+                expiry_time,
+            })
+    }
+    
+    fn mgmt_op() -> impl Strategy<Value = MgmtOp> {
+        prop_oneof![
+            Just(MgmtOp::Get),
+            Just(MgmtOp::Set),
+            Just(MgmtOp::Response),
+        ]
+    }
+    
+    fn mgmt_status() -> impl Strategy<Value = MgmtStatus> {
+        prop_oneof![
+            Just(MgmtStatus::Ok),
+            Just(MgmtStatus::UnknownAttribute),
+            Just(MgmtStatus::ReadOnly),
+            Just(MgmtStatus::InvalidValue),
+            Just(MgmtStatus::Unauthorized),
+            Just(MgmtStatus::Unsupported),
+        ]
+    }
+    
+    fn ota_status() -> impl Strategy<Value = OtaStatus> {
+        prop_oneof![
+            Just(OtaStatus::Installed),
+            Just(OtaStatus::CrcMismatch),
+            Just(OtaStatus::TooLarge),
+            Just(OtaStatus::StorageError),
+            Just(OtaStatus::UpToDate),
+            Just(OtaStatus::DigestMismatch),
+        ]
+    }
+    
+    /// 将数据包编码为无线电上的字节序列
+    fn encode(packet: &DataPacket) -> Vec<u8> {
+        let mut buffer = vec![0u8; HEADER_LEN + packet.data.len()];
+        packet.encode(&mut buffer).unwrap();
+        buffer
+    }
+    
+    proptest! {
+        #[test]
+        fn service_request_round_trip(request in service_request()) {
+            let mut buffer = [0u8; 16];
+            let len = serialize_service_request(&request, &mut buffer);
+            prop_assert_eq!(deserialize_service_request(&buffer[..len]), Some(request));
+        }
+        
+        #[test]
+        fn unversioned_service_requests_decode(request in service_request()) {
+            // v1只带过期时间高16位；加入版本字节之前的10字节格式在其后补上低16位
+            let request = ServiceRequest { request_id: 0, ..request };
+            let mut buffer = [0u8; 16];
+            let len = serialize_service_request_v1(&request, &mut buffer);
+            let truncated = ServiceRequest { expiry_time: request.expiry_time & 0xFFFF_0000, ..request };
+            prop_assert_eq!(deserialize_service_request(&buffer[..len]), Some(truncated));
+            
+            buffer[len..SERVICE_REQUEST_V1_FULL_EXPIRY_LEN].copy_from_slice(&(request.expiry_time as u16).to_be_bytes());
+            prop_assert_eq!(deserialize_service_request(&buffer[..SERVICE_REQUEST_V1_FULL_EXPIRY_LEN]), Some(request));
+        }
+        
+        #[test]
+        fn service_response_round_trip(request_id in any::<u16>(), service_id in any::<u32>(), server in node_id(), status in any::<u8>()) {
+            let response = ServiceResponse { request_id, service_id, server_node_id: server, status };
+            let mut buffer = [0u8; 16];
+            let len = serialize_service_response(&response, &mut buffer);
+            prop_assert_eq!(deserialize_service_response(&buffer[..len]), Some(response));
+        }
+        
+        #[test]
+        fn service_renewal_round_trip(service_id in any::<u32>(), expiry_time in any::<u32>()) {
+            let renewal = ServiceRenewal { service_id, expiry_time };
+            let mut buffer = [0u8; 16];
+            let len = serialize_service_renewal(&renewal, &mut buffer);
+            prop_assert_eq!(deserialize_service_renewal(&buffer[..len]), Some(renewal));
+        }
+        
+        #[test]
+        fn service_handover_round_trip(service_id in any::<u32>(), server in node_id(), request in service_request()) {
+            let handover = ServiceHandover { service_id, server_node_id: server, request };
+            let mut buffer = [0u8; 32];
+            let len = serialize_service_handover(&handover, &mut buffer);
+            prop_assert_eq!(deserialize_service_handover(&buffer[..len]), Some(handover));
+        }
+        
+        #[test]
+        fn service_close_round_trip(service_id in any::<u32>(), reason in any::<u8>()) {
+            let close = ServiceClose { service_id, reason };
+            let mut buffer = [0u8; 8];
+            let len = serialize_service_close(&close, &mut buffer);
+            prop_assert_eq!(deserialize_service_close(&buffer[..len]), Some(close));
+        }
+        
+        #[test]
+        fn keepalive_round_trip(service_id in any::<u32>(), sequence in any::<u16>()) {
+            let keepalive = PathKeepAlive { service_id, sequence };
+            let mut buffer = [0u8; KEEPALIVE_PAYLOAD_LEN];
+            let len = keepalive.serialize(&mut buffer);
+            prop_assert_eq!(PathKeepAlive::deserialize(&buffer[..len]), Some(keepalive));
+        }
+        
+        #[test]
+        fn echo_round_trip(origin in node_id(), target in node_id(), sent_at in any::<u64>(), hop_count in any::<u8>()) {
+            let echo = Echo { origin, target, sent_at, hop_count, trace: None };
+            let mut buffer = [0u8; 32];
+            let len = echo.serialize(&mut buffer);
+            prop_assert_eq!(Echo::deserialize(&buffer[..len]), Some(echo));
+        }
+        
+        #[test]
+        fn echo_trace_round_trip(
+            origin in node_id(),
+            target in node_id(),
+            hops in proptest::collection::vec((node_id(), any::<i8>()), 0..=MAX_TRACE_HOPS)
+        ) {
+            let mut echo = Echo { origin, target, sent_at: 0, hop_count: 0, trace: Some(Trace::new()) };
+            for (node, rssi) in hops.iter() {
+                echo.record_hop(*node, *rssi);
+            }
+            prop_assert_eq!(echo.hop_count as usize, hops.len());
+            
+            let mut buffer = [0u8; MAX_ECHO_PAYLOAD_LEN];
+            let len = echo.serialize(&mut buffer);
+            prop_assert!(len <= MAX_SECURE_PAYLOAD);
+            prop_assert_eq!(Echo::deserialize(&buffer[..len]), Some(echo));
+        }
+        
+        #[test]
+        fn time_beacon_round_trip(master in node_id(), sequence in any::<u16>(), network_time in any::<u64>(), hop_count in any::<u8>()) {
+            let beacon = TimeBeacon { master, sequence, network_time, hop_count };
+            let mut buffer = [0u8; 32];
+            let len = beacon.serialize(&mut buffer);
+            prop_assert_eq!(TimeBeacon::deserialize(&buffer[..len]), Some(beacon));
+        }
+        
+        #[test]
+        fn video_frame_round_trip(
+            service_id in any::<u32>(),
+            frame_number in any::<u16>(),
+            data in proptest::collection::vec(any::<u8>(), 1..=MAX_VIDEO_FRAME_SIZE)
+        ) {
+            let frame = VideoFrame { service_id, frame_number, data: &data };
+            let mut buffer = vec![0u8; MAX_VIDEO_FRAME_SIZE + 16];
+            let len = payload::encode(&frame, &mut buffer);
+            prop_assert_eq!(payload::decode::<VideoFrame>(&buffer[..len]), Some(frame));
+        }
+        
+        #[test]
+        fn election_message_round_trip(
+            election_id in any::<u16>(),
+            (priority, battery_level, load) in (any::<u8>(), 0..=100u8, 0..=100u8),
+            master in node_id(),
+            kind in 0..3u8
+        ) {
+            let candidacy = Candidacy { priority, battery_level, load };
+            let message = match kind {
+                0 => ElectionMessage::Start { election_id, candidacy },
+                1 => ElectionMessage::Response { election_id, candidacy },
+                _ => ElectionMessage::Result { election_id, master },
+            };
+            let mut buffer = [0u8; 16];
+            let len = message.serialize(&mut buffer);
+            prop_assert_eq!(ElectionMessage::deserialize(&buffer[..len]), Some(message));
+        }
+        
+        #[test]
+        fn mgmt_message_round_trip(
+            op in mgmt_op(),
+            status in mgmt_status(),
+            origin in node_id(),
+            target in node_id(),
+            sequence in any::<u32>(),
+            attribute in any::<u8>(),
+            value in proptest::collection::vec(any::<u8>(), 0..=MAX_MGMT_VALUE)
+        ) {
+            let message = MgmtMessage {
+                op,
+                origin,
+                target,
+                sequence,
+                attribute,
+                status,
+                value: &value,
+            };
+            let mut buffer = [0u8; 256];
+            let len = message.serialize(&mut buffer);
+            let empty: &[u8] = &[];
+            prop_assert_eq!(MgmtMessage::deserialize(&buffer[..len]), Some((message, empty)));
+        }
+        
+        #[test]
+        fn ota_message_round_trip(
+            origin in node_id(),
+            target in node_id(),
+            version in any::<u32>(),
+            offset in any::<u32>(),
+            crc in any::<u32>(),
+            digest in any::<[u8; OTA_DIGEST_LEN]>(),
+            tag in any::<[u8; OTA_TAG_LEN]>(),
+            status in ota_status(),
+            data in proptest::collection::vec(any::<u8>(), 1..=OTA_CHUNK_SIZE),
+            kind in 0..4u8
+        ) {
+            let body = match kind {
+                0 => OtaBody::Offer { size: offset, crc, digest },
+                1 => OtaBody::Request { offset },
+                2 => OtaBody::Chunk { offset, data: &data },
+                _ => OtaBody::Result { status },
+            };
+            let message = OtaMessage { origin, target, version, body, tag };
+            let mut buffer = [0u8; 256];
+            let len = message.serialize(&mut buffer);
+            prop_assert_eq!(OtaMessage::deserialize(&buffer[..len]), Some(message));
+        }
+        
+        #[test]
+        fn sample_batch_round_trip(
+            service_id in any::<u32>(),
+            samples in proptest::collection::vec((0..60_000u64, -4000..4000i16, 0..10_000u16, 0..6553u16), 1..=MAX_BATCH_SAMPLES)
+        ) {
+            // 只取线上格式可以精确表示的值，量化误差不属于往返失真
+            let now = 60_000_000;
+            let mut batch = SampleBatch::new();
+            for (age_secs, temperature, humidity, pressure) in &samples {
+                batch.push(now - age_secs * 1000, *temperature as f32 / 100.0, *humidity as f32 / 100.0, *pressure as f32 * 10.0);
+            }
+            
+            let mut buffer = [0u8; 256];
+            let len = batch.serialize(service_id, now, &mut buffer);
+            let (parsed_id, parsed) = deserialize_batch(&buffer[..len]).unwrap();
+            prop_assert_eq!(parsed_id, service_id);
+            
+            let parsed: Vec<_> = parsed.collect();
+            prop_assert_eq!(parsed.len(), samples.len());
+            for (sample, (age_secs, temperature, humidity, pressure)) in parsed.iter().zip(&samples) {
+                prop_assert_eq!(sample.age_secs as u64, *age_secs);
+                prop_assert_eq!((sample.temperature * 100.0).round() as i16, *temperature);
+                prop_assert_eq!((sample.humidity * 100.0).round() as u16, *humidity);
+                prop_assert_eq!((sample.pressure / 10.0).round() as u16, *pressure);
+            }
+        }
+        
+        #[test]
+        fn topology_report_round_trip(
+            master in node_id(),
+            request_id in any::<u16>(),
+            reporter in node_id(),
+            routes in proptest::collection::vec((node_id(), node_id(), any::<i8>()), 0..=MAX_TOPOLOGY_ENTRIES)
+        ) {
+            let message = TopologyMessage::Report(TopologyReport::from_routes(master, request_id, reporter, routes.clone()));
+            let mut buffer = [0u8; MAX_SECURE_PAYLOAD];
+            let len = message.serialize(&mut buffer);
+            
+            prop_assert!(len > 0);
+            prop_assert_eq!(TopologyMessage::deserialize(&buffer[..len]), Some(message));
+            prop_assert!(TopologyMessage::deserialize(&buffer[..len - 1]).is_none());
+        }
+        
+        #[test]
+        fn error_report_round_trip(
+            origin in node_id(),
+            code in proptest::sample::select(ErrorCode::ALL.to_vec()),
+            occurrences in any::<u16>(),
+            timestamp in any::<u64>(),
+            detail in any::<u32>()
+        ) {
+            let report = ErrorReport { origin, code, occurrences, timestamp, detail };
+            let mut buffer = [0u8; ERROR_REPORT_LEN];
+            prop_assert_eq!(report.serialize(&mut buffer), ERROR_REPORT_LEN);
+            prop_assert_eq!(ErrorReport::deserialize(&buffer), Some(report));
+            prop_assert_eq!(ErrorCode::from_u8(code as u8), Some(code));
+            prop_assert_eq!(code.category() as u8, code as u8 >> 4);
+        }
+        
+        #[test]
+        fn data_packet_round_trip(
+            source in node_id(),
+            destination in node_id(),
+            packet_id in any::<u16>(),
+            data in proptest::collection::vec(any::<u8>(), 0..=200)
+        ) {
+            let packet = DataPacket::new(source, destination, packet_id, &data);
+            let buffer = encode(&packet);
+            let parsed = DataPacket::parse(&buffer).unwrap();
+            
+            prop_assert!(parsed.is_valid());
+            prop_assert_eq!(parsed.header.packet_type, PacketType::Data as u8);
+            prop_assert_eq!(parsed.header.source, source.0);
+            prop_assert_eq!(parsed.header.destination, destination.0);
+            let parsed_id = parsed.header.packet_id;
+            prop_assert_eq!(parsed_id, packet_id);
+            prop_assert_eq!(parsed.data, &data[..]);
+        }
+        
+        #[test]
+        fn data_packet_checksum_detects_single_bit_flip(
+            source in node_id(),
+            packet_id in any::<u16>(),
+            data in proptest::collection::vec(any::<u8>(), 1..=200),
+            bit in any::<prop::sample::Index>()
+        ) {
+            let packet = DataPacket::new(source, NodeId::BROADCAST, packet_id, &data);
+            let mut buffer = encode(&packet);
+            
+            let mut bit = bit.index(buffer.len() * 8);
+            if bit / 8 == DATA_LENGTH_OFFSET || bit / 8 == DATA_LENGTH_OFFSET + 1 {
+                // 长度字段被破坏时由解析负责拒绝或截取，这里只检验校验和
+                bit += 16;
+            }
+            buffer[bit / 8] ^= 1 << (bit % 8);
+            
+            let parsed = DataPacket::parse(&buffer).unwrap();
+            prop_assert!(!parsed.is_valid());
+        }
+        
+        #[test]
+        fn beacon_checksum_detects_single_bit_flip(
+            source in node_id(),
+            battery_level in any::<u8>(),
+            rssi in any::<i8>(),
+            bit in 0..Beacon::SIZE * 8
+        ) {
+            let beacon = Beacon::new(source, battery_level, rssi);
+            let mut bytes = beacon.to_bytes();
+            prop_assert!(Beacon::from_bytes(&bytes).unwrap().is_valid());
+            
+            bytes[bit / 8] ^= 1 << (bit % 8);
+            // 类型字节被破坏时解析直接拒绝
+            if let Some(corrupted) = Beacon::from_bytes(&bytes) {
+                prop_assert!(!corrupted.is_valid());
+            }
+        }
+    }
+    
+    #[test]
+    fn service_request_keeps_max_expiry_time() {
+        let request = ServiceRequest {
+            request_id: u16::MAX,
+            service_type: ServiceType::Storage,
+            qos: QosRequirements { min_bandwidth: u16::MAX, max_latency: u16::MAX, reliability: u8::MAX },
+            expiry_time: u32::MAX,
+        };
+        
+        let mut buffer = [0u8; 16];
+        let len = serialize_service_request(&request, &mut buffer);
+        assert_eq!(deserialize_service_request(&buffer[..len]).unwrap().expiry_time, u32::MAX);
+    }
+    
+    /// 与引入版本字节之前的序列化完全相同：只写出过期时间的高16位
+    fn serialize_service_request_v1(request: &ServiceRequest, buffer: &mut [u8]) -> usize {
+        if buffer.len() < 8 {
+            return 0;
+        }
+        
+        buffer[0] = request.service_type as u8;
+        
+        let bandwidth_bytes = request.qos.min_bandwidth.to_be_bytes();
+        buffer[1] = bandwidth_bytes[0];
+        buffer[2] = bandwidth_bytes[1];
+        
+        let latency_bytes = request.qos.max_latency.to_be_bytes();
+        buffer[3] = latency_bytes[0];
+        buffer[4] = latency_bytes[1];
+        
+        buffer[5] = request.qos.reliability;
+        
+        let expiry_bytes = request.expiry_time.to_be_bytes();
+        buffer[6] = expiry_bytes[0];
+        buffer[7] = expiry_bytes[1];
+        
+        8
+    }
+    
+    #[test]
+    fn service_messages_decode_v1_format() {
+        // v1请求：类型(1) 带宽(2) 延迟(2) 可靠性(1) 过期时间高16位(2)，没有请求ID
+        let original = ServiceRequest {
+            request_id: 0,
+            service_type: ServiceType::Processing,
+            qos: QosRequirements { min_bandwidth: 500, max_latency: 100, reliability: 80 },
+            expiry_time: 86400,
+        };
+        let mut request = [0u8; 16];
+        let len = serialize_service_request_v1(&original, &mut request);
+        assert_eq!(len, SERVICE_REQUEST_V1_LEN);
+        let request = &request[..len];
+        // 低16位不在线上，解码结果与旧服务器相同
+        assert_eq!(deserialize_service_request(request), Some(ServiceRequest { expiry_time: 0x0001_0000, ..original }));
+        
+        // 旧客户端漫游时发送的切换请求内嵌v1请求，共18字节
+        let mut handover = [0u8; 10 + SERVICE_REQUEST_V1_LEN];
+        handover[0..4].copy_from_slice(&42u32.to_be_bytes());
+        handover[4..10].copy_from_slice(&[0xAA; 6]);
+        handover[10..].copy_from_slice(request);
+        assert_eq!(deserialize_service_handover(&handover), Some(ServiceHandover {
+            service_id: 42,
+            server_node_id: NodeId::new([0xAA; 6]),
+            request: ServiceRequest { expiry_time: 0x0001_0000, ..original },
+        }));
+        
+        // v1响应：服务ID(4) 服务器(6) 状态(1)
+        let mut response = [0u8; SERVICE_RESPONSE_V1_LEN];
+        response[0..4].copy_from_slice(&0x8000_0001u32.to_be_bytes());
+        response[4..10].copy_from_slice(&[0xAA; 6]);
+        assert_eq!(deserialize_service_response(&response), Some(ServiceResponse {
+            request_id: 0,
+            service_id: 0x8000_0001,
+            server_node_id: NodeId::new([0xAA; 6]),
+            status: 0,
+        }));
+        
+        // 未知版本直接拒绝
+        let mut buffer = [0u8; 16];
+        let len = serialize_service_request(&ServiceRequest { request_id: 7, ..deserialize_service_request(&request).unwrap() }, &mut buffer);
+        buffer[0] = 0x83;
+        assert_eq!(deserialize_service_request(&buffer[..len]), None);
+    }
+    
+    #[test]
+    fn uncorrelated_response_uses_v1_format() {
+        // 请求ID为0的响应发给v1客户端，必须是11字节且服务ID在开头
+        let response = ServiceResponse {
+            request_id: 0,
+            service_id: 0x0102_0304,
+            server_node_id: NodeId::new([0xAA; 6]),
+            status: 0,
+        };
+        let mut buffer = [0u8; 16];
+        let len = serialize_service_response(&response, &mut buffer);
+        assert_eq!(len, SERVICE_RESPONSE_V1_LEN);
+        assert_eq!(&buffer[0..4], &0x0102_0304u32.to_be_bytes());
+        assert_eq!(deserialize_service_response(&buffer[..len]), Some(response));
+        
+        // 带请求ID的响应保持当前格式
+        let correlated = ServiceResponse { request_id: 9, service_id: 0x0102_0304, server_node_id: NodeId::new([0xAA; 6]), status: 0 };
+        assert_eq!(serialize_service_response(&correlated, &mut buffer), SERVICE_RESPONSE_LEN);
+        assert_eq!(deserialize_service_response(&buffer[..SERVICE_RESPONSE_LEN]), Some(correlated));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,284 @@
+#[cfg(test)]
+mod replay_counters_tests {
+    use common::hal::{FirmwareStorage, Hardware};
+    use common::hal::nvs::{keys, NvStorage};
+    use common::hal::simulator::{SimChannel, SimHardware};
+    use common::mgmt::{Managed, MgmtAgent, MAX_REQUESTERS};
+    use common::ota::{image_digest, OtaEvent, OtaReceiver};
+    use common::protocol::{Beacon, DataPacket, NodeId, NodeRole, PacketType, MAX_PACKET_SIZE};
+    use common::protocol::mgmt::{MgmtAttribute, MgmtMessage, MgmtOp, MgmtStatus};
+    use common::protocol::ota::{OtaBody, OtaMessage, OtaStatus, MAX_OTA_MESSAGE, OTA_TAG_LEN};
+    use common::protocol::command::{CommandType, COMMAND_PAYLOAD_TYPE};
+    use common::security::{
+        beacon_tag, command_tag, management_tag, secure_unwrap, secure_wrap, sign_ota, verify_command,
+        SecurityContext, SecurityError,
+    };
+    
+    const KEY: [u8; 16] = [0x5A; 16];
+    
+    fn context() -> SecurityContext {
+        let mut context = SecurityContext::new();
+        context.set_network_key(Some(KEY));
+        context
+    }
+    
+    /// 保护一个包，返回头部和密文，便于多次投递模拟重放
+    fn wrap(sender: &mut SecurityContext, packet_id: u16) -> (common::protocol::data::DataHeader, Vec<u8>) {
+        let source = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let destination = NodeId::new([0x11, 0x12, 0x13, 0x14, 0x15, 0x16]);
+        let packet = DataPacket::new(source, destination, packet_id, b"reading");
+        let mut out = [0u8; MAX_PACKET_SIZE];
+        let wrapped = secure_wrap(sender, &packet, &mut out).unwrap();
+        (wrapped.header, wrapped.data.to_vec())
+    }
+    
+    fn deliver(receiver: &mut SecurityContext, frame: &(common::protocol::data::DataHeader, Vec<u8>)) -> Result<usize, SecurityError> {
+        let mut header = frame.0;
+        let mut data = frame.1.clone();
+        secure_unwrap(receiver, &mut header, &mut data)
+    }
+    
+    #[test]
+    fn test_replay_rejected_after_reboot() {
+        let mut sender = context();
+        let mut receiver = context();
+        
+        let first = wrap(&mut sender, 1);
+        let second = wrap(&mut sender, 2);
+        assert!(deliver(&mut receiver, &first).is_ok());
+        assert!(receiver.needs_checkpoint());
+        let saved = receiver.peer_counters_to_bytes();
+        assert!(deliver(&mut receiver, &second).is_ok());
+        
+        // 重启后恢复保存的上限，重启前收到的包都不能被重放
+        let mut rebooted = context();
+        rebooted.restore_peer_counters(&saved);
+        assert!(!rebooted.needs_checkpoint());
+        assert_eq!(deliver(&mut rebooted, &first), Err(SecurityError::Replay));
+        assert_eq!(deliver(&mut rebooted, &second), Err(SecurityError::Replay));
+        
+        // 对端计数器越过上限后恢复通信
+        let mut accepted = None;
+        for packet_id in 3..100 {
+            let frame = wrap(&mut sender, packet_id);
+            if deliver(&mut rebooted, &frame).is_ok() {
+                accepted = Some(packet_id);
+                break;
+            }
+        }
+        assert!(matches!(accepted, Some(id) if id <= 66));
+    }
+    
+    #[test]
+    fn test_beacon_authentication() {
+        let node = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let sender = context();
+        let mut beacon = Beacon::with_role(node, 90, -50, NodeRole::Server);
+        beacon.set_tag(beacon_tag(&sender, &beacon).unwrap());
+        assert!(beacon.is_valid());
+        
+        // 线上格式保留认证码，转发增加跳数后认证码仍然有效
+        let received = Beacon::from_bytes(&beacon.to_bytes()).unwrap();
+        let receiver = context();
+        assert_eq!(beacon_tag(&receiver, &received), Some(received.tag));
+        let relayed = received.relayed().unwrap();
+        assert_eq!(beacon_tag(&receiver, &relayed), Some(relayed.tag));
+        
+        // 篡改内容或使用其他网络密钥时认证码不符
+        let mut forged = received;
+        forged.battery_level = 100;
+        forged.update_checksum();
+        assert!(forged.is_valid());
+        assert_ne!(beacon_tag(&receiver, &forged), Some(forged.tag));
+        let mut other = SecurityContext::new();
+        other.set_network_key(Some([0xA5; 16]));
+        assert_ne!(beacon_tag(&other, &received), Some(received.tag));
+        
+        // 未配置网络密钥时不做认证
+        assert_eq!(beacon_tag(&SecurityContext::new(), &received), None);
+    }
+    
+    /// 记录执行过的设置请求
+    #[derive(Default)]
+    struct CountingNode {
+        sets: usize,
+    }
+    
+    impl Managed for CountingNode {
+        fn get_attribute<H: Hardware>(
+            &mut self,
+            _hardware: &mut H,
+            _attribute: MgmtAttribute,
+            _arg: &[u8],
+            _out: &mut [u8]
+        ) -> Result<usize, MgmtStatus> {
+            Ok(0)
+        }
+        
+        fn set_attribute<H: Hardware>(
+            &mut self,
+            _hardware: &mut H,
+            _attribute: MgmtAttribute,
+            _value: &[u8]
+        ) -> Result<(), MgmtStatus> {
+            self.sets += 1;
+            Ok(())
+        }
+    }
+    
+    /// 构造一个带认证码的设置请求
+    fn set_request(hardware: &mut SimHardware, origin: NodeId, sequence: u32) -> Vec<u8> {
+        let request = MgmtMessage {
+            op: MgmtOp::Set,
+            origin,
+            target: hardware.get_node_id(),
+            sequence,
+            attribute: MgmtAttribute::Channel as u8,
+            status: MgmtStatus::Ok,
+            value: &[20],
+        };
+        let mut data = [0u8; 64];
+        let len = request.serialize(&mut data);
+        let tag = management_tag(hardware.get_security(), &data[..len]).unwrap();
+        let mut bytes = data[..len].to_vec();
+        bytes.extend_from_slice(&tag);
+        bytes
+    }
+    
+    #[test]
+    fn test_mgmt_replay_rejected_after_reboot_and_lru_eviction() {
+        let node_id = NodeId::new([0x11, 0x12, 0x13, 0x14, 0x15, 0x16]);
+        let mut hardware = SimHardware::new(node_id, SimChannel::new());
+        hardware.get_security().set_network_key(Some(KEY));
+        let mut node = CountingNode::default();
+        let admin = NodeId::new([0x01; 6]);
+        let deliver = |agent: &mut MgmtAgent, hardware: &mut SimHardware, node: &mut CountingNode, origin: NodeId, sequence: u32| {
+            let data = set_request(hardware, origin, sequence);
+            let packet = DataPacket::with_type(origin, node_id, PacketType::Mgmt, 0, &data);
+            let before = node.sets;
+            assert!(agent.handle(hardware, &packet, node));
+            node.sets > before
+        };
+        
+        let mut agent = MgmtAgent::new(hardware.get_nvs());
+        assert!(deliver(&mut agent, &mut hardware, &mut node, admin, 5));
+        assert!(!deliver(&mut agent, &mut hardware, &mut node, admin, 5));
+        
+        // 重启后序号从非易失存储恢复，截获的请求不能重放
+        let mut agent = MgmtAgent::new(hardware.get_nvs());
+        assert!(!deliver(&mut agent, &mut hardware, &mut node, admin, 5));
+        assert!(deliver(&mut agent, &mut hardware, &mut node, admin, 6));
+        
+        // 表满时新的请求方替换最久未使用的记录，最近使用过的请求方仍被跟踪
+        for i in 1..MAX_REQUESTERS {
+            assert!(deliver(&mut agent, &mut hardware, &mut node, NodeId::new([0x01 + i as u8; 6]), 1));
+        }
+        assert!(deliver(&mut agent, &mut hardware, &mut node, admin, 7));
+        assert!(deliver(&mut agent, &mut hardware, &mut node, NodeId::new([0x0F; 6]), 1));
+        assert!(!deliver(&mut agent, &mut hardware, &mut node, admin, 7));
+        assert!(!deliver(&mut agent, &mut hardware, &mut node, NodeId::new([0x0F; 6]), 1));
+        
+        // 使用顺序随序号一起保存，重启后按同样的顺序替换
+        let mut agent = MgmtAgent::new(hardware.get_nvs());
+        assert!(!deliver(&mut agent, &mut hardware, &mut node, admin, 7));
+        assert!(deliver(&mut agent, &mut hardware, &mut node, NodeId::new([0x02; 6]), 1));
+        assert!(!deliver(&mut agent, &mut hardware, &mut node, NodeId::new([0x04; 6]), 1));
+        assert!(!deliver(&mut agent, &mut hardware, &mut node, admin, 7));
+    }
+    
+    #[test]
+    fn test_mgmt_agent_tolerates_empty_replay_state() {
+        let node_id = NodeId::new([0x11, 0x12, 0x13, 0x14, 0x15, 0x16]);
+        let mut hardware = SimHardware::new(node_id, SimChannel::new());
+        hardware.get_nvs().nvs_write(keys::MGMT_REPLAY, &[]).unwrap();
+        
+        // 空的重放状态不会让构造时越界
+        let mut agent = MgmtAgent::new(hardware.get_nvs());
+        hardware.get_security().set_network_key(Some(KEY));
+        let admin = NodeId::new([0x01; 6]);
+        let data = set_request(&mut hardware, admin, 1);
+        let packet = DataPacket::with_type(admin, node_id, PacketType::Mgmt, 0, &data);
+        let mut node = CountingNode::default();
+        assert!(agent.handle(&mut hardware, &packet, &mut node));
+        assert_eq!(node.sets, 1);
+    }    
+    #[test]
+    fn test_ota_requires_authenticated_offer_and_chunks() {
+        let server_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let node_id = NodeId::new([0x11, 0x12, 0x13, 0x14, 0x15, 0x16]);
+        let channel = SimChannel::new();
+        let mut server = SimHardware::new(server_id, channel.clone());
+        server.get_security().set_network_key(Some(KEY));
+        let mut forger = SimHardware::new(server_id, channel.clone());
+        forger.get_security().set_network_key(Some([0xA5; 16]));
+        let mut node = SimHardware::new(node_id, channel);
+        node.get_security().set_network_key(Some(KEY));
+        
+        let image = [0x3Cu8; 100];
+        server.get_firmware().write_staging(0, &image).unwrap();
+        let (crc, digest) = image_digest(&mut server, image.len() as u32).unwrap();
+        
+        let deliver = |signer: &mut SimHardware, node: &mut SimHardware, receiver: &mut OtaReceiver, body: OtaBody| {
+            let mut message = OtaMessage { origin: server_id, target: node_id, version: 2, body, tag: [0; OTA_TAG_LEN] };
+            sign_ota(signer, &mut message);
+            let mut data = [0u8; MAX_OTA_MESSAGE];
+            let len = message.serialize(&mut data);
+            let packet = DataPacket::with_type(server_id, node_id, PacketType::Ota, 0, &data[..len]);
+            receiver.handle(node, &packet, 0)
+        };
+        let mut receiver = OtaReceiver::new(1, node.get_nvs());
+        let offer = OtaBody::Offer { size: image.len() as u32, crc, digest };
+        let chunk = OtaBody::Chunk { offset: 0, data: &image };
+        
+        // 其他网络密钥签发的通知和分块都被拒绝，暂存区不被擦写
+        assert_eq!(deliver(&mut forger, &mut node, &mut receiver, offer), OtaEvent::Rejected);
+        assert!(!receiver.is_active());
+        assert_eq!(deliver(&mut server, &mut node, &mut receiver, offer), OtaEvent::Progress { offset: 0, size: 100 });
+        assert_eq!(deliver(&mut forger, &mut node, &mut receiver, chunk), OtaEvent::Rejected);
+        let mut staged = [0u8; 100];
+        assert!(matches!(node.get_firmware().read_staging(0, &mut staged), Ok(0)));
+        
+        // 认证通过但内容与通知的摘要不符时不会标记启动
+        let mut wrong = digest;
+        wrong[0] ^= 0x01;
+        let offer_wrong = OtaBody::Offer { size: image.len() as u32, crc, digest: wrong };
+        let mut receiver = OtaReceiver::new(1, node.get_nvs());
+        assert_eq!(deliver(&mut server, &mut node, &mut receiver, offer_wrong), OtaEvent::Progress { offset: 0, size: 100 });
+        assert_eq!(deliver(&mut server, &mut node, &mut receiver, chunk), OtaEvent::Failed(OtaStatus::DigestMismatch));
+        
+        assert_eq!(deliver(&mut server, &mut node, &mut receiver, offer), OtaEvent::Progress { offset: 0, size: 100 });
+        assert_eq!(deliver(&mut server, &mut node, &mut receiver, chunk), OtaEvent::Installed { version: 2 });
+        
+        // 未配置网络密钥的节点无法确认来源，拒绝所有固件更新
+        node.get_security().set_network_key(None);
+        let mut receiver = OtaReceiver::new(1, node.get_nvs());
+        assert_eq!(deliver(&mut server, &mut node, &mut receiver, offer), OtaEvent::Rejected);
+    }    
+    #[test]
+    fn test_privileged_command_authentication() {
+        let node_id = NodeId::new([0x11, 0x12, 0x13, 0x14, 0x15, 0x16]);
+        let other_id = NodeId::new([0x21, 0x22, 0x23, 0x24, 0x25, 0x26]);
+        let mut node = SimHardware::new(node_id, SimChannel::new());
+        node.get_security().set_network_key(Some(KEY));
+        
+        let command = [COMMAND_PAYLOAD_TYPE, CommandType::Reboot as u8];
+        let signed = |target: NodeId, context: &SecurityContext| {
+            let mut data = command.to_vec();
+            data.extend_from_slice(&command_tag(context, target, &command).unwrap());
+            data
+        };
+        assert!(verify_command(&mut node, &signed(node_id, &context())));
+        
+        // 发给其他节点的命令、其他网络密钥签发的命令和不带认证码的命令都不通过
+        assert!(!verify_command(&mut node, &signed(other_id, &context())));
+        let mut forger = SecurityContext::new();
+        forger.set_network_key(Some([0xA5; 16]));
+        assert!(!verify_command(&mut node, &signed(node_id, &forger)));
+        assert!(!verify_command(&mut node, &command));
+        
+        // 未配置网络密钥时无法认证
+        node.get_security().set_network_key(None);
+        assert!(!verify_command(&mut node, &signed(node_id, &context())));
+        assert_eq!(command_tag(&SecurityContext::new(), node_id, &command), None);
+    }
+}
\ No newline at end of file
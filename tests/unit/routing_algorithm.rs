@@ -1,8 +1,27 @@
 #[cfg(test)]
 mod routing_algorithm_tests {
-    use common::protocol::NodeId;
+    use common::protocol::{Beacon, DataPacket, NodeId, PacketType};
+    use common::protocol::flood::Flood;
+    use common::protocol::route_advert::{AdvertisedRoute, ETX_SCALE, INFINITE_HOPS};
+    use common::config::{ConfigChanges, ConfigObserver, NodeConfig};
+    use common::protocol::NodeRole;
+    use forward::neighbors::{LinkQuality, NeighborTable};
+    use common::protocol::hello::answer_hello;
+    use common::protocol::route_discovery::{answer_route_request, local_route, DiscoveryMessage, ROUTE_REQUEST_LEN};
+    use common::protocol::route_table::RouteTableMessage;
     use forward::routing::RoutingTable;
-    use forward::routing::dynamic_forwarding::ForwardingEngine;
+    use forward::routing::discovery::{handle_route_request, RouteDiscovery, DISCOVERY_ATTEMPTS, DISCOVERY_HOLDDOWN_MS, DISCOVERY_TIMEOUT_MS};
+    use forward::routing::dynamic_forwarding::{ForwardingEngine, UNCONFIRMED_ROUTE_TTL_MS};
+    use forward::routing::flooding::{FloodRelay, FLOOD_DUPLICATE_WINDOW_MS, FLOOD_JITTER_MS};
+    use forward::relay_gate::{RelayGate, RELAY_RESUME_MARGIN};
+    use forward::routing::strategy::{EtxStrategy, GradientStrategy, ReactiveStrategy, RoutingStrategy, ACTIVE_ROUTE_TIMEOUT_MS};
+    use forward::scheduler::{QueuedPacket, TrafficClass};
+    use common::hal::nvs::{keys, NvStorage};
+    use common::hal::Hardware;
+    use common::pool;
+    use common::hal::simulator::{SimChannel, SimHardware, SimNvs};
+    use common::protocol::mgmt::{MgmtAttribute, MgmtMessage, MgmtOp, MgmtStatus};
+    use common::security::{management_tag, receive_secure};
     
     #[test]
     fn test_routing_table_basic_operations() {
@@ -86,4 +105,416 @@ mod routing_algorithm_tests {
         let next_hop = engine.get_next_hop(node_id);
         assert!(next_hop.is_none());
     }
-} 
\ No newline at end of file
+    
+    #[test]
+    fn test_routes_survive_reboot_until_unconfirmed_ttl() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let neighbor = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x07]);
+        let other = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x08]);
+        let mut nvs = SimNvs::new();
+        
+        let mut engine = ForwardingEngine::new(node_id);
+        engine.update_route(neighbor, -60);
+        engine.update_route(other, -75);
+        assert!(engine.checkpoint(&mut nvs).unwrap());
+        // 没有增删路由时不重复写入
+        assert!(!engine.checkpoint(&mut nvs).unwrap());
+        
+        // 重启后路由立即可用，但在信标刷新前未确认
+        let mut rebooted = ForwardingEngine::new(node_id);
+        assert_eq!(rebooted.restore(&mut nvs, 1_000), 2);
+        assert_eq!(rebooted.get_next_hop(neighbor), Some(neighbor));
+        assert_eq!(rebooted.unconfirmed_count(), 2);
+        
+        rebooted.update_route(neighbor, -58);
+        assert_eq!(rebooted.unconfirmed_count(), 1);
+        
+        // 一直没有被刷新的恢复路由比正常路由更早过期
+        rebooted.cleanup(1_000 + UNCONFIRMED_ROUTE_TTL_MS + 1);
+        assert_eq!(rebooted.get_next_hop(neighbor), Some(neighbor));
+        assert_eq!(rebooted.get_next_hop(other), None);
+        
+        // 升级前的旧格式（路由数加每条14字节）被丢弃而不是错位解析
+        let mut legacy = vec![1u8];
+        legacy.extend_from_slice(&neighbor.0);
+        legacy.extend_from_slice(&neighbor.0);
+        legacy.extend_from_slice(&[(-60i8) as u8, 1]);
+        nvs.nvs_write(keys::ROUTING_TABLE, &legacy).unwrap();
+        assert_eq!(ForwardingEngine::new(node_id).restore(&mut nvs, 2_000), 0);
+    }
+    
+    #[test]
+    fn test_multi_hop_routes_from_adverts() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let neighbor = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x07]);
+        let far = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x08]);
+        let other = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x09]);
+        let mut engine = ForwardingEngine::new(node_id);
+        engine.update_route(neighbor, -60);
+        let link = LinkQuality { rssi: -60, delivery: 100, etx: ETX_SCALE, battery: 100, relay_unwilling: false };
+        
+        // 邻居直接连着的节点经由邻居可达
+        let advert = [
+            AdvertisedRoute { destination: far, next_hop: far, hops: 1, rssi: -70, etx: ETX_SCALE },
+            // 邻居经由本节点到达的路由不学习
+            AdvertisedRoute { destination: other, next_hop: node_id, hops: 2, rssi: -50, etx: 2 * ETX_SCALE },
+        ];
+        assert_eq!(engine.learn_routes(neighbor, link, &advert, 1_000), 1);
+        assert_eq!(engine.get_next_hop(far), Some(neighbor));
+        assert_eq!(engine.get_next_hop(other), None);
+        
+        // 当前下一跳通告失效后路由被毒化，并立即通告给邻居
+        let poisoned = [AdvertisedRoute { destination: far, next_hop: far, hops: INFINITE_HOPS, rssi: -70, etx: ETX_SCALE }];
+        engine.learn_routes(neighbor, link, &poisoned, 2_000);
+        assert_eq!(engine.get_next_hop(far), None);
+        assert!(engine.take_triggered_update());
+        assert!(engine.advertised_routes().any(|route| route.destination == far && route.is_poisoned()));
+        
+        // 保持期内不重新学习
+        assert_eq!(engine.learn_routes(neighbor, link, &advert[..1], 3_000), 0);
+        assert_eq!(engine.get_next_hop(far), None);
+    }
+    
+    #[test]
+    fn test_etx_prefers_reliable_link() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let strong_lossy = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x07]);
+        let weak_reliable = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x08]);
+        let far = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x09]);
+        let mut engine = ForwardingEngine::new(node_id);
+        
+        // 信号强但一半信标丢失的链路，ETX为4；信号弱但不丢包的链路，ETX为1
+        let lossy = LinkQuality { rssi: -45, delivery: 50, etx: 4 * ETX_SCALE, battery: 100, relay_unwilling: false };
+        let reliable = LinkQuality { rssi: -85, delivery: 100, etx: ETX_SCALE, battery: 100, relay_unwilling: false };
+        engine.update_link(strong_lossy, lossy);
+        engine.update_link(weak_reliable, reliable);
+        
+        let advert = [AdvertisedRoute { destination: far, next_hop: far, hops: 1, rssi: -70, etx: ETX_SCALE }];
+        assert_eq!(engine.learn_routes(strong_lossy, lossy, &advert, 1_000), 1);
+        assert_eq!(engine.get_next_hop(far), Some(strong_lossy));
+        
+        // 路径ETX更小的邻居取代信号更强的邻居
+        assert_eq!(engine.learn_routes(weak_reliable, reliable, &advert, 2_000), 1);
+        assert_eq!(engine.get_next_hop(far), Some(weak_reliable));
+        assert!(engine.advertised_routes().any(|route| route.destination == far && route.etx == 2 * ETX_SCALE));
+    }
+    
+    #[test]
+    fn test_strategy_selects_next_hop() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let reliable = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x07]);
+        let short = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x08]);
+        let far = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x09]);
+        let reliable_link = LinkQuality { rssi: -60, delivery: 100, etx: ETX_SCALE, battery: 100, relay_unwilling: false };
+        let lossy_link = LinkQuality { rssi: -80, delivery: 50, etx: 4 * ETX_SCALE, battery: 100, relay_unwilling: false };
+        
+        // 经可靠邻居3跳、ETX为3；经丢包的邻居2跳、ETX为5
+        let long_advert = [AdvertisedRoute { destination: far, next_hop: far, hops: 2, rssi: -60, etx: 2 * ETX_SCALE }];
+        let short_advert = [AdvertisedRoute { destination: far, next_hop: far, hops: 1, rssi: -60, etx: ETX_SCALE }];
+        
+        let mut etx = ForwardingEngine::with_strategy(node_id, EtxStrategy);
+        let mut gradient = ForwardingEngine::with_strategy(node_id, GradientStrategy);
+        etx.learn_routes(reliable, reliable_link, &long_advert, 1_000);
+        etx.learn_routes(short, lossy_link, &short_advert, 1_000);
+        gradient.learn_routes(reliable, reliable_link, &long_advert, 1_000);
+        gradient.learn_routes(short, lossy_link, &short_advert, 1_000);
+        
+        // 按ETX选路径代价小的，梯度路由选跳数少的
+        assert_eq!(etx.get_next_hop(far), Some(reliable));
+        assert_eq!(gradient.get_next_hop(far), Some(short));
+    }
+    
+    #[test]
+    fn test_reactive_strategy_discovers_and_maintains_by_use() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let neighbor = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x07]);
+        let far = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x09]);
+        let link = LinkQuality { rssi: -60, delivery: 100, etx: ETX_SCALE, battery: 100, relay_unwilling: false };
+        let route = AdvertisedRoute { destination: far, next_hop: far, hops: 1, rssi: -60, etx: ETX_SCALE };
+        
+        let mut engine = ForwardingEngine::with_strategy(node_id, ReactiveStrategy);
+        assert!(engine.strategy().discovers_on_demand());
+        
+        // 不从路由通告学习，只安装路由发现得到的路由
+        engine.update_link(neighbor, link);
+        assert_eq!(engine.learn_routes(neighbor, link, &[route], 1_000), 0);
+        assert_eq!(engine.get_next_hop(far), None);
+        assert_eq!(engine.learn_discovered(neighbor, link, route, 1_000), 1);
+        assert_eq!(engine.get_next_hop(far), Some(neighbor));
+        
+        // 有流量经过的路由超过按需路由的失效时间仍可用，之后一段时间没有流量时失效
+        let used_at = ACTIVE_ROUTE_TIMEOUT_MS / 2 + 1_000;
+        engine.route_used(far, used_at);
+        engine.route_used(neighbor, used_at);
+        engine.cleanup(ACTIVE_ROUTE_TIMEOUT_MS + 2_000);
+        assert_eq!(engine.get_next_hop(far), Some(neighbor));
+        engine.cleanup(used_at + ACTIVE_ROUTE_TIMEOUT_MS + 1);
+        assert_eq!(engine.get_next_hop(far), None);
+        
+        // 没有使用刷新的策略中路由只由通告维持
+        let mut etx = ForwardingEngine::with_strategy(node_id, EtxStrategy);
+        assert!(!etx.strategy().refreshes_on_use());
+        etx.learn_discovered(neighbor, link, route, 1_000);
+        etx.route_used(far, 10_000);
+        assert!(etx.dump(8, 10_000).entries().iter().any(|entry| entry.destination == far && entry.age_secs == 9));
+    }
+    
+    #[test]
+    fn test_flood_duplicates_suppressed() {
+        let node_id = NodeId::new([0x01; 6]);
+        let origin = NodeId::new([0x0A; 6]);
+        let mut floods = FloodRelay::new(node_id);
+        
+        // 泛洪包携带发起节点，还原后的原始包源地址为发起节点
+        let flood = Flood { origin, sequence: 7, packet_type: PacketType::Mgmt as u8, payload: &[1, 2, 3] };
+        let mut buffer = [0u8; 32];
+        let len = flood.serialize(&mut buffer);
+        let parsed = Flood::deserialize(&buffer[..len]).unwrap();
+        assert_eq!(parsed, flood);
+        assert_eq!(parsed.inner().header.source, origin.0);
+        assert_eq!(parsed.inner().header.packet_type, PacketType::Mgmt as u8);
+        
+        // 同一泛洪经多条路径到达只处理一次，新序号和窗口过后照常处理
+        assert!(floods.accept(origin, 7, 0));
+        assert!(!floods.accept(origin, 7, 1_000));
+        assert!(floods.accept(origin, 8, 1_000));
+        assert!(floods.accept(origin, 7, FLOOD_DUPLICATE_WINDOW_MS + 1));
+    }
+    
+    #[test]
+    fn test_flood_rebroadcast_jittered_and_mgmt_origin_authenticated() {
+        const KEY: [u8; 16] = [0x5A; 16];
+        let admin = NodeId::new([0x0A; 6]);
+        let forger = NodeId::new([0x0F; 6]);
+        let relay = NodeId::new([0x02; 6]);
+        let neighbor = NodeId::new([0x03; 6]);
+        let channel = SimChannel::new();
+        channel.connect(relay, neighbor);
+        let mut relay_node = SimHardware::new(relay, channel.clone());
+        let mut neighbor_node = SimHardware::new(neighbor, channel);
+        relay_node.get_security().set_network_key(Some(KEY));
+        neighbor_node.get_security().set_network_key(Some(KEY));
+        let mut floods = FloodRelay::new(relay);
+        
+        // 管理员发起的广播设置请求，附带认证码
+        let request = MgmtMessage {
+            op: MgmtOp::Set,
+            origin: admin,
+            target: NodeId::BROADCAST,
+            sequence: 1,
+            attribute: MgmtAttribute::BeaconInterval as u8,
+            status: MgmtStatus::Ok,
+            value: &[20],
+        };
+        let mut mgmt = [0u8; 64];
+        let len = request.serialize(&mut mgmt);
+        let tag = management_tag(relay_node.get_security(), &mgmt[..len]).unwrap();
+        let mut payload = mgmt[..len].to_vec();
+        payload.extend_from_slice(&tag);
+        let flood_from = |origin, payload: &[u8], buffer: &mut [u8; 128]| -> usize {
+            Flood { origin, sequence: 1, packet_type: PacketType::Mgmt as u8, payload }.serialize(buffer)
+        };
+        
+        // 泛洪包头冒充管理员以外的发起节点，或认证码被篡改，既不处理也不转发
+        let mut buffer = [0u8; 128];
+        let len = flood_from(forger, &payload, &mut buffer);
+        let packet = DataPacket::with_type(forger, NodeId::BROADCAST, PacketType::Flood, 1, &buffer[..len]).with_ttl(3);
+        assert!(floods.flood_packet(&mut relay_node, &packet, 0).is_none());
+        let mut tampered = payload.clone();
+        *tampered.last_mut().unwrap() ^= 0x01;
+        let len = flood_from(admin, &tampered, &mut buffer);
+        let packet = DataPacket::with_type(forger, NodeId::BROADCAST, PacketType::Flood, 1, &buffer[..len]).with_ttl(3);
+        assert!(floods.flood_packet(&mut relay_node, &packet, 0).is_none());
+        assert_eq!(floods.wait_ms(0), None);
+        
+        // 真实的请求没有被伪造的副本挡住，随机延时后才重新广播
+        let len = flood_from(admin, &payload, &mut buffer);
+        let packet = DataPacket::with_type(forger, NodeId::BROADCAST, PacketType::Flood, 1, &buffer[..len]).with_ttl(3);
+        assert!(floods.flood_packet(&mut relay_node, &packet, 0).is_some());
+        let wait_ms = floods.wait_ms(0).unwrap();
+        assert!(wait_ms <= FLOOD_JITTER_MS);
+        let mut received_buffer = [0u8; 128];
+        if wait_ms > 0 {
+            floods.poll(&mut relay_node, wait_ms - 1);
+            assert!(receive_secure(&mut neighbor_node, &mut received_buffer).is_none());
+        }
+        floods.poll(&mut relay_node, wait_ms);
+        assert_eq!(floods.wait_ms(wait_ms), None);
+        let relayed = receive_secure(&mut neighbor_node, &mut received_buffer).unwrap();
+        assert_eq!(relayed.header.ttl, 2);
+        assert_eq!(Flood::deserialize(relayed.data).map(|flood| flood.origin), Some(admin));
+    }
+    
+    #[test]
+    fn test_route_discovery_buffers_until_reply() {
+        let target = NodeId::new([0x0B; 6]);
+        let other = NodeId::new([0x0C; 6]);
+        let next_hop = NodeId::new([0x02; 6]);
+        let mut discovery = RouteDiscovery::new();
+        
+        // 应答中的路由原样往返
+        let route = AdvertisedRoute { destination: target, next_hop, hops: 2, rssi: -70, etx: 2 * ETX_SCALE };
+        let reply = DiscoveryMessage::Reply { origin: other, route };
+        let mut buffer = [0u8; 32];
+        let len = reply.serialize(&mut buffer);
+        assert_eq!(DiscoveryMessage::deserialize(PacketType::RouteReply as u8, &buffer[..len]), Some(reply));
+        
+        // 同一目的地只发起一次路由请求
+        let pool = pool::node_pool();
+        let packet = |id| QueuedPacket::new(pool, target, PacketType::Data, id, &[id as u8; 4]).unwrap();
+        assert_eq!(discovery.hold(target, TrafficClass::Control, packet(1), 0).ok(), Some(true));
+        assert_eq!(discovery.hold(target, TrafficClass::Control, packet(2), 10).ok(), Some(false));
+        assert!(discovery.is_discovering(target));
+        
+        // 应答到达后按顺序发出缓存的包，并填写下一跳
+        let mut sent = Vec::new();
+        assert_eq!(discovery.resolve(target, next_hop, |_, queued| sent.push((queued.next_hop, queued.packet_id))), 2);
+        assert_eq!(sent, vec![(next_hop, 1), (next_hop, 2)]);
+        assert!(!discovery.is_discovering(target));
+        
+        // 没有应答时重发请求，次数用完后丢弃缓存的包
+        assert_eq!(discovery.hold(other, TrafficClass::Control, packet(3), 0).ok(), Some(true));
+        let mut retries = 0;
+        let mut dropped = 0;
+        for attempt in 1..=DISCOVERY_ATTEMPTS as u64 {
+            dropped += discovery.poll(attempt * DISCOVERY_TIMEOUT_MS, |_| retries += 1);
+        }
+        assert_eq!(retries, DISCOVERY_ATTEMPTS - 1);
+        assert_eq!(dropped, 1);
+        assert!(!discovery.is_discovering(other));
+        
+        // 失败后暂停该目的地的发现，到期前的包直接丢弃，其他目的地不受影响
+        let failed_at = DISCOVERY_ATTEMPTS as u64 * DISCOVERY_TIMEOUT_MS;
+        assert!(discovery.hold(other, TrafficClass::Control, packet(4), failed_at + 1).is_err());
+        assert!(!discovery.is_discovering(other));
+        assert_eq!(discovery.hold(target, TrafficClass::Control, packet(5), failed_at + 1).ok(), Some(true));
+        assert_eq!(discovery.hold(other, TrafficClass::Control, packet(6), failed_at + DISCOVERY_HOLDDOWN_MS).ok(), Some(true));
+    }
+    
+    #[test]
+    fn test_route_request_handler_hops_and_destination_reply() {
+        let origin = NodeId::new([0x0A; 6]);
+        let relay = NodeId::new([0x02; 6]);
+        let forwarder = NodeId::new([0x03; 6]);
+        let server = NodeId::new([0x51; 6]);
+        
+        // 发起方 - 中继 - 转发节点，服务器也挂在中继下
+        let channel = SimChannel::new();
+        channel.connect(origin, relay);
+        channel.connect(relay, forwarder);
+        channel.connect(relay, server);
+        let mut origin_node = SimHardware::new(origin, channel.clone());
+        let mut relay_node = SimHardware::new(relay, channel.clone());
+        let mut forwarder_node = SimHardware::new(forwarder, channel.clone());
+        let mut server_node = SimHardware::new(server, channel);
+        
+        // 转发节点听到中继的信标后交换HELLO，验证双向链路
+        let mut neighbors = NeighborTable::new();
+        let beacon = Beacon::with_role(relay, 90, -50, NodeRole::Forward);
+        assert_eq!(neighbors.observe(&mut forwarder_node, &beacon, -50, 0), None);
+        let mut hello_buffer = [0u8; 128];
+        let hello = receive_secure(&mut relay_node, &mut hello_buffer).unwrap();
+        assert!(answer_hello(&mut relay_node, &hello));
+        let mut ack_buffer = [0u8; 128];
+        let ack = receive_secure(&mut forwarder_node, &mut ack_buffer).unwrap();
+        assert_eq!(neighbors.handle_ack(&ack, 0).map(|(node, _)| node), Some(relay));
+        
+        // 发起方泛洪查找服务器，中继第一次收到后等待随机延时，跳数减一重新广播
+        let request = DiscoveryMessage::Request { target: server, initial_ttl: origin_node.default_ttl() };
+        let mut payload = [0u8; ROUTE_REQUEST_LEN];
+        let len = request.serialize(&mut payload);
+        FloodRelay::new(origin).originate(&mut origin_node, PacketType::RouteRequest, &payload[..len], 0).unwrap();
+        let mut received_buffer = [0u8; 128];
+        let received = receive_secure(&mut relay_node, &mut received_buffer).unwrap();
+        let mut relay_floods = FloodRelay::new(relay);
+        assert!(relay_floods.flood_packet(&mut relay_node, &received, 0).is_some());
+        relay_floods.poll(&mut relay_node, FLOOD_JITTER_MS);
+        
+        // 转发节点经中继建立到发起方的反向路由：中继离发起方1跳，本节点2跳；没有到服务器的路由，不应答
+        let mut forwarder_buffer = [0u8; 128];
+        let flooded = receive_secure(&mut forwarder_node, &mut forwarder_buffer).unwrap();
+        let inner = Flood::deserialize(flooded.data).unwrap().inner();
+        let mut engine = ForwardingEngine::new(forwarder);
+        handle_route_request(&mut forwarder_node, &mut engine, &neighbors, &flooded, &inner, 0);
+        assert_eq!(engine.get_next_hop(origin), Some(relay));
+        assert_eq!(engine.advertised_routes().find(|route| route.destination == origin).map(|route| route.hops), Some(2));
+        
+        // 服务器是查找的目标，直接向转发请求的中继应答
+        let mut server_buffer = [0u8; 128];
+        let flooded = receive_secure(&mut server_node, &mut server_buffer).unwrap();
+        assert!(answer_route_request(&mut server_node, &flooded));
+        let mut reply_buffer = [0u8; 128];
+        let reply = receive_secure(&mut relay_node, &mut reply_buffer).unwrap();
+        assert_eq!(NodeId(reply.header.source), server);
+        assert_eq!(DiscoveryMessage::deserialize(reply.header.packet_type, reply.data),
+                   Some(DiscoveryMessage::Reply { origin, route: local_route(server) }));
+    }
+    
+    #[test]
+    fn test_route_table_dump_roundtrip() {
+        let node_id = NodeId::new([0x01; 6]);
+        let origin = NodeId::new([0x0A; 6]);
+        let neighbor1 = NodeId::new([0x02; 6]);
+        let neighbor2 = NodeId::new([0x03; 6]);
+        let mut engine = ForwardingEngine::new(node_id);
+        engine.update_route(neighbor1, -60);
+        engine.update_route(neighbor2, -70);
+        
+        // 快照按请求的条数截断，但报告路由总数；存在时间按秒计
+        let dump = engine.dump(1, 5_500);
+        assert_eq!(dump.total, 2);
+        assert_eq!(dump.entries().len(), 1);
+        assert_eq!(dump.entries()[0].hops, 1);
+        assert_eq!(dump.entries()[0].age_secs, 5);
+        
+        let response = RouteTableMessage::Response { origin, target: node_id, dump: engine.dump(8, 0) };
+        let mut buffer = [0u8; 128];
+        let len = response.serialize(&mut buffer);
+        assert_eq!(RouteTableMessage::deserialize(PacketType::RouteTableResponse as u8, &buffer[..len]), Some(response));
+    }
+    
+    #[test]
+    fn test_battery_aware_forwarding() {
+        let node_id = NodeId::new([0x01; 6]);
+        let drained = NodeId::new([0x02; 6]);
+        let charged = NodeId::new([0x03; 6]);
+        let far = NodeId::new([0x09; 6]);
+        let mut engine = ForwardingEngine::new(node_id);
+        engine.config_changed(&NodeConfig::defaults(NodeRole::Forward), ConfigChanges { routing: true, ..ConfigChanges::default() });
+        
+        // 两条链路同样可靠，缺电的邻居代价更高
+        let drained_link = LinkQuality { rssi: -50, delivery: 100, etx: ETX_SCALE, battery: 20, relay_unwilling: false };
+        let charged_link = LinkQuality { rssi: -70, delivery: 100, etx: ETX_SCALE, battery: 100, relay_unwilling: false };
+        let advert = [AdvertisedRoute { destination: far, next_hop: far, hops: 1, rssi: -60, etx: ETX_SCALE }];
+        engine.learn_routes(drained, drained_link, &advert, 1_000);
+        engine.learn_routes(charged, charged_link, &advert, 1_000);
+        assert_eq!(engine.get_next_hop(far), Some(charged));
+        
+        // 当前下一跳声明不再中继后，经由它的路由失效
+        let unwilling = LinkQuality { battery: 5, relay_unwilling: true, ..charged_link };
+        engine.learn_routes(charged, unwilling, &advert, 2_000);
+        assert_eq!(engine.get_next_hop(far), None);
+    }
+    
+    #[test]
+    fn test_relay_gate_hysteresis() {
+        let config = NodeConfig::defaults(NodeRole::Forward);
+        let critical = config.critical_battery;
+        let mut gate = RelayGate::new();
+        gate.config_changed(&config, ConfigChanges { routing: true, ..ConfigChanges::default() });
+        
+        // 低于阈值时停止中继，状态不变时不再报告
+        assert_eq!(gate.update(critical), None);
+        assert_eq!(gate.update(critical - 1), Some(true));
+        assert_eq!(gate.update(critical - 2), None);
+        assert!(gate.is_unwilling());
+        
+        // 电量在阈值附近波动不会恢复中继，超过回差后才恢复
+        assert_eq!(gate.update(critical), None);
+        assert_eq!(gate.update(critical + RELAY_RESUME_MARGIN - 1), None);
+        assert!(gate.is_unwilling());
+        assert_eq!(gate.update(critical + RELAY_RESUME_MARGIN), Some(false));
+        assert_eq!(gate.update(critical), None);
+        assert!(!gate.is_unwilling());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod sample_log_tests {
+    use client::SensorData;
+    use client::sample_log::{SampleLog, LOG_PAGES, PAGE_SAMPLES};
+    use common::hal::nvs::{keys, NvStorage};
+    use common::hal::simulator::SimNvs;
+    use common::protocol::command::LoggedSample;
+    
+    fn append(log: &mut SampleLog, nvs: &mut SimNvs, count: u32) {
+        for _ in 0..count {
+            let seq = log.next_seq();
+            let data = SensorData { temperature: seq as f32, humidity: 50.0, pressure: 101000.0 };
+            log.append(nvs, seq as u64 * 1000, &data).unwrap();
+        }
+    }
+    
+    fn read_all(log: &SampleLog, nvs: &mut SimNvs) -> (u32, Vec<LoggedSample>) {
+        let mut out = vec![LoggedSample { timestamp: 0, temperature: 0.0, humidity: 0.0, pressure: 0.0 }; log.sample_count()];
+        let (first, count) = log.read(nvs, 0, &mut out);
+        out.truncate(count);
+        (first, out)
+    }
+    
+    #[test]
+    fn test_log_wraps_and_reloads() {
+        let mut nvs = SimNvs::new();
+        let mut log = SampleLog::load(&mut nvs);
+        
+        // 写满所有页后再追加两页半，最早的三页被覆盖
+        let total = LOG_PAGES as u32 * PAGE_SAMPLES as u32 + 2 * PAGE_SAMPLES as u32 + 6;
+        append(&mut log, &mut nvs, total);
+        let oldest = 3 * PAGE_SAMPLES as u32;
+        assert_eq!(log.oldest_seq(), oldest);
+        assert_eq!(log.next_seq(), total);
+        
+        let (first, samples) = read_all(&log, &mut nvs);
+        assert_eq!(first, oldest);
+        assert_eq!(samples.len(), (total - oldest) as usize);
+        assert!(samples.iter().zip(oldest..).all(|(sample, seq)| sample.timestamp == seq as u64 * 1000));
+        
+        // 重启后从页索引恢复，内容相同并从原处继续追加
+        let mut reloaded = SampleLog::load(&mut nvs.clone());
+        assert_eq!((reloaded.oldest_seq(), reloaded.next_seq()), (oldest, total));
+        assert_eq!(read_all(&reloaded, &mut nvs), (first, samples));
+        
+        append(&mut reloaded, &mut nvs, PAGE_SAMPLES as u32);
+        assert_eq!(reloaded.oldest_seq(), oldest + PAGE_SAMPLES as u32);
+        assert_eq!(reloaded.next_seq(), total + PAGE_SAMPLES as u32);
+    }
+    
+    #[test]
+    fn test_torn_page_write_is_dropped_on_reload() {
+        let mut nvs = SimNvs::new();
+        let mut log = SampleLog::load(&mut nvs);
+        append(&mut log, &mut nvs, 2 * PAGE_SAMPLES as u32 + 6);
+        
+        // 当前页（第三个槽位）写到一半掉电，只留下页头和部分样本
+        let mut record = [0u8; 256];
+        let len = nvs.nvs_read(keys::SAMPLE_LOG_BASE + 2, &mut record).unwrap().unwrap();
+        nvs.nvs_write(keys::SAMPLE_LOG_BASE + 2, &record[..len / 2]).unwrap();
+        
+        // 损坏的末页被丢弃，之前的页完整保留
+        let mut reloaded = SampleLog::load(&mut nvs);
+        assert_eq!(reloaded.oldest_seq(), 0);
+        assert_eq!(reloaded.next_seq(), 2 * PAGE_SAMPLES as u32);
+        let (first, samples) = read_all(&reloaded, &mut nvs);
+        assert_eq!((first, samples.len()), (0, 2 * PAGE_SAMPLES));
+        
+        // 继续追加时重写损坏的槽位，序号保持连续
+        append(&mut reloaded, &mut nvs, 3);
+        let reloaded = SampleLog::load(&mut nvs);
+        assert_eq!(reloaded.next_seq(), 2 * PAGE_SAMPLES as u32 + 3);
+        let (_, samples) = read_all(&reloaded, &mut nvs);
+        assert_eq!(samples.last().unwrap().timestamp, (2 * PAGE_SAMPLES as u64 + 2) * 1000);
+    }
+}
\ No newline at end of file
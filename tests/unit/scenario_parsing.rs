@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod scenario_parsing_tests {
+    use common::metrics::Counter;
+    use common::protocol::NodeId;
+    use simulator::scenario::{parse, Action, Metric, Role};
+    
+    #[test]
+    fn test_parse_scenario() {
+        let text = "
+            # 两个节点
+            node server 51:52:53:54:55:56 0 0
+            node client C1C2C3C4C5C6
+            at 5000 move C1:C2:C3:C4:C5:C6 10 0
+            link C1:C2:C3:C4:C5:C6 51:52:53:54:55:56 0.5
+            run 10000
+            expect records_stored >= 3
+            expect 51:52:53:54:55:56 records_stored >= 1
+            expect delivery C1:C2:C3:C4:C5:C6 51:52:53:54:55:56 >= 2
+        ";
+        let scenario = parse(text).unwrap();
+        let client = NodeId([0xC1, 0xC2, 0xC3, 0xC4, 0xC5, 0xC6]);
+        
+        assert_eq!(scenario.nodes.len(), 2);
+        assert_eq!(scenario.nodes[0].role, Role::Server);
+        assert_eq!(scenario.nodes[0].position, Some((0.0, 0.0)));
+        assert_eq!(scenario.nodes[1].id, client);
+        assert_eq!(scenario.nodes[1].position, None);
+        
+        // 操作按时间排序，没有时间的在启动时执行
+        assert_eq!(scenario.events[0].at_ms, 0);
+        assert!(matches!(scenario.events[0].action, Action::Link { loss, .. } if loss == 0.5));
+        assert_eq!(scenario.events[1].action, Action::Move { node: client, x: 10.0, y: 0.0 });
+        
+        assert_eq!(scenario.duration_ms, 10000);
+        assert_eq!(scenario.expectations[0].metric, Metric::Counter { node: None, counter: Counter::RecordsStored });
+        assert_eq!(scenario.expectations[0].min, 3);
+        
+        // 指定节点的计数器和端到端送达
+        let server = NodeId([0x51, 0x52, 0x53, 0x54, 0x55, 0x56]);
+        assert_eq!(scenario.expectations[1].metric, Metric::Counter { node: Some(server), counter: Counter::RecordsStored });
+        assert_eq!(scenario.expectations[2].metric, Metric::Delivery { source: client, destination: server });
+        assert_eq!(scenario.expectations[2].min, 2);
+        
+        // 错误带行号
+        let error = parse("node server 51:52:53:54:55:56\nnode relay F1:F2:F3:F4:F5:F6\nrun 1000").unwrap_err();
+        assert_eq!(error.line, Some(2));
+        
+        // 期望中的节点必须在场景中
+        let error = parse("node server 51:52:53:54:55:56\nrun 1000\nexpect F1:F2:F3:F4:F5:F6 packets_forwarded >= 1").unwrap_err();
+        assert_eq!(error.reason, "期望中的节点不在场景中");
+    }
+}
\ No newline at end of file
@@ -1,201 +1,370 @@
 #[cfg(test)]
 mod service_discovery_tests {
     use common::protocol::{NodeId, ServiceType, QosRequirements, DataPacket};
-    use common::hal::simulator::{SimChannel, SimHardware};
-    use common::protocol::{ServiceRequest, serialize_service_request, deserialize_service_response};
-    use common::protocol::{PacketType, PathStatus};
-    use forward::directory::service_directory::{NetworkServiceDirectory, Capabilities, ServiceMetrics};
+    use common::protocol::ServiceRequest;
+    use common::config::{ConfigChanges, ConfigObserver, NodeConfig, ScoreWeights};
+    use common::protocol::NodeRole;
+    use forward::directory::service_directory::{NetworkServiceDirectory, Capabilities, SelectionPolicy, ServiceMetrics};
+    use forward::directory::lease_table::LeaseTable;
+    use forward::directory::proxy::{Deferred, DirectoryProxy, ServiceQuery, LOOKUP_TIMEOUT_MS};
+    use common::protocol::service_advert::ServiceAdvertisement;
+    use common::protocol::service_beacon::{ServiceBeacon, SERVICE_BEACON_LEN};
+    use common::protocol::election::{Candidacy, ElectionMessage};
+    use forward::directory::election::{elect, Candidate, ElectionProtocol, ELECTION_WINDOW_MS};
+    use forward::routing::flooding::FloodRelay;
+    use common::hal::simulator::{SimChannel, SimHardware, SimNvs};
+    use simulator::scenario;
     
     #[test]
     fn test_service_discovery_and_path_establishment() {
-        // 创建共享通信信道
-        let channel = SimChannel::new();
-        
-        // 创建客户端、转发节点和服务器节点
-        let client_id = NodeId::new([0xC1, 0xC2, 0xC3, 0xC4, 0xC5, 0xC6]);
-        let forward_id = NodeId::new([0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6]);
-        let server_id = NodeId::new([0xS1, 0xS2, 0xS3, 0xS4, 0xS5, 0xS6]);
-        
-        let mut client = SimHardware::new(client_id, channel.clone());
-        let mut forward = SimHardware::new(forward_id, channel.clone());
-        let mut server = SimHardware::new(server_id, channel.clone());
-        
-        // 1. 初始化服务目录，并注册服务器的视频中继服务
-        let mut service_directory = NetworkServiceDirectory::new();
-        
-        let capabilities = Capabilities {
-            max_bandwidth: 1000, // 1 Mbps
-            min_latency: 50,     // 50ms
-            reliability: 95,      // 95%可靠性
-            battery_level: 80,    // 80%电池电量
-        };
-        
+        // 客户端、转发节点和服务器都运行真实的主循环，客户端只能经过转发节点到达服务器
+        let scenario = scenario::parse("
+            node server  51:52:53:54:55:56 0 0
+            node forward F1:F2:F3:F4:F5:F6 40 0
+            node client  C1:C2:C3:C4:C5:C6 80 0
+            connect 51:52:53:54:55:56 F1:F2:F3:F4:F5:F6
+            connect F1:F2:F3:F4:F5:F6 C1:C2:C3:C4:C5:C6
+            run 60000
+            
+            # 转发节点从服务目录中为客户端的服务请求找到服务器并分配租约
+            expect F1:F2:F3:F4:F5:F6 leases_granted >= 1
+            
+            # 路径建立后客户端的采样经转发节点送达服务器并被存储
+            expect delivery C1:C2:C3:C4:C5:C6 51:52:53:54:55:56 >= 1
+            expect 51:52:53:54:55:56 records_stored >= 1
+        ").unwrap();
+        
+        let outcome = simulator::run(&scenario).unwrap();
+        for check in &outcome.checks {
+            assert!(check.passed(), "{:?} = {}", check.expectation, check.value);
+        }
+    }
+    
+    #[test]
+    fn test_score_weights_change_ranking() {
+        let fast = NodeId::new([0x01, 0x01, 0x01, 0x01, 0x01, 0x01]);
+        let steady = NodeId::new([0x02, 0x02, 0x02, 0x02, 0x02, 0x02]);
         let metrics = ServiceMetrics {
-            success_rate: 100,      // 100%成功率
-            avg_response_time: 20,  // 20ms平均响应时间
-            signal_strength: -60,   // -60dBm信号强度
+            success_rate: 100,
+            avg_response_time: 20,
+            signal_strength: -60,
         };
+        // 低延迟但可靠性一般的服务器，和延迟较高但非常可靠的服务器
+        let fast_capabilities = Capabilities { max_bandwidth: 1000, min_latency: 10, reliability: 85, battery_level: 80 };
+        let steady_capabilities = Capabilities { max_bandwidth: 1000, min_latency: 200, reliability: 100, battery_level: 80 };
         
-        service_directory.update_service(
-            server_id,
-            ServiceType::VideoRelay,
-            20, // 20%负载
-            capabilities,
-            metrics,
-            0  // 时间戳
-        );
+        let mut directory = NetworkServiceDirectory::new();
+        for service_type in [ServiceType::VideoRelay, ServiceType::Storage] {
+            directory.update_service(fast, service_type, 20, fast_capabilities, metrics, 0, 0);
+            directory.update_service(steady, service_type, 20, steady_capabilities, metrics, 0, 0);
+        }
         
-        // 2. 客户端发送服务请求
         let qos = QosRequirements {
-            min_bandwidth: 500, // 500kbps
-            max_latency: 100,   // 100ms延迟
-            reliability: 80,    // 80%可靠性
+            min_bandwidth: 500,
+            max_latency: 300,
+            reliability: 80,
         };
         
-        let service_request = ServiceRequest {
-            service_type: ServiceType::VideoRelay,
-            qos,
-            expiry_time: 60, // 60秒
+        // 视频侧重延迟，存储侧重可靠性
+        assert_eq!(directory.find_best_service(ServiceType::VideoRelay, &qos).unwrap().node_id, fast);
+        assert_eq!(directory.find_best_service(ServiceType::Storage, &qos).unwrap().node_id, steady);
+        
+        // 通过配置调低延迟权重后，视频也选择更可靠的服务器
+        let mut config = NodeConfig::defaults(NodeRole::Forward);
+        config.score_weights = ScoreWeights { latency: 0, reliability: 60, ..ScoreWeights::default() };
+        directory.config_changed(&config, ConfigChanges { directory: true, ..ConfigChanges::default() });
+        assert_eq!(directory.find_best_service(ServiceType::VideoRelay, &qos).unwrap().node_id, steady);
+        
+        // 每跳扣分足够大时，直接听到的服务器优先
+        directory.set_weights(ScoreWeights { hop_penalty: 255, ..ScoreWeights::default() });
+        directory.update_service(steady, ServiceType::Storage, 20, steady_capabilities, metrics, 2, 0);
+        assert_eq!(directory.find_best_service(ServiceType::Storage, &qos).unwrap().node_id, fast);
+    }
+    
+    #[test]
+    fn test_directory_iteration_without_allocation() {
+        let first = NodeId::new([0x01, 0x01, 0x01, 0x01, 0x01, 0x01]);
+        let second = NodeId::new([0x02, 0x02, 0x02, 0x02, 0x02, 0x02]);
+        let capabilities = Capabilities { max_bandwidth: 1000, min_latency: 50, reliability: 90, battery_level: 100 };
+        let metrics = ServiceMetrics { success_rate: 100, avg_response_time: 20, signal_strength: -60 };
+        
+        let mut directory = NetworkServiceDirectory::new();
+        directory.update_service(first, ServiceType::Storage, 70, capabilities, metrics, 0, 0);
+        directory.update_service(second, ServiceType::Storage, 10, capabilities, metrics, 1, 0);
+        directory.update_service(second, ServiceType::Gateway, 10, capabilities, metrics, 1, 0);
+        
+        assert_eq!(directory.get_services_by_type(ServiceType::Storage).count(), 2);
+        
+        let mut visited = 0;
+        directory.for_each_service(|_| visited += 1);
+        assert_eq!(visited, 3);
+        
+        // 自定义评分：只看跳数，跳数少的优先
+        let nearest = directory.find_best_service_with(ServiceType::Storage, |service| 10 - service.hops as u16);
+        assert_eq!(nearest.unwrap().node_id, first);
+        
+        // 得分为0的服务不会被选中
+        assert!(directory.find_best_service_with(ServiceType::Gateway, |_| 0).is_none());
+    }
+    
+    #[test]
+    fn test_selection_policies_spread_sessions() {
+        let busy = NodeId::new([0x01, 0x01, 0x01, 0x01, 0x01, 0x01]);
+        let idle = NodeId::new([0x02, 0x02, 0x02, 0x02, 0x02, 0x02]);
+        let capabilities = Capabilities { max_bandwidth: 1000, min_latency: 50, reliability: 90, battery_level: 100 };
+        let metrics = ServiceMetrics { success_rate: 100, avg_response_time: 20, signal_strength: -60 };
+        let qos = QosRequirements { min_bandwidth: 500, max_latency: 300, reliability: 80 };
+        
+        let mut directory = NetworkServiceDirectory::new();
+        for service_type in [ServiceType::VideoRelay, ServiceType::Storage] {
+            directory.update_service(busy, service_type, 90, capabilities, metrics, 0, 0);
+            directory.update_service(idle, service_type, 0, capabilities, metrics, 0, 0);
+        }
+        
+        // 视频中继默认轮流分配，按进行中的会话分摊，两个服务器各承载一半会话
+        let client = NodeId::new([0xC1; 6]);
+        let video = ServiceType::VideoRelay;
+        let mut leases = LeaseTable::new();
+        let pick = |directory: &NetworkServiceDirectory, leases: &mut LeaseTable, service_id: u32, now: u64| {
+            let server = directory.select_service(video, &qos, service_id, now, |server| leases.sessions(server, video)).unwrap();
+            leases.grant(service_id, client, server, video, 0, 60, now);
+            server
+        };
+        assert_eq!(directory.policy(video), SelectionPolicy::RoundRobin);
+        let picks: Vec<_> = (0..4).map(|i| pick(&directory, &mut leases, i, 0)).collect();
+        assert_eq!(picks.iter().filter(|&&node| node == busy).count(), 2);
+        assert_ne!(picks[0], picks[1]);
+        
+        // 会话结束后不再计入，新会话分给会话少的服务器
+        for (service_id, server) in picks.iter().enumerate() {
+            if *server == busy {
+                leases.release(service_id as u32);
+            }
+        }
+        assert_eq!(pick(&directory, &mut leases, 4, 0), busy);
+        assert_eq!(pick(&directory, &mut leases, 5, 0), busy);
+        
+        // 为代理挑选的会话同样计入，到期后不再计入
+        directory.record_remote_session(idle, video, 10_000);
+        directory.record_remote_session(idle, video, 10_000);
+        assert_eq!(pick(&directory, &mut leases, 6, 0), busy);
+        assert_eq!(pick(&directory, &mut leases, 7, 10_000), idle);
+        
+        // 其他类型默认总是选得分最高的
+        for i in 0..3 {
+            assert_eq!(directory.select_service(ServiceType::Storage, &qos, i, 0, |_| 0), Some(idle));
+        }
+        
+        // 加权随机按空闲负载分配：空闲服务器权重101，繁忙服务器权重11
+        directory.set_policy(ServiceType::Storage, SelectionPolicy::WeightedRandom);
+        let busy_picks = (0..112).filter(|&i| directory.select_service(ServiceType::Storage, &qos, i, 0, |_| 0) == Some(busy)).count();
+        assert_eq!(busy_picks, 11);
+        
+        // 策略随运行配置设置
+        let mut config = NodeConfig::defaults(NodeRole::Forward);
+        config.selection_policies[ServiceType::Storage as usize - 1] = SelectionPolicy::RoundRobin;
+        directory.config_changed(&config, ConfigChanges { directory: true, ..ConfigChanges::default() });
+        assert_eq!(directory.policy(ServiceType::Storage), SelectionPolicy::RoundRobin);
+    }
+    
+    #[test]
+    fn test_directory_survives_reboot() {
+        let server = NodeId::new([0x01, 0x01, 0x01, 0x01, 0x01, 0x01]);
+        let capabilities = Capabilities { max_bandwidth: 2000, min_latency: 30, reliability: 95, battery_level: 80 };
+        let metrics = ServiceMetrics { success_rate: 99, avg_response_time: 15, signal_strength: -55 };
+        let mut nvs = SimNvs::new();
+        
+        let mut directory = NetworkServiceDirectory::new();
+        directory.update_service(server, ServiceType::Storage, 40, capabilities, metrics, 1, 0);
+        assert!(directory.checkpoint(&mut nvs).unwrap());
+        // 只刷新负载时不重复写入
+        directory.update_service(server, ServiceType::Storage, 50, capabilities, metrics, 1, 1_000);
+        assert!(!directory.checkpoint(&mut nvs).unwrap());
+        
+        // 重启后服务立即可查，以恢复时间重新计算过期
+        let mut rebooted = NetworkServiceDirectory::new();
+        assert_eq!(rebooted.restore(&mut nvs, 200_000), 1);
+        let service = rebooted.get_services_by_type(ServiceType::Storage).next().unwrap();
+        assert_eq!((service.node_id, service.load, service.hops), (server, 40, 1));
+        assert_eq!(service.capabilities.max_bandwidth, 2000);
+        assert_eq!(service.metrics.signal_strength, -55);
+        assert!(!rebooted.checkpoint(&mut nvs).unwrap());
+        
+        rebooted.cleanup(400_000);
+        assert_eq!(rebooted.services().count(), 1);
+        rebooted.cleanup(600_000);
+        assert_eq!(rebooted.services().count(), 0);
+        assert!(rebooted.checkpoint(&mut nvs).unwrap());
+        assert_eq!(NetworkServiceDirectory::new().restore(&mut nvs, 600_000), 0);
+    }
+    
+    #[test]
+    fn test_directory_proxy_caches_master_answers() {
+        let master = NodeId::new([0x4D, 0x4D, 0x4D, 0x4D, 0x4D, 0x4D]);
+        let server = NodeId::new([0x5E, 0x5E, 0x5E, 0x5E, 0x5E, 0x5E]);
+        let qos = QosRequirements { min_bandwidth: 100, max_latency: 200, reliability: 80 };
+        let request = ServiceRequest { request_id: 0, service_type: ServiceType::Storage, qos, expiry_time: 60 };
+        let first = ServiceQuery { client: NodeId::new([0xC1; 6]), packet_id: 1, request };
+        let second = ServiceQuery { client: NodeId::new([0xC2; 6]), packet_id: 2, request };
+        
+        let mut proxy = DirectoryProxy::new();
+        proxy.set_master(Some(master));
+        assert!(proxy.lookup(&request, 0).is_none());
+        
+        // 相同的查询只向主节点发送一次
+        assert_eq!(proxy.defer(&first, 0), Deferred::Query);
+        assert_eq!(proxy.defer(&second, 0), Deferred::Waiting);
+        
+        // 应答同时完成两个等待中的请求，并在有效期内缓存
+        let mut answered = Vec::new();
+        proxy.complete(&request, Some(server), 30, 100, |query, answer| answered.push((query.client, answer)));
+        assert_eq!(answered, vec![(first.client, Some(server)), (second.client, Some(server))]);
+        assert_eq!(proxy.lookup(&request, 1000), Some(Some(server)));
+        assert!(proxy.lookup(&request, 100 + 30_000).is_none());
+        
+        // 主节点变化后缓存作废
+        proxy.set_master(Some(server));
+        assert!(proxy.lookup(&request, 1000).is_none());
+        
+        // 主节点迟迟不应答时请求超时
+        proxy.defer(&first, 0);
+        let mut expired = 0;
+        proxy.expire(LOOKUP_TIMEOUT_MS, |_| expired += 1);
+        assert_eq!(expired, 1);
+    }
+    
+    #[test]
+    fn test_directory_gossip_propagates_without_loops() {
+        let server = NodeId::new([0x5E, 0x5E, 0x5E, 0x5E, 0x5E, 0x5E]);
+        let capabilities = Capabilities { max_bandwidth: 1000, min_latency: 100, reliability: 90, battery_level: 70 };
+        let metrics = ServiceMetrics { success_rate: 100, avg_response_time: 50, signal_strength: -65 };
+        
+        // 第一个转发节点在网络时间10秒时直接听到服务器
+        let mut first = NetworkServiceDirectory::new();
+        let mut second = NetworkServiceDirectory::new();
+        first.update_service(server, ServiceType::Storage, 30, capabilities, metrics, 0, 10_000);
+        
+        let mut advert = ServiceAdvertisement::new();
+        for service in first.advertised_services() {
+            advert.push(service);
+        }
+        let mut buffer = [0u8; 256];
+        let len = advert.serialize(&mut buffer);
+        let received = ServiceAdvertisement::deserialize(&buffer[..len]).unwrap();
+        
+        // 邻居学到的服务多一跳，保留源时间
+        assert_eq!(second.learn_services(received.entries(), 12_000), 1);
+        let learned = second.get_services_by_type(ServiceType::Storage).next().unwrap();
+        assert_eq!((learned.node_id, learned.hops, learned.load), (server, 1, 30));
+        assert_eq!(learned.last_update_time, 10_000);
+        
+        // 转回来的通告不比本地新，不刷新本地条目；重复收到也不再计数
+        let echoed: Vec<_> = second.advertised_services().collect();
+        assert_eq!(first.learn_services(&echoed, 12_000), 0);
+        assert_eq!(first.get_services_by_type(ServiceType::Storage).next().unwrap().hops, 0);
+        assert_eq!(second.learn_services(received.entries(), 13_000), 0);
+        
+        // 源时间已超过过期时间的服务不再学习
+        let mut third = NetworkServiceDirectory::new();
+        assert_eq!(third.learn_services(received.entries(), 10_000 + 300_001), 0);
+    }
+    
+    #[test]
+    fn test_election_picks_best_candidate() {
+        let candidate = |id: u8, priority: u8, battery_level: u8, load: u8| Candidate {
+            node_id: NodeId::new([id; 6]),
+            candidacy: Candidacy { priority, battery_level, load },
         };
         
-        // 序列化请求
-        let mut request_buffer = [0u8; 32];
-        let request_len = serialize_service_request(&service_request, &mut request_buffer);
-        
-        assert!(request_len > 0, "服务请求序列化失败");
-        
-        // 创建请求数据包
-        let request_packet = DataPacket::new(
-            client_id,
-            forward_id,
-            1, // 包ID
-            &request_buffer[..request_len]
-        );
+        // 优先级最高者胜出，其次比较电量，再比较负载
+        let candidates = [candidate(1, 10, 50, 50), candidate(2, 20, 10, 90), candidate(3, 20, 80, 90)];
+        assert_eq!(elect(candidates).unwrap().node_id, NodeId::new([3; 6]));
+        let candidates = [candidate(1, 20, 80, 30), candidate(2, 20, 80, 10)];
+        assert_eq!(elect(candidates).unwrap().node_id, NodeId::new([2; 6]));
+        
+        // 条件完全相同时节点ID较小者胜出，与收集顺序无关
+        let candidates = [candidate(9, 20, 80, 10), candidate(4, 20, 80, 10)];
+        assert_eq!(elect(candidates).unwrap().node_id, NodeId::new([4; 6]));
+        assert!(elect([]).is_none());
+        
+        // 只接受当前这一轮选举的结果
+        let node_id = NodeId::new([0x0F; 6]);
+        let initiator = NodeId::new([0x0A; 6]);
+        let mut hardware = SimHardware::new(node_id, SimChannel::new());
+        let mut election = ElectionProtocol::new(node_id);
+        let mut deliver = |election: &mut ElectionProtocol, message: ElectionMessage| {
+            let mut buffer = [0u8; 9];
+            let len = message.serialize(&mut buffer);
+            let packet = DataPacket::new(initiator, NodeId::BROADCAST, 0, &buffer[..len]);
+            election.handle_packet(&mut hardware, &packet, 1000);
+        };
         
-        // 发送请求
-        client.get_radio().send_data(&request_packet).unwrap();
-        
-        // 3. 转发节点接收请求并处理
-        let mut rx_buffer = [0u8; 256];
-        let received_packet = forward.get_radio().receive_data(&mut rx_buffer).unwrap().unwrap();
-        
-        assert_eq!(received_packet.header.source, client_id.0);
-        assert_eq!(received_packet.header.destination, forward_id.0);
-        
-        // 4. 查询服务目录找到最佳服务提供者
-        let best_service = service_directory.find_best_service(
-            ServiceType::VideoRelay, 
-            &qos
-        ).unwrap();
-        
-        assert_eq!(best_service.node_id, server_id);
-        
-        // 5. 转发节点向客户端发送服务响应
-        let mut response_buffer = [0u8; 32];
-        
-        // 构造服务响应数据
-        response_buffer[0] = 0x00; // 成功状态
-        response_buffer[1] = 0x00;
-        response_buffer[2] = 0x00;
-        response_buffer[3] = 0x01; // 服务ID = 1
-        response_buffer[4..10].copy_from_slice(&server_id.0); // 服务器ID
-        
-        // 创建响应数据包
-        let response_packet = DataPacket::new(
-            forward_id,
-            client_id,
-            1, // 包ID
-            &response_buffer[..11]
-        );
-        
-        // 发送响应
-        forward.get_radio().send_data(&response_packet).unwrap();
-        
-        // 6. 转发节点向服务器发送路径建立请求
-        let mut path_buffer = [0u8; 32];
-        
-        // 填充路径建立请求
-        path_buffer[0..6].copy_from_slice(&client_id.0); // 客户端ID
-        path_buffer[6] = ServiceType::VideoRelay as u8;  // 服务类型
-        
-        // 设置QoS参数
-        let bandwidth_bytes = qos.min_bandwidth.to_be_bytes();
-        path_buffer[7] = bandwidth_bytes[0];
-        path_buffer[8] = bandwidth_bytes[1];
-        
-        let latency_bytes = qos.max_latency.to_be_bytes();
-        path_buffer[9] = latency_bytes[0];
-        path_buffer[10] = latency_bytes[1];
-        
-        path_buffer[11] = qos.reliability;
-        
-        // 创建路径建立数据包
-        let path_packet = DataPacket::new(
-            forward_id,
-            server_id,
-            2, // 新包ID
-            &path_buffer[..12]
-        );
-        
-        // 发送路径建立请求
-        forward.get_radio().send_data(&path_packet).unwrap();
-        
-        // 7. 服务器接收路径建立请求
-        let received_path = server.get_radio().receive_data(&mut rx_buffer).unwrap().unwrap();
-        
-        assert_eq!(received_path.header.source, forward_id.0);
-        assert_eq!(received_path.header.destination, server_id.0);
-        
-        // 8. 服务器向转发节点发送路径确认
-        let mut confirm_buffer = [0u8; 32];
-        
-        // 填充路径确认
-        confirm_buffer[0..6].copy_from_slice(&client_id.0); // 客户端ID
-        confirm_buffer[6] = PathStatus::Success as u8;      // 成功状态
-        confirm_buffer[7] = 1; // 跳数为1
-        
-        // 创建路径确认数据包
-        let confirm_packet = DataPacket::new(
-            server_id,
-            forward_id,
-            2, // 与请求相同的包ID
-            &confirm_buffer[..8]
-        );
-        
-        // 发送路径确认
-        server.get_radio().send_data(&confirm_packet).unwrap();
-        
-        // 9. 转发节点接收路径确认并转发给客户端
-        let received_confirm = forward.get_radio().receive_data(&mut rx_buffer).unwrap().unwrap();
-        
-        assert_eq!(received_confirm.header.source, server_id.0);
-        assert_eq!(received_confirm.header.destination, forward_id.0);
-        
-        // 10. 转发节点更新跳数并转发给客户端
-        let mut fwd_confirm_buffer = [0u8; 32];
-        fwd_confirm_buffer.copy_from_slice(&confirm_buffer[..8]);
-        fwd_confirm_buffer[7] = 2; // 增加跳数为2
-        
-        // 创建转发给客户端的确认数据包
-        let fwd_confirm_packet = DataPacket::new(
-            forward_id,
-            client_id,
-            2, // 与请求相同的包ID
-            &fwd_confirm_buffer[..8]
-        );
-        
-        // 发送确认
-        forward.get_radio().send_data(&fwd_confirm_packet).unwrap();
-        
-        // 11. 客户端接收路径确认
-        let client_confirm = client.get_radio().receive_data(&mut rx_buffer).unwrap().unwrap();
-        
-        assert_eq!(client_confirm.header.source, forward_id.0);
-        assert_eq!(client_confirm.header.destination, client_id.0);
-        assert_eq!(client_confirm.data[6], PathStatus::Success as u8); // 确认成功状态
-        assert_eq!(client_confirm.data[7], 2); // 确认跳数为2
-        
-        // 总结: 验证了服务发现和路径建立的完整流程
-        println!("服务发现和路径建立测试通过!");
+        let candidacy = Candidacy { priority: 10, battery_level: 80, load: 10 };
+        deliver(&mut election, ElectionMessage::Start { election_id: 7, candidacy });
+        deliver(&mut election, ElectionMessage::Result { election_id: 6, master: NodeId::new([0x0B; 6]) });
+        assert_eq!(election.get_master(), None);
+        deliver(&mut election, ElectionMessage::Result { election_id: 7, master: initiator });
+        assert_eq!(election.get_master(), Some(initiator));
+    }
+    
+    #[test]
+    fn test_ineligible_node_never_elected() {
+        let node_id = NodeId::new([0xFF; 6]);
+        let other = NodeId::new([0x01; 6]);
+        let mut hardware = SimHardware::new(node_id, SimChannel::new());
+        let mut floods = FloodRelay::new(node_id);
+        
+        // 不带服务目录的节点发起选举时不作为候选者，即使优先级最高
+        let mut election = ElectionProtocol::new(node_id);
+        election.set_eligible(false);
+        election.initiate_election(&mut hardware, &mut floods, 0);
+        assert_eq!(election.candidates().count(), 0);
+        
+        let mut buffer = [0u8; 9];
+        let len = ElectionMessage::Response { election_id: 1, candidacy: Candidacy { priority: 1, battery_level: 50, load: 0 } }
+            .serialize(&mut buffer);
+        election.handle_packet(&mut hardware, &DataPacket::new(other, node_id, 0, &buffer[..len]), 100);
+        election.poll(&mut hardware, &mut floods, ELECTION_WINDOW_MS);
+        assert_eq!(election.get_master(), Some(other));
+        
+        // 没有其他候选者时不选出主节点
+        let mut alone = ElectionProtocol::new(node_id);
+        alone.set_eligible(false);
+        alone.initiate_election(&mut hardware, &mut floods, 0);
+        alone.poll(&mut hardware, &mut floods, ELECTION_WINDOW_MS);
+        assert_eq!(alone.get_master(), None);
+    }
+    
+    #[test]
+    fn test_service_beacon_round_trip() {
+        let beacon = ServiceBeacon::new(35, 72, 2000, 40, 98)
+            .with_service(ServiceType::Storage)
+            .with_service(ServiceType::SensorCollection);
+        
+        let mut buffer = [0u8; SERVICE_BEACON_LEN];
+        assert_eq!(beacon.serialize(&mut buffer), SERVICE_BEACON_LEN);
+        assert_eq!(beacon.serialize(&mut buffer[..SERVICE_BEACON_LEN - 1]), 0);
+        
+        let decoded = ServiceBeacon::deserialize(&buffer).unwrap();
+        assert_eq!(decoded, beacon);
+        assert!(decoded.supports(ServiceType::Storage));
+        assert!(!decoded.supports(ServiceType::VideoRelay));
+        assert_eq!(decoded.services().collect::<Vec<_>>(), [ServiceType::Storage, ServiceType::SensorCollection]);
+        assert!(ServiceBeacon::deserialize(&buffer[..4]).is_none());
+    }
+    
+    #[test]
+    fn test_lease_found_by_request_id() {
+        let client = NodeId::new([0xC1; 6]);
+        let server = NodeId::new([0x51; 6]);
+        let mut leases = LeaseTable::new();
+        leases.grant(7, client, server, ServiceType::VideoRelay, 3, 60, 0);
+        leases.grant(8, client, server, ServiceType::Storage, 0, 60, 0);
+        
+        // 重传的请求找到已分配的租约，不再分配新的服务ID
+        assert_eq!(leases.find_request(client, 3, ServiceType::VideoRelay).map(|lease| lease.service_id), Some(7));
+        assert!(leases.find_request(client, 3, ServiceType::Storage).is_none());
+        assert!(leases.find_request(NodeId::new([0xC2; 6]), 3, ServiceType::VideoRelay).is_none());
+        assert!(leases.find_request(client, 0, ServiceType::Storage).is_none());
     }
 } 
\ No newline at end of file
@@ -0,0 +1,568 @@
+#[cfg(test)]
+mod storage_retention_tests {
+    use std::cell::Cell;
+    
+    use common::hal::FlashStorage;
+    use common::hal::simulator::{SimFlash, SimulatorError};
+    use common::protocol::NodeId;
+    use common::protocol::command::{
+        deserialize_aggregate_response, deserialize_latest_response, parse_paged_query, AggregatePage, AggregateParams,
+        AggregateWindow, CommandStatus, CommandType, Downsample, Measurement, QueryPage, QueryParams, QueryProgress,
+        QueryReassembler, AGGREGATE_PARAMS_LEN, LATEST_RECORD_LEN, MAX_AGGREGATE_BUCKETS, MAX_QUERY_CHUNK_DATA,
+    };
+    use common::security::MAX_SECURE_PAYLOAD;
+    use server::api::paging::{query_chunks, QuerySnapshots, MAX_CHUNKS_PER_RESPONSE, QUERY_SNAPSHOT_TTL_MS};
+    use server::storage::{aggregate, SensorRecord, Storage, StorageEvent, Watermarks};
+    use server::storage::circular_buffer::CircularBuffer;
+    use server::storage::flash_log::FlashLog;
+    use server::storage::dedup::{UploadDedup, DEDUP_TIMESTAMP_TOLERANCE_MS, DEDUP_WINDOW_MS};
+    use server::storage::retention::{RecordArchive, RetentionAction, RetentionPolicy};
+    
+    struct TestArchive {
+        records: Vec<SensorRecord>,
+    }
+    
+    impl RecordArchive for TestArchive {
+        fn archive_record(&mut self, record: &SensorRecord) -> bool {
+            self.records.push(*record);
+            true
+        }
+    }
+    
+    #[test]
+    fn test_drop_records_older_than_max_age() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let mut storage = CircularBuffer::new();
+        
+        // 在不同时间写入三条记录
+        storage.update_timestamp(1_000);
+        storage.add_data(node_id, 20.0, 50.0, 101000.0);
+        storage.update_timestamp(5_000);
+        storage.add_data(node_id, 21.0, 51.0, 101000.0);
+        storage.update_timestamp(9_000);
+        storage.add_data(node_id, 22.0, 52.0, 101000.0);
+        
+        // 最大保留5秒，当前时间10秒：只有第一条记录过期
+        let mut retention = RetentionPolicy::new(5_000, RetentionAction::Drop);
+        let evicted = retention.apply(&mut storage, None, 10_000);
+        
+        assert_eq!(evicted, 1);
+        assert_eq!(retention.evicted_total(), 1);
+        
+        // 剩余两条记录，每条序列化后20字节
+        assert_eq!(storage.get_data_for_node(node_id).len(), 40);
+    }
+    
+    #[test]
+    fn test_archive_expired_records() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let mut storage = CircularBuffer::new();
+        
+        storage.update_timestamp(1_000);
+        storage.add_data(node_id, 20.0, 50.0, 101000.0);
+        storage.update_timestamp(2_000);
+        storage.add_data(node_id, 21.0, 51.0, 101000.0);
+        
+        let mut archive = TestArchive { records: Vec::new() };
+        let mut retention = RetentionPolicy::new(1_000, RetentionAction::Archive);
+        let evicted = retention.apply(&mut storage, Some(&mut archive), 10_000);
+        
+        // 两条记录都被归档后移除
+        assert_eq!(evicted, 2);
+        assert_eq!(archive.records.len(), 2);
+        assert!(storage.get_data_for_node(node_id).is_empty());
+    }
+    
+    #[test]
+    fn test_run_respects_check_interval() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let mut storage = CircularBuffer::new();
+        
+        storage.update_timestamp(1_000);
+        storage.add_data(node_id, 20.0, 50.0, 101000.0);
+        
+        let mut retention = RetentionPolicy::new(1_000, RetentionAction::Drop)
+            .with_check_interval(60_000);
+        
+        // 未到检查间隔，不执行
+        assert_eq!(retention.run(&mut storage, None, 30_000), 0);
+        
+        // 到达检查间隔后执行
+        assert_eq!(retention.run(&mut storage, None, 60_000), 1);
+    }
+    
+    #[test]
+    fn test_watermark_and_overwrite_events() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let watermarks = Watermarks { high_percent: 50, low_percent: 25, overwrite_margin: 100 };
+        let mut storage = CircularBuffer::new().with_watermarks(watermarks);
+        let capacity = storage.capacity();
+        let mut events = Vec::new();
+        
+        for _ in 0..capacity / 2 {
+            storage.add_data(node_id, 20.0, 50.0, 101000.0);
+        }
+        storage.take_events(|event| events.push(event));
+        assert_eq!(events, vec![StorageEvent::HighWatermark { records: capacity / 2, capacity }]);
+        
+        // 未读记录快要占满时提前报告，之后真正覆盖时再报告覆盖的条数
+        events.clear();
+        for _ in capacity / 2..capacity + 3 {
+            storage.add_data(node_id, 20.0, 50.0, 101000.0);
+        }
+        storage.take_events(|event| events.push(event));
+        assert_eq!(events, vec![
+            StorageEvent::OverwriteImminent { unread: capacity, capacity },
+            StorageEvent::UnreadOverwritten { count: 3 },
+        ]);
+        
+        // 上传后标记已读，覆盖已读记录不再报告
+        storage.mark_all_read();
+        storage.add_data(node_id, 20.0, 50.0, 101000.0);
+        events.clear();
+        storage.take_events(|event| events.push(event));
+        assert!(events.is_empty());
+        assert_eq!(storage.unread_count(), 1);
+        
+        storage.clear_all_data();
+        storage.take_events(|event| events.push(event));
+        assert_eq!(events, vec![StorageEvent::LowWatermark { records: 0, capacity }]);
+    }
+    
+    #[test]
+    fn test_query_downsamples_by_time() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let mut storage = CircularBuffer::new();
+        
+        // 一小时内每分钟一条记录，倒序写入
+        for minute in (0..60u64).rev() {
+            storage.add_data_at(node_id, minute * 60_000, 20.0, 50.0, 101000.0);
+        }
+        let timestamps = |data: Vec<u8>| -> Vec<u64> {
+            data.chunks_exact(20)
+                .map(|record| u64::from_be_bytes(record[6..14].try_into().unwrap()))
+                .collect()
+        };
+        
+        // 不降采样时按时间顺序返回范围内的记录
+        let range = QueryParams { start_time: 0, end_time: 9 * 60_000, downsample: Downsample::None };
+        assert_eq!(timestamps(storage.query(node_id, &range)), (0..10).map(|m| m * 60_000).collect::<Vec<_>>());
+        
+        // 每10分钟一条
+        let every = QueryParams { downsample: Downsample::PerMinutes(10), ..QueryParams::ALL };
+        assert_eq!(timestamps(storage.query(node_id, &every)), (0..6).map(|m| m * 600_000).collect::<Vec<_>>());
+        
+        // 最多7条，均匀分布在整个范围
+        let max = QueryParams { downsample: Downsample::MaxRecords(7), ..QueryParams::ALL };
+        let sampled = timestamps(storage.query(node_id, &max));
+        assert!(sampled.len() <= 7 && sampled.len() >= 6);
+        assert_eq!(sampled[0], 0);
+        assert!(*sampled.last().unwrap() >= 50 * 60_000);
+        
+        // 参数编码往返，降采样参数为0时拒绝
+        assert_eq!(QueryParams::from_bytes(&max.to_bytes()), Some(max));
+        assert_eq!(QueryParams::from_bytes(&[]), Some(QueryParams::ALL));
+        let mut zero = every.to_bytes();
+        zero[17..19].copy_from_slice(&0u16.to_be_bytes());
+        assert_eq!(QueryParams::from_bytes(&zero), None);
+    }
+    
+    #[test]
+    fn test_latest_returns_newest_record_per_node() {
+        let node_a = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let node_b = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x07]);
+        let mut storage = CircularBuffer::new();
+        
+        // 乱序写入的历史记录，最新值按时间戳而不是写入顺序选出
+        storage.add_data_at(node_a, 3_000, 23.0, 50.0, 101000.0);
+        storage.add_data_at(node_b, 2_000, 18.0, 60.0, 100000.0);
+        storage.add_data_at(node_a, 1_000, 21.0, 50.0, 101000.0);
+        storage.add_data_at(node_b, 4_000, 19.5, 61.0, 100000.0);
+        
+        let mut response = vec![CommandType::Latest as u8, CommandStatus::Ok as u8];
+        response.extend_from_slice(&storage.latest(None));
+        let records: Vec<_> = deserialize_latest_response(&response).unwrap().collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].0, records[0].1.timestamp), (node_a, 3_000));
+        assert_eq!((records[1].0, records[1].1.timestamp), (node_b, 4_000));
+        assert!((records[1].1.temperature - 19.5).abs() < 0.01);
+        
+        // 指定节点时只返回该节点
+        assert_eq!(storage.latest(Some(node_b)).len(), LATEST_RECORD_LEN);
+        assert!(storage.latest(Some(NodeId::new([0; 6]))).is_empty());
+    }
+    
+    #[test]
+    fn test_aggregate_by_window() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let other = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x07]);
+        let mut storage = CircularBuffer::new();
+        
+        // 三小时内每10分钟一条记录，温度依次递增，倒序写入
+        for i in (0..18).rev() {
+            storage.add_data_at(node_id, i * 600_000, i as f32, 50.0, 101000.0);
+            storage.add_data_at(other, i * 600_000, 100.0, 50.0, 101000.0);
+        }
+        
+        // 每小时一个窗口，与整点对齐
+        let mut params = AggregateParams {
+            node_id,
+            measurement: Measurement::Temperature,
+            start_time: 0,
+            end_time: 3 * 3_600_000,
+            window: AggregateWindow::Minutes(60),
+            offset: 0,
+        };
+        let hourly = storage.aggregate(&params);
+        assert_eq!(hourly.len(), 3);
+        assert_eq!((hourly[1].start_time, hourly[1].count), (3_600_000, 6));
+        assert_eq!((hourly[1].min, hourly[1].max), (6.0, 11.0));
+        assert!((hourly[1].avg - 8.5).abs() < 0.01);
+        
+        // 按窗口数等分时间跨度
+        params.window = AggregateWindow::Points(2);
+        let halves = storage.aggregate(&params);
+        assert_eq!(halves.iter().map(|bucket| bucket.count).collect::<Vec<_>>(), vec![9, 9]);
+        assert_eq!((halves[1].min, halves[1].max), (9.0, 17.0));
+        
+        // 参数和响应经过编码后保持不变，零窗口无效
+        assert_eq!(AggregateParams::from_bytes(&params.to_bytes()), Some(params));
+        params.window = AggregateWindow::Minutes(0);
+        assert_eq!(AggregateParams::from_bytes(&params.to_bytes()), None);
+        
+        // 不带窗口偏移的旧参数从第一个窗口开始
+        params.window = AggregateWindow::Minutes(60);
+        params.offset = 3;
+        let mut legacy = params;
+        legacy.offset = 0;
+        assert_eq!(AggregateParams::from_bytes(&params.to_bytes()[..AGGREGATE_PARAMS_LEN - 2]), Some(legacy));
+        
+        let page = AggregatePage { node_id, measurement: Measurement::Temperature, offset: 3, more: true };
+        let mut response = vec![CommandType::Aggregate as u8, CommandStatus::Ok as u8];
+        response.extend_from_slice(&page.to_bytes());
+        for bucket in &hourly {
+            response.extend_from_slice(&bucket.to_bytes());
+        }
+        let (header, buckets) = deserialize_aggregate_response(&response).unwrap();
+        assert_eq!(header, page);
+        assert_eq!(buckets.collect::<Vec<_>>(), hourly);
+    }
+    
+    #[test]
+    fn test_aggregate_rollups_follow_overwrites_and_eviction() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let mut storage = CircularBuffer::new();
+        let params = AggregateParams {
+            node_id,
+            measurement: Measurement::Temperature,
+            start_time: 0,
+            end_time: 24 * 3_600_000 - 1,
+            window: AggregateWindow::Minutes(60),
+            offset: 0,
+        };
+        
+        // 一天内每分钟一条记录，超过缓冲区容量，最早的记录被覆盖
+        for minute in 0..1440u64 {
+            storage.add_data_at(node_id, minute * 60_000, (minute % 60) as f32, 50.0, 101000.0);
+        }
+        let hourly = storage.aggregate(&params);
+        assert_eq!(hourly.len(), 18);
+        assert_eq!(hourly.iter().map(|bucket| bucket.count as usize).sum::<usize>(), storage.record_count());
+        assert_eq!((hourly[0].start_time, hourly[0].count), (6 * 3_600_000, 4));
+        assert_eq!((hourly[0].min, hourly[0].max), (56.0, 59.0));
+        assert_eq!((hourly[17].min, hourly[17].max, hourly[17].count), (0.0, 59.0, 60));
+        
+        // 清空后没有窗口
+        storage.clear_all_data();
+        assert!(storage.aggregate(&params).is_empty());
+        
+        // 每30秒一条记录，移除一分钟中的最小值后这一分钟的统计仍与记录一致
+        for minute in 0..200u64 {
+            storage.add_data_at(node_id, minute * 60_000, (minute % 60) as f32, 50.0, 101000.0);
+            storage.add_data_at(node_id, minute * 60_000 + 30_000, (minute % 60) as f32 + 0.5, 50.0, 101000.0);
+        }
+        storage.evict_older_than(3_600_000 + 1, |_| {});
+        let hourly = storage.aggregate(&params);
+        assert_eq!(hourly.len(), 3);
+        assert_eq!((hourly[0].start_time, hourly[0].count), (3_600_000, 119));
+        assert_eq!((hourly[0].min, hourly[0].max), (0.5, 59.5));
+        assert!((hourly[0].avg - 30.0).abs() < 0.01);
+    }
+    
+    #[test]
+    fn test_aggregate_pages_beyond_one_packet() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let mut storage = CircularBuffer::new();
+        for hour in 0..24u64 {
+            storage.add_data_at(node_id, hour * 3_600_000, hour as f32, 50.0, 101000.0);
+        }
+        
+        // 按每页的窗口数逐页取回一天的每小时窗口
+        let mut params = AggregateParams {
+            node_id,
+            measurement: Measurement::Temperature,
+            start_time: 0,
+            end_time: 24 * 3_600_000 - 1,
+            window: AggregateWindow::Minutes(60),
+            offset: 0,
+        };
+        let all = storage.aggregate(&params);
+        assert_eq!(all.len(), 24);
+        
+        let mut pages = Vec::new();
+        loop {
+            let (header, page) = aggregate::page(&params, &all);
+            assert_eq!(header.offset, params.offset);
+            assert!(page.len() <= MAX_AGGREGATE_BUCKETS);
+            pages.extend_from_slice(page);
+            if !header.more {
+                break;
+            }
+            params.offset += page.len() as u16;
+            params = AggregateParams::from_bytes(&params.to_bytes()).unwrap();
+        }
+        assert_eq!(pages, all);
+        
+        // 偏移超出结果时返回空页
+        params.offset = 100;
+        let (header, page) = aggregate::page(&params, &all);
+        assert!(page.is_empty() && !header.more);
+    }
+    
+    #[test]
+    fn test_paged_query_resumes_after_lost_chunk() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let mut storage = CircularBuffer::new();
+        for i in 0..120 {
+            storage.add_data_at(node_id, i * 1000, 20.0, 50.0, 101000.0);
+        }
+        
+        // 分页参数附在查询参数之后，没有分页参数时从开头查询
+        let (params, page) = parse_paged_query(&QueryPage { token: 7, offset: 0 }.to_bytes()).unwrap();
+        assert_eq!((params, page), (QueryParams::ALL, QueryPage { token: 7, offset: 0 }));
+        assert_eq!(parse_paged_query(&[]), Some((QueryParams::ALL, QueryPage::default())));
+        
+        let data = storage.query(node_id, &params);
+        assert_eq!(data.len(), 120 * 20);
+        assert!(data.len() > MAX_CHUNKS_PER_RESPONSE * MAX_QUERY_CHUNK_DATA);
+        
+        let mut reassembler = QueryReassembler::new(7, vec![0u8; 4096]);
+        let deliver = |reassembler: &mut QueryReassembler<Vec<u8>>, page: QueryPage, lost: Option<usize>| {
+            let mut progress = Vec::new();
+            for (index, chunk) in query_chunks(page, &data).unwrap().enumerate() {
+                if Some(index) == lost {
+                    continue;
+                }
+                let mut packet = [0u8; MAX_SECURE_PAYLOAD];
+                let len = chunk.serialize(&mut packet);
+                progress.push(reassembler.accept(&packet[..len]));
+            }
+            progress
+        };
+        
+        // 第三个分片丢失：缺口只报告一次续传，之后的分片被忽略
+        let progress = deliver(&mut reassembler, page, Some(2));
+        assert_eq!(progress.len(), MAX_CHUNKS_PER_RESPONSE - 1);
+        assert_eq!(&progress[..3], &[QueryProgress::Receiving, QueryProgress::Receiving, QueryProgress::Resume]);
+        assert!(progress[3..].iter().all(|progress| *progress == QueryProgress::Ignored));
+        assert_eq!(reassembler.next_page().offset as usize, 2 * MAX_QUERY_CHUNK_DATA);
+        
+        // 从缺口续传，每次应答发送的分片数有上限，最后一个分片要求继续续传
+        let page = reassembler.next_page();
+        let progress = deliver(&mut reassembler, page, None);
+        assert_eq!(progress.last(), Some(&QueryProgress::Resume));
+        assert!(reassembler.data().is_none());
+        
+        let page = reassembler.next_page();
+        let progress = deliver(&mut reassembler, page, None);
+        assert_eq!(progress.last(), Some(&QueryProgress::Complete));
+        assert_eq!(reassembler.data(), Some(&data[..]));
+        
+        // 偏移超出结果时服务器拒绝，空结果只有一个结束分片
+        assert!(query_chunks(QueryPage { token: 7, offset: data.len() as u32 + 1 }, &data).is_none());
+        let empty: Vec<_> = query_chunks(QueryPage::default(), &[]).unwrap().collect();
+        assert_eq!(empty.len(), 1);
+        assert!(!empty[0].more && empty[0].data.is_empty());
+        
+        let mut buffer = [0u8; 16];
+        let mut rejected = QueryReassembler::new(7, &mut buffer);
+        let response = [CommandType::Query as u8, CommandStatus::InvalidParameter as u8];
+        assert_eq!(rejected.accept(&response), QueryProgress::Rejected(CommandStatus::InvalidParameter));
+    }
+    
+    #[test]
+    fn test_paged_query_resumes_from_snapshot() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let other = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x07]);
+        let mut storage = CircularBuffer::new();
+        for i in 0..60 {
+            storage.add_data_at(node_id, i * 1000, 20.0, 50.0, 101000.0);
+        }
+        
+        let mut snapshots = QuerySnapshots::new();
+        let first = QueryPage { token: 7, offset: 0 };
+        let data = snapshots.result(node_id, first, 0, || storage.query(node_id, &QueryParams::ALL)).unwrap().to_vec();
+        
+        // 两页之间写入新记录，续传仍按第一次的结果切片，偏移不会移动
+        for i in 60..80 {
+            storage.add_data_at(node_id, i * 1000, 21.0, 50.0, 101000.0);
+        }
+        let resume = QueryPage { token: 7, offset: 400 };
+        let resumed = snapshots.result(node_id, resume, 1000, || unreachable!()).unwrap();
+        assert_eq!(resumed, &data[..]);
+        
+        // 其他请求方或其他令牌没有快照，过期的快照也不能续传
+        assert!(snapshots.result(other, resume, 1000, || unreachable!()).is_none());
+        assert!(snapshots.result(node_id, QueryPage { token: 8, offset: 400 }, 1000, || unreachable!()).is_none());
+        assert!(snapshots.result(node_id, resume, QUERY_SNAPSHOT_TTL_MS, || unreachable!()).is_none());
+        
+        // 从头查询时重新生成快照，包含新写入的记录
+        let fresh = snapshots.result(node_id, first, QUERY_SNAPSHOT_TTL_MS, || storage.query(node_id, &QueryParams::ALL)).unwrap();
+        assert_eq!(fresh.len(), 80 * 20);
+    }
+    
+    #[test]
+    fn test_dedup_suppresses_retransmitted_uploads() {
+        let node_a = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let node_b = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x07]);
+        let mut dedup = UploadDedup::new();
+        
+        assert!(!dedup.is_duplicate(node_a, 7, 100_000, 100_000));
+        // 重传晚到几秒，推算出的时间戳也晚几秒
+        assert!(dedup.is_duplicate(node_a, 7, 104_000, 104_000));
+        // 其他节点或其他包ID不受影响，不需要确认的包不去重
+        assert!(!dedup.is_duplicate(node_b, 7, 104_000, 104_000));
+        assert!(!dedup.is_duplicate(node_a, 8, 104_000, 104_000));
+        assert!(!dedup.is_duplicate(node_a, 0, 104_000, 104_000));
+        assert!(!dedup.is_duplicate(node_a, 0, 104_000, 104_000));
+        
+        // 客户端重启后复用包ID，但样本时间相差很远
+        assert!(!dedup.is_duplicate(node_a, 7, 100_000 + DEDUP_TIMESTAMP_TOLERANCE_MS + 1, 130_000));
+        
+        // 超过保留时间后不再记得
+        assert!(!dedup.is_duplicate(node_a, 8, 104_000, 104_000 + DEDUP_WINDOW_MS + 1));
+    }
+    
+    #[test]
+    fn test_flash_log_survives_reopen_and_rotates_sectors() {
+        let node_id = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let other = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x07]);
+        // 4个扇区，每个扇区去掉扇区头后放7条记录
+        let flash = SimFlash::with_geometry(256, 4);
+        let mut storage = FlashLog::open(flash.clone());
+        assert_eq!(storage.capacity(), 28);
+        
+        for i in 0..10 {
+            storage.add_data_at(node_id, i * 1000, 20.0 + i as f32, 50.0, 101000.0);
+        }
+        storage.add_data_at(other, 500, 18.0, 40.0, 100000.0);
+        storage.clear_data_for_node(other);
+        assert_eq!(storage.record_count(), 10);
+        
+        // 重新打开后记录仍在，删除的记录不会恢复
+        let mut reopened = FlashLog::open(flash.clone());
+        assert_eq!(reopened.record_count(), 10);
+        assert_eq!(reopened.get_data_for_node(node_id), storage.get_data_for_node(node_id));
+        assert!(reopened.get_data_for_node(other).is_empty());
+        
+        // 从上次的位置继续写入，写满分区后擦除最旧的扇区，各扇区擦除次数相同
+        reopened.mark_all_read();
+        for i in 10..55 {
+            reopened.add_data_at(node_id, i * 1000, 20.0, 50.0, 101000.0);
+        }
+        assert_eq!(flash.erase_counts(), vec![2, 2, 2, 2]);
+        assert_eq!(reopened.record_count(), 28);
+        assert!(reopened.get_data_in_timerange(0, 26_999).is_empty());
+        assert_eq!(reopened.get_data_in_timerange(27_000, 54_000).len(), 28 * 20);
+        
+        // 上传前被擦除的未读记录照常报告
+        let mut events = Vec::new();
+        reopened.take_events(|event| events.push(event));
+        assert_eq!(events, vec![
+            StorageEvent::OverwriteImminent { unread: 28, capacity: 28 },
+            StorageEvent::UnreadOverwritten { count: 17 },
+        ]);
+        
+        assert_eq!(FlashLog::open(flash).record_count(), 28);
+    }
+    
+    /// 前几次读取扇区数时报告忙的闪存
+    struct BusyFlash {
+        flash: SimFlash,
+        busy_reads: Cell<usize>,
+    }
+    
+    impl FlashStorage for BusyFlash {
+        type Error = SimulatorError;
+        
+        fn sector_size(&self) -> usize {
+            self.flash.sector_size()
+        }
+        
+        fn sector_count(&self) -> Result<usize, SimulatorError> {
+            match self.busy_reads.get() {
+                0 => self.flash.sector_count(),
+                remaining => {
+                    self.busy_reads.set(remaining - 1);
+                    Err(SimulatorError::ConfigError)
+                },
+            }
+        }
+        
+        fn erase_sector(&mut self, sector: usize) -> Result<(), SimulatorError> {
+            self.flash.erase_sector(sector)
+        }
+        
+        fn flash_write(&mut self, offset: u32, data: &[u8]) -> Result<(), SimulatorError> {
+            self.flash.flash_write(offset, data)
+        }
+        
+        fn flash_read(&self, offset: u32, buffer: &mut [u8]) -> Result<(), SimulatorError> {
+            self.flash.flash_read(offset, buffer)
+        }
+    }
+    
+    #[test]
+    fn test_flash_log_open_retries_busy_flash() {
+        // 暂时忙的闪存重试后正常打开
+        let busy = BusyFlash { flash: SimFlash::with_geometry(256, 4), busy_reads: Cell::new(3) };
+        assert_eq!(FlashLog::open(busy).capacity(), 28);
+        
+        // 一直忙时容量为0，记录被丢弃而不是写到错误的位置
+        let stuck = BusyFlash { flash: SimFlash::with_geometry(256, 4), busy_reads: Cell::new(usize::MAX) };
+        let mut storage = FlashLog::open(stuck);
+        assert_eq!(storage.capacity(), 0);
+        storage.add_data_at(NodeId::new([0x01; 6]), 1000, 20.0, 50.0, 101000.0);
+        assert_eq!(storage.record_count(), 0);
+    }
+    
+    /// 先写入时间戳最大的记录，再写满一整圈较旧的记录把它覆盖，最新值要从剩下的记录中重新选出
+    fn check_latest_after_newest_overwritten<S: Storage>(mut storage: S) {
+        let node_a = NodeId::new([0x01; 6]);
+        let node_b = NodeId::new([0x02; 6]);
+        storage.add_data_at(node_a, 1_000_000, 30.0, 50.0, 101000.0);
+        let capacity = storage.capacity() as u64;
+        for timestamp in 1..=capacity {
+            storage.add_data_at(node_a, timestamp, 20.0, 50.0, 101000.0);
+        }
+        storage.add_data_at(node_b, 500, 18.0, 60.0, 100000.0);
+        
+        let mut response = vec![CommandType::Latest as u8, CommandStatus::Ok as u8];
+        response.extend_from_slice(&storage.latest(None));
+        let records: Vec<_> = deserialize_latest_response(&response).unwrap().collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].0, records[0].1.timestamp), (node_a, capacity));
+        assert_eq!((records[1].0, records[1].1.timestamp), (node_b, 500));
+        
+        // 清空一个节点后不再返回它
+        storage.clear_data_for_node(node_a);
+        assert_eq!(storage.latest(None).len(), LATEST_RECORD_LEN);
+        assert!(storage.latest(Some(node_a)).is_empty());
+    }
+    
+    #[test]
+    fn test_latest_index_after_newest_record_removed() {
+        check_latest_after_newest_overwritten(CircularBuffer::new());
+        check_latest_after_newest_overwritten(FlashLog::open(SimFlash::with_geometry(256, 4)));
+    }
+}
\ No newline at end of file
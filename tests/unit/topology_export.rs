@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod topology_export_tests {
+    use common::protocol::NodeId;
+    use common::protocol::topology::TopologyReport;
+    use common::topology::{TopologyMap, TOPOLOGY_MAX_AGE_MS};
+    
+    fn node(last: u8) -> NodeId {
+        NodeId([0, 0, 0, 0, 0, last])
+    }
+    
+    /// 主节点1听到2，2听到3，1经2到达3
+    fn sample_map() -> TopologyMap {
+        let mut map = TopologyMap::new();
+        map.update(&TopologyReport::from_routes(node(1), 1, node(1), [
+            (node(2), node(2), -60),
+            (node(3), node(2), -60),
+        ]), 1000);
+        map.update(&TopologyReport::from_routes(node(1), 1, node(2), [
+            (node(1), node(1), -62),
+            (node(3), node(3), -75),
+        ]), 1200);
+        map
+    }
+    
+    #[test]
+    fn test_topology_dot_export() {
+        let mut dot = String::new();
+        sample_map().write_dot(&mut dot).unwrap();
+        
+        assert!(dot.starts_with("digraph linknebula {\n"));
+        assert!(dot.contains("\"000000000001\" -> \"000000000002\" [label=\"-60 dBm\"];"));
+        assert!(dot.contains("\"000000000001\" -> \"000000000003\" [style=dashed, label=\"via 000000000002\"];"));
+        assert!(dot.contains("\"000000000002\" -> \"000000000003\" [label=\"-75 dBm\"];"));
+        assert!(dot.ends_with("}\n"));
+    }
+    
+    #[test]
+    fn test_topology_json_export() {
+        let mut json = String::new();
+        sample_map().write_json(&mut json).unwrap();
+        
+        assert!(json.starts_with("{\"nodes\":[{\"id\":\"000000000001\",\"updated_at\":1000},"));
+        assert!(json.contains("{\"from\":\"000000000001\",\"to\":\"000000000003\",\"via\":\"000000000002\",\"metric\":-60,\"neighbor\":false}"));
+        assert_eq!(json.matches("\"from\"").count(), 4);
+    }
+    
+    #[test]
+    fn test_topology_report_replaced_and_expired() {
+        let mut map = sample_map();
+        
+        // 同一节点的新报告替换旧报告
+        map.update(&TopologyReport::from_routes(node(1), 2, node(2), [(node(1), node(1), -61)]), 5000);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.edges().filter(|(reporter, _)| *reporter == node(2)).count(), 1);
+        
+        // 只剩节点2的报告未过期
+        assert_eq!(map.expire(1000 + TOPOLOGY_MAX_AGE_MS + 1), 1);
+        assert_eq!(map.len(), 1);
+    }
+}
\ No newline at end of file
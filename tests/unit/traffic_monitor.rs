@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod traffic_monitor_tests {
+    use common::monitor::{TrafficMonitor, MAX_MONITORED_NODES};
+    use common::protocol::{Beacon, DataPacket, NodeId, PacketType};
+    
+    #[test]
+    fn test_monitor_counts_per_node_and_type() {
+        let relay = NodeId::new([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let server = NodeId::new([0x11, 0x12, 0x13, 0x14, 0x15, 0x16]);
+        let mut monitor = TrafficMonitor::new(0);
+        
+        monitor.observe_beacon(&Beacon::new(relay, 90, -60), -61, 100);
+        monitor.observe_data(&DataPacket::new(relay, server, 1, b"reading"), -62, 200);
+        monitor.observe_data(&DataPacket::with_type(server, relay, PacketType::Ack, 1, &[0; 4]), -70, 300);
+        
+        let relay_traffic = monitor.nodes().find(|traffic| traffic.node == relay).unwrap();
+        assert_eq!(relay_traffic.packets[PacketType::Beacon as usize], 1);
+        assert_eq!(relay_traffic.packets[PacketType::Data as usize], 1);
+        assert_eq!(relay_traffic.total(), 2);
+        assert_eq!(relay_traffic.rssi, -62);
+        assert_eq!((relay_traffic.first_seen, relay_traffic.last_seen), (100, 200));
+        
+        let mut report = String::new();
+        monitor.report(&mut report, 1000).unwrap();
+        assert!(report.contains("听到 2 个节点"));
+        
+        monitor.clear(1000);
+        assert_eq!(monitor.nodes().count(), 0);
+    }
+    
+    #[test]
+    fn test_monitor_replaces_oldest_node() {
+        let mut monitor = TrafficMonitor::new(0);
+        for i in 0..=MAX_MONITORED_NODES {
+            let node = NodeId::new([0, 0, 0, 0, 0, i as u8]);
+            monitor.observe_data(&DataPacket::new(node, NodeId::new([0xFF; 6]), 1, b""), -80, i as u64);
+        }
+        
+        // 表满后最早听到的节点被替换
+        assert_eq!(monitor.nodes().count(), MAX_MONITORED_NODES);
+        assert!(monitor.nodes().all(|traffic| traffic.node != NodeId::new([0; 6])));
+    }
+}
\ No newline at end of file
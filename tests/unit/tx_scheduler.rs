@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tx_scheduler_tests {
+    use common::pool::{self, NodePool};
+    use common::protocol::{NodeId, PacketType};
+    use forward::scheduler::{QueuedPacket, TrafficClass, TxScheduler};
+    
+    /// 把流的队列填满
+    fn fill(scheduler: &mut TxScheduler, pool: &'static NodePool, flow_id: u16, len: usize) {
+        let next_hop = NodeId::new([0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6]);
+        let data = vec![0u8; len];
+        loop {
+            let packet = QueuedPacket::new(pool, next_hop, PacketType::Data, 1, &data).unwrap().with_flow(flow_id);
+            if scheduler.enqueue(TrafficClass::Data { flow_id, weight: 1 }, packet).is_err() {
+                break;
+            }
+        }
+    }
+    
+    #[test]
+    fn test_flows_share_bytes_fairly() {
+        let mut scheduler = TxScheduler::new();
+        let pool = pool::node_pool();
+        let mut sent = [0usize; 2];
+        
+        // 流1发大包，流2发小包，两个流一直有积压
+        for _ in 0..200 {
+            fill(&mut scheduler, pool, 1, 200);
+            fill(&mut scheduler, pool, 2, 50);
+            let packet = scheduler.dequeue(true).unwrap();
+            sent[packet.flow_id as usize - 1] += packet.data().len();
+        }
+        
+        // 按字节而不是按包数轮转，两个流的发送量相差不超过一轮的份额
+        let total = sent[0] + sent[1];
+        assert!(sent[0].abs_diff(sent[1]) * 10 < total, "发送字节数不均衡: {:?}", sent);
+        
+        // 控制流量仍然严格优先
+        let control = QueuedPacket::new(pool, NodeId::BROADCAST, PacketType::Data, 2, &[0u8; 8]).unwrap();
+        scheduler.enqueue(TrafficClass::Control, control).unwrap();
+        assert_eq!(scheduler.dequeue(true).unwrap().packet_id, 2);
+        
+        // 没有时隙时会话数据留在队列中
+        assert!(scheduler.dequeue(false).is_none());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,40 @@
+use std::fmt::Write;
+
+use common::link_budget::LinkBudget;
+
+/// 部署时默认保留的链路余量（dB）
+const DEFAULT_MARGIN_DB: i16 = 10;
+
+/// 解析参数并生成链路预算报告，参数无效时返回None
+///
+/// 参数：[--tx-power <dBm>] [--margin <dB>] <RSSI>...
+pub fn run(args: &[String]) -> Option<String> {
+    let mut budget = LinkBudget::default();
+    let mut margin = DEFAULT_MARGIN_DB;
+    let mut samples = Vec::new();
+    
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tx-power" => budget.tx_power_dbm = args.next()?.parse().ok()?,
+            "--margin" => margin = args.next()?.parse().ok()?,
+            rssi => samples.push(rssi.parse::<i8>().ok()?),
+        }
+    }
+    
+    let estimate = budget.estimate_samples(&samples)?;
+    let mut out = String::new();
+    let _ = writeln!(out, "采样 {} 次，平均 RSSI {} dBm（发射功率 {} dBm，灵敏度 {} dBm）",
+        samples.len(), estimate.rssi_dbm, budget.tx_power_dbm, budget.sensitivity_dbm);
+    let _ = writeln!(out, "路径损耗 {} dB，链路余量 {} dB，估计距离 {:.1} m",
+        estimate.path_loss_db, estimate.margin_db, estimate.distance_m);
+    
+    let max_distance = budget.max_distance(margin);
+    if estimate.margin_db < margin {
+        let _ = writeln!(out, "余量已低于 {} dB，下一个中继应放在更靠近上一跳的位置（约 {:.1} m 以内）", margin, max_distance);
+    } else {
+        let _ = writeln!(out, "保持 {} dB 余量时最远约 {:.1} m，下一个中继应在此之前放置", margin, max_distance);
+    }
+    
+    Some(out)
+}
\ No newline at end of file
@@ -0,0 +1,475 @@
+use std::fmt::Write;
+
+use common::protocol::{
+    Beacon, DataPacket, NodeId, PacketType,
+    deserialize_service_close, deserialize_service_handover, deserialize_service_renewal,
+    deserialize_service_request, deserialize_service_response,
+};
+use common::protocol::batch::deserialize_batch;
+use common::protocol::channel::{ChannelMessage, CHANNEL_COUNT, FIRST_CHANNEL};
+use common::protocol::echo::Echo;
+use common::protocol::election::ElectionMessage;
+use common::protocol::error_report::ErrorReport;
+use common::protocol::hello::hello_nonce;
+use common::protocol::lookup::LookupMessage;
+use common::protocol::frame::VideoTierNotice;
+use common::protocol::mgmt::{MgmtAttribute, MgmtMessage};
+use common::protocol::ota::{OtaBody, OtaMessage};
+use common::protocol::payload::{self, Command, PayloadType, Query, SensorReading, VideoFrame};
+use common::protocol::tdma::{SlotRequest, SlotTable};
+use common::protocol::time_sync::TimeBeacon;
+use common::protocol::route_advert::{RouteAdvert, ETX_SCALE};
+use common::protocol::route_discovery::DiscoveryMessage;
+use common::protocol::route_table::RouteTableMessage;
+use common::protocol::service_advert::ServiceAdvertisement;
+use common::protocol::service_beacon::ServiceBeacon;
+use common::protocol::flood::Flood;
+use common::protocol::stats::StatsMessage;
+use common::protocol::keepalive::PathKeepAlive;
+use common::protocol::topology::TopologyMessage;
+use common::protocol::wire::get_u32be;
+use common::security::{SECURE_FLAG, SECURE_OVERHEAD};
+
+/// 将一帧解码为可读的多行描述
+pub fn describe(frame: &[u8]) -> String {
+    let mut out = String::new();
+    
+    // 信标的长度固定，与数据包头部区分
+    if frame.len() == Beacon::SIZE {
+        if let Some(beacon) = Beacon::from_bytes(frame) {
+            describe_beacon(&mut out, &beacon);
+            return out;
+        }
+    }
+    
+    match DataPacket::parse(frame) {
+        Some(packet) => describe_packet(&mut out, &packet),
+        None => {
+            let _ = writeln!(out, "无法解析的帧（{} 字节，截断或长度字段越界）", frame.len());
+            let _ = writeln!(out, "  原始数据: {}", hex(frame));
+        },
+    }
+    
+    out
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
+
+fn validity(valid: bool) -> &'static str {
+    if valid { "正确" } else { "错误" }
+}
+
+fn describe_beacon(out: &mut String, beacon: &Beacon) {
+    let checksum = beacon.checksum;
+    let _ = writeln!(out, "信标 v{} 来自 {}", beacon.version, NodeId(beacon.source));
+    let _ = writeln!(out, "  角色: {:?}  电量: {}%  RSSI: {} dBm  跳数: {}",
+        beacon.role(), beacon.battery_level, beacon.rssi, beacon.hop_count);
+    // 没有网络密钥无法验证认证码，只显示内容
+    if beacon.tag.iter().any(|&b| b != 0) {
+        let _ = writeln!(out, "  认证码: {}", hex(&beacon.tag));
+    } else {
+        let _ = writeln!(out, "  认证码: 无");
+    }
+    let _ = writeln!(out, "  校验和: 0x{:04X}（{}）", checksum, validity(beacon.is_valid()));
+}
+
+fn describe_packet(out: &mut String, packet: &DataPacket) {
+    let header = packet.header;
+    let secure = header.packet_type & SECURE_FLAG != 0;
+    let packet_type = PacketType::from_u8(header.packet_type & !SECURE_FLAG);
+    let packet_id = header.packet_id;
+    let flow_id = header.flow_id;
+    let checksum = header.checksum;
+    
+    match packet_type {
+        Some(packet_type) => {
+            let _ = write!(out, "{:?}", packet_type);
+        },
+        None => {
+            let _ = write!(out, "未知类型 0x{:02X}", header.packet_type & !SECURE_FLAG);
+        },
+    }
+    let _ = writeln!(out, "{} v{} {} -> {}",
+        if secure { "（受保护）" } else { "" },
+        header.version, NodeId(header.source), NodeId(header.destination));
+    let _ = writeln!(out, "  包ID: {}  流ID: {}  分片: {}/{}  跳数: {}  负载: {} 字节",
+        packet_id, flow_id, header.fragment_index, header.total_fragments, header.ttl, packet.data.len());
+    let _ = writeln!(out, "  校验和: 0x{:04X}（{}）", checksum, validity(packet.is_valid()));
+    
+    if secure {
+        // 没有网络密钥无法解密，只显示计数器
+        if packet.data.len() >= SECURE_OVERHEAD {
+            let counter = get_u32be(&packet.data[0..4]);
+            let _ = writeln!(out, "  负载已加密，计数器: {}", counter);
+        } else {
+            let _ = writeln!(out, "  受保护的负载过短");
+        }
+        return;
+    }
+    
+    let described = match packet_type {
+        Some(packet_type) => describe_payload(out, packet_type, packet),
+        None => false,
+    };
+    if !described && !packet.data.is_empty() {
+        let _ = writeln!(out, "  负载: {}", hex(packet.data));
+    }
+}
+
+/// 按包类型解码负载，无法识别时返回false
+fn describe_payload(out: &mut String, packet_type: PacketType, packet: &DataPacket) -> bool {
+    let data = packet.data;
+    
+    // 只有第一个分片带负载头部，其余分片按原始字节显示
+    if packet.header.fragment_index != 0 {
+        return false;
+    }
+    
+    match packet_type {
+        PacketType::ServiceRequest => match deserialize_service_request(data) {
+            Some(request) => {
+                let _ = writeln!(out, "  服务请求 #{}: {:?}  带宽≥{} kbps  延迟≤{} ms  可靠性 {}%  租期 {} 秒",
+                    request.request_id, request.service_type, request.qos.min_bandwidth, request.qos.max_latency,
+                    request.qos.reliability, request.expiry_time);
+                true
+            },
+            None => false,
+        },
+        PacketType::ServiceResponse => match deserialize_service_response(data) {
+            Some(response) => {
+                let _ = writeln!(out, "  服务响应 #{}: 服务ID {}  服务器 {}  状态 {}",
+                    response.request_id, response.service_id, response.server_node_id, response.status);
+                true
+            },
+            None => false,
+        },
+        PacketType::ServiceRenew => match deserialize_service_renewal(data) {
+            Some(renewal) => {
+                let _ = writeln!(out, "  租约续期: 服务ID {}  租期 {} 秒", renewal.service_id, renewal.expiry_time);
+                true
+            },
+            None => false,
+        },
+        PacketType::PathKeepAlive | PacketType::PathKeepAliveAck => match PathKeepAlive::deserialize(data) {
+            Some(keepalive) => {
+                let _ = writeln!(out, "  路径保活: 服务ID {}  序号 {}", keepalive.service_id, keepalive.sequence);
+                true
+            },
+            None => false,
+        },
+        PacketType::StatsRequest | PacketType::StatsResponse => match StatsMessage::deserialize(packet_type as u8, data) {
+            Some(StatsMessage::Request { origin, target, first }) => {
+                let _ = writeln!(out, "  计数器查询: {} -> {}  从第 {} 项起", origin, target, first);
+                true
+            },
+            Some(StatsMessage::Response { origin, target, page }) => {
+                let _ = write!(out, "  计数器应答: {} -> {}  从第 {} 项起\n{}", target, origin, page.first, page);
+                true
+            },
+            None => false,
+        },
+        PacketType::RouteTableRequest | PacketType::RouteTableResponse => match RouteTableMessage::deserialize(packet_type as u8, data) {
+            Some(RouteTableMessage::Request { origin, target, max_entries }) => {
+                let _ = writeln!(out, "  路由表查询: {} -> {}  最多 {} 条", origin, target, max_entries);
+                true
+            },
+            Some(RouteTableMessage::Response { origin, target, dump }) => {
+                let _ = write!(out, "  路由表应答: {} -> {}\n{}", target, origin, dump);
+                true
+            },
+            None => false,
+        },
+        PacketType::RouteRequest | PacketType::RouteReply => match DiscoveryMessage::deserialize(packet_type as u8, data) {
+            Some(DiscoveryMessage::Request { target, initial_ttl }) => {
+                let _ = writeln!(out, "  路由请求: 目标 {}  初始跳数 {}", target, initial_ttl);
+                true
+            },
+            Some(DiscoveryMessage::Reply { origin, route }) => {
+                let _ = writeln!(out, "  路由应答: 发往 {}  {} 经 {}  {} 跳  ETX {:.1}  {} dBm", origin, route.destination,
+                    route.next_hop, route.hops, route.etx as f32 / ETX_SCALE as f32, route.rssi);
+                true
+            },
+            None => false,
+        },
+        PacketType::Flood => match Flood::deserialize(data) {
+            Some(flood) => {
+                let _ = writeln!(out, "  泛洪: 发起 {}  序号 {}  原始类型 0x{:02X}  负载 {} 字节",
+                    flood.origin, flood.sequence, flood.packet_type, flood.payload.len());
+                true
+            },
+            None => false,
+        },
+        PacketType::ServiceClose => match deserialize_service_close(data) {
+            Some(close) => {
+                let _ = writeln!(out, "  服务关闭: 服务ID {}  原因 {}", close.service_id, close.reason);
+                true
+            },
+            None => false,
+        },
+        PacketType::ServiceHandover => match deserialize_service_handover(data) {
+            Some(handover) => {
+                let _ = writeln!(out, "  服务切换: 服务ID {}  原服务器 {}  {:?}  租期 {} 秒",
+                    handover.service_id, handover.server_node_id,
+                    handover.request.service_type, handover.request.expiry_time);
+                true
+            },
+            None => false,
+        },
+        PacketType::Ack | PacketType::Congestion => {
+            if data.len() < 4 {
+                return false;
+            }
+            let session_id = get_u32be(&data[0..4]);
+            let _ = writeln!(out, "  会话ID: {}", session_id);
+            true
+        },
+        PacketType::Hello | PacketType::HelloAck => match hello_nonce(packet) {
+            Some(nonce) => {
+                let _ = writeln!(out, "  随机数: 0x{:08X}", nonce);
+                true
+            },
+            None => false,
+        },
+        PacketType::EchoRequest | PacketType::EchoReply => match Echo::deserialize(data) {
+            Some(echo) => {
+                let _ = writeln!(out, "  回显: {} -> {}  发送时间 {} ms  跳数 {}",
+                    echo.origin, echo.target, echo.sent_at, echo.hop_count);
+                for (index, hop) in echo.trace.iter().flat_map(|trace| trace.hops()).enumerate() {
+                    let _ = writeln!(out, "    {:>2}. {}  RSSI {} dBm", index + 1, hop.node_id, hop.rssi);
+                }
+                true
+            },
+            None => false,
+        },
+        PacketType::Ota => match OtaMessage::deserialize(data) {
+            Some(message) => {
+                let _ = write!(out, "  固件更新 v{}: {} -> {}  ", message.version, message.origin, message.target);
+                let _ = match message.body {
+                    OtaBody::Offer { size, crc, digest } => writeln!(out, "提供镜像 {} 字节 CRC 0x{:08X} 摘要 {:02X}{:02X}{:02X}{:02X}…",
+                        size, crc, digest[0], digest[1], digest[2], digest[3]),
+                    OtaBody::Request { offset } => writeln!(out, "请求偏移 {}", offset),
+                    OtaBody::Chunk { offset, data } => writeln!(out, "分块 偏移 {} 长度 {}", offset, data.len()),
+                    OtaBody::Result { status } => writeln!(out, "结果 {:?}", status),
+                };
+                true
+            },
+            None => false,
+        },
+        PacketType::Mgmt => match MgmtMessage::deserialize(data) {
+            Some((message, tag)) => {
+                let attribute = MgmtAttribute::from_u8(message.attribute)
+                    .map(|attribute| format!("{:?}", attribute))
+                    .unwrap_or_else(|| format!("0x{:02X}", message.attribute));
+                let _ = writeln!(out, "  管理 {:?}: {} -> {}  序号 {}  属性 {}  状态 {:?}",
+                    message.op, message.origin, message.target,
+                    message.sequence, attribute, message.status);
+                if !message.value.is_empty() {
+                    let _ = writeln!(out, "  值: {}", hex(message.value));
+                }
+                let _ = writeln!(out, "  认证码: {}", if tag.is_empty() { "无".to_string() } else { hex(tag) });
+                true
+            },
+            None => false,
+        },
+        PacketType::TimeSync => match TimeBeacon::deserialize(data) {
+            Some(beacon) => {
+                let _ = writeln!(out, "  时间信标: 主节点 {}  序号 {}  网络时间 {} ms  跳数 {}",
+                    beacon.master, beacon.sequence, beacon.network_time, beacon.hop_count);
+                let slots = SlotTable::from_time_sync(data);
+                for (index, owner) in slots.owners[..slots.slot_count()].iter().enumerate() {
+                    if let Some(owner) = owner {
+                        let _ = writeln!(out, "  时隙 {}: {}  长度 {} ms", index, owner, slots.slot_ms);
+                    }
+                }
+                true
+            },
+            None => false,
+        },
+        PacketType::SlotRequest => match SlotRequest::deserialize(data) {
+            Some(request) => {
+                let _ = writeln!(out, "  时隙申请: {} -> 主节点 {}", request.node, request.master);
+                true
+            },
+            None => false,
+        },
+        PacketType::Topology => match TopologyMessage::deserialize(data) {
+            Some(TopologyMessage::Request { master, request_id }) => {
+                let _ = writeln!(out, "  拓扑请求: 主节点 {}  请求ID {}", master, request_id);
+                true
+            },
+            Some(TopologyMessage::Report(report)) => {
+                let _ = writeln!(out, "  拓扑报告: {} -> 主节点 {}  请求ID {}  {} 条路由",
+                    report.reporter, report.master, report.request_id, report.entries().len());
+                for edge in report.entries() {
+                    let _ = writeln!(out, "    {} 经 {}  {} dBm", edge.destination, edge.next_hop, edge.metric);
+                }
+                true
+            },
+            None => false,
+        },
+        PacketType::RouteAdvert => match RouteAdvert::deserialize(data) {
+            Some(advert) => {
+                let _ = writeln!(out, "  路由通告: {} 条路由", advert.entries().len());
+                for route in advert.entries() {
+                    if route.is_poisoned() {
+                        let _ = writeln!(out, "    {} 经 {}  不可达", route.destination, route.next_hop);
+                    } else {
+                        let _ = writeln!(out, "    {} 经 {}  {} 跳  ETX {:.1}  {} dBm", route.destination, route.next_hop, route.hops,
+                            route.etx as f32 / ETX_SCALE as f32, route.rssi);
+                    }
+                }
+                true
+            },
+            None => false,
+        },
+        PacketType::ServiceAdvertisement => match ServiceAdvertisement::deserialize(data) {
+            Some(advert) => {
+                let _ = writeln!(out, "  服务通告: {} 个服务", advert.entries().len());
+                for service in advert.entries() {
+                    let _ = writeln!(out, "    {} {:?}  {} 跳  负载 {}%  电量 {}%  {} dBm  源时间 {} s",
+                        service.server, service.service_type, service.hops, service.load,
+                        service.battery_level, service.rssi, service.origin_time);
+                }
+                true
+            },
+            None => false,
+        },
+        PacketType::ServiceBeacon => match ServiceBeacon::deserialize(data) {
+            Some(beacon) => {
+                let services: Vec<_> = beacon.services().collect();
+                let _ = writeln!(out, "  服务信标: {:?}  负载 {}%  电量 {}%  带宽 {} kbps  延迟 {} ms  可靠性 {}%",
+                    services, beacon.load, beacon.battery_level, beacon.max_bandwidth, beacon.min_latency, beacon.reliability);
+                true
+            },
+            None => false,
+        },
+        PacketType::ErrorReport => match ErrorReport::deserialize(data) {
+            Some(report) => {
+                let _ = writeln!(out, "  错误报告: {}  {}（0x{:02X}，{:?}）  {} 次  时间 {} ms  附加信息 {}",
+                    report.origin, report.code.name(), report.code as u8, report.code.category(),
+                    report.occurrences, report.timestamp, report.detail);
+                true
+            },
+            None => false,
+        },
+        PacketType::DirectoryLookup => match LookupMessage::deserialize(data) {
+            Some(LookupMessage::Query { origin, master, request }) => {
+                let _ = writeln!(out, "  目录查询: {} -> 主节点 {}  {:?}  带宽≥{} kbps  延迟≤{} ms  可靠性 {}%",
+                    origin, master, request.service_type, request.qos.min_bandwidth,
+                    request.qos.max_latency, request.qos.reliability);
+                true
+            },
+            Some(LookupMessage::Answer { origin, server, ttl_secs, request }) => {
+                let server = server.map(|server| server.to_string()).unwrap_or_else(|| "无".to_string());
+                let _ = writeln!(out, "  目录应答: -> {}  {:?}  服务器 {}  有效期 {} 秒",
+                    origin, request.service_type, server, ttl_secs);
+                true
+            },
+            None => false,
+        },
+        PacketType::ChannelPlan => match ChannelMessage::deserialize(data) {
+            Some(ChannelMessage::Report(report)) => {
+                let _ = writeln!(out, "  信道质量: {} -> 主节点 {}  信道 {}  CRC错误率 {}‰",
+                    report.reporter, report.master, report.channel, report.crc_permille);
+                for channel in FIRST_CHANNEL..FIRST_CHANNEL + CHANNEL_COUNT as u8 {
+                    if let Some(energy) = report.energy_on(channel) {
+                        let _ = writeln!(out, "    信道 {}: {} dBm", channel, energy);
+                    }
+                }
+                true
+            },
+            Some(ChannelMessage::Switch { master, switch_id, channel, switch_at, grace_ms }) => {
+                let _ = writeln!(out, "  信道切换: 主节点 {}  切换ID {}  新信道 {}  网络时间 {} ms  宽限期 {} ms",
+                    master, switch_id, channel, switch_at, grace_ms);
+                true
+            },
+            None => false,
+        },
+        PacketType::Data => describe_data(out, packet),
+        _ => false,
+    }
+}
+
+/// 解码应用数据，负载第0字节为类型
+fn describe_data(out: &mut String, packet: &DataPacket) -> bool {
+    let data = packet.data;
+    
+    // 选举消息只在转发节点之间交换，开始和结果都是广播
+    if NodeId(packet.header.destination).is_broadcast() {
+        if let Some(message) = ElectionMessage::deserialize(data) {
+            let _ = match message {
+                ElectionMessage::Start { election_id, candidacy } =>
+                    writeln!(out, "  选举开始: 选举ID {}  优先级 {}  电量 {}%  负载 {}%",
+                        election_id, candidacy.priority, candidacy.battery_level, candidacy.load),
+                ElectionMessage::Response { election_id, candidacy } =>
+                    writeln!(out, "  选举回应: 选举ID {}  优先级 {}  电量 {}%  负载 {}%",
+                        election_id, candidacy.priority, candidacy.battery_level, candidacy.load),
+                ElectionMessage::Result { election_id, master } =>
+                    writeln!(out, "  选举结果: 选举ID {}  主服务器 {}", election_id, master),
+            };
+            return true;
+        }
+    }
+    
+    match PayloadType::of(data) {
+        Some(PayloadType::Batch) => match deserialize_batch(data) {
+            Some((service_id, samples)) => {
+                let _ = writeln!(out, "  批量传感器数据: 服务ID {}", service_id);
+                for sample in samples {
+                    let _ = writeln!(out, "    {} 秒前  温度 {:.2}°C  湿度 {:.2}%  气压 {:.0} Pa",
+                        sample.age_secs, sample.temperature, sample.humidity, sample.pressure);
+                }
+                true
+            },
+            None => false,
+        },
+        Some(PayloadType::VideoFrame) => match payload::decode::<VideoFrame>(data) {
+            Some(VideoFrame { service_id, frame_number, data: frame }) => {
+                let _ = writeln!(out, "  视频帧: 服务ID {}  帧 #{}  {} 字节",
+                    service_id, frame_number, frame.len());
+                true
+            },
+            None => false,
+        },
+        Some(PayloadType::VideoTier) => match VideoTierNotice::deserialize(data) {
+            Some(notice) => {
+                let tier = notice.video_tier();
+                let _ = writeln!(out, "  视频档位: 服务ID {}  档位 {}  每 {}ms 一帧  {} 字节",
+                    notice.service_id, notice.tier, tier.frame_interval_ms, tier.frame_size);
+                true
+            },
+            None => false,
+        },
+        Some(PayloadType::Command) if data.len() >= 2 => {
+            match payload::decode::<Command>(data) {
+                Some(command) => {
+                    let _ = writeln!(out, "  命令: {:?}  参数: {}", command.command, hex(command.parameters));
+                },
+                None => {
+                    let _ = writeln!(out, "  命令: 未知 0x{:02X}", data[1]);
+                },
+            }
+            true
+        },
+        Some(PayloadType::Query) => match payload::decode::<Query>(data) {
+            Some(query) => {
+                let _ = writeln!(out, "  查询: {} - {}  降采样 {:?}  令牌 {}  偏移 {}",
+                    query.params.start_time, query.params.end_time, query.params.downsample,
+                    query.page.token, query.page.offset);
+                true
+            },
+            None => false,
+        },
+        Some(PayloadType::SensorReading) => match payload::decode::<SensorReading>(data) {
+            Some(reading) => {
+                let _ = writeln!(out, "  传感器数据: 服务ID {}  温度 {:.2}°C  湿度 {:.2}%  气压 {:.0} Pa",
+                    reading.service_id, reading.temperature, reading.humidity, reading.pressure);
+                true
+            },
+            None => false,
+        },
+        _ => false,
+    }
+}
\ No newline at end of file
@@ -0,0 +1,162 @@
+use std::fmt;
+use std::io;
+
+use common::protocol::MAX_PACKET_SIZE;
+use common::protocol::slip::SlipDecoder;
+
+/// 经典pcap文件的字节序标识（微秒时间戳）
+const PCAP_MAGIC: u32 = 0xA1B2_C3D4;
+
+/// 纳秒时间戳的pcap字节序标识
+const PCAP_MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+
+/// pcap文件头长度
+const PCAP_HEADER_LEN: usize = 24;
+
+/// pcap记录头长度：秒(4) 微秒(4) 捕获长度(4) 原始长度(4)
+const PCAP_RECORD_HEADER_LEN: usize = 16;
+
+/// 输入读取错误
+#[derive(Debug)]
+pub enum InputError {
+    /// 读取文件失败
+    Io(io::Error),
+    /// 十六进制文本格式错误，附带行号
+    InvalidHex(usize),
+    /// pcap文件格式错误
+    InvalidPcap(&'static str),
+}
+
+impl From<io::Error> for InputError {
+    fn from(e: io::Error) -> Self {
+        InputError::Io(e)
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Io(e) => write!(f, "读取输入失败: {}", e),
+            InputError::InvalidHex(line) => write!(f, "第 {} 行不是有效的十六进制", line),
+            InputError::InvalidPcap(reason) => write!(f, "pcap格式错误: {}", reason),
+        }
+    }
+}
+
+/// 捕获到的一帧
+pub struct Frame {
+    /// 捕获时间（微秒），十六进制和串口输入没有时间
+    pub timestamp_us: Option<u64>,
+    /// 原始无线帧
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    fn untimed(data: Vec<u8>) -> Self {
+        Self { timestamp_us: None, data }
+    }
+}
+
+/// 解析十六进制转储，每个非空行一帧
+///
+/// 允许字节间的空格、冒号和`0x`前缀，`#`之后为注释。
+pub fn parse_hex(text: &str) -> Result<Vec<Frame>, InputError> {
+    let mut frames = Vec::new();
+    
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let digits: String = line
+            .split(|c: char| c.is_whitespace() || c == ':' || c == ',')
+            .map(|token| token.trim_start_matches("0x").trim_start_matches("0X"))
+            .collect();
+        if digits.is_empty() {
+            continue;
+        }
+        // 按字节切分两位数字，非ASCII字符会落在字符边界之外
+        if !digits.is_ascii() || digits.len() % 2 != 0 {
+            return Err(InputError::InvalidHex(index + 1));
+        }
+        
+        let data = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| InputError::InvalidHex(index + 1))?;
+        frames.push(Frame::untimed(data));
+    }
+    
+    Ok(frames)
+}
+
+/// 解析pcap捕获文件，每条记录的数据即一个无线帧
+pub fn parse_pcap(bytes: &[u8]) -> Result<Vec<Frame>, InputError> {
+    if bytes.len() < PCAP_HEADER_LEN {
+        return Err(InputError::InvalidPcap("文件头不完整"));
+    }
+    
+    let magic = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    let (big_endian, nanos) = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+        (PCAP_MAGIC, _) => (false, false),
+        (PCAP_MAGIC_NANOS, _) => (false, true),
+        (_, PCAP_MAGIC) => (true, false),
+        (_, PCAP_MAGIC_NANOS) => (true, true),
+        _ => return Err(InputError::InvalidPcap("未知的文件标识")),
+    };
+    let read_u32 = |offset: usize| {
+        let word = [bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]];
+        if big_endian { u32::from_be_bytes(word) } else { u32::from_le_bytes(word) }
+    };
+    
+    let mut frames = Vec::new();
+    let mut offset = PCAP_HEADER_LEN;
+    while offset < bytes.len() {
+        if offset + PCAP_RECORD_HEADER_LEN > bytes.len() {
+            return Err(InputError::InvalidPcap("记录头被截断"));
+        }
+        
+        let seconds = read_u32(offset) as u64;
+        let fraction = read_u32(offset + 4) as u64;
+        let captured = read_u32(offset + 8) as usize;
+        offset += PCAP_RECORD_HEADER_LEN;
+        
+        if offset + captured > bytes.len() {
+            return Err(InputError::InvalidPcap("记录数据被截断"));
+        }
+        
+        let micros = if nanos { fraction / 1000 } else { fraction };
+        frames.push(Frame {
+            timestamp_us: Some(seconds * 1_000_000 + micros),
+            data: bytes[offset..offset + captured].to_vec(),
+        });
+        offset += captured;
+    }
+    
+    Ok(frames)
+}
+
+/// 解析串口嗅探器输出，嗅探器以SLIP分帧输出收到的原始无线帧
+pub fn parse_uart(bytes: &[u8]) -> Vec<Frame> {
+    let mut decoder = SlipDecoder::<MAX_PACKET_SIZE>::new();
+    bytes.iter()
+        .filter_map(|&byte| decoder.push(byte).map(|frame| Frame::untimed(frame.to_vec())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_hex() {
+        let frames = parse_hex("# 注释\n0x01 02:03,04\n\nAA bb # 行尾注释\n").unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].data, [0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(frames[1].data, [0xAA, 0xBB]);
+        
+        // 奇数位数字、非十六进制和非ASCII字符都报告所在行，不会越过字符边界
+        assert!(matches!(parse_hex("01\n012"), Err(InputError::InvalidHex(2))));
+        assert!(matches!(parse_hex("0g"), Err(InputError::InvalidHex(1))));
+        assert!(matches!(parse_hex("0é"), Err(InputError::InvalidHex(1))));
+        assert!(matches!(parse_hex("01\n温度"), Err(InputError::InvalidHex(2))));
+    }
+}
\ No newline at end of file
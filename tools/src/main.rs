@@ -0,0 +1,97 @@
+mod budget;
+mod decode;
+mod input;
+
+use std::fs;
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+use input::{parse_hex, parse_pcap, parse_uart, Frame, InputError};
+
+const USAGE: &str = "用法: linknebula-tools <hex|pcap|uart> [文件]
+       linknebula-tools budget [--tx-power <dBm>] [--margin <dB>] <RSSI>...
+  
+  hex     每行一帧的十六进制转储，字节间可用空格或冒号分隔
+  pcap    pcap捕获文件，每条记录为一个原始无线帧
+  uart    串口嗅探器输出，以SLIP分帧的原始无线帧
+  budget  由部署现场测得的RSSI采样估计距离和链路余量，并给出下一个中继的最远位置
+
+省略文件或文件为 - 时从标准输入读取";
+
+/// 输入格式
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Hex,
+    Pcap,
+    Uart,
+}
+
+impl Format {
+    fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "hex" => Some(Format::Hex),
+            "pcap" => Some(Format::Pcap),
+            "uart" => Some(Format::Uart),
+            _ => None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("budget") {
+        return match budget::run(&args[1..]) {
+            Some(report) => {
+                print!("{}", report);
+                ExitCode::SUCCESS
+            },
+            None => {
+                eprintln!("{}", USAGE);
+                ExitCode::FAILURE
+            },
+        };
+    }
+    
+    let format = match args.first().and_then(|arg| Format::from_arg(arg)) {
+        Some(format) => format,
+        None => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        },
+    };
+    
+    match read_frames(format, args.get(1).map(String::as_str)) {
+        Ok(frames) => {
+            for (index, frame) in frames.iter().enumerate() {
+                match frame.timestamp_us {
+                    Some(us) => println!("#{} [{}.{:06}]", index + 1, us / 1_000_000, us % 1_000_000),
+                    None => println!("#{}", index + 1),
+                }
+                print!("{}", decode::describe(&frame.data));
+            }
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+/// 读取输入并按格式拆分为帧
+fn read_frames(format: Format, path: Option<&str>) -> Result<Vec<Frame>, InputError> {
+    let bytes = match path {
+        Some(path) if path != "-" => fs::read(path)?,
+        _ => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        },
+    };
+    
+    match format {
+        Format::Hex => parse_hex(&String::from_utf8_lossy(&bytes)),
+        Format::Pcap => parse_pcap(&bytes),
+        Format::Uart => Ok(parse_uart(&bytes)),
+    }
+}
\ No newline at end of file